@@ -1,24 +1,37 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::cmp;
+use core::marker::PhantomData;
+use core::fmt::{self, Display, Formatter};
+use core::str::{self, Utf8Error};
+#[cfg(feature = "std")]
 use std::error;
-use std::fmt::{self, Display, Formatter};
+#[cfg(feature = "std")]
 use std::io::{self, Cursor};
-use std::str::{self, Utf8Error};
-
-use byteorder::{self, ReadBytesExt};
 
 use serde;
-use serde::de::{self, Deserialize, DeserializeOwned, DeserializeSeed, Visitor};
+#[cfg(feature = "std")]
+use serde::de::DeserializeOwned;
+use serde::de::{self, Deserialize, DeserializeSeed, StdError, Visitor};
+use serde::de::value::{BorrowedStrDeserializer, SeqDeserializer, U64Deserializer};
 
 use rmp;
 use rmp::Marker;
-use rmp::decode::{MarkerReadError, DecodeStringError, ValueReadError, NumValueReadError,
-                  read_array_len};
+use rmp::decode::{MarkerReadError, DecodeStringError, Header, RmpRead, ValueReadError,
+                  NumValueReadError, read_array_len, read_data_u8, read_data_u16, read_data_u32,
+                  read_header};
+use rmp::encode;
+#[cfg(feature = "std")]
+use rmp::parse::Parser;
+
+use {Discriminants, MSGPACK_EXT_STRUCT_NAME, RAW_VALUE_NAME, TIMESTAMP_NAME, TIMESTAMP_TYPE};
 
 ///
 // TODO: Write docs.
 #[derive(Debug)]
 pub enum Error {
-    InvalidMarkerRead(io::Error),
-    InvalidDataRead(io::Error),
+    InvalidMarkerRead(rmp::decode::Error),
+    InvalidDataRead(rmp::decode::Error),
     /// The actual value type isn't equal with the expected one.
     TypeMismatch(Marker),
     /// Numeric cast failed due to out of range error.
@@ -29,13 +42,23 @@ pub enum Error {
     Syntax(String),
     Utf8Error(Utf8Error),
     DepthLimitExceeded,
+    /// An array, map, string, binary or extension is longer than the configured maximum, which
+    /// is the length read.
+    LengthLimitExceeded(u32),
+    /// The version of an envelope is not registered in `envelope::Versions`.
+    UnsupportedVersion(u32),
+    /// A map holds several entries with the same key and `DuplicateKeys::Reject` is configured.
+    DuplicateKey,
+    /// A `PartialDeserializer` needs at least this many more bytes to complete the next value.
+    NeedMoreData(usize),
 }
 
-impl error::Error for Error {
+impl StdError for Error {
     fn description(&self) -> &str {
         "error while decoding value"
     }
 
+    #[cfg(feature = "std")]
     fn cause(&self) -> Option<&error::Error> {
         match *self {
             Error::TypeMismatch(..) => None,
@@ -47,6 +70,10 @@ impl error::Error for Error {
             Error::Syntax(..) => None,
             Error::Utf8Error(ref err) => Some(err),
             Error::DepthLimitExceeded => None,
+            Error::LengthLimitExceeded(..) => None,
+            Error::UnsupportedVersion(..) => None,
+            Error::DuplicateKey => None,
+            Error::NeedMoreData(..) => None,
         }
     }
 }
@@ -59,7 +86,7 @@ impl de::Error for Error {
 
 impl Display for Error {
     fn fmt(&self, fmt: &mut Formatter) -> Result<(), fmt::Error> {
-        error::Error::description(self).fmt(fmt)
+        fmt.write_str("error while decoding value")
     }
 }
 
@@ -108,6 +135,274 @@ impl<'a> From<DecodeStringError<'a>> for Error {
     }
 }
 
+/// Describes what to do with a map holding several entries with the same key.
+///
+/// Visitors differ on duplicates: derived structs fail on a repeated field, while `HashMap` keeps
+/// the last value it is given. A consumer validating a map before passing it on may thus disagree
+/// with the next one about which value counts. The policy settles this before the visitor sees
+/// the map.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DuplicateKeys {
+    /// Pass all entries to the visitor. This is the default.
+    Allow,
+    /// Fail with `Error::DuplicateKey`.
+    Reject,
+    /// Pass only the first entry for every key.
+    FirstWins,
+    /// Pass only the last entry for every key, at the position it was read.
+    LastWins,
+}
+
+/// Describes what to do with a string whose payload is not valid UTF-8.
+///
+/// Such strings are common in data written by C producers or by encoders from before the str and
+/// bin types were split, which store arbitrary bytes as strings.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InvalidUtf8 {
+    /// Fail with `Error::Utf8Error`.
+    Reject,
+    /// Pass the payload as it is to visitors accepting bytes, like `serde_bytes::ByteBuf`, or
+    /// sequences of bytes, like `Vec<u8>`, and fail with `Error::Utf8Error` for others. This is
+    /// the default.
+    Bytes,
+    /// Replace every invalid sequence with `U+FFFD REPLACEMENT CHARACTER` and pass the result as a
+    /// string.
+    Replace,
+}
+
+/// Deserialization options, gathered in a single place.
+///
+/// A config is built once and then applied to any number of deserializers, either using
+/// `Deserializer::set_config` or with the `from_read_with` and `from_slice_with` functions. It is
+/// plain data, so it can be freely cloned and shared between threads.
+///
+/// # Examples
+///
+/// ```
+/// use rmp_serde::decode::DeserializerConfig;
+///
+/// let config = DeserializerConfig::new()
+///     .max_depth(16)
+///     .strict_utf8(true);
+///
+/// assert_eq!(16, config.get_max_depth());
+/// assert!(config.is_strict_utf8());
+///
+/// // [[1]] nests deeper than allowed.
+/// let config = DeserializerConfig::new().max_depth(1);
+/// let res: Result<Vec<Vec<u8>>, _> = rmp_serde::from_slice_with(&[0x91, 0x91, 0x01], &config);
+/// assert!(res.is_err());
+/// ```
+#[derive(Clone, Debug)]
+pub struct DeserializerConfig {
+    max_depth: usize,
+    max_bytes: usize,
+    max_seq_len: usize,
+    invalid_utf8: InvalidUtf8,
+    lenient_bool: bool,
+    discriminants: Discriminants,
+    duplicate_keys: DuplicateKeys,
+    deny_unknown_fields: bool,
+    lenient_struct_arrays: bool,
+    legacy_raw: bool,
+}
+
+impl DeserializerConfig {
+    /// Constructs a new config with default options.
+    pub fn new() -> Self {
+        DeserializerConfig::default()
+    }
+
+    /// Constructs a new config with default options, except that the nesting depth and lengths
+    /// are not limited.
+    ///
+    /// Only use this for trusted input: a few bytes are then enough to allocate gigabytes of
+    /// memory or to overflow the stack.
+    pub fn unbounded() -> Self {
+        DeserializerConfig::new()
+            .max_depth(usize::MAX)
+            .max_bytes(usize::MAX)
+            .max_seq_len(usize::MAX)
+    }
+
+    /// Sets the maximum nesting depth of arrays and maps that is allowed.
+    ///
+    /// Deserializing deeper structures fails with `Error::DepthLimitExceeded`. The default is
+    /// 1024.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    /// Returns the maximum nesting depth that is allowed.
+    pub fn get_max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    /// Sets the maximum length of a single string, binary or extension payload that is allowed.
+    ///
+    /// Deserializing from an `io::Read` allocates a buffer for the whole payload before reading
+    /// it, so the length is checked first and longer payloads fail with
+    /// `Error::LengthLimitExceeded`. The default is 64 MiB.
+    pub fn max_bytes(mut self, len: usize) -> Self {
+        self.max_bytes = len;
+        self
+    }
+
+    /// Returns the maximum length of a payload that is allowed.
+    pub fn get_max_bytes(&self) -> usize {
+        self.max_bytes
+    }
+
+    /// Sets the maximum number of elements of an array or entries of a map that is allowed.
+    ///
+    /// The length is passed to serde as a size hint, which collections preallocate from, so it
+    /// is checked before deserializing any element. Longer containers fail with
+    /// `Error::LengthLimitExceeded`. The default is 2^24.
+    pub fn max_seq_len(mut self, len: usize) -> Self {
+        self.max_seq_len = len;
+        self
+    }
+
+    /// Returns the maximum length of a container that is allowed.
+    pub fn get_max_seq_len(&self) -> usize {
+        self.max_seq_len
+    }
+
+    /// Enables or disables strict UTF-8 validation.
+    ///
+    /// By default a string containing invalid UTF-8 is passed to the visitor as a byte array, if
+    /// it accepts one. In strict mode such a string always fails with `Error::Utf8Error`. This is
+    /// a shorthand for `invalid_utf8` with `InvalidUtf8::Reject` or `InvalidUtf8::Bytes`.
+    pub fn strict_utf8(mut self, strict: bool) -> Self {
+        self.invalid_utf8 = if strict { InvalidUtf8::Reject } else { InvalidUtf8::Bytes };
+        self
+    }
+
+    /// Returns `true` if strict UTF-8 validation is enabled.
+    pub fn is_strict_utf8(&self) -> bool {
+        self.invalid_utf8 == InvalidUtf8::Reject
+    }
+
+    /// Sets the policy applied to strings whose payload is not valid UTF-8.
+    pub fn invalid_utf8(mut self, policy: InvalidUtf8) -> Self {
+        self.invalid_utf8 = policy;
+        self
+    }
+
+    /// Returns the policy applied to strings whose payload is not valid UTF-8.
+    pub fn get_invalid_utf8(&self) -> InvalidUtf8 {
+        self.invalid_utf8
+    }
+
+    /// Enables or disables accepting the integers `0` and `1` as booleans.
+    ///
+    /// Several C and Lua producers encode booleans as integers. In lenient mode `bool` values
+    /// accept them in any integer format, while other integers fail with `Error::OutOfRange`.
+    pub fn lenient_bool(mut self, lenient: bool) -> Self {
+        self.lenient_bool = lenient;
+        self
+    }
+
+    /// Returns `true` if integers are accepted as booleans.
+    pub fn is_lenient_bool(&self) -> bool {
+        self.lenient_bool
+    }
+
+    /// Sets the discriminants the variants of the listed enums are encoded with, instead of
+    /// their declaration-order indices.
+    pub fn discriminants(mut self, discriminants: Discriminants) -> Self {
+        self.discriminants = discriminants;
+        self
+    }
+
+    /// Returns the discriminants enum variants are encoded with.
+    pub fn get_discriminants(&self) -> &Discriminants {
+        &self.discriminants
+    }
+
+    /// Sets the policy applied to maps holding several entries with the same key.
+    ///
+    /// Keys are compared by their encoding, with every length and integer in its shortest form,
+    /// so `1` is the same key whatever its width. With any policy but `Allow`, each map is read
+    /// in full, along with the maps nested in it, before its entries are passed to the visitor.
+    /// This buffers the map, and its strings and binaries can't be borrowed from the input.
+    pub fn duplicate_keys(mut self, policy: DuplicateKeys) -> Self {
+        self.duplicate_keys = policy;
+        self
+    }
+
+    /// Returns the policy applied to maps holding several entries with the same key.
+    pub fn get_duplicate_keys(&self) -> DuplicateKeys {
+        self.duplicate_keys
+    }
+
+    /// Enables or disables rejecting unknown fields of structs encoded as maps.
+    ///
+    /// By default entries whose key is not a field of the struct are skipped. When enabled, a
+    /// string key matching none of the field names or an integer key past the last field index
+    /// fails with `Error::Syntax`, like with `#[serde(deny_unknown_fields)]` but for every struct.
+    /// Other keys are left to the struct to check.
+    pub fn deny_unknown_fields(mut self, deny: bool) -> Self {
+        self.deny_unknown_fields = deny;
+        self
+    }
+
+    /// Returns `true` if unknown fields of structs are rejected.
+    pub fn denies_unknown_fields(&self) -> bool {
+        self.deny_unknown_fields
+    }
+
+    /// Enables or disables accepting structs encoded as arrays with fewer elements than fields.
+    ///
+    /// By default such arrays fail with `Error::Syntax`. In lenient mode the missing trailing
+    /// fields are handled as if they were absent from a map: `Option` fields are `None` and
+    /// fields marked with `#[serde(default)]` take their default, so that new optional fields can
+    /// be appended to a struct while still reading data written before.
+    pub fn lenient_struct_arrays(mut self, lenient: bool) -> Self {
+        self.lenient_struct_arrays = lenient;
+        self
+    }
+
+    /// Returns `true` if structs may be encoded as arrays missing trailing fields.
+    pub fn is_lenient_struct_arrays(&self) -> bool {
+        self.lenient_struct_arrays
+    }
+
+    /// Enables or disables reading strings as the raw values of the MessagePack specification
+    /// before 2013, which held both text and binary data.
+    ///
+    /// In this mode byte arrays, like `serde_bytes` values and `Vec<u8>`, accept strings, whose
+    /// payload is passed as it is without checking it is UTF-8. Other values still read strings
+    /// as text.
+    pub fn legacy_raw(mut self, legacy: bool) -> Self {
+        self.legacy_raw = legacy;
+        self
+    }
+
+    /// Returns `true` if strings are read as old-spec raw values.
+    pub fn is_legacy_raw(&self) -> bool {
+        self.legacy_raw
+    }
+}
+
+impl Default for DeserializerConfig {
+    fn default() -> Self {
+        DeserializerConfig {
+            max_depth: 1024,
+            max_bytes: 1 << 26,
+            max_seq_len: 1 << 24,
+            invalid_utf8: InvalidUtf8::Bytes,
+            lenient_bool: false,
+            discriminants: Discriminants::new(),
+            duplicate_keys: DuplicateKeys::Allow,
+            deny_unknown_fields: false,
+            lenient_struct_arrays: false,
+            legacy_raw: false,
+        }
+    }
+}
+
 /// A Deserializer that reads bytes from a buffer.
 ///
 /// # Note
@@ -117,6 +412,8 @@ impl<'a> From<DecodeStringError<'a>> for Error {
 pub struct Deserializer<R> {
     rd: R,
     marker: Option<Marker>,
+    config: DeserializerConfig,
+    // Current nesting depth of arrays and maps.
     depth: usize,
 }
 
@@ -125,7 +422,8 @@ impl<'de> Deserializer<SliceReader<'de>> {
         Deserializer {
             rd: SliceReader::new(slice),
             marker: None,
-            depth: 1024,
+            config: DeserializerConfig::default(),
+            depth: 0,
         }
     }
 
@@ -135,13 +433,15 @@ impl<'de> Deserializer<SliceReader<'de>> {
     }
 }
 
+#[cfg(feature = "std")]
 impl<R: io::Read> Deserializer<ReadReader<R>> {
     pub fn from_read(rd: R) -> Self {
         Deserializer {
             rd: ReadReader::new(rd),
             // Cached marker in case of deserializing options.
             marker: None,
-            depth: 1024,
+            config: DeserializerConfig::default(),
+            depth: 0,
         }
     }
 
@@ -166,6 +466,7 @@ impl<R: io::Read> Deserializer<ReadReader<R>> {
     }
 }
 
+#[cfg(feature = "std")]
 impl<R: AsRef<[u8]>> Deserializer<ReadReader<Cursor<R>>> {
     /// Returns the current position of this deserializer, i.e. how many bytes were read.
     pub fn position(&self) -> u64 {
@@ -176,16 +477,66 @@ impl<R: AsRef<[u8]>> Deserializer<ReadReader<Cursor<R>>> {
 impl<'de, R: Read<'de>> Deserializer<R> {
     /// Changes the maximum nesting depth that is allowed
     pub fn set_max_depth(&mut self, depth: usize) {
-        self.depth = depth;
+        self.config.max_depth = depth;
+    }
+
+    /// Changes the maximum length of a string, binary or extension payload that is allowed.
+    pub fn set_max_bytes(&mut self, len: usize) {
+        self.config.max_bytes = len;
+    }
+
+    /// Changes the maximum number of elements of an array or entries of a map that is allowed.
+    pub fn set_max_seq_len(&mut self, len: usize) {
+        self.config.max_seq_len = len;
+    }
+
+    /// Changes the policy applied to maps holding several entries with the same key.
+    pub fn set_duplicate_keys(&mut self, policy: DuplicateKeys) {
+        self.config.duplicate_keys = policy;
+    }
+
+    /// Changes the policy applied to strings whose payload is not valid UTF-8.
+    pub fn set_invalid_utf8(&mut self, policy: InvalidUtf8) {
+        self.config.invalid_utf8 = policy;
+    }
+
+    /// Enables or disables rejecting unknown fields of structs encoded as maps.
+    pub fn set_deny_unknown_fields(&mut self, deny: bool) {
+        self.config.deny_unknown_fields = deny;
+    }
+
+    /// Enables or disables accepting structs encoded as arrays missing trailing fields.
+    pub fn set_lenient_struct_arrays(&mut self, lenient: bool) {
+        self.config.lenient_struct_arrays = lenient;
+    }
+
+    /// Enables or disables reading strings as old-spec raw values, which byte arrays accept.
+    pub fn set_legacy_raw(&mut self, legacy: bool) {
+        self.config.legacy_raw = legacy;
+    }
+
+    /// Applies all options from the given config at once, replacing the current ones.
+    pub fn set_config(&mut self, config: &DeserializerConfig) {
+        self.config = config.clone();
+    }
+
+    /// Returns the options this deserializer currently uses.
+    pub fn config(&self) -> &DeserializerConfig {
+        &self.config
     }
 
     fn read_str_data<V>(&mut self, len: u32, visitor: V) -> Result<V::Value, Error>
         where V: Visitor<'de>
     {
+        let policy = self.config.invalid_utf8;
         match self.read_bin_data(len as u32)? {
             Reference::Borrowed(buf) => {
                 match str::from_utf8(buf) {
                     Ok(s) => visitor.visit_borrowed_str(s),
+                    Err(err) if policy == InvalidUtf8::Reject => Err(Error::Utf8Error(err)),
+                    Err(..) if policy == InvalidUtf8::Replace => {
+                        visitor.visit_string(String::from_utf8_lossy(buf).into_owned())
+                    }
                     Err(err) => {
                         // Allow to unpack invalid UTF-8 bytes into a byte array.
                         match visitor.visit_borrowed_bytes::<Error>(buf) {
@@ -198,6 +549,10 @@ impl<'de, R: Read<'de>> Deserializer<R> {
             Reference::Copied(buf) => {
                 match str::from_utf8(buf) {
                     Ok(s) => visitor.visit_str(s),
+                    Err(err) if policy == InvalidUtf8::Reject => Err(Error::Utf8Error(err)),
+                    Err(..) if policy == InvalidUtf8::Replace => {
+                        visitor.visit_string(String::from_utf8_lossy(buf).into_owned())
+                    }
                     Err(err) => {
                         // Allow to unpack invalid UTF-8 bytes into a byte array.
                         match visitor.visit_bytes::<Error>(buf) {
@@ -211,19 +566,264 @@ impl<'de, R: Read<'de>> Deserializer<R> {
     }
 
     fn read_bin_data<'a>(&'a mut self, len: u32) -> Result<Reference<'de,'a, [u8]>, Error> {
+        check_len(len, self.config.max_bytes)?;
         self.rd.read_slice(len as usize).map_err(Error::InvalidDataRead)
     }
 
+    fn read_vec(&mut self, len: u32) -> Result<Vec<u8>, Error> {
+        check_len(len, self.config.max_bytes)?;
+        self.rd.read_vec(len as usize).map_err(Error::InvalidDataRead)
+    }
+
     fn read_array<V>(&mut self, len: u32, visitor: V) -> Result<V::Value, Error>
         where V: Visitor<'de>
     {
-        visitor.visit_seq(SeqAccess::new(self, len as usize))
+        check_len(len, self.config.max_seq_len)?;
+        self.enter()?;
+        let res = visitor.visit_seq(SeqAccess::new(self, len as usize));
+        self.depth -= 1;
+        res
+    }
+
+    /// Passes an array whose header was read to the visitor of a struct with more fields than
+    /// elements, as a map from the leading field names to the elements.
+    fn read_short_struct<V>(&mut self, len: u32, fields: &'static [&'static str], visitor: V) ->
+        Result<V::Value, Error>
+        where V: Visitor<'de>
+    {
+        self.enter()?;
+        let res = visitor.visit_map(FieldsAccess { de: self, fields: &fields[..len as usize] });
+        self.depth -= 1;
+        res
+    }
+
+    /// Passes a map whose header was read to the visitor, checking its string keys against the
+    /// given struct fields, if any.
+    fn read_map<V>(&mut self, len: u32, fields: Option<&'static [&'static str]>, visitor: V) ->
+        Result<V::Value, Error>
+        where V: Visitor<'de>
+    {
+        check_len(len, self.config.max_seq_len)?;
+        if self.config.duplicate_keys != DuplicateKeys::Allow {
+            return self.read_checked_map(len, fields, visitor);
+        }
+
+        self.enter()?;
+        let res = visitor.visit_map(MapAccess::new(self, len as usize, fields));
+        self.depth -= 1;
+        res
+    }
+
+    /// Reads a whole map, dropping or rejecting duplicate keys in it and in all maps nested in
+    /// it, and then passes what remains to the visitor.
+    fn read_checked_map<V>(&mut self, len: u32, fields: Option<&'static [&'static str]>,
+                           visitor: V) -> Result<V::Value, Error>
+        where V: Visitor<'de>
+    {
+        let mut buf = Vec::new();
+        self.enter()?;
+        let res = self.copy_map(len, &mut buf);
+        self.depth -= 1;
+        res?;
+
+        let mut config = self.config.clone();
+        config.duplicate_keys = DuplicateKeys::Allow;
+        let mut de = Deserializer {
+            rd: CopyReader { inner: &buf },
+            marker: None,
+            config,
+            depth: self.depth,
+        };
+        match fields {
+            Some(fields) => serde::Deserializer::deserialize_struct(&mut de, "", fields, visitor),
+            None => serde::Deserializer::deserialize_any(&mut de, visitor),
+        }
+    }
+
+    /// Copies the next value into the buffer with the shortest headers, applying the duplicate
+    /// key policy to the maps in it.
+    ///
+    /// Maps are only copied at entry boundaries, where no marker is cached.
+    fn copy_value(&mut self, out: &mut Vec<u8>) -> Result<(), Error> {
+        let header = read_header(&mut self.rd)?;
+        match header {
+            Header::Str(len) | Header::Bin(len) | Header::Ext(_, len) => {
+                check_len(len, self.config.max_bytes)?;
+                write_header(out, header);
+                let buf = self.rd.read_vec(len as usize).map_err(Error::InvalidDataRead)?;
+                out.extend_from_slice(&buf);
+            }
+            Header::Array(len) => {
+                check_len(len, self.config.max_seq_len)?;
+                self.enter()?;
+                write_header(out, header);
+                for _ in 0..len {
+                    self.copy_value(out)?;
+                }
+                self.depth -= 1;
+            }
+            Header::Map(len) => {
+                check_len(len, self.config.max_seq_len)?;
+                self.enter()?;
+                self.copy_map(len, out)?;
+                self.depth -= 1;
+            }
+            header => write_header(out, header),
+        }
+
+        Ok(())
+    }
+
+    /// Copies the entries of a map whose header was read, writing the header for the entries
+    /// the duplicate key policy keeps.
+    fn copy_map(&mut self, len: u32, out: &mut Vec<u8>) -> Result<(), Error> {
+        let mut entries = Vec::new();
+        for _ in 0..len {
+            let mut key = Vec::new();
+            self.copy_value(&mut key)?;
+            let mut val = Vec::new();
+            self.copy_value(&mut val)?;
+            entries.push((key, val));
+        }
+
+        let dropped = dropped_entries(self.config.duplicate_keys, &entries)?;
+        let kept = dropped.iter().filter(|&&dropped| !dropped).count();
+        write_header(out, Header::Map(kept as u32));
+        for (entry, dropped) in entries.iter().zip(dropped) {
+            if !dropped {
+                out.extend_from_slice(&entry.0);
+                out.extend_from_slice(&entry.1);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Enters a nested container, failing if the maximum nesting depth would be exceeded.
+    fn enter(&mut self) -> Result<(), Error> {
+        if self.depth >= self.config.max_depth {
+            return Err(Error::DepthLimitExceeded);
+        }
+
+        self.depth += 1;
+        Ok(())
+    }
+
+    /// Reads the length of a string whose marker was read if it is to be read as an old-spec raw
+    /// value holding bytes, returning `None` otherwise.
+    fn read_raw_len(&mut self, marker: Marker) -> Result<Option<u32>, Error> {
+        if !self.config.legacy_raw {
+            return Ok(None);
+        }
+
+        self.read_str_len(marker)
+    }
+
+    /// Reads the length of a string whose marker was read, returning `None` for other values.
+    fn read_str_len(&mut self, marker: Marker) -> Result<Option<u32>, Error> {
+        match marker {
+            Marker::FixStr(len) => Ok(Some(len as u32)),
+            Marker::Str8 => Ok(Some(read_u8(&mut self.rd)? as u32)),
+            Marker::Str16 => Ok(Some(read_u16(&mut self.rd)? as u32)),
+            Marker::Str32 => Ok(Some(read_u32(&mut self.rd)?)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Returns the marker cached by a previous peek, or reads the next one.
+    fn take_marker(&mut self) -> Result<Marker, Error> {
+        match self.marker.take() {
+            Some(marker) => Ok(marker),
+            None => Ok(rmp::decode::read_marker(&mut self.rd)?),
+        }
+    }
+
+    fn read_array_len(&mut self) -> Result<u32, Error> {
+        match self.take_marker()? {
+            Marker::FixArray(len) => Ok(len as u32),
+            Marker::Array16 => Ok(read_u16(&mut self.rd)? as u32),
+            Marker::Array32 => Ok(read_u32(&mut self.rd)?),
+            marker => Err(Error::TypeMismatch(marker)),
+        }
+    }
+
+    /// Passes the data of a timestamp extension to the visitor as bytes.
+    fn read_timestamp<V>(&mut self, visitor: V) -> Result<V::Value, Error>
+        where V: Visitor<'de>
+    {
+        let marker = match self.marker.take() {
+            Some(marker) => marker,
+            None => rmp::decode::read_marker(&mut self.rd)?,
+        };
+
+        let len = match marker {
+            Marker::FixExt4 => 4,
+            Marker::FixExt8 => 8,
+            Marker::Ext8 => read_u8(&mut self.rd)? as u32,
+            marker => return Err(Error::TypeMismatch(marker)),
+        };
+
+        if rmp::decode::read_data_i8(&mut self.rd)? != TIMESTAMP_TYPE {
+            return Err(Error::TypeMismatch(marker));
+        }
+
+        self.read_bytes(len, visitor)
+    }
+
+    /// Passes an extension to the visitor as a newtype struct of its type and data.
+    fn read_ext<V>(&mut self, visitor: V) -> Result<V::Value, Error>
+        where V: Visitor<'de>
+    {
+        let len = match self.take_marker()? {
+            Marker::FixExt1 => 1,
+            Marker::FixExt2 => 2,
+            Marker::FixExt4 => 4,
+            Marker::FixExt8 => 8,
+            Marker::FixExt16 => 16,
+            Marker::Ext8 => read_u8(&mut self.rd)? as u32,
+            Marker::Ext16 => read_u16(&mut self.rd)? as u32,
+            Marker::Ext32 => read_u32(&mut self.rd)?,
+            marker => return Err(Error::TypeMismatch(marker)),
+        };
+        let ty = rmp::decode::read_data_i8(&mut self.rd)?;
+
+        visitor.visit_newtype_struct(ExtDeserializer {
+            de: self,
+            ty,
+            len,
+            pos: 0,
+        })
+    }
+
+    /// Passes the encoded bytes of the next value to the visitor, as they are in the input.
+    fn read_raw_value<V>(&mut self, visitor: V) -> Result<V::Value, Error>
+        where V: Visitor<'de>
+    {
+        let marker = self.take_marker()?;
+        let mut rd = RecordReader {
+            inner: &mut self.rd,
+            buf: vec![marker.to_u8()],
+        };
+        rmp::decode::skip_data(&mut rd, marker)?;
+
+        visitor.visit_byte_buf(rd.buf)
     }
 
-    fn read_map<V>(&mut self, len: u32, visitor: V) -> Result<V::Value, Error>
+    /// Reads a string for a visitor expecting a sequence, which gets the payload as a sequence of
+    /// bytes if it is not valid UTF-8.
+    fn read_str_seq<V>(&mut self, len: u32, visitor: V) -> Result<V::Value, Error>
         where V: Visitor<'de>
     {
-        visitor.visit_map(MapAccess::new(self, len as usize))
+        match self.read_bin_data(len)? {
+            Reference::Borrowed(buf) => match str::from_utf8(buf) {
+                Ok(s) => visitor.visit_borrowed_str(s),
+                Err(..) => visitor.visit_seq(SeqDeserializer::<_, Error>::new(buf.iter().cloned())),
+            },
+            Reference::Copied(buf) => match str::from_utf8(buf) {
+                Ok(s) => visitor.visit_str(s),
+                Err(..) => visitor.visit_seq(SeqDeserializer::<_, Error>::new(buf.iter().cloned())),
+            },
+        }
     }
 
     fn read_bytes<V>(&mut self, len: u32, visitor: V) -> Result<V::Value, Error>
@@ -236,16 +836,66 @@ impl<'de, R: Read<'de>> Deserializer<R> {
     }
 }
 
+fn check_len(len: u32, max: usize) -> Result<(), Error> {
+    if len as usize > max {
+        return Err(Error::LengthLimitExceeded(len));
+    }
+
+    Ok(())
+}
+
+/// Applies the policy to the entries of a map, returning which of them are dropped.
+fn dropped_entries(policy: DuplicateKeys, entries: &[(Vec<u8>, Vec<u8>)]) ->
+    Result<Vec<bool>, Error>
+{
+    // The sort is stable, so entries with the same key stay in the order they were read.
+    let mut order: Vec<usize> = (0..entries.len()).collect();
+    order.sort_by(|&a, &b| entries[a].0.cmp(&entries[b].0));
+
+    let mut dropped = vec![false; entries.len()];
+    for pair in order.windows(2) {
+        if entries[pair[0]].0 != entries[pair[1]].0 {
+            continue;
+        }
+        match policy {
+            DuplicateKeys::Allow => {}
+            DuplicateKeys::Reject => return Err(Error::DuplicateKey),
+            DuplicateKeys::FirstWins => dropped[pair[1]] = true,
+            DuplicateKeys::LastWins => dropped[pair[0]] = true,
+        }
+    }
+
+    Ok(dropped)
+}
+
+/// Writes the header in its shortest encoding.
+fn write_header(out: &mut Vec<u8>, header: Header) {
+    let written = match header {
+        Header::Nil => encode::write_nil(out).is_ok(),
+        Header::Bool(val) => encode::write_bool(out, val).is_ok(),
+        Header::UInt(val) => encode::write_uint(out, val).is_ok(),
+        Header::SInt(val) => encode::write_sint(out, val).is_ok(),
+        Header::F32(val) => encode::write_f32(out, val).is_ok(),
+        Header::F64(val) => encode::write_f64(out, val).is_ok(),
+        Header::Str(len) => encode::write_str_len(out, len).is_ok(),
+        Header::Bin(len) => encode::write_bin_len(out, len).is_ok(),
+        Header::Array(len) => encode::write_array_len(out, len).is_ok(),
+        Header::Map(len) => encode::write_map_len(out, len).is_ok(),
+        Header::Ext(ty, len) => encode::write_ext_meta(out, len, ty).is_ok(),
+    };
+    debug_assert!(written, "writing into a vector can't fail");
+}
+
 fn read_u8<'de, R: Read<'de>>(rd: &mut R) -> Result<u8, Error> {
-    rd.read_u8().map_err(Error::InvalidDataRead)
+    Ok(read_data_u8(rd)?)
 }
 
 fn read_u16<'de, R: Read<'de>>(rd: &mut R) -> Result<u16, Error> {
-    rd.read_u16::<byteorder::BigEndian>().map_err(Error::InvalidDataRead)
+    Ok(read_data_u16(rd)?)
 }
 
 fn read_u32<'de, R: Read<'de>>(rd: &mut R) -> Result<u32, Error> {
-    rd.read_u32::<byteorder::BigEndian>().map_err(Error::InvalidDataRead)
+    Ok(read_data_u32(rd)?)
 }
 
 impl<'de, 'a, R: Read<'de>> serde::Deserializer<'de> for &'a mut Deserializer<R> {
@@ -302,15 +952,15 @@ impl<'de, 'a, R: Read<'de>> serde::Deserializer<'de> for &'a mut Deserializer<R>
                 self.read_array(len, visitor)
             }
             Marker::FixMap(len) => {
-                self.read_map(len as u32, visitor)
+                self.read_map(len as u32, None, visitor)
             }
             Marker::Map16 => {
                 let len = read_u16(&mut self.rd)?;
-                self.read_map(len as u32, visitor)
+                self.read_map(len as u32, None, visitor)
             }
             Marker::Map32 => {
                 let len = read_u32(&mut self.rd)?;
-                self.read_map(len, visitor)
+                self.read_map(len, None, visitor)
             }
             Marker::Bin8 => {
                 let len = read_u8(&mut self.rd)?;
@@ -324,73 +974,562 @@ impl<'de, 'a, R: Read<'de>> serde::Deserializer<'de> for &'a mut Deserializer<R>
                 let len = read_u32(&mut self.rd)?;
                 self.read_bytes(len, visitor)
             }
-            Marker::Reserved => Err(Error::TypeMismatch(Marker::Reserved)),
-            // TODO: Make something with exts.
+            Marker::FixExt1 | Marker::FixExt2 | Marker::FixExt4 | Marker::FixExt8 |
+            Marker::FixExt16 | Marker::Ext8 | Marker::Ext16 | Marker::Ext32 => {
+                self.marker = Some(marker);
+                self.read_ext(visitor)
+            }
             marker => Err(Error::TypeMismatch(marker)),
         }
     }
 
-    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value, Self::Error>
         where V: Visitor<'de>
     {
-        let marker = rmp::decode::read_marker(&mut self.rd)?;
+        if !self.config.lenient_bool {
+            return self.deserialize_any(visitor);
+        }
 
-        if marker == Marker::Null {
-            visitor.visit_none()
-        } else {
-            self.marker = Some(marker);
-            visitor.visit_some(self)
+        let marker = match self.marker.take() {
+            Some(marker) => marker,
+            None => rmp::decode::read_marker(&mut self.rd)?,
+        };
+
+        let val = match marker {
+            Marker::FixPos(val) => val as u64,
+            Marker::U8 => rmp::decode::read_data_u8(&mut self.rd)? as u64,
+            Marker::U16 => rmp::decode::read_data_u16(&mut self.rd)? as u64,
+            Marker::U32 => rmp::decode::read_data_u32(&mut self.rd)? as u64,
+            Marker::U64 => rmp::decode::read_data_u64(&mut self.rd)?,
+            Marker::FixNeg(..) => return Err(Error::OutOfRange),
+            Marker::I8 => rmp::decode::read_data_i8(&mut self.rd)? as u64,
+            Marker::I16 => rmp::decode::read_data_i16(&mut self.rd)? as u64,
+            Marker::I32 => rmp::decode::read_data_i32(&mut self.rd)? as u64,
+            Marker::I64 => rmp::decode::read_data_i64(&mut self.rd)? as u64,
+            marker => {
+                self.marker = Some(marker);
+                return self.deserialize_any(visitor);
+            }
+        };
+
+        match val {
+            0 => visitor.visit_bool(false),
+            1 => visitor.visit_bool(true),
+            _ => Err(Error::OutOfRange),
         }
     }
 
-    fn deserialize_enum<V>(self, _name: &str, _variants: &[&str], visitor: V) -> Result<V::Value, Error>
+    fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value, Self::Error>
         where V: Visitor<'de>
     {
-        match read_array_len(&mut self.rd)? {
-            2 => visitor.visit_enum(VariantAccess::new(self)),
-            n => Err(Error::LengthMismatch(n as u32)),
+        let marker = match self.marker.take() {
+            Some(marker) => marker,
+            None => rmp::decode::read_marker(&mut self.rd)?,
+        };
+
+        if let Some(len) = self.read_raw_len(marker)? {
+            return self.read_bytes(len, visitor);
+        }
+
+        // Byte slices without `serde_bytes` are serialized as arrays of integers. Collect them, so
+        // that borrowed `Cow<[u8]>` fields can fall back to owned data in that case.
+        let len = match marker {
+            Marker::FixArray(len) => len as u32,
+            Marker::Array16 => read_u16(&mut self.rd)? as u32,
+            Marker::Array32 => read_u32(&mut self.rd)?,
+            marker => {
+                self.marker = Some(marker);
+                return self.deserialize_any(visitor);
+            }
+        };
+
+        self.enter()?;
+        let mut buf = Vec::with_capacity(cmp::min(len as usize, 4096));
+        for _ in 0..len {
+            buf.push(u8::deserialize(&mut *self)?);
         }
+        self.depth -= 1;
+
+        visitor.visit_byte_buf(buf)
     }
 
-    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value, Error>
+    fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value, Self::Error>
         where V: Visitor<'de>
     {
-        match read_array_len(&mut self.rd)? {
-            1 => visitor.visit_newtype_struct(self),
-            n => Err(Error::LengthMismatch(n as u32)),
-        }
-    }
+        let marker = match self.marker.take() {
+            Some(marker) => marker,
+            None => rmp::decode::read_marker(&mut self.rd)?,
+        };
 
-    forward_to_deserialize_any! {
-        bool u8 u16 u32 u64 i8 i16 i32 i64 f32 f64 char
-        str string bytes byte_buf unit unit_struct seq map
-        tuple_struct struct identifier tuple
-        ignored_any
+        // Hand the payload over in one piece, rather than through a borrowed or scratch slice the
+        // visitor has to copy again.
+        let len = match marker {
+            Marker::Bin8 => read_u8(&mut self.rd)? as u32,
+            Marker::Bin16 => read_u16(&mut self.rd)? as u32,
+            Marker::Bin32 => read_u32(&mut self.rd)?,
+            marker => match self.read_raw_len(marker)? {
+                Some(len) => len,
+                None => {
+                    self.marker = Some(marker);
+                    return self.deserialize_bytes(visitor);
+                }
+            },
+        };
+
+        let buf = self.read_vec(len)?;
+        visitor.visit_byte_buf(buf)
     }
-}
 
-struct SeqAccess<'a, R: 'a> {
-    de: &'a mut Deserializer<R>,
-    left: usize,
-}
+    fn deserialize_string<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+        where V: Visitor<'de>
+    {
+        let marker = match self.marker.take() {
+            Some(marker) => marker,
+            None => rmp::decode::read_marker(&mut self.rd)?,
+        };
 
-impl<'a, R: 'a> SeqAccess<'a, R> {
-    fn new(de: &'a mut Deserializer<R>, len: usize) -> Self {
-        SeqAccess {
-            de: de,
-            left: len,
+        let len = match marker {
+            Marker::FixStr(len) => len as u32,
+            Marker::Str8 => read_u8(&mut self.rd)? as u32,
+            Marker::Str16 => read_u16(&mut self.rd)? as u32,
+            Marker::Str32 => read_u32(&mut self.rd)?,
+            marker => {
+                self.marker = Some(marker);
+                return self.deserialize_any(visitor);
+            }
+        };
+
+        let buf = self.read_vec(len)?;
+        match String::from_utf8(buf) {
+            Ok(s) => visitor.visit_string(s),
+            Err(err) => match self.config.invalid_utf8 {
+                InvalidUtf8::Reject => Err(Error::Utf8Error(err.utf8_error())),
+                InvalidUtf8::Replace => {
+                    visitor.visit_string(String::from_utf8_lossy(err.as_bytes()).into_owned())
+                }
+                InvalidUtf8::Bytes => {
+                    // Allow to unpack invalid UTF-8 bytes into a byte array.
+                    let utf8 = err.utf8_error();
+                    match visitor.visit_byte_buf::<Error>(err.into_bytes()) {
+                        Ok(buf) => Ok(buf),
+                        Err(..) => Err(Error::Utf8Error(utf8)),
+                    }
+                }
+            },
         }
     }
-}
 
-impl<'de, 'a, R: Read<'de> + 'a> de::SeqAccess<'de> for SeqAccess<'a, R> {
-    type Error = Error;
-
-    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
-        where T: DeserializeSeed<'de>
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+        where V: Visitor<'de>
     {
-        if self.left > 0 {
-            self.left -= 1;
+        // Sequences of bytes may have been written as binaries, see
+        // `Serializer::set_binary_strings`, or as raw values by old-spec encoders.
+        let len = match self.take_marker()? {
+            Marker::Bin8 => read_u8(&mut self.rd)? as u32,
+            Marker::Bin16 => read_u16(&mut self.rd)? as u32,
+            Marker::Bin32 => read_u32(&mut self.rd)?,
+            marker => match self.read_raw_len(marker)? {
+                Some(len) => len,
+                None => {
+                    if self.config.invalid_utf8 == InvalidUtf8::Bytes {
+                        if let Some(len) = self.read_str_len(marker)? {
+                            return self.read_str_seq(len, visitor);
+                        }
+                    }
+                    self.marker = Some(marker);
+                    return self.deserialize_any(visitor);
+                }
+            },
+        };
+
+        let buf = self.read_vec(len)?;
+        visitor.visit_seq(SeqDeserializer::<_, Error>::new(buf.into_iter()))
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+        where V: Visitor<'de>
+    {
+        let marker = self.take_marker()?;
+
+        if marker == Marker::Null {
+            visitor.visit_none()
+        } else {
+            self.marker = Some(marker);
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_enum<V>(self, name: &'static str, variants: &'static [&'static str],
+                           visitor: V) -> Result<V::Value, Error>
+        where V: Visitor<'de>
+    {
+        // Externally tagged enums are written as the name of a unit variant or as a map with a
+        // single entry from the name to the value, see `Serializer::set_externally_tagged`.
+        let marker = self.take_marker()?;
+        let len = match marker {
+            Marker::FixStr(..) | Marker::Str8 | Marker::Str16 | Marker::Str32 => {
+                self.marker = Some(marker);
+                return visitor.visit_enum(TaggedVariantAccess { de: self, has_value: false });
+            }
+            Marker::FixMap(len) => len as u32,
+            Marker::Map16 => read_u16(&mut self.rd)? as u32,
+            Marker::Map32 => read_u32(&mut self.rd)?,
+            marker => {
+                self.marker = Some(marker);
+                return match self.read_array_len()? {
+                    2 => {
+                        visitor.visit_enum(VariantAccess {
+                            de: self,
+                            name,
+                            variants,
+                        })
+                    }
+                    n => Err(Error::LengthMismatch(n)),
+                };
+            }
+        };
+
+        match len {
+            1 => visitor.visit_enum(TaggedVariantAccess { de: self, has_value: true }),
+            n => Err(Error::LengthMismatch(n)),
+        }
+    }
+
+    fn deserialize_newtype_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value, Error>
+        where V: Visitor<'de>
+    {
+        if name == TIMESTAMP_NAME {
+            return self.read_timestamp(visitor);
+        }
+        if name == MSGPACK_EXT_STRUCT_NAME {
+            return self.read_ext(visitor);
+        }
+        if name == RAW_VALUE_NAME {
+            return self.read_raw_value(visitor);
+        }
+
+        match self.read_array_len()? {
+            1 => visitor.visit_newtype_struct(self),
+            n => Err(Error::LengthMismatch(n as u32)),
+        }
+    }
+
+    fn deserialize_struct<V>(self, _name: &'static str, fields: &'static [&'static str],
+                             visitor: V) -> Result<V::Value, Error>
+        where V: Visitor<'de>
+    {
+        if !self.config.deny_unknown_fields && !self.config.lenient_struct_arrays {
+            return self.deserialize_any(visitor);
+        }
+
+        let marker = self.take_marker()?;
+        if self.config.deny_unknown_fields {
+            let len = match marker {
+                Marker::FixMap(len) => Some(len as u32),
+                Marker::Map16 => Some(read_u16(&mut self.rd)? as u32),
+                Marker::Map32 => Some(read_u32(&mut self.rd)?),
+                _ => None,
+            };
+            if let Some(len) = len {
+                return self.read_map(len, Some(fields), visitor);
+            }
+        }
+        if self.config.lenient_struct_arrays {
+            let len = match marker {
+                Marker::FixArray(len) => Some(len as u32),
+                Marker::Array16 => Some(read_u16(&mut self.rd)? as u32),
+                Marker::Array32 => Some(read_u32(&mut self.rd)?),
+                _ => None,
+            };
+            match len {
+                Some(len) if (len as usize) < fields.len() => {
+                    return self.read_short_struct(len, fields, visitor);
+                }
+                Some(len) => return self.read_array(len, visitor),
+                None => {}
+            }
+        }
+
+        self.marker = Some(marker);
+        self.deserialize_any(visitor)
+    }
+
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value, Error>
+        where V: Visitor<'de>
+    {
+        // Ignored values, like unknown struct fields, are skipped over without being decoded.
+        let marker = self.take_marker()?;
+        rmp::decode::skip_data(&mut self.rd, marker)?;
+
+        visitor.visit_unit()
+    }
+
+    forward_to_deserialize_any! {
+        u8 u16 u32 u64 i8 i16 i32 i64 f32 f64 char
+        str unit unit_struct map
+        tuple_struct identifier tuple
+    }
+}
+
+/// An iterator deserializing a stream of concatenated MessagePack values, one `T` at a time.
+///
+/// Log files and sockets often carry messages back to back, without any framing. The iterator
+/// ends when the input ends between two values, while input ending in the middle of a value is
+/// reported as an error. After an error the position in the stream is unknown, so the iterator
+/// ends as well.
+///
+/// # Examples
+///
+/// ```
+/// use rmp_serde::{Deserializer, StreamDeserializer};
+///
+/// // "a", "bc" and the beginning of a third string.
+/// let buf = [0xa1, 0x61, 0xa2, 0x62, 0x63, 0xa3, 0x64];
+///
+/// let mut stream = StreamDeserializer::<_, String>::new(Deserializer::from_slice(&buf));
+/// assert_eq!("a", stream.next().unwrap().unwrap());
+/// assert_eq!("bc", stream.next().unwrap().unwrap());
+/// assert!(stream.next().unwrap().is_err());
+/// assert!(stream.next().is_none());
+/// ```
+pub struct StreamDeserializer<'de, R, T> {
+    de: Deserializer<R>,
+    failed: bool,
+    lifetime: PhantomData<&'de ()>,
+    output: PhantomData<fn() -> T>,
+}
+
+impl<'de, R: Read<'de>, T: Deserialize<'de>> StreamDeserializer<'de, R, T> {
+    /// Constructs an iterator over the values read by the given deserializer, which keeps its
+    /// configuration.
+    pub fn new(de: Deserializer<R>) -> Self {
+        StreamDeserializer {
+            de,
+            failed: false,
+            lifetime: PhantomData,
+            output: PhantomData,
+        }
+    }
+
+    /// Consumes the iterator, returning the deserializer positioned after the last value read.
+    pub fn into_inner(self) -> Deserializer<R> {
+        self.de
+    }
+}
+
+impl<'de, R: Read<'de>, T: Deserialize<'de>> Iterator for StreamDeserializer<'de, R, T> {
+    type Item = Result<T, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        // Peek at the marker to tell the end of the input from a truncated value.
+        let res = match rmp::decode::read_marker(&mut self.de.rd) {
+            Ok(marker) => {
+                self.de.marker = Some(marker);
+                T::deserialize(&mut self.de)
+            }
+            Err(MarkerReadError(ref err)) if is_eof(err) => return None,
+            Err(err) => Err(err.into()),
+        };
+
+        self.failed = res.is_err();
+        Some(res)
+    }
+}
+
+/// A deserializer fed with chunks of input, which tells when it needs more of them instead of
+/// blocking.
+///
+/// Non-blocking protocols receive a message in pieces of arbitrary size. Each chunk is appended
+/// to an internal buffer and scanned with `rmp::parse::Parser`, which keeps track of where the
+/// next value ends without going over the bytes scanned already. Once the value is complete it is
+/// deserialized, and until then `next_value` fails with `Error::NeedMoreData` telling the least
+/// number of bytes still missing, so the caller can wait for them and try again.
+///
+/// Values are removed from the buffer once they were deserialized, whether this succeeded or
+/// not, so a value not matching the expected type doesn't block the ones after it.
+///
+/// # Examples
+///
+/// ```
+/// use rmp_serde::decode::{Error, PartialDeserializer};
+///
+/// let mut de = PartialDeserializer::new();
+///
+/// // ["ab", 42], arriving in two chunks.
+/// de.feed(&[0x92, 0xa2, 0x61]);
+/// match de.next_value::<(String, u8)>() {
+///     Err(Error::NeedMoreData(1)) => {}
+///     other => panic!("unexpected result: {:?}", other),
+/// }
+///
+/// de.feed(&[0x62, 0x2a]);
+/// assert_eq!(("ab".to_string(), 42), de.next_value().unwrap());
+/// ```
+#[cfg(feature = "std")]
+#[derive(Debug, Default)]
+pub struct PartialDeserializer {
+    buf: Vec<u8>,
+    parser: Parser,
+    // Whether the parser found the end of the value at the start of the buffer.
+    complete: bool,
+    config: DeserializerConfig,
+}
+
+#[cfg(feature = "std")]
+impl PartialDeserializer {
+    /// Constructs a deserializer waiting for the first chunk, with the default config.
+    pub fn new() -> Self {
+        PartialDeserializer::default()
+    }
+
+    /// Applies all options from the given config at once, replacing the current ones.
+    pub fn set_config(&mut self, config: &DeserializerConfig) {
+        self.config = config.clone();
+    }
+
+    /// Appends the chunk to the input.
+    pub fn feed(&mut self, chunk: &[u8]) {
+        self.buf.extend_from_slice(chunk);
+        self.parser.feed(chunk);
+    }
+
+    /// Deserializes the next value if all of its bytes were fed.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::NeedMoreData` if the value is incomplete, in which case nothing is
+    /// consumed, and `Error::LengthLimitExceeded` if a single payload of the value claims more
+    /// bytes than the configured maximum, before waiting for them. The reserved marker fails with
+    /// `Error::TypeMismatch`, after which the input can't be parsed any further. Otherwise
+    /// returns the errors of deserializing `T` from a slice.
+    pub fn next_value<T: DeserializeOwned>(&mut self) -> Result<T, Error> {
+        while !self.complete {
+            match self.parser.next_event() {
+                Ok(Some(..)) => {}
+                Ok(None) => {
+                    let needed = self.parser.needed();
+                    check_len(cmp::min(needed, u32::MAX as usize) as u32, self.config.max_bytes)?;
+                    return Err(Error::NeedMoreData(needed));
+                }
+                Err(..) => return Err(Error::TypeMismatch(Marker::Reserved)),
+            }
+            self.complete = self.parser.depth() == 0;
+        }
+
+        let end = self.buf.len() - self.parser.buffered();
+        let res = from_slice_with(&self.buf[..end], &self.config);
+        self.buf.drain(..end);
+        self.complete = false;
+        res
+    }
+
+    /// Returns the number of bytes fed and not deserialized yet.
+    pub fn buffered(&self) -> usize {
+        self.buf.len()
+    }
+}
+
+#[cfg(feature = "std")]
+fn is_eof(err: &rmp::decode::Error) -> bool {
+    err.kind() == io::ErrorKind::UnexpectedEof
+}
+
+#[cfg(not(feature = "std"))]
+fn is_eof(err: &rmp::decode::Error) -> bool {
+    match *err {
+        rmp::decode::BytesReadError::InsufficientBytes { .. } => true,
+    }
+}
+
+/// Presents an extension whose header was read as a tuple of its type and data.
+///
+/// The data is borrowed from the input whenever the reader allows it.
+struct ExtDeserializer<'a, R: 'a> {
+    de: &'a mut Deserializer<R>,
+    ty: i8,
+    len: u32,
+    // Number of tuple elements produced.
+    pos: u8,
+}
+
+impl<'de, 'a, R: Read<'de>> serde::Deserializer<'de> for ExtDeserializer<'a, R> {
+    type Error = Error;
+
+    fn deserialize_any<V>(mut self, visitor: V) -> Result<V::Value, Error>
+        where V: Visitor<'de>
+    {
+        let val = visitor.visit_seq(&mut self)?;
+        if self.pos < 2 {
+            return Err(Error::LengthMismatch(2));
+        }
+
+        Ok(val)
+    }
+
+    forward_to_deserialize_any! {
+        bool u8 u16 u32 u64 i8 i16 i32 i64 f32 f64 char str string bytes byte_buf option unit
+        unit_struct newtype_struct seq tuple tuple_struct map struct enum identifier
+        ignored_any
+    }
+}
+
+impl<'de, 'a, 'b, R: Read<'de>> de::SeqAccess<'de> for &'b mut ExtDeserializer<'a, R> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Error>
+        where T: DeserializeSeed<'de>
+    {
+        use serde::de::IntoDeserializer;
+        use serde::de::value::{BorrowedBytesDeserializer, BytesDeserializer};
+
+        self.pos += 1;
+        match self.pos {
+            1 => seed.deserialize(self.ty.into_deserializer()).map(Some),
+            2 => {
+                match self.de.read_bin_data(self.len)? {
+                    Reference::Borrowed(buf) => {
+                        seed.deserialize(BorrowedBytesDeserializer::new(buf)).map(Some)
+                    }
+                    Reference::Copied(buf) => {
+                        seed.deserialize(BytesDeserializer::new(buf)).map(Some)
+                    }
+                }
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(2usize.saturating_sub(self.pos as usize))
+    }
+}
+
+struct SeqAccess<'a, R: 'a> {
+    de: &'a mut Deserializer<R>,
+    left: usize,
+}
+
+impl<'a, R: 'a> SeqAccess<'a, R> {
+    fn new(de: &'a mut Deserializer<R>, len: usize) -> Self {
+        SeqAccess {
+            de: de,
+            left: len,
+        }
+    }
+}
+
+impl<'de, 'a, R: Read<'de> + 'a> de::SeqAccess<'de> for SeqAccess<'a, R> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
+        where T: DeserializeSeed<'de>
+    {
+        if self.left > 0 {
+            self.left -= 1;
             Ok(Some(seed.deserialize(&mut *self.de)?))
         } else {
             Ok(None)
@@ -405,28 +1544,84 @@ impl<'de, 'a, R: Read<'de> + 'a> de::SeqAccess<'de> for SeqAccess<'a, R> {
 struct MapAccess<'a, R: 'a> {
     de: &'a mut Deserializer<R>,
     left: usize,
+    // The fields string keys must be one of, when unknown fields are rejected.
+    fields: Option<&'static [&'static str]>,
 }
 
 impl<'a, R: 'a> MapAccess<'a, R> {
-    fn new(de: &'a mut Deserializer<R>, len: usize) -> Self {
+    fn new(de: &'a mut Deserializer<R>, len: usize,
+           fields: Option<&'static [&'static str]>) -> Self
+    {
         MapAccess {
             de: de,
             left: len,
+            fields,
         }
     }
 }
 
+impl<'de, 'a, R: Read<'de> + 'a> MapAccess<'a, R> {
+    /// Reads a key, passing string keys to the seed only if they name one of the fields, and
+    /// integer keys only if they are the index of one.
+    fn next_field_seed<K>(&mut self, fields: &'static [&'static str], seed: K) ->
+        Result<K::Value, Error>
+        where K: DeserializeSeed<'de>
+    {
+        let len = match self.de.take_marker()? {
+            Marker::FixStr(len) => len as u32,
+            Marker::Str8 => read_u8(&mut self.de.rd)? as u32,
+            Marker::Str16 => read_u16(&mut self.de.rd)? as u32,
+            Marker::Str32 => read_u32(&mut self.de.rd)?,
+            marker => {
+                let idx = match marker {
+                    Marker::FixPos(val) => val as u64,
+                    Marker::U8 => read_u8(&mut self.de.rd)? as u64,
+                    Marker::U16 => read_u16(&mut self.de.rd)? as u64,
+                    Marker::U32 => read_u32(&mut self.de.rd)? as u64,
+                    Marker::U64 => rmp::decode::read_data_u64(&mut self.de.rd)?,
+                    marker => {
+                        self.de.marker = Some(marker);
+                        return seed.deserialize(&mut *self.de);
+                    }
+                };
+                if idx >= fields.len() as u64 {
+                    return Err(de::Error::unknown_field(&idx.to_string(), fields));
+                }
+                return seed.deserialize(U64Deserializer::new(idx));
+            }
+        };
+
+        let field = {
+            let name = match self.de.read_bin_data(len)? {
+                Reference::Borrowed(buf) => buf,
+                Reference::Copied(buf) => buf,
+            };
+            match fields.iter().find(|field| field.as_bytes() == name) {
+                Some(field) => *field,
+                None => {
+                    return Err(de::Error::unknown_field(&String::from_utf8_lossy(name), fields));
+                }
+            }
+        };
+
+        seed.deserialize(BorrowedStrDeserializer::new(field))
+    }
+}
+
 impl<'de, 'a, R: Read<'de> + 'a> de::MapAccess<'de> for MapAccess<'a, R> {
     type Error = Error;
 
     fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
         where K: DeserializeSeed<'de>
     {
-        if self.left > 0 {
-            self.left -= 1;
-            Ok(Some(seed.deserialize(&mut *self.de)?))
-        } else {
-            Ok(None)
+        if self.left == 0 {
+            return Ok(None);
+        }
+
+        self.left -= 1;
+        match self.fields {
+            Some(fields) => Ok(Some(self.next_field_seed(fields, seed)?)),
+            None => Ok(Some(seed.deserialize(&mut *self.de)?)),
         }
     }
 
@@ -441,6 +1636,37 @@ impl<'de, 'a, R: Read<'de> + 'a> de::MapAccess<'de> for MapAccess<'a, R> {
     }
 }
 
+/// Presents the elements of an array as the values of a map keyed by the given struct fields.
+struct FieldsAccess<'a, R: 'a> {
+    de: &'a mut Deserializer<R>,
+    // The fields whose value was not read yet, one for every element left.
+    fields: &'static [&'static str],
+}
+
+impl<'de, 'a, R: Read<'de> + 'a> de::MapAccess<'de> for FieldsAccess<'a, R> {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+        where K: DeserializeSeed<'de>
+    {
+        match self.fields.first() {
+            Some(field) => seed.deserialize(BorrowedStrDeserializer::new(field)).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
+        where V: DeserializeSeed<'de>
+    {
+        self.fields = &self.fields[1..];
+        seed.deserialize(&mut *self.de)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.fields.len())
+    }
+}
+
 /// Default variant visitor.
 ///
 /// # Note
@@ -448,13 +1674,33 @@ impl<'de, 'a, R: Read<'de> + 'a> de::MapAccess<'de> for MapAccess<'a, R> {
 /// We use default behaviour for new type, which decodes enums with a single value as a tuple.
 pub struct VariantAccess<'a, R: 'a> {
     de: &'a mut Deserializer<R>,
+    // The enum being deserialized, used to look up discriminants.
+    name: &'static str,
+    variants: &'static [&'static str],
 }
 
 impl<'a, R: 'a> VariantAccess<'a, R> {
     pub fn new(de: &'a mut Deserializer<R>) -> Self {
         VariantAccess {
             de: de,
+            name: "",
+            variants: &[],
+        }
+    }
+
+    /// Converts the number read into the declaration-order index of the variant.
+    fn variant_idx(&self, id: u32) -> Result<u32, Error> {
+        let discriminants = &self.de.config.discriminants;
+        if !discriminants.contains(self.name) {
+            return Ok(id);
         }
+
+        discriminants.find(self.name, id)
+            .and_then(|variant| self.variants.iter().position(|&name| name == variant))
+            .map(|idx| idx as u32)
+            .ok_or_else(|| {
+                Error::Syntax(format!("unknown discriminant {} of enum {}", id, self.name))
+            })
     }
 }
 
@@ -467,7 +1713,17 @@ impl<'de, 'a, R: Read<'de>> de::EnumAccess<'de> for VariantAccess<'a, R> {
     {
         use serde::de::IntoDeserializer;
 
-        let idx: u32 = serde::Deserialize::deserialize(&mut *self.de)?;
+        // Variants may be identified by name rather than by number, see
+        // `Serializer::set_string_variants`. Names are looked up by the visitor itself.
+        let marker = self.de.take_marker()?;
+        self.de.marker = Some(marker);
+        if let Marker::FixStr(..) | Marker::Str8 | Marker::Str16 | Marker::Str32 = marker {
+            let val = seed.deserialize(&mut *self.de)?;
+            return Ok((val, self));
+        }
+
+        let id: u32 = serde::Deserialize::deserialize(&mut *self.de)?;
+        let idx = self.variant_idx(id)?;
         let val: Result<_, Error> = seed.deserialize(idx.into_deserializer());
         Ok((val?, self))
     }
@@ -501,14 +1757,93 @@ impl<'de, 'a, R: Read<'de>> de::VariantAccess<'de> for VariantAccess<'a, R> {
     }
 }
 
+/// Reads a variant of an externally tagged enum, whose name comes first and is followed by its
+/// value unless it is a unit variant written without a map.
+struct TaggedVariantAccess<'a, R: 'a> {
+    de: &'a mut Deserializer<R>,
+    has_value: bool,
+}
+
+impl<'a, R: 'a> TaggedVariantAccess<'a, R> {
+    fn expect_value(&self, expected: &str) -> Result<(), Error> {
+        if self.has_value {
+            Ok(())
+        } else {
+            Err(de::Error::invalid_type(de::Unexpected::UnitVariant, &expected))
+        }
+    }
+}
+
+impl<'de, 'a, R: Read<'de>> de::EnumAccess<'de> for TaggedVariantAccess<'a, R> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self), Error>
+        where V: de::DeserializeSeed<'de>,
+    {
+        let val = seed.deserialize(&mut *self.de)?;
+        Ok((val, self))
+    }
+}
+
+impl<'de, 'a, R: Read<'de>> de::VariantAccess<'de> for TaggedVariantAccess<'a, R> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        if self.has_value {
+            <()>::deserialize(self.de)?;
+        }
+
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Self::Error>
+        where T: DeserializeSeed<'de>
+    {
+        self.expect_value("newtype variant")?;
+        seed.deserialize(self.de)
+    }
+
+    fn tuple_variant<V>(self, len: usize, visitor: V) -> Result<V::Value, Error>
+        where V: Visitor<'de>
+    {
+        self.expect_value("tuple variant")?;
+        de::Deserializer::deserialize_tuple(self.de, len, visitor)
+    }
+
+    fn struct_variant<V>(self, fields: &'static [&'static str], visitor: V) ->
+        Result<V::Value, Error>
+        where V: Visitor<'de>
+    {
+        self.expect_value("struct variant")?;
+        de::Deserializer::deserialize_tuple(self.de, fields.len(), visitor)
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Reference<'b, 'c, T: ?Sized + 'static> {
     Borrowed(&'b T),
     Copied(&'c T),
 }
 
-pub trait Read<'de>: io::Read {
-    fn read_slice<'a>(&'a mut self, len: usize) -> Result<Reference<'de, 'a, [u8]>, io::Error>;
+/// A reader the `Deserializer` can borrow data from.
+///
+/// Read failures are reported as `rmp::decode::Error`, which is `std::io::Error` with the `std`
+/// feature and `rmp::decode::BytesReadError` without it.
+pub trait Read<'de>: RmpRead<Error = rmp::decode::Error> {
+    fn read_slice<'a>(&'a mut self, len: usize) ->
+        Result<Reference<'de, 'a, [u8]>, rmp::decode::Error>;
+
+    /// Reads exactly `len` bytes into a new vector, which owned strings and byte buffers take over.
+    ///
+    /// The default implementation copies the result of `read_slice`. Readers which can't borrow
+    /// should read straight into the vector instead of going through a scratch buffer.
+    fn read_vec(&mut self, len: usize) -> Result<Vec<u8>, rmp::decode::Error> {
+        match self.read_slice(len)? {
+            Reference::Borrowed(buf) => Ok(buf.to_vec()),
+            Reference::Copied(buf) => Ok(buf.to_vec()),
+        }
+    }
 }
 
 pub struct SliceReader<'a> {
@@ -525,9 +1860,11 @@ impl<'a> SliceReader<'a> {
 
 impl<'de> Read<'de> for SliceReader<'de> {
     #[inline]
-    fn read_slice<'a>(&'a mut self, len: usize) -> Result<Reference<'de, 'a, [u8]>, io::Error> {
+    fn read_slice<'a>(&'a mut self, len: usize) ->
+        Result<Reference<'de, 'a, [u8]>, rmp::decode::Error>
+    {
         if len > self.inner.len() {
-            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "unexpected EOF"))
+            return Err(unexpected_eof(len, self.inner.len()));
         }
         let (a, b) = self.inner.split_at(len);
         self.inner = b;
@@ -535,6 +1872,86 @@ impl<'de> Read<'de> for SliceReader<'de> {
     }
 }
 
+/// Reads a buffer filled by the deserializer itself, which can't be borrowed from.
+struct CopyReader<'a> {
+    inner: &'a [u8],
+}
+
+impl<'de, 'b> Read<'de> for CopyReader<'b> {
+    fn read_slice<'a>(&'a mut self, len: usize) ->
+        Result<Reference<'de, 'a, [u8]>, rmp::decode::Error>
+    {
+        if len > self.inner.len() {
+            return Err(unexpected_eof(len, self.inner.len()));
+        }
+        let (a, b) = self.inner.split_at(len);
+        self.inner = b;
+        Ok(Reference::Copied(a))
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'a> io::Read for CopyReader<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.read(buf)
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl<'a> RmpRead for CopyReader<'a> {
+    type Error = rmp::decode::Error;
+
+    fn read_exact_buf(&mut self, buf: &mut [u8]) -> Result<(), Self::Error> {
+        self.inner.read_exact_buf(buf)
+    }
+}
+
+/// Copies everything read from the inner reader into a buffer.
+struct RecordReader<'a, R: 'a> {
+    inner: &'a mut R,
+    buf: Vec<u8>,
+}
+
+#[cfg(feature = "std")]
+impl<'a, R: RmpRead<Error = io::Error>> io::Read for RecordReader<'a, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.read_exact(buf)?;
+        Ok(buf.len())
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        self.inner.read_exact_buf(buf)?;
+        self.buf.extend_from_slice(buf);
+        Ok(())
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl<'a, R: RmpRead> RmpRead for RecordReader<'a, R> {
+    type Error = R::Error;
+
+    fn read_exact_buf(&mut self, buf: &mut [u8]) -> Result<(), Self::Error> {
+        self.inner.read_exact_buf(buf)?;
+        self.buf.extend_from_slice(buf);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+fn unexpected_eof(_expected: usize, _actual: usize) -> rmp::decode::Error {
+    io::Error::new(io::ErrorKind::UnexpectedEof, "unexpected EOF")
+}
+
+#[cfg(not(feature = "std"))]
+fn unexpected_eof(expected: usize, actual: usize) -> rmp::decode::Error {
+    rmp::decode::BytesReadError::InsufficientBytes {
+        expected: expected,
+        actual: actual,
+        position: 0,
+    }
+}
+
+#[cfg(feature = "std")]
 impl<'a> io::Read for SliceReader<'a> {
     #[inline]
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
@@ -547,11 +1964,23 @@ impl<'a> io::Read for SliceReader<'a> {
     }
 }
 
+#[cfg(not(feature = "std"))]
+impl<'a> RmpRead for SliceReader<'a> {
+    type Error = rmp::decode::Error;
+
+    #[inline]
+    fn read_exact_buf(&mut self, buf: &mut [u8]) -> Result<(), Self::Error> {
+        self.inner.read_exact_buf(buf)
+    }
+}
+
+#[cfg(feature = "std")]
 pub struct ReadReader<R: io::Read> {
     inner: R,
     buf: Vec<u8>
 }
 
+#[cfg(feature = "std")]
 impl<R: io::Read> ReadReader<R> {
     fn new(rd: R) -> Self {
         ReadReader {
@@ -561,6 +1990,7 @@ impl<R: io::Read> ReadReader<R> {
     }
 }
 
+#[cfg(feature = "std")]
 impl<'de, R: io::Read> Read<'de> for ReadReader<R> {
     #[inline]
     fn read_slice<'a>(&'a mut self, len: usize) -> Result<Reference<'de, 'a, [u8]>, io::Error> {
@@ -570,8 +2000,16 @@ impl<'de, R: io::Read> Read<'de> for ReadReader<R> {
 
         Ok(Reference::Copied(&self.buf[..]))
     }
+
+    fn read_vec(&mut self, len: usize) -> Result<Vec<u8>, io::Error> {
+        let mut buf = vec![0; len];
+        self.inner.read_exact(&mut buf[..])?;
+
+        Ok(buf)
+    }
 }
 
+#[cfg(feature = "std")]
 impl<R: io::Read> io::Read for ReadReader<R> {
     #[inline]
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
@@ -600,9 +2038,32 @@ fn test_slice_read() {
 /// This conversion can fail if the structure of the Value does not match the structure expected
 /// by `T`. It can also fail if the structure is correct but `T`'s implementation of `Deserialize`
 /// decides that something is wrong with the data, for example required struct fields are missing.
+#[cfg(feature = "std")]
 pub fn from_read<R, T>(rd: R) -> Result<T, Error>
     where R: io::Read,
           T: DeserializeOwned
 {
     Deserialize::deserialize(&mut Deserializer::new(rd))
 }
+
+/// Deserialize an instance of type `T` from an I/O stream of MessagePack, using the given config.
+#[cfg(feature = "std")]
+pub fn from_read_with<R, T>(rd: R, config: &DeserializerConfig) -> Result<T, Error>
+    where R: io::Read,
+          T: DeserializeOwned
+{
+    let mut de = Deserializer::new(rd);
+    de.set_config(config);
+    Deserialize::deserialize(&mut de)
+}
+
+/// Deserialize an instance of type `T` from a byte slice of MessagePack, using the given config.
+///
+/// Like `from_slice`, strings and byte arrays can be borrowed from the input.
+pub fn from_slice_with<'a, T>(input: &'a [u8], config: &DeserializerConfig) -> Result<T, Error>
+    where T: Deserialize<'a>
+{
+    let mut de = Deserializer::from_slice(input);
+    de.set_config(config);
+    Deserialize::deserialize(&mut de)
+}
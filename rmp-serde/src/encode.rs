@@ -1,16 +1,47 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::{self, Display};
+use core::marker::PhantomData;
+#[cfg(feature = "std")]
 use std::error;
-use std::fmt::{self, Display};
-use std::io::Write;
+#[cfg(feature = "std")]
+use std::io;
 
 use serde;
 use serde::Serialize;
-use serde::ser::{SerializeMap, SerializeSeq, SerializeStruct, SerializeStructVariant,
+use serde::ser::{StdError, SerializeMap, SerializeSeq, SerializeStruct, SerializeStructVariant,
                  SerializeTuple, SerializeTupleStruct, SerializeTupleVariant};
 
 use rmp;
 use rmp::Marker;
+
+use {Discriminants, MSGPACK_EXT_STRUCT_NAME, RAW_VALUE_NAME, TIMESTAMP_NAME, TIMESTAMP_TYPE};
 use rmp::encode::{write_nil, write_bool, write_uint, write_sint, write_f32, write_f64, write_str,
-                  write_array_len, write_map_len, write_bin_len, ValueWriteError};
+                  write_array_len, write_map_len, write_bin_len, write_ext_meta, Counted, RmpWrite,
+                  ValueWriteError};
+
+#[cfg(feature = "std")]
+pub use std::io::Write;
+
+pub use self::batch::Batch;
+pub use self::hash::{content_hash, content_hash_with, ContentHasher, Sha256};
+pub use self::map::MapStream;
+pub use self::trace::{to_vec_traced, TraceEntry, TraceWriter, TracingCompound, TracingSerializer};
+
+mod batch;
+mod bytes;
+mod ext;
+mod hash;
+mod map;
+mod trace;
+
+/// A writer the `Serializer` can output to without the `std` feature, like a fixed-size
+/// `&mut [u8]` buffer. With the `std` feature this is `std::io::Write`.
+#[cfg(not(feature = "std"))]
+pub trait Write: RmpWrite<Error = rmp::encode::Error> {}
+
+#[cfg(not(feature = "std"))]
+impl<T: RmpWrite<Error = rmp::encode::Error> + ?Sized> Write for T {}
 
 #[derive(Debug)]
 pub enum Error {
@@ -22,10 +53,24 @@ pub enum Error {
     /// Depth limit exceeded
     DepthLimitExceeded,
     Syntax(String),
+
+    /// A lossy conversion was rejected by the hook set with `Serializer::set_lossy_hook`.
+    LossyConversion(Lossy),
+
+    /// A map had several entries with the same key in the canonical mode, holding the encoded key.
+    DuplicateKey(Vec<u8>),
+
+    /// The buffer given to `to_slice` is shorter than the encoded value, which takes `needed`
+    /// bytes.
+    BufferTooSmall { needed: usize },
+
+    /// An extension of the given type was to be written while `Serializer::set_deny_ext` forbids
+    /// them.
+    ExtNotAllowed(i8),
 }
 
-impl error::Error for Error {
-    fn description(&self) -> &str {
+impl Error {
+    fn as_str(&self) -> &str {
         match *self {
             Error::InvalidValueWrite(..) => "invalid value write",
             Error::UnknownLength => {
@@ -33,157 +78,1432 @@ impl error::Error for Error {
             }
             Error::DepthLimitExceeded => "depth limit exceeded",
             Error::Syntax(..) => "syntax error",
+            Error::LossyConversion(..) => "lossy conversion rejected",
+            Error::DuplicateKey(..) => "duplicate map key",
+            Error::BufferTooSmall { .. } => "buffer too small",
+            Error::ExtNotAllowed(..) => "extension types are not allowed",
+        }
+    }
+}
+
+impl StdError for Error {
+    fn description(&self) -> &str {
+        self.as_str()
+    }
+
+    #[cfg(feature = "std")]
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            Error::InvalidValueWrite(ref err) => Some(err),
+            Error::UnknownLength => None,
+            Error::DepthLimitExceeded => None,
+            Error::Syntax(..) => None,
+            Error::LossyConversion(..) => None,
+            Error::DuplicateKey(..) => None,
+            Error::BufferTooSmall { .. } => None,
+            Error::ExtNotAllowed(..) => None,
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.write_str(self.as_str())
+    }
+}
+
+impl From<ValueWriteError> for Error {
+    fn from(err: ValueWriteError) -> Error {
+        Error::InvalidValueWrite(err)
+    }
+}
+
+impl serde::ser::Error for Error {
+    /// Raised when there is general error when deserializing a type.
+    fn custom<T: Display>(msg: T) -> Error {
+        Error::Syntax(format!("{}", msg))
+    }
+}
+
+// TODO: Great idea - drop this trait and make Serializer types configurable.
+pub trait VariantWriter {
+    fn write_struct_len<W>(&self, wr: &mut W, len: u32) -> Result<Marker, ValueWriteError>
+        where W: Write;
+    fn write_field_name<W>(&self, wr: &mut W, key: &str) -> Result<(), ValueWriteError>
+        where W: Write;
+
+    /// Returns `true` if struct fields are identified by their position rather than by their
+    /// name, so that skipping one of them shifts the following ones.
+    fn is_positional(&self) -> bool {
+        false
+    }
+}
+
+/// Writes struct as MessagePack array with no field names
+pub struct StructArrayWriter;
+
+impl VariantWriter for StructArrayWriter {
+    fn write_struct_len<W>(&self, wr: &mut W, len: u32) -> Result<Marker, ValueWriteError>
+        where W: Write
+    {
+        write_array_len(wr, len)
+    }
+
+    /// This implementation does not write field names
+    #[allow(unused_variables)]
+    fn write_field_name<W>(&self, wr: &mut W, _key: &str) -> Result<(), ValueWriteError>
+        where W: Write
+    {
+        Ok(())
+    }
+
+    fn is_positional(&self) -> bool {
+        true
+    }
+}
+
+/// Writes struct as MessagePack map including field names
+pub struct StructMapWriter;
+
+impl VariantWriter for StructMapWriter {
+    fn write_struct_len<W>(&self, wr: &mut W, len: u32) -> Result<Marker, ValueWriteError>
+        where W: Write
+    {
+        write_map_len(wr, len)
+    }
+
+    fn write_field_name<W>(&self, wr: &mut W, key: &str) -> Result<(), ValueWriteError>
+        where W: Write
+    {
+        write_str(wr, key)
+    }
+}
+
+/// A predicate deciding whether a struct field is serialized, given the names of the struct and of
+/// the field. See `Serializer::set_field_filter`.
+pub type FieldFilter = fn(&str, &str) -> bool;
+
+/// A potentially lossy conversion the `Serializer` performs because of its options, after which
+/// a decoder can't tell the original type anymore.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Lossy {
+    /// A byte array is written as a string because of `Serializer::set_bytes_format`, like in the
+    /// old-spec compatibility mode.
+    BinaryAsString,
+    /// A string is written as a binary because of `Serializer::set_str_format`.
+    StringAsBinary,
+    /// A boolean is written as an integer because of `Serializer::set_bool_as_int`.
+    BoolAsInt,
+    /// A 64-bit float is written as a 32-bit one because of `Override::F64AsF32`.
+    F64AsF32,
+}
+
+/// The MessagePack types strings or byte arrays are written as, see `Serializer::set_str_format`
+/// and `Serializer::set_bytes_format`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RawFormat {
+    /// The str family: fixstr, str8, str16 or str32, whichever is the shortest.
+    Str,
+    /// The bin family: bin8, bin16 or bin32, whichever is the shortest.
+    Bin,
+    /// The raw type of the specification before 2013, written as fixstr, str16 or str32 but never
+    /// str8, which old decoders don't know.
+    Legacy,
+}
+
+/// A callback deciding whether a lossy conversion may happen, returning `false` to fail the
+/// serialization with `Error::LossyConversion` instead. See `Serializer::set_lossy_hook`.
+pub type LossyHook = fn(Lossy) -> bool;
+
+/// A change to how a value is written, see `Overrides`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Override {
+    /// Writes all 64-bit floats inside the value as 32-bit ones.
+    ///
+    /// This loses precision, so it is reported to the lossy hook as `Lossy::F64AsF32`.
+    F64AsF32,
+    /// Writes the value as an extension of the given type, whose data is the MessagePack encoding
+    /// the value would have had otherwise.
+    Ext(i8),
+}
+
+/// A registry of overrides applied by the `Serializer` to values of the given types or at the
+/// given field paths, for adapting the wire format per peer without changing the Rust types.
+///
+/// Types are matched by the name serde sees for structs, tuple structs, newtype structs and unit
+/// structs, i.e. after applying `rename` attributes. Paths are made of the names of the struct
+/// fields leading to the value from the root, like `/user/embedding`; elements of sequences and
+/// entries of maps don't add segments to the path, so `/items/price` matches the `price` field of
+/// every struct in the `items` field.
+///
+/// An override applies to the whole value it matches, including everything nested inside it.
+/// When several overrides match the same value, all of them apply.
+///
+/// The registry is applied with `Serializer::set_overrides` or `SerializerConfig::overrides`.
+///
+/// # Examples
+///
+/// ```
+/// extern crate serde;
+/// #[macro_use]
+/// extern crate serde_derive;
+/// extern crate rmp_serde;
+///
+/// use rmp_serde::SerializerConfig;
+/// use rmp_serde::encode::{Override, Overrides};
+///
+/// #[derive(Serialize)]
+/// struct Point(u8, u8);
+///
+/// #[derive(Serialize)]
+/// struct Doc {
+///     origin: Point,
+///     embedding: Vec<f64>,
+/// }
+///
+/// fn main() {
+///     let overrides = Overrides::new()
+///         .for_type("Point", Override::Ext(7))
+///         .for_path("/embedding", Override::F64AsF32);
+///     let config = SerializerConfig::new().overrides(overrides);
+///
+///     let doc = Doc { origin: Point(1, 2), embedding: vec![0.5] };
+///     let buf = rmp_serde::to_vec_with(&doc, &config).unwrap();
+///
+///     assert_eq!(vec![
+///         0x92,
+///         0xc7, 0x03, 0x07, 0x92, 0x01, 0x02, // ext 7 holding [1, 2]
+///         0x91, 0xca, 0x3f, 0x00, 0x00, 0x00, // [0.5f32]
+///     ], buf);
+/// }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Overrides {
+    types: Vec<(&'static str, Override)>,
+    paths: Vec<(String, Override)>,
+}
+
+impl Overrides {
+    /// Constructs an empty registry, which leaves the wire format as it is.
+    pub fn new() -> Self {
+        Overrides::default()
+    }
+
+    /// Registers an override for all values of the type with the given name.
+    pub fn for_type(mut self, name: &'static str, ov: Override) -> Self {
+        self.types.push((name, ov));
+        self
+    }
+
+    /// Registers an override for the value at the given path of struct field names, written with
+    /// a leading slash like `/user/embedding`.
+    pub fn for_path(mut self, path: &str, ov: Override) -> Self {
+        self.paths.push((path.into(), ov));
+        self
+    }
+
+    /// Returns `true` if no override is registered.
+    pub fn is_empty(&self) -> bool {
+        self.types.is_empty() && self.paths.is_empty()
+    }
+
+    /// Returns `true` if overrides are registered for field paths, which need to be tracked then.
+    fn has_paths(&self) -> bool {
+        !self.paths.is_empty()
+    }
+
+    /// Returns the combined overrides for the type with the given name.
+    fn for_type_name(&self, name: &str) -> Option<Scope> {
+        Scope::combine(self.types.iter().filter(|entry| entry.0 == name).map(|entry| entry.1))
+    }
+
+    /// Returns the combined overrides for the value at the given path.
+    fn for_field_path(&self, path: &[&str]) -> Option<Scope> {
+        let matches = self.paths.iter()
+            .filter(|entry| {
+                let mut segments = entry.0.split('/');
+                // Skip the empty segment before the leading slash.
+                segments.next();
+                segments.eq(path.iter().cloned())
+            })
+            .map(|entry| entry.1);
+
+        Scope::combine(matches)
+    }
+}
+
+/// The overrides in effect for a value.
+#[derive(Clone, Copy, Debug)]
+struct Scope {
+    ext: Option<i8>,
+    f64_as_f32: bool,
+}
+
+impl Scope {
+    fn combine<I: Iterator<Item = Override>>(iter: I) -> Option<Scope> {
+        let mut scope = None;
+        for ov in iter {
+            let entry = scope.get_or_insert(Scope { ext: None, f64_as_f32: false });
+            match ov {
+                Override::F64AsF32 => entry.f64_as_f32 = true,
+                Override::Ext(ty) => entry.ext = Some(ty),
+            }
+        }
+
+        scope
+    }
+}
+
+/// Serialization options that can be applied to a `Serializer` at once.
+///
+/// A serializer built with `Serializer::with_config` has the same type whatever the options are,
+/// which makes it easy to store in a struct field, unlike one built with `Serializer::with` and a
+/// `VariantWriter`.
+///
+/// # Examples
+///
+/// ```
+/// use rmp_serde::encode::SerializerConfig;
+///
+/// let config = SerializerConfig::new().max_depth(16).compat(true);
+///
+/// assert_eq!(16, config.get_max_depth());
+/// assert!(config.is_compat());
+/// ```
+///
+/// ```
+/// extern crate serde;
+/// #[macro_use]
+/// extern crate serde_derive;
+/// extern crate rmp_serde;
+///
+/// use serde::Serialize;
+/// use rmp_serde::Serializer;
+/// use rmp_serde::encode::SerializerConfig;
+///
+/// #[derive(Serialize)]
+/// enum Shape {
+///     Circle { r: u8 },
+/// }
+///
+/// struct Encoder {
+///     se: Serializer<Vec<u8>>,
+/// }
+///
+/// fn main() {
+///     let config = SerializerConfig::new().with_struct_map().with_string_variants();
+///     let mut enc = Encoder { se: Serializer::with_config(Vec::new(), &config) };
+///
+///     Shape::Circle { r: 1 }.serialize(&mut enc.se).unwrap();
+///
+///     // ["Circle", {"r": 1}]
+///     assert_eq!(vec![0x92, 0xa6, 0x43, 0x69, 0x72, 0x63, 0x6c, 0x65, 0x81, 0xa1, 0x72, 0x01],
+///                enc.se.into_inner());
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct SerializerConfig {
+    max_depth: usize,
+    str_format: RawFormat,
+    bytes_format: RawFormat,
+    deny_ext: bool,
+    canonical: bool,
+    struct_map: bool,
+    struct_index_map: bool,
+    string_variants: bool,
+    externally_tagged: bool,
+    binary_strings: bool,
+    bool_as_int: bool,
+    lossy: Option<LossyHook>,
+    discriminants: Discriminants,
+    overrides: Overrides,
+    skipped_as_nil: bool,
+}
+
+impl SerializerConfig {
+    /// Constructs a new config with default options.
+    pub fn new() -> Self {
+        SerializerConfig::default()
+    }
+
+    /// Sets the maximum nesting depth that is allowed, see `Serializer::set_max_depth`.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    /// Returns the maximum nesting depth that is allowed.
+    pub fn get_max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    /// Enables or disables the old-spec compatibility mode, see `Serializer::set_compat`.
+    pub fn compat(self, compat: bool) -> Self {
+        if compat {
+            self.str_format(RawFormat::Legacy).bytes_format(RawFormat::Legacy).deny_ext(true)
+        } else {
+            self.str_format(RawFormat::Str).bytes_format(RawFormat::Bin).deny_ext(false)
+        }
+    }
+
+    /// Returns `true` if only types of the old spec are written: strings and byte arrays as raw
+    /// values, and no extensions.
+    pub fn is_compat(&self) -> bool {
+        self.str_format == RawFormat::Legacy && self.bytes_format == RawFormat::Legacy &&
+            self.deny_ext
+    }
+
+    /// Enables or disables failing on extensions, see `Serializer::set_deny_ext`.
+    pub fn deny_ext(mut self, deny: bool) -> Self {
+        self.deny_ext = deny;
+        self
+    }
+
+    /// Returns `true` if writing an extension fails.
+    pub fn is_deny_ext(&self) -> bool {
+        self.deny_ext
+    }
+
+    /// Sets the types strings are written as, see `Serializer::set_str_format`.
+    pub fn str_format(mut self, format: RawFormat) -> Self {
+        self.str_format = format;
+        self
+    }
+
+    /// Returns the types strings are written as.
+    pub fn get_str_format(&self) -> RawFormat {
+        self.str_format
+    }
+
+    /// Sets the types byte arrays are written as, see `Serializer::set_bytes_format`.
+    pub fn bytes_format(mut self, format: RawFormat) -> Self {
+        self.bytes_format = format;
+        self
+    }
+
+    /// Returns the types byte arrays are written as.
+    pub fn get_bytes_format(&self) -> RawFormat {
+        self.bytes_format
+    }
+
+    /// Enables or disables the canonical mode, see `Serializer::set_canonical`.
+    pub fn canonical(mut self, canonical: bool) -> Self {
+        self.canonical = canonical;
+        self
+    }
+
+    /// Returns `true` if map entries are sorted by key.
+    pub fn is_canonical(&self) -> bool {
+        self.canonical
+    }
+
+    /// Writes structs as maps with their field names as keys, see `Serializer::set_struct_map`.
+    pub fn with_struct_map(mut self) -> Self {
+        self.struct_map = true;
+        self
+    }
+
+    /// Returns `true` if structs are written as maps.
+    pub fn is_struct_map(&self) -> bool {
+        self.struct_map
+    }
+
+    /// Writes structs as maps with their field indices as keys, see
+    /// `Serializer::set_struct_index_map`.
+    pub fn with_struct_index_map(mut self) -> Self {
+        self.struct_index_map = true;
+        self
+    }
+
+    /// Returns `true` if structs are written as maps keyed by field index.
+    pub fn is_struct_index_map(&self) -> bool {
+        self.struct_index_map
+    }
+
+    /// Identifies enum variants by their names, see `Serializer::set_string_variants`.
+    pub fn with_string_variants(mut self) -> Self {
+        self.string_variants = true;
+        self
+    }
+
+    /// Returns `true` if enum variants are identified by their names.
+    pub fn is_string_variants(&self) -> bool {
+        self.string_variants
+    }
+
+    /// Writes enums as maps from the variant name to its value, see
+    /// `Serializer::set_externally_tagged`.
+    pub fn with_externally_tagged(mut self) -> Self {
+        self.externally_tagged = true;
+        self
+    }
+
+    /// Returns `true` if enums are written as maps from the variant name to its value.
+    pub fn is_externally_tagged(&self) -> bool {
+        self.externally_tagged
+    }
+
+    /// Writes sequences of bytes as binaries, see `Serializer::set_binary_strings`.
+    pub fn with_binary_strings(mut self) -> Self {
+        self.binary_strings = true;
+        self
+    }
+
+    /// Returns `true` if sequences of bytes are written as binaries.
+    pub fn is_binary_strings(&self) -> bool {
+        self.binary_strings
+    }
+
+    /// Enables or disables writing booleans as integers, see `Serializer::set_bool_as_int`.
+    pub fn bool_as_int(mut self, enabled: bool) -> Self {
+        self.bool_as_int = enabled;
+        self
+    }
+
+    /// Returns `true` if booleans are written as integers.
+    pub fn is_bool_as_int(&self) -> bool {
+        self.bool_as_int
+    }
+
+    /// Sets the callback invoked before each lossy conversion, see `Serializer::set_lossy_hook`.
+    pub fn lossy_hook(mut self, hook: Option<LossyHook>) -> Self {
+        self.lossy = hook;
+        self
+    }
+
+    /// Returns the callback invoked before each lossy conversion.
+    pub fn get_lossy_hook(&self) -> Option<LossyHook> {
+        self.lossy
+    }
+
+    /// Sets the discriminants enum variants are encoded with, see `Serializer::set_discriminants`.
+    pub fn discriminants(mut self, discriminants: Discriminants) -> Self {
+        self.discriminants = discriminants;
+        self
+    }
+
+    /// Returns the discriminants enum variants are encoded with.
+    pub fn get_discriminants(&self) -> &Discriminants {
+        &self.discriminants
+    }
+
+    /// Sets the overrides applied to selected types and fields, see `Serializer::set_overrides`.
+    pub fn overrides(mut self, overrides: Overrides) -> Self {
+        self.overrides = overrides;
+        self
+    }
+
+    /// Returns the overrides applied to selected types and fields.
+    pub fn get_overrides(&self) -> &Overrides {
+        &self.overrides
+    }
+
+    /// Enables or disables writing nil in place of skipped struct fields in the positional
+    /// representation, see `Serializer::set_skipped_as_nil`.
+    pub fn skipped_as_nil(mut self, enabled: bool) -> Self {
+        self.skipped_as_nil = enabled;
+        self
+    }
+
+    /// Returns `true` if nil is written in place of skipped struct fields.
+    pub fn is_skipped_as_nil(&self) -> bool {
+        self.skipped_as_nil
+    }
+}
+
+impl Default for SerializerConfig {
+    fn default() -> Self {
+        SerializerConfig {
+            max_depth: 1024,
+            str_format: RawFormat::Str,
+            bytes_format: RawFormat::Bin,
+            deny_ext: false,
+            canonical: false,
+            struct_map: false,
+            struct_index_map: false,
+            string_variants: false,
+            externally_tagged: false,
+            binary_strings: false,
+            bool_as_int: false,
+            lossy: None,
+            discriminants: Discriminants::new(),
+            overrides: Overrides::new(),
+            skipped_as_nil: false,
+        }
+    }
+}
+
+/// Represents MessagePack serialization implementation.
+///
+/// # Note
+///
+/// MessagePack has no specification about how to encode enum types. Thus we are free to do
+/// whatever we want, so the given chose may be not ideal for you.
+///
+/// Every Rust enum value can be represented as a tuple of index with a value.
+///
+/// All instances of `ErrorKind::Interrupted` are handled by this function and the underlying
+/// operation is retried.
+// TODO: Docs. Examples.
+pub struct Serializer<W, V = StructArrayWriter> {
+    wr: Output<W>,
+    vw: V,
+    depth: usize,
+    str_format: RawFormat,
+    bytes_format: RawFormat,
+    deny_ext: bool,
+    canonical: bool,
+    struct_map: bool,
+    struct_index_map: bool,
+    string_variants: bool,
+    externally_tagged: bool,
+    binary_strings: bool,
+    bool_as_int: bool,
+    lossy: Option<LossyHook>,
+    filter: Option<FieldFilter>,
+    discriminants: Discriminants,
+    overrides: Overrides,
+    skipped_as_nil: bool,
+    // Names of the struct fields leading to the current value, tracked for path overrides only.
+    path: Vec<&'static str>,
+    // Set while serializing a value 64-bit floats are written as 32-bit ones in.
+    f64_as_f32: bool,
+    // Set while serializing the data of an extension, which is passed as bytes.
+    ext: Option<i8>,
+    // Set while serializing a `RawValue`, whose bytes are written as they are.
+    raw: bool,
+}
+
+impl<W, V> Serializer<W, V> {
+    /// Changes the maximum nesting depth that is allowed
+    pub fn set_max_depth(&mut self, depth: usize) {
+        self.depth = depth;
+    }
+
+    /// Enables or disables the old-spec compatibility mode.
+    ///
+    /// In this mode the serializer emits only types known to MessagePack decoders that predate the
+    /// 2013 specification update: both strings and byte arrays are written as the old raw type
+    /// (fixstr, str16 or str32 markers), never using the str8 or bin markers, and extensions,
+    /// like timestamps, fail with `Error::ExtNotAllowed`.
+    ///
+    /// This is a shorthand for setting both `set_str_format` and `set_bytes_format` to
+    /// `RawFormat::Legacy` and enabling `set_deny_ext`, or for restoring their defaults.
+    pub fn set_compat(&mut self, compat: bool) {
+        if compat {
+            self.str_format = RawFormat::Legacy;
+            self.bytes_format = RawFormat::Legacy;
+        } else {
+            self.str_format = RawFormat::Str;
+            self.bytes_format = RawFormat::Bin;
+        }
+        self.deny_ext = compat;
+    }
+
+    /// Enables or disables failing with `Error::ExtNotAllowed` instead of writing an extension,
+    /// for peers that don't know the ext family, which includes timestamps and values with an
+    /// `Override::Ext`.
+    pub fn set_deny_ext(&mut self, deny: bool) {
+        self.deny_ext = deny;
+    }
+
+    /// Sets the types strings are written as, which is `RawFormat::Str` by default.
+    ///
+    /// This applies to chars, field names of structs written as maps and variant names too.
+    /// Writing strings as binaries is reported to the lossy hook as `Lossy::StringAsBinary`.
+    pub fn set_str_format(&mut self, format: RawFormat) {
+        self.str_format = format;
+    }
+
+    /// Sets the types byte arrays, like `serde_bytes` values, are written as, which is
+    /// `RawFormat::Bin` by default.
+    ///
+    /// Writing them as strings, for peers predating the bin family or mapping every raw value
+    /// to a string type, is reported to the lossy hook as `Lossy::BinaryAsString`. Extension
+    /// data is not affected.
+    pub fn set_bytes_format(&mut self, format: RawFormat) {
+        self.bytes_format = format;
+    }
+
+    /// Enables or disables the canonical mode, in which the entries of every map are sorted by the
+    /// bytes of their encoded keys, and maps with duplicate keys fail with `Error::DuplicateKey`.
+    ///
+    /// Together with the smallest encoding this crate always picks for integers, strings and
+    /// lengths, this makes the output a function of the content alone, whatever the iteration
+    /// order of the maps is, so it can be hashed or signed. Structs written as maps keep their
+    /// fields in declaration order, and floats keep their width, unlike with `rmp::canonicalize`.
+    /// Entries are buffered until the map ends, which is why `map_stream` fails in this mode.
+    pub fn set_canonical(&mut self, canonical: bool) {
+        self.canonical = canonical;
+    }
+
+    /// Enables or disables writing structs as maps with their field names as keys, whatever the
+    /// `VariantWriter` of this serializer is.
+    pub fn set_struct_map(&mut self, enabled: bool) {
+        self.struct_map = enabled;
+    }
+
+    /// Enables or disables writing structs as maps with the indices of their fields as integer
+    /// keys, whatever the `VariantWriter` of this serializer is.
+    ///
+    /// This is nearly as compact as arrays, while fields can still be skipped, added or dropped
+    /// without shifting the others, as long as the declaration order is kept. Indices count the
+    /// fields serde passes to the serializer, including skipped ones, but not those marked with
+    /// `#[serde(skip)]`, which serde doesn't report. The `Deserializer` reads such maps as they
+    /// are, since serde accepts field indices as keys. This takes precedence over
+    /// `set_struct_map`.
+    pub fn set_struct_index_map(&mut self, enabled: bool) {
+        self.struct_index_map = enabled;
+    }
+
+    /// Enables or disables identifying enum variants by their names instead of their indices,
+    /// like `["Variant", [args...]]`, for peers that don't know the declaration order.
+    ///
+    /// Names take precedence over the discriminants set with `set_discriminants`.
+    pub fn set_string_variants(&mut self, enabled: bool) {
+        self.string_variants = enabled;
+    }
+
+    /// Enables or disables writing enums the way self-describing formats like JSON do: unit
+    /// variants as their name, and other variants as a map with a single entry from their name to
+    /// their value, like `{"Variant": [args...]}`.
+    ///
+    /// This lets enums round-trip with data produced by other languages or converted from other
+    /// serde formats. The `Deserializer` accepts both representations, whatever this option is.
+    /// Variants are always identified by name here, so discriminants don't apply.
+    pub fn set_externally_tagged(&mut self, enabled: bool) {
+        self.externally_tagged = enabled;
+    }
+
+    /// Enables or disables writing non-empty sequences of `u8`, like `Vec<u8>` without
+    /// `serde_bytes`, as binaries instead of arrays of integers.
+    ///
+    /// Elements are checked as they come, so a sequence is written as an array after all if one
+    /// of them is not a `u8`. Deserializing a sequence accepts binaries, so such values read
+    /// back as they were. Tuples and fixed-size arrays are not affected.
+    pub fn set_binary_strings(&mut self, enabled: bool) {
+        self.binary_strings = enabled;
+    }
+
+    /// Applies all options from the given config at once.
+    pub fn set_config(&mut self, config: &SerializerConfig) {
+        self.depth = config.max_depth;
+        self.str_format = config.str_format;
+        self.bytes_format = config.bytes_format;
+        self.deny_ext = config.deny_ext;
+        self.canonical = config.canonical;
+        self.struct_map = config.struct_map;
+        self.struct_index_map = config.struct_index_map;
+        self.string_variants = config.string_variants;
+        self.externally_tagged = config.externally_tagged;
+        self.binary_strings = config.binary_strings;
+        self.bool_as_int = config.bool_as_int;
+        self.lossy = config.lossy;
+        self.discriminants = config.discriminants.clone();
+        self.overrides = config.overrides.clone();
+        self.skipped_as_nil = config.skipped_as_nil;
+    }
+
+    /// Enables or disables writing booleans as the integers `0` and `1`, for peers that have no
+    /// boolean type, like some C and Lua implementations.
+    pub fn set_bool_as_int(&mut self, enabled: bool) {
+        self.bool_as_int = enabled;
+    }
+
+    /// Encodes the variants of the enums listed in the given mapping with their assigned
+    /// discriminants instead of their declaration-order indices.
+    pub fn set_discriminants(&mut self, discriminants: Discriminants) {
+        self.discriminants = discriminants;
+    }
+
+    /// Returns the number the given enum variant is encoded with.
+    fn variant_id(&self, name: &str, idx: u32, variant: &str) -> Result<u32, Error> {
+        if !self.discriminants.contains(name) {
+            return Ok(idx);
+        }
+
+        match self.discriminants.get(name, variant) {
+            Some(id) => Ok(id),
+            None => {
+                Err(Error::Syntax(format!("no discriminant for variant {}::{}", name, variant)))
+            }
+        }
+    }
+
+    /// Installs a predicate, which decides for every struct field whether it is serialized.
+    ///
+    /// This allows to shape the output dynamically, like returning sparse fieldsets requested by
+    /// a client, without defining a separate struct for each of them. The predicate gets the
+    /// names of the struct and of the field, the latter as renamed by serde attributes.
+    ///
+    /// The filter is meant for structs written as maps, for example with `StructMapWriter`. In the
+    /// compact array representation skipping a field shifts the positions of the following ones,
+    /// unless placeholders are enabled with `set_skipped_as_nil`.
+    ///
+    /// The number of fields kept is not known until the struct ends, so while a filter is
+    /// installed the fields of each struct are collected in memory before being written.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate serde;
+    /// extern crate rmp_serde;
+    ///
+    /// use std::collections::BTreeMap;
+    ///
+    /// use rmp_serde::Serializer;
+    /// use rmp_serde::encode::StructMapWriter;
+    /// use serde::ser::{Serialize, SerializeStruct, Serializer as SerdeSerializer};
+    ///
+    /// struct User;
+    ///
+    /// impl Serialize for User {
+    ///     fn serialize<S: SerdeSerializer>(&self, se: S) -> Result<S::Ok, S::Error> {
+    ///         let mut st = se.serialize_struct("User", 2)?;
+    ///         st.serialize_field("name", "John")?;
+    ///         st.serialize_field("email", "john@example.com")?;
+    ///         st.end()
+    ///     }
+    /// }
+    ///
+    /// fn main() {
+    ///     let mut se = Serializer::with(Vec::new(), StructMapWriter);
+    ///     se.set_field_filter(Some(|_, field| field != "email"));
+    ///     User.serialize(&mut se).unwrap();
+    ///
+    ///     let map: BTreeMap<String, String> = rmp_serde::from_slice(&se.into_inner()).unwrap();
+    ///     assert_eq!(1, map.len());
+    ///     assert_eq!("John", map["name"]);
+    /// }
+    /// ```
+    pub fn set_field_filter(&mut self, filter: Option<FieldFilter>) {
+        self.filter = filter;
+    }
+
+    /// Installs a callback invoked before every lossy conversion the other options cause, like
+    /// writing a byte array as a string in the compatibility mode, so pipelines can log silent
+    /// data degradation or reject it by returning `false`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate serde;
+    /// extern crate rmp_serde;
+    ///
+    /// use serde::Serialize;
+    /// use rmp_serde::Serializer;
+    /// use rmp_serde::encode::{Error, Lossy};
+    ///
+    /// fn main() {
+    ///     let mut se = Serializer::new(Vec::new());
+    ///     se.set_bool_as_int(true);
+    ///     se.set_lossy_hook(Some(|_| false));
+    ///
+    ///     match true.serialize(&mut se) {
+    ///         Err(Error::LossyConversion(Lossy::BoolAsInt)) => {}
+    ///         other => panic!("unexpected result: {:?}", other),
+    ///     }
+    /// }
+    /// ```
+    pub fn set_lossy_hook(&mut self, hook: Option<LossyHook>) {
+        self.lossy = hook;
+    }
+
+    /// Installs overrides changing how values of selected types or at selected field paths are
+    /// written, see `Overrides`.
+    pub fn set_overrides(&mut self, overrides: Overrides) {
+        self.overrides = overrides;
+    }
+
+    /// Enables or disables writing nil in place of struct fields that are skipped, either by
+    /// `#[serde(skip_serializing_if = "...")]` or by the field filter, when structs are written as
+    /// arrays.
+    ///
+    /// In the positional representation the decoder tells fields apart by their index only, so
+    /// leaving one out shifts all the following ones. Placeholders keep every field at its
+    /// position, which an `Option` field decodes as `None` on the other side. Structs written as
+    /// maps are not affected.
+    ///
+    /// The number of fields written is not known until the struct ends, so while this is enabled
+    /// the fields of each struct are collected in memory before being written.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// extern crate serde;
+    /// #[macro_use]
+    /// extern crate serde_derive;
+    /// extern crate rmp_serde;
+    ///
+    /// use serde::Serialize;
+    /// use rmp_serde::Serializer;
+    ///
+    /// #[derive(Serialize)]
+    /// struct User {
+    ///     id: u32,
+    ///     #[serde(skip_serializing_if = "Option::is_none")]
+    ///     email: Option<String>,
+    ///     age: u8,
+    /// }
+    ///
+    /// fn main() {
+    ///     let mut se = Serializer::new(Vec::new());
+    ///     se.set_skipped_as_nil(true);
+    ///     User { id: 1, email: None, age: 42 }.serialize(&mut se).unwrap();
+    ///
+    ///     assert_eq!(vec![0x93, 0x01, 0xc0, 0x2a], se.into_inner());
+    /// }
+    /// ```
+    pub fn set_skipped_as_nil(&mut self, enabled: bool) {
+        self.skipped_as_nil = enabled;
+    }
+
+    /// Reports a lossy conversion to the hook, failing if it rejects it.
+    fn lossy(&self, kind: Lossy) -> Result<(), Error> {
+        match self.lossy {
+            Some(hook) if !hook(kind) => Err(Error::LossyConversion(kind)),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The output of a `Serializer`, which is redirected into a buffer while the fields of a filtered
+/// struct are written.
+struct Output<W> {
+    wr: W,
+    bufs: Vec<Vec<u8>>,
+}
+
+#[cfg(feature = "std")]
+impl<W: io::Write> io::Write for Output<W> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        match self.bufs.last_mut() {
+            Some(buf) => {
+                buf.extend_from_slice(data);
+                Ok(data.len())
+            }
+            None => self.wr.write(data),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.wr.flush()
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl<W: Write> RmpWrite for Output<W> {
+    type Error = rmp::encode::Error;
+
+    fn write_bytes(&mut self, data: &[u8]) -> Result<(), Self::Error> {
+        match self.bufs.last_mut() {
+            Some(buf) => {
+                buf.extend_from_slice(data);
+                Ok(())
+            }
+            None => self.wr.write_bytes(data),
+        }
+    }
+
+    fn write_bytes_counted(&mut self, data: &[u8]) -> Result<(), (usize, Self::Error)> {
+        match self.bufs.last_mut() {
+            Some(buf) => {
+                buf.extend_from_slice(data);
+                Ok(())
+            }
+            None => self.wr.write_bytes_counted(data),
+        }
+    }
+}
+
+/// Writes the old-spec raw type header, which never uses the str8 marker.
+fn write_raw_len<W: Write>(wr: &mut W, len: u32) -> Result<(), ValueWriteError> {
+    if len < 32 {
+        rmp::encode::write_str_len(wr, len)?;
+        return Ok(());
+    }
+
+    let mut buf = [0; 5];
+    let buf = if len < 65536 {
+        buf[0] = Marker::Str16.to_u8();
+        buf[1..3].copy_from_slice(&(len as u16).to_be_bytes());
+        &buf[..3]
+    } else {
+        buf[0] = Marker::Str32.to_u8();
+        buf[1..].copy_from_slice(&len.to_be_bytes());
+        &buf[..]
+    };
+
+    // Failing before the marker got out is told apart from failing within the length, like the
+    // writing functions of rmp do.
+    wr.write_bytes_counted(buf).map_err(|(written, err)| {
+        if written == 0 {
+            ValueWriteError::InvalidMarkerWrite(err)
+        } else {
+            ValueWriteError::InvalidDataWrite(err)
+        }
+    })
+}
+
+impl<W: Write> Serializer<W, StructArrayWriter> {
+    /// Constructs a new `MessagePack` serializer whose output will be written to the writer
+    /// specified.
+    ///
+    /// # Note
+    ///
+    /// This is the default constructor, which returns a serializer that will serialize structs
+    /// using compact tuple representation, without field names.
+    pub fn new(wr: W) -> Self {
+        Serializer::with(wr, StructArrayWriter)
+    }
+
+    /// Constructs a serializer with all options of the given config applied.
+    ///
+    /// Unlike `with`, the type of the serializer doesn't depend on how structs are written, see
+    /// `SerializerConfig::with_struct_map`.
+    pub fn with_config(wr: W, config: &SerializerConfig) -> Self {
+        let mut se = Serializer::new(wr);
+        se.set_config(config);
+        se
+    }
+}
+
+impl<W: Write, V> Serializer<W, V> {
+    /// Gets a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.wr.wr
+    }
+
+    /// Gets a mutable reference to the underlying writer.
+    ///
+    /// It is inadvisable to directly write to the underlying writer.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.wr.wr
+    }
+
+    /// Unwraps this `Serializer`, returning the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.wr.wr
+    }
+}
+
+impl<W: Write, V: VariantWriter> Serializer<W, V> {
+    /// Creates a new MessagePack encoder whose output will be written to the writer specified.
+    pub fn with(wr: W, vw: V) -> Self {
+        Serializer {
+            wr: Output {
+                wr: wr,
+                bufs: Vec::new(),
+            },
+            vw: vw,
+            depth: 1024,
+            str_format: RawFormat::Str,
+            bytes_format: RawFormat::Bin,
+            deny_ext: false,
+            canonical: false,
+            struct_map: false,
+            struct_index_map: false,
+            string_variants: false,
+            externally_tagged: false,
+            binary_strings: false,
+            bool_as_int: false,
+            lossy: None,
+            filter: None,
+            discriminants: Discriminants::new(),
+            overrides: Overrides::new(),
+            skipped_as_nil: false,
+            path: Vec::new(),
+            f64_as_f32: false,
+            ext: None,
+            raw: false,
+        }
+    }
+
+    /// Starts applying the given overrides, returning what `leave_scope` needs to restore.
+    fn enter_scope(&mut self, scope: Scope) -> Entered {
+        if scope.ext.is_some() {
+            // Collect the encoding of the value to write it as the extension data afterwards.
+            self.wr.bufs.push(Vec::new());
+        }
+
+        let f64_as_f32 = self.f64_as_f32;
+        self.f64_as_f32 |= scope.f64_as_f32;
+
+        Entered { ext: scope.ext, f64_as_f32 }
+    }
+
+    fn leave_scope(&mut self, entered: Entered) -> Result<(), Error> {
+        self.f64_as_f32 = entered.f64_as_f32;
+
+        if let Some(ty) = entered.ext {
+            let buf = self.wr.bufs.pop().unwrap_or_default();
+            self.write_ext(ty, &buf)?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes an extension, unless they are denied.
+    fn write_ext(&mut self, ty: i8, data: &[u8]) -> Result<(), Error> {
+        if self.deny_ext {
+            return Err(Error::ExtNotAllowed(ty));
+        }
+
+        write_ext_meta(&mut self.wr, data.len() as u32, ty)?;
+        self.wr
+            .write_bytes(data)
+            .map_err(|err| Error::InvalidValueWrite(ValueWriteError::InvalidDataWrite(err)))
+    }
+
+    /// Writes a string or a byte array as the given types.
+    fn write_raw(&mut self, format: RawFormat, data: &[u8]) -> Result<(), Error> {
+        let len = data.len() as u32;
+        match format {
+            RawFormat::Str => {
+                rmp::encode::write_str_len(&mut self.wr, len)?;
+            }
+            RawFormat::Bin => {
+                write_bin_len(&mut self.wr, len)?;
+            }
+            RawFormat::Legacy => write_raw_len(&mut self.wr, len)?,
+        }
+
+        self.wr
+            .write_bytes(data)
+            .map_err(|err| Error::InvalidValueWrite(ValueWriteError::InvalidDataWrite(err)))
+    }
+
+    /// Returns `true` if skipped struct fields are replaced with nil.
+    fn has_placeholders(&self) -> bool {
+        self.skipped_as_nil && !self.struct_map && !self.struct_index_map && self.vw.is_positional()
+    }
+
+    /// Writes the header of a struct, as a map if `set_struct_map` or `set_struct_index_map`
+    /// overrides the variant writer.
+    fn write_struct_len(&mut self, len: u32) -> Result<(), Error> {
+        if self.struct_map || self.struct_index_map {
+            write_map_len(&mut self.wr, len)?;
+        } else {
+            self.vw.write_struct_len(&mut self.wr, len)?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes the key of the struct field with the given name and index, if there is one.
+    fn write_field_name(&mut self, key: &str, idx: u32) -> Result<(), Error> {
+        if self.struct_index_map {
+            write_uint(&mut self.wr, idx as u64)?;
+        } else if self.struct_map {
+            let format = self.str_format;
+            self.write_raw(format, key.as_bytes())?;
+        } else {
+            self.vw.write_field_name(&mut self.wr, key)?;
+        }
+
+        Ok(())
+    }
+
+    fn write_seq_len(&mut self, len: Option<usize>) -> Result<(), Error> {
+        let len = match len {
+            Some(len) => len,
+            None => return Err(Error::UnknownLength),
+        };
+
+        write_array_len(&mut self.wr, len as u32)?;
+        Ok(())
+    }
+
+    fn write_map_len(&mut self, len: Option<usize>) -> Result<(), Error> {
+        let len = match len {
+            Some(len) => len,
+            None => return Err(Error::UnknownLength),
+        };
+
+        write_map_len(&mut self.wr, len as u32)?;
+        Ok(())
+    }
+
+    /// Writes the `[variant, ...]` header of an enum variant, identifying it by name or number,
+    /// or the `{"variant": ...}` one if enums are externally tagged.
+    fn write_variant(&mut self, name: &str, idx: u32, variant: &str) -> Result<(), Error> {
+        if self.externally_tagged {
+            write_map_len(&mut self.wr, 1)?;
+            return serde::Serializer::serialize_str(self, variant);
+        }
+
+        write_array_len(&mut self.wr, 2)?;
+        if self.string_variants {
+            serde::Serializer::serialize_str(self, variant)
+        } else {
+            let id = self.variant_id(name, idx, variant)?;
+            serde::Serializer::serialize_u32(self, id)
         }
     }
 
-    fn cause(&self) -> Option<&error::Error> {
-        match *self {
-            Error::InvalidValueWrite(ref err) => Some(err),
-            Error::UnknownLength => None,
-            Error::DepthLimitExceeded => None,
-            Error::Syntax(..) => None,
+    /// Writes everything of a newtype variant but its value, which externally tagged enums don't
+    /// wrap into an array.
+    fn write_newtype_variant(&mut self, name: &str, idx: u32, variant: &str) -> Result<(), Error> {
+        self.write_variant(name, idx, variant)?;
+        if !self.externally_tagged {
+            write_array_len(&mut self.wr, 1)?;
         }
+
+        Ok(())
     }
-}
 
-impl Display for Error {
-    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-        error::Error::description(self).fmt(f)
+    /// Writes the header of a struct unless its fields are filtered or replaced with
+    /// placeholders, in which case the header is written once the number of fields is known.
+    fn begin_struct(&mut self, name: &'static str, len: usize) ->
+        Result<Compound<'_, W, V>, Error>
+    {
+        if self.filter.is_some() || self.has_placeholders() {
+            self.wr.bufs.push(Vec::new());
+            let mut compound = Compound::new(self);
+            compound.filtered = Some(Filtered { name, len: 0 });
+            return Ok(compound);
+        }
+
+        self.write_struct_len(len as u32)?;
+        Ok(Compound::new(self))
     }
-}
 
-impl From<ValueWriteError> for Error {
-    fn from(err: ValueWriteError) -> Error {
-        Error::InvalidValueWrite(err)
+    /// Serializes a value into a separate buffer instead of the output.
+    fn serialize_to_buf<N, T>(&mut self, value: &T) -> Result<Vec<u8>, Error>
+        where N: Nested<W, V>,
+              T: ?Sized + Serialize
+    {
+        self.wr.bufs.push(Vec::new());
+        let res = N::serialize(self, value);
+        let buf = self.wr.bufs.pop().unwrap_or_default();
+        res.map(|()| buf)
     }
-}
 
-impl serde::ser::Error for Error {
-    /// Raised when there is general error when deserializing a type.
-    fn custom<T: Display>(msg: T) -> Error {
-        Error::Syntax(format!("{}", msg))
+    /// Serializes a value with the given overrides applied, if any.
+    fn serialize_scoped<N, T>(&mut self, scope: Option<Scope>, value: &T) -> Result<(), Error>
+        where N: Nested<W, V>,
+              T: ?Sized + Serialize
+    {
+        match scope {
+            Some(scope) => {
+                let entered = self.enter_scope(scope);
+                N::serialize(self, value)?;
+                self.leave_scope(entered)
+            }
+            None => N::serialize(self, value),
+        }
     }
 }
 
-// TODO: Great idea - drop this trait and make Serializer types configurable.
-pub trait VariantWriter {
-    fn write_struct_len<W>(&self, wr: &mut W, len: u32) -> Result<Marker, ValueWriteError>
-        where W: Write;
-    fn write_field_name<W>(&self, wr: &mut W, key: &str) -> Result<(), ValueWriteError>
-        where W: Write;
+/// Serializes the values nested in a `Compound`, which the `TracingSerializer` does with itself
+/// to record them too.
+#[doc(hidden)]
+pub trait Nested<W, V> {
+    fn serialize<T>(se: &mut Serializer<W, V>, value: &T) -> Result<(), Error>
+        where T: ?Sized + Serialize;
 }
 
-/// Writes struct as MessagePack array with no field names
-pub struct StructArrayWriter;
+/// Serializes nested values with the serializer the compound belongs to.
+#[doc(hidden)]
+pub struct Direct;
 
-impl VariantWriter for StructArrayWriter {
-    fn write_struct_len<W>(&self, wr: &mut W, len: u32) -> Result<Marker, ValueWriteError>
-        where W: Write
+impl<W: Write, V: VariantWriter> Nested<W, V> for Direct {
+    fn serialize<T>(se: &mut Serializer<W, V>, value: &T) -> Result<(), Error>
+        where T: ?Sized + Serialize
     {
-        write_array_len(wr, len)
+        value.serialize(se)
     }
+}
 
-    /// This implementation does not write field names
-    #[allow(unused_variables)]
-    fn write_field_name<W>(&self, wr: &mut W, _key: &str) -> Result<(), ValueWriteError>
-        where W: Write
-    {
-        Ok(())
-    }
+/// The state to restore once the value overrides were applied to is written.
+struct Entered {
+    ext: Option<i8>,
+    f64_as_f32: bool,
 }
 
-/// Represents MessagePack serialization implementation.
-///
-/// # Note
-///
-/// MessagePack has no specification about how to encode enum types. Thus we are free to do
-/// whatever we want, so the given chose may be not ideal for you.
-///
-/// Every Rust enum value can be represented as a tuple of index with a value.
-///
-/// All instances of `ErrorKind::Interrupted` are handled by this function and the underlying
-/// operation is retried.
-// TODO: Docs. Examples.
-pub struct Serializer<W, V> {
-    wr: W,
-    vw: V,
-    depth: usize,
+pub struct Compound<'a, W: 'a, V: 'a, N = Direct> {
+    se: &'a mut Serializer<W, V>,
+    // Set for structs whose fields are counted as they are written.
+    filtered: Option<Filtered>,
+    // Index of the next struct field, counting skipped ones.
+    field: u32,
+    // Set for values of types with overrides.
+    entered: Option<Entered>,
+    // Set for sequences written as binaries for as long as all of their elements are bytes.
+    bytes: Option<PendingBytes>,
+    // Set for maps whose encoded entries are sorted before being written.
+    entries: Option<Vec<Entry>>,
+    nested: PhantomData<N>,
 }
 
-impl<W, V> Serializer<W, V> {
-    /// Changes the maximum nesting depth that is allowed
-    pub fn set_max_depth(&mut self, depth: usize) {
-        self.depth = depth;
-    }
+/// The encoded key and value of a map entry.
+type Entry = (Vec<u8>, Vec<u8>);
+
+/// The elements of a sequence collected so far, along with its length.
+#[derive(Default)]
+struct PendingBytes {
+    len: u32,
+    buf: Vec<u8>,
 }
 
-impl<W: Write> Serializer<W, StructArrayWriter> {
-    /// Constructs a new `MessagePack` serializer whose output will be written to the writer
-    /// specified.
-    ///
-    /// # Note
-    ///
-    /// This is the default constructor, which returns a serializer that will serialize structs
-    /// using compact tuple representation, without field names.
-    pub fn new(wr: W) -> Self {
-        Serializer::with(wr, StructArrayWriter)
+/// The state of a struct whose fields are being filtered or replaced with placeholders.
+struct Filtered {
+    name: &'static str,
+    len: u32,
+}
+
+impl<'a, W: 'a, V: 'a, N> Compound<'a, W, V, N> {
+    fn new(se: &'a mut Serializer<W, V>) -> Self {
+        Compound {
+            se,
+            filtered: None,
+            field: 0,
+            entered: None,
+            bytes: None,
+            entries: None,
+            nested: PhantomData,
+        }
+    }
+
+    /// Switches to serializing the nested values with `M`.
+    fn nest<M>(self) -> Compound<'a, W, V, M> {
+        Compound {
+            se: self.se,
+            filtered: self.filtered,
+            field: self.field,
+            entered: self.entered,
+            bytes: self.bytes,
+            entries: self.entries,
+            nested: PhantomData,
+        }
     }
 }
 
-impl<W: Write, V> Serializer<W, V> {
-    /// Gets a reference to the underlying writer.
-    pub fn get_ref(&self) -> &W {
-        &self.wr
+impl<'a, W: Write + 'a, V: VariantWriter + 'a, N: Nested<W, V>> Compound<'a, W, V, N> {
+    /// Adds an element to a sequence that is being collected as a binary, switching to an
+    /// array once an element is not a byte.
+    fn serialize_byte_element<T>(&mut self, value: &T) -> Result<(), Error>
+        where T: ?Sized + Serialize
+    {
+        let probed = match self.bytes {
+            Some(ref mut pending) => value.serialize(&mut bytes::ByteProbe).map(|byte| {
+                pending.buf.push(byte);
+            }),
+            None => return N::serialize(self.se, value),
+        };
+
+        if probed.is_err() {
+            let pending = self.bytes.take().unwrap_or_default();
+            write_array_len(&mut self.se.wr, pending.len)?;
+            for byte in pending.buf {
+                serde::Serializer::serialize_u8(&mut *self.se, byte)?;
+            }
+            N::serialize(self.se, value)?;
+        }
+
+        Ok(())
     }
 
-    /// Gets a mutable reference to the underlying writer.
-    ///
-    /// It is inadvisable to directly write to the underlying writer.
-    pub fn get_mut(&mut self) -> &mut W {
-        &mut self.wr
+    /// Finishes applying the overrides of the value's type.
+    fn finish(self) -> Result<(), Error> {
+        match self.entered {
+            Some(entered) => self.se.leave_scope(entered),
+            None => Ok(()),
+        }
     }
 
-    /// Unwraps this `Serializer`, returning the underlying writer.
-    pub fn into_inner(self) -> W {
-        self.wr
+    /// Serializes a struct field, tracking its path if overrides are registered for paths.
+    fn serialize_field_value<T>(&mut self, key: &'static str, value: &T) -> Result<(), Error>
+        where T: ?Sized + Serialize
+    {
+        if !self.se.overrides.has_paths() {
+            return N::serialize(self.se, value);
+        }
+
+        self.se.path.push(key);
+        let scope = self.se.overrides.for_field_path(&self.se.path);
+        let res = self.se.serialize_scoped::<N, _>(scope, value);
+        self.se.path.pop();
+        res
     }
-}
 
-impl<W: Write, V: VariantWriter> Serializer<W, V> {
-    /// Creates a new MessagePack encoder whose output will be written to the writer specified.
-    pub fn with(wr: W, vw: V) -> Self {
-        Serializer {
-            wr: wr,
-            vw: vw,
-            depth: 1024,
+    /// Returns the index of the struct field being serialized or skipped, moving to the next one.
+    fn next_field(&mut self) -> u32 {
+        let idx = self.field;
+        self.field += 1;
+        idx
+    }
+
+    /// Returns `true` if the struct field with the given name is to be written.
+    fn keep_field(&mut self, key: &'static str) -> bool {
+        match self.filtered.as_mut() {
+            Some(filtered) => {
+                let keep = match self.se.filter {
+                    Some(filter) => filter(filtered.name, key),
+                    None => true,
+                };
+                if keep {
+                    filtered.len += 1;
+                }
+                keep
+            }
+            None => true,
         }
     }
-}
 
-pub struct Compound<'a, W: 'a, V: 'a> {
-    // Note, that the implementation is stateless.
-    se: &'a mut Serializer<W, V>,
+    /// Writes nil in place of a skipped struct field if placeholders are enabled.
+    fn skip_field_value(&mut self) -> Result<(), Error> {
+        if !self.se.has_placeholders() {
+            return Ok(());
+        }
+
+        if let Some(filtered) = self.filtered.as_mut() {
+            filtered.len += 1;
+        }
+        write_nil(&mut self.se.wr)
+            .map_err(|err| Error::InvalidValueWrite(ValueWriteError::InvalidMarkerWrite(err)))
+    }
+
+    /// Writes the header of a filtered struct followed by its buffered fields.
+    fn end_struct(mut self) -> Result<(), Error> {
+        if let Some(filtered) = self.filtered.take() {
+            let buf = self.se.wr.bufs.pop().unwrap_or_default();
+            self.se.write_struct_len(filtered.len)?;
+            self.se.wr
+                .write_bytes(&buf)
+                .map_err(|err| Error::InvalidValueWrite(ValueWriteError::InvalidDataWrite(err)))?;
+        }
+
+        self.finish()
+    }
 }
 
-impl<'a, W: Write + 'a, V: VariantWriter + 'a> SerializeSeq for Compound<'a, W, V> {
+impl<'a, W, V, N> SerializeSeq for Compound<'a, W, V, N>
+    where W: Write + 'a, V: VariantWriter + 'a, N: Nested<W, V>
+{
     type Ok = ();
     type Error = Error;
 
     fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Self::Error> {
-        value.serialize(&mut *self.se)
+        self.serialize_byte_element(value)
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        Ok(())
+        match self.bytes {
+            Some(pending) => serde::Serializer::serialize_bytes(self.se, &pending.buf),
+            None => Ok(()),
+        }
     }
 }
 
-impl<'a, W: Write + 'a, V: VariantWriter + 'a> SerializeTuple for Compound<'a, W, V> {
+impl<'a, W, V, N> SerializeTuple for Compound<'a, W, V, N>
+    where W: Write + 'a, V: VariantWriter + 'a, N: Nested<W, V>
+{
     type Ok = ();
     type Error = Error;
 
     fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Self::Error> {
-        value.serialize(&mut *self.se)
+        N::serialize(self.se, value)
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
@@ -191,25 +1511,29 @@ impl<'a, W: Write + 'a, V: VariantWriter + 'a> SerializeTuple for Compound<'a, W
     }
 }
 
-impl<'a, W: Write + 'a, V: VariantWriter + 'a> SerializeTupleStruct for Compound<'a, W, V> {
+impl<'a, W, V, N> SerializeTupleStruct for Compound<'a, W, V, N>
+    where W: Write + 'a, V: VariantWriter + 'a, N: Nested<W, V>
+{
     type Ok = ();
     type Error = Error;
 
     fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Self::Error> {
-        value.serialize(&mut *self.se)
+        N::serialize(self.se, value)
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        Ok(())
+        self.finish()
     }
 }
 
-impl<'a, W: Write + 'a, V: VariantWriter + 'a> SerializeTupleVariant for Compound<'a, W, V> {
+impl<'a, W, V, N> SerializeTupleVariant for Compound<'a, W, V, N>
+    where W: Write + 'a, V: VariantWriter + 'a, N: Nested<W, V>
+{
     type Ok = ();
     type Error = Error;
 
     fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Self::Error> {
-        value.serialize(&mut *self.se)
+        N::serialize(self.se, value)
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
@@ -217,51 +1541,111 @@ impl<'a, W: Write + 'a, V: VariantWriter + 'a> SerializeTupleVariant for Compoun
     }
 }
 
-impl<'a, W: Write + 'a, V: VariantWriter + 'a> SerializeMap for Compound<'a, W, V> {
+impl<'a, W, V, N> SerializeMap for Compound<'a, W, V, N>
+    where W: Write + 'a, V: VariantWriter + 'a, N: Nested<W, V>
+{
     type Ok = ();
     type Error = Error;
 
     fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), Self::Error> {
-        key.serialize(&mut *self.se)
+        if self.entries.is_none() {
+            return N::serialize(self.se, key);
+        }
+
+        let key = self.se.serialize_to_buf::<N, _>(key)?;
+        if let Some(ref mut entries) = self.entries {
+            entries.push((key, Vec::new()));
+        }
+        Ok(())
     }
 
     fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Self::Error> {
-        value.serialize(&mut *self.se)
+        if self.entries.is_none() {
+            return N::serialize(self.se, value);
+        }
+
+        let value = self.se.serialize_to_buf::<N, _>(value)?;
+        if let Some(entry) = self.entries.as_mut().and_then(|entries| entries.last_mut()) {
+            entry.1 = value;
+        }
+        Ok(())
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
+        let mut entries = match self.entries {
+            Some(entries) => entries,
+            None => return Ok(()),
+        };
+
+        entries.sort();
+        if let Some(pair) = entries.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+            return Err(Error::DuplicateKey(pair[0].0.clone()));
+        }
+
+        let wr = &mut self.se.wr;
+        write_map_len(wr, entries.len() as u32)?;
+        for (key, value) in entries {
+            wr.write_bytes(&key)
+                .and_then(|()| wr.write_bytes(&value))
+                .map_err(|err| Error::InvalidValueWrite(ValueWriteError::InvalidDataWrite(err)))?;
+        }
         Ok(())
     }
 }
 
-impl<'a, W: Write + 'a, V: VariantWriter + 'a> SerializeStruct for Compound<'a, W, V> {
+impl<'a, W, V, N> SerializeStruct for Compound<'a, W, V, N>
+    where W: Write + 'a, V: VariantWriter + 'a, N: Nested<W, V>
+{
     type Ok = ();
     type Error = Error;
 
     fn serialize_field<T: ?Sized + Serialize>(&mut self, key: &'static str, value: &T) ->
         Result<(), Self::Error>
     {
-        self.se.vw.write_field_name(&mut self.se.wr, key)?;
-        value.serialize(&mut *self.se)
+        let idx = self.next_field();
+        if !self.keep_field(key) {
+            return self.skip_field_value();
+        }
+
+        self.se.write_field_name(key, idx)?;
+        self.serialize_field_value(key, value)
+    }
+
+    fn skip_field(&mut self, _key: &'static str) -> Result<(), Self::Error> {
+        self.next_field();
+        self.skip_field_value()
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        Ok(())
+        self.end_struct()
     }
 }
 
-impl<'a, W: Write + 'a, V: VariantWriter + 'a> SerializeStructVariant for Compound<'a, W, V> {
+impl<'a, W, V, N> SerializeStructVariant for Compound<'a, W, V, N>
+    where W: Write + 'a, V: VariantWriter + 'a, N: Nested<W, V>
+{
     type Ok = ();
     type Error = Error;
 
-    fn serialize_field<T: ?Sized + Serialize>(&mut self, _key: &'static str, value: &T) ->
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, key: &'static str, value: &T) ->
         Result<(), Self::Error>
     {
-        value.serialize(&mut *self.se)
+        let idx = self.next_field();
+        if !self.keep_field(key) {
+            return self.skip_field_value();
+        }
+
+        self.se.write_field_name(key, idx)?;
+        self.serialize_field_value(key, value)
+    }
+
+    fn skip_field(&mut self, _key: &'static str) -> Result<(), Self::Error> {
+        self.next_field();
+        self.skip_field_value()
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        Ok(())
+        self.end_struct()
     }
 }
 
@@ -278,6 +1662,11 @@ impl<'a, W: Write, V: VariantWriter> serde::Serializer for &'a mut Serializer<W,
     type SerializeStructVariant = Compound<'a, W, V>;
 
     fn serialize_bool(self, v: bool) -> Result<Self::Ok, Self::Error> {
+        if self.bool_as_int {
+            self.lossy(Lossy::BoolAsInt)?;
+            return self.serialize_u8(v as u8);
+        }
+
         write_bool(&mut self.wr, v)
             .map_err(|err| Error::InvalidValueWrite(ValueWriteError::InvalidMarkerWrite(err)))
     }
@@ -322,6 +1711,11 @@ impl<'a, W: Write, V: VariantWriter> serde::Serializer for &'a mut Serializer<W,
     }
 
     fn serialize_f64(self, v: f64) -> Result<Self::Ok, Self::Error> {
+        if self.f64_as_f32 {
+            self.lossy(Lossy::F64AsF32)?;
+            return self.serialize_f32(v as f32);
+        }
+
         write_f64(&mut self.wr, v)?;
         Ok(())
     }
@@ -334,15 +1728,31 @@ impl<'a, W: Write, V: VariantWriter> serde::Serializer for &'a mut Serializer<W,
     }
 
     fn serialize_str(self, v: &str) -> Result<Self::Ok, Self::Error> {
-        write_str(&mut self.wr, v)?;
-        Ok(())
+        let format = self.str_format;
+        if format == RawFormat::Bin {
+            self.lossy(Lossy::StringAsBinary)?;
+        }
+
+        self.write_raw(format, v.as_bytes())
     }
 
     fn serialize_bytes(self, value: &[u8]) -> Result<Self::Ok, Self::Error> {
-        write_bin_len(&mut self.wr, value.len() as u32)?;
-        self.wr
-            .write_all(value)
-            .map_err(|err| Error::InvalidValueWrite(ValueWriteError::InvalidDataWrite(err)))
+        if let Some(ty) = self.ext.take() {
+            return self.write_ext(ty, value);
+        }
+        if self.raw {
+            self.raw = false;
+            return self.wr
+                .write_bytes(value)
+                .map_err(|err| Error::InvalidValueWrite(ValueWriteError::InvalidDataWrite(err)));
+        }
+
+        let format = self.bytes_format;
+        if format != RawFormat::Bin {
+            self.lossy(Lossy::BinaryAsString)?;
+        }
+
+        self.write_raw(format, value)
     }
 
     fn serialize_none(self) -> Result<(), Self::Error> {
@@ -358,83 +1768,135 @@ impl<'a, W: Write, V: VariantWriter> serde::Serializer for &'a mut Serializer<W,
             .map_err(|err| Error::InvalidValueWrite(ValueWriteError::InvalidMarkerWrite(err)))
     }
 
-    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok, Self::Error> {
-        self.vw.write_struct_len(&mut self.wr, 0)?;
-        Ok(())
+    fn serialize_unit_struct(self, name: &'static str) -> Result<Self::Ok, Self::Error> {
+        let entered = self.overrides.for_type_name(name).map(|scope| self.enter_scope(scope));
+        self.write_struct_len(0)?;
+
+        match entered {
+            Some(entered) => self.leave_scope(entered),
+            None => Ok(()),
+        }
     }
 
-    fn serialize_unit_variant(self, _name: &str, idx: u32, _variant: &str) ->
+    fn serialize_unit_variant(self, name: &str, idx: u32, variant: &str) ->
         Result<Self::Ok, Self::Error>
     {
-        write_array_len(&mut self.wr, 2)?;
-        self.serialize_u32(idx)?;
+        if self.externally_tagged {
+            return self.serialize_str(variant);
+        }
+
+        self.write_variant(name, idx, variant)?;
         write_array_len(&mut self.wr, 0)?;
         Ok(())
     }
 
     fn serialize_newtype_struct<T: ?Sized + serde::Serialize>(self, name: &'static str, value: &T) -> Result<(), Self::Error> {
-        self.serialize_tuple_struct(name, 1)?;
-        value.serialize(self)
+        if name == TIMESTAMP_NAME {
+            self.ext = Some(TIMESTAMP_TYPE);
+            let res = value.serialize(&mut *self);
+            self.ext = None;
+            return res;
+        }
+        if name == MSGPACK_EXT_STRUCT_NAME {
+            let mut se = ext::ExtSerializer::new(self);
+            value.serialize(&mut se)?;
+            return se.finish();
+        }
+        if name == RAW_VALUE_NAME {
+            self.raw = true;
+            let res = value.serialize(&mut *self);
+            self.raw = false;
+            return res;
+        }
+
+        match self.overrides.for_type_name(name) {
+            Some(scope) => {
+                let entered = self.enter_scope(scope);
+                self.serialize_tuple(1)?;
+                value.serialize(&mut *self)?;
+                self.leave_scope(entered)
+            }
+            None => {
+                self.serialize_tuple(1)?;
+                value.serialize(self)
+            }
+        }
     }
 
     fn serialize_newtype_variant<T: ?Sized + serde::Serialize>(self, name: &'static str, variant_index: u32, variant: &'static str, value: &T) -> Result<Self::Ok, Self::Error> {
-        self.serialize_tuple_variant(name, variant_index, variant, 1)?;
+        self.write_newtype_variant(name, variant_index, variant)?;
         value.serialize(self)
     }
 
     fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, Error> {
-        let len = match len {
-            Some(len) => len,
-            None => return Err(Error::UnknownLength),
-        };
-
-        write_array_len(&mut self.wr, len as u32)?;
-
-        Ok(Compound { se: self })
+        match len {
+            Some(len) if self.binary_strings && len > 0 => {
+                let mut compound = Compound::new(self);
+                compound.bytes = Some(PendingBytes {
+                    len: len as u32,
+                    buf: Vec::with_capacity(len),
+                });
+                Ok(compound)
+            }
+            _ => {
+                self.write_seq_len(len)?;
+                Ok(Compound::new(self))
+            }
+        }
     }
 
     fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Self::Error> {
-        self.serialize_seq(Some(len))
+        self.write_seq_len(Some(len))?;
+        Ok(Compound::new(self))
     }
 
-    fn serialize_tuple_struct(self, _name: &'static str, len: usize) ->
+    fn serialize_tuple_struct(self, name: &'static str, len: usize) ->
         Result<Self::SerializeTupleStruct, Self::Error>
     {
-        self.serialize_tuple(len)
+        let entered = self.overrides.for_type_name(name).map(|scope| self.enter_scope(scope));
+
+        let mut compound = self.serialize_tuple(len)?;
+        compound.entered = entered;
+        Ok(compound)
     }
 
-    fn serialize_tuple_variant(self,  name: &'static str,  idx: u32,  _variant: &'static str,  len: usize) ->
+    fn serialize_tuple_variant(self,  name: &'static str,  idx: u32,  variant: &'static str,  len: usize) ->
         Result<Self::SerializeTupleVariant, Error>
     {
         // We encode variant types as a tuple of id with array of args, like: [id, [args...]].
-        rmp::encode::write_array_len(&mut self.wr, 2)?;
-        self.serialize_u32(idx)?;
-        self.serialize_tuple_struct(name, len)
+        self.write_variant(name, idx, variant)?;
+        self.serialize_tuple(len)
     }
 
     fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, Error> {
         match len {
-            Some(len) => {
-                write_map_len(&mut self.wr, len as u32)?;
-                Ok(Compound { se: self })
+            Some(len) if self.canonical => {
+                let mut compound = Compound::new(self);
+                compound.entries = Some(Vec::with_capacity(len));
+                Ok(compound)
+            }
+            _ => {
+                self.write_map_len(len)?;
+                Ok(Compound::new(self))
             }
-            None => Err(Error::UnknownLength),
         }
     }
 
-    fn serialize_struct(self, _name: &'static str, len: usize) ->
+    fn serialize_struct(self, name: &'static str, len: usize) ->
         Result<Self::SerializeStruct, Self::Error>
     {
-        self.vw.write_struct_len(&mut self.wr, len as u32)?;
-        Ok(Compound { se: self })
+        let entered = self.overrides.for_type_name(name).map(|scope| self.enter_scope(scope));
+
+        let mut compound = self.begin_struct(name, len)?;
+        compound.entered = entered;
+        Ok(compound)
     }
 
-    fn serialize_struct_variant(self, name: &'static str, id: u32, _variant: &'static str, len: usize) ->
+    fn serialize_struct_variant(self, name: &'static str, idx: u32, variant: &'static str, len: usize) ->
         Result<Self::SerializeStructVariant, Error>
     {
-        write_array_len(&mut self.wr, 2)?;
-        self.serialize_u32(id)?;
-        self.serialize_struct(name, len)
+        self.write_variant(name, idx, variant)?;
+        self.begin_struct(name, len)
     }
 }
 
@@ -449,6 +1911,31 @@ pub fn write<W: ?Sized, T: ?Sized>(wr: &mut W, val: &T) -> Result<(), Error>
     val.serialize(&mut Serializer::new(wr))
 }
 
+/// Serialize the given data structure as MessagePack into the I/O stream, writing structs as maps
+/// with their field names as keys.
+///
+/// This is useful when the data is read by other MessagePack implementations, which usually expect
+/// named fields.
+#[inline]
+pub fn write_named<W: ?Sized, T: ?Sized>(wr: &mut W, val: &T) -> Result<(), Error>
+    where W: Write,
+          T: Serialize
+{
+    val.serialize(&mut Serializer::with(wr, StructMapWriter))
+}
+
+/// Serialize the given data structure as MessagePack into the I/O stream, using the given config.
+#[inline]
+pub fn write_with<W: ?Sized, T: ?Sized>(wr: &mut W, val: &T, config: &SerializerConfig) ->
+    Result<(), Error>
+    where W: Write,
+          T: Serialize
+{
+    let mut se = Serializer::new(wr);
+    se.set_config(config);
+    val.serialize(&mut se)
+}
+
 
 /// Serialize the given data structure as a MessagePack byte vector.
 ///
@@ -461,3 +1948,79 @@ pub fn to_vec<T: ?Sized>(val: &T) -> Result<Vec<u8>, Error>
     write(&mut buf, val)?;
     Ok(buf)
 }
+
+/// Serialize the given data structure as a MessagePack byte vector, writing structs as maps with
+/// their field names as keys.
+///
+/// Serialization can fail if `T`'s implementation of `Serialize` decides to fail.
+#[inline]
+pub fn to_vec_named<T: ?Sized>(val: &T) -> Result<Vec<u8>, Error>
+    where T: Serialize
+{
+    let mut buf = Vec::with_capacity(128);
+    write_named(&mut buf, val)?;
+    Ok(buf)
+}
+
+/// Serialize the given data structure as a MessagePack byte vector, using the given config.
+///
+/// Serialization can fail if `T`'s implementation of `Serialize` decides to fail.
+#[inline]
+pub fn to_vec_with<T: ?Sized>(val: &T, config: &SerializerConfig) -> Result<Vec<u8>, Error>
+    where T: Serialize
+{
+    let mut buf = Vec::with_capacity(128);
+    write_with(&mut buf, val, config)?;
+    Ok(buf)
+}
+
+/// Serialize the given data structure as MessagePack into the given buffer, returning the number
+/// of bytes written.
+///
+/// Nothing is allocated for the output, which suits embedded targets and shared memory.
+///
+/// # Errors
+///
+/// Returns `Error::BufferTooSmall` with the length of the encoded value if it doesn't fit, in
+/// which case the buffer holds its beginning. Otherwise fails like `to_vec`.
+pub fn to_slice<T>(buf: &mut [u8], val: &T) -> Result<usize, Error>
+    where T: ?Sized + Serialize
+{
+    to_slice_with(buf, val, &SerializerConfig::default())
+}
+
+/// Serialize the given data structure as MessagePack into the given buffer using the given
+/// config, returning the number of bytes written.
+pub fn to_slice_with<T>(buf: &mut [u8], val: &T, config: &SerializerConfig) ->
+    Result<usize, Error>
+    where T: ?Sized + Serialize
+{
+    let mut wr = Counted::new(buf);
+    write_with(&mut wr, val, config)?;
+    if !wr.fits() {
+        return Err(Error::BufferTooSmall { needed: wr.count() });
+    }
+
+    Ok(wr.count())
+}
+
+/// Returns the number of bytes `to_vec` would output for the given data structure, without
+/// keeping them.
+///
+/// This allows to allocate a buffer of the right size, or to reject a message exceeding a size
+/// limit before encoding it. The value is serialized twice if it is encoded afterwards, so it
+/// must serialize the same way each time.
+pub fn serialized_size<T>(val: &T) -> Result<u64, Error>
+    where T: ?Sized + Serialize
+{
+    serialized_size_with(val, &SerializerConfig::default())
+}
+
+/// Returns the number of bytes `to_vec_with` would output for the given data structure and config.
+pub fn serialized_size_with<T>(val: &T, config: &SerializerConfig) -> Result<u64, Error>
+    where T: ?Sized + Serialize
+{
+    let mut wr = Counted::new(&mut []);
+    write_with(&mut wr, val, config)?;
+    Ok(wr.count() as u64)
+}
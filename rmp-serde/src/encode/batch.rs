@@ -0,0 +1,111 @@
+//! Serialization of many messages into a single buffer.
+
+use alloc::vec::Vec;
+
+use serde::Serialize;
+
+use super::{write_with, Error, SerializerConfig};
+
+/// A contiguous buffer of serialized messages together with the position of each of them.
+///
+/// Batch writers can hand the whole buffer to a single write or file append, keep the index
+/// aside and later address individual records by their offset and length.
+///
+/// # Examples
+///
+/// ```
+/// use rmp_serde::encode::Batch;
+///
+/// let mut batch = Batch::new();
+/// batch.push(&"first").unwrap();
+/// batch.push(&(42, "second")).unwrap();
+///
+/// assert_eq!(&[(0, 6), (6, 9)], batch.index());
+/// assert_eq!(15, batch.as_bytes().len());
+///
+/// let second: (u8, String) = rmp_serde::from_slice(batch.get(1).unwrap()).unwrap();
+/// assert_eq!((42, "second".to_owned()), second);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Batch {
+    buf: Vec<u8>,
+    index: Vec<(usize, usize)>,
+    config: SerializerConfig,
+}
+
+impl Batch {
+    /// Constructs a new empty batch, which serializes messages with default options.
+    pub fn new() -> Self {
+        Batch::default()
+    }
+
+    /// Constructs a new empty batch with the buffer preallocated for the given number of bytes.
+    pub fn with_capacity(cap: usize) -> Self {
+        Batch {
+            buf: Vec::with_capacity(cap),
+            index: Vec::new(),
+            config: SerializerConfig::default(),
+        }
+    }
+
+    /// Changes the options messages pushed from now on are serialized with.
+    pub fn set_config(&mut self, config: &SerializerConfig) {
+        self.config = config.clone();
+    }
+
+    /// Serializes the given message at the end of the buffer, returning its number in the index.
+    ///
+    /// # Errors
+    ///
+    /// Fails if `T`'s implementation of `Serialize` decides to fail. The partially written message
+    /// is discarded then, leaving the batch as it was.
+    pub fn push<T>(&mut self, val: &T) -> Result<usize, Error>
+        where T: ?Sized + Serialize
+    {
+        let offset = self.buf.len();
+        if let Err(err) = write_with(&mut self.buf, val, &self.config) {
+            self.buf.truncate(offset);
+            return Err(err);
+        }
+
+        self.index.push((offset, self.buf.len() - offset));
+        Ok(self.index.len() - 1)
+    }
+
+    /// Returns the number of messages in the batch.
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    /// Returns `true` if no message was pushed yet.
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    /// Returns the serialized messages, one after another.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf
+    }
+
+    /// Returns the offset and the length of each message in the buffer, in the order they were
+    /// pushed.
+    pub fn index(&self) -> &[(usize, usize)] {
+        &self.index
+    }
+
+    /// Returns the encoded message with the given number, if there is one.
+    pub fn get(&self, idx: usize) -> Option<&[u8]> {
+        self.index.get(idx).map(|&(offset, len)| &self.buf[offset..offset + len])
+    }
+
+    /// Removes all messages, keeping the allocated memory and the options.
+    pub fn clear(&mut self) {
+        self.buf.clear();
+        self.index.clear();
+    }
+
+    /// Unwraps this `Batch`, returning the buffer and the index.
+    pub fn into_parts(self) -> (Vec<u8>, Vec<(usize, usize)>) {
+        (self.buf, self.index)
+    }
+}
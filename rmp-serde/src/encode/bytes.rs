@@ -0,0 +1,156 @@
+//! Detection of sequence elements that are bytes, for `Serializer::set_binary_strings`.
+
+use serde;
+use serde::Serialize;
+use serde::ser::Impossible;
+
+use super::Error;
+
+/// Accepts a single `u8`, returning it instead of writing anything, and fails for any other
+/// value.
+pub struct ByteProbe;
+
+fn not_a_byte() -> Error {
+    Error::Syntax("expected a byte".into())
+}
+
+impl serde::Serializer for &mut ByteProbe {
+    type Ok = u8;
+    type Error = Error;
+
+    type SerializeSeq = Impossible<u8, Error>;
+    type SerializeTuple = Impossible<u8, Error>;
+    type SerializeTupleStruct = Impossible<u8, Error>;
+    type SerializeTupleVariant = Impossible<u8, Error>;
+    type SerializeMap = Impossible<u8, Error>;
+    type SerializeStruct = Impossible<u8, Error>;
+    type SerializeStructVariant = Impossible<u8, Error>;
+
+    fn serialize_u8(self, v: u8) -> Result<u8, Error> {
+        Ok(v)
+    }
+
+    fn serialize_bool(self, _v: bool) -> Result<u8, Error> {
+        Err(not_a_byte())
+    }
+
+    fn serialize_i8(self, _v: i8) -> Result<u8, Error> {
+        Err(not_a_byte())
+    }
+
+    fn serialize_i16(self, _v: i16) -> Result<u8, Error> {
+        Err(not_a_byte())
+    }
+
+    fn serialize_i32(self, _v: i32) -> Result<u8, Error> {
+        Err(not_a_byte())
+    }
+
+    fn serialize_i64(self, _v: i64) -> Result<u8, Error> {
+        Err(not_a_byte())
+    }
+
+    fn serialize_u16(self, _v: u16) -> Result<u8, Error> {
+        Err(not_a_byte())
+    }
+
+    fn serialize_u32(self, _v: u32) -> Result<u8, Error> {
+        Err(not_a_byte())
+    }
+
+    fn serialize_u64(self, _v: u64) -> Result<u8, Error> {
+        Err(not_a_byte())
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<u8, Error> {
+        Err(not_a_byte())
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<u8, Error> {
+        Err(not_a_byte())
+    }
+
+    fn serialize_char(self, _v: char) -> Result<u8, Error> {
+        Err(not_a_byte())
+    }
+
+    fn serialize_str(self, _v: &str) -> Result<u8, Error> {
+        Err(not_a_byte())
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<u8, Error> {
+        Err(not_a_byte())
+    }
+
+    fn serialize_none(self) -> Result<u8, Error> {
+        Err(not_a_byte())
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, _value: &T) -> Result<u8, Error> {
+        Err(not_a_byte())
+    }
+
+    fn serialize_unit(self) -> Result<u8, Error> {
+        Err(not_a_byte())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<u8, Error> {
+        Err(not_a_byte())
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, _idx: u32, _variant: &'static str) ->
+        Result<u8, Error>
+    {
+        Err(not_a_byte())
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(self, _name: &'static str, _value: &T) ->
+        Result<u8, Error>
+    {
+        // Newtypes may be written differently because of overrides or extensions.
+        Err(not_a_byte())
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(self, _name: &'static str, _idx: u32,
+                                                        _variant: &'static str, _value: &T) ->
+        Result<u8, Error>
+    {
+        Err(not_a_byte())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Error> {
+        Err(not_a_byte())
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Error> {
+        Err(not_a_byte())
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) ->
+        Result<Self::SerializeTupleStruct, Error>
+    {
+        Err(not_a_byte())
+    }
+
+    fn serialize_tuple_variant(self, _name: &'static str, _idx: u32, _variant: &'static str,
+                               _len: usize) -> Result<Self::SerializeTupleVariant, Error>
+    {
+        Err(not_a_byte())
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Error> {
+        Err(not_a_byte())
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) ->
+        Result<Self::SerializeStruct, Error>
+    {
+        Err(not_a_byte())
+    }
+
+    fn serialize_struct_variant(self, _name: &'static str, _idx: u32, _variant: &'static str,
+                                _len: usize) -> Result<Self::SerializeStructVariant, Error>
+    {
+        Err(not_a_byte())
+    }
+}
@@ -0,0 +1,212 @@
+//! Serialization of values that ask to be written as MessagePack extensions.
+
+use serde;
+use serde::Serialize;
+use serde::ser::{Impossible, SerializeTuple};
+
+use super::{Error, Serializer, VariantWriter, Write};
+
+/// Receives the `(i8, bytes)` tuple wrapped into a newtype struct named `MSGPACK_EXT_STRUCT_NAME`
+/// and writes it as an extension of that type and data.
+pub struct ExtSerializer<'a, W: 'a, V: 'a> {
+    se: &'a mut Serializer<W, V>,
+    ty: Option<i8>,
+    written: bool,
+}
+
+impl<'a, W: Write + 'a, V: VariantWriter + 'a> ExtSerializer<'a, W, V> {
+    pub fn new(se: &'a mut Serializer<W, V>) -> Self {
+        ExtSerializer {
+            se,
+            ty: None,
+            written: false,
+        }
+    }
+
+    /// Checks that the extension was written, failing if the value serialized nothing or only
+    /// the type.
+    pub fn finish(self) -> Result<(), Error> {
+        if self.written {
+            Ok(())
+        } else {
+            Err(unexpected())
+        }
+    }
+}
+
+fn unexpected() -> Error {
+    Error::Syntax("expected a tuple of an extension type and bytes".into())
+}
+
+impl<'a, W: Write + 'a, V: VariantWriter + 'a> serde::Serializer
+    for &mut ExtSerializer<'a, W, V>
+{
+    type Ok = ();
+    type Error = Error;
+
+    type SerializeSeq = Impossible<(), Error>;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Impossible<(), Error>;
+    type SerializeTupleVariant = Impossible<(), Error>;
+    type SerializeMap = Impossible<(), Error>;
+    type SerializeStruct = Impossible<(), Error>;
+    type SerializeStructVariant = Impossible<(), Error>;
+
+    fn serialize_i8(self, v: i8) -> Result<(), Error> {
+        if self.ty.is_some() {
+            return Err(unexpected());
+        }
+
+        self.ty = Some(v);
+        Ok(())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), Error> {
+        let ty = match (self.ty, self.written) {
+            (Some(ty), false) => ty,
+            _ => return Err(unexpected()),
+        };
+
+        self.se.write_ext(ty, v)?;
+        self.written = true;
+        Ok(())
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self, Error> {
+        match len {
+            2 => Ok(self),
+            _ => Err(unexpected()),
+        }
+    }
+
+    fn serialize_bool(self, _v: bool) -> Result<(), Error> {
+        Err(unexpected())
+    }
+
+    fn serialize_i16(self, _v: i16) -> Result<(), Error> {
+        Err(unexpected())
+    }
+
+    fn serialize_i32(self, _v: i32) -> Result<(), Error> {
+        Err(unexpected())
+    }
+
+    fn serialize_i64(self, _v: i64) -> Result<(), Error> {
+        Err(unexpected())
+    }
+
+    fn serialize_u8(self, _v: u8) -> Result<(), Error> {
+        Err(unexpected())
+    }
+
+    fn serialize_u16(self, _v: u16) -> Result<(), Error> {
+        Err(unexpected())
+    }
+
+    fn serialize_u32(self, _v: u32) -> Result<(), Error> {
+        Err(unexpected())
+    }
+
+    fn serialize_u64(self, _v: u64) -> Result<(), Error> {
+        Err(unexpected())
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<(), Error> {
+        Err(unexpected())
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<(), Error> {
+        Err(unexpected())
+    }
+
+    fn serialize_char(self, _v: char) -> Result<(), Error> {
+        Err(unexpected())
+    }
+
+    fn serialize_str(self, _v: &str) -> Result<(), Error> {
+        Err(unexpected())
+    }
+
+    fn serialize_none(self) -> Result<(), Error> {
+        Err(unexpected())
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, _value: &T) -> Result<(), Error> {
+        Err(unexpected())
+    }
+
+    fn serialize_unit(self) -> Result<(), Error> {
+        Err(unexpected())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), Error> {
+        Err(unexpected())
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, _idx: u32, _variant: &'static str) ->
+        Result<(), Error>
+    {
+        Err(unexpected())
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(self, _name: &'static str, value: &T) ->
+        Result<(), Error>
+    {
+        // Wrappers like `serde_bytes::ByteBuf` are transparent.
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(self, _name: &'static str, _idx: u32,
+                                                        _variant: &'static str, _value: &T) ->
+        Result<(), Error>
+    {
+        Err(unexpected())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Error> {
+        Err(unexpected())
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) ->
+        Result<Self::SerializeTupleStruct, Error>
+    {
+        Err(unexpected())
+    }
+
+    fn serialize_tuple_variant(self, _name: &'static str, _idx: u32, _variant: &'static str,
+                               _len: usize) -> Result<Self::SerializeTupleVariant, Error>
+    {
+        Err(unexpected())
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Error> {
+        Err(unexpected())
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) ->
+        Result<Self::SerializeStruct, Error>
+    {
+        Err(unexpected())
+    }
+
+    fn serialize_struct_variant(self, _name: &'static str, _idx: u32, _variant: &'static str,
+                                _len: usize) -> Result<Self::SerializeStructVariant, Error>
+    {
+        Err(unexpected())
+    }
+}
+
+impl<'a, W: Write + 'a, V: VariantWriter + 'a> SerializeTuple
+    for &mut ExtSerializer<'a, W, V>
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
@@ -0,0 +1,204 @@
+//! Hashing of the encoded form of values.
+
+#[cfg(feature = "std")]
+use std::io;
+
+use serde::Serialize;
+
+#[cfg(not(feature = "std"))]
+use rmp;
+#[cfg(not(feature = "std"))]
+use rmp::encode::RmpWrite;
+
+use super::{Error, Serializer};
+
+/// A hash function `content_hash_with` feeds the encoded bytes to.
+///
+/// Implement it for a wrapper around the hashing crate of your choice to get digests other than
+/// SHA-256. Digests shorter than 32 bytes should be zero-padded.
+pub trait ContentHasher {
+    /// Appends the given bytes to the hashed data.
+    fn update(&mut self, data: &[u8]);
+
+    /// Completes the hash, returning the digest.
+    fn finish(self) -> [u8; 32];
+}
+
+const K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+const INIT: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+/// The SHA-256 hash function, used by `content_hash`.
+#[derive(Clone)]
+pub struct Sha256 {
+    state: [u32; 8],
+    block: [u8; 64],
+    block_len: usize,
+    len: u64,
+}
+
+impl Sha256 {
+    /// Constructs a new hasher with no data fed.
+    pub fn new() -> Self {
+        Sha256 {
+            state: INIT,
+            block: [0; 64],
+            block_len: 0,
+            len: 0,
+        }
+    }
+
+    fn compress(&mut self) {
+        let mut w = [0u32; 64];
+        for (idx, chunk) in self.block.chunks(4).enumerate() {
+            w[idx] = (chunk[0] as u32) << 24 | (chunk[1] as u32) << 16 | (chunk[2] as u32) << 8 |
+                     chunk[3] as u32;
+        }
+        for idx in 16..64 {
+            let (w15, w2) = (w[idx - 15], w[idx - 2]);
+            let s0 = w15.rotate_right(7) ^ w15.rotate_right(18) ^ (w15 >> 3);
+            let s1 = w2.rotate_right(17) ^ w2.rotate_right(19) ^ (w2 >> 10);
+            w[idx] = w[idx - 16].wrapping_add(s0).wrapping_add(w[idx - 7]).wrapping_add(s1);
+        }
+
+        let mut s = self.state;
+        for idx in 0..64 {
+            let s1 = s[4].rotate_right(6) ^ s[4].rotate_right(11) ^ s[4].rotate_right(25);
+            let ch = (s[4] & s[5]) ^ (!s[4] & s[6]);
+            let t1 = s[7].wrapping_add(s1).wrapping_add(ch).wrapping_add(K[idx])
+                .wrapping_add(w[idx]);
+            let s0 = s[0].rotate_right(2) ^ s[0].rotate_right(13) ^ s[0].rotate_right(22);
+            let maj = (s[0] & s[1]) ^ (s[0] & s[2]) ^ (s[1] & s[2]);
+            let t2 = s0.wrapping_add(maj);
+
+            s = [t1.wrapping_add(t2), s[0], s[1], s[2], s[3].wrapping_add(t1), s[4], s[5], s[6]];
+        }
+
+        for (state, s) in self.state.iter_mut().zip(s.iter()) {
+            *state = state.wrapping_add(*s);
+        }
+    }
+}
+
+impl Default for Sha256 {
+    fn default() -> Self {
+        Sha256::new()
+    }
+}
+
+impl ContentHasher for Sha256 {
+    fn update(&mut self, mut data: &[u8]) {
+        self.len += data.len() as u64;
+
+        while !data.is_empty() {
+            let len = ::core::cmp::min(64 - self.block_len, data.len());
+            self.block[self.block_len..self.block_len + len].copy_from_slice(&data[..len]);
+            self.block_len += len;
+            data = &data[len..];
+
+            if self.block_len == 64 {
+                self.compress();
+                self.block_len = 0;
+            }
+        }
+    }
+
+    fn finish(mut self) -> [u8; 32] {
+        let bits = self.len.wrapping_mul(8);
+
+        self.update(&[0x80]);
+        while self.block_len != 56 {
+            self.update(&[0]);
+        }
+        let mut tail = [0; 8];
+        for (idx, byte) in tail.iter_mut().enumerate() {
+            *byte = (bits >> (56 - 8 * idx)) as u8;
+        }
+        self.update(&tail);
+
+        let mut digest = [0; 32];
+        for (chunk, word) in digest.chunks_mut(4).zip(self.state.iter()) {
+            chunk[0] = (word >> 24) as u8;
+            chunk[1] = (word >> 16) as u8;
+            chunk[2] = (word >> 8) as u8;
+            chunk[3] = *word as u8;
+        }
+        digest
+    }
+}
+
+/// Feeds everything written to the hasher.
+struct HashWriter<'a, H: 'a>(&'a mut H);
+
+#[cfg(feature = "std")]
+impl<'a, H: ContentHasher> io::Write for HashWriter<'a, H> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl<'a, H: ContentHasher> RmpWrite for HashWriter<'a, H> {
+    type Error = rmp::encode::Error;
+
+    fn write_bytes(&mut self, buf: &[u8]) -> Result<(), Self::Error> {
+        self.0.update(buf);
+        Ok(())
+    }
+}
+
+/// Computes the SHA-256 digest of the encoding of the given value, without collecting the encoded
+/// bytes in memory.
+///
+/// The value is encoded the way `to_vec` does in the canonical mode, see
+/// `Serializer::set_canonical`, so the digest equals the SHA-256 of the output of `to_vec_with`
+/// given `SerializerConfig::new().canonical(true)`. Map entries are sorted by key, so equal
+/// `HashMap`s have the same digest whatever their iteration order is, which makes it suitable as
+/// a cache key or for detecting duplicate messages.
+///
+/// # Errors
+///
+/// Fails with `Error::DuplicateKey` if a map holds the same key twice, or if `T`'s implementation
+/// of `Serialize` decides to fail.
+///
+/// # Examples
+///
+/// ```
+/// let digest = rmp_serde::content_hash(&(42, "the Answer")).unwrap();
+///
+/// assert_eq!(digest, rmp_serde::content_hash(&(42, "the Answer")).unwrap());
+/// assert_ne!(digest, rmp_serde::content_hash(&(42, "the Question")).unwrap());
+/// ```
+pub fn content_hash<T>(val: &T) -> Result<[u8; 32], Error>
+    where T: ?Sized + Serialize
+{
+    content_hash_with(val, Sha256::new())
+}
+
+/// Computes the digest of the encoding of the given value like `content_hash` does, using the
+/// given hasher.
+pub fn content_hash_with<T, H>(val: &T, mut hasher: H) -> Result<[u8; 32], Error>
+    where T: ?Sized + Serialize,
+          H: ContentHasher
+{
+    let mut se = Serializer::new(HashWriter(&mut hasher));
+    se.set_canonical(true);
+    val.serialize(&mut se)?;
+    Ok(hasher.finish())
+}
@@ -0,0 +1,117 @@
+//! Entry-by-entry serialization of maps.
+
+use serde::Serialize;
+
+use rmp::encode::write_map_len;
+
+use super::{Error, Serializer, VariantWriter, Write};
+
+/// A map being serialized one entry at a time, returned by `Serializer::map_stream`.
+///
+/// The number of entries is written up front, so the map doesn't need to be held in memory:
+/// entries can be fed from an iterator, a database cursor or an asynchronous source as they
+/// become available.
+pub struct MapStream<'a, W: 'a, V: 'a> {
+    se: &'a mut Serializer<W, V>,
+    len: u32,
+    left: u32,
+}
+
+impl<W: Write, V: VariantWriter> Serializer<W, V> {
+    /// Writes the header of a map with the given number of entries, returning a `MapStream` to
+    /// write the entries with.
+    ///
+    /// # Errors
+    ///
+    /// Fails in the canonical mode, see `set_canonical`, because entries written as they come
+    /// can't be sorted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    ///
+    /// use rmp_serde::Serializer;
+    ///
+    /// let mut se = Serializer::new(Vec::new());
+    /// {
+    ///     let mut map = se.map_stream(1000).unwrap();
+    ///     map.entries((0..1000u32).map(|n| (n, n * n))).unwrap();
+    ///     map.end().unwrap();
+    /// }
+    ///
+    /// let map: BTreeMap<u32, u32> = rmp_serde::from_slice(&se.into_inner()).unwrap();
+    /// assert_eq!(1000, map.len());
+    /// assert_eq!(998001, map[&999]);
+    /// ```
+    pub fn map_stream(&mut self, len: u32) -> Result<MapStream<'_, W, V>, Error> {
+        if self.canonical {
+            return Err(Error::Syntax("map streamed in the canonical mode".into()));
+        }
+
+        write_map_len(&mut self.wr, len)?;
+
+        Ok(MapStream {
+            se: self,
+            len,
+            left: len,
+        })
+    }
+}
+
+impl<'a, W: Write + 'a, V: VariantWriter + 'a> MapStream<'a, W, V> {
+    /// Serializes a single entry.
+    ///
+    /// # Errors
+    ///
+    /// Fails if all the entries announced were already written, or if the key or the value fail
+    /// to serialize.
+    pub fn entry<K, T>(&mut self, key: &K, val: &T) -> Result<(), Error>
+        where K: ?Sized + Serialize,
+              T: ?Sized + Serialize
+    {
+        if self.left == 0 {
+            return Err(Error::Syntax(format!("map of {} entries got more of them", self.len)));
+        }
+
+        key.serialize(&mut *self.se)?;
+        val.serialize(&mut *self.se)?;
+        self.left -= 1;
+
+        Ok(())
+    }
+
+    /// Serializes all entries from the given iterator, stopping at the first error.
+    pub fn entries<I, K, T>(&mut self, iter: I) -> Result<(), Error>
+        where I: IntoIterator<Item = (K, T)>,
+              K: Serialize,
+              T: Serialize
+    {
+        for (key, val) in iter {
+            self.entry(&key, &val)?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the number of entries still to be written.
+    pub fn remaining(&self) -> u32 {
+        self.left
+    }
+
+    /// Finishes the map.
+    ///
+    /// # Errors
+    ///
+    /// Fails if fewer entries were written than announced, because the output is not a valid
+    /// MessagePack map then.
+    pub fn end(self) -> Result<(), Error> {
+        match self.left {
+            0 => Ok(()),
+            left => {
+                let msg = format!("map of {} entries ended {} entries early", self.len, left);
+                Err(Error::Syntax(msg))
+            }
+        }
+    }
+}
@@ -0,0 +1,793 @@
+//! Serialization that records what was emitted and why.
+
+use alloc::vec::Vec;
+use core::fmt::{self, Display, Formatter};
+#[cfg(feature = "std")]
+use std::io;
+
+use serde;
+use serde::Serialize;
+use serde::ser::{SerializeMap, SerializeSeq, SerializeStruct, SerializeStructVariant,
+                 SerializeTuple, SerializeTupleStruct, SerializeTupleVariant};
+
+use rmp::Marker;
+#[cfg(not(feature = "std"))]
+use rmp;
+#[cfg(not(feature = "std"))]
+use rmp::encode::RmpWrite;
+
+use {MSGPACK_EXT_STRUCT_NAME, RAW_VALUE_NAME, TIMESTAMP_NAME};
+use super::{Compound, Error, Nested, Serializer, SerializerConfig, StructArrayWriter, VariantWriter,
+            Write};
+
+/// A single MessagePack marker written by a `TracingSerializer`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TraceEntry {
+    /// Position of the marker byte in the output.
+    pub offset: u64,
+    /// The marker written.
+    pub marker: Marker,
+    /// Length carried by the marker or its header: the number of bytes for strings, binaries and
+    /// extensions, and the number of elements or entries for arrays and maps. `None` for scalars.
+    pub len: Option<u32>,
+    /// Name of the `serde::Serializer` method which caused the marker to be written, like
+    /// `"serialize_struct"`, or `"serialize_field"` for field names of structs written as maps.
+    pub call: &'static str,
+}
+
+impl Display for TraceEntry {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        write!(f, "{:#06x}: {:?}", self.offset, self.marker)?;
+        if let Some(len) = self.len {
+            write!(f, " len={}", len)?;
+        }
+        write!(f, " <- {}", self.call)
+    }
+}
+
+/// Header bytes still expected after the marker.
+#[derive(Clone, Copy, Debug)]
+struct Header {
+    offset: u64,
+    marker: Marker,
+    /// Number of big-endian length bytes left to read.
+    left: usize,
+    len: u32,
+    /// Bytes following the length before the payload, that is the extension type.
+    extra: u64,
+    /// Whether the length counts payload bytes rather than nested values.
+    payload: bool,
+}
+
+/// Wraps a writer, splitting the bytes written through it into markers.
+pub struct TraceWriter<W> {
+    wr: W,
+    pos: u64,
+    call: &'static str,
+    header: Option<Header>,
+    skip: u64,
+    entries: Vec<TraceEntry>,
+}
+
+impl<W> TraceWriter<W> {
+    fn new(wr: W) -> Self {
+        TraceWriter {
+            wr,
+            pos: 0,
+            call: "",
+            header: None,
+            skip: 0,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Gets a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.wr
+    }
+
+    /// Returns the entries recorded so far.
+    pub fn entries(&self) -> &[TraceEntry] {
+        &self.entries
+    }
+
+    fn feed(&mut self, buf: &[u8]) {
+        for &byte in buf {
+            self.feed_u8(byte);
+            self.pos += 1;
+        }
+    }
+
+    fn feed_u8(&mut self, byte: u8) {
+        if self.skip > 0 {
+            self.skip -= 1;
+            return;
+        }
+
+        if let Some(mut header) = self.header.take() {
+            header.len = (header.len << 8) | byte as u32;
+            header.left -= 1;
+            if header.left > 0 {
+                self.header = Some(header);
+            } else {
+                self.push(header.offset, header.marker, Some(header.len));
+                self.skip = header.extra + if header.payload { header.len as u64 } else { 0 };
+            }
+            return;
+        }
+
+        let marker = Marker::from_u8(byte);
+        let (size, extra, payload) = match marker {
+            Marker::FixPos(..) | Marker::FixNeg(..) | Marker::Null | Marker::True |
+            Marker::False | Marker::Reserved => return self.push(self.pos, marker, None),
+            Marker::U8 | Marker::I8 => return self.scalar(marker, 1),
+            Marker::U16 | Marker::I16 => return self.scalar(marker, 2),
+            Marker::U32 | Marker::I32 | Marker::F32 => return self.scalar(marker, 4),
+            Marker::U64 | Marker::I64 | Marker::F64 => return self.scalar(marker, 8),
+            Marker::FixStr(len) => {
+                self.skip = len as u64;
+                return self.push(self.pos, marker, Some(len as u32));
+            }
+            Marker::FixArray(len) | Marker::FixMap(len) => {
+                return self.push(self.pos, marker, Some(len as u32));
+            }
+            Marker::FixExt1 => return self.fixext(marker, 1),
+            Marker::FixExt2 => return self.fixext(marker, 2),
+            Marker::FixExt4 => return self.fixext(marker, 4),
+            Marker::FixExt8 => return self.fixext(marker, 8),
+            Marker::FixExt16 => return self.fixext(marker, 16),
+            Marker::Str8 | Marker::Bin8 => (1, 0, true),
+            Marker::Str16 | Marker::Bin16 => (2, 0, true),
+            Marker::Str32 | Marker::Bin32 => (4, 0, true),
+            Marker::Array16 | Marker::Map16 => (2, 0, false),
+            Marker::Array32 | Marker::Map32 => (4, 0, false),
+            Marker::Ext8 => (1, 1, true),
+            Marker::Ext16 => (2, 1, true),
+            Marker::Ext32 => (4, 1, true),
+        };
+
+        self.header = Some(Header {
+            offset: self.pos,
+            marker,
+            left: size,
+            len: 0,
+            extra,
+            payload,
+        });
+    }
+
+    fn scalar(&mut self, marker: Marker, size: u64) {
+        self.skip = size;
+        let offset = self.pos;
+        self.push(offset, marker, None);
+    }
+
+    fn fixext(&mut self, marker: Marker, len: u32) {
+        // The extension type precedes the data.
+        self.skip = 1 + len as u64;
+        let offset = self.pos;
+        self.push(offset, marker, Some(len));
+    }
+
+    fn push(&mut self, offset: u64, marker: Marker, len: Option<u32>) {
+        self.entries.push(TraceEntry {
+            offset,
+            marker,
+            len,
+            call: self.call,
+        });
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: io::Write> io::Write for TraceWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = self.wr.write(buf)?;
+        self.feed(&buf[..len]);
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.wr.flush()
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl<W: Write> RmpWrite for TraceWriter<W> {
+    type Error = rmp::encode::Error;
+
+    fn write_bytes(&mut self, buf: &[u8]) -> Result<(), Self::Error> {
+        self.wr.write_bytes(buf)?;
+        self.feed(buf);
+        Ok(())
+    }
+}
+
+/// A `Serializer` wrapper, which records every marker written together with its length and the
+/// serde call that caused it.
+///
+/// Tracing makes it practical to find out why the other side rejects a message: the trace shows,
+/// for example, that a struct went out as an array where a map was expected, or that a string was
+/// written with a str8 marker an old decoder doesn't know.
+///
+/// The output is byte for byte the same as the wrapped `Serializer` would produce, so tracing can
+/// be switched on for a single message without affecting the peer.
+///
+/// Some values are held back until the compound value containing them ends, like the fields of
+/// structs with placeholders for skipped fields, and values written as extensions by overrides,
+/// whose data is not traced. Their markers are recorded as they reach the output, with the call
+/// which began the compound value.
+///
+/// # Examples
+///
+/// ```
+/// extern crate serde;
+/// extern crate rmp;
+/// extern crate rmp_serde;
+///
+/// use rmp::Marker;
+/// use rmp_serde::encode::{StructMapWriter, TracingSerializer};
+/// use serde::Serialize;
+///
+/// fn main() {
+///     let mut se = TracingSerializer::with(Vec::new(), StructMapWriter);
+///     (42, "le message").serialize(&mut se).unwrap();
+///
+///     let trace = se.trace();
+///     assert_eq!(3, trace.len());
+///     assert_eq!((Marker::FixArray(2), Some(2), "serialize_tuple"),
+///                (trace[0].marker, trace[0].len, trace[0].call));
+///     assert_eq!((1, Marker::FixPos(42), "serialize_i32"),
+///                (trace[1].offset, trace[1].marker, trace[1].call));
+///     assert_eq!("0x0002: FixStr(10) len=10 <- serialize_str", trace[2].to_string());
+/// }
+/// ```
+pub struct TracingSerializer<W, V = StructArrayWriter> {
+    se: Serializer<TraceWriter<W>, V>,
+}
+
+impl<W: Write> TracingSerializer<W, StructArrayWriter> {
+    /// Constructs a new tracing serializer, which writes structs as arrays like
+    /// `Serializer::new`.
+    pub fn new(wr: W) -> Self {
+        TracingSerializer::with(wr, StructArrayWriter)
+    }
+}
+
+impl<W: Write, V: VariantWriter> TracingSerializer<W, V> {
+    /// Constructs a new tracing serializer with the given struct representation.
+    pub fn with(wr: W, vw: V) -> Self {
+        TracingSerializer {
+            se: Serializer::with(TraceWriter::new(wr), vw),
+        }
+    }
+}
+
+impl<W, V> TracingSerializer<W, V> {
+    /// Applies all options from the given config at once, see `Serializer::set_config`.
+    pub fn set_config(&mut self, config: &SerializerConfig) {
+        self.se.set_config(config);
+    }
+
+    /// Returns the entries recorded so far, in the order they were written.
+    pub fn trace(&self) -> &[TraceEntry] {
+        self.se.wr.wr.entries()
+    }
+
+    /// Gets a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        self.se.wr.wr.get_ref()
+    }
+
+    /// Unwraps this `TracingSerializer`, returning the underlying writer and the trace.
+    pub fn into_parts(self) -> (W, Vec<TraceEntry>) {
+        let wr = self.se.wr.wr;
+        (wr.wr, wr.entries)
+    }
+
+    fn tracer(&mut self) -> Tracer<'_, W, V> {
+        Tracer(&mut self.se)
+    }
+}
+
+/// Serialize the given data structure as a MessagePack byte vector like `to_vec` does, also
+/// returning the trace of what was written.
+///
+/// # Examples
+///
+/// ```
+/// use rmp_serde::encode::to_vec_traced;
+///
+/// let (buf, trace) = to_vec_traced(&Some(vec![1, 2])).unwrap();
+///
+/// assert_eq!(vec![0x92, 0x01, 0x02], buf);
+/// assert_eq!(vec!["serialize_seq", "serialize_i32", "serialize_i32"],
+///            trace.iter().map(|entry| entry.call).collect::<Vec<_>>());
+/// ```
+pub fn to_vec_traced<T>(val: &T) -> Result<(Vec<u8>, Vec<TraceEntry>), Error>
+    where T: ?Sized + Serialize
+{
+    let mut buf = Vec::with_capacity(128);
+    let trace = {
+        let mut se = TracingSerializer::new(&mut buf);
+        val.serialize(&mut se)?;
+        se.into_parts().1
+    };
+
+    Ok((buf, trace))
+}
+
+/// Serializes the values nested in compounds with a `Tracer`.
+struct Traced;
+
+impl<W: Write, V: VariantWriter> Nested<TraceWriter<W>, V> for Traced {
+    fn serialize<T>(se: &mut Serializer<TraceWriter<W>, V>, value: &T) -> Result<(), Error>
+        where T: ?Sized + Serialize
+    {
+        value.serialize(Tracer(se))
+    }
+}
+
+/// Serializes the elements of compound values with the `Compound` of the wrapped serializer,
+/// which serializes them with the tracing serializer in turn, so that nested calls are recorded
+/// too.
+pub struct TracingCompound<'a, W: 'a, V: 'a> {
+    compound: Compound<'a, TraceWriter<W>, V, Traced>,
+    // The call which began the compound.
+    call: &'static str,
+}
+
+impl<'a, W: Write + 'a, V: VariantWriter + 'a> TracingCompound<'a, W, V> {
+    fn new(compound: Compound<'a, TraceWriter<W>, V>, call: &'static str) -> Self {
+        TracingCompound {
+            compound: compound.nest(),
+            call,
+        }
+    }
+
+    fn enter(&mut self, call: &'static str) -> &mut Compound<'a, TraceWriter<W>, V, Traced> {
+        self.compound.se.wr.wr.call = call;
+        &mut self.compound
+    }
+
+    /// Records the markers written by the compound itself, like the headers it holds back, as the
+    /// call which began it.
+    fn resume(&mut self) -> &mut Compound<'a, TraceWriter<W>, V, Traced> {
+        let call = self.call;
+        self.enter(call)
+    }
+
+    fn finish(mut self) -> Compound<'a, TraceWriter<W>, V, Traced> {
+        self.resume();
+        self.compound
+    }
+}
+
+impl<'a, W: Write + 'a, V: VariantWriter + 'a> SerializeSeq for TracingCompound<'a, W, V> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Self::Error> {
+        SerializeSeq::serialize_element(self.resume(), value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        SerializeSeq::end(self.finish())
+    }
+}
+
+impl<'a, W: Write + 'a, V: VariantWriter + 'a> SerializeTuple for TracingCompound<'a, W, V> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Self::Error> {
+        SerializeTuple::serialize_element(self.resume(), value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        SerializeTuple::end(self.finish())
+    }
+}
+
+impl<'a, W: Write + 'a, V: VariantWriter + 'a> SerializeTupleStruct for TracingCompound<'a, W, V> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Self::Error> {
+        SerializeTupleStruct::serialize_field(self.resume(), value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        SerializeTupleStruct::end(self.finish())
+    }
+}
+
+impl<'a, W: Write + 'a, V: VariantWriter + 'a> SerializeTupleVariant for TracingCompound<'a, W, V> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Self::Error> {
+        SerializeTupleVariant::serialize_field(self.resume(), value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        SerializeTupleVariant::end(self.finish())
+    }
+}
+
+impl<'a, W: Write + 'a, V: VariantWriter + 'a> SerializeMap for TracingCompound<'a, W, V> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), Self::Error> {
+        SerializeMap::serialize_key(self.resume(), key)
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Self::Error> {
+        SerializeMap::serialize_value(self.resume(), value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        SerializeMap::end(self.finish())
+    }
+}
+
+impl<'a, W: Write + 'a, V: VariantWriter + 'a> SerializeStruct for TracingCompound<'a, W, V> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, key: &'static str, value: &T) ->
+        Result<(), Self::Error>
+    {
+        SerializeStruct::serialize_field(self.enter("serialize_field"), key, value)
+    }
+
+    fn skip_field(&mut self, key: &'static str) -> Result<(), Self::Error> {
+        SerializeStruct::skip_field(self.resume(), key)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        SerializeStruct::end(self.finish())
+    }
+}
+
+impl<'a, W: Write + 'a, V: VariantWriter + 'a> SerializeStructVariant
+    for TracingCompound<'a, W, V>
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, key: &'static str, value: &T) ->
+        Result<(), Self::Error>
+    {
+        SerializeStructVariant::serialize_field(self.enter("serialize_field"), key, value)
+    }
+
+    fn skip_field(&mut self, key: &'static str) -> Result<(), Self::Error> {
+        SerializeStructVariant::skip_field(self.resume(), key)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        SerializeStructVariant::end(self.finish())
+    }
+}
+
+/// Traces the serialization of a value with the serializer wrapped by a `TracingSerializer`.
+struct Tracer<'a, W: 'a, V: 'a>(&'a mut Serializer<TraceWriter<W>, V>);
+
+impl<'a, W, V> Tracer<'a, W, V> {
+    fn enter(self, call: &'static str) -> &'a mut Serializer<TraceWriter<W>, V> {
+        self.0.wr.wr.call = call;
+        self.0
+    }
+}
+
+impl<'a, W: Write, V: VariantWriter> serde::Serializer for Tracer<'a, W, V> {
+    type Ok = ();
+    type Error = Error;
+
+    type SerializeSeq = TracingCompound<'a, W, V>;
+    type SerializeTuple = TracingCompound<'a, W, V>;
+    type SerializeTupleStruct = TracingCompound<'a, W, V>;
+    type SerializeTupleVariant = TracingCompound<'a, W, V>;
+    type SerializeMap = TracingCompound<'a, W, V>;
+    type SerializeStruct = TracingCompound<'a, W, V>;
+    type SerializeStructVariant = TracingCompound<'a, W, V>;
+
+    fn serialize_bool(self, v: bool) -> Result<Self::Ok, Self::Error> {
+        self.enter("serialize_bool").serialize_bool(v)
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Self::Ok, Self::Error> {
+        self.enter("serialize_i8").serialize_i8(v)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Self::Ok, Self::Error> {
+        self.enter("serialize_i16").serialize_i16(v)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Self::Ok, Self::Error> {
+        self.enter("serialize_i32").serialize_i32(v)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Self::Ok, Self::Error> {
+        self.enter("serialize_i64").serialize_i64(v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Self::Ok, Self::Error> {
+        self.enter("serialize_u8").serialize_u8(v)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Self::Ok, Self::Error> {
+        self.enter("serialize_u16").serialize_u16(v)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Self::Ok, Self::Error> {
+        self.enter("serialize_u32").serialize_u32(v)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Self::Ok, Self::Error> {
+        self.enter("serialize_u64").serialize_u64(v)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Self::Ok, Self::Error> {
+        self.enter("serialize_f32").serialize_f32(v)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Self::Ok, Self::Error> {
+        self.enter("serialize_f64").serialize_f64(v)
+    }
+
+    fn serialize_char(self, v: char) -> Result<Self::Ok, Self::Error> {
+        self.enter("serialize_char").serialize_char(v)
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Self::Ok, Self::Error> {
+        self.enter("serialize_str").serialize_str(v)
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok, Self::Error> {
+        self.enter("serialize_bytes").serialize_bytes(v)
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
+        self.enter("serialize_none").serialize_none()
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<Self::Ok, Self::Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
+        self.enter("serialize_unit").serialize_unit()
+    }
+
+    fn serialize_unit_struct(self, name: &'static str) -> Result<Self::Ok, Self::Error> {
+        self.enter("serialize_unit_struct").serialize_unit_struct(name)
+    }
+
+    fn serialize_unit_variant(self, name: &'static str, idx: u32, variant: &'static str) ->
+        Result<Self::Ok, Self::Error>
+    {
+        self.enter("serialize_unit_variant").serialize_unit_variant(name, idx, variant)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(self, name: &'static str, value: &T) ->
+        Result<Self::Ok, Self::Error>
+    {
+        let se = self.enter("serialize_newtype_struct");
+        if name == TIMESTAMP_NAME || name == MSGPACK_EXT_STRUCT_NAME || name == RAW_VALUE_NAME {
+            return se.serialize_newtype_struct(name, value);
+        }
+
+        let entered = se.overrides.for_type_name(name).map(|scope| se.enter_scope(scope));
+        se.write_seq_len(Some(1))?;
+        value.serialize(Tracer(&mut *se))?;
+
+        match entered {
+            Some(entered) => {
+                se.wr.wr.call = "serialize_newtype_struct";
+                se.leave_scope(entered)
+            }
+            None => Ok(()),
+        }
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(self, name: &'static str, idx: u32,
+                                                        variant: &'static str, value: &T) ->
+        Result<Self::Ok, Self::Error>
+    {
+        let se = self.enter("serialize_newtype_variant");
+        se.write_newtype_variant(name, idx, variant)?;
+        value.serialize(Tracer(se))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+        let compound = self.enter("serialize_seq").serialize_seq(len)?;
+        Ok(TracingCompound::new(compound, "serialize_seq"))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        let compound = self.enter("serialize_tuple").serialize_tuple(len)?;
+        Ok(TracingCompound::new(compound, "serialize_tuple"))
+    }
+
+    fn serialize_tuple_struct(self, name: &'static str, len: usize) ->
+        Result<Self::SerializeTupleStruct, Self::Error>
+    {
+        let compound = self.enter("serialize_tuple_struct").serialize_tuple_struct(name, len)?;
+        Ok(TracingCompound::new(compound, "serialize_tuple_struct"))
+    }
+
+    fn serialize_tuple_variant(self, name: &'static str, idx: u32, variant: &'static str,
+                               len: usize) ->
+        Result<Self::SerializeTupleVariant, Self::Error>
+    {
+        let se = self.enter("serialize_tuple_variant");
+        let compound = se.serialize_tuple_variant(name, idx, variant, len)?;
+        Ok(TracingCompound::new(compound, "serialize_tuple_variant"))
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+        let compound = self.enter("serialize_map").serialize_map(len)?;
+        Ok(TracingCompound::new(compound, "serialize_map"))
+    }
+
+    fn serialize_struct(self, name: &'static str, len: usize) ->
+        Result<Self::SerializeStruct, Self::Error>
+    {
+        let compound = self.enter("serialize_struct").serialize_struct(name, len)?;
+        Ok(TracingCompound::new(compound, "serialize_struct"))
+    }
+
+    fn serialize_struct_variant(self, name: &'static str, idx: u32, variant: &'static str,
+                                len: usize) ->
+        Result<Self::SerializeStructVariant, Self::Error>
+    {
+        let se = self.enter("serialize_struct_variant");
+        let compound = se.serialize_struct_variant(name, idx, variant, len)?;
+        Ok(TracingCompound::new(compound, "serialize_struct_variant"))
+    }
+}
+
+impl<'a, W: Write, V: VariantWriter> serde::Serializer for &'a mut TracingSerializer<W, V> {
+    type Ok = ();
+    type Error = Error;
+
+    type SerializeSeq = TracingCompound<'a, W, V>;
+    type SerializeTuple = TracingCompound<'a, W, V>;
+    type SerializeTupleStruct = TracingCompound<'a, W, V>;
+    type SerializeTupleVariant = TracingCompound<'a, W, V>;
+    type SerializeMap = TracingCompound<'a, W, V>;
+    type SerializeStruct = TracingCompound<'a, W, V>;
+    type SerializeStructVariant = TracingCompound<'a, W, V>;
+
+    fn serialize_bool(self, v: bool) -> Result<Self::Ok, Self::Error> {
+        self.tracer().serialize_bool(v)
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Self::Ok, Self::Error> {
+        self.tracer().serialize_i8(v)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Self::Ok, Self::Error> {
+        self.tracer().serialize_i16(v)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Self::Ok, Self::Error> {
+        self.tracer().serialize_i32(v)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Self::Ok, Self::Error> {
+        self.tracer().serialize_i64(v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Self::Ok, Self::Error> {
+        self.tracer().serialize_u8(v)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Self::Ok, Self::Error> {
+        self.tracer().serialize_u16(v)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Self::Ok, Self::Error> {
+        self.tracer().serialize_u32(v)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Self::Ok, Self::Error> {
+        self.tracer().serialize_u64(v)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Self::Ok, Self::Error> {
+        self.tracer().serialize_f32(v)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Self::Ok, Self::Error> {
+        self.tracer().serialize_f64(v)
+    }
+
+    fn serialize_char(self, v: char) -> Result<Self::Ok, Self::Error> {
+        self.tracer().serialize_char(v)
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Self::Ok, Self::Error> {
+        self.tracer().serialize_str(v)
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok, Self::Error> {
+        self.tracer().serialize_bytes(v)
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
+        self.tracer().serialize_none()
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<Self::Ok, Self::Error> {
+        self.tracer().serialize_some(value)
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
+        self.tracer().serialize_unit()
+    }
+
+    fn serialize_unit_struct(self, name: &'static str) -> Result<Self::Ok, Self::Error> {
+        self.tracer().serialize_unit_struct(name)
+    }
+
+    fn serialize_unit_variant(self, name: &'static str, idx: u32, variant: &'static str) ->
+        Result<Self::Ok, Self::Error>
+    {
+        self.tracer().serialize_unit_variant(name, idx, variant)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(self, name: &'static str, value: &T) ->
+        Result<Self::Ok, Self::Error>
+    {
+        self.tracer().serialize_newtype_struct(name, value)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(self, name: &'static str, idx: u32,
+                                                        variant: &'static str, value: &T) ->
+        Result<Self::Ok, Self::Error>
+    {
+        self.tracer().serialize_newtype_variant(name, idx, variant, value)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+        self.tracer().serialize_seq(len)
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        self.tracer().serialize_tuple(len)
+    }
+
+    fn serialize_tuple_struct(self, name: &'static str, len: usize) ->
+        Result<Self::SerializeTupleStruct, Self::Error>
+    {
+        self.tracer().serialize_tuple_struct(name, len)
+    }
+
+    fn serialize_tuple_variant(self, name: &'static str, idx: u32, variant: &'static str,
+                               len: usize) ->
+        Result<Self::SerializeTupleVariant, Self::Error>
+    {
+        self.tracer().serialize_tuple_variant(name, idx, variant, len)
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+        self.tracer().serialize_map(len)
+    }
+
+    fn serialize_struct(self, name: &'static str, len: usize) ->
+        Result<Self::SerializeStruct, Self::Error>
+    {
+        self.tracer().serialize_struct(name, len)
+    }
+
+    fn serialize_struct_variant(self, name: &'static str, idx: u32, variant: &'static str,
+                                len: usize) ->
+        Result<Self::SerializeStructVariant, Self::Error>
+    {
+        self.tracer().serialize_struct_variant(name, idx, variant, len)
+    }
+}
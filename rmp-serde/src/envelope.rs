@@ -0,0 +1,175 @@
+//! Versioned message envelopes.
+//!
+//! Long-lived protocols have to decode messages written by older peers after their types have
+//! changed. An envelope tags the payload with a schema version, so the receiver can pick the type
+//! the payload was written with and upgrade it to the current one.
+//!
+//! An envelope is encoded as the array `[version, payload]`, where the version is an unsigned
+//! integer and the payload is the message encoded as usual.
+//!
+//! # Examples
+//!
+//! ```
+//! extern crate serde;
+//! #[macro_use]
+//! extern crate serde_derive;
+//! extern crate rmp_serde;
+//!
+//! use rmp_serde::envelope::{self, Versions};
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct UserV1 {
+//!     name: String,
+//! }
+//!
+//! #[derive(Debug, PartialEq, Serialize, Deserialize)]
+//! struct User {
+//!     first: String,
+//!     last: String,
+//! }
+//!
+//! impl From<UserV1> for User {
+//!     fn from(user: UserV1) -> User {
+//!         let mut parts = user.name.splitn(2, ' ');
+//!         let first = parts.next().unwrap_or("").into();
+//!         let last = parts.next().unwrap_or("").into();
+//!         User { first, last }
+//!     }
+//! }
+//!
+//! fn main() {
+//!     let versions = Versions::new()
+//!         .version::<UserV1>(1)
+//!         .version::<User>(2);
+//!
+//!     let old = envelope::to_vec(1, &UserV1 { name: "John Smith".into() }).unwrap();
+//!     let new = envelope::to_vec(2, &User { first: "Jane".into(), last: "Doe".into() }).unwrap();
+//!
+//!     assert_eq!(User { first: "John".into(), last: "Smith".into() },
+//!                versions.from_slice(&old).unwrap());
+//!     assert_eq!(User { first: "Jane".into(), last: "Doe".into() },
+//!                versions.from_slice(&new).unwrap());
+//! }
+//! ```
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::fmt::{self, Debug, Formatter};
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use rmp;
+
+use decode::{self, Deserializer};
+use encode::{self, Write};
+
+/// Serializes the value into an envelope with the given version.
+pub fn to_vec<T>(version: u32, val: &T) -> Result<Vec<u8>, encode::Error>
+    where T: ?Sized + Serialize
+{
+    encode::to_vec(&(version, val))
+}
+
+/// Serializes the value into an envelope with the given version, writing it to the given writer.
+pub fn write<W, T>(wr: &mut W, version: u32, val: &T) -> Result<(), encode::Error>
+    where W: ?Sized + Write,
+          T: ?Sized + Serialize
+{
+    encode::write(wr, &(version, val))
+}
+
+/// Reads the header of an envelope, returning its version and the encoded payload.
+///
+/// # Errors
+///
+/// Fails if the input doesn't start with an array of two elements, the first of which is a
+/// version.
+pub fn read_version(buf: &[u8]) -> Result<(u32, &[u8]), decode::Error> {
+    let mut rd = buf;
+    match rmp::decode::read_array_len(&mut rd)? {
+        2 => {}
+        len => return Err(decode::Error::LengthMismatch(len)),
+    }
+    let version = rmp::decode::read_int(&mut rd)?;
+
+    Ok((version, rd))
+}
+
+type Decoder<T> = Box<dyn Fn(&[u8]) -> Result<T, decode::Error> + Send + Sync>;
+
+/// A registry of the message types of every supported version, which decodes envelopes into the
+/// current type.
+///
+/// Each version is registered with the type its payload is deserialized as, and a conversion into
+/// the current type. Conversions of successive versions can be chained by the caller, for example
+/// `|v1| V3::from(V2::from(v1))`, so that every migration step is written once.
+pub struct Versions<T> {
+    entries: Vec<(u32, Decoder<T>)>,
+}
+
+impl<T> Versions<T> {
+    /// Constructs an empty registry, which rejects all versions.
+    pub fn new() -> Self {
+        Versions { entries: Vec::new() }
+    }
+
+    /// Registers a version whose payload is deserialized as `V` and converted with `Into`.
+    ///
+    /// The current version is registered with `T` itself.
+    pub fn version<V>(self, version: u32) -> Self
+        where V: DeserializeOwned + Into<T> + 'static
+    {
+        self.migrate(version, |val: V| val.into())
+    }
+
+    /// Registers a version whose payload is deserialized as `V` and converted with the given
+    /// function.
+    pub fn migrate<V, F>(mut self, version: u32, f: F) -> Self
+        where V: DeserializeOwned + 'static,
+              F: Fn(V) -> T + Send + Sync + 'static
+    {
+        let decoder = move |buf: &[u8]| {
+            let val = serde::Deserialize::deserialize(&mut Deserializer::from_slice(buf))?;
+            Ok(f(val))
+        };
+
+        // A later registration of the same version replaces the previous one.
+        self.entries.retain(|entry| entry.0 != version);
+        self.entries.push((version, Box::new(decoder)));
+        self
+    }
+
+    /// Returns `true` if the given version is registered.
+    pub fn contains(&self, version: u32) -> bool {
+        self.entries.iter().any(|entry| entry.0 == version)
+    }
+
+    /// Decodes an envelope, deserializing its payload as the type registered for its version.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::UnsupportedVersion` if the version is not registered, and with any other
+    /// error if the envelope or its payload is malformed.
+    pub fn from_slice(&self, buf: &[u8]) -> Result<T, decode::Error> {
+        let (version, payload) = read_version(buf)?;
+        match self.entries.iter().find(|entry| entry.0 == version) {
+            Some(entry) => (entry.1)(payload),
+            None => Err(decode::Error::UnsupportedVersion(version)),
+        }
+    }
+}
+
+impl<T> Default for Versions<T> {
+    fn default() -> Self {
+        Versions::new()
+    }
+}
+
+impl<T> Debug for Versions<T> {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        f.debug_set()
+            .entries(self.entries.iter().map(|entry| entry.0))
+            .finish()
+    }
+}
@@ -0,0 +1,173 @@
+//! Wrappers reading and writing MessagePack extensions through serde.
+//!
+//! `Ext` borrows the extension data and `ExtBuf` owns it. Both go through the newtype struct named
+//! `MSGPACK_EXT_STRUCT_NAME`, so the `Serializer` writes them with the most compact fixext or ext
+//! marker and the `Deserializer` reads them back from any extension, which lets application
+//! extension types be fields of derived structs.
+//!
+//! With other serde formats they are written as a newtype struct of the `(i8, bytes)` tuple.
+//!
+//! # Examples
+//!
+//! ```
+//! extern crate serde;
+//! #[macro_use]
+//! extern crate serde_derive;
+//! extern crate rmp_serde;
+//!
+//! use rmp_serde::{Ext, ExtBuf};
+//!
+//! #[derive(Debug, PartialEq, Serialize, Deserialize)]
+//! struct Packet<'a> {
+//!     id: u32,
+//!     #[serde(borrow)]
+//!     payload: Ext<'a>,
+//! }
+//!
+//! fn main() {
+//!     let packet = Packet { id: 1, payload: Ext(42, &[1, 2]) };
+//!     let buf = rmp_serde::to_vec(&packet).unwrap();
+//!     assert_eq!(vec![0x92, 0x01, 0xd5, 0x2a, 0x01, 0x02], buf);
+//!
+//!     // The payload is borrowed from the buffer.
+//!     assert_eq!(packet, rmp_serde::from_slice(&buf).unwrap());
+//!
+//!     let (_, payload): (u32, ExtBuf) = rmp_serde::from_slice(&buf).unwrap();
+//!     assert_eq!(ExtBuf(42, vec![1, 2]), payload);
+//! }
+//! ```
+
+use alloc::vec::Vec;
+use core::cmp;
+use core::fmt::{self, Formatter};
+
+use serde::de::{self, Deserialize, Deserializer, SeqAccess, Visitor};
+use serde::ser::{Serialize, Serializer};
+
+use MSGPACK_EXT_STRUCT_NAME;
+
+/// An extension of the given type, borrowing its data.
+///
+/// Deserializing it borrows the data from the input, so it must come from a slice: use `ExtBuf`
+/// to read extensions from an `io::Read`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Ext<'a>(pub i8, pub &'a [u8]);
+
+/// An extension of the given type, owning its data.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ExtBuf(pub i8, pub Vec<u8>);
+
+impl ExtBuf {
+    /// Borrows this extension as an `Ext`.
+    pub fn as_ext(&self) -> Ext<'_> {
+        Ext(self.0, &self.1)
+    }
+}
+
+impl<'a> From<Ext<'a>> for ExtBuf {
+    fn from(ext: Ext<'a>) -> Self {
+        ExtBuf(ext.0, ext.1.into())
+    }
+}
+
+/// Data serialized as bytes rather than as a sequence.
+struct Bytes<'a>(&'a [u8]);
+
+impl<'a> Serialize for Bytes<'a> {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_bytes(self.0)
+    }
+}
+
+impl<'a> Serialize for Ext<'a> {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_newtype_struct(MSGPACK_EXT_STRUCT_NAME, &(self.0, Bytes(self.1)))
+    }
+}
+
+impl Serialize for ExtBuf {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        self.as_ext().serialize(s)
+    }
+}
+
+struct ExtVisitor;
+
+impl<'de> Visitor<'de> for ExtVisitor {
+    type Value = Ext<'de>;
+
+    fn expecting(&self, fmt: &mut Formatter) -> Result<(), fmt::Error> {
+        fmt.write_str("an extension")
+    }
+
+    fn visit_newtype_struct<D>(self, de: D) -> Result<Self::Value, D::Error>
+        where D: Deserializer<'de>
+    {
+        let (ty, data) = Deserialize::deserialize(de)?;
+        Ok(Ext(ty, data))
+    }
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for Ext<'a> {
+    fn deserialize<D: Deserializer<'de>>(de: D) -> Result<Self, D::Error> {
+        de.deserialize_newtype_struct(MSGPACK_EXT_STRUCT_NAME, ExtVisitor)
+    }
+}
+
+/// Owned data deserialized from bytes, or from a sequence of them.
+struct ByteBuf(Vec<u8>);
+
+struct ByteBufVisitor;
+
+impl<'de> Visitor<'de> for ByteBufVisitor {
+    type Value = ByteBuf;
+
+    fn expecting(&self, fmt: &mut Formatter) -> Result<(), fmt::Error> {
+        fmt.write_str("extension data")
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+        Ok(ByteBuf(v.into()))
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<Self::Value, E> {
+        Ok(ByteBuf(v))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut buf = Vec::with_capacity(cmp::min(seq.size_hint().unwrap_or(0), 4096));
+        while let Some(byte) = seq.next_element()? {
+            buf.push(byte);
+        }
+        Ok(ByteBuf(buf))
+    }
+}
+
+impl<'de> Deserialize<'de> for ByteBuf {
+    fn deserialize<D: Deserializer<'de>>(de: D) -> Result<Self, D::Error> {
+        de.deserialize_byte_buf(ByteBufVisitor)
+    }
+}
+
+struct ExtBufVisitor;
+
+impl<'de> Visitor<'de> for ExtBufVisitor {
+    type Value = ExtBuf;
+
+    fn expecting(&self, fmt: &mut Formatter) -> Result<(), fmt::Error> {
+        fmt.write_str("an extension")
+    }
+
+    fn visit_newtype_struct<D>(self, de: D) -> Result<Self::Value, D::Error>
+        where D: Deserializer<'de>
+    {
+        let (ty, data): (i8, ByteBuf) = Deserialize::deserialize(de)?;
+        Ok(ExtBuf(ty, data.0))
+    }
+}
+
+impl<'de> Deserialize<'de> for ExtBuf {
+    fn deserialize<D: Deserializer<'de>>(de: D) -> Result<Self, D::Error> {
+        de.deserialize_newtype_struct(MSGPACK_EXT_STRUCT_NAME, ExtBufVisitor)
+    }
+}
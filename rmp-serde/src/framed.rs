@@ -0,0 +1,198 @@
+//! Length-prefixed framing of messages.
+//!
+//! MessagePack values carry their own length, but a stream protocol still has to know how many
+//! bytes to wait for before decoding a message, and to skip a message it can't decode without
+//! losing its place. A frame is the message encoded as usual, preceded by its length in bytes as
+//! a 4-byte big-endian unsigned integer.
+//!
+//! `write_frame` and `read_frame` work on blocking writers and readers. Event loops, which receive
+//! data in chunks whenever it arrives, feed them to a `FrameDecoder` instead.
+//!
+//! # Examples
+//!
+//! ```
+//! use rmp_serde::framed;
+//!
+//! let mut buf = Vec::new();
+//! framed::write_frame(&mut buf, &("ping", 1)).unwrap();
+//! framed::write_frame(&mut buf, &("pong", 2)).unwrap();
+//! assert_eq!([0, 0, 0, 7, 0x92, 0xa4], buf[..6]);
+//!
+//! let mut rd = &buf[..];
+//! assert_eq!(("ping".to_string(), 1), framed::read_frame(&mut rd).unwrap());
+//! assert_eq!(("pong".to_string(), 2), framed::read_frame(&mut rd).unwrap());
+//! ```
+
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::io;
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use rmp::encode::{RmpWrite, ValueWriteError};
+
+use decode::{self, Deserializer, DeserializerConfig};
+use encode::{self, SerializerConfig, Write};
+
+/// Length of the prefix of a frame.
+const PREFIX_LEN: usize = 4;
+
+/// Serializes the value into a frame, writing it to the given writer.
+///
+/// # Errors
+///
+/// Returns the errors of `encode::to_vec`, and `encode::Error::Syntax` if the message is longer
+/// than `u32::MAX` bytes.
+pub fn write_frame<W, T>(wr: &mut W, val: &T) -> Result<(), encode::Error>
+    where W: ?Sized + Write,
+          T: ?Sized + Serialize
+{
+    write_frame_with(wr, val, &SerializerConfig::default())
+}
+
+/// Serializes the value into a frame using the given config, writing it to the given writer.
+pub fn write_frame_with<W, T>(wr: &mut W, val: &T, config: &SerializerConfig) ->
+    Result<(), encode::Error>
+    where W: ?Sized + Write,
+          T: ?Sized + Serialize
+{
+    let buf = encode::to_vec_with(val, config)?;
+    if buf.len() > u32::MAX as usize {
+        return Err(encode::Error::Syntax("frame too long".into()));
+    }
+
+    let prefix = (buf.len() as u32).to_be_bytes();
+    RmpWrite::write_bytes(wr, &prefix).map_err(ValueWriteError::InvalidMarkerWrite)?;
+    RmpWrite::write_bytes(wr, &buf).map_err(ValueWriteError::InvalidDataWrite)?;
+    Ok(())
+}
+
+/// Reads a frame from the given reader and deserializes its message.
+///
+/// # Errors
+///
+/// Returns `decode::Error::InvalidDataRead` if the reader fails or ends before the end of the
+/// frame, `decode::Error::LengthLimitExceeded` if the frame is longer than the default
+/// `DeserializerConfig::max_bytes` before reading it, and `decode::Error::Syntax` if the message
+/// doesn't fill the frame. Otherwise returns the errors of deserializing `T` from a slice.
+#[cfg(feature = "std")]
+pub fn read_frame<R, T>(rd: &mut R) -> Result<T, decode::Error>
+    where R: ?Sized + io::Read,
+          T: DeserializeOwned
+{
+    read_frame_with(rd, &DeserializerConfig::default())
+}
+
+/// Reads a frame from the given reader and deserializes its message using the given config,
+/// whose `max_bytes` limits the length of the frame.
+#[cfg(feature = "std")]
+pub fn read_frame_with<R, T>(rd: &mut R, config: &DeserializerConfig) -> Result<T, decode::Error>
+    where R: ?Sized + io::Read,
+          T: DeserializeOwned
+{
+    let mut prefix = [0; PREFIX_LEN];
+    rd.read_exact(&mut prefix).map_err(decode::Error::InvalidDataRead)?;
+    let len = u32::from_be_bytes(prefix);
+    if len as usize > config.get_max_bytes() {
+        return Err(decode::Error::LengthLimitExceeded(len));
+    }
+
+    let mut buf = vec![0; len as usize];
+    rd.read_exact(&mut buf).map_err(decode::Error::InvalidDataRead)?;
+    from_frame(&buf, config)
+}
+
+/// Deserializes the message filling the whole frame.
+fn from_frame<T: DeserializeOwned>(buf: &[u8], config: &DeserializerConfig) ->
+    Result<T, decode::Error>
+{
+    let mut de = Deserializer::from_slice(buf);
+    de.set_config(config);
+    let val = T::deserialize(&mut de)?;
+    if !de.get_ref().is_empty() {
+        return Err(decode::Error::Syntax("trailing bytes in frame".into()));
+    }
+
+    Ok(val)
+}
+
+/// A decoder of frames fed with chunks of input, which tells when it needs more of them instead
+/// of blocking.
+///
+/// # Examples
+///
+/// ```
+/// use rmp_serde::decode::Error;
+/// use rmp_serde::framed::{self, FrameDecoder};
+///
+/// let mut buf = Vec::new();
+/// framed::write_frame(&mut buf, &42).unwrap();
+///
+/// let mut dec = FrameDecoder::new();
+/// dec.feed(&buf[..4]);
+/// match dec.next_frame::<u8>() {
+///     Err(Error::NeedMoreData(1)) => {}
+///     other => panic!("unexpected result: {:?}", other),
+/// }
+///
+/// dec.feed(&buf[4..]);
+/// assert_eq!(42, dec.next_frame::<u8>().unwrap());
+/// ```
+#[derive(Debug, Default)]
+pub struct FrameDecoder {
+    buf: Vec<u8>,
+    config: DeserializerConfig,
+}
+
+impl FrameDecoder {
+    /// Constructs a decoder waiting for the first chunk, with the default config.
+    pub fn new() -> FrameDecoder {
+        FrameDecoder::default()
+    }
+
+    /// Applies all options from the given config at once, replacing the current ones.
+    ///
+    /// Its `max_bytes` limits the length of frames.
+    pub fn set_config(&mut self, config: &DeserializerConfig) {
+        self.config = config.clone();
+    }
+
+    /// Appends the chunk to the input.
+    pub fn feed(&mut self, chunk: &[u8]) {
+        self.buf.extend_from_slice(chunk);
+    }
+
+    /// Deserializes the message of the next frame if all of its bytes were fed.
+    ///
+    /// The frame is removed from the input once deserialized, whether this succeeded or not.
+    ///
+    /// # Errors
+    ///
+    /// Returns `decode::Error::NeedMoreData` with the number of bytes missing if the frame is
+    /// incomplete, and `decode::Error::LengthLimitExceeded` if it is longer than the configured
+    /// maximum. Otherwise returns the same errors as `read_frame`.
+    pub fn next_frame<T: DeserializeOwned>(&mut self) -> Result<T, decode::Error> {
+        if self.buf.len() < PREFIX_LEN {
+            return Err(decode::Error::NeedMoreData(PREFIX_LEN - self.buf.len()));
+        }
+
+        let len = u32::from_be_bytes([self.buf[0], self.buf[1], self.buf[2], self.buf[3]]);
+        if len as usize > self.config.get_max_bytes() {
+            return Err(decode::Error::LengthLimitExceeded(len));
+        }
+        let end = PREFIX_LEN + len as usize;
+        if self.buf.len() < end {
+            return Err(decode::Error::NeedMoreData(end - self.buf.len()));
+        }
+
+        let res = from_frame(&self.buf[PREFIX_LEN..end], &self.config);
+        self.buf.drain(..end);
+        res
+    }
+
+    /// Returns the number of bytes fed and not decoded yet.
+    pub fn buffered(&self) -> usize {
+        self.buf.len()
+    }
+}
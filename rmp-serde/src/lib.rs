@@ -57,22 +57,165 @@
 //!     val.serialize(&mut Serializer::new(&mut buf)).unwrap();
 //! }
 //! ```
+//!
+//! # `no_std` support
+//!
+//! Disabling the default `std` feature makes this crate depend on `alloc` only. Serialization
+//! then targets `Vec<u8>` or fixed-size `&mut [u8]` buffers, either through `to_vec` and `write`
+//! or through the `Serializer`, and deserialization reads from byte slices with `from_slice`.
+//!
+//! # Borrowing
+//!
+//! Strings and binaries deserialized from a byte slice, for example with `from_slice`, are passed
+//! to serde as borrowed from the input. Mark `Cow<'a, str>` and `Cow<'a, [u8]>` fields with
+//! `#[serde(borrow)]` to take advantage of that: they are then `Cow::Borrowed` when reading from
+//! a slice and `Cow::Owned` when reading from an `io::Read`, where the data is copied anyway.
+//! Without the attribute serde always allocates an owned value.
+//!
+//! Byte slices serialized without `serde_bytes` are written as arrays of integers, which can't be
+//! borrowed. A borrowing `Cow<'a, [u8]>` field accepts them too, falling back to owned data.
+
+#![cfg_attr(not(feature = "std"), no_std)]
 
+#[cfg(feature = "std")]
+extern crate core;
+#[macro_use]
+extern crate alloc;
 extern crate rmp;
-extern crate byteorder;
 #[macro_use]
 extern crate serde;
 
-use std::fmt::{self, Display, Formatter};
-use std::str::{self, Utf8Error};
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::{self, Display, Formatter};
+use core::str::{self, Utf8Error};
 
 use serde::de::{self, Deserialize};
 
-pub use decode::Deserializer;
-pub use encode::Serializer;
+#[cfg(feature = "std")]
+pub use decode::{from_read, from_read_with};
+pub use decode::{from_slice_with, Deserializer, DeserializerConfig, StreamDeserializer};
+pub use encode::{content_hash, to_vec_named, to_vec_with, Serializer, SerializerConfig};
+pub use ext::{Ext, ExtBuf};
+pub use raw_value::RawValue;
+use encode::Write;
 
 pub mod decode;
 pub mod encode;
+pub mod envelope;
+mod ext;
+pub mod framed;
+pub mod neovim;
+mod raw_value;
+#[cfg(feature = "test-util")]
+pub mod test_util;
+pub mod timestamp;
+
+/// Name of the newtype struct the `timestamp` functions go through, which the `Serializer` and the
+/// `Deserializer` recognize to read and write timestamp extensions.
+const TIMESTAMP_NAME: &str = "_rmp_serde::Timestamp";
+
+/// Name of the newtype struct `RawValue` goes through, which the `Serializer` writes as the bytes
+/// it holds and the `Deserializer` fills with the bytes of the next value.
+const RAW_VALUE_NAME: &str = "_rmp_serde::RawValue";
+
+/// Extension type of timestamps, reserved by the MessagePack specification.
+const TIMESTAMP_TYPE: i8 = -1;
+
+/// Name of the newtype struct the `Serializer` writes as a MessagePack extension.
+///
+/// The wrapped value must serialize as a tuple of the `i8` extension type and the data as bytes,
+/// like `(i8, serde_bytes::ByteBuf)`. Other serde formats see the newtype struct and the tuple as
+/// they are. This is how `rmpv::Value::Ext` keeps being an extension when serialized with serde.
+///
+/// The `Deserializer` reads an extension when asked for a newtype struct of this name, presenting
+/// it as the same tuple. The data is borrowed when deserializing from a slice, so it can go into
+/// a `&'de [u8]`.
+pub const MSGPACK_EXT_STRUCT_NAME: &str = "_ExtStruct";
+
+/// A runtime mapping from enum variants to the discriminants they are encoded with.
+///
+/// By default enum variants are encoded using their declaration-order index, so reordering the
+/// variants or inserting one in the middle changes the wire format. Assigning explicit
+/// discriminants keeps the protocol stable, like `#[repr]` discriminants do for C-like enums.
+///
+/// Once at least one variant of an enum is listed, every variant of that enum must be: encoding
+/// an unlisted variant or decoding an unknown discriminant fails with `Syntax` errors. Enums
+/// that are not listed at all keep using indices.
+///
+/// The mapping is applied with `SerializerConfig::discriminants` and
+/// `DeserializerConfig::discriminants`.
+///
+/// # Examples
+///
+/// ```
+/// extern crate serde;
+/// #[macro_use]
+/// extern crate serde_derive;
+/// extern crate rmp_serde;
+///
+/// use rmp_serde::{Discriminants, DeserializerConfig, SerializerConfig};
+///
+/// #[derive(Debug, PartialEq, Serialize, Deserialize)]
+/// enum Status {
+///     Active,
+///     Deleted,
+///     Blocked,
+/// }
+///
+/// fn main() {
+///     let discriminants = Discriminants::new()
+///         .variant("Status", "Active", 1)
+///         .variant("Status", "Blocked", 2)
+///         .variant("Status", "Deleted", 10);
+///
+///     let config = SerializerConfig::new().discriminants(discriminants.clone());
+///     let buf = rmp_serde::to_vec_with(&Status::Deleted, &config).unwrap();
+///     assert_eq!(vec![0x92, 0x0a, 0x90], buf);
+///
+///     let config = DeserializerConfig::new().discriminants(discriminants);
+///     let status: Status = rmp_serde::from_slice_with(&buf, &config).unwrap();
+///     assert_eq!(Status::Deleted, status);
+/// }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Discriminants {
+    entries: Vec<(&'static str, &'static str, u32)>,
+}
+
+impl Discriminants {
+    /// Constructs an empty mapping, which leaves all enums encoded with indices.
+    pub fn new() -> Self {
+        Discriminants::default()
+    }
+
+    /// Assigns the discriminant to the variant of the enum with the given name.
+    ///
+    /// Names are the ones serde sees, i.e. after applying `rename` attributes.
+    pub fn variant(mut self, name: &'static str, variant: &'static str, id: u32) -> Self {
+        self.entries.push((name, variant, id));
+        self
+    }
+
+    /// Returns `true` if discriminants are assigned to the variants of the given enum.
+    fn contains(&self, name: &str) -> bool {
+        self.entries.iter().any(|entry| entry.0 == name)
+    }
+
+    /// Returns the discriminant of the given variant.
+    fn get(&self, name: &str, variant: &str) -> Option<u32> {
+        self.entries.iter()
+            .find(|entry| entry.0 == name && entry.1 == variant)
+            .map(|entry| entry.2)
+    }
+
+    /// Returns the name of the variant with the given discriminant.
+    fn find(&self, name: &str, id: u32) -> Option<&'static str> {
+        self.entries.iter()
+            .find(|entry| entry.0 == name && entry.2 == id)
+            .map(|entry| entry.1)
+    }
+}
 
 /// Helper that allows to decode strings no matter whether they contain valid or invalid UTF-8.
 #[derive(Clone, Debug, PartialEq)]
@@ -273,9 +416,29 @@ impl<'de> Deserialize<'de> for RawRef<'de> {
 pub fn to_vec<T>(value: &T) -> Result<Vec<u8>, encode::Error>
     where T: serde::Serialize
 {
-    let mut buf = Vec::with_capacity(64);
-    value.serialize(&mut Serializer::new(&mut buf))?;
-    Ok(buf)
+    encode::to_vec(value)
+}
+
+/// Serializes a value into the given buffer, returning the number of bytes written.
+pub fn to_slice<T>(buf: &mut [u8], value: &T) -> Result<usize, encode::Error>
+    where T: ?Sized + serde::Serialize
+{
+    encode::to_slice(buf, value)
+}
+
+/// Returns the length of the encoding of a value, without allocating it.
+pub fn serialized_size<T>(value: &T) -> Result<u64, encode::Error>
+    where T: ?Sized + serde::Serialize
+{
+    encode::serialized_size(value)
+}
+
+/// Serializes a value to the given writer, writing structs as maps with their field names as keys.
+pub fn to_writer_named<W: ?Sized, T: ?Sized>(wr: &mut W, value: &T) -> Result<(), encode::Error>
+    where W: Write,
+          T: serde::Serialize
+{
+    encode::write_named(wr, value)
 }
 
 /// Deserializes a byte slice into the desired type.
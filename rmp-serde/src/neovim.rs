@@ -0,0 +1,143 @@
+//! Typed handles and configuration presets for Neovim's msgpack-rpc API.
+//!
+//! Neovim refers to its buffers, windows and tabpages by integer handles, which it sends as
+//! extensions of types `0`, `1` and `2` whose data is the MessagePack encoding of the handle. The
+//! `Buffer`, `Window` and `Tabpage` types read and write these extensions, checking their type,
+//! so API payloads can be deserialized into derived structs and vectors of handles directly.
+//!
+//! Neovim reports the extension types in the result of `nvim_get_api_info`. They have never
+//! changed, so the handles use them as constants.
+//!
+//! Dictionaries are keyed by strings on both sides, which `serializer_config` and
+//! `deserializer_config` set up for derived structs and enums.
+//!
+//! # Examples
+//!
+//! ```
+//! extern crate serde;
+//! #[macro_use]
+//! extern crate serde_derive;
+//! extern crate rmp_serde;
+//!
+//! use rmp_serde::neovim::{self, Buffer, Window};
+//!
+//! #[derive(Debug, PartialEq, Serialize, Deserialize)]
+//! struct WinInfo {
+//!     winid: Window,
+//!     bufnr: Buffer,
+//! }
+//!
+//! fn main() {
+//!     // [1, 0, nil, [{"winid": Window(1000), "bufnr": Buffer(1)}]]
+//!     let buf = [
+//!         0x94, 0x01, 0x00, 0xc0, 0x91, 0x82,
+//!         0xa5, 0x77, 0x69, 0x6e, 0x69, 0x64, 0xc7, 0x03, 0x01, 0xcd, 0x03, 0xe8,
+//!         0xa5, 0x62, 0x75, 0x66, 0x6e, 0x72, 0xd4, 0x00, 0x01,
+//!     ];
+//!
+//!     let config = neovim::deserializer_config();
+//!     let (_, id, err, info): (u8, u32, Option<String>, Vec<WinInfo>) =
+//!         rmp_serde::from_slice_with(&buf, &config).unwrap();
+//!     assert_eq!((0, None), (id, err));
+//!     assert_eq!(vec![WinInfo { winid: Window(1000), bufnr: Buffer(1) }], info);
+//!
+//!     let out = rmp_serde::to_vec_with(&info[0], &neovim::serializer_config()).unwrap();
+//!     assert_eq!(&buf[5..], &out[..]);
+//! }
+//! ```
+
+use alloc::vec::Vec;
+
+use rmp;
+use serde::de::{self, Deserialize, Deserializer};
+use serde::ser::{self, Serialize, Serializer};
+
+use decode::{DeserializerConfig, InvalidUtf8};
+use encode::SerializerConfig;
+use {Ext, ExtBuf};
+
+/// Extension type of buffer handles.
+pub const BUFFER_TYPE: i8 = 0;
+
+/// Extension type of window handles.
+pub const WINDOW_TYPE: i8 = 1;
+
+/// Extension type of tabpage handles.
+pub const TABPAGE_TYPE: i8 = 2;
+
+/// Returns a serializer config writing structs as maps keyed by field name and unit enum variants
+/// as their name, which is how Neovim expects dictionaries like the options of `nvim_open_win`.
+pub fn serializer_config() -> SerializerConfig {
+    SerializerConfig::new().with_struct_map().with_externally_tagged()
+}
+
+/// Returns a deserializer config for payloads sent by Neovim.
+///
+/// Buffer lines and other text are passed as strings whatever the encoding of the file they come
+/// from, so invalid UTF-8 sequences are replaced rather than failing the whole message.
+pub fn deserializer_config() -> DeserializerConfig {
+    DeserializerConfig::new().invalid_utf8(InvalidUtf8::Replace)
+}
+
+fn serialize_handle<S: Serializer>(ty: i8, handle: i64, se: S) -> Result<S::Ok, S::Error> {
+    let mut buf = Vec::with_capacity(9);
+    rmp::encode::write_sint(&mut buf, handle).map_err(|_| ser::Error::custom("write failed"))?;
+    Ext(ty, &buf).serialize(se)
+}
+
+fn deserialize_handle<'de, D>(ty: i8, name: &str, de: D) -> Result<i64, D::Error>
+    where D: Deserializer<'de>
+{
+    let ExtBuf(actual, data) = ExtBuf::deserialize(de)?;
+    if actual != ty {
+        return Err(de::Error::custom(format!("expected {} extension of type {}, got type {}",
+                                             name, ty, actual)));
+    }
+
+    let mut rd = &data[..];
+    match rmp::decode::read_int(&mut rd) {
+        Ok(handle) if rd.is_empty() => Ok(handle),
+        _ => Err(de::Error::custom(format!("invalid {} handle", name))),
+    }
+}
+
+macro_rules! handle {
+    ($(#[$attr:meta])* $name:ident, $ty:expr, $desc:expr) => {
+        $(#[$attr])*
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+        pub struct $name(pub i64);
+
+        impl Serialize for $name {
+            fn serialize<S: Serializer>(&self, se: S) -> Result<S::Ok, S::Error> {
+                serialize_handle($ty, self.0, se)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: Deserializer<'de>>(de: D) -> Result<Self, D::Error> {
+                deserialize_handle($ty, $desc, de).map($name)
+            }
+        }
+    };
+}
+
+handle!(
+    /// A handle to a Neovim buffer, written as an extension of type `BUFFER_TYPE`.
+    ///
+    /// The current buffer is `Buffer(0)` in API calls.
+    Buffer, BUFFER_TYPE, "buffer"
+);
+
+handle!(
+    /// A handle to a Neovim window, written as an extension of type `WINDOW_TYPE`.
+    ///
+    /// The current window is `Window(0)` in API calls.
+    Window, WINDOW_TYPE, "window"
+);
+
+handle!(
+    /// A handle to a Neovim tabpage, written as an extension of type `TABPAGE_TYPE`.
+    ///
+    /// The current tabpage is `Tabpage(0)` in API calls.
+    Tabpage, TABPAGE_TYPE, "tabpage"
+);
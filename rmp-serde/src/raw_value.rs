@@ -0,0 +1,152 @@
+//! A MessagePack value kept as its encoded bytes.
+//!
+//! Proxies and envelope protocols often forward a payload without looking into it. A `RawValue`
+//! field captures the exact bytes of one value while the rest of the message is deserialized,
+//! and splices them back verbatim when the message is serialized, so the payload is neither
+//! decoded nor re-encoded.
+//!
+//! Only the `Serializer` and `Deserializer` of this crate handle raw values: other serde formats
+//! see a newtype struct of bytes, and a raw value is read back from such a newtype struct as the
+//! bytes it wraps.
+//!
+//! # Examples
+//!
+//! ```
+//! extern crate serde;
+//! #[macro_use]
+//! extern crate serde_derive;
+//! extern crate rmp_serde;
+//!
+//! use rmp_serde::RawValue;
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Request {
+//!     method: String,
+//!     params: RawValue,
+//! }
+//!
+//! fn main() {
+//!     // ["add", [1, 2]]
+//!     let buf = [0x92, 0xa3, 0x61, 0x64, 0x64, 0x92, 0x01, 0x02];
+//!
+//!     let req: Request = rmp_serde::from_slice(&buf).unwrap();
+//!     assert_eq!("add", req.method);
+//!     assert_eq!(&[0x92, 0x01, 0x02], req.params.as_bytes());
+//!
+//!     let params: (u8, u8) = rmp_serde::from_slice(req.params.as_bytes()).unwrap();
+//!     assert_eq!((1, 2), params);
+//!
+//!     assert_eq!(&buf[..], &rmp_serde::to_vec(&req).unwrap()[..]);
+//! }
+//! ```
+
+use alloc::vec::Vec;
+use core::fmt::{self, Formatter};
+
+use rmp;
+use serde::de::{self, Deserialize, DeserializeSeed, Deserializer, SeqAccess, Visitor};
+use serde::ser::{Serialize, Serializer};
+
+use {decode, encode, RAW_VALUE_NAME};
+
+/// The encoded bytes of a single MessagePack value.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RawValue {
+    buf: Vec<u8>,
+}
+
+impl RawValue {
+    /// Takes the given bytes as a raw value, checking that they hold exactly one value.
+    ///
+    /// # Errors
+    ///
+    /// Returns `decode::Error::Syntax` if there are bytes left after the value, and the error of
+    /// reading it if it is truncated.
+    pub fn from_vec(buf: Vec<u8>) -> Result<Self, decode::Error> {
+        let mut rd = &buf[..];
+        let marker = rmp::decode::read_marker(&mut rd)?;
+        rmp::decode::skip_data(&mut rd, marker)?;
+        if !rd.is_empty() {
+            return Err(decode::Error::Syntax(format!("{} bytes after the value", rd.len())));
+        }
+
+        Ok(RawValue { buf })
+    }
+
+    /// Serializes the given value into a raw value.
+    pub fn from_value<T: ?Sized + Serialize>(val: &T) -> Result<Self, encode::Error> {
+        let mut buf = Vec::new();
+        val.serialize(&mut encode::Serializer::new(&mut buf))?;
+        Ok(RawValue { buf })
+    }
+
+    /// Returns the encoded bytes of the value.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf
+    }
+
+    /// Consumes the raw value, returning its encoded bytes.
+    pub fn into_vec(self) -> Vec<u8> {
+        self.buf
+    }
+}
+
+/// Data serialized as bytes rather than as a sequence.
+struct Bytes<'a>(&'a [u8]);
+
+impl<'a> Serialize for Bytes<'a> {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_bytes(self.0)
+    }
+}
+
+impl Serialize for RawValue {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_newtype_struct(RAW_VALUE_NAME, &Bytes(&self.buf))
+    }
+}
+
+struct RawValueVisitor;
+
+impl<'de> Visitor<'de> for RawValueVisitor {
+    type Value = RawValue;
+
+    fn expecting(&self, fmt: &mut Formatter) -> Result<(), fmt::Error> {
+        fmt.write_str("a MessagePack value")
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(self, de: D) -> Result<Self::Value, D::Error> {
+        DeserializeSeed::deserialize(self, de)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        match seq.next_element_seed(self)? {
+            Some(val) => Ok(val),
+            None => Err(de::Error::invalid_length(0, &"a newtype struct of bytes")),
+        }
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+        Ok(RawValue { buf: v.into() })
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<Self::Value, E> {
+        Ok(RawValue { buf: v })
+    }
+}
+
+/// Deserializes the bytes wrapped by the newtype struct, as formats that don't know raw values
+/// serialize it.
+impl<'de> DeserializeSeed<'de> for RawValueVisitor {
+    type Value = RawValue;
+
+    fn deserialize<D: Deserializer<'de>>(self, de: D) -> Result<RawValue, D::Error> {
+        de.deserialize_byte_buf(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for RawValue {
+    fn deserialize<D: Deserializer<'de>>(de: D) -> Result<Self, D::Error> {
+        de.deserialize_newtype_struct(RAW_VALUE_NAME, RawValueVisitor)
+    }
+}
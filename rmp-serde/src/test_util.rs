@@ -0,0 +1,253 @@
+//! Assertions for testing `Serialize` and `Deserialize` implementations against MessagePack.
+//!
+//! Available with the `test-util` feature, these helpers are meant for the test suites of crates
+//! with hand-written serde implementations or wire formats that have to stay stable. They panic
+//! with a hex dump of the offending encoding, so they can be used directly in `#[test]`
+//! functions.
+//!
+//! Encodings are compared in their canonical form, see `canonicalize`, so tests don't depend on
+//! the order of map entries or on an encoder picking a wider format than necessary. With the
+//! `with-serde` feature of `rmpv`, `rmpv::Value` can be passed wherever a type is expected.
+//!
+//! # Examples
+//!
+//! ```
+//! extern crate serde;
+//! #[macro_use]
+//! extern crate serde_derive;
+//! extern crate rmp_serde;
+//!
+//! use rmp_serde::test_util;
+//!
+//! #[derive(Debug, PartialEq, Serialize, Deserialize)]
+//! struct Point {
+//!     x: i32,
+//!     y: i32,
+//! }
+//!
+//! fn main() {
+//!     test_util::assert_roundtrip_corpus(vec![Point { x: 1, y: -1 }, Point { x: 0, y: 300 }]);
+//!
+//!     // [1, -1], with the first element written as a 16-bit integer.
+//!     test_util::assert_reencode_corpus::<Point>(&[&[0x92, 0xcd, 0x00, 0x01, 0xff]]);
+//! }
+//! ```
+
+use std::fmt::{Debug, Write as FmtWrite};
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use rmp::decode::{read_header, Header};
+use rmp::encode::{write_array_len, write_bin_len, write_bool, write_ext_meta, write_f32,
+                  write_f64, write_map_len, write_nil, write_sint, write_str_len, write_uint};
+
+use decode::{self, Deserializer};
+use encode;
+
+/// Serializes the value, deserializes it back and asserts that the result is equal to it,
+/// returning the encoding.
+///
+/// Both the compact encoding of `to_vec` and the one with named struct fields of `to_vec_named`
+/// are checked. The compact one is returned.
+///
+/// # Panics
+///
+/// Panics if serialization or deserialization fails, or if the value changes on the way.
+pub fn assert_roundtrip<T>(val: &T) -> Vec<u8>
+    where T: Serialize + DeserializeOwned + PartialEq + Debug
+{
+    roundtrip(val).unwrap_or_else(|msg| panic!("{}", msg))
+}
+
+/// Runs `assert_roundtrip` on every value, reporting the position of the first failing one.
+///
+/// # Panics
+///
+/// Panics if any value doesn't survive the round trip.
+pub fn assert_roundtrip_corpus<T, I>(vals: I)
+    where T: Serialize + DeserializeOwned + PartialEq + Debug,
+          I: IntoIterator<Item = T>
+{
+    for (idx, val) in vals.into_iter().enumerate() {
+        if let Err(msg) = roundtrip(&val) {
+            panic!("corpus value #{}: {}", idx, msg);
+        }
+    }
+}
+
+fn roundtrip<T>(val: &T) -> Result<Vec<u8>, String>
+    where T: Serialize + DeserializeOwned + PartialEq + Debug
+{
+    let named = encode::to_vec_named(val)
+        .map_err(|err| format!("failed to serialize {:?} with named fields: {}", val, err))?;
+    check_decoded(val, &named)?;
+
+    let buf = encode::to_vec(val)
+        .map_err(|err| format!("failed to serialize {:?}: {}", val, err))?;
+    check_decoded(val, &buf)?;
+
+    Ok(buf)
+}
+
+fn check_decoded<T>(val: &T, buf: &[u8]) -> Result<(), String>
+    where T: DeserializeOwned + PartialEq + Debug
+{
+    let decoded: T = decode(buf)
+        .map_err(|err| format!("failed to deserialize {:?} from {}: {}", val, hex(buf), err))?;
+    if decoded != *val {
+        return Err(format!("{:?} was deserialized as {:?} from {}", val, decoded, hex(buf)));
+    }
+
+    Ok(())
+}
+
+/// Asserts that every encoded sample deserializes as `T`, and that serializing the result gives
+/// back the sample, up to canonicalization.
+///
+/// This checks that a type accepts data written by other implementations, for example a corpus
+/// captured from a peer, and loses nothing from it. A sample passes if it matches either the
+/// compact or the named encoding of the deserialized value.
+///
+/// # Panics
+///
+/// Panics with the position of the first sample that fails to deserialize, to serialize or to
+/// match the original.
+pub fn assert_reencode_corpus<T>(corpus: &[&[u8]])
+    where T: Serialize + DeserializeOwned + Debug
+{
+    for (idx, sample) in corpus.iter().enumerate() {
+        let val: T = decode(sample).unwrap_or_else(|err| {
+            panic!("failed to deserialize corpus sample #{} {}: {}", idx, hex(sample), err)
+        });
+
+        let expected = canonical_or_panic(sample);
+        let buf = encode::to_vec(&val)
+            .unwrap_or_else(|err| panic!("failed to serialize {:?}: {}", val, err));
+        let named = encode::to_vec_named(&val)
+            .unwrap_or_else(|err| panic!("failed to serialize {:?}: {}", val, err));
+
+        if canonical_or_panic(&buf) != expected && canonical_or_panic(&named) != expected {
+            panic!("corpus sample #{} {} was deserialized as {:?}, which is serialized as {}",
+                   idx, hex(sample), val, hex(&buf));
+        }
+    }
+}
+
+/// Asserts that two buffers hold the same MessagePack value, ignoring differences that don't
+/// change its meaning.
+///
+/// # Panics
+///
+/// Panics if either buffer isn't a single valid value or if their canonical forms differ.
+pub fn assert_bytes_eq_canonical(expected: &[u8], actual: &[u8]) {
+    if canonical_or_panic(expected) != canonical_or_panic(actual) {
+        panic!("encodings differ\n  expected: {}\n    actual: {}", hex(expected), hex(actual));
+    }
+}
+
+/// Re-encodes a single value in canonical form.
+///
+/// The canonical form uses the smallest format of every integer, string, binary, extension,
+/// array and map header, with non-negative integers always written as unsigned, and sorts map
+/// entries by the canonical encoding of their keys. Floats keep their width.
+///
+/// # Errors
+///
+/// Fails if the buffer is not exactly one valid value.
+pub fn canonicalize(buf: &[u8]) -> Result<Vec<u8>, decode::Error> {
+    let mut rd = buf;
+    let mut out = Vec::with_capacity(buf.len());
+    write_canonical(&mut rd, &mut out)?;
+
+    if !rd.is_empty() {
+        return Err(decode::Error::Syntax(format!("{} trailing bytes", rd.len())));
+    }
+
+    Ok(out)
+}
+
+fn write_canonical(rd: &mut &[u8], out: &mut Vec<u8>) -> Result<(), decode::Error> {
+    // Writing into a vector can't fail.
+    match read_header(rd)? {
+        Header::Nil => write_nil(out).unwrap(),
+        Header::Bool(val) => write_bool(out, val).unwrap(),
+        Header::UInt(val) => {
+            write_uint(out, val).unwrap();
+        }
+        Header::SInt(val) => {
+            write_sint(out, val).unwrap();
+        }
+        Header::F32(val) => write_f32(out, val).unwrap(),
+        Header::F64(val) => write_f64(out, val).unwrap(),
+        Header::Str(len) => {
+            write_str_len(out, len).unwrap();
+            out.extend_from_slice(take(rd, len)?);
+        }
+        Header::Bin(len) => {
+            write_bin_len(out, len).unwrap();
+            out.extend_from_slice(take(rd, len)?);
+        }
+        Header::Ext(ty, len) => {
+            write_ext_meta(out, len, ty).unwrap();
+            out.extend_from_slice(take(rd, len)?);
+        }
+        Header::Array(len) => {
+            write_array_len(out, len).unwrap();
+            for _ in 0..len {
+                write_canonical(rd, out)?;
+            }
+        }
+        Header::Map(len) => {
+            let mut entries = Vec::new();
+            for _ in 0..len {
+                let mut key = Vec::new();
+                write_canonical(rd, &mut key)?;
+                let mut val = Vec::new();
+                write_canonical(rd, &mut val)?;
+                entries.push((key, val));
+            }
+            entries.sort();
+
+            write_map_len(out, len).unwrap();
+            for (key, val) in entries {
+                out.extend_from_slice(&key);
+                out.extend_from_slice(&val);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn take<'a>(rd: &mut &'a [u8], len: u32) -> Result<&'a [u8], decode::Error> {
+    let len = len as usize;
+    if rd.len() < len {
+        return Err(decode::Error::LengthMismatch(len as u32));
+    }
+
+    let (head, tail) = rd.split_at(len);
+    *rd = tail;
+    Ok(head)
+}
+
+fn canonical_or_panic(buf: &[u8]) -> Vec<u8> {
+    canonicalize(buf).unwrap_or_else(|err| panic!("invalid encoding {}: {}", hex(buf), err))
+}
+
+fn decode<T: DeserializeOwned>(buf: &[u8]) -> Result<T, decode::Error> {
+    serde::Deserialize::deserialize(&mut Deserializer::from_slice(buf))
+}
+
+fn hex(buf: &[u8]) -> String {
+    let mut out = String::with_capacity(2 + 3 * buf.len());
+    out.push('[');
+    for (idx, byte) in buf.iter().enumerate() {
+        if idx > 0 {
+            out.push(' ');
+        }
+        write!(out, "{:02x}", byte).unwrap();
+    }
+    out.push(']');
+    out
+}
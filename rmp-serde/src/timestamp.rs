@@ -0,0 +1,241 @@
+//! Serialization of `SystemTime` and `Duration` as the MessagePack timestamp extension.
+//!
+//! Serde serializes these types as structs of seconds and nanoseconds, which other MessagePack
+//! implementations don't recognize as time. The functions here are meant for the
+//! `#[serde(with = "...")]` attribute and write the standard timestamp extension of type `-1`
+//! instead, using the most compact of its 32, 64 and 96-bit formats, without any date-time
+//! library.
+//!
+//! `SystemTime` is encoded as the time since the Unix epoch. Times before the epoch are written
+//! as negative seconds in the 96-bit format. The `duration` module encodes a `Duration` as a
+//! timestamp the same time after the epoch, so it can be decoded by any peer that knows the
+//! extension.
+//!
+//! With other serde formats the values are written as a newtype struct of bytes holding the
+//! extension data.
+//!
+//! The `option` module handles `Option<SystemTime>` fields. Time types of date-time libraries can
+//! be written as timestamps too, by implementing their serialization with `serialize_parts` and
+//! `deserialize_parts`.
+//!
+//! # Examples
+//!
+//! ```
+//! extern crate serde;
+//! #[macro_use]
+//! extern crate serde_derive;
+//! extern crate rmp_serde;
+//!
+//! use std::time::{Duration, SystemTime, UNIX_EPOCH};
+//!
+//! #[derive(Debug, PartialEq, Serialize, Deserialize)]
+//! struct Event {
+//!     #[serde(with = "rmp_serde::timestamp")]
+//!     at: SystemTime,
+//!     #[serde(with = "rmp_serde::timestamp::duration")]
+//!     took: Duration,
+//! }
+//!
+//! fn main() {
+//!     let event = Event {
+//!         at: UNIX_EPOCH + Duration::from_secs(1500000000),
+//!         took: Duration::new(2, 500),
+//!     };
+//!
+//!     let buf = rmp_serde::to_vec(&event).unwrap();
+//!     assert_eq!([0x92, 0xd6, 0xff, 0x59, 0x68, 0x2f, 0x00], buf[..7]);
+//!
+//!     assert_eq!(event, rmp_serde::from_slice(&buf).unwrap());
+//! }
+//! ```
+
+use core::fmt::{self, Formatter};
+#[cfg(feature = "std")]
+use std::time::SystemTime;
+
+use rmp::timestamp::Timestamp;
+use serde::{de, ser, Deserializer, Serialize, Serializer};
+
+use TIMESTAMP_NAME;
+
+/// Serializes a `SystemTime` as a timestamp extension.
+///
+/// # Errors
+///
+/// Fails if the time is more than `i64::MAX` seconds away from the epoch.
+#[cfg(feature = "std")]
+pub fn serialize<S: Serializer>(time: &SystemTime, se: S) -> Result<S::Ok, S::Error> {
+    match Timestamp::from_system_time(*time) {
+        Some(ts) => write(se, ts),
+        None => Err(ser::Error::custom("timestamp out of range")),
+    }
+}
+
+/// Deserializes a `SystemTime` from a timestamp extension in any of its formats.
+///
+/// # Errors
+///
+/// Fails if the value is not a timestamp extension, if its nanoseconds are out of range or if the
+/// time can't be represented by the platform.
+#[cfg(feature = "std")]
+pub fn deserialize<'de, D: Deserializer<'de>>(de: D) -> Result<SystemTime, D::Error> {
+    read(de)?.to_system_time().ok_or_else(|| de::Error::custom("timestamp out of range"))
+}
+
+/// Serialization of `Option<SystemTime>` as either nil or a timestamp extension.
+///
+/// Serde's `with` attribute applies to the whole field type, so optional times need their own
+/// functions.
+#[cfg(feature = "std")]
+pub mod option {
+    use core::fmt::{self, Formatter};
+    use std::time::SystemTime;
+
+    use serde::{de, Deserializer, Serializer};
+
+    /// Serializes `None` as nil and a time as a timestamp extension.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the time is more than `i64::MAX` seconds away from the epoch.
+    pub fn serialize<S: Serializer>(time: &Option<SystemTime>, se: S) -> Result<S::Ok, S::Error> {
+        match *time {
+            Some(ref time) => super::serialize(time, se),
+            None => se.serialize_none(),
+        }
+    }
+
+    /// Deserializes nil as `None` and a timestamp extension in any of its formats as a time.
+    ///
+    /// # Errors
+    ///
+    /// Fails under the same conditions as `timestamp::deserialize`, for values other than nil.
+    pub fn deserialize<'de, D>(de: D) -> Result<Option<SystemTime>, D::Error>
+        where D: Deserializer<'de>
+    {
+        de.deserialize_option(OptionVisitor)
+    }
+
+    struct OptionVisitor;
+
+    impl<'de> de::Visitor<'de> for OptionVisitor {
+        type Value = Option<SystemTime>;
+
+        fn expecting(&self, fmt: &mut Formatter) -> fmt::Result {
+            fmt.write_str("nil or a timestamp extension")
+        }
+
+        fn visit_none<E: de::Error>(self) -> Result<Self::Value, E> {
+            Ok(None)
+        }
+
+        fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+            Ok(None)
+        }
+
+        fn visit_some<D: Deserializer<'de>>(self, de: D) -> Result<Self::Value, D::Error> {
+            super::deserialize(de).map(Some)
+        }
+    }
+}
+
+/// Serialization of `Duration` as a timestamp extension the given time after the epoch.
+pub mod duration {
+    use core::time::Duration;
+
+    use rmp::timestamp::Timestamp;
+    use serde::{de, ser, Deserializer, Serializer};
+
+    /// Serializes a `Duration` as a timestamp extension.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the duration is longer than `i64::MAX` seconds.
+    pub fn serialize<S: Serializer>(dur: &Duration, se: S) -> Result<S::Ok, S::Error> {
+        match Timestamp::from_duration(*dur) {
+            Some(ts) => super::write(se, ts),
+            None => Err(ser::Error::custom("duration out of range")),
+        }
+    }
+
+    /// Deserializes a `Duration` from a timestamp extension in any of its formats.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the value is not a timestamp extension, if its nanoseconds are out of range or if
+    /// it is before the epoch.
+    pub fn deserialize<'de, D: Deserializer<'de>>(de: D) -> Result<Duration, D::Error> {
+        super::read(de)?.to_duration().ok_or_else(|| de::Error::custom("negative duration"))
+    }
+}
+
+/// Serializes seconds since the epoch and nanoseconds after them as a timestamp extension.
+///
+/// This is the building block of the other functions of this module. Time types of other crates,
+/// like `chrono::DateTime<Utc>` or `time::OffsetDateTime`, can be written as timestamps by
+/// splitting them into a Unix timestamp and its subsecond nanoseconds and passing those here.
+///
+/// # Errors
+///
+/// Fails if the nanoseconds are not less than one second.
+pub fn serialize_parts<S: Serializer>(secs: i64, nanos: u32, se: S) -> Result<S::Ok, S::Error> {
+    match Timestamp::new(secs, nanos) {
+        Some(ts) => write(se, ts),
+        None => Err(ser::Error::custom("timestamp nanoseconds out of range")),
+    }
+}
+
+/// Deserializes a timestamp extension in any of its formats into seconds since the epoch and
+/// nanoseconds after them.
+///
+/// This is the counterpart of `serialize_parts`. Nanoseconds are always counted forward, so times
+/// before the epoch have them added to the negative seconds.
+///
+/// # Errors
+///
+/// Fails if the value is not a timestamp extension or if its nanoseconds are out of range.
+pub fn deserialize_parts<'de, D: Deserializer<'de>>(de: D) -> Result<(i64, u32), D::Error> {
+    let ts = read(de)?;
+    Ok((ts.secs(), ts.nanos()))
+}
+
+/// Extension data of a timestamp, which is serialized as bytes.
+struct Data<'a>(&'a [u8]);
+
+impl<'a> Serialize for Data<'a> {
+    fn serialize<S: Serializer>(&self, se: S) -> Result<S::Ok, S::Error> {
+        se.serialize_bytes(self.0)
+    }
+}
+
+fn write<S: Serializer>(se: S, ts: Timestamp) -> Result<S::Ok, S::Error> {
+    se.serialize_newtype_struct(TIMESTAMP_NAME, &Data(ts.encode(&mut [0; 12])))
+}
+
+fn read<'de, D: Deserializer<'de>>(de: D) -> Result<Timestamp, D::Error> {
+    de.deserialize_newtype_struct(TIMESTAMP_NAME, TimestampVisitor)
+}
+
+struct TimestampVisitor;
+
+impl<'de> de::Visitor<'de> for TimestampVisitor {
+    type Value = Timestamp;
+
+    fn expecting(&self, fmt: &mut Formatter) -> fmt::Result {
+        fmt.write_str("a timestamp extension")
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(self, de: D) -> Result<Self::Value, D::Error> {
+        de.deserialize_bytes(self)
+    }
+
+    fn visit_bytes<E: de::Error>(self, buf: &[u8]) -> Result<Self::Value, E> {
+        match buf.len() {
+            4 | 8 | 12 => {}
+            len => return Err(de::Error::invalid_length(len, &self)),
+        }
+
+        Timestamp::from_ext_data(buf)
+            .ok_or_else(|| de::Error::custom("timestamp nanoseconds out of range"))
+    }
+}
@@ -10,7 +10,7 @@ use serde::de;
 use serde::Deserialize;
 
 use rmp::Marker;
-use rmps::{Deserializer, Raw, RawRef};
+use rmps::{Deserializer, Raw, RawRef, StreamDeserializer};
 use rmps::decode::{self, Error};
 
 #[test]
@@ -507,3 +507,563 @@ fn fail_str_invalid_utf8() {
         err => panic!("unexpected error: {:?}", err),
     }
 }
+
+#[test]
+fn fail_depth_limit_exceeded() {
+    let buf = [0x91, 0x91, 0x91, 0x01];
+
+    let mut de = Deserializer::new(&buf[..]);
+    de.set_max_depth(2);
+
+    match Vec::<Vec<Vec<u8>>>::deserialize(&mut de) {
+        Err(Error::DepthLimitExceeded) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    let mut de = Deserializer::new(&buf[..]);
+    de.set_max_depth(3);
+    assert_eq!(vec![vec![vec![1]]], Vec::<Vec<Vec<u8>>>::deserialize(&mut de).unwrap());
+}
+
+#[test]
+fn fail_bytes_limit_exceeded_before_allocating() {
+    // A binary claiming 4 GiB, followed by nothing.
+    let buf = [0xc6, 0xff, 0xff, 0xff, 0xff];
+
+    let mut de = Deserializer::new(&buf[..]);
+    match serde_bytes::ByteBuf::deserialize(&mut de) {
+        Err(Error::LengthLimitExceeded(0xffffffff)) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    let buf = [0xa5, 0x68, 0x65, 0x6c, 0x6c, 0x6f];
+
+    let mut de = Deserializer::new(&buf[..]);
+    de.set_max_bytes(4);
+    match String::deserialize(&mut de) {
+        Err(Error::LengthLimitExceeded(5)) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    let mut de = Deserializer::new(&buf[..]);
+    de.set_max_bytes(5);
+    assert_eq!("hello", String::deserialize(&mut de).unwrap());
+}
+
+#[test]
+fn fail_seq_len_limit_exceeded() {
+    let buf = [0x93, 0x01, 0x02, 0x03];
+
+    let mut de = Deserializer::from_slice(&buf[..]);
+    de.set_max_seq_len(2);
+    match Vec::<u8>::deserialize(&mut de) {
+        Err(Error::LengthLimitExceeded(3)) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    // {1: 2, 3: 4}
+    let buf = [0x82, 0x01, 0x02, 0x03, 0x04];
+
+    let mut de = Deserializer::from_slice(&buf[..]);
+    de.set_max_seq_len(1);
+    match std::collections::BTreeMap::<u8, u8>::deserialize(&mut de) {
+        Err(Error::LengthLimitExceeded(2)) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn pass_duplicate_keys_policy() {
+    use std::collections::BTreeMap;
+    use rmps::decode::{DeserializerConfig, DuplicateKeys};
+
+    // {"a": 1, "b": 2, "a": 3}, with the second "a" encoded as str8 and 3 as u16.
+    let buf = [0x83, 0xa1, 0x61, 0x01, 0xa1, 0x62, 0x02, 0xd9, 0x01, 0x61, 0xcd, 0x00, 0x03];
+
+    let config = DeserializerConfig::new().duplicate_keys(DuplicateKeys::FirstWins);
+    let map: BTreeMap<String, u8> = rmps::from_slice_with(&buf, &config).unwrap();
+    assert_eq!(Some(&1), map.get("a"));
+    assert_eq!(Some(&2), map.get("b"));
+
+    let mut de = Deserializer::new(&buf[..]);
+    de.set_duplicate_keys(DuplicateKeys::LastWins);
+    let map = BTreeMap::<String, u8>::deserialize(&mut de).unwrap();
+    assert_eq!(Some(&3), map.get("a"));
+    assert_eq!(Some(&2), map.get("b"));
+}
+
+#[test]
+fn fail_duplicate_keys_rejected() {
+    use std::collections::BTreeMap;
+    use rmps::decode::DuplicateKeys;
+
+    // [{}, {1: 2, 1: 3}]
+    let buf = [0x92, 0x80, 0x82, 0x01, 0x02, 0x01, 0x03];
+
+    let mut de = Deserializer::from_slice(&buf[..]);
+    de.set_duplicate_keys(DuplicateKeys::Reject);
+    match Vec::<BTreeMap<u8, u8>>::deserialize(&mut de) {
+        Err(Error::DuplicateKey) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    // A map nested in a value of a map is checked too: {1: {2: 3, 2: 4}}.
+    let buf = [0x81, 0x01, 0x82, 0x02, 0x03, 0x02, 0x04];
+
+    let mut de = Deserializer::from_slice(&buf[..]);
+    de.set_duplicate_keys(DuplicateKeys::Reject);
+    match BTreeMap::<u8, BTreeMap<u8, u8>>::deserialize(&mut de) {
+        Err(Error::DuplicateKey) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn pass_partial_deserializer_byte_by_byte() {
+    use rmps::decode::PartialDeserializer;
+
+    // [1, "ab"] and {"k": nil}, back to back.
+    let buf = [0x92, 0x01, 0xa2, 0x61, 0x62, 0x81, 0xa1, 0x6b, 0xc0];
+
+    let mut de = PartialDeserializer::new();
+    let mut tuples = Vec::new();
+    for (idx, &byte) in buf[..5].iter().enumerate() {
+        de.feed(&[byte]);
+        match de.next_value::<(u8, String)>() {
+            Ok(val) => tuples.push(val),
+            Err(Error::NeedMoreData(..)) => assert!(idx < 4),
+            Err(err) => panic!("unexpected error: {:?}", err),
+        }
+    }
+    assert_eq!(vec![(1, "ab".to_string())], tuples);
+    assert_eq!(0, de.buffered());
+
+    de.feed(&buf[5..]);
+    let map: std::collections::BTreeMap<String, ()> = de.next_value().unwrap();
+    assert_eq!(Some(&()), map.get("k"));
+
+    match de.next_value::<u8>() {
+        Err(Error::NeedMoreData(1)) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn fail_partial_deserializer_skips_mismatched_value() {
+    use rmps::decode::{DeserializerConfig, PartialDeserializer};
+
+    let mut de = PartialDeserializer::new();
+    de.feed(&[0xa1, 0x61, 0x2a]);
+
+    match de.next_value::<u8>() {
+        Err(Error::Syntax(..)) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+    assert_eq!(42u8, de.next_value().unwrap());
+
+    // A binary claiming 4 GiB is rejected before waiting for it.
+    let mut de = PartialDeserializer::new();
+    de.set_config(&DeserializerConfig::new().max_bytes(1024));
+    de.feed(&[0xc6, 0xff, 0xff, 0xff, 0xff]);
+    match de.next_value::<serde_bytes::ByteBuf>() {
+        Err(Error::LengthLimitExceeded(..)) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn pass_unbounded_config() {
+    use rmps::decode::DeserializerConfig;
+
+    let config = DeserializerConfig::new();
+    assert_eq!(1024, config.get_max_depth());
+    assert_eq!(1 << 26, config.get_max_bytes());
+    assert_eq!(1 << 24, config.get_max_seq_len());
+
+    let config = DeserializerConfig::unbounded();
+    assert_eq!(usize::MAX, config.get_max_depth());
+    assert_eq!(usize::MAX, config.get_max_bytes());
+    assert_eq!(usize::MAX, config.get_max_seq_len());
+
+    // A binary claiming 4 GiB, which a slice can only fail to provide.
+    let buf = [0xc6, 0xff, 0xff, 0xff, 0xff];
+    let mut de = Deserializer::from_slice(&buf[..]);
+    de.set_config(&config);
+    match serde_bytes::ByteBuf::deserialize(&mut de) {
+        Err(Error::InvalidDataRead(..)) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn pass_config_shared_between_threads() {
+    use std::sync::Arc;
+    use std::thread;
+    use rmps::decode::DeserializerConfig;
+
+    let config = Arc::new(DeserializerConfig::new().max_depth(1));
+
+    let threads: Vec<_> = (0..4u8).map(|idx| {
+        let config = config.clone();
+        thread::spawn(move || {
+            let buf = [0x92, idx, 0x2a];
+            let val: Vec<u8> = rmps::from_slice_with(&buf, &config).unwrap();
+            assert_eq!(vec![idx, 42], val);
+        })
+    }).collect();
+
+    for thread in threads {
+        thread.join().unwrap();
+    }
+}
+
+#[test]
+fn fail_str_invalid_utf8_strict() {
+    use rmps::decode::DeserializerConfig;
+
+    let buf = [0xa2, 0xc3, 0x28];
+
+    let config = DeserializerConfig::new().strict_utf8(true);
+    let mut de = Deserializer::from_slice(&buf[..]);
+    de.set_config(&config);
+    assert!(de.config().is_strict_utf8());
+
+    match RawRef::deserialize(&mut de) {
+        Err(Error::Utf8Error(..)) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    let raw: RawRef = rmps::from_slice(&buf).unwrap();
+    assert!(raw.as_str().is_none());
+}
+
+#[test]
+fn pass_lenient_bool_from_ints() {
+    use rmps::decode::DeserializerConfig;
+
+    let config = DeserializerConfig::new().lenient_bool(true);
+
+    assert!(!rmps::from_slice_with::<bool>(&[0x00], &config).unwrap());
+    assert!(rmps::from_slice_with::<bool>(&[0x01], &config).unwrap());
+    assert!(rmps::from_slice_with::<bool>(&[0xcd, 0x00, 0x01], &config).unwrap());
+    assert!(!rmps::from_slice_with::<bool>(&[0xd3, 0, 0, 0, 0, 0, 0, 0, 0], &config).unwrap());
+    assert!(rmps::from_slice_with::<bool>(&[0xc3], &config).unwrap());
+
+    let (flag, num): (bool, u8) = rmps::from_slice_with(&[0x92, 0x01, 0x01], &config).unwrap();
+    assert_eq!((true, 1), (flag, num));
+}
+
+#[test]
+fn fail_lenient_bool_from_other_values() {
+    use rmps::decode::DeserializerConfig;
+
+    let config = DeserializerConfig::new().lenient_bool(true);
+
+    match rmps::from_slice_with::<bool>(&[0x02], &config) {
+        Err(Error::OutOfRange) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+    match rmps::from_slice_with::<bool>(&[0xff], &config) {
+        Err(Error::OutOfRange) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+    match rmps::from_slice_with::<bool>(&[0xa1, 0x31], &config) {
+        Err(Error::Syntax(..)) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn fail_bool_from_int_by_default() {
+    assert!(rmps::from_slice::<bool>(&[0x01]).is_err());
+}
+
+#[test]
+fn pass_large_string_and_bytebuf_from_read_and_slice() {
+    use serde_bytes::ByteBuf;
+
+    let text = "le message ".repeat(10000);
+    let data = (0..100000u32).map(|n| n as u8).collect::<Vec<u8>>();
+
+    let mut buf = vec![0x92, 0xdb, 0x00, 0x01, 0xad, 0xb0];
+    buf.extend_from_slice(text.as_bytes());
+    buf.extend_from_slice(&[0xc6, 0x00, 0x01, 0x86, 0xa0]);
+    buf.extend_from_slice(&data);
+
+    let (s, bin): (String, ByteBuf) = rmps::from_read(&buf[..]).unwrap();
+    assert_eq!(text, s);
+    assert_eq!(data, Vec::from(bin));
+
+    let (s, bin): (String, ByteBuf) = rmps::from_slice(&buf).unwrap();
+    assert_eq!(text, s);
+    assert_eq!(data, Vec::from(bin));
+}
+
+#[test]
+fn pass_bytebuf_from_str_and_array() {
+    use serde_bytes::ByteBuf;
+
+    let buf = [0x92, 0xa2, 0x61, 0x62, 0x92, 0x01, 0xcc, 0xff];
+
+    let (s, arr): (ByteBuf, ByteBuf) = rmps::from_read(&buf[..]).unwrap();
+    assert_eq!(b"ab".to_vec(), Vec::from(s));
+    assert_eq!(vec![1, 0xff], Vec::from(arr));
+}
+
+#[test]
+fn fail_string_truncated() {
+    let buf = [0xa4, 0x61, 0x62];
+
+    match rmps::from_read::<_, String>(&buf[..]) {
+        Err(Error::InvalidDataRead(..)) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn fail_string_invalid_utf8_from_read() {
+    let buf = [0xa2, 0xc3, 0x28];
+
+    match rmps::from_read::<_, String>(&buf[..]) {
+        Err(Error::Utf8Error(err)) => assert_eq!(0, err.valid_up_to()),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn pass_str_invalid_utf8_replace() {
+    use rmps::decode::{DeserializerConfig, InvalidUtf8};
+
+    // "a\xc3(" twice, from a slice and from a reader.
+    let buf = [0xa3, 0x61, 0xc3, 0x28];
+
+    let config = DeserializerConfig::new().invalid_utf8(InvalidUtf8::Replace);
+    assert!(!config.is_strict_utf8());
+    assert_eq!("a\u{fffd}(", rmps::from_slice_with::<String>(&buf, &config).unwrap());
+    assert_eq!("a\u{fffd}(", rmps::from_read_with::<_, String>(&buf[..], &config).unwrap());
+
+    let mut de = Deserializer::from_slice(&buf[..]);
+    de.set_invalid_utf8(InvalidUtf8::Replace);
+    let raw = Raw::deserialize(&mut de).unwrap();
+    assert_eq!(Some("a\u{fffd}("), raw.as_str());
+}
+
+#[test]
+fn pass_str_invalid_utf8_into_bytes() {
+    use rmps::decode::{DeserializerConfig, InvalidUtf8};
+    use serde_bytes::ByteBuf;
+
+    let buf = [0xa2, 0xc3, 0x28];
+    assert_eq!(InvalidUtf8::Bytes, DeserializerConfig::new().get_invalid_utf8());
+
+    let out: Vec<u8> = rmps::from_slice(&buf).unwrap();
+    assert_eq!(vec![0xc3, 0x28], out);
+    let out: Vec<u8> = rmps::from_read(&buf[..]).unwrap();
+    assert_eq!(vec![0xc3, 0x28], out);
+    let out: ByteBuf = rmps::from_slice(&buf).unwrap();
+    assert_eq!(vec![0xc3, 0x28], out.to_vec());
+
+    // Valid strings are still text.
+    assert!(rmps::from_slice::<Vec<u8>>(&[0xa1, 0x61]).is_err());
+
+    let config = DeserializerConfig::new().strict_utf8(true);
+    assert_eq!(InvalidUtf8::Reject, config.get_invalid_utf8());
+    match rmps::from_slice_with::<Vec<u8>>(&buf, &config) {
+        Err(Error::Utf8Error(..)) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn pass_bytes_borrowed_from_slice() {
+    let buf = [0x92, 0xc4, 0x02, 0xcc, 0x80, 0xa2, 0x6c, 0x65];
+    let (bin, s): (&[u8], &str) = rmps::from_slice(&buf).unwrap();
+
+    assert_eq!([0xcc, 0x80], bin);
+    assert_eq!(buf[3..5].as_ptr(), bin.as_ptr());
+    assert_eq!("le", s);
+
+    // Data copied out of a reader can't be borrowed.
+    let mut de = Deserializer::new(&buf[..]);
+    assert!(<(&[u8], &str)>::deserialize(&mut de).is_err());
+}
+
+/// An extension borrowing its data, deserialized through `MSGPACK_EXT_STRUCT_NAME`.
+#[derive(Debug, PartialEq)]
+struct Ext<'a>(i8, &'a [u8]);
+
+impl<'de> Deserialize<'de> for Ext<'de> {
+    fn deserialize<D: de::Deserializer<'de>>(de: D) -> Result<Self, D::Error> {
+        struct ExtVisitor;
+
+        impl<'de> de::Visitor<'de> for ExtVisitor {
+            type Value = Ext<'de>;
+
+            fn expecting(&self, fmt: &mut Formatter) -> Result<(), fmt::Error> {
+                fmt.write_str("an extension")
+            }
+
+            fn visit_newtype_struct<D>(self, de: D) -> Result<Self::Value, D::Error>
+                where D: de::Deserializer<'de>
+            {
+                let (ty, data) = <(i8, &[u8])>::deserialize(de)?;
+                Ok(Ext(ty, data))
+            }
+        }
+
+        de.deserialize_newtype_struct(rmps::MSGPACK_EXT_STRUCT_NAME, ExtVisitor)
+    }
+}
+
+#[test]
+fn pass_ext_borrowed_from_slice() {
+    let buf = [0xd5, 0x05, 0x01, 0x02];
+    let ext: Ext = rmps::from_slice(&buf).unwrap();
+
+    assert_eq!(Ext(5, &[0x01, 0x02]), ext);
+    assert_eq!(buf[2..].as_ptr(), ext.1.as_ptr());
+
+    let buf = [0xc8, 0x00, 0x01, 0xfe, 0x2a];
+    assert_eq!(Ext(-2, &[0x2a]), rmps::from_slice(&buf).unwrap());
+}
+
+#[test]
+fn pass_ext_copied_from_read() {
+    use serde_bytes::ByteBuf;
+
+    struct OwnedExt(i8, ByteBuf);
+
+    impl<'de> Deserialize<'de> for OwnedExt {
+        fn deserialize<D: de::Deserializer<'de>>(de: D) -> Result<Self, D::Error> {
+            struct ExtVisitor;
+
+            impl<'de> de::Visitor<'de> for ExtVisitor {
+                type Value = OwnedExt;
+
+                fn expecting(&self, fmt: &mut Formatter) -> Result<(), fmt::Error> {
+                    fmt.write_str("an extension")
+                }
+
+                fn visit_newtype_struct<D>(self, de: D) -> Result<Self::Value, D::Error>
+                    where D: de::Deserializer<'de>
+                {
+                    let (ty, data) = <(i8, ByteBuf)>::deserialize(de)?;
+                    Ok(OwnedExt(ty, data))
+                }
+            }
+
+            de.deserialize_newtype_struct(rmps::MSGPACK_EXT_STRUCT_NAME, ExtVisitor)
+        }
+    }
+
+    // The extension followed by nil, which must be left in place.
+    let buf = [0xd4, 0x05, 0x01, 0xc0];
+    let mut de = Deserializer::new(&buf[..]);
+    let ext = OwnedExt::deserialize(&mut de).unwrap();
+    assert_eq!((5, vec![0x01]), (ext.0, Vec::from(ext.1)));
+    assert_eq!(&[0xc0], de.get_ref());
+}
+
+#[test]
+fn fail_ext_from_other_values() {
+    match rmps::from_slice::<Ext>(&[0xc4, 0x01, 0x00]) {
+        Err(Error::TypeMismatch(Marker::Bin8)) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    match rmps::from_slice::<Ext>(&[0xd5, 0x05, 0x01]) {
+        Err(Error::InvalidDataRead(..)) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn pass_stream_from_read() {
+    // 1, nil, 300 and [2, 3], back to back.
+    let buf = [0x01, 0xc0, 0xcd, 0x01, 0x2c, 0x92, 0x02, 0x03];
+
+    let de = Deserializer::new(Cursor::new(&buf[..5]));
+    let stream = StreamDeserializer::<_, Option<u16>>::new(de);
+    assert_eq!(vec![Some(1), None, Some(300)], stream.map(Result::unwrap).collect::<Vec<_>>());
+
+    let stream = StreamDeserializer::<_, Option<u16>>::new(Deserializer::new(&buf[..]));
+    let vals = stream.collect::<Vec<_>>();
+    assert_eq!(4, vals.len());
+    match vals[3] {
+        Err(Error::Syntax(..)) => {}
+        ref other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn pass_stream_empty() {
+    let mut stream = StreamDeserializer::<_, u8>::new(Deserializer::from_slice(&[]));
+    assert!(stream.next().is_none());
+}
+
+#[test]
+fn fail_stream_truncated() {
+    let buf = [0x01, 0xcd, 0x01];
+    let mut stream = StreamDeserializer::<_, u16>::new(Deserializer::new(&buf[..]));
+
+    assert_eq!(1, stream.next().unwrap().unwrap());
+    match stream.next() {
+        Some(Err(Error::InvalidDataRead(..))) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+    assert!(stream.next().is_none());
+}
+
+#[test]
+fn pass_stream_keeps_position() {
+    let buf = [0x01, 0x02, 0x03];
+    let mut stream = StreamDeserializer::<_, u8>::new(Deserializer::from_slice(&buf));
+
+    assert_eq!(1, stream.next().unwrap().unwrap());
+    assert_eq!(&[0x02, 0x03], stream.into_inner().get_ref());
+}
+
+#[test]
+fn fail_ext_wrapper_from_other_type() {
+    match rmps::from_slice::<rmps::ExtBuf>(&[0xc4, 0x01, 0x2a]) {
+        Err(Error::TypeMismatch(Marker::Bin8)) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+    match rmps::from_slice::<rmps::Ext>(&[0x2a]) {
+        Err(Error::TypeMismatch(Marker::FixPos(42))) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn fail_neovim_handle_of_other_type() {
+    use rmps::neovim::{Buffer, Window};
+
+    // A window extension is not a buffer.
+    match rmps::from_slice::<Buffer>(&[0xd4, 0x01, 0x01]) {
+        Err(Error::Syntax(ref msg)) => {
+            assert_eq!("expected buffer extension of type 0, got type 1", msg);
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+    assert_eq!(Window(1), rmps::from_slice(&[0xd4, 0x01, 0x01]).unwrap());
+
+    // The data must be exactly one integer.
+    for buf in &[&[0xd4, 0x00, 0xc0][..], &[0xd5, 0x00, 0x01, 0x02], &[0xc7, 0x00, 0x00]] {
+        match rmps::from_slice::<Buffer>(buf) {
+            Err(Error::Syntax(ref msg)) if msg == "invalid buffer handle" => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
+
+#[test]
+fn pass_neovim_lines_with_invalid_utf8() {
+    // ["caf\xe9"], a line of a latin-1 file.
+    let buf = [0x91, 0xa4, 0x63, 0x61, 0x66, 0xe9];
+    assert!(rmps::from_slice::<Vec<String>>(&buf).is_err());
+
+    let lines: Vec<String> =
+        rmps::from_slice_with(&buf, &rmps::neovim::deserializer_config()).unwrap();
+    assert_eq!(vec!["caf\u{fffd}".to_string()], lines);
+}
@@ -84,6 +84,39 @@ fn pass_struct_from_map() {
     assert_eq!(expected, actual);
 }
 
+#[test]
+fn pass_struct_from_map_skipping_unknown_fields() {
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Struct {
+        le: u8,
+    }
+
+    let buf = [
+        0x82, // 2 (size)
+        0xa2, 0x65, 0x74, // "et"
+        0x92, 0x81, 0xa1, 0x61, 0x90, 0xc4, 0x01, 0xff, // [{"a": []}, bin [0xff]]
+        0xa2, 0x6c, 0x65, // "le"
+        0x2a, // 42
+    ];
+    let cur = Cursor::new(&buf[..]);
+
+    let mut de = Deserializer::new(cur);
+    let actual: Struct = Deserialize::deserialize(&mut de).unwrap();
+
+    assert_eq!(Struct { le: 42 }, actual);
+}
+
+#[test]
+fn pass_ignored_any_skips_deeply_nested_value() {
+    let mut buf = vec![0x91; 10_000];
+    buf.push(0xc0);
+    buf.push(0x2a);
+    let mut de = Deserializer::from_slice(&buf);
+
+    serde::de::IgnoredAny::deserialize(&mut de).unwrap();
+    assert_eq!(42, u8::deserialize(&mut de).unwrap());
+}
+
 #[test]
 fn pass_unit_variant() {
     // We expect enums to be encoded as [id, [...]]
@@ -325,3 +358,127 @@ fn pass_from_slice() {
 
     assert_eq!(Person { name: "John", surname: "Smith", age: 42 }, rmps::from_slice(&buf[..]).unwrap());
 }
+
+#[test]
+fn pass_struct_from_map_with_known_fields_only() {
+    use rmps::decode::DeserializerConfig;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Struct {
+        id: u8,
+        name: String,
+    }
+
+    // {"name": "a", "id": 1}
+    let buf = [0x82, 0xa4, 0x6e, 0x61, 0x6d, 0x65, 0xa1, 0x61, 0xa2, 0x69, 0x64, 0x01];
+
+    let config = DeserializerConfig::new().deny_unknown_fields(true);
+    let actual: Struct = rmps::from_slice_with(&buf, &config).unwrap();
+    assert_eq!(Struct { id: 1, name: "a".into() }, actual);
+
+    // Structs encoded as arrays are not affected: [1, "a"].
+    let buf = [0x92, 0x01, 0xa1, 0x61];
+    let actual: Struct = rmps::from_slice_with(&buf, &config).unwrap();
+    assert_eq!(Struct { id: 1, name: "a".into() }, actual);
+}
+
+#[test]
+fn fail_struct_from_map_with_unknown_field() {
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Inner {
+        id: u8,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Outer {
+        inner: Inner,
+    }
+
+    // {"inner": {"id": 1, "extra": nil}}
+    let buf = [0x81, 0xa5, 0x69, 0x6e, 0x6e, 0x65, 0x72, 0x82, 0xa2, 0x69, 0x64, 0x01, 0xa5, 0x65,
+               0x78, 0x74, 0x72, 0x61, 0xc0];
+
+    let mut de = Deserializer::from_slice(&buf);
+    assert!(Outer::deserialize(&mut de).is_ok());
+
+    let mut de = Deserializer::from_slice(&buf);
+    de.set_deny_unknown_fields(true);
+    match Outer::deserialize(&mut de) {
+        Err(Error::Syntax(msg)) => assert!(msg.contains("unknown field `extra`"), "{}", msg),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn fail_struct_from_map_with_unknown_field_and_duplicate_keys_policy() {
+    use rmps::decode::{DeserializerConfig, DuplicateKeys};
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Struct {
+        id: u8,
+    }
+
+    // {"id": 1, "x": 2}
+    let buf = [0x82, 0xa2, 0x69, 0x64, 0x01, 0xa1, 0x78, 0x02];
+
+    let config = DeserializerConfig::new()
+        .deny_unknown_fields(true)
+        .duplicate_keys(DuplicateKeys::Reject);
+    match rmps::from_slice_with::<Struct>(&buf, &config) {
+        Err(Error::Syntax(..)) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn pass_struct_from_short_array_with_defaults() {
+    use rmps::decode::DeserializerConfig;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Struct {
+        id: u8,
+        name: Option<String>,
+        #[serde(default)]
+        tags: Vec<String>,
+    }
+
+    let config = DeserializerConfig::new().lenient_struct_arrays(true);
+
+    // [1]
+    let actual: Struct = rmps::from_slice_with(&[0x91, 0x01], &config).unwrap();
+    assert_eq!(Struct { id: 1, name: None, tags: Vec::new() }, actual);
+
+    // [1, "a"]
+    let actual: Struct = rmps::from_slice_with(&[0x92, 0x01, 0xa1, 0x61], &config).unwrap();
+    assert_eq!(Struct { id: 1, name: Some("a".into()), tags: Vec::new() }, actual);
+
+    // [1, nil, ["b"]]
+    let buf = [0x93, 0x01, 0xc0, 0x91, 0xa1, 0x62];
+    let actual: Struct = rmps::from_slice_with(&buf, &config).unwrap();
+    assert_eq!(Struct { id: 1, name: None, tags: vec!["b".into()] }, actual);
+}
+
+#[test]
+fn fail_struct_from_short_array() {
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Struct {
+        id: u8,
+        name: Option<String>,
+        count: u32,
+    }
+
+    // [1]
+    let buf = [0x91, 0x01];
+
+    // Strict by default.
+    let mut de = Deserializer::from_slice(&buf);
+    assert!(Struct::deserialize(&mut de).is_err());
+
+    // Fields neither optional nor defaulted are still required.
+    let mut de = Deserializer::from_slice(&buf);
+    de.set_lenient_struct_arrays(true);
+    match Struct::deserialize(&mut de) {
+        Err(Error::Syntax(msg)) => assert!(msg.contains("missing field `count`"), "{}", msg),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
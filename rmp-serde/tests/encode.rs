@@ -320,3 +320,507 @@ fn get_mut() {
     se.get_mut().push(42);
     assert_eq!(vec![0xc3, 42], se.into_inner());
 }
+
+#[test]
+fn pass_compat_str() {
+    let mut se = Serializer::new(Vec::new());
+    se.set_compat(true);
+
+    "le message".serialize(&mut se).unwrap();
+    // A 32-byte string would use str8 in the new spec.
+    let val = String::from_utf8(vec![b'a'; 32]).unwrap();
+    val.serialize(&mut se).unwrap();
+
+    let buf = se.into_inner();
+    assert_eq!([0xaa, 0x6c, 0x65, 0x20, 0x6d, 0x65, 0x73, 0x73, 0x61, 0x67, 0x65], buf[..11]);
+    assert_eq!([0xda, 0x00, 0x20], buf[11..14]);
+    assert_eq!(14 + 32, buf.len());
+}
+
+#[test]
+fn pass_compat_bin() {
+    use serde_bytes::Bytes;
+
+    let mut se = Serializer::new(Vec::new());
+    se.set_compat(true);
+
+    Bytes::from(&[0xcc, 0x80][..]).serialize(&mut se).unwrap();
+    Bytes::from(&vec![0; 65536][..]).serialize(&mut se).unwrap();
+
+    let buf = se.into_inner();
+    assert_eq!([0xa2, 0xcc, 0x80], buf[..3]);
+    assert_eq!([0xdb, 0x00, 0x01, 0x00, 0x00], buf[3..8]);
+}
+
+#[test]
+fn fail_compat_ext() {
+    use rmps::encode::SerializerConfig;
+
+    let mut buf = Vec::new();
+    let mut se = Serializer::new(&mut buf);
+    se.set_compat(true);
+    match rmps::timestamp::serialize_parts(1, 0, &mut se) {
+        Err(Error::ExtNotAllowed(-1)) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    let config = SerializerConfig::new().deny_ext(true);
+    assert!(!config.is_compat());
+    assert!(config.clone().compat(true).is_compat());
+
+    // Extensions alone can be denied, leaving strings and binaries as they are.
+    let mut se = Serializer::with_config(Vec::new(), &config);
+    assert!(rmps::timestamp::serialize_parts(1, 0, &mut se).is_err());
+    serde_bytes::Bytes::new(&[1]).serialize(&mut se).unwrap();
+    assert_eq!(vec![0xc4, 0x01, 0x01], se.into_inner());
+}
+
+#[test]
+fn pass_str_and_bytes_formats() {
+    use serde_bytes::Bytes;
+    use rmps::encode::RawFormat;
+
+    let val = ("ab", Bytes::new(&[1]));
+
+    let mut se = Serializer::new(Vec::new());
+    se.set_str_format(RawFormat::Bin);
+    se.set_bytes_format(RawFormat::Str);
+    val.serialize(&mut se).unwrap();
+    assert_eq!(vec![0x92, 0xc4, 0x02, 0x61, 0x62, 0xa1, 0x01], se.into_inner());
+
+    // Only the bytes use the old raw type, while strings may use str8.
+    let text = String::from_utf8(vec![b'a'; 32]).unwrap();
+    let config = encode::SerializerConfig::new().bytes_format(RawFormat::Legacy);
+    assert!(!config.is_compat());
+    let buf = rmps::to_vec_with(&(&text, Bytes::new(&[0; 32])), &config).unwrap();
+    assert_eq!([0x92, 0xd9, 0x20], buf[..3]);
+    assert_eq!([0xda, 0x00, 0x20], buf[35..38]);
+
+    let config = config.str_format(RawFormat::Legacy).deny_ext(true);
+    assert!(config.is_compat());
+    assert_eq!(RawFormat::Str, config.compat(false).get_str_format());
+}
+
+#[test]
+fn fail_legacy_str_reports_the_part_not_written() {
+    use rmp::encode::ValueWriteError;
+    use rmps::encode::RawFormat;
+
+    let text = String::from_utf8(vec![b'a'; 40]).unwrap();
+
+    let mut buf = [0x00; 2];
+    let mut se = Serializer::new(&mut buf[..]);
+    se.set_str_format(RawFormat::Legacy);
+    match text.serialize(&mut se) {
+        Err(Error::InvalidValueWrite(ValueWriteError::InvalidDataWrite(..))) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+    assert_eq!([0xda, 0x00], buf);
+
+    let mut buf = [];
+    let mut se = Serializer::new(&mut buf[..]);
+    se.set_str_format(RawFormat::Legacy);
+    match text.serialize(&mut se) {
+        Err(Error::InvalidValueWrite(ValueWriteError::InvalidMarkerWrite(..))) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn pass_trace_matches_output() {
+    use rmp::Marker;
+    use serde_bytes::Bytes;
+
+    let text = String::from_utf8(vec![b'a'; 40]).unwrap();
+    let val = (vec![Bytes::from(&[0; 300][..])], -1000i64, text);
+
+    let mut se = encode::TracingSerializer::new(Vec::new());
+    val.serialize(&mut se).unwrap();
+    let (buf, trace) = se.into_parts();
+
+    assert_eq!(rmps::to_vec(&val).unwrap(), buf);
+
+    let entries = trace.iter()
+        .map(|entry| (entry.offset, entry.marker, entry.len, entry.call))
+        .collect::<Vec<_>>();
+    assert_eq!(vec![
+        (0, Marker::FixArray(3), Some(3), "serialize_tuple"),
+        (1, Marker::FixArray(1), Some(1), "serialize_seq"),
+        (2, Marker::Bin16, Some(300), "serialize_bytes"),
+        (305, Marker::I16, None, "serialize_i64"),
+        (308, Marker::Str8, Some(40), "serialize_str"),
+    ], entries);
+}
+
+#[test]
+fn pass_trace_compat() {
+    let mut se = encode::TracingSerializer::new(Vec::new());
+    se.set_config(&encode::SerializerConfig::new().compat(true));
+    String::from_utf8(vec![b'a'; 40]).unwrap().serialize(&mut se).unwrap();
+
+    assert_eq!("0x0000: Str16 len=40 <- serialize_str", se.trace()[0].to_string());
+}
+
+#[test]
+fn pass_bool_as_int() {
+    let config = encode::SerializerConfig::new().bool_as_int(true);
+
+    assert_eq!(vec![0x92, 0x01, 0x00], rmps::to_vec_with(&(true, false), &config).unwrap());
+}
+
+#[test]
+fn fail_lossy_conversions_rejected_by_hook() {
+    use rmps::encode::Lossy;
+
+    let config = encode::SerializerConfig::new().bool_as_int(true).lossy_hook(Some(|_| false));
+    match rmps::to_vec_with(&true, &config) {
+        Err(Error::LossyConversion(Lossy::BoolAsInt)) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    let config = encode::SerializerConfig::new().compat(true).lossy_hook(Some(|_| false));
+    match rmps::to_vec_with(&serde_bytes::Bytes::new(&[1, 2]), &config) {
+        Err(Error::LossyConversion(Lossy::BinaryAsString)) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    // Strings are raw in the old spec, so writing them is not lossy.
+    assert_eq!(vec![0xa1, 0x61], rmps::to_vec_with(&"a", &config).unwrap());
+
+    let config = config.str_format(encode::RawFormat::Bin);
+    match rmps::to_vec_with(&"a", &config) {
+        Err(Error::LossyConversion(Lossy::StringAsBinary)) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn pass_lossy_conversions_reported_to_hook() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use rmps::encode::Lossy;
+
+    static BOOLS: AtomicUsize = AtomicUsize::new(0);
+
+    fn count(kind: Lossy) -> bool {
+        assert_eq!(Lossy::BoolAsInt, kind);
+        BOOLS.fetch_add(1, Ordering::SeqCst);
+        true
+    }
+
+    let mut se = Serializer::new(Vec::new());
+    se.set_bool_as_int(true);
+    se.set_lossy_hook(Some(count));
+    (true, 42, false).serialize(&mut se).unwrap();
+
+    assert_eq!(vec![0x93, 0x01, 0x2a, 0x00], se.into_inner());
+    assert_eq!(2, BOOLS.load(Ordering::SeqCst));
+}
+
+fn sha256(data: &[u8]) -> [u8; 32] {
+    let mut hasher = encode::Sha256::new();
+    encode::ContentHasher::update(&mut hasher, data);
+    encode::ContentHasher::finish(hasher)
+}
+
+#[test]
+fn pass_sha256_known_digests() {
+    assert_eq!([0xe3, 0xb0, 0xc4, 0x42, 0x98, 0xfc, 0x1c, 0x14], sha256(b"")[..8]);
+    assert_eq!([0xba, 0x78, 0x16, 0xbf, 0x8f, 0x01, 0xcf, 0xea], sha256(b"abc")[..8]);
+
+    // Two blocks, with the padding spilling into the second one.
+    let data = b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq";
+    assert_eq!([0x24, 0x8d, 0x6a, 0x61, 0xd2, 0x06, 0x38, 0xb8], sha256(data)[..8]);
+}
+
+#[test]
+fn pass_content_hash_matches_encoding() {
+    let val = (vec![1u32, 300, 70000], "le message", Some(-42i64));
+    let buf = rmps::to_vec(&val).unwrap();
+
+    assert_eq!(sha256(&buf), rmps::content_hash(&val).unwrap());
+}
+
+#[test]
+fn pass_content_hash_of_maps_ignores_insertion_order() {
+    use std::collections::HashMap;
+
+    let keys: Vec<String> = (0..64).map(|idx| format!("key{}", idx)).collect();
+    let mut forward = HashMap::new();
+    for (idx, key) in keys.iter().enumerate() {
+        forward.insert(key.clone(), idx);
+    }
+    let mut backward = HashMap::with_capacity(1024);
+    for (idx, key) in keys.iter().enumerate().rev() {
+        backward.insert(key.clone(), idx);
+    }
+    assert_eq!(forward, backward);
+
+    let config = rmps::SerializerConfig::new().canonical(true);
+    let digest = rmps::content_hash(&forward).unwrap();
+    assert_eq!(digest, rmps::content_hash(&backward).unwrap());
+    assert_eq!(sha256(&rmps::to_vec_with(&forward, &config).unwrap()), digest);
+}
+
+#[test]
+fn pass_content_hash_with_hasher() {
+    struct Len(usize);
+
+    impl encode::ContentHasher for Len {
+        fn update(&mut self, data: &[u8]) {
+            self.0 += data.len();
+        }
+
+        fn finish(self) -> [u8; 32] {
+            let mut digest = [0; 32];
+            digest[0] = self.0 as u8;
+            digest
+        }
+    }
+
+    let digest = encode::content_hash_with(&"le message", Len(0)).unwrap();
+    assert_eq!(11, digest[0]);
+}
+
+#[test]
+fn pass_batch_index() {
+    let mut batch = encode::Batch::with_capacity(64);
+    assert!(batch.is_empty());
+
+    assert_eq!(0, batch.push(&1).unwrap());
+    assert_eq!(1, batch.push(&"le message").unwrap());
+    assert_eq!(2, batch.push(&vec![300u16; 3]).unwrap());
+
+    assert_eq!(3, batch.len());
+    assert_eq!(&[(0, 1), (1, 11), (12, 10)], batch.index());
+    assert_eq!(Some(&[0x93, 0xcd, 0x01, 0x2c, 0xcd, 0x01, 0x2c, 0xcd, 0x01, 0x2c][..]),
+               batch.get(2));
+    assert_eq!(None, batch.get(3));
+
+    let (buf, index) = batch.into_parts();
+    for &(offset, len) in &index {
+        let mut rd = &buf[offset..offset + len];
+        rmp::decode::read_marker(&mut rd).unwrap();
+    }
+    assert_eq!(22, buf.len());
+}
+
+#[test]
+fn pass_batch_discards_failed_message() {
+    struct Failing;
+
+    impl Serialize for Failing {
+        fn serialize<S: serde::Serializer>(&self, se: S) -> Result<S::Ok, S::Error> {
+            use serde::ser::{Error, SerializeSeq};
+
+            let mut seq = se.serialize_seq(Some(2))?;
+            seq.serialize_element(&1)?;
+            Err(S::Error::custom("failed halfway"))
+        }
+    }
+
+    let mut batch = encode::Batch::new();
+    batch.push(&[1, 2]).unwrap();
+    assert!(batch.push(&Failing).is_err());
+    batch.push(&"ok").unwrap();
+
+    assert_eq!(&[(0, 3), (3, 3)], batch.index());
+    assert_eq!(vec![0x92, 0x01, 0x02, 0xa2, 0x6f, 0x6b], batch.as_bytes());
+}
+
+#[test]
+fn pass_map_stream() {
+    let mut se = Serializer::new(Vec::new());
+    {
+        let mut map = se.map_stream(3).unwrap();
+        map.entry("a", &1).unwrap();
+        assert_eq!(2, map.remaining());
+        map.entries(vec![("b", vec![2]), ("c", vec![])]).unwrap();
+        map.end().unwrap();
+    }
+
+    assert_eq!(vec![0x83, 0xa1, 0x61, 0x01, 0xa1, 0x62, 0x91, 0x02, 0xa1, 0x63, 0x90],
+               se.into_inner());
+}
+
+#[test]
+fn fail_map_stream_length_mismatch() {
+    let mut se = Serializer::new(Vec::new());
+
+    let mut map = se.map_stream(1).unwrap();
+    map.entry(&1, &2).unwrap();
+    match map.entry(&3, &4) {
+        Err(Error::Syntax(..)) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    let mut se = Serializer::new(Vec::new());
+    let map = se.map_stream(2).unwrap();
+    match map.end() {
+        Err(Error::Syntax(..)) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn fail_map_stream_canonical() {
+    let mut se = Serializer::new(Vec::new());
+    se.set_canonical(true);
+
+    match se.map_stream(1) {
+        Err(Error::Syntax(..)) => {}
+        other => panic!("unexpected result: {:?}", other.map(|map| map.remaining())),
+    }
+    assert!(se.into_inner().is_empty());
+}
+
+#[test]
+fn pass_trace_canonical_map() {
+    use rmp::Marker;
+
+    /// A map which keeps its entries in the order they were given.
+    struct Entries(Vec<(u32, &'static str)>);
+
+    impl Serialize for Entries {
+        fn serialize<S: serde::Serializer>(&self, se: S) -> Result<S::Ok, S::Error> {
+            se.collect_map(self.0.iter().cloned())
+        }
+    }
+
+    let val = Entries(vec![(300, "b"), (1, "a")]);
+
+    let mut se = encode::TracingSerializer::new(Vec::new());
+    se.set_config(&encode::SerializerConfig::new().canonical(true));
+    (vec![2, 1], &val).serialize(&mut se).unwrap();
+    let (buf, trace) = se.into_parts();
+
+    // The key 1 is encoded as 0x01, which sorts before the 0xcd of 300.
+    assert_eq!(vec![0x92, 0x92, 0x02, 0x01, 0x82, 0x01, 0xa1, 0x61, 0xcd, 0x01, 0x2c, 0xa1, 0x62],
+               buf);
+
+    // The entries are held back until the map ends.
+    let entries = trace.iter()
+        .map(|entry| (entry.offset, entry.marker, entry.call))
+        .collect::<Vec<_>>();
+    assert_eq!(vec![
+        (0, Marker::FixArray(2), "serialize_tuple"),
+        (1, Marker::FixArray(2), "serialize_seq"),
+        (2, Marker::FixPos(2), "serialize_i32"),
+        (3, Marker::FixPos(1), "serialize_i32"),
+        (4, Marker::FixMap(2), "serialize_map"),
+        (5, Marker::FixPos(1), "serialize_map"),
+        (6, Marker::FixStr(1), "serialize_map"),
+        (8, Marker::U16, "serialize_map"),
+        (11, Marker::FixStr(1), "serialize_map"),
+    ], entries);
+}
+
+struct Ext<T>(T);
+
+impl<T: Serialize> Serialize for Ext<T> {
+    fn serialize<S: serde::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_newtype_struct(rmps::MSGPACK_EXT_STRUCT_NAME, &self.0)
+    }
+}
+
+#[test]
+fn pass_ext_struct() {
+    let val = Ext((5i8, serde_bytes::Bytes::new(&[1, 2])));
+    assert_eq!(vec![0xd5, 0x05, 0x01, 0x02], encode::to_vec(&val).unwrap());
+
+    let val = Ext((5i8, serde_bytes::ByteBuf::from(vec![0; 3])));
+    assert_eq!(vec![0xc7, 0x03, 0x05, 0x00, 0x00, 0x00], encode::to_vec(&val).unwrap());
+}
+
+#[test]
+fn fail_ext_struct_malformed() {
+    for res in vec![encode::to_vec(&Ext((5i8, vec![1u8, 2]))),
+                    encode::to_vec(&Ext((5u8, serde_bytes::Bytes::new(&[1])))),
+                    encode::to_vec(&Ext(5i8))] {
+        match res {
+            Err(Error::Syntax(..)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
+
+struct Pairs(Vec<(&'static str, u8)>);
+
+impl Serialize for Pairs {
+    fn serialize<S: serde::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_map(self.0.iter().cloned())
+    }
+}
+
+#[test]
+fn pass_canonical_map_order() {
+    use std::collections::HashMap;
+
+    use rmps::encode::SerializerConfig;
+
+    let config = SerializerConfig::new().canonical(true);
+
+    // Keys are compared in their encoded form, where short strings start with their length, so
+    // "b" < "c" < "ba".
+    let val = Pairs(vec![("c", 3), ("ba", 2), ("b", 1)]);
+    assert_eq!(vec![0x83, 0xa1, 0x62, 0x01, 0xa1, 0x63, 0x03, 0xa2, 0x62, 0x61, 0x02],
+               rmps::to_vec_with(&val, &config).unwrap());
+
+    // Maps with different hash seeds and insertion orders.
+    let mut map = HashMap::new();
+    let mut other = HashMap::new();
+    for idx in 0..64u32 {
+        map.insert(idx * 1000, idx.to_string());
+        other.insert((63 - idx) * 1000, (63 - idx).to_string());
+    }
+    assert_eq!(rmps::to_vec_with(&map, &config).unwrap(),
+               rmps::to_vec_with(&other, &config).unwrap());
+}
+
+#[test]
+fn fail_canonical_duplicate_key() {
+    use rmps::encode::SerializerConfig;
+
+    let val = Pairs(vec![("a", 1), ("b", 2), ("a", 3)]);
+    match rmps::to_vec_with(&val, &SerializerConfig::new().canonical(true)) {
+        Err(Error::DuplicateKey(key)) => assert_eq!(vec![0xa1, 0x61], key),
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    // Duplicates are written as they are otherwise.
+    assert_eq!(vec![0x83, 0xa1, 0x61, 0x01, 0xa1, 0x62, 0x02, 0xa1, 0x61, 0x03],
+               encode::to_vec(&val).unwrap());
+}
+
+#[test]
+fn pass_to_slice() {
+    let val = (42u8, "le message");
+    let mut buf = [0xff; 16];
+
+    let len = rmps::to_slice(&mut buf, &val).unwrap();
+    assert_eq!(rmps::to_vec(&val).unwrap(), buf[..len]);
+    assert_eq!([0xff; 3], buf[len..]);
+}
+
+#[test]
+fn fail_to_slice_buffer_too_small() {
+    let mut buf = [0; 4];
+
+    match rmps::to_slice(&mut buf, &(42u8, "le message")) {
+        Err(Error::BufferTooSmall { needed: 13 }) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+    assert_eq!([0x92, 0x2a, 0xaa, 0x6c], buf);
+
+    let config = rmps::SerializerConfig::default();
+    assert_eq!(1, encode::to_slice_with(&mut buf[..1], &(), &config).unwrap());
+}
+
+#[test]
+fn pass_serialized_size() {
+    let val = (vec![1u32, 300, 70000], "le message", Some(-42i64));
+    assert_eq!(rmps::to_vec(&val).unwrap().len() as u64, rmps::serialized_size(&val).unwrap());
+
+    let config = rmps::SerializerConfig::default().with_struct_map();
+    assert_eq!(1, encode::serialized_size_with(&(), &config).unwrap());
+}
@@ -173,3 +173,509 @@ fn pass_struct_as_map() {
     assert_eq!(vec![0x82, 0xa4, 0x6e, 0x61, 0x6d, 0x65, 0xa5, 0x42, 0x6f, 0x62, 0x62, 0x79, 0xa3, 0x61, 0x67, 0x65, 0x08],
         se.into_inner());
 }
+
+#[test]
+fn pass_trace_struct_as_map() {
+    use rmps::encode::{StructMapWriter, TracingSerializer};
+
+    #[derive(Serialize)]
+    struct Dog {
+        name: &'static str,
+        owner: Option<Owner>,
+    }
+
+    #[derive(Serialize)]
+    struct Owner(u8);
+
+    let dog = Dog { name: "Bobby", owner: Some(Owner(42)) };
+
+    let mut se = TracingSerializer::with(Vec::new(), StructMapWriter);
+    dog.serialize(&mut se).unwrap();
+
+    let calls = se.trace().iter().map(|entry| entry.call).collect::<Vec<_>>();
+    assert_eq!(vec!["serialize_struct", "serialize_field", "serialize_str", "serialize_field",
+                    "serialize_newtype_struct", "serialize_u8"], calls);
+}
+
+#[test]
+fn pass_trace_with_type_overrides() {
+    use rmp::Marker;
+    use rmps::encode::{Override, Overrides, SerializerConfig, TracingSerializer};
+
+    #[derive(Serialize)]
+    struct Inner {
+        x: f64,
+    }
+
+    #[derive(Serialize)]
+    struct Id(u16);
+
+    #[derive(Serialize)]
+    struct Outer {
+        a: Inner,
+        id: Id,
+    }
+
+    let overrides = Overrides::new()
+        .for_type("Inner", Override::Ext(5))
+        .for_type("Inner", Override::F64AsF32)
+        .for_type("Id", Override::Ext(1));
+    let config = SerializerConfig::new().overrides(overrides);
+    let val = Outer { a: Inner { x: 0.5 }, id: Id(300) };
+
+    let mut se = TracingSerializer::new(Vec::new());
+    se.set_config(&config);
+    val.serialize(&mut se).unwrap();
+    let (buf, trace) = se.into_parts();
+
+    assert_eq!(vec![
+        0x92,
+        0xc7, 0x06, 0x05, 0x91, 0xca, 0x3f, 0x00, 0x00, 0x00,
+        0xd6, 0x01, 0x91, 0xcd, 0x01, 0x2c,
+    ], buf);
+    assert_eq!(rmps::to_vec_with(&val, &config).unwrap(), buf);
+
+    let entries = trace.iter()
+        .map(|entry| (entry.offset, entry.marker, entry.len, entry.call))
+        .collect::<Vec<_>>();
+    assert_eq!(vec![
+        (0, Marker::FixArray(2), Some(2), "serialize_struct"),
+        (1, Marker::Ext8, Some(6), "serialize_struct"),
+        (10, Marker::FixExt4, Some(4), "serialize_newtype_struct"),
+    ], entries);
+}
+
+#[test]
+fn pass_trace_with_path_overrides() {
+    use rmp::Marker;
+    use rmps::encode::{Override, Overrides, SerializerConfig, TracingSerializer};
+
+    #[derive(Serialize)]
+    struct Inner {
+        x: f64,
+    }
+
+    #[derive(Serialize)]
+    struct Outer {
+        a: Inner,
+        x: f64,
+    }
+
+    let overrides = Overrides::new().for_path("/a/x", Override::F64AsF32);
+    let config = SerializerConfig::new().with_struct_map().overrides(overrides);
+    let val = Outer { a: Inner { x: 0.5 }, x: 0.5 };
+
+    let mut se = TracingSerializer::new(Vec::new());
+    se.set_config(&config);
+    val.serialize(&mut se).unwrap();
+    let (buf, trace) = se.into_parts();
+
+    assert_eq!(rmps::to_vec_with(&val, &config).unwrap(), buf);
+
+    let entries = trace.iter()
+        .map(|entry| (entry.marker, entry.call))
+        .collect::<Vec<_>>();
+    assert_eq!(vec![
+        (Marker::FixMap(2), "serialize_struct"),
+        (Marker::FixStr(1), "serialize_field"),
+        (Marker::FixMap(1), "serialize_struct"),
+        (Marker::FixStr(1), "serialize_field"),
+        (Marker::F32, "serialize_f64"),
+        (Marker::FixStr(1), "serialize_field"),
+        (Marker::F64, "serialize_f64"),
+    ], entries);
+}
+
+#[test]
+fn pass_struct_field_filter() {
+    use rmps::encode::StructMapWriter;
+
+    #[derive(Serialize)]
+    struct Owner {
+        name: &'static str,
+        email: &'static str,
+    }
+
+    #[derive(Serialize)]
+    struct Dog {
+        name: &'static str,
+        age: u8,
+        owner: Owner,
+    }
+
+    fn public(ty: &str, field: &str) -> bool {
+        match ty {
+            "Dog" => field != "age",
+            "Owner" => field == "name",
+            _ => true,
+        }
+    }
+
+    let dog = Dog {
+        name: "Bobby",
+        age: 8,
+        owner: Owner { name: "John", email: "john@example.com" },
+    };
+
+    let mut se = Serializer::with(Vec::new(), StructMapWriter);
+    se.set_field_filter(Some(public));
+    dog.serialize(&mut se).unwrap();
+
+    // Expect: {"name": "Bobby", "owner": {"name": "John"}}.
+    assert_eq!(vec![0x82, 0xa4, 0x6e, 0x61, 0x6d, 0x65, 0xa5, 0x42, 0x6f, 0x62, 0x62, 0x79,
+                    0xa5, 0x6f, 0x77, 0x6e, 0x65, 0x72, 0x81, 0xa4, 0x6e, 0x61, 0x6d, 0x65,
+                    0xa4, 0x4a, 0x6f, 0x68, 0x6e],
+               se.into_inner());
+}
+
+#[test]
+fn pass_struct_variant_field_filter() {
+    #[derive(Serialize)]
+    enum Event {
+        Login { user: &'static str, password: &'static str },
+    }
+
+    let mut se = Serializer::new(Vec::new());
+    se.set_field_filter(Some(|_, field| field != "password"));
+    Event::Login { user: "John", password: "hunter2" }.serialize(&mut se).unwrap();
+
+    // Expect: [0, ["John"]].
+    assert_eq!(vec![0x92, 0x00, 0x91, 0xa4, 0x4a, 0x6f, 0x68, 0x6e], se.into_inner());
+}
+
+#[test]
+fn pass_skipped_fields_as_nil() {
+    use rmps::SerializerConfig;
+
+    #[derive(Serialize)]
+    struct Inner {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        a: Option<u8>,
+        b: u8,
+    }
+
+    #[derive(Serialize)]
+    struct Outer {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        first: Option<u8>,
+        inner: Inner,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        tags: Vec<u8>,
+    }
+
+    let val = Outer { first: None, inner: Inner { a: None, b: 2 }, tags: Vec::new() };
+
+    // Without placeholders the fields shift: [[2]].
+    assert_eq!(vec![0x91, 0x91, 0x02], rmps::to_vec(&val).unwrap());
+
+    // Expect: [nil, [nil, 2], nil].
+    let config = SerializerConfig::new().skipped_as_nil(true);
+    assert_eq!(vec![0x93, 0xc0, 0x92, 0xc0, 0x02, 0xc0], rmps::to_vec_with(&val, &config).unwrap());
+
+    // Fields that are not skipped are written as usual.
+    let val = Outer { first: Some(1), inner: Inner { a: Some(3), b: 2 }, tags: vec![4] };
+    assert_eq!(vec![0x93, 0x01, 0x92, 0x03, 0x02, 0x91, 0x04],
+               rmps::to_vec_with(&val, &config).unwrap());
+}
+
+#[test]
+fn pass_trace_skipped_fields_as_nil() {
+    use rmp::Marker;
+    use rmps::encode::{SerializerConfig, TracingSerializer};
+
+    #[derive(Serialize)]
+    struct Sample {
+        a: u8,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        b: Option<u8>,
+        c: u8,
+    }
+
+    let config = SerializerConfig::new().skipped_as_nil(true);
+    let val = Sample { a: 1, b: None, c: 3 };
+
+    let mut se = TracingSerializer::new(Vec::new());
+    se.set_config(&config);
+    val.serialize(&mut se).unwrap();
+    let (buf, trace) = se.into_parts();
+
+    // Expect: [1, nil, 3].
+    assert_eq!(vec![0x93, 0x01, 0xc0, 0x03], buf);
+    assert_eq!(rmps::to_vec_with(&val, &config).unwrap(), buf);
+
+    // The fields are held back until the number of fields is known.
+    let entries = trace.iter()
+        .map(|entry| (entry.offset, entry.marker, entry.call))
+        .collect::<Vec<_>>();
+    assert_eq!(vec![
+        (0, Marker::FixArray(3), "serialize_struct"),
+        (1, Marker::FixPos(1), "serialize_struct"),
+        (2, Marker::Null, "serialize_struct"),
+        (3, Marker::FixPos(3), "serialize_struct"),
+    ], entries);
+}
+
+#[test]
+fn pass_skipped_fields_as_nil_with_field_filter_and_map() {
+    use rmps::encode::StructMapWriter;
+
+    #[derive(Serialize)]
+    struct User {
+        name: &'static str,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        email: Option<&'static str>,
+        password: &'static str,
+    }
+
+    let val = User { name: "J", email: None, password: "x" };
+
+    let mut se = Serializer::new(Vec::new());
+    se.set_skipped_as_nil(true);
+    se.set_field_filter(Some(|_, field| field != "password"));
+    val.serialize(&mut se).unwrap();
+
+    // Expect: ["J", nil, nil].
+    assert_eq!(vec![0x93, 0xa1, 0x4a, 0xc0, 0xc0], se.into_inner());
+
+    // Maps leave skipped fields out: {"name": "J", "password": "x"}.
+    let mut se = Serializer::with(Vec::new(), StructMapWriter);
+    se.set_skipped_as_nil(true);
+    val.serialize(&mut se).unwrap();
+    assert_eq!(vec![0x82, 0xa4, 0x6e, 0x61, 0x6d, 0x65, 0xa1, 0x4a,
+                    0xa8, 0x70, 0x61, 0x73, 0x73, 0x77, 0x6f, 0x72, 0x64, 0xa1, 0x78],
+               se.into_inner());
+}
+
+#[test]
+fn pass_type_overrides() {
+    use rmps::encode::{Override, Overrides};
+
+    #[derive(Serialize)]
+    struct Id(u16);
+
+    #[derive(Serialize)]
+    struct Marker;
+
+    #[derive(Serialize)]
+    struct Pair(u8, u8);
+
+    #[derive(Serialize)]
+    struct Inner {
+        val: f64,
+    }
+
+    #[derive(Serialize)]
+    struct Outer {
+        id: Id,
+        marker: Marker,
+        pair: Pair,
+        inner: Inner,
+        val: f64,
+    }
+
+    let overrides = Overrides::new()
+        .for_type("Id", Override::Ext(1))
+        .for_type("Marker", Override::Ext(2))
+        .for_type("Pair", Override::Ext(3))
+        .for_type("Inner", Override::Ext(4))
+        .for_type("Inner", Override::F64AsF32);
+
+    let mut se = Serializer::new(Vec::new());
+    se.set_overrides(overrides);
+    Outer { id: Id(300), marker: Marker, pair: Pair(1, 2), inner: Inner { val: 0.5 }, val: 0.5 }
+        .serialize(&mut se)
+        .unwrap();
+
+    assert_eq!(vec![
+        0x95,
+        0xd6, 0x01, 0x91, 0xcd, 0x01, 0x2c,
+        0xd4, 0x02, 0x90,
+        0xc7, 0x03, 0x03, 0x92, 0x01, 0x02,
+        0xc7, 0x06, 0x04, 0x91, 0xca, 0x3f, 0x00, 0x00, 0x00,
+        0xcb, 0x3f, 0xe0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ], se.into_inner());
+}
+
+#[test]
+fn pass_path_overrides() {
+    use rmps::encode::{Override, Overrides, StructMapWriter};
+
+    #[derive(Serialize)]
+    struct Item {
+        weight: f64,
+    }
+
+    #[derive(Serialize)]
+    struct Doc {
+        items: Vec<Item>,
+        weight: f64,
+    }
+
+    let overrides = Overrides::new().for_path("/items/weight", Override::F64AsF32);
+
+    let mut se = Serializer::with(Vec::new(), StructMapWriter);
+    se.set_overrides(overrides);
+    Doc { items: vec![Item { weight: 2.0 }], weight: 2.0 }.serialize(&mut se).unwrap();
+
+    // Expect: {"items": [{"weight": 2.0f32}], "weight": 2.0}.
+    assert_eq!(vec![
+        0x82,
+        0xa5, 0x69, 0x74, 0x65, 0x6d, 0x73, 0x91, 0x81,
+        0xa6, 0x77, 0x65, 0x69, 0x67, 0x68, 0x74, 0xca, 0x40, 0x00, 0x00, 0x00,
+        0xa6, 0x77, 0x65, 0x69, 0x67, 0x68, 0x74, 0xcb, 0x40, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00,
+    ], se.into_inner());
+}
+
+#[test]
+fn pass_overrides_with_field_filter() {
+    use rmps::encode::{Override, Overrides};
+
+    #[derive(Serialize)]
+    struct User {
+        name: &'static str,
+        secret: &'static str,
+    }
+
+    let mut se = Serializer::new(Vec::new());
+    se.set_field_filter(Some(|_, field| field != "secret"));
+    se.set_overrides(Overrides::new().for_type("User", Override::Ext(9)));
+    User { name: "a", secret: "b" }.serialize(&mut se).unwrap();
+
+    // Expect: ext 9 holding ["a"].
+    assert_eq!(vec![0xc7, 0x03, 0x09, 0x91, 0xa1, 0x61], se.into_inner());
+}
+
+#[test]
+fn fail_f64_override_rejected_by_lossy_hook() {
+    use rmps::encode::{Error, Lossy, Override, Overrides, SerializerConfig};
+
+    #[derive(Serialize)]
+    struct Sample {
+        val: f64,
+    }
+
+    let config = SerializerConfig::new()
+        .overrides(Overrides::new().for_path("/val", Override::F64AsF32))
+        .lossy_hook(Some(|_| false));
+
+    match rmps::to_vec_with(&Sample { val: 1.0 }, &config) {
+        Err(Error::LossyConversion(Lossy::F64AsF32)) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn pass_struct_map_from_config() {
+    use rmps::encode::SerializerConfig;
+
+    #[derive(Serialize)]
+    struct Point {
+        x: u8,
+        y: u8,
+    }
+
+    struct Encoder {
+        se: Serializer<Vec<u8>>,
+    }
+
+    let mut enc = Encoder {
+        se: Serializer::with_config(Vec::new(), &SerializerConfig::new().with_struct_map()),
+    };
+    Point { x: 1, y: 2 }.serialize(&mut enc.se).unwrap();
+
+    // Expect: {"x": 1, "y": 2}.
+    assert_eq!(vec![0x82, 0xa1, 0x78, 0x01, 0xa1, 0x79, 0x02], enc.se.into_inner());
+}
+
+#[test]
+fn pass_string_variants() {
+    use rmps::encode::SerializerConfig;
+
+    #[derive(Serialize)]
+    enum Enum {
+        Unit,
+        Tuple(u8, u8),
+    }
+
+    let config = SerializerConfig::new().with_string_variants();
+
+    // Expect: ["Unit", []].
+    assert_eq!(vec![0x92, 0xa4, 0x55, 0x6e, 0x69, 0x74, 0x90],
+               rmps::to_vec_with(&Enum::Unit, &config).unwrap());
+    // Expect: ["Tuple", [1, 2]].
+    assert_eq!(vec![0x92, 0xa5, 0x54, 0x75, 0x70, 0x6c, 0x65, 0x92, 0x01, 0x02],
+               rmps::to_vec_with(&Enum::Tuple(1, 2), &config).unwrap());
+}
+
+#[test]
+fn pass_binary_strings() {
+    use rmps::encode::SerializerConfig;
+
+    let config = SerializerConfig::new().with_binary_strings();
+
+    assert_eq!(vec![0xc4, 0x02, 0x01, 0xff], rmps::to_vec_with(&vec![1u8, 255], &config).unwrap());
+    // Empty sequences and tuples stay arrays.
+    assert_eq!(vec![0x90], rmps::to_vec_with(&Vec::<u8>::new(), &config).unwrap());
+    assert_eq!(vec![0x92, 0x01, 0x02], rmps::to_vec_with(&(1u8, 2u8), &config).unwrap());
+}
+
+#[test]
+fn pass_binary_strings_fall_back_to_array() {
+    use rmps::encode::SerializerConfig;
+
+    #[derive(Serialize)]
+    #[serde(untagged)]
+    enum Item {
+        Byte(u8),
+        Word(u16),
+    }
+
+    let config = SerializerConfig::new().with_binary_strings();
+    let items = vec![Item::Byte(1), Item::Byte(2), Item::Word(300)];
+
+    assert_eq!(vec![0x93, 0x01, 0x02, 0xcd, 0x01, 0x2c],
+               rmps::to_vec_with(&items, &config).unwrap());
+    assert_eq!(vec![0x92, 0x01, 0x02], rmps::to_vec_with(&vec![1u16, 2], &config).unwrap());
+}
+
+#[test]
+fn pass_externally_tagged_enum() {
+    use rmps::encode::SerializerConfig;
+
+    #[derive(Serialize)]
+    enum Enum {
+        Unit,
+        Newtype(u8),
+        Tuple(u8, u8),
+    }
+
+    let config = SerializerConfig::new().with_externally_tagged();
+
+    // Expect: "Unit".
+    assert_eq!(vec![0xa4, 0x55, 0x6e, 0x69, 0x74],
+               rmps::to_vec_with(&Enum::Unit, &config).unwrap());
+    // Expect: {"Newtype": 1}.
+    assert_eq!(vec![0x81, 0xa7, 0x4e, 0x65, 0x77, 0x74, 0x79, 0x70, 0x65, 0x01],
+               rmps::to_vec_with(&Enum::Newtype(1), &config).unwrap());
+    // Expect: {"Tuple": [1, 2]}.
+    assert_eq!(vec![0x81, 0xa5, 0x54, 0x75, 0x70, 0x6c, 0x65, 0x92, 0x01, 0x02],
+               rmps::to_vec_with(&Enum::Tuple(1, 2), &config).unwrap());
+}
+
+#[test]
+fn pass_str_format_applies_to_field_names() {
+    use rmps::encode::RawFormat;
+
+    #[derive(Serialize)]
+    struct Struct {
+        a: u8,
+    }
+
+    let mut se = Serializer::new(Vec::new());
+    se.set_struct_map(true);
+    se.set_str_format(RawFormat::Bin);
+    Struct { a: 1 }.serialize(&mut se).unwrap();
+
+    assert_eq!(vec![0x81, 0xc4, 0x01, 0x61, 0x01], se.into_inner());
+}
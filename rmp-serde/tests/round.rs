@@ -2,6 +2,7 @@ extern crate serde;
 #[macro_use]
 extern crate serde_derive;
 extern crate rmp;
+extern crate serde_bytes;
 extern crate rmp_serde as rmps;
 
 use std::borrow::Cow;
@@ -86,3 +87,758 @@ fn round_enum_with_nested_struct() {
 
     assert_eq!(expected, Deserialize::deserialize(&mut de).unwrap());
 }
+
+#[test]
+fn round_struct_named() {
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Dog {
+        name: String,
+        age: u16,
+    }
+
+    let expected = Dog { name: "Bobby".into(), age: 8 };
+
+    let buf = rmps::to_vec_named(&expected).unwrap();
+    assert_eq!(vec![0x82, 0xa4, 0x6e, 0x61, 0x6d, 0x65, 0xa5, 0x42, 0x6f, 0x62, 0x62, 0x79, 0xa3, 0x61, 0x67, 0x65, 0x08],
+        buf);
+    assert_eq!(expected, rmps::from_slice(&buf).unwrap());
+
+    let mut out = Vec::new();
+    rmps::to_writer_named(&mut out, &expected).unwrap();
+    assert_eq!(buf, out);
+}
+
+#[test]
+fn round_with_config() {
+    use rmps::{DeserializerConfig, SerializerConfig};
+
+    let expected = (42u8, "le message".to_string());
+
+    let buf = rmps::to_vec_with(&expected, &SerializerConfig::new().compat(true)).unwrap();
+    assert_eq!(0xaa, buf[2]);
+
+    let config = DeserializerConfig::new().max_depth(8);
+    assert_eq!(expected, rmps::from_slice_with(&buf, &config).unwrap());
+    assert_eq!(expected, rmps::from_read_with(Cursor::new(&buf[..]), &config).unwrap());
+}
+
+#[test]
+fn round_binary_strings() {
+    use rmps::SerializerConfig;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Frame {
+        payload: Vec<u8>,
+        words: Vec<u16>,
+    }
+
+    let expected = Frame { payload: vec![0, 1, 255], words: vec![1, 1000] };
+    let config = SerializerConfig::new().with_struct_map().with_binary_strings();
+    let buf = rmps::to_vec_with(&expected, &config).unwrap();
+
+    // Expect: {"payload": bin [0, 1, 255], "words": [1, 1000]}.
+    assert_eq!(vec![0x82, 0xa7, 0x70, 0x61, 0x79, 0x6c, 0x6f, 0x61, 0x64, 0xc4, 0x03, 0x00, 0x01,
+                    0xff, 0xa5, 0x77, 0x6f, 0x72, 0x64, 0x73, 0x92, 0x01, 0xcd, 0x03, 0xe8], buf);
+    assert_eq!(expected, rmps::from_slice(&buf).unwrap());
+}
+
+#[test]
+fn round_struct_index_map() {
+    use rmps::{DeserializerConfig, SerializerConfig};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Old {
+        id: u32,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
+        score: u8,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct New {
+        id: u32,
+        name: Option<String>,
+        score: u8,
+        #[serde(default)]
+        tags: Vec<String>,
+    }
+
+    let config = SerializerConfig::new().with_struct_index_map();
+    let old = Old { id: 7, name: None, score: 3 };
+    let buf = rmps::to_vec_with(&old, &config).unwrap();
+
+    // Expect: {0: 7, 2: 3}, the skipped field keeping its index.
+    assert_eq!(vec![0x82, 0x00, 0x07, 0x02, 0x03], buf);
+    assert_eq!(old, rmps::from_slice(&buf).unwrap());
+
+    // A newer reader with an appended field accepts it.
+    let new: New = rmps::from_slice(&buf).unwrap();
+    assert_eq!(New { id: 7, name: None, score: 3, tags: Vec::new() }, new);
+
+    // An older reader skips the appended field, unless unknown fields are rejected.
+    let buf = rmps::to_vec_with(&new, &config).unwrap();
+    assert_eq!(vec![0x84, 0x00, 0x07, 0x01, 0xc0, 0x02, 0x03, 0x03, 0x90], buf);
+    assert_eq!(Old { id: 7, name: None, score: 3 }, rmps::from_slice(&buf).unwrap());
+
+    let strict = DeserializerConfig::new().deny_unknown_fields(true);
+    assert!(rmps::from_slice_with::<New>(&buf, &strict).is_ok());
+    match rmps::from_slice_with::<Old>(&buf, &strict) {
+        Err(rmps::decode::Error::Syntax(msg)) => assert!(msg.contains("`3`"), "{}", msg),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn round_invalid_utf8_str() {
+    use rmps::encode::RawFormat;
+    use serde_bytes::ByteBuf;
+
+    // A string written by a producer not caring about encodings, holding Latin-1 "é".
+    let buf = [0xa2, 0xe9, 0x74];
+
+    let val: ByteBuf = rmps::from_slice(&buf).unwrap();
+    assert_eq!([0xe9, 0x74], val[..]);
+
+    let mut se = Serializer::new(Vec::new());
+    se.set_bytes_format(RawFormat::Str);
+    val.serialize(&mut se).unwrap();
+    assert_eq!(buf[..], se.into_inner()[..]);
+}
+
+#[test]
+fn round_legacy_raw() {
+    use rmps::{DeserializerConfig, SerializerConfig};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Packet {
+        name: String,
+        #[serde(with = "serde_bytes")]
+        payload: Vec<u8>,
+        data: Vec<u8>,
+    }
+
+    let expected = Packet { name: "p".into(), payload: vec![0xff, 0x00], data: vec![0x71] };
+
+    // Old encoders write every byte array as a raw value: ["p", raw ff 00, raw 71].
+    let buf = [0x93, 0xa1, 0x70, 0xa2, 0xff, 0x00, 0xa1, 0x71];
+    assert!(rmps::from_slice::<Packet>(&buf).is_err());
+
+    let config = DeserializerConfig::new().legacy_raw(true);
+    assert_eq!(expected, rmps::from_slice_with(&buf, &config).unwrap());
+    assert_eq!(expected, rmps::from_read_with(Cursor::new(&buf[..]), &config).unwrap());
+
+    let mut de = Deserializer::from_slice(&buf);
+    de.set_legacy_raw(true);
+    assert_eq!(expected, Packet::deserialize(&mut de).unwrap());
+
+    // Binaries are still accepted, so the output of the compatibility mode reads back as well.
+    let config = SerializerConfig::new().compat(true).with_binary_strings();
+    let out = rmps::to_vec_with(&expected, &config).unwrap();
+    assert_eq!(buf[..], out[..]);
+}
+
+#[test]
+fn round_externally_tagged_enum() {
+    use rmps::SerializerConfig;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Shape {
+        Empty,
+        Dot(u8),
+        Line(u8, u8),
+        Rect { w: u8, h: u8 },
+    }
+
+    let shapes = vec![Shape::Empty, Shape::Dot(1), Shape::Line(1, 2), Shape::Rect { w: 3, h: 4 }];
+    let tagged = SerializerConfig::new().with_externally_tagged();
+    let named = SerializerConfig::new().with_externally_tagged().with_struct_map();
+
+    for config in &[SerializerConfig::new(), tagged, named] {
+        let buf = rmps::to_vec_with(&shapes, config).unwrap();
+        assert_eq!(shapes, rmps::from_slice::<Vec<Shape>>(&buf).unwrap());
+    }
+}
+
+#[test]
+fn fail_externally_tagged_enum_without_value() {
+    #[derive(Debug, PartialEq, Deserialize)]
+    enum Shape {
+        Dot(u8),
+    }
+
+    // {"Dot": 1} is the only valid encoding.
+    assert_eq!(Shape::Dot(1), rmps::from_slice(&[0x81, 0xa3, 0x44, 0x6f, 0x74, 0x01]).unwrap());
+
+    // "Dot" and {"Dot": 1, "Dot": 2}.
+    assert!(rmps::from_slice::<Shape>(&[0xa3, 0x44, 0x6f, 0x74]).is_err());
+    match rmps::from_slice::<Shape>(&[0x82, 0xa3, 0x44, 0x6f, 0x74, 0x01, 0xa3, 0x44, 0x6f, 0x74,
+                                      0x02]) {
+        Err(rmps::decode::Error::LengthMismatch(2)) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn round_enum_by_index_or_name() {
+    use rmps::SerializerConfig;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Shape {
+        Empty,
+        Dot(u8),
+        Line(u8, u8),
+        Rect { w: u8, h: u8 },
+    }
+
+    let shapes = vec![Shape::Empty, Shape::Dot(1), Shape::Line(1, 2), Shape::Rect { w: 3, h: 4 }];
+    let buf = rmps::to_vec_with(&shapes, &SerializerConfig::new().with_string_variants()).unwrap();
+    assert_eq!(shapes, rmps::from_slice::<Vec<Shape>>(&buf).unwrap());
+
+    // [[1, [5]], ["Dot", [6]]].
+    let mixed = [0x92, 0x92, 0x01, 0x91, 0x05, 0x92, 0xa3, 0x44, 0x6f, 0x74, 0x91, 0x06];
+    assert_eq!(vec![Shape::Dot(5), Shape::Dot(6)],
+               rmps::from_slice::<Vec<Shape>>(&mixed).unwrap());
+
+    // ["Circle", [1]].
+    let unknown = [0x92, 0xa6, 0x43, 0x69, 0x72, 0x63, 0x6c, 0x65, 0x91, 0x01];
+    assert!(rmps::from_slice::<Shape>(&unknown).is_err());
+}
+
+#[test]
+fn round_enum_with_discriminants() {
+    use rmps::{Discriminants, DeserializerConfig, SerializerConfig};
+
+    mod v1 {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        pub enum Message {
+            Ping,
+            Data(u32, String),
+            Close { code: u16 },
+        }
+    }
+
+    // Variants are reordered and one is added in the middle.
+    mod v2 {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        pub enum Message {
+            Close { code: u16 },
+            Hello(u8),
+            Ping,
+            Data(u32, String),
+        }
+    }
+
+    let discriminants = Discriminants::new()
+        .variant("Message", "Ping", 100)
+        .variant("Message", "Data", 200)
+        .variant("Message", "Close", 300)
+        .variant("Message", "Hello", 400);
+    let se_config = SerializerConfig::new().discriminants(discriminants.clone());
+    let de_config = DeserializerConfig::new().discriminants(discriminants);
+
+    let messages = vec![v1::Message::Ping, v1::Message::Data(42, "le message".into()),
+                        v1::Message::Close { code: 1000 }];
+    let buf = rmps::to_vec_with(&messages, &se_config).unwrap();
+
+    let actual: Vec<v2::Message> = rmps::from_slice_with(&buf, &de_config).unwrap();
+    assert_eq!(vec![v2::Message::Ping, v2::Message::Data(42, "le message".into()),
+                    v2::Message::Close { code: 1000 }], actual);
+
+    // Unknown discriminants are rejected rather than decoded as another variant.
+    let buf = rmps::to_vec_with(&v2::Message::Hello(1), &se_config).unwrap();
+    assert_eq!([0x92, 0xcd, 0x01, 0x90], buf[..4]);
+    match rmps::from_slice_with::<v1::Message>(&buf, &de_config) {
+        Err(rmps::decode::Error::Syntax(..)) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn fail_enum_without_discriminant() {
+    use rmps::{Discriminants, SerializerConfig};
+
+    #[derive(Serialize)]
+    enum Status {
+        Active,
+        Deleted,
+    }
+
+    let config = SerializerConfig::new()
+        .discriminants(Discriminants::new().variant("Status", "Active", 1));
+
+    assert_eq!(vec![0x92, 0x01, 0x90], rmps::to_vec_with(&Status::Active, &config).unwrap());
+    match rmps::to_vec_with(&Status::Deleted, &config) {
+        Err(rmps::encode::Error::Syntax(..)) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Borrowing<'a> {
+    #[serde(borrow)]
+    name: Cow<'a, str>,
+    #[serde(borrow)]
+    data: Cow<'a, [u8]>,
+}
+
+fn borrowing_buf() -> Vec<u8> {
+    let mut buf = Vec::new();
+    rmp::encode::write_array_len(&mut buf, 2).unwrap();
+    rmp::encode::write_str(&mut buf, "le message").unwrap();
+    rmp::encode::write_bin(&mut buf, &[1, 2, 3]).unwrap();
+    buf
+}
+
+#[test]
+fn pass_cow_borrowed_from_slice() {
+    let buf = borrowing_buf();
+    let val: Borrowing = rmps::from_slice(&buf).unwrap();
+
+    assert_eq!(Cow::Borrowed("le message"), val.name);
+    assert_eq!(Cow::Borrowed(&[1u8, 2, 3][..]), val.data);
+    match (val.name, val.data) {
+        (Cow::Borrowed(..), Cow::Borrowed(..)) => {}
+        (name, data) => panic!("expected borrowed data, got {:?} and {:?}", name, data),
+    }
+}
+
+#[test]
+fn pass_cow_owned_from_read() {
+    let buf = borrowing_buf();
+    let val: Borrowing = Deserialize::deserialize(&mut Deserializer::new(Cursor::new(&buf[..])))
+        .unwrap();
+
+    match (val.name, val.data) {
+        (Cow::Owned(ref name), Cow::Owned(ref data)) => {
+            assert_eq!("le message", name);
+            assert_eq!(vec![1, 2, 3], *data);
+        }
+        (name, data) => panic!("expected owned data, got {:?} and {:?}", name, data),
+    }
+}
+
+#[test]
+fn pass_cow_bytes_owned_from_array() {
+    #[derive(Serialize)]
+    struct Plain<'a> {
+        name: &'a str,
+        data: &'a [u8],
+    }
+
+    let buf = rmps::to_vec(&Plain { name: "le message", data: &[1, 2, 3] }).unwrap();
+    let val: Borrowing = rmps::from_slice(&buf).unwrap();
+
+    match val.data {
+        Cow::Owned(data) => assert_eq!(vec![1, 2, 3], data),
+        Cow::Borrowed(data) => panic!("expected owned data, got {:?}", data),
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Time(#[serde(with = "rmps::timestamp")] std::time::SystemTime);
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Elapsed(#[serde(with = "rmps::timestamp::duration")] std::time::Duration);
+
+#[test]
+fn round_timestamp_formats() {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    let cases: Vec<(Duration, Vec<u8>)> = vec![
+        (Duration::from_secs(0), vec![0xd6, 0xff, 0x00, 0x00, 0x00, 0x00]),
+        (Duration::from_secs(0xffffffff), vec![0xd6, 0xff, 0xff, 0xff, 0xff, 0xff]),
+        (Duration::new(1, 1), vec![0xd7, 0xff, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x01]),
+        (Duration::from_secs(0x400000000), vec![
+            0xc7, 0x0c, 0xff, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x00,
+        ]),
+    ];
+
+    for (dur, ext) in cases {
+        // Newtype structs are wrapped into single-element arrays.
+        let mut expected = vec![0x91];
+        expected.extend_from_slice(&ext);
+
+        let buf = rmps::to_vec(&Time(UNIX_EPOCH + dur)).unwrap();
+        assert_eq!(expected, buf);
+        assert_eq!(Time(UNIX_EPOCH + dur), rmps::from_slice(&buf).unwrap());
+
+        assert_eq!(expected, rmps::to_vec(&Elapsed(dur)).unwrap());
+        assert_eq!(Elapsed(dur), rmps::from_slice(&buf).unwrap());
+    }
+}
+
+#[test]
+fn round_timestamp_before_epoch() {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    let time = Time(UNIX_EPOCH - Duration::new(1, 500000000));
+    let buf = rmps::to_vec(&time).unwrap();
+
+    // -2 seconds and 500000000 nanoseconds.
+    assert_eq!(vec![
+        0x91, 0xc7, 0x0c, 0xff, 0x1d, 0xcd, 0x65, 0x00,
+        0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xfe,
+    ], buf);
+    assert_eq!(time, rmps::from_slice(&buf).unwrap());
+
+    match rmps::from_slice::<Elapsed>(&buf) {
+        Err(rmps::decode::Error::Syntax(..)) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn fail_timestamp_from_other_values() {
+    // An extension of another type.
+    match rmps::from_slice::<Time>(&[0x91, 0xd6, 0x01, 0x00, 0x00, 0x00, 0x00]) {
+        Err(rmps::decode::Error::TypeMismatch(rmp::Marker::FixExt4)) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    // A plain integer.
+    match rmps::from_slice::<Time>(&[0x91, 0x01]) {
+        Err(rmps::decode::Error::TypeMismatch(rmp::Marker::FixPos(1))) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    // Nanoseconds of the 96-bit format out of range.
+    let buf = [0x91, 0xc7, 0x0c, 0xff, 0x3b, 0x9a, 0xca, 0x00, 0, 0, 0, 0, 0, 0, 0, 0];
+    match rmps::from_slice::<Time>(&buf) {
+        Err(rmps::decode::Error::Syntax(..)) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn pass_timestamp_traced() {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    let time = Time(UNIX_EPOCH + Duration::from_secs(1500000000));
+    let (buf, _) = rmps::encode::to_vec_traced(&time).unwrap();
+
+    assert_eq!(rmps::to_vec(&time).unwrap(), buf);
+}
+
+#[test]
+fn round_option_of_marker_peeking_types() {
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Newtype(u32);
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Enum {
+        Newtype(u32),
+    }
+
+    let val = (Some(Newtype(5)), Some(Enum::Newtype(6)), Some(Some(7u8)));
+    let buf = rmps::to_vec(&val).unwrap();
+    assert_eq!(val, rmps::from_slice(&buf).unwrap());
+}
+
+#[test]
+fn round_timestamp_option() {
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Event {
+        #[serde(with = "rmps::timestamp::option")]
+        at: Option<SystemTime>,
+    }
+
+    let event = Event { at: Some(UNIX_EPOCH + Duration::from_secs(1)) };
+    let buf = rmps::to_vec(&event).unwrap();
+    assert_eq!(vec![0x91, 0xd6, 0xff, 0x00, 0x00, 0x00, 0x01], buf);
+    assert_eq!(event, rmps::from_slice(&buf).unwrap());
+
+    let event = Event { at: None };
+    let buf = rmps::to_vec(&event).unwrap();
+    assert_eq!(vec![0x91, 0xc0], buf);
+    assert_eq!(event, rmps::from_slice(&buf).unwrap());
+}
+
+/// A calendar-free time type standing in for those of other crates.
+#[derive(Debug, PartialEq)]
+struct UnixTime {
+    secs: i64,
+    nanos: u32,
+}
+
+impl Serialize for UnixTime {
+    fn serialize<S: serde::Serializer>(&self, se: S) -> Result<S::Ok, S::Error> {
+        rmps::timestamp::serialize_parts(self.secs, self.nanos, se)
+    }
+}
+
+impl<'de> Deserialize<'de> for UnixTime {
+    fn deserialize<D: serde::Deserializer<'de>>(de: D) -> Result<Self, D::Error> {
+        let (secs, nanos) = rmps::timestamp::deserialize_parts(de)?;
+        Ok(UnixTime { secs, nanos })
+    }
+}
+
+#[test]
+fn round_timestamp_parts() {
+    let time = UnixTime { secs: -1, nanos: 5 };
+    let buf = rmps::to_vec(&time).unwrap();
+    assert_eq!(vec![
+        0xc7, 0x0c, 0xff, 0x00, 0x00, 0x00, 0x05,
+        0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    ], buf);
+    assert_eq!(time, rmps::from_slice(&buf).unwrap());
+
+    match rmps::to_vec(&UnixTime { secs: 0, nanos: 1_000_000_000 }) {
+        Err(rmps::encode::Error::Syntax(..)) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn round_trip_envelope_migrations() {
+    use rmps::envelope::{self, Versions};
+
+    #[derive(Serialize, Deserialize)]
+    struct V1(u32);
+
+    #[derive(Serialize, Deserialize)]
+    struct V2 {
+        count: u32,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct V3 {
+        count: u64,
+        label: String,
+    }
+
+    impl From<V1> for V2 {
+        fn from(v1: V1) -> V2 {
+            V2 { count: v1.0 }
+        }
+    }
+
+    impl From<V2> for V3 {
+        fn from(v2: V2) -> V3 {
+            V3 { count: v2.count as u64, label: String::new() }
+        }
+    }
+
+    let versions = Versions::new()
+        .migrate(1, |v1: V1| V3::from(V2::from(v1)))
+        .version::<V2>(2)
+        .version::<V3>(3);
+
+    let buf = envelope::to_vec(1, &V1(7)).unwrap();
+    assert_eq!(vec![0x92, 0x01, 0x91, 0x07], buf);
+    assert_eq!(V3 { count: 7, label: String::new() }, versions.from_slice(&buf).unwrap());
+
+    let buf = envelope::to_vec(2, &V2 { count: 8 }).unwrap();
+    assert_eq!(V3 { count: 8, label: String::new() }, versions.from_slice(&buf).unwrap());
+
+    let val = V3 { count: 9, label: "x".into() };
+    let mut buf = Vec::new();
+    envelope::write(&mut buf, 3, &val).unwrap();
+    assert_eq!((3, &buf[2..]), envelope::read_version(&buf).unwrap());
+    assert_eq!(val, versions.from_slice(&buf).unwrap());
+}
+
+#[test]
+fn round_trip_envelope_unsupported_version() {
+    use rmps::decode::Error;
+    use rmps::envelope::{self, Versions};
+
+    let versions = Versions::<u8>::new().version::<u8>(1);
+    assert!(versions.contains(1));
+    assert!(!versions.contains(2));
+
+    let buf = envelope::to_vec(2, &42u8).unwrap();
+    match versions.from_slice(&buf) {
+        Err(Error::UnsupportedVersion(2)) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    // Not an envelope at all.
+    match versions.from_slice(&[0x93, 0x01, 0x02, 0x03]) {
+        Err(Error::LengthMismatch(3)) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn round_trip_frames() {
+    use rmps::decode::Error;
+    use rmps::framed::{self, FrameDecoder};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Request {
+        id: u32,
+        method: String,
+    }
+
+    let req = Request { id: 1, method: "ping".into() };
+    let mut buf = Vec::new();
+    framed::write_frame(&mut buf, &req).unwrap();
+    framed::write_frame(&mut buf, &[1u8, 2, 3]).unwrap();
+    assert_eq!([0, 0, 0, 7], buf[..4]);
+
+    let mut rd = &buf[..];
+    assert_eq!(req, framed::read_frame(&mut rd).unwrap());
+    assert_eq!([1u8, 2, 3], framed::read_frame::<_, [u8; 3]>(&mut rd).unwrap());
+    match framed::read_frame::<_, u8>(&mut rd) {
+        Err(Error::InvalidDataRead(..)) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    let mut dec = FrameDecoder::new();
+    let mut reqs = Vec::new();
+    for &byte in &buf[..11] {
+        dec.feed(&[byte]);
+        match dec.next_frame::<Request>() {
+            Ok(val) => reqs.push(val),
+            Err(Error::NeedMoreData(..)) => {}
+            Err(err) => panic!("unexpected error: {:?}", err),
+        }
+    }
+    assert_eq!(vec![req], reqs);
+    assert_eq!(0, dec.buffered());
+}
+
+#[test]
+fn round_trip_frames_malformed() {
+    use rmps::decode::{DeserializerConfig, Error};
+    use rmps::framed::{self, FrameDecoder};
+
+    // A frame of 3 bytes holding only 42.
+    let buf = [0, 0, 0, 3, 0x2a, 0xc0, 0xc0, 0, 0, 0, 1, 0x07];
+    match framed::read_frame::<_, u8>(&mut &buf[..]) {
+        Err(Error::Syntax(..)) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    // The decoder drops the bad frame and goes on with the next one.
+    let mut dec = FrameDecoder::new();
+    dec.feed(&buf);
+    match dec.next_frame::<u8>() {
+        Err(Error::Syntax(..)) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+    assert_eq!(7u8, dec.next_frame().unwrap());
+
+    // A frame claiming 4 GiB is rejected before reading it.
+    let config = DeserializerConfig::new().max_bytes(1024);
+    let buf = [0xff, 0xff, 0xff, 0xff, 0xc0];
+    match framed::read_frame_with::<_, ()>(&mut &buf[..], &config) {
+        Err(Error::LengthLimitExceeded(..)) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    let mut dec = FrameDecoder::new();
+    dec.set_config(&config);
+    dec.feed(&buf);
+    match dec.next_frame::<()>() {
+        Err(Error::LengthLimitExceeded(..)) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn round_ext_wrappers() {
+    use rmps::{Ext, ExtBuf};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Packet<'a> {
+        #[serde(borrow)]
+        small: Ext<'a>,
+        large: ExtBuf,
+        missing: Option<ExtBuf>,
+    }
+
+    let packet = Packet {
+        small: Ext(-5, &[0xcc; 16]),
+        large: ExtBuf(7, vec![0xcc; 300]),
+        missing: None,
+    };
+    let buf = rmps::to_vec(&packet).unwrap();
+    // The first field is a fixext 16, the second an ext 16.
+    assert_eq!(&[0x93, 0xd8, 0xfb], &buf[..3]);
+    assert_eq!(&[0xc8, 0x01, 0x2c, 0x07], &buf[19..23]);
+
+    let decoded: Packet = rmps::from_slice(&buf).unwrap();
+    assert_eq!(packet, decoded);
+    assert_eq!(buf[3..].as_ptr(), decoded.small.1.as_ptr());
+
+    // Owned extensions can be read from a reader.
+    let mut de = Deserializer::new(Cursor::new(&buf[19..]));
+    let ext = ExtBuf::deserialize(&mut de).unwrap();
+    assert_eq!(packet.large, ext);
+    assert_eq!(Ext(7, &[0xcc; 300][..]), ext.as_ext());
+    assert_eq!(ExtBuf(-5, vec![0xcc; 16]), ExtBuf::from(packet.small));
+}
+
+#[test]
+fn round_raw_value() {
+    use rmps::RawValue;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Envelope {
+        id: u32,
+        payload: RawValue,
+        tail: bool,
+    }
+
+    // A map with a non-canonical `u16` 1, which must be forwarded as it is, and an extension.
+    let payload = [0x82, 0xa1, 0x61, 0xcd, 0x00, 0x01, 0xa1, 0x62, 0xd4, 0x05, 0x2a];
+    let mut buf = vec![0x93, 0x07];
+    buf.extend_from_slice(&payload);
+    buf.push(0xc3);
+
+    let env: Envelope = rmps::from_slice(&buf).unwrap();
+    assert_eq!(7, env.id);
+    assert_eq!(&payload[..], env.payload.as_bytes());
+    assert!(env.tail);
+    assert_eq!(buf, rmps::to_vec(&env).unwrap());
+
+    let mut de = Deserializer::new(Cursor::new(&buf[..]));
+    assert_eq!(env, Envelope::deserialize(&mut de).unwrap());
+
+    let raw = RawValue::from_value(&(1, "a")).unwrap();
+    assert_eq!(&[0x92, 0x01, 0xa1, 0x61], raw.as_bytes());
+    assert_eq!(raw, RawValue::from_vec(raw.clone().into_vec()).unwrap());
+    assert!(RawValue::from_vec(vec![0x92, 0x01]).is_err());
+    assert!(RawValue::from_vec(vec![0x01, 0x02]).is_err());
+    assert!(RawValue::from_vec(vec![0xc1]).is_err());
+}
+
+#[test]
+fn round_neovim_handles() {
+    use rmps::neovim::{self, Buffer, Tabpage, Window};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Split {
+        Left,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Layout {
+        split: Split,
+        win: Window,
+        tab: Tabpage,
+        bufs: Vec<Buffer>,
+    }
+
+    let layout = Layout {
+        split: Split::Left,
+        win: Window(1000),
+        tab: Tabpage(1),
+        bufs: vec![Buffer(0), Buffer(-1), Buffer(i64::MAX)],
+    };
+    let buf = rmps::to_vec_with(&layout, &neovim::serializer_config()).unwrap();
+    // {"split": "Left", ...
+    assert_eq!(&[0x84, 0xa5, 0x73, 0x70, 0x6c, 0x69, 0x74, 0xa4, 0x4c], &buf[..9]);
+    // ... "win": ext 8 of type 1 holding the uint 16 1000, "tab": fixext 1 of type 2, ...
+    assert_eq!(&[0xa3, 0x77, 0x69, 0x6e, 0xc7, 0x03, 0x01, 0xcd, 0x03, 0xe8], &buf[12..22]);
+    assert_eq!(&[0xa3, 0x74, 0x61, 0x62, 0xd4, 0x02, 0x01], &buf[22..29]);
+
+    let config = neovim::deserializer_config();
+    assert_eq!(layout, rmps::from_slice_with(&buf, &config).unwrap());
+    assert_eq!(layout, rmps::from_read_with(Cursor::new(&buf[..]), &config).unwrap());
+}
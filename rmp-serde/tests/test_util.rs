@@ -0,0 +1,87 @@
+#![cfg(feature = "test-util")]
+
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate rmp_serde as rmps;
+
+use std::collections::BTreeMap;
+use std::panic;
+
+use rmps::test_util::{assert_bytes_eq_canonical, assert_reencode_corpus, assert_roundtrip,
+                      assert_roundtrip_corpus, canonicalize};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Point {
+    x: i32,
+    y: i32,
+}
+
+/// Accepts any integer, but always reads it as zero.
+#[derive(Debug, PartialEq)]
+struct Lossy(u32);
+
+impl serde::Serialize for Lossy {
+    fn serialize<S: serde::Serializer>(&self, se: S) -> Result<S::Ok, S::Error> {
+        se.serialize_u32(self.0)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Lossy {
+    fn deserialize<D: serde::Deserializer<'de>>(de: D) -> Result<Self, D::Error> {
+        u32::deserialize(de).map(|_| Lossy(0))
+    }
+}
+
+#[test]
+fn pass_roundtrip() {
+    assert_eq!(vec![0x92, 0x01, 0xff], assert_roundtrip(&Point { x: 1, y: -1 }));
+    assert_roundtrip_corpus(vec![Some("a".to_string()), None]);
+}
+
+#[test]
+fn fail_roundtrip_reports_corpus_index() {
+    let err = panic::catch_unwind(|| assert_roundtrip_corpus(vec![Lossy(0), Lossy(1)]))
+        .unwrap_err();
+    let msg = err.downcast_ref::<String>().unwrap();
+    assert!(msg.starts_with("corpus value #1: "), "{}", msg);
+}
+
+#[test]
+fn pass_canonicalize_integers_and_map_order() {
+    // {"b": 300 as u32, "a": 1 as i64}
+    let buf = [0x82, 0xa1, 0x62, 0xce, 0x00, 0x00, 0x01, 0x2c,
+               0xa1, 0x61, 0xd3, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01];
+    assert_eq!(vec![0x82, 0xa1, 0x61, 0x01, 0xa1, 0x62, 0xcd, 0x01, 0x2c],
+               canonicalize(&buf).unwrap());
+
+    let mut map = BTreeMap::new();
+    map.insert("a", 1);
+    map.insert("b", 300);
+    assert_bytes_eq_canonical(&rmps::to_vec(&map).unwrap(), &buf);
+}
+
+#[test]
+fn fail_canonicalize_trailing_and_truncated() {
+    assert!(canonicalize(&[0xc0, 0xc0]).is_err());
+    assert!(canonicalize(&[0xa2, 0x61]).is_err());
+    assert!(canonicalize(&[0x92, 0x01]).is_err());
+}
+
+#[test]
+#[should_panic(expected = "encodings differ")]
+fn fail_bytes_eq_canonical() {
+    assert_bytes_eq_canonical(&[0x01], &[0xcb, 0x3f, 0xf0, 0, 0, 0, 0, 0, 0]);
+}
+
+#[test]
+fn pass_reencode_corpus() {
+    assert_reencode_corpus::<Point>(&[&[0x92, 0xd0, 0x01, 0xff],
+                                      &[0x82, 0xa1, 0x79, 0xff, 0xa1, 0x78, 0x01]]);
+}
+
+#[test]
+#[should_panic(expected = "corpus sample #1")]
+fn fail_reencode_corpus_lossy() {
+    assert_reencode_corpus::<Lossy>(&[&[0x00], &[0x01]]);
+}
@@ -43,3 +43,67 @@ fn from_string_read_str(b: &mut Bencher) {
         test::black_box(res);
     });
 }
+
+/// Unsigned and signed integers of every width, as in telemetry or columnar payloads.
+fn integers() -> Vec<i64> {
+    (0..1024i64).map(|n| (n * 0x1234_5678_9abc).rotate_left(n as u32) >> (n % 64)).collect()
+}
+
+#[bench]
+fn from_integers_write_sint(b: &mut Bencher) {
+    let vals = integers();
+    let mut buf = Vec::with_capacity(9 * vals.len());
+
+    b.iter(|| {
+        buf.clear();
+        for &val in &vals {
+            rmp::encode::write_sint(&mut buf, val).unwrap();
+        }
+        test::black_box(&buf);
+    });
+}
+
+#[bench]
+fn from_integers_write_i64(b: &mut Bencher) {
+    let vals = integers();
+    let mut buf = Vec::with_capacity(9 * vals.len());
+
+    b.iter(|| {
+        buf.clear();
+        for &val in &vals {
+            rmp::encode::write_i64(&mut buf, val).unwrap();
+        }
+        test::black_box(&buf);
+    });
+}
+
+#[bench]
+fn from_integers_read_int(b: &mut Bencher) {
+    let mut buf = Vec::new();
+    for val in integers() {
+        rmp::encode::write_sint(&mut buf, val).unwrap();
+    }
+
+    b.iter(|| {
+        let mut rd = &buf[..];
+        while !rd.is_empty() {
+            let res: i64 = read_int(&mut rd).unwrap();
+            test::black_box(res);
+        }
+    });
+}
+
+#[bench]
+fn from_f64s_read_f64(b: &mut Bencher) {
+    let mut buf = Vec::new();
+    for val in integers() {
+        rmp::encode::write_f64(&mut buf, val as f64).unwrap();
+    }
+
+    b.iter(|| {
+        let mut rd = &buf[..];
+        while !rd.is_empty() {
+            test::black_box(read_f64(&mut rd).unwrap());
+        }
+    });
+}
@@ -0,0 +1,404 @@
+use std::error;
+use std::fmt::{self, Display, Formatter};
+use std::io::{self, ErrorKind, Read, Write};
+
+use Marker;
+use decode::{read_marker, read_data_u8, read_data_u16, read_data_u32, read_data_u64,
+             read_data_i8, read_data_i16, read_data_i32, read_data_i64, read_data_f32,
+             read_data_f64, MarkerReadError, ValueReadError};
+use encode::{write_nil, write_bool, write_uint, write_sint, write_f32, write_f64, write_str_len,
+             write_bin_len, write_array_len, write_map_len, write_ext_meta, ValueWriteError};
+
+/// The maximum nesting depth `canonicalize` accepts.
+const MAX_DEPTH: usize = 1024;
+
+/// An error which can occur when transcoding a MessagePack value into its canonical form.
+#[derive(Debug)]
+pub enum CanonicalizeError {
+    /// Failed to read the marker.
+    InvalidMarkerRead(io::Error),
+    /// Failed to read the data.
+    InvalidDataRead(io::Error),
+    /// The input contains a reserved marker, which has no canonical form.
+    TypeMismatch(Marker),
+    /// Failed to write the marker.
+    InvalidMarkerWrite(io::Error),
+    /// Failed to write the data.
+    InvalidDataWrite(io::Error),
+    /// A map contains two keys with the same canonical encoding.
+    DuplicateKey,
+    /// The input is nested deeper than the transcoder allows.
+    DepthLimitExceeded,
+}
+
+impl error::Error for CanonicalizeError {
+    fn description(&self) -> &str {
+        match *self {
+            CanonicalizeError::InvalidMarkerRead(..) => "failed to read MessagePack marker",
+            CanonicalizeError::InvalidDataRead(..) => "failed to read MessagePack data",
+            CanonicalizeError::TypeMismatch(..) => "reserved MessagePack marker found",
+            CanonicalizeError::InvalidMarkerWrite(..) => "failed to write MessagePack marker",
+            CanonicalizeError::InvalidDataWrite(..) => "failed to write MessagePack data",
+            CanonicalizeError::DuplicateKey => "duplicate key found in MessagePack map",
+            CanonicalizeError::DepthLimitExceeded => "depth limit exceeded",
+        }
+    }
+
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            CanonicalizeError::InvalidMarkerRead(ref err) |
+            CanonicalizeError::InvalidDataRead(ref err) |
+            CanonicalizeError::InvalidMarkerWrite(ref err) |
+            CanonicalizeError::InvalidDataWrite(ref err) => Some(err),
+            CanonicalizeError::TypeMismatch(..) |
+            CanonicalizeError::DuplicateKey |
+            CanonicalizeError::DepthLimitExceeded => None,
+        }
+    }
+}
+
+impl Display for CanonicalizeError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        error::Error::description(self).fmt(f)
+    }
+}
+
+impl From<MarkerReadError> for CanonicalizeError {
+    fn from(err: MarkerReadError) -> CanonicalizeError {
+        CanonicalizeError::InvalidMarkerRead(err.0)
+    }
+}
+
+impl From<ValueReadError> for CanonicalizeError {
+    fn from(err: ValueReadError) -> CanonicalizeError {
+        match err {
+            ValueReadError::InvalidMarkerRead(err) => CanonicalizeError::InvalidMarkerRead(err),
+            ValueReadError::InvalidDataRead(err) => CanonicalizeError::InvalidDataRead(err),
+            ValueReadError::TypeMismatch(marker) => CanonicalizeError::TypeMismatch(marker),
+        }
+    }
+}
+
+impl From<ValueWriteError> for CanonicalizeError {
+    fn from(err: ValueWriteError) -> CanonicalizeError {
+        match err {
+            ValueWriteError::InvalidMarkerWrite(err) => CanonicalizeError::InvalidMarkerWrite(err),
+            ValueWriteError::InvalidDataWrite(err) => CanonicalizeError::InvalidDataWrite(err),
+        }
+    }
+}
+
+/// Copies exactly `len` bytes from the reader to the writer.
+fn copy_data<R: Read, W: Write>(rd: &mut R, wr: &mut W, len: u32) -> Result<(), CanonicalizeError> {
+    let mut buf = [0u8; 4096];
+    let mut rd = rd.take(len as u64);
+
+    loop {
+        let nread = match rd.read(&mut buf) {
+            Ok(0) => break,
+            Ok(nread) => nread,
+            Err(ref err) if err.kind() == ErrorKind::Interrupted => continue,
+            Err(err) => return Err(CanonicalizeError::InvalidDataRead(err)),
+        };
+        wr.write_all(&buf[..nread]).map_err(CanonicalizeError::InvalidDataWrite)?;
+    }
+
+    if rd.limit() > 0 {
+        let err = io::Error::from(ErrorKind::UnexpectedEof);
+        return Err(CanonicalizeError::InvalidDataRead(err));
+    }
+
+    Ok(())
+}
+
+fn transcode_f64<W: Write>(wr: &mut W, val: f64) -> Result<(), ValueWriteError> {
+    if val.is_nan() {
+        write_f32(wr, f32::NAN)
+    } else if val as f32 as f64 == val {
+        write_f32(wr, val as f32)
+    } else {
+        write_f64(wr, val)
+    }
+}
+
+/// A container which is being transcoded.
+enum Frame {
+    /// An array with the given number of elements left.
+    Array(u32),
+    /// A map, whose entries are buffered until all of them are read to be sorted.
+    Map {
+        len: u32,
+        entries: Vec<(Vec<u8>, Vec<u8>)>,
+        key: Option<Vec<u8>>,
+        buf: Vec<u8>,
+    },
+}
+
+/// Where the transcoded bytes go: into the innermost map being buffered or into the output.
+enum Sink<'a, W: 'a> {
+    Buf(&'a mut Vec<u8>),
+    Out(&'a mut W),
+}
+
+impl<'a, W: Write> Write for Sink<'a, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match *self {
+            Sink::Buf(ref mut vec) => vec.write(buf),
+            Sink::Out(ref mut wr) => wr.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match *self {
+            Sink::Buf(ref mut vec) => vec.flush(),
+            Sink::Out(ref mut wr) => wr.flush(),
+        }
+    }
+}
+
+fn sink<'a, W: Write>(stack: &'a mut [Frame], wr: &'a mut W) -> Sink<'a, W> {
+    for frame in stack.iter_mut().rev() {
+        if let Frame::Map { ref mut buf, .. } = *frame {
+            return Sink::Buf(buf);
+        }
+    }
+
+    Sink::Out(wr)
+}
+
+fn write_entries<W: Write>(wr: &mut W, len: u32, mut entries: Vec<(Vec<u8>, Vec<u8>)>)
+    -> Result<(), CanonicalizeError>
+{
+    entries.sort_by(|lhs, rhs| lhs.0.cmp(&rhs.0));
+    if entries.windows(2).any(|pair| pair[0].0 == pair[1].0) {
+        return Err(CanonicalizeError::DuplicateKey);
+    }
+
+    write_map_len(wr, len)?;
+    for (key, val) in entries {
+        wr.write_all(&key).map_err(CanonicalizeError::InvalidDataWrite)?;
+        wr.write_all(&val).map_err(CanonicalizeError::InvalidDataWrite)?;
+    }
+
+    Ok(())
+}
+
+fn read_ext_len<R: Read>(rd: &mut R, marker: Marker) -> Result<u32, CanonicalizeError> {
+    let len = match marker {
+        Marker::FixExt1 => 1,
+        Marker::FixExt2 => 2,
+        Marker::FixExt4 => 4,
+        Marker::FixExt8 => 8,
+        Marker::FixExt16 => 16,
+        Marker::Ext8 => read_data_u8(rd)? as u32,
+        Marker::Ext16 => read_data_u16(rd)? as u32,
+        _ => read_data_u32(rd)?,
+    };
+
+    Ok(len)
+}
+
+/// Transcodes a single marker with its data, returning the container it opens, if any.
+fn transcode_item<R, W>(rd: &mut R, wr: &mut W) -> Result<Option<Frame>, CanonicalizeError>
+    where R: Read,
+          W: Write
+{
+    match read_marker(rd)? {
+        Marker::Null => write_nil(wr).map_err(CanonicalizeError::InvalidMarkerWrite)?,
+        Marker::True => write_bool(wr, true).map_err(CanonicalizeError::InvalidMarkerWrite)?,
+        Marker::False => write_bool(wr, false).map_err(CanonicalizeError::InvalidMarkerWrite)?,
+        Marker::FixPos(val) => {
+            write_uint(wr, val as u64)?;
+        }
+        Marker::FixNeg(val) => {
+            write_sint(wr, val as i64)?;
+        }
+        Marker::U8 => {
+            write_uint(wr, read_data_u8(rd)? as u64)?;
+        }
+        Marker::U16 => {
+            write_uint(wr, read_data_u16(rd)? as u64)?;
+        }
+        Marker::U32 => {
+            write_uint(wr, read_data_u32(rd)? as u64)?;
+        }
+        Marker::U64 => {
+            write_uint(wr, read_data_u64(rd)?)?;
+        }
+        Marker::I8 => {
+            write_sint(wr, read_data_i8(rd)? as i64)?;
+        }
+        Marker::I16 => {
+            write_sint(wr, read_data_i16(rd)? as i64)?;
+        }
+        Marker::I32 => {
+            write_sint(wr, read_data_i32(rd)? as i64)?;
+        }
+        Marker::I64 => {
+            write_sint(wr, read_data_i64(rd)?)?;
+        }
+        Marker::F32 => transcode_f64(wr, read_data_f32(rd)? as f64)?,
+        Marker::F64 => transcode_f64(wr, read_data_f64(rd)?)?,
+        marker @ Marker::FixStr(..) |
+        marker @ Marker::Str8 |
+        marker @ Marker::Str16 |
+        marker @ Marker::Str32 => {
+            let len = match marker {
+                Marker::FixStr(len) => len as u32,
+                Marker::Str8 => read_data_u8(rd)? as u32,
+                Marker::Str16 => read_data_u16(rd)? as u32,
+                _ => read_data_u32(rd)?,
+            };
+            write_str_len(wr, len)?;
+            copy_data(rd, wr, len)?;
+        }
+        marker @ Marker::Bin8 |
+        marker @ Marker::Bin16 |
+        marker @ Marker::Bin32 => {
+            let len = match marker {
+                Marker::Bin8 => read_data_u8(rd)? as u32,
+                Marker::Bin16 => read_data_u16(rd)? as u32,
+                _ => read_data_u32(rd)?,
+            };
+            write_bin_len(wr, len)?;
+            copy_data(rd, wr, len)?;
+        }
+        marker @ Marker::FixArray(..) |
+        marker @ Marker::Array16 |
+        marker @ Marker::Array32 => {
+            let len = match marker {
+                Marker::FixArray(len) => len as u32,
+                Marker::Array16 => read_data_u16(rd)? as u32,
+                _ => read_data_u32(rd)?,
+            };
+            write_array_len(wr, len)?;
+            if len > 0 {
+                return Ok(Some(Frame::Array(len)));
+            }
+        }
+        marker @ Marker::FixMap(..) |
+        marker @ Marker::Map16 |
+        marker @ Marker::Map32 => {
+            let len = match marker {
+                Marker::FixMap(len) => len as u32,
+                Marker::Map16 => read_data_u16(rd)? as u32,
+                _ => read_data_u32(rd)?,
+            };
+            if len == 0 {
+                write_map_len(wr, 0)?;
+            } else {
+                let frame = Frame::Map {
+                    len: len,
+                    entries: Vec::new(),
+                    key: None,
+                    buf: Vec::new(),
+                };
+                return Ok(Some(frame));
+            }
+        }
+        marker @ Marker::FixExt1 |
+        marker @ Marker::FixExt2 |
+        marker @ Marker::FixExt4 |
+        marker @ Marker::FixExt8 |
+        marker @ Marker::FixExt16 |
+        marker @ Marker::Ext8 |
+        marker @ Marker::Ext16 |
+        marker @ Marker::Ext32 => {
+            let len = read_ext_len(rd, marker)?;
+            let ty = read_data_i8(rd)?;
+            write_ext_meta(wr, len, ty)?;
+            copy_data(rd, wr, len)?;
+        }
+        Marker::Reserved => return Err(CanonicalizeError::TypeMismatch(Marker::Reserved)),
+    }
+
+    Ok(None)
+}
+
+/// Reads a single MessagePack value from the given reader and writes it to the given writer in
+/// canonical form.
+///
+/// The canonical form is defined as follows:
+///
+/// - integers, strings, binaries, arrays, maps and extensions use the most compact marker that can
+///   hold them, so non-negative integers are always written using unsigned markers;
+/// - floats are written as `f32` if that is lossless, and all NaNs are written as the same `f32`
+///   NaN;
+/// - map entries are sorted bytewise by the canonical encoding of their keys, and maps with
+///   duplicate keys are rejected.
+///
+/// Values that differ only in their encoding or in the order of map entries thus produce identical
+/// bytes, which makes the output suitable for hashing or content-addressed storage.
+///
+/// Everything outside maps is transcoded on the fly. Map entries have to be buffered for sorting,
+/// so the memory used is bounded by the size of the largest map in the input. To transcode a
+/// stream of several values call this function repeatedly.
+///
+/// # Errors
+///
+/// Returns `CanonicalizeError` on any I/O error, on reserved markers, on duplicate map keys, and
+/// if the input is nested deeper than 1024 levels.
+///
+/// # Examples
+///
+/// ```
+/// // {"b": 1u64, "a": 1.0f64}
+/// let buf = [0x82, 0xa1, 0x62, 0xcf, 0, 0, 0, 0, 0, 0, 0, 0x01,
+///            0xa1, 0x61, 0xcb, 0x3f, 0xf0, 0, 0, 0, 0, 0, 0];
+/// let mut out = Vec::new();
+///
+/// rmp::canonicalize(&mut &buf[..], &mut out).unwrap();
+///
+/// assert_eq!(vec![0x82, 0xa1, 0x61, 0xca, 0x3f, 0x80, 0, 0, 0xa1, 0x62, 0x01], out);
+/// ```
+pub fn canonicalize<R, W>(rd: &mut R, wr: &mut W) -> Result<(), CanonicalizeError>
+    where R: Read,
+          W: Write
+{
+    // Containers are tracked explicitly rather than by recursion, so that the nesting depth is
+    // limited by `MAX_DEPTH` instead of the stack size.
+    let mut stack = Vec::new();
+
+    loop {
+        let frame = {
+            let mut wr = sink(&mut stack, wr);
+            transcode_item(rd, &mut wr)?
+        };
+
+        if let Some(frame) = frame {
+            if stack.len() == MAX_DEPTH {
+                return Err(CanonicalizeError::DepthLimitExceeded);
+            }
+            stack.push(frame);
+            continue;
+        }
+
+        // An item is complete, walk up through all containers it completes.
+        loop {
+            let done = match stack.last_mut() {
+                None => return Ok(()),
+                Some(&mut Frame::Array(ref mut remaining)) => {
+                    *remaining -= 1;
+                    *remaining == 0
+                }
+                Some(&mut Frame::Map { ref mut len, ref mut entries, ref mut key, ref mut buf }) => {
+                    let item = ::std::mem::take(buf);
+                    match key.take() {
+                        None => *key = Some(item),
+                        Some(k) => entries.push((k, item)),
+                    }
+                    key.is_none() && entries.len() == *len as usize
+                }
+            };
+
+            if !done {
+                break;
+            }
+
+            if let Some(Frame::Map { len, entries, .. }) = stack.pop() {
+                let mut wr = sink(&mut stack, wr);
+                write_entries(&mut wr, len, entries)?;
+            }
+        }
+    }
+}
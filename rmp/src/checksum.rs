@@ -0,0 +1,378 @@
+//! Checksums over the exact encoded bytes of values.
+//!
+//! Storage formats often keep a checksum next to each record to detect damaged data. Computing it
+//! over a value re-encoded after decoding would miss damage that decodes fine and would depend on
+//! the encoder choosing the same markers, so the adapters here hash the bytes as they pass
+//! through instead. `ChecksumReader` and `ChecksumWriter` wrap a reader or a writer, and feed a
+//! `Hasher` with every byte the decoding or encoding functions of this crate read or write
+//! through them.
+//!
+//! Any `core::hash::Hasher` can be used. This module provides `Crc32`, the CRC-32 of zlib and
+//! PNG, and `XxHash64`, the 64-bit xxHash, which is much faster on large values.
+//!
+//! With the `std` feature the adapters implement `io::Read` and `io::Write`, so they also work
+//! with other consumers of readers and writers. Without it they implement `RmpRead` and
+//! `RmpWrite`.
+//!
+//! # Examples
+//!
+//! ```
+//! use rmp::{decode, encode};
+//! use rmp::checksum::{self, Crc32};
+//!
+//! let mut buf = Vec::new();
+//! let ((), crc) = checksum::write_with_checksum(&mut buf, Crc32::new(), |wr| {
+//!     encode::write_array_len(wr, 2)?;
+//!     encode::write_str(wr, "id")?;
+//!     encode::write_uint(wr, 300).map(|_| ())
+//! }).unwrap();
+//! assert_eq!(checksum::crc32(&buf) as u64, crc);
+//!
+//! let mut rd = &buf[..];
+//! let (len, digest) = checksum::read_with_checksum(&mut rd, Crc32::new(), |rd| {
+//!     let len = decode::read_array_len(rd)?;
+//!     decode::skip_value(rd)?;
+//!     decode::skip_value(rd)?;
+//!     Ok::<_, decode::ValueReadError>(len)
+//! }).unwrap();
+//! assert_eq!((2, crc), (len, digest));
+//! ```
+
+use core::hash::Hasher;
+#[cfg(feature = "std")]
+use std::io;
+
+#[cfg(not(feature = "std"))]
+use decode::RmpRead;
+#[cfg(not(feature = "std"))]
+use encode::RmpWrite;
+
+const CRC32_POLY: u32 = 0xedb8_8320;
+
+const CRC32_TABLE: [u32; 256] = crc32_table();
+
+const fn crc32_table() -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ CRC32_POLY } else { crc >> 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+/// Returns the CRC-32 of the given data.
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = Crc32::new();
+    crc.write(data);
+    crc.crc
+}
+
+/// Returns the 64-bit xxHash of the given data with the given seed.
+pub fn xxhash64(data: &[u8], seed: u64) -> u64 {
+    let mut hasher = XxHash64::with_seed(seed);
+    hasher.write(data);
+    hasher.finish()
+}
+
+/// A hasher computing the CRC-32 of the data written to it, with the reflected polynomial
+/// `0xedb88320` of zlib, PNG and Ethernet.
+///
+/// `finish` returns the checksum in the lower 32 bits.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Crc32 {
+    crc: u32,
+}
+
+impl Crc32 {
+    /// Constructs a hasher with nothing written yet.
+    pub fn new() -> Self {
+        Crc32::default()
+    }
+}
+
+impl Hasher for Crc32 {
+    fn write(&mut self, data: &[u8]) {
+        let mut crc = !self.crc;
+        for &byte in data {
+            crc = CRC32_TABLE[((crc ^ byte as u32) & 0xff) as usize] ^ (crc >> 8);
+        }
+        self.crc = !crc;
+    }
+
+    fn finish(&self) -> u64 {
+        self.crc as u64
+    }
+}
+
+const PRIME64_1: u64 = 0x9e37_79b1_85eb_ca87;
+const PRIME64_2: u64 = 0xc2b2_ae3d_27d4_eb4f;
+const PRIME64_3: u64 = 0x1656_67b1_9e37_79f9;
+const PRIME64_4: u64 = 0x85eb_ca77_c2b2_ae63;
+const PRIME64_5: u64 = 0x27d4_eb2f_1656_67c5;
+
+/// A hasher computing the 64-bit xxHash (XXH64) of the data written to it.
+#[derive(Clone, Copy, Debug)]
+pub struct XxHash64 {
+    seed: u64,
+    acc: [u64; 4],
+    // Bytes written since the last complete stripe of 32 bytes.
+    stripe: [u8; 32],
+    stripe_len: usize,
+    total_len: u64,
+}
+
+impl XxHash64 {
+    /// Constructs a hasher with a seed of zero.
+    pub fn new() -> Self {
+        XxHash64::with_seed(0)
+    }
+
+    /// Constructs a hasher with the given seed.
+    pub fn with_seed(seed: u64) -> Self {
+        XxHash64 {
+            seed,
+            acc: [
+                seed.wrapping_add(PRIME64_1).wrapping_add(PRIME64_2),
+                seed.wrapping_add(PRIME64_2),
+                seed,
+                seed.wrapping_sub(PRIME64_1),
+            ],
+            stripe: [0; 32],
+            stripe_len: 0,
+            total_len: 0,
+        }
+    }
+
+    fn consume_stripe(acc: &mut [u64; 4], stripe: &[u8]) {
+        for (lane, acc) in acc.iter_mut().enumerate() {
+            *acc = xxh64_round(*acc, read_u64_le(&stripe[lane * 8..]));
+        }
+    }
+}
+
+impl Default for XxHash64 {
+    fn default() -> Self {
+        XxHash64::new()
+    }
+}
+
+impl Hasher for XxHash64 {
+    fn write(&mut self, mut data: &[u8]) {
+        self.total_len += data.len() as u64;
+
+        if self.stripe_len > 0 {
+            let len = (32 - self.stripe_len).min(data.len());
+            self.stripe[self.stripe_len..self.stripe_len + len].copy_from_slice(&data[..len]);
+            self.stripe_len += len;
+            data = &data[len..];
+
+            if self.stripe_len < 32 {
+                return;
+            }
+            let stripe = self.stripe;
+            XxHash64::consume_stripe(&mut self.acc, &stripe);
+            self.stripe_len = 0;
+        }
+
+        while data.len() >= 32 {
+            XxHash64::consume_stripe(&mut self.acc, &data[..32]);
+            data = &data[32..];
+        }
+
+        self.stripe[..data.len()].copy_from_slice(data);
+        self.stripe_len = data.len();
+    }
+
+    fn finish(&self) -> u64 {
+        let mut hash = if self.total_len >= 32 {
+            let [v1, v2, v3, v4] = self.acc;
+            let mut hash = v1.rotate_left(1)
+                .wrapping_add(v2.rotate_left(7))
+                .wrapping_add(v3.rotate_left(12))
+                .wrapping_add(v4.rotate_left(18));
+            for &acc in &self.acc {
+                hash = (hash ^ xxh64_round(0, acc)).wrapping_mul(PRIME64_1).wrapping_add(PRIME64_4);
+            }
+            hash
+        } else {
+            self.seed.wrapping_add(PRIME64_5)
+        };
+        hash = hash.wrapping_add(self.total_len);
+
+        let mut rest = &self.stripe[..self.stripe_len];
+        while rest.len() >= 8 {
+            hash ^= xxh64_round(0, read_u64_le(rest));
+            hash = hash.rotate_left(27).wrapping_mul(PRIME64_1).wrapping_add(PRIME64_4);
+            rest = &rest[8..];
+        }
+        if rest.len() >= 4 {
+            let lane = u32::from_le_bytes([rest[0], rest[1], rest[2], rest[3]]) as u64;
+            hash ^= lane.wrapping_mul(PRIME64_1);
+            hash = hash.rotate_left(23).wrapping_mul(PRIME64_2).wrapping_add(PRIME64_3);
+            rest = &rest[4..];
+        }
+        for &byte in rest {
+            hash ^= (byte as u64).wrapping_mul(PRIME64_5);
+            hash = hash.rotate_left(11).wrapping_mul(PRIME64_1);
+        }
+
+        hash ^= hash >> 33;
+        hash = hash.wrapping_mul(PRIME64_2);
+        hash ^= hash >> 29;
+        hash = hash.wrapping_mul(PRIME64_3);
+        hash ^ (hash >> 32)
+    }
+}
+
+fn xxh64_round(acc: u64, lane: u64) -> u64 {
+    acc.wrapping_add(lane.wrapping_mul(PRIME64_2)).rotate_left(31).wrapping_mul(PRIME64_1)
+}
+
+fn read_u64_le(buf: &[u8]) -> u64 {
+    let mut bytes = [0; 8];
+    bytes.copy_from_slice(&buf[..8]);
+    u64::from_le_bytes(bytes)
+}
+
+/// A reader wrapper hashing every byte read through it.
+#[derive(Debug)]
+pub struct ChecksumReader<R, H> {
+    rd: R,
+    hasher: H,
+}
+
+impl<R, H: Hasher> ChecksumReader<R, H> {
+    /// Wraps the given reader, feeding the bytes read to the given hasher.
+    pub fn new(rd: R, hasher: H) -> Self {
+        ChecksumReader { rd, hasher }
+    }
+
+    /// Returns the digest of the bytes read so far.
+    pub fn digest(&self) -> u64 {
+        self.hasher.finish()
+    }
+
+    /// Gets a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.rd
+    }
+
+    /// Unwraps this `ChecksumReader`, returning the underlying reader and the digest of the
+    /// bytes read.
+    pub fn finish(self) -> (R, u64) {
+        let digest = self.digest();
+        (self.rd, digest)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: io::Read, H: Hasher> io::Read for ChecksumReader<R, H> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.rd.read(buf)?;
+        self.hasher.write(&buf[..len]);
+        Ok(len)
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl<R: RmpRead, H: Hasher> RmpRead for ChecksumReader<R, H> {
+    type Error = R::Error;
+
+    fn read_exact_buf(&mut self, buf: &mut [u8]) -> Result<(), Self::Error> {
+        self.rd.read_exact_buf(buf)?;
+        self.hasher.write(buf);
+        Ok(())
+    }
+}
+
+/// A writer wrapper hashing every byte written through it.
+#[derive(Debug)]
+pub struct ChecksumWriter<W, H> {
+    wr: W,
+    hasher: H,
+}
+
+impl<W, H: Hasher> ChecksumWriter<W, H> {
+    /// Wraps the given writer, feeding the bytes written to the given hasher.
+    pub fn new(wr: W, hasher: H) -> Self {
+        ChecksumWriter { wr, hasher }
+    }
+
+    /// Returns the digest of the bytes written so far.
+    pub fn digest(&self) -> u64 {
+        self.hasher.finish()
+    }
+
+    /// Gets a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.wr
+    }
+
+    /// Unwraps this `ChecksumWriter`, returning the underlying writer and the digest of the
+    /// bytes written.
+    pub fn finish(self) -> (W, u64) {
+        let digest = self.digest();
+        (self.wr, digest)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: io::Write, H: Hasher> io::Write for ChecksumWriter<W, H> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = self.wr.write(buf)?;
+        self.hasher.write(&buf[..len]);
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.wr.flush()
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl<W: RmpWrite, H: Hasher> RmpWrite for ChecksumWriter<W, H> {
+    type Error = W::Error;
+
+    fn write_bytes(&mut self, buf: &[u8]) -> Result<(), Self::Error> {
+        self.wr.write_bytes(buf)?;
+        self.hasher.write(buf);
+        Ok(())
+    }
+}
+
+/// Runs the decoding function against the reader through a `ChecksumReader`, returning its result
+/// along with the digest of the bytes it read.
+///
+/// # Errors
+///
+/// Returns the error of the decoding function.
+pub fn read_with_checksum<R, H, F, T, E>(rd: R, hasher: H, f: F) -> Result<(T, u64), E>
+    where H: Hasher,
+          F: FnOnce(&mut ChecksumReader<R, H>) -> Result<T, E>
+{
+    let mut rd = ChecksumReader::new(rd, hasher);
+    let val = f(&mut rd)?;
+    Ok((val, rd.digest()))
+}
+
+/// Runs the encoding function against the writer through a `ChecksumWriter`, returning its
+/// result along with the digest of the bytes it wrote.
+///
+/// # Errors
+///
+/// Returns the error of the encoding function.
+pub fn write_with_checksum<W, H, F, T, E>(wr: W, hasher: H, f: F) -> Result<(T, u64), E>
+    where H: Hasher,
+          F: FnOnce(&mut ChecksumWriter<W, H>) -> Result<T, E>
+{
+    let mut wr = ChecksumWriter::new(wr, hasher);
+    let val = f(&mut wr)?;
+    Ok((val, wr.digest()))
+}
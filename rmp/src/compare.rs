@@ -0,0 +1,244 @@
+use std::cmp::Ordering;
+use std::io::{self, ErrorKind};
+
+use Marker;
+use decode::{read_marker, read_data_u8, read_data_u16, read_data_u32, read_data_u64,
+             read_data_i8, read_data_i16, read_data_i32, read_data_i64, read_data_f32,
+             read_data_f64, ValueReadError};
+
+/// A single decoded item, where containers are represented only by their length.
+enum Item<'a> {
+    Nil,
+    Boolean(bool),
+    PosInt(u64),
+    NegInt(i64),
+    Float(f64),
+    String(&'a [u8]),
+    Binary(&'a [u8]),
+    Array(u32),
+    Map(u32),
+    Ext(i8, &'a [u8]),
+    Reserved,
+}
+
+impl<'a> Item<'a> {
+    /// Returns the rank used to order items of different types.
+    fn rank(&self) -> u8 {
+        match *self {
+            Item::Nil => 0,
+            Item::Boolean(..) => 1,
+            Item::PosInt(..) | Item::NegInt(..) => 2,
+            Item::Float(..) => 3,
+            Item::String(..) => 4,
+            Item::Binary(..) => 5,
+            Item::Array(..) => 6,
+            Item::Map(..) => 7,
+            Item::Ext(..) => 8,
+            Item::Reserved => 9,
+        }
+    }
+}
+
+fn read_slice<'a>(rd: &mut &'a [u8], len: usize) -> Result<&'a [u8], ValueReadError> {
+    if rd.len() < len {
+        let err = io::Error::from(ErrorKind::UnexpectedEof);
+        return Err(ValueReadError::InvalidDataRead(err));
+    }
+
+    let (head, tail) = rd.split_at(len);
+    *rd = tail;
+
+    Ok(head)
+}
+
+fn read_ext<'a>(rd: &mut &'a [u8], len: usize) -> Result<Item<'a>, ValueReadError> {
+    let ty = read_data_i8(rd)?;
+    Ok(Item::Ext(ty, read_slice(rd, len)?))
+}
+
+fn int(val: i64) -> Item<'static> {
+    if val < 0 {
+        Item::NegInt(val)
+    } else {
+        Item::PosInt(val as u64)
+    }
+}
+
+fn read_item<'a>(rd: &mut &'a [u8]) -> Result<Item<'a>, ValueReadError> {
+    let item = match read_marker(rd)? {
+        Marker::Null => Item::Nil,
+        Marker::True => Item::Boolean(true),
+        Marker::False => Item::Boolean(false),
+        Marker::FixPos(val) => Item::PosInt(val as u64),
+        Marker::FixNeg(val) => Item::NegInt(val as i64),
+        Marker::U8 => Item::PosInt(read_data_u8(rd)? as u64),
+        Marker::U16 => Item::PosInt(read_data_u16(rd)? as u64),
+        Marker::U32 => Item::PosInt(read_data_u32(rd)? as u64),
+        Marker::U64 => Item::PosInt(read_data_u64(rd)?),
+        Marker::I8 => int(read_data_i8(rd)? as i64),
+        Marker::I16 => int(read_data_i16(rd)? as i64),
+        Marker::I32 => int(read_data_i32(rd)? as i64),
+        Marker::I64 => int(read_data_i64(rd)?),
+        Marker::F32 => Item::Float(read_data_f32(rd)? as f64),
+        Marker::F64 => Item::Float(read_data_f64(rd)?),
+        Marker::FixStr(len) => Item::String(read_slice(rd, len as usize)?),
+        Marker::Str8 => {
+            let len = read_data_u8(rd)? as usize;
+            Item::String(read_slice(rd, len)?)
+        }
+        Marker::Str16 => {
+            let len = read_data_u16(rd)? as usize;
+            Item::String(read_slice(rd, len)?)
+        }
+        Marker::Str32 => {
+            let len = read_data_u32(rd)? as usize;
+            Item::String(read_slice(rd, len)?)
+        }
+        Marker::Bin8 => {
+            let len = read_data_u8(rd)? as usize;
+            Item::Binary(read_slice(rd, len)?)
+        }
+        Marker::Bin16 => {
+            let len = read_data_u16(rd)? as usize;
+            Item::Binary(read_slice(rd, len)?)
+        }
+        Marker::Bin32 => {
+            let len = read_data_u32(rd)? as usize;
+            Item::Binary(read_slice(rd, len)?)
+        }
+        Marker::FixArray(len) => Item::Array(len as u32),
+        Marker::Array16 => Item::Array(read_data_u16(rd)? as u32),
+        Marker::Array32 => Item::Array(read_data_u32(rd)?),
+        Marker::FixMap(len) => Item::Map(len as u32),
+        Marker::Map16 => Item::Map(read_data_u16(rd)? as u32),
+        Marker::Map32 => Item::Map(read_data_u32(rd)?),
+        Marker::FixExt1 => read_ext(rd, 1)?,
+        Marker::FixExt2 => read_ext(rd, 2)?,
+        Marker::FixExt4 => read_ext(rd, 4)?,
+        Marker::FixExt8 => read_ext(rd, 8)?,
+        Marker::FixExt16 => read_ext(rd, 16)?,
+        Marker::Ext8 => {
+            let len = read_data_u8(rd)? as usize;
+            read_ext(rd, len)?
+        }
+        Marker::Ext16 => {
+            let len = read_data_u16(rd)? as usize;
+            read_ext(rd, len)?
+        }
+        Marker::Ext32 => {
+            let len = read_data_u32(rd)? as usize;
+            read_ext(rd, len)?
+        }
+        Marker::Reserved => Item::Reserved,
+    };
+
+    Ok(item)
+}
+
+/// Total order over floats, where NaN is equal to itself and greater than any other number.
+fn cmp_f64(lhs: f64, rhs: f64) -> Ordering {
+    match lhs.partial_cmp(&rhs) {
+        Some(ord) => ord,
+        None => lhs.is_nan().cmp(&rhs.is_nan()),
+    }
+}
+
+fn cmp_scalar(lhs: &Item, rhs: &Item) -> Ordering {
+    match (lhs, rhs) {
+        (&Item::Boolean(l), &Item::Boolean(r)) => l.cmp(&r),
+        (&Item::PosInt(l), &Item::PosInt(r)) => l.cmp(&r),
+        (&Item::NegInt(l), &Item::NegInt(r)) => l.cmp(&r),
+        (&Item::NegInt(..), &Item::PosInt(..)) => Ordering::Less,
+        (&Item::PosInt(..), &Item::NegInt(..)) => Ordering::Greater,
+        (&Item::Float(l), &Item::Float(r)) => cmp_f64(l, r),
+        (&Item::String(l), &Item::String(r)) |
+        (&Item::Binary(l), &Item::Binary(r)) => l.cmp(r),
+        (&Item::Ext(lty, l), &Item::Ext(rty, r)) => lty.cmp(&rty).then(l.cmp(r)),
+        (l, r) => l.rank().cmp(&r.rank()),
+    }
+}
+
+/// Compares the first MessagePack values encoded in the given buffers semantically, without
+/// decoding them into intermediate trees.
+///
+/// Both buffers are walked in lockstep, so that values that differ only in their encoding are
+/// considered equal: integers and floats of different widths holding the same number, and strings,
+/// binaries, arrays, maps and extensions with different length prefixes holding the same content.
+/// Integers are never equal to floats.
+///
+/// Values are totally ordered: by type first (nil, boolean, integer, float, string, binary, array,
+/// map, extension), then by value. Strings and binaries compare bytewise, arrays and maps compare
+/// lexicographically element by element (maps in their encoded order), extensions compare by type
+/// and then by data. NaN is equal to itself and greater than any other float.
+///
+/// Any bytes following the first value in each buffer are ignored.
+///
+/// # Errors
+///
+/// Returns `ValueReadError` if either of the buffers is truncated before the point where the
+/// ordering is determined.
+///
+/// # Examples
+///
+/// ```
+/// use std::cmp::Ordering;
+///
+/// // [42, "a"] encoded compactly and with wide headers.
+/// let lhs = [0x92, 0x2a, 0xa1, 0x61];
+/// let rhs = [0xdc, 0x00, 0x02, 0xcf, 0, 0, 0, 0, 0, 0, 0, 0x2a, 0xd9, 0x01, 0x61];
+///
+/// assert_eq!(Ordering::Equal, rmp::compare(&lhs, &rhs).unwrap());
+/// assert_eq!(Ordering::Less, rmp::compare(&[0x01], &[0x02]).unwrap());
+/// ```
+pub fn compare(lhs: &[u8], rhs: &[u8]) -> Result<Ordering, ValueReadError> {
+    let mut lhs = lhs;
+    let mut rhs = rhs;
+
+    // For each open container pair: the number of items left in the enclosing containers and the
+    // order to use if the common prefix of this pair turns out to be equal.
+    let mut stack: Vec<(u64, Ordering)> = Vec::new();
+    let mut pending = 1u64;
+
+    loop {
+        while pending == 0 {
+            match stack.pop() {
+                Some((remaining, Ordering::Equal)) => pending = remaining,
+                Some((_, ord)) => return Ok(ord),
+                None => return Ok(Ordering::Equal),
+            }
+        }
+        pending -= 1;
+
+        let l = read_item(&mut lhs)?;
+        let r = read_item(&mut rhs)?;
+
+        let (llen, rlen) = match (&l, &r) {
+            (&Item::Array(l), &Item::Array(r)) => (l as u64, r as u64),
+            (&Item::Map(l), &Item::Map(r)) => (2 * l as u64, 2 * r as u64),
+            (l, r) => {
+                match cmp_scalar(l, r) {
+                    Ordering::Equal => continue,
+                    ord => return Ok(ord),
+                }
+            }
+        };
+
+        stack.push((pending, llen.cmp(&rlen)));
+        pending = ::std::cmp::min(llen, rlen);
+    }
+}
+
+/// Returns `true` if the first MessagePack values encoded in the given buffers are semantically
+/// equal, as defined by `compare`.
+///
+/// Malformed or truncated buffers are never equal.
+///
+/// # Examples
+///
+/// ```
+/// assert!(rmp::equal(&[0x2a], &[0xcd, 0x00, 0x2a]));
+/// assert!(!rmp::equal(&[0x2a], &[0xcb, 0x40, 0x45, 0, 0, 0, 0, 0, 0]));
+/// ```
+pub fn equal(lhs: &[u8], rhs: &[u8]) -> bool {
+    matches!(compare(lhs, rhs), Ok(Ordering::Equal))
+}
@@ -0,0 +1,98 @@
+#[cfg(feature = "std")]
+use std::error;
+use core::fmt::{self, Display, Formatter};
+
+use Marker;
+use super::{read_bin_len, Error, RmpRead, RmpReadErr, ValueReadError};
+
+/// An error that can occur when copying binary data into a caller-provided buffer.
+#[derive(Debug)]
+pub enum DecodeBinError<E: RmpReadErr = Error> {
+    InvalidMarkerRead(E),
+    InvalidDataRead(E),
+    TypeMismatch(Marker),
+    /// The given buffer is not large enough to hold the binary data of the specified length.
+    BufferSizeTooSmall(u32),
+}
+
+#[cfg(feature = "std")]
+impl<E: RmpReadErr> error::Error for DecodeBinError<E> {
+    fn description(&self) -> &str {
+        "error while decoding binary"
+    }
+
+    fn cause(&self) -> Option<&dyn error::Error> {
+        match *self {
+            DecodeBinError::InvalidMarkerRead(ref err) |
+            DecodeBinError::InvalidDataRead(ref err) => Some(err),
+            DecodeBinError::TypeMismatch(..) |
+            DecodeBinError::BufferSizeTooSmall(..) => None,
+        }
+    }
+}
+
+impl<E: RmpReadErr> Display for DecodeBinError<E> {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            DecodeBinError::BufferSizeTooSmall(len) => {
+                write!(f, "buffer too small for binary data of {} bytes", len)
+            }
+            _ => f.write_str("error while decoding binary"),
+        }
+    }
+}
+
+impl<E: RmpReadErr> From<ValueReadError<E>> for DecodeBinError<E> {
+    fn from(err: ValueReadError<E>) -> DecodeBinError<E> {
+        match err {
+            ValueReadError::InvalidMarkerRead(err) => DecodeBinError::InvalidMarkerRead(err),
+            ValueReadError::InvalidDataRead(err) => DecodeBinError::InvalidDataRead(err),
+            ValueReadError::TypeMismatch(marker) => DecodeBinError::TypeMismatch(marker),
+        }
+    }
+}
+
+/// Attempts to read a binary value from the given reader and copy its data to the buffer
+/// provided, returning the part of the buffer filled.
+///
+/// Together with `read_str` this allows to decode bounded fields without any heap allocation,
+/// for example into stack arrays on embedded targets.
+///
+/// # Errors
+///
+/// Returns `DecodeBinError::BufferSizeTooSmall` with the length of the data if it doesn't fit
+/// into the buffer. Only the header is consumed from the reader in that case, so the data can
+/// still be read or skipped.
+///
+/// Other errors are returned on I/O failures and if the value is not a binary one.
+///
+/// # Examples
+///
+/// ```
+/// use rmp::decode::{read_bin, DecodeBinError};
+///
+/// let buf = [0xc4, 0x03, 0x01, 0x02, 0x03];
+/// let mut out = [0u8; 4];
+///
+/// assert_eq!(&[1, 2, 3], read_bin(&mut &buf[..], &mut out).unwrap());
+///
+/// match read_bin(&mut &buf[..], &mut out[..2]) {
+///     Err(DecodeBinError::BufferSizeTooSmall(3)) => {}
+///     other => panic!("unexpected result: {:?}", other),
+/// }
+/// ```
+pub fn read_bin<'r, R>(rd: &mut R, buf: &'r mut [u8]) -> Result<&'r [u8], DecodeBinError<R::Error>>
+    where R: RmpRead
+{
+    let len = read_bin_len(rd)?;
+    let ulen = len as usize;
+
+    if buf.len() < ulen {
+        return Err(DecodeBinError::BufferSizeTooSmall(len));
+    }
+
+    let buf = &mut buf[..ulen];
+    rd.read_exact_buf(buf).map_err(DecodeBinError::InvalidDataRead)?;
+
+    Ok(buf)
+}
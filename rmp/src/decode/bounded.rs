@@ -0,0 +1,241 @@
+#[cfg(feature = "std")]
+use std::error;
+use core::cmp;
+use core::fmt::{self, Display, Formatter};
+
+use Marker;
+use super::{RmpRead, RmpReadErr};
+
+/// An error which can occur when reading through a `Bounded` reader.
+#[derive(Debug)]
+pub enum BoundedReadError<E: RmpReadErr> {
+    /// The underlying reader failed.
+    InvalidRead(E),
+    /// Decoding attempted to read past the end of the guarded value.
+    OutOfBounds,
+    /// Decoding finished before reaching the end of the guarded value.
+    Unfinished,
+}
+
+#[cfg(feature = "std")]
+impl<E: RmpReadErr> error::Error for BoundedReadError<E> {
+    fn description(&self) -> &str {
+        match *self {
+            BoundedReadError::InvalidRead(..) => "error while reading the guarded value",
+            BoundedReadError::OutOfBounds => "attempt to read past the end of the guarded value",
+            BoundedReadError::Unfinished => "the guarded value was not read completely",
+        }
+    }
+
+    fn cause(&self) -> Option<&dyn error::Error> {
+        match *self {
+            BoundedReadError::InvalidRead(ref err) => Some(err),
+            BoundedReadError::OutOfBounds |
+            BoundedReadError::Unfinished => None,
+        }
+    }
+}
+
+impl<E: RmpReadErr> Display for BoundedReadError<E> {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            BoundedReadError::InvalidRead(ref err) => {
+                write!(f, "error while reading the guarded value: {}", err)
+            }
+            BoundedReadError::OutOfBounds => {
+                f.write_str("attempt to read past the end of the guarded value")
+            }
+            BoundedReadError::Unfinished => {
+                f.write_str("the guarded value was not read completely")
+            }
+        }
+    }
+}
+
+/// A guard wrapping the reader for the duration of a nested value, which fails with
+/// `BoundedReadError::OutOfBounds` instead of consuming bytes beyond the declared extent of that
+/// value.
+///
+/// The guard follows the structure of the data passing through it, so for arrays and maps the
+/// extent is the number of elements from the header rather than a number of bytes. Bytes past the
+/// extent are never taken from the underlying reader, which makes a hand-written decoder that
+/// gets out of step with the data fail right at the faulty value instead of misinterpreting
+/// everything after it.
+///
+/// # Examples
+///
+/// ```
+/// use rmp::decode::{self, Bounded, BoundedReadError, ValueReadError};
+///
+/// // [1, 2] followed by 3.
+/// let buf = [0x92, 0x01, 0x02, 0x03];
+/// let mut rd = &buf[..];
+///
+/// let len = decode::read_array_len(&mut rd).unwrap();
+/// {
+///     let mut guard = Bounded::array(&mut rd, len);
+///     assert_eq!(1, decode::read_pfix(&mut guard).unwrap());
+///     assert_eq!(2, decode::read_pfix(&mut guard).unwrap());
+///
+///     // A decoder expecting three elements stops here, without consuming the next value.
+///     match decode::read_pfix(&mut guard) {
+///         Err(ValueReadError::InvalidMarkerRead(BoundedReadError::OutOfBounds)) => {}
+///         other => panic!("unexpected result: {:?}", other),
+///     }
+///     guard.finish().unwrap();
+/// }
+///
+/// assert_eq!([0x03], rd);
+/// ```
+#[derive(Debug)]
+pub struct Bounded<'r, R: 'r> {
+    rd: &'r mut R,
+    // Number of values whose marker was not read yet, including nested ones.
+    pending: u64,
+    // Number of data bytes left in the current value.
+    data: u64,
+    // Number of bytes of a length field left to read, with the part of it already read.
+    len_left: u8,
+    len: u64,
+    // What the length being read counts: data bytes plus `len_extra` for zero, otherwise values,
+    // `len_values` of them per unit.
+    len_values: u8,
+    len_extra: u8,
+}
+
+impl<'r, R: RmpRead> Bounded<'r, R> {
+    fn new(rd: &'r mut R, pending: u64, data: u64) -> Bounded<'r, R> {
+        Bounded {
+            rd,
+            pending,
+            data,
+            len_left: 0,
+            len: 0,
+            len_values: 0,
+            len_extra: 0,
+        }
+    }
+
+    /// Guards a single complete value starting at the current position of the reader.
+    pub fn value(rd: &'r mut R) -> Bounded<'r, R> {
+        Bounded::new(rd, 1, 0)
+    }
+
+    /// Guards the elements of an array, whose length was just read with `read_array_len`.
+    pub fn array(rd: &'r mut R, len: u32) -> Bounded<'r, R> {
+        Bounded::new(rd, len as u64, 0)
+    }
+
+    /// Guards the entries of a map, whose length was just read with `read_map_len`.
+    pub fn map(rd: &'r mut R, len: u32) -> Bounded<'r, R> {
+        Bounded::new(rd, 2 * len as u64, 0)
+    }
+
+    /// Guards the given number of raw bytes, like the data of a string, a binary or an extension
+    /// whose header was just read.
+    pub fn bytes(rd: &'r mut R, len: u32) -> Bounded<'r, R> {
+        Bounded::new(rd, 0, len as u64)
+    }
+
+    /// Returns `true` if the whole guarded value was read.
+    pub fn is_finished(&self) -> bool {
+        self.pending == 0 && self.data == 0 && self.len_left == 0
+    }
+
+    /// Consumes the guard, checking that the whole guarded value was read.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BoundedReadError::Unfinished` if decoding stopped before the end of the value.
+    pub fn finish(self) -> Result<(), BoundedReadError<R::Error>> {
+        if self.is_finished() {
+            Ok(())
+        } else {
+            Err(BoundedReadError::Unfinished)
+        }
+    }
+
+    fn read_length(&mut self, size: u8, values: u8, extra: u8) {
+        self.len_left = size;
+        self.len = 0;
+        self.len_values = values;
+        self.len_extra = extra;
+    }
+
+    fn on_marker(&mut self, marker: Marker) {
+        match marker {
+            Marker::FixPos(..) | Marker::FixNeg(..) | Marker::Null | Marker::True |
+            Marker::False | Marker::Reserved => {}
+            Marker::U8 | Marker::I8 => self.data = 1,
+            Marker::U16 | Marker::I16 => self.data = 2,
+            Marker::U32 | Marker::I32 | Marker::F32 => self.data = 4,
+            Marker::U64 | Marker::I64 | Marker::F64 => self.data = 8,
+            Marker::FixStr(len) => self.data = len as u64,
+            Marker::Str8 | Marker::Bin8 => self.read_length(1, 0, 0),
+            Marker::Str16 | Marker::Bin16 => self.read_length(2, 0, 0),
+            Marker::Str32 | Marker::Bin32 => self.read_length(4, 0, 0),
+            Marker::FixArray(len) => self.pending += len as u64,
+            Marker::Array16 => self.read_length(2, 1, 0),
+            Marker::Array32 => self.read_length(4, 1, 0),
+            Marker::FixMap(len) => self.pending += 2 * len as u64,
+            Marker::Map16 => self.read_length(2, 2, 0),
+            Marker::Map32 => self.read_length(4, 2, 0),
+            Marker::FixExt1 => self.data = 1 + 1,
+            Marker::FixExt2 => self.data = 1 + 2,
+            Marker::FixExt4 => self.data = 1 + 4,
+            Marker::FixExt8 => self.data = 1 + 8,
+            Marker::FixExt16 => self.data = 1 + 16,
+            Marker::Ext8 => self.read_length(1, 0, 1),
+            Marker::Ext16 => self.read_length(2, 0, 1),
+            Marker::Ext32 => self.read_length(4, 0, 1),
+        }
+    }
+
+    fn on_length_byte(&mut self, byte: u8) {
+        self.len = self.len << 8 | byte as u64;
+        self.len_left -= 1;
+
+        if self.len_left == 0 {
+            match self.len_values {
+                0 => self.data = self.len + self.len_extra as u64,
+                values => self.pending += values as u64 * self.len,
+            }
+        }
+    }
+}
+
+impl<'r, R: RmpRead> RmpRead for Bounded<'r, R> {
+    type Error = BoundedReadError<R::Error>;
+
+    fn read_exact_buf(&mut self, buf: &mut [u8]) -> Result<(), Self::Error> {
+        let mut pos = 0;
+        while pos < buf.len() {
+            // Data bytes carry no structure, so pass them through in one piece.
+            if self.data > 0 {
+                let len = cmp::min(self.data, (buf.len() - pos) as u64) as usize;
+                self.rd.read_exact_buf(&mut buf[pos..pos + len])
+                    .map_err(BoundedReadError::InvalidRead)?;
+                self.data -= len as u64;
+                pos += len;
+                continue;
+            }
+
+            if self.len_left == 0 && self.pending == 0 {
+                return Err(BoundedReadError::OutOfBounds);
+            }
+
+            let byte = self.rd.read_u8().map_err(BoundedReadError::InvalidRead)?;
+            buf[pos] = byte;
+            pos += 1;
+
+            if self.len_left > 0 {
+                self.on_length_byte(byte);
+            } else {
+                self.pending -= 1;
+                self.on_marker(Marker::from_u8(byte));
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,133 @@
+#[cfg(feature = "std")]
+use std::error;
+#[cfg(feature = "std")]
+use std::io;
+use core::fmt::{self, Display, Formatter};
+
+/// An error which can occur when reading from a byte slice: there were not enough bytes left.
+///
+/// Unlike `std::io::Error` it never allocates and is available without the `std` feature.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BytesReadError {
+    /// The given number of bytes was expected at the given position, but only `actual` bytes were
+    /// left.
+    InsufficientBytes {
+        /// The number of bytes requested.
+        expected: usize,
+        /// The number of bytes left.
+        actual: usize,
+        /// The number of bytes consumed before the failed read.
+        position: u64,
+    },
+}
+
+impl Display for BytesReadError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            BytesReadError::InsufficientBytes { expected, actual, position } => {
+                write!(f, "expected {} bytes at position {}, but only {} left", expected, position,
+                       actual)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl error::Error for BytesReadError {
+    fn description(&self) -> &str {
+        "not enough bytes left in the buffer"
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<BytesReadError> for io::Error {
+    fn from(err: BytesReadError) -> io::Error {
+        io::Error::new(io::ErrorKind::UnexpectedEof, err)
+    }
+}
+
+/// A reader over a byte slice, which tracks its position.
+///
+/// Reading from `Bytes` never allocates, even on failure: running out of input results in a
+/// `BytesReadError`, which also reports where exactly that happened. This makes it a cheap
+/// alternative to `&[u8]` in hot loops, and the primary reader without the `std` feature.
+///
+/// # Examples
+///
+/// ```
+/// use rmp::decode::{self, Bytes};
+///
+/// let mut rd = Bytes::new(&[0xcd, 0x01, 0x2c, 0xc3]);
+///
+/// assert_eq!(300, decode::read_u16(&mut rd).unwrap());
+/// assert_eq!(3, rd.position());
+/// assert_eq!(&[0xc3], rd.remaining_slice());
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Bytes<'a> {
+    buf: &'a [u8],
+    position: u64,
+}
+
+impl<'a> Bytes<'a> {
+    /// Constructs a new reader starting at the beginning of the given slice.
+    pub fn new(buf: &'a [u8]) -> Bytes<'a> {
+        Bytes {
+            buf: buf,
+            position: 0,
+        }
+    }
+
+    /// Returns the bytes not consumed yet.
+    pub fn remaining_slice(&self) -> &'a [u8] {
+        self.buf
+    }
+
+    /// Returns the number of bytes consumed so far.
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    fn read_into(&mut self, out: &mut [u8]) -> Result<(), BytesReadError> {
+        if out.len() > self.buf.len() {
+            return Err(BytesReadError::InsufficientBytes {
+                expected: out.len(),
+                actual: self.buf.len(),
+                position: self.position,
+            });
+        }
+
+        let (head, tail) = self.buf.split_at(out.len());
+        out.copy_from_slice(head);
+        self.buf = tail;
+        self.position += out.len() as u64;
+
+        Ok(())
+    }
+}
+
+impl<'a> From<&'a [u8]> for Bytes<'a> {
+    fn from(buf: &'a [u8]) -> Bytes<'a> {
+        Bytes::new(buf)
+    }
+}
+
+impl<'a> super::RmpRead for Bytes<'a> {
+    type Error = BytesReadError;
+
+    fn read_exact_buf(&mut self, buf: &mut [u8]) -> Result<(), BytesReadError> {
+        self.read_into(buf)
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl<'a> super::RmpRead for &'a [u8] {
+    type Error = BytesReadError;
+
+    fn read_exact_buf(&mut self, buf: &mut [u8]) -> Result<(), BytesReadError> {
+        let mut rd = Bytes::new(self);
+        rd.read_into(buf)?;
+        *self = rd.remaining_slice();
+        Ok(())
+    }
+}
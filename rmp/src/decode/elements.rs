@@ -0,0 +1,273 @@
+use super::{read_array_len, read_map_len, skip_value, Bytes, ValueReadError};
+
+/// An iterator over the elements of an encoded array, yielding each of them as a slice holding
+/// exactly one complete value.
+///
+/// The yielded slices are readers on their own, so every element can be decoded with the usual
+/// functions without being able to read past its end, and the iterator stops after the number of
+/// elements announced in the header.
+///
+/// # Examples
+///
+/// ```
+/// use rmp::decode::{self, Elements};
+///
+/// // [1, "a", [2, 3]] followed by `true`.
+/// let buf = [0x93, 0x01, 0xa1, 0x61, 0x92, 0x02, 0x03, 0xc3];
+/// let mut rd = &buf[..];
+///
+/// let len = decode::read_array_len(&mut rd).unwrap();
+/// let mut elements = Elements::new(rd, len);
+///
+/// assert_eq!(1, decode::read_int::<u8, _>(&mut elements.next().unwrap().unwrap()).unwrap());
+/// assert_eq!(&[0xa1, 0x61], elements.next().unwrap().unwrap());
+/// assert_eq!(&[0x92, 0x02, 0x03], elements.next().unwrap().unwrap());
+/// assert!(elements.next().is_none());
+///
+/// assert_eq!(&[0xc3], elements.tail());
+/// ```
+#[derive(Clone, Debug)]
+pub struct Elements<'a> {
+    buf: &'a [u8],
+    left: u32,
+}
+
+impl<'a> Elements<'a> {
+    /// Constructs a new iterator over `len` elements at the beginning of the given slice, usually
+    /// the remainder of the input after `read_array_len`.
+    pub fn new(buf: &'a [u8], len: u32) -> Elements<'a> {
+        Elements { buf, left: len }
+    }
+
+    /// Returns the number of elements not yielded yet.
+    pub fn remaining(&self) -> u32 {
+        self.left
+    }
+
+    /// Returns the input after the elements yielded so far, which follows the array once the
+    /// iterator is exhausted.
+    pub fn tail(&self) -> &'a [u8] {
+        self.buf
+    }
+}
+
+impl<'a> Iterator for Elements<'a> {
+    type Item = Result<&'a [u8], ValueReadError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.left == 0 {
+            return None;
+        }
+
+        match split_value(self.buf) {
+            Ok((head, tail)) => {
+                self.buf = tail;
+                self.left -= 1;
+                Some(Ok(head))
+            }
+            Err(err) => {
+                // The position of the following elements is unknown after a malformed one.
+                self.left = 0;
+                Some(Err(err))
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.left as usize))
+    }
+}
+
+/// An iterator over the entries of an encoded map, yielding the key and the value of each of them
+/// as slices holding exactly one complete value.
+///
+/// This is the map counterpart of `Elements`.
+///
+/// # Examples
+///
+/// ```
+/// use rmp::decode::{self, Entries};
+///
+/// // {"a": 1, "b": nil}
+/// let buf = [0x82, 0xa1, 0x61, 0x01, 0xa1, 0x62, 0xc0];
+/// let mut rd = &buf[..];
+///
+/// let len = decode::read_map_len(&mut rd).unwrap();
+/// let keys = Entries::new(rd, len)
+///     .map(|entry| decode::read_str_from_slice(entry.unwrap().0).unwrap().0)
+///     .collect::<Vec<_>>();
+///
+/// assert_eq!(vec!["a", "b"], keys);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Entries<'a> {
+    buf: &'a [u8],
+    left: u32,
+}
+
+impl<'a> Entries<'a> {
+    /// Constructs a new iterator over `len` entries at the beginning of the given slice, usually
+    /// the remainder of the input after `read_map_len`.
+    pub fn new(buf: &'a [u8], len: u32) -> Entries<'a> {
+        Entries { buf, left: len }
+    }
+
+    /// Returns the number of entries not yielded yet.
+    pub fn remaining(&self) -> u32 {
+        self.left
+    }
+
+    /// Returns the input after the entries yielded so far, which follows the map once the
+    /// iterator is exhausted.
+    pub fn tail(&self) -> &'a [u8] {
+        self.buf
+    }
+}
+
+impl<'a> Iterator for Entries<'a> {
+    type Item = Result<(&'a [u8], &'a [u8]), ValueReadError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.left == 0 {
+            return None;
+        }
+
+        let entry = split_value(self.buf).and_then(|(key, tail)| {
+            let (val, tail) = split_value(tail)?;
+            Ok((key, val, tail))
+        });
+
+        match entry {
+            Ok((key, val, tail)) => {
+                self.buf = tail;
+                self.left -= 1;
+                Some(Ok((key, val)))
+            }
+            Err(err) => {
+                self.left = 0;
+                Some(Err(err))
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.left as usize))
+    }
+}
+
+/// An iterator over the top-level values of a buffer holding several of them back to back, like
+/// a log file, yielding each of them as a slice holding exactly one complete value.
+///
+/// The iterator ends at the end of the buffer. A truncated or malformed value is yielded as an
+/// error, after which the iterator ends too, as the position of the following values is unknown.
+#[derive(Clone, Debug)]
+pub struct ObjectBounds<'a> {
+    buf: &'a [u8],
+    pos: usize,
+    failed: bool,
+}
+
+impl<'a> ObjectBounds<'a> {
+    /// Returns the offset of the next value from the beginning of the buffer, which is the start
+    /// of its byte range in an index.
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// Returns the input after the values yielded so far.
+    pub fn tail(&self) -> &'a [u8] {
+        &self.buf[self.pos..]
+    }
+}
+
+impl<'a> Iterator for ObjectBounds<'a> {
+    type Item = Result<&'a [u8], ValueReadError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed || self.pos == self.buf.len() {
+            return None;
+        }
+
+        match split_value(&self.buf[self.pos..]) {
+            Ok((head, _)) => {
+                self.pos += head.len();
+                Some(Ok(head))
+            }
+            Err(err) => {
+                self.failed = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+/// Attempts to read an array header from the beginning of the given slice, returning an iterator
+/// over its elements.
+///
+/// # Errors
+///
+/// This function will return `ValueReadError` on any I/O error while reading the header or if
+/// the value is not an array.
+pub fn read_array_elements(buf: &[u8]) -> Result<Elements<'_>, ValueReadError> {
+    let mut rd = buf;
+    let len = read_array_len(&mut rd)?;
+
+    Ok(Elements::new(rd, len))
+}
+
+/// Attempts to read a map header from the beginning of the given slice, returning an iterator
+/// over its entries.
+///
+/// # Errors
+///
+/// This function will return `ValueReadError` on any I/O error while reading the header or if
+/// the value is not a map.
+pub fn read_map_elements(buf: &[u8]) -> Result<Entries<'_>, ValueReadError> {
+    let mut rd = buf;
+    let len = read_map_len(&mut rd)?;
+
+    Ok(Entries::new(rd, len))
+}
+
+/// Returns an iterator over the top-level values of the given buffer, yielding the bytes of each
+/// of them.
+///
+/// This is the building block for indexing files of concatenated values, which can then be
+/// accessed at random, for example through a memory map.
+///
+/// # Examples
+///
+/// ```
+/// use rmp::decode;
+///
+/// // 1, "ab", [nil]
+/// let buf = [0x01, 0xa2, 0x61, 0x62, 0x91, 0xc0];
+///
+/// let mut index = Vec::new();
+/// let mut objects = decode::object_bounds(&buf);
+/// loop {
+///     let start = objects.position();
+///     match objects.next() {
+///         Some(obj) => index.push(start..start + obj.unwrap().len()),
+///         None => break,
+///     }
+/// }
+///
+/// assert_eq!(vec![0..1, 1..4, 4..6], index);
+/// assert_eq!(&[0x91, 0xc0], &buf[index[2].clone()]);
+/// ```
+pub fn object_bounds(buf: &[u8]) -> ObjectBounds<'_> {
+    ObjectBounds { buf, pos: 0, failed: false }
+}
+
+/// Splits the slice after the first complete value it starts with.
+fn split_value(buf: &[u8]) -> Result<(&[u8], &[u8]), ValueReadError> {
+    let mut rd = Bytes::new(buf);
+    skip_value(&mut rd).map_err(|err| match err {
+        ValueReadError::InvalidMarkerRead(err) => ValueReadError::InvalidMarkerRead(err.into()),
+        ValueReadError::InvalidDataRead(err) => ValueReadError::InvalidDataRead(err.into()),
+        ValueReadError::TypeMismatch(marker) => ValueReadError::TypeMismatch(marker),
+    })?;
+
+    Ok(buf.split_at(rd.position() as usize))
+}
@@ -1,7 +1,6 @@
-use std::io::Read;
-
 use Marker;
-use super::{read_marker, read_data_i8, read_data_u8, read_data_u16, read_data_u32, ValueReadError};
+use super::{read_marker, read_data_i8, read_data_u8, read_data_u16, read_data_u32, RmpRead,
+            ValueReadError};
 
 /// Attempts to read exactly 3 bytes from the given reader and interpret them as a fixext1 type
 /// with data attached.
@@ -20,7 +19,7 @@ use super::{read_marker, read_data_i8, read_data_u8, read_data_u16, read_data_u3
 ///
 /// This function will silently retry on every EINTR received from the underlying `Read` until
 /// successful read.
-pub fn read_fixext1<R: Read>(rd: &mut R) -> Result<(i8, u8), ValueReadError> {
+pub fn read_fixext1<R: RmpRead>(rd: &mut R) -> Result<(i8, u8), ValueReadError<R::Error>> {
     match try!(read_marker(rd)) {
         Marker::FixExt1 => {
             let ty = try!(read_data_i8(rd));
@@ -44,7 +43,7 @@ pub fn read_fixext1<R: Read>(rd: &mut R) -> Result<(i8, u8), ValueReadError> {
 ///
 /// This function will return `ValueReadError` on any I/O error while reading either the marker or
 /// the data.
-pub fn read_fixext2<R: Read>(rd: &mut R) -> Result<(i8, [u8; 2]), ValueReadError> {
+pub fn read_fixext2<R: RmpRead>(rd: &mut R) -> Result<(i8, [u8; 2]), ValueReadError<R::Error>> {
     match try!(read_marker(rd)) {
         Marker::FixExt2 => {
             let mut buf = [0; 2];
@@ -67,7 +66,7 @@ pub fn read_fixext2<R: Read>(rd: &mut R) -> Result<(i8, [u8; 2]), ValueReadError
 ///
 /// This function will return `ValueReadError` on any I/O error while reading either the marker or
 /// the data.
-pub fn read_fixext4<R: Read>(rd: &mut R) -> Result<(i8, [u8; 4]), ValueReadError> {
+pub fn read_fixext4<R: RmpRead>(rd: &mut R) -> Result<(i8, [u8; 4]), ValueReadError<R::Error>> {
     match try!(read_marker(rd)) {
         Marker::FixExt4 => {
             let mut buf = [0; 4];
@@ -90,7 +89,7 @@ pub fn read_fixext4<R: Read>(rd: &mut R) -> Result<(i8, [u8; 4]), ValueReadError
 ///
 /// This function will return `ValueReadError` on any I/O error while reading either the marker or
 /// the data.
-pub fn read_fixext8<R: Read>(rd: &mut R) -> Result<(i8, [u8; 8]), ValueReadError> {
+pub fn read_fixext8<R: RmpRead>(rd: &mut R) -> Result<(i8, [u8; 8]), ValueReadError<R::Error>> {
     match try!(read_marker(rd)) {
         Marker::FixExt8 => {
             let mut buf = [0; 8];
@@ -113,7 +112,7 @@ pub fn read_fixext8<R: Read>(rd: &mut R) -> Result<(i8, [u8; 8]), ValueReadError
 ///
 /// This function will return `ValueReadError` on any I/O error while reading either the marker or
 /// the data.
-pub fn read_fixext16<R: Read>(rd: &mut R) -> Result<(i8, [u8; 16]), ValueReadError> {
+pub fn read_fixext16<R: RmpRead>(rd: &mut R) -> Result<(i8, [u8; 16]), ValueReadError<R::Error>> {
     match try!(read_marker(rd)) {
         Marker::FixExt16 => {
             let mut buf = [0; 16];
@@ -123,11 +122,13 @@ pub fn read_fixext16<R: Read>(rd: &mut R) -> Result<(i8, [u8; 16]), ValueReadErr
     }
 }
 
-fn read_fixext_data<R: Read>(rd: &mut R, buf: &mut [u8]) -> Result<i8, ValueReadError> {
+fn read_fixext_data<R: RmpRead>(rd: &mut R, buf: &mut [u8]) ->
+    Result<i8, ValueReadError<R::Error>>
+{
     let id = try!(read_data_i8(rd));
-    match rd.read_exact(buf) {
+    match rd.read_exact_buf(buf) {
         Ok(()) => Ok(id),
-        Err(err) => Err(ValueReadError::InvalidDataRead(From::from(err))),
+        Err(err) => Err(ValueReadError::InvalidDataRead(err)),
     }
 }
 
@@ -150,7 +151,7 @@ pub struct ExtMeta {
     pub size: u32,
 }
 
-pub fn read_ext_meta<R: Read>(rd: &mut R) -> Result<ExtMeta, ValueReadError> {
+pub fn read_ext_meta<R: RmpRead>(rd: &mut R) -> Result<ExtMeta, ValueReadError<R::Error>> {
     let size = match read_marker(rd)? {
         Marker::FixExt1 => 1,
         Marker::FixExt2 => 2,
@@ -171,3 +172,109 @@ pub fn read_ext_meta<R: Read>(rd: &mut R) -> Result<ExtMeta, ValueReadError> {
 
     Ok(meta)
 }
+
+/// The data of an extension, read on demand from the underlying reader.
+///
+/// Like `std::io::Take`, the payload never reads past the data length declared in the header, so
+/// large extensions like images or tensors can be streamed to their destination in chunks rather
+/// than buffered in memory first. With the `std` feature it implements `std::io::Read`.
+///
+/// The payload must be read or skipped completely before the reader is used again, otherwise the
+/// rest of the data is taken for the next value.
+#[derive(Debug)]
+pub struct ExtPayload<'r, R: 'r> {
+    rd: &'r mut R,
+    typeid: i8,
+    size: u32,
+    left: u32,
+}
+
+impl<'r, R: RmpRead> ExtPayload<'r, R> {
+    /// Returns the extension type.
+    pub fn typeid(&self) -> i8 {
+        self.typeid
+    }
+
+    /// Returns the data length declared in the header.
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// Returns the number of bytes not read yet.
+    pub fn remaining(&self) -> u32 {
+        self.left
+    }
+
+    /// Reads the next chunk of data into the given buffer, returning the number of bytes read.
+    ///
+    /// The whole buffer is filled unless the data ends earlier, and `0` is returned once all of it
+    /// was read.
+    pub fn read_chunk(&mut self, buf: &mut [u8]) -> Result<usize, R::Error> {
+        let len = if buf.len() < self.left as usize {
+            buf.len()
+        } else {
+            self.left as usize
+        };
+
+        self.rd.read_exact_buf(&mut buf[..len])?;
+        self.left -= len as u32;
+
+        Ok(len)
+    }
+
+    /// Discards the data not read yet, leaving the reader at the next value.
+    pub fn skip(mut self) -> Result<(), R::Error> {
+        let mut buf = [0; 256];
+        while self.read_chunk(&mut buf)? > 0 {}
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'r, R: ::std::io::Read> ::std::io::Read for ExtPayload<'r, R> {
+    fn read(&mut self, buf: &mut [u8]) -> ::std::io::Result<usize> {
+        self.read_chunk(buf)
+    }
+}
+
+/// Attempts to read an extension header of any size from the given reader, returning the payload
+/// to read its data from.
+///
+/// # Errors
+///
+/// This function will return `ValueReadError` on any I/O error while reading the header or if
+/// the value is not an extension.
+///
+/// # Examples
+///
+/// ```
+/// use std::io;
+///
+/// use rmp::decode;
+///
+/// let buf = [0xc7, 0x04, 0x2a, 0x01, 0x02, 0x03, 0x04, 0xc0];
+/// let mut rd = &buf[..];
+/// let mut out = Vec::new();
+///
+/// {
+///     let mut payload = decode::read_ext_header(&mut rd).unwrap();
+///     assert_eq!(42, payload.typeid());
+///     io::copy(&mut payload, &mut out).unwrap();
+/// }
+///
+/// assert_eq!(vec![1, 2, 3, 4], out);
+/// assert_eq!([0xc0], rd);
+/// ```
+pub fn read_ext_header<R: RmpRead>(rd: &mut R) ->
+    Result<ExtPayload<'_, R>, ValueReadError<R::Error>>
+{
+    let meta = read_ext_meta(rd)?;
+
+    Ok(ExtPayload {
+        rd,
+        typeid: meta.typeid,
+        size: meta.size,
+        left: meta.size,
+    })
+}
@@ -0,0 +1,112 @@
+use Marker;
+use super::{read_marker, read_data_u8, read_data_u16, read_data_u32, read_data_u64, read_data_i8,
+            read_data_i16, read_data_i32, read_data_i64, read_data_f32, read_data_f64, RmpRead,
+            ValueReadError};
+
+/// The header of a MessagePack value, which is the whole value for scalars and the part before
+/// the data or the elements otherwise.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Header {
+    /// Nil.
+    Nil,
+    /// A boolean.
+    Bool(bool),
+    /// An integer in one of the unsigned encodings, including a positive fixint.
+    UInt(u64),
+    /// An integer in one of the signed encodings, including a negative fixint.
+    SInt(i64),
+    /// A 32-bit float.
+    F32(f32),
+    /// A 64-bit float.
+    F64(f64),
+    /// A string of the given length in bytes, followed by its data.
+    Str(u32),
+    /// A binary of the given length in bytes, followed by its data.
+    Bin(u32),
+    /// An array of the given number of elements, followed by them.
+    Array(u32),
+    /// A map of the given number of entries, followed by their keys and values in turn.
+    Map(u32),
+    /// An extension of the given type and length in bytes, followed by its data.
+    Ext(i8, u32),
+}
+
+/// Attempts to read the header of the next value: its marker, along with the value itself for
+/// scalars, or the length and the extension type for the others.
+///
+/// This is the building block of streaming decoders, which would otherwise have to match on the
+/// marker and to read the length in as many ways as there are encodings of it.
+///
+/// # Errors
+///
+/// This function will return `ValueReadError` on any I/O error while reading the header, and
+/// `ValueReadError::TypeMismatch` on the reserved marker.
+///
+/// # Examples
+///
+/// ```
+/// use rmp::decode::{self, Header};
+///
+/// // {"a": [1, -2]}
+/// let buf = [0x81, 0xa1, 0x61, 0x92, 0x01, 0xfe];
+/// let mut rd = &buf[..];
+///
+/// assert_eq!(Header::Map(1), decode::read_header(&mut rd).unwrap());
+/// assert_eq!(Header::Str(1), decode::read_header(&mut rd).unwrap());
+/// rd = &rd[1..];
+/// assert_eq!(Header::Array(2), decode::read_header(&mut rd).unwrap());
+/// assert_eq!(Header::UInt(1), decode::read_header(&mut rd).unwrap());
+/// assert_eq!(Header::SInt(-2), decode::read_header(&mut rd).unwrap());
+/// ```
+pub fn read_header<R: RmpRead>(rd: &mut R) -> Result<Header, ValueReadError<R::Error>> {
+    let header = match read_marker(rd)? {
+        Marker::Null => Header::Nil,
+        Marker::True => Header::Bool(true),
+        Marker::False => Header::Bool(false),
+        Marker::FixPos(val) => Header::UInt(val as u64),
+        Marker::U8 => Header::UInt(read_data_u8(rd)? as u64),
+        Marker::U16 => Header::UInt(read_data_u16(rd)? as u64),
+        Marker::U32 => Header::UInt(read_data_u32(rd)? as u64),
+        Marker::U64 => Header::UInt(read_data_u64(rd)?),
+        Marker::FixNeg(val) => Header::SInt(val as i64),
+        Marker::I8 => Header::SInt(read_data_i8(rd)? as i64),
+        Marker::I16 => Header::SInt(read_data_i16(rd)? as i64),
+        Marker::I32 => Header::SInt(read_data_i32(rd)? as i64),
+        Marker::I64 => Header::SInt(read_data_i64(rd)?),
+        Marker::F32 => Header::F32(read_data_f32(rd)?),
+        Marker::F64 => Header::F64(read_data_f64(rd)?),
+        Marker::FixStr(len) => Header::Str(len as u32),
+        Marker::Str8 => Header::Str(read_data_u8(rd)? as u32),
+        Marker::Str16 => Header::Str(read_data_u16(rd)? as u32),
+        Marker::Str32 => Header::Str(read_data_u32(rd)?),
+        Marker::Bin8 => Header::Bin(read_data_u8(rd)? as u32),
+        Marker::Bin16 => Header::Bin(read_data_u16(rd)? as u32),
+        Marker::Bin32 => Header::Bin(read_data_u32(rd)?),
+        Marker::FixArray(len) => Header::Array(len as u32),
+        Marker::Array16 => Header::Array(read_data_u16(rd)? as u32),
+        Marker::Array32 => Header::Array(read_data_u32(rd)?),
+        Marker::FixMap(len) => Header::Map(len as u32),
+        Marker::Map16 => Header::Map(read_data_u16(rd)? as u32),
+        Marker::Map32 => Header::Map(read_data_u32(rd)?),
+        Marker::FixExt1 => Header::Ext(read_data_i8(rd)?, 1),
+        Marker::FixExt2 => Header::Ext(read_data_i8(rd)?, 2),
+        Marker::FixExt4 => Header::Ext(read_data_i8(rd)?, 4),
+        Marker::FixExt8 => Header::Ext(read_data_i8(rd)?, 8),
+        Marker::FixExt16 => Header::Ext(read_data_i8(rd)?, 16),
+        Marker::Ext8 => {
+            let len = read_data_u8(rd)? as u32;
+            Header::Ext(read_data_i8(rd)?, len)
+        }
+        Marker::Ext16 => {
+            let len = read_data_u16(rd)? as u32;
+            Header::Ext(read_data_i8(rd)?, len)
+        }
+        Marker::Ext32 => {
+            let len = read_data_u32(rd)?;
+            Header::Ext(read_data_i8(rd)?, len)
+        }
+        Marker::Reserved => return Err(ValueReadError::TypeMismatch(Marker::Reserved)),
+    };
+
+    Ok(header)
+}
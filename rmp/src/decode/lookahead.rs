@@ -0,0 +1,114 @@
+use Marker;
+use super::{MarkerReadError, RmpRead};
+
+/// A reader wrapper which can inspect the next marker without consuming it.
+///
+/// Decoders often branch on the type of the next value, like accepting either a string or a
+/// binary, before calling the function reading it. Seekable readers and byte slices can just look
+/// at the next byte, but sockets and pipes can't give it back once read. `Lookahead` keeps the
+/// peeked byte in a one-byte buffer and returns it first on the next read, so the reading
+/// functions see the input as if nothing was peeked.
+///
+/// # Examples
+///
+/// ```
+/// use rmp::Marker;
+/// use rmp::decode::{self, Lookahead};
+///
+/// // "ab" followed by bin [1].
+/// let buf = [0xa2, 0x61, 0x62, 0xc4, 0x01, 0x01];
+/// let mut rd = Lookahead::new(&buf[..]);
+///
+/// assert_eq!(Marker::FixStr(2), rd.peek_marker().unwrap());
+/// assert_eq!(Marker::FixStr(2), rd.peek_marker().unwrap());
+///
+/// let mut out = [0; 2];
+/// assert_eq!("ab", decode::read_str(&mut rd, &mut out).unwrap());
+///
+/// assert_eq!(Marker::Bin8, rd.peek_marker().unwrap());
+/// assert_eq!(1, decode::read_bin_len(&mut rd).unwrap());
+/// ```
+#[derive(Debug)]
+pub struct Lookahead<R> {
+    rd: R,
+    peeked: Option<u8>,
+}
+
+impl<R: RmpRead> Lookahead<R> {
+    /// Wraps the given reader.
+    pub fn new(rd: R) -> Lookahead<R> {
+        Lookahead {
+            rd,
+            peeked: None,
+        }
+    }
+
+    /// Returns the next marker without consuming it, reading it from the underlying reader only
+    /// if it was not peeked already.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the underlying reader if it fails to provide the byte, in which case
+    /// nothing is buffered.
+    pub fn peek_marker(&mut self) -> Result<Marker, MarkerReadError<R::Error>> {
+        let byte = match self.peeked {
+            Some(byte) => byte,
+            None => {
+                let byte = self.rd.read_u8().map_err(MarkerReadError)?;
+                self.peeked = Some(byte);
+                byte
+            }
+        };
+
+        Ok(Marker::from_u8(byte))
+    }
+
+    /// Returns `true` if a marker was peeked and is waiting to be read.
+    pub fn has_peeked(&self) -> bool {
+        self.peeked.is_some()
+    }
+
+    /// Gets a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.rd
+    }
+
+    /// Gets a mutable reference to the underlying reader.
+    ///
+    /// Reading from it directly skips a peeked marker, which is still returned first by this
+    /// wrapper.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.rd
+    }
+
+    /// Unwraps the underlying reader, along with the byte of the marker peeked and not read yet,
+    /// if there is one.
+    pub fn into_inner(self) -> (R, Option<u8>) {
+        (self.rd, self.peeked)
+    }
+}
+
+impl<R: RmpRead> RmpRead for Lookahead<R> {
+    type Error = R::Error;
+
+    fn read_u8(&mut self) -> Result<u8, Self::Error> {
+        match self.peeked.take() {
+            Some(byte) => Ok(byte),
+            None => self.rd.read_u8(),
+        }
+    }
+
+    fn read_exact_buf(&mut self, buf: &mut [u8]) -> Result<(), Self::Error> {
+        if buf.is_empty() {
+            return Ok(());
+        }
+
+        match self.peeked.take() {
+            Some(byte) => {
+                buf[0] = byte;
+                self.rd.read_exact_buf(&mut buf[1..])
+            }
+            None => self.rd.read_exact_buf(buf),
+        }
+    }
+}
@@ -9,49 +9,222 @@
 //! non-blocking socket and it returns EWOULDBLOCK) be sure that you buffer the data externally
 //! to avoid data loss (using `BufRead` readers with manual consuming or some other way).
 
+/// Attempts to read a MessagePack array of the given arity from the reader into a tuple of the
+/// given types.
+///
+/// This is a shorthand for `<(T0, T1, ...) as Readable>::read_from(rd)`, which reads the array
+/// header, checks that the number of elements matches the tuple arity and then reads each element
+/// in order. Tuples of up to 12 elements are supported.
+///
+/// # Errors
+///
+/// Returns `TypedReadError::LengthMismatch` with the actual array length if it differs from the
+/// tuple arity, or any error occurred while reading the elements.
+///
+/// # Examples
+///
+/// ```
+/// #[macro_use]
+/// extern crate rmp;
+///
+/// fn main() {
+///     // [42, "le message", true]
+///     let buf = [0x93, 0x2a, 0xaa, 0x6c, 0x65, 0x20, 0x6d, 0x65, 0x73, 0x73, 0x61, 0x67, 0x65, 0xc3];
+///
+///     let (id, msg, flag) = read_tuple!(&mut &buf[..], (u32, String, bool)).unwrap();
+///
+///     assert_eq!(42, id);
+///     assert_eq!("le message", msg);
+///     assert!(flag);
+/// }
+/// ```
+#[macro_export]
+macro_rules! read_tuple {
+    ($rd:expr, ($($ty:ty),+ $(,)*)) => {
+        <($($ty,)+) as $crate::decode::Readable>::read_from($rd)
+    };
+}
+
 mod sint;
 mod uint;
 mod dec;
 mod str;
+mod bin;
 mod ext;
+mod bytes;
+mod bounded;
+mod elements;
+mod header;
+mod lookahead;
+mod skip;
+#[cfg(feature = "std")]
+mod raw;
+#[cfg(feature = "std")]
+mod typed;
 
 pub use self::sint::{read_nfix, read_i8, read_i16, read_i32, read_i64};
 pub use self::uint::{read_pfix, read_u8, read_u16, read_u32, read_u64};
 pub use self::dec::{read_f32, read_f64};
 pub use self::str::{read_str_len, read_str, read_str_from_slice, read_str_ref, DecodeStringError};
+pub use self::bin::{read_bin, DecodeBinError};
 pub use self::ext::{read_fixext1, read_fixext2, read_fixext4, read_fixext8, read_fixext16,
-                    read_ext_meta, ExtMeta};
-
+                    read_ext_meta, read_ext_header, ExtMeta, ExtPayload};
+pub use self::bytes::{Bytes, BytesReadError};
+pub use self::bounded::{Bounded, BoundedReadError};
+pub use self::elements::{object_bounds, read_array_elements, read_map_elements, Elements, Entries,
+                         ObjectBounds};
+pub use self::header::{read_header, Header};
+pub use self::lookahead::Lookahead;
+pub use self::skip::{skip_data, skip_value};
+#[cfg(feature = "std")]
+pub use self::raw::{read_map_entries, read_unique_map_entries, MapReadError};
+#[cfg(feature = "std")]
+pub use self::typed::{read_array_of, read_array_with, read_map_of, read_map_with, Readable,
+                      TypedReadError};
+
+use core::fmt::{self, Display, Formatter};
+#[cfg(feature = "std")]
 use std::error;
-use std::fmt::{self, Display, Formatter};
-use std::io::Read;
-
-use byteorder::{self, ReadBytesExt};
 
 use num_traits::cast::FromPrimitive;
 
 use Marker;
 
 /// An error that can occur when attempting to read bytes from the reader.
+///
+/// This is `std::io::Error` when the `std` feature is enabled and `BytesReadError` otherwise, i.e.
+/// the error of reading from byte slices in both cases.
+#[cfg(feature = "std")]
 pub type Error = ::std::io::Error;
 
+/// An error that can occur when attempting to read bytes from a byte slice, which is the only
+/// reader available without the `std` feature.
+#[cfg(not(feature = "std"))]
+pub type Error = BytesReadError;
+
+/// The error type of an `RmpRead` implementation.
+///
+/// Any `std::error::Error` qualifies when the `std` feature is enabled.
+#[cfg(feature = "std")]
+pub trait RmpReadErr: error::Error + 'static {}
+
+#[cfg(feature = "std")]
+impl<T: error::Error + 'static> RmpReadErr for T {}
+
+/// The error type of an `RmpRead` implementation.
+///
+/// Without the `std` feature any type implementing both `Display` and `Debug` qualifies.
+#[cfg(not(feature = "std"))]
+pub trait RmpReadErr: Display + fmt::Debug + 'static {}
+
+#[cfg(not(feature = "std"))]
+impl<T: Display + fmt::Debug + 'static> RmpReadErr for T {}
+
+/// A source of bytes the decoding functions can read from.
+///
+/// With the `std` feature enabled it is implemented for every `std::io::Read`, having
+/// `std::io::Error` as its error type, and for `Bytes`, which reports truncated input using the
+/// compact `BytesReadError`. Without it, byte slices are supported as well, using the same error.
+///
+/// Other sources, like serial ports on bare-metal targets, can implement this trait themselves.
+/// Adapting another I/O abstraction, such as the `embedded-io` traits, takes only forwarding
+/// `read_exact_buf` to its own `read_exact` method.
+///
+/// # Examples
+///
+/// ```
+/// use std::error::Error;
+/// use std::fmt::{self, Display, Formatter};
+///
+/// use rmp::decode::{self, RmpRead};
+///
+/// #[derive(Debug)]
+/// struct Timeout;
+///
+/// impl Display for Timeout {
+///     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+///         f.write_str("timed out waiting for data")
+///     }
+/// }
+///
+/// impl Error for Timeout {}
+///
+/// /// A serial port, which times out once its receive FIFO runs dry.
+/// struct Uart {
+///     fifo: Vec<u8>,
+/// }
+///
+/// impl RmpRead for Uart {
+///     type Error = Timeout;
+///
+///     fn read_exact_buf(&mut self, buf: &mut [u8]) -> Result<(), Timeout> {
+///         if self.fifo.len() < buf.len() {
+///             return Err(Timeout);
+///         }
+///
+///         let rest = self.fifo.split_off(buf.len());
+///         buf.copy_from_slice(&self.fifo);
+///         self.fifo = rest;
+///         Ok(())
+///     }
+/// }
+///
+/// let mut uart = Uart { fifo: vec![0xcd, 0x01, 0x2c, 0xcd] };
+///
+/// assert_eq!(300, decode::read_u16(&mut uart).unwrap());
+/// assert!(decode::read_u16(&mut uart).is_err());
+/// ```
+pub trait RmpRead {
+    /// The error type returned when the reading fails.
+    type Error: RmpReadErr;
+
+    /// Reads a single byte.
+    fn read_u8(&mut self) -> Result<u8, Self::Error> {
+        let mut buf = [0];
+        self.read_exact_buf(&mut buf)?;
+        Ok(buf[0])
+    }
+
+    /// Reads exactly the number of bytes needed to fill the given buffer, failing if there are not
+    /// enough of them.
+    fn read_exact_buf(&mut self, buf: &mut [u8]) -> Result<(), Self::Error>;
+}
+
+#[cfg(feature = "std")]
+impl<T: ::std::io::Read + ?Sized> RmpRead for T {
+    type Error = ::std::io::Error;
+
+    fn read_exact_buf(&mut self, buf: &mut [u8]) -> Result<(), Self::Error> {
+        ::std::io::Read::read_exact(self, buf)
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl<'a, R: RmpRead + ?Sized> RmpRead for &'a mut R {
+    type Error = R::Error;
+
+    fn read_exact_buf(&mut self, buf: &mut [u8]) -> Result<(), Self::Error> {
+        (**self).read_exact_buf(buf)
+    }
+}
+
 /// An error that can occur when attempting to read a MessagePack marker from the reader.
 #[derive(Debug)]
-pub struct MarkerReadError(pub Error);
+pub struct MarkerReadError<E: RmpReadErr = Error>(pub E);
 
 /// An error which can occur when attempting to read a MessagePack value from the reader.
 #[derive(Debug)]
-pub enum ValueReadError {
+pub enum ValueReadError<E: RmpReadErr = Error> {
     /// Failed to read the marker.
-    InvalidMarkerRead(Error),
+    InvalidMarkerRead(E),
     /// Failed to read the data.
-    InvalidDataRead(Error),
+    InvalidDataRead(E),
     /// The type decoded isn't match with the expected one.
     TypeMismatch(Marker),
 }
 
-impl error::Error for ValueReadError {
-    fn description(&self) -> &str {
+impl<E: RmpReadErr> ValueReadError<E> {
+    fn as_str(&self) -> &'static str {
         match *self {
             ValueReadError::InvalidMarkerRead(..) => "failed to read MessagePack marker",
             ValueReadError::InvalidDataRead(..) => "failed to read MessagePack data",
@@ -60,6 +233,13 @@ impl error::Error for ValueReadError {
             }
         }
     }
+}
+
+#[cfg(feature = "std")]
+impl<E: RmpReadErr> error::Error for ValueReadError<E> {
+    fn description(&self) -> &str {
+        self.as_str()
+    }
 
     fn cause(&self) -> Option<&error::Error> {
         match *self {
@@ -70,29 +250,29 @@ impl error::Error for ValueReadError {
     }
 }
 
-impl Display for ValueReadError {
+impl<E: RmpReadErr> Display for ValueReadError<E> {
     fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
-        error::Error::description(self).fmt(f)
+        f.write_str(self.as_str())
     }
 }
 
-impl From<MarkerReadError> for ValueReadError {
-    fn from(err: MarkerReadError) -> ValueReadError {
+impl<E: RmpReadErr> From<MarkerReadError<E>> for ValueReadError<E> {
+    fn from(err: MarkerReadError<E>) -> ValueReadError<E> {
         match err {
             MarkerReadError(err) => ValueReadError::InvalidMarkerRead(err),
         }
     }
 }
 
-impl From<Error> for MarkerReadError {
-    fn from(err: Error) -> MarkerReadError {
+impl<E: RmpReadErr> From<E> for MarkerReadError<E> {
+    fn from(err: E) -> MarkerReadError<E> {
         MarkerReadError(err)
     }
 }
 
 /// Attempts to read a single byte from the given reader and to decode it as a MessagePack marker.
-pub fn read_marker<R: Read>(rd: &mut R) -> Result<Marker, MarkerReadError> {
-    Ok(Marker::from_u8(try!(rd.read_u8())))
+pub fn read_marker<R: RmpRead>(rd: &mut R) -> Result<Marker, MarkerReadError<R::Error>> {
+    Ok(Marker::from_u8(rd.read_u8()?))
 }
 
 /// Attempts to read a single byte from the given reader and to decode it as a nil value.
@@ -111,7 +291,7 @@ pub fn read_marker<R: Read>(rd: &mut R) -> Result<Marker, MarkerReadError> {
 ///
 /// This function will silently retry on every EINTR received from the underlying `Read` until
 /// successful read.
-pub fn read_nil<R: Read>(rd: &mut R) -> Result<(), ValueReadError> {
+pub fn read_nil<R: RmpRead>(rd: &mut R) -> Result<(), ValueReadError<R::Error>> {
     match try!(read_marker(rd)) {
         Marker::Null => Ok(()),
         marker => Err(ValueReadError::TypeMismatch(marker)),
@@ -135,7 +315,7 @@ pub fn read_nil<R: Read>(rd: &mut R) -> Result<(), ValueReadError> {
 ///
 /// This function will silently retry on every EINTR received from the underlying `Read` until
 /// successful read.
-pub fn read_bool<R: Read>(rd: &mut R) -> Result<bool, ValueReadError> {
+pub fn read_bool<R: RmpRead>(rd: &mut R) -> Result<bool, ValueReadError<R::Error>> {
     match try!(read_marker(rd)) {
         Marker::True => Ok(true),
         Marker::False => Ok(false),
@@ -145,19 +325,19 @@ pub fn read_bool<R: Read>(rd: &mut R) -> Result<bool, ValueReadError> {
 
 /// An error which can occur when attempting to read a MessagePack numeric value from the reader.
 #[derive(Debug)]
-pub enum NumValueReadError {
+pub enum NumValueReadError<E: RmpReadErr = Error> {
     /// Failed to read the marker.
-    InvalidMarkerRead(Error),
+    InvalidMarkerRead(E),
     /// Failed to read the data.
-    InvalidDataRead(Error),
+    InvalidDataRead(E),
     /// The type decoded isn't match with the expected one.
     TypeMismatch(Marker),
     /// Out of range integral type conversion attempted.
     OutOfRange,
 }
 
-impl error::Error for NumValueReadError {
-    fn description(&self) -> &str {
+impl<E: RmpReadErr> NumValueReadError<E> {
+    fn as_str(&self) -> &'static str {
         match *self {
             NumValueReadError::InvalidMarkerRead(..) => "failed to read MessagePack marker",
             NumValueReadError::InvalidDataRead(..) => "failed to read MessagePack data",
@@ -167,6 +347,13 @@ impl error::Error for NumValueReadError {
             NumValueReadError::OutOfRange => "out of range integral type conversion attempted",
         }
     }
+}
+
+#[cfg(feature = "std")]
+impl<E: RmpReadErr> error::Error for NumValueReadError<E> {
+    fn description(&self) -> &str {
+        self.as_str()
+    }
 
     fn cause(&self) -> Option<&error::Error> {
         match *self {
@@ -178,22 +365,22 @@ impl error::Error for NumValueReadError {
     }
 }
 
-impl Display for NumValueReadError {
+impl<E: RmpReadErr> Display for NumValueReadError<E> {
     fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
-        error::Error::description(self).fmt(f)
+        f.write_str(self.as_str())
     }
 }
 
-impl From<MarkerReadError> for NumValueReadError {
-    fn from(err: MarkerReadError) -> NumValueReadError {
+impl<E: RmpReadErr> From<MarkerReadError<E>> for NumValueReadError<E> {
+    fn from(err: MarkerReadError<E>) -> NumValueReadError<E> {
         match err {
             MarkerReadError(err) => NumValueReadError::InvalidMarkerRead(err),
         }
     }
 }
 
-impl From<ValueReadError> for NumValueReadError {
-    fn from(err: ValueReadError) -> NumValueReadError {
+impl<E: RmpReadErr> From<ValueReadError<E>> for NumValueReadError<E> {
+    fn from(err: ValueReadError<E>) -> NumValueReadError<E> {
         match err {
             ValueReadError::InvalidMarkerRead(err) => NumValueReadError::InvalidMarkerRead(err),
             ValueReadError::InvalidDataRead(err) => NumValueReadError::InvalidDataRead(err),
@@ -204,54 +391,63 @@ impl From<ValueReadError> for NumValueReadError {
 
 // Helper functions to map I/O error into the `InvalidDataRead` error.
 
+/// Reads exactly `$size` bytes into a stack buffer.
+macro_rules! read_data {
+    ($rd:expr, $size:expr) => {{
+        let mut buf = [0; $size];
+        $rd.read_exact_buf(&mut buf).map_err(ValueReadError::InvalidDataRead)?;
+        buf
+    }};
+}
+
 #[doc(hidden)]
-pub fn read_data_u8<R: Read>(rd: &mut R) -> Result<u8, ValueReadError> {
+pub fn read_data_u8<R: RmpRead>(rd: &mut R) -> Result<u8, ValueReadError<R::Error>> {
     rd.read_u8().map_err(ValueReadError::InvalidDataRead)
 }
 
 #[doc(hidden)]
-pub fn read_data_u16<R: Read>(rd: &mut R) -> Result<u16, ValueReadError> {
-    rd.read_u16::<byteorder::BigEndian>().map_err(ValueReadError::InvalidDataRead)
+pub fn read_data_u16<R: RmpRead>(rd: &mut R) -> Result<u16, ValueReadError<R::Error>> {
+    Ok(u16::from_be_bytes(read_data!(rd, 2)))
 }
 
 #[doc(hidden)]
-pub fn read_data_u32<R: Read>(rd: &mut R) -> Result<u32, ValueReadError> {
-    rd.read_u32::<byteorder::BigEndian>().map_err(ValueReadError::InvalidDataRead)
+pub fn read_data_u32<R: RmpRead>(rd: &mut R) -> Result<u32, ValueReadError<R::Error>> {
+    Ok(u32::from_be_bytes(read_data!(rd, 4)))
 }
 
 #[doc(hidden)]
-pub fn read_data_u64<R: Read>(rd: &mut R) -> Result<u64, ValueReadError> {
-    rd.read_u64::<byteorder::BigEndian>().map_err(ValueReadError::InvalidDataRead)
+pub fn read_data_u64<R: RmpRead>(rd: &mut R) -> Result<u64, ValueReadError<R::Error>> {
+    Ok(u64::from_be_bytes(read_data!(rd, 8)))
 }
 
 #[doc(hidden)]
-pub fn read_data_i8<R: Read>(rd: &mut R) -> Result<i8, ValueReadError> {
-    rd.read_i8().map_err(ValueReadError::InvalidDataRead)
+pub fn read_data_i8<R: RmpRead>(rd: &mut R) -> Result<i8, ValueReadError<R::Error>> {
+    read_data_u8(rd).map(|val| val as i8)
 }
 
 #[doc(hidden)]
-pub fn read_data_i16<R: Read>(rd: &mut R) -> Result<i16, ValueReadError> {
-    rd.read_i16::<byteorder::BigEndian>().map_err(ValueReadError::InvalidDataRead)
+pub fn read_data_i16<R: RmpRead>(rd: &mut R) -> Result<i16, ValueReadError<R::Error>> {
+    Ok(i16::from_be_bytes(read_data!(rd, 2)))
 }
 
 #[doc(hidden)]
-pub fn read_data_i32<R: Read>(rd: &mut R) -> Result<i32, ValueReadError> {
-    rd.read_i32::<byteorder::BigEndian>().map_err(ValueReadError::InvalidDataRead)
+pub fn read_data_i32<R: RmpRead>(rd: &mut R) -> Result<i32, ValueReadError<R::Error>> {
+    Ok(i32::from_be_bytes(read_data!(rd, 4)))
 }
 
 #[doc(hidden)]
-pub fn read_data_i64<R: Read>(rd: &mut R) -> Result<i64, ValueReadError> {
-    rd.read_i64::<byteorder::BigEndian>().map_err(ValueReadError::InvalidDataRead)
+pub fn read_data_i64<R: RmpRead>(rd: &mut R) -> Result<i64, ValueReadError<R::Error>> {
+    Ok(i64::from_be_bytes(read_data!(rd, 8)))
 }
 
 #[doc(hidden)]
-pub fn read_data_f32<R: Read>(rd: &mut R) -> Result<f32, ValueReadError> {
-    rd.read_f32::<byteorder::BigEndian>().map_err(ValueReadError::InvalidDataRead)
+pub fn read_data_f32<R: RmpRead>(rd: &mut R) -> Result<f32, ValueReadError<R::Error>> {
+    Ok(f32::from_be_bytes(read_data!(rd, 4)))
 }
 
 #[doc(hidden)]
-pub fn read_data_f64<R: Read>(rd: &mut R) -> Result<f64, ValueReadError> {
-    rd.read_f64::<byteorder::BigEndian>().map_err(ValueReadError::InvalidDataRead)
+pub fn read_data_f64<R: RmpRead>(rd: &mut R) -> Result<f64, ValueReadError<R::Error>> {
+    Ok(f64::from_be_bytes(read_data!(rd, 8)))
 }
 
 /// Attempts to read up to 9 bytes from the given reader and to decode them as integral `T` value.
@@ -284,7 +480,9 @@ pub fn read_data_f64<R: Read>(rd: &mut R) -> Result<f64, ValueReadError> {
 /// assert_eq!(300usize, rmp::decode::read_int(&mut &buf[..]).unwrap());
 /// assert_eq!(300isize, rmp::decode::read_int(&mut &buf[..]).unwrap());
 /// ```
-pub fn read_int<T: FromPrimitive, R: Read>(rd: &mut R) -> Result<T, NumValueReadError> {
+pub fn read_int<T: FromPrimitive, R: RmpRead>(rd: &mut R) ->
+    Result<T, NumValueReadError<R::Error>>
+{
     let val = match try!(read_marker(rd)) {
         Marker::FixPos(val) => T::from_u8(val),
         Marker::FixNeg(val) => T::from_i8(val),
@@ -314,8 +512,8 @@ pub fn read_int<T: FromPrimitive, R: Read>(rd: &mut R) -> Result<T, NumValueRead
 /// successful read.
 // TODO: Docs.
 // NOTE: EINTR is managed internally.
-pub fn read_array_len<R>(rd: &mut R) -> Result<u32, ValueReadError>
-    where R: Read
+pub fn read_array_len<R>(rd: &mut R) -> Result<u32, ValueReadError<R::Error>>
+    where R: RmpRead
 {
     match try!(read_marker(rd)) {
         Marker::FixArray(size) => Ok(size as u32),
@@ -336,7 +534,7 @@ pub fn read_array_len<R>(rd: &mut R) -> Result<u32, ValueReadError>
 /// This function will silently retry on every EINTR received from the underlying `Read` until
 /// successful read.
 // TODO: Docs.
-pub fn read_map_len<R: Read>(rd: &mut R) -> Result<u32, ValueReadError> {
+pub fn read_map_len<R: RmpRead>(rd: &mut R) -> Result<u32, ValueReadError<R::Error>> {
     match try!(read_marker(rd)) {
         Marker::FixMap(size) => Ok(size as u32),
         Marker::Map16 => Ok(try!(read_data_u16(rd)) as u32),
@@ -352,7 +550,7 @@ pub fn read_map_len<R: Read>(rd: &mut R) -> Result<u32, ValueReadError> {
 /// This function will silently retry on every EINTR received from the underlying `Read` until
 /// successful read.
 // TODO: Docs.
-pub fn read_bin_len<R: Read>(rd: &mut R) -> Result<u32, ValueReadError> {
+pub fn read_bin_len<R: RmpRead>(rd: &mut R) -> Result<u32, ValueReadError<R::Error>> {
     match try!(read_marker(rd)) {
         Marker::Bin8 => Ok(try!(read_data_u8(rd)) as u32),
         Marker::Bin16 => Ok(try!(read_data_u16(rd)) as u32),
@@ -0,0 +1,179 @@
+use std::collections::HashSet;
+use std::error;
+use std::fmt::{self, Display, Formatter};
+use std::io::{self, Read};
+
+use Marker;
+use super::{read_map_len, skip_value, Error, ValueReadError};
+
+/// A reader appending everything read through it to a buffer.
+///
+/// The buffer grows as the data arrives, so a malicious length prefix can't force a huge
+/// allocation up front.
+struct Recorder<'a, R: 'a> {
+    rd: &'a mut R,
+    buf: &'a mut Vec<u8>,
+}
+
+impl<'a, R: Read> Read for Recorder<'a, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.rd.read(buf)?;
+        self.buf.extend_from_slice(&buf[..n]);
+        Ok(n)
+    }
+}
+
+/// Attempts to read a single complete MessagePack value from the given reader, appending its
+/// exact encoded bytes to the buffer.
+///
+/// The value is walked like `skip_value` does. The reserved marker `0xc1` is not a value and
+/// fails with `ValueReadError::TypeMismatch`.
+pub fn read_raw_value<R: Read>(rd: &mut R, buf: &mut Vec<u8>) -> Result<(), ValueReadError> {
+    skip_value(&mut Recorder { rd, buf })
+}
+
+/// An error which can occur when walking through map entries with `read_unique_map_entries`.
+#[derive(Debug)]
+pub enum MapReadError {
+    /// Failed to read the marker.
+    InvalidMarkerRead(Error),
+    /// Failed to read the data.
+    InvalidDataRead(Error),
+    /// The type decoded isn't match with the expected one.
+    TypeMismatch(Marker),
+    /// The key of the entry with the given index is bytewise equal to the key of one of the
+    /// previous entries.
+    DuplicateKey(u32),
+}
+
+impl error::Error for MapReadError {
+    fn description(&self) -> &str {
+        match *self {
+            MapReadError::InvalidMarkerRead(..) => "failed to read MessagePack marker",
+            MapReadError::InvalidDataRead(..) => "failed to read MessagePack data",
+            MapReadError::TypeMismatch(..) => "the type decoded isn't match with the expected one",
+            MapReadError::DuplicateKey(..) => "duplicate key found in MessagePack map",
+        }
+    }
+
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            MapReadError::InvalidMarkerRead(ref err) |
+            MapReadError::InvalidDataRead(ref err) => Some(err),
+            MapReadError::TypeMismatch(..) |
+            MapReadError::DuplicateKey(..) => None,
+        }
+    }
+}
+
+impl Display for MapReadError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        error::Error::description(self).fmt(f)
+    }
+}
+
+impl From<ValueReadError> for MapReadError {
+    fn from(err: ValueReadError) -> MapReadError {
+        match err {
+            ValueReadError::InvalidMarkerRead(err) => MapReadError::InvalidMarkerRead(err),
+            ValueReadError::InvalidDataRead(err) => MapReadError::InvalidDataRead(err),
+            ValueReadError::TypeMismatch(marker) => MapReadError::TypeMismatch(marker),
+        }
+    }
+}
+
+/// Attempts to read a MessagePack map from the given reader, calling `f` with the raw encoded
+/// bytes of every key and value in order.
+///
+/// Only a single entry is kept in memory at once, which makes this function suitable for
+/// inspecting or forwarding maps without materializing them. Returns the number of entries read.
+///
+/// # Errors
+///
+/// This function will return `ValueReadError` on any I/O error while reading the map, and
+/// `ValueReadError::TypeMismatch` if the value read is not a map or if a key or a value contains
+/// the reserved marker `0xc1`.
+///
+/// # Examples
+///
+/// ```
+/// use rmp::decode::read_map_entries;
+///
+/// // {"a": 1, "b": [2]}
+/// let buf = [0x82, 0xa1, 0x61, 0x01, 0xa1, 0x62, 0x91, 0x02];
+/// let mut entries = Vec::new();
+///
+/// let len = read_map_entries(&mut &buf[..], |key, val| entries.push((key.to_vec(), val.to_vec())))
+///     .unwrap();
+///
+/// assert_eq!(2, len);
+/// assert_eq!((vec![0xa1, 0x62], vec![0x91, 0x02]), entries[1]);
+/// ```
+pub fn read_map_entries<R, F>(rd: &mut R, mut f: F) -> Result<u32, ValueReadError>
+    where R: Read,
+          F: FnMut(&[u8], &[u8])
+{
+    let len = read_map_len(rd)?;
+
+    let mut key = Vec::new();
+    let mut val = Vec::new();
+    for _ in 0..len {
+        key.clear();
+        val.clear();
+        read_raw_value(rd, &mut key)?;
+        read_raw_value(rd, &mut val)?;
+        f(&key, &val);
+    }
+
+    Ok(len)
+}
+
+/// Attempts to read a MessagePack map from the given reader like `read_map_entries` does, but
+/// also ensures that its keys are unique.
+///
+/// Keys are compared by their raw encoded bytes, so `1` encoded as a fixint and as an `u64` are
+/// considered different keys. Only the keys are retained while walking the map, values are
+/// dropped as soon as the callback returns.
+///
+/// # Errors
+///
+/// In addition to the errors `read_map_entries` returns, this function fails with
+/// `MapReadError::DuplicateKey` as soon as a key repeats, before the callback is invoked for
+/// that entry.
+///
+/// # Examples
+///
+/// ```
+/// use rmp::decode::{read_unique_map_entries, MapReadError};
+///
+/// // {"a": 1, "a": 2}
+/// let buf = [0x82, 0xa1, 0x61, 0x01, 0xa1, 0x61, 0x02];
+///
+/// match read_unique_map_entries(&mut &buf[..], |_, _| {}) {
+///     Err(MapReadError::DuplicateKey(1)) => {}
+///     other => panic!("unexpected result: {:?}", other),
+/// }
+/// ```
+pub fn read_unique_map_entries<R, F>(rd: &mut R, mut f: F) -> Result<u32, MapReadError>
+    where R: Read,
+          F: FnMut(&[u8], &[u8])
+{
+    let len = read_map_len(rd)?;
+
+    let mut keys = HashSet::new();
+    let mut val = Vec::new();
+    for idx in 0..len {
+        let mut key = Vec::new();
+        read_raw_value(rd, &mut key)?;
+        if keys.contains(&key) {
+            return Err(MapReadError::DuplicateKey(idx));
+        }
+
+        val.clear();
+        read_raw_value(rd, &mut val)?;
+        f(&key, &val);
+        keys.insert(key);
+    }
+
+    Ok(len)
+}
@@ -0,0 +1,116 @@
+use Marker;
+use super::{read_marker, read_data_u8, read_data_u16, read_data_u32, RmpRead, ValueReadError};
+
+/// Attempts to skip over one complete value, including all elements of arrays and maps, leaving
+/// the reader at the value following it.
+///
+/// Nothing is allocated: data is discarded through a small stack buffer, and nested arrays and
+/// maps are walked iteratively by counting the values left, so deeply nested input can't
+/// overflow the stack either.
+///
+/// # Errors
+///
+/// This function will return `ValueReadError` on any I/O error while reading the value, in which
+/// case the reader is left somewhere inside of it. The reserved marker `0xc1` is not a value and
+/// fails with `ValueReadError::TypeMismatch`.
+///
+/// # Examples
+///
+/// ```
+/// use rmp::decode;
+///
+/// // {"a": [1, "b"]} followed by `true`.
+/// let buf = [0x81, 0xa1, 0x61, 0x92, 0x01, 0xa1, 0x62, 0xc3];
+/// let mut rd = &buf[..];
+///
+/// decode::skip_value(&mut rd).unwrap();
+/// assert!(decode::read_bool(&mut rd).unwrap());
+/// ```
+pub fn skip_value<R: RmpRead>(rd: &mut R) -> Result<(), ValueReadError<R::Error>> {
+    let marker = read_marker(rd)?;
+    skip_data(rd, marker)
+}
+
+/// Attempts to skip over the rest of a value whose marker was already read, like `skip_value`.
+///
+/// This is for decoders peeking at the marker before deciding whether to decode the value.
+///
+/// # Errors
+///
+/// This function will return `ValueReadError` on any I/O error while reading the value, and
+/// `ValueReadError::TypeMismatch` if it is or holds the reserved marker.
+pub fn skip_data<R: RmpRead>(rd: &mut R, marker: Marker) -> Result<(), ValueReadError<R::Error>> {
+    let mut marker = marker;
+    // Number of values still to be skipped after the current one, including nested ones.
+    let mut pending = 0u64;
+
+    loop {
+        let len = match marker {
+            Marker::FixPos(..) | Marker::FixNeg(..) | Marker::Null | Marker::True |
+            Marker::False => 0,
+            Marker::Reserved => return Err(ValueReadError::TypeMismatch(Marker::Reserved)),
+            Marker::U8 | Marker::I8 => 1,
+            Marker::U16 | Marker::I16 => 2,
+            Marker::U32 | Marker::I32 | Marker::F32 => 4,
+            Marker::U64 | Marker::I64 | Marker::F64 => 8,
+            Marker::FixStr(len) => len as u64,
+            Marker::Str8 | Marker::Bin8 => read_data_u8(rd)? as u64,
+            Marker::Str16 | Marker::Bin16 => read_data_u16(rd)? as u64,
+            Marker::Str32 | Marker::Bin32 => read_data_u32(rd)? as u64,
+            Marker::FixArray(len) => {
+                pending += len as u64;
+                0
+            }
+            Marker::Array16 => {
+                pending += read_data_u16(rd)? as u64;
+                0
+            }
+            Marker::Array32 => {
+                pending += read_data_u32(rd)? as u64;
+                0
+            }
+            Marker::FixMap(len) => {
+                pending += 2 * len as u64;
+                0
+            }
+            Marker::Map16 => {
+                pending += 2 * read_data_u16(rd)? as u64;
+                0
+            }
+            Marker::Map32 => {
+                pending += 2 * read_data_u32(rd)? as u64;
+                0
+            }
+            Marker::FixExt1 => 1 + 1,
+            Marker::FixExt2 => 1 + 2,
+            Marker::FixExt4 => 1 + 4,
+            Marker::FixExt8 => 1 + 8,
+            Marker::FixExt16 => 1 + 16,
+            Marker::Ext8 => 1 + read_data_u8(rd)? as u64,
+            Marker::Ext16 => 1 + read_data_u16(rd)? as u64,
+            Marker::Ext32 => 1 + read_data_u32(rd)? as u64,
+        };
+
+        discard(rd, len).map_err(ValueReadError::InvalidDataRead)?;
+
+        if pending == 0 {
+            return Ok(());
+        }
+        pending -= 1;
+        marker = read_marker(rd)?;
+    }
+}
+
+/// Reads and drops the given number of bytes.
+fn discard<R: RmpRead>(rd: &mut R, len: u64) -> Result<(), R::Error> {
+    let mut buf = [0; 256];
+    let mut left = len;
+
+    while left > 0 {
+        let chunk = if left < buf.len() as u64 { left as usize } else { buf.len() };
+        rd.read_exact_buf(&mut buf[..chunk])?;
+        left -= chunk as u64;
+    }
+
+    Ok(())
+}
@@ -1,22 +1,24 @@
+#[cfg(feature = "std")]
 use std::error;
-use std::io::{self, Read};
-use std::fmt::{self, Display, Formatter};
-use std::str::{Utf8Error, from_utf8};
+use core::fmt::{self, Display, Formatter};
+use core::str::{Utf8Error, from_utf8};
 
 use Marker;
-use super::{read_marker, read_data_u8, read_data_u16, read_data_u32, Error, ValueReadError};
+use super::{read_marker, read_data_u8, read_data_u16, read_data_u32, Error, RmpRead, RmpReadErr,
+            ValueReadError};
 
 #[derive(Debug)]
-pub enum DecodeStringError<'a> {
-    InvalidMarkerRead(Error),
-    InvalidDataRead(Error),
+pub enum DecodeStringError<'a, E: RmpReadErr = Error> {
+    InvalidMarkerRead(E),
+    InvalidDataRead(E),
     TypeMismatch(Marker),
     /// The given buffer is not large enough to accumulate the specified amount of bytes.
     BufferSizeTooSmall(u32),
     InvalidUtf8(&'a [u8], Utf8Error),
 }
 
-impl<'a> error::Error for DecodeStringError<'a> {
+#[cfg(feature = "std")]
+impl<'a, E: RmpReadErr> error::Error for DecodeStringError<'a, E> {
     fn description(&self) -> &str {
         "error while decoding string"
     }
@@ -32,14 +34,14 @@ impl<'a> error::Error for DecodeStringError<'a> {
     }
 }
 
-impl<'a> Display for DecodeStringError<'a> {
+impl<'a, E: RmpReadErr> Display for DecodeStringError<'a, E> {
     fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
-        error::Error::description(self).fmt(f)
+        f.write_str("error while decoding string")
     }
 }
 
-impl<'a> From<ValueReadError> for DecodeStringError<'a> {
-    fn from(err: ValueReadError) -> DecodeStringError<'a> {
+impl<'a, E: RmpReadErr> From<ValueReadError<E>> for DecodeStringError<'a, E> {
+    fn from(err: ValueReadError<E>) -> DecodeStringError<'a, E> {
         match err {
             ValueReadError::InvalidMarkerRead(err) => DecodeStringError::InvalidMarkerRead(err),
             ValueReadError::InvalidDataRead(err) => DecodeStringError::InvalidDataRead(err),
@@ -61,12 +63,12 @@ impl<'a> From<ValueReadError> for DecodeStringError<'a> {
 ///
 /// It also returns `ValueReadError::TypeMismatch` if the actual type is not equal with the
 /// expected one, indicating you with the actual type.
-pub fn read_str_len<R: Read>(rd: &mut R) -> Result<u32, ValueReadError> {
+pub fn read_str_len<R: RmpRead>(rd: &mut R) -> Result<u32, ValueReadError<R::Error>> {
     Ok(read_str_len_with_nread(rd)?.0)
 }
 
-fn read_str_len_with_nread<R>(rd: &mut R) -> Result<(u32, usize), ValueReadError>
-    where R: Read
+fn read_str_len_with_nread<R>(rd: &mut R) -> Result<(u32, usize), ValueReadError<R::Error>>
+    where R: RmpRead
 {
     match read_marker(rd)? {
         Marker::FixStr(size) => Ok((size as u32, 1)),
@@ -107,8 +109,9 @@ fn read_str_len_with_nread<R>(rd: &mut R) -> Result<(u32, usize), ValueReadError
 ///
 /// This function is **unstable**, because it needs review.
 // TODO: Stabilize. Mark error values for each error case (in docs).
-pub fn read_str<'r, R>(rd: &mut R, mut buf: &'r mut [u8]) -> Result<&'r str, DecodeStringError<'r>>
-    where R: Read
+pub fn read_str<'r, R>(rd: &mut R, mut buf: &'r mut [u8]) ->
+    Result<&'r str, DecodeStringError<'r, R::Error>>
+    where R: RmpRead
 {
     let len = try!(read_str_len(rd));
     let ulen = len as usize;
@@ -123,20 +126,20 @@ pub fn read_str<'r, R>(rd: &mut R, mut buf: &'r mut [u8]) -> Result<&'r str, Dec
 pub fn read_str_data<'r, R>(rd: &mut R,
                             len: u32,
                             buf: &'r mut [u8])
-                            -> Result<&'r str, DecodeStringError<'r>>
-    where R: Read
+                            -> Result<&'r str, DecodeStringError<'r, R::Error>>
+    where R: RmpRead
 {
     debug_assert_eq!(len as usize, buf.len());
 
     // Trying to copy exact `len` bytes.
-    match rd.read_exact(buf) {
+    match rd.read_exact_buf(buf) {
         Ok(()) => {
             match from_utf8(buf) {
                 Ok(decoded) => Ok(decoded),
                 Err(err) => Err(DecodeStringError::InvalidUtf8(buf, err)),
             }
         }
-        Err(err) => Err(DecodeStringError::InvalidDataRead(From::from(err))),
+        Err(err) => Err(DecodeStringError::InvalidDataRead(err)),
     }
 }
 
@@ -145,9 +148,9 @@ pub fn read_str_data<'r, R>(rd: &mut R,
 // TODO: Also it's possible to implement all borrowing functions for all `BufRead` implementors.
 #[deprecated(since = "0.8.6", note = "useless, use `read_str_from_slice` instead")]
 pub fn read_str_ref(rd: &[u8]) -> Result<&[u8], DecodeStringError> {
-    let mut cur = io::Cursor::new(rd);
+    let mut cur = rd;
     let len = try!(read_str_len(&mut cur));
-    let start = cur.position() as usize;
+    let start = rd.len() - cur.len();
     Ok(&rd[start..start + len as usize])
 }
 
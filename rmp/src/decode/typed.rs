@@ -0,0 +1,293 @@
+use std::error;
+use std::fmt::{self, Display, Formatter};
+use std::io::Read;
+use std::str::Utf8Error;
+
+use Marker;
+use super::{read_array_len, read_bool, read_f32, read_f64, read_int, read_map_len, read_str_len,
+            Error, NumValueReadError, ValueReadError};
+
+/// An error which can occur when attempting to read a typed value using the `Readable` trait.
+#[derive(Debug)]
+pub enum TypedReadError {
+    /// Failed to read the marker.
+    InvalidMarkerRead(Error),
+    /// Failed to read the data.
+    InvalidDataRead(Error),
+    /// The type decoded isn't match with the expected one.
+    TypeMismatch(Marker),
+    /// Out of range integral type conversion attempted.
+    OutOfRange,
+    /// The string read is not valid UTF-8.
+    InvalidUtf8(Utf8Error),
+    /// The container read has the given length, which doesn't match the expected one.
+    LengthMismatch(u32),
+}
+
+impl error::Error for TypedReadError {
+    fn description(&self) -> &str {
+        match *self {
+            TypedReadError::InvalidMarkerRead(..) => "failed to read MessagePack marker",
+            TypedReadError::InvalidDataRead(..) => "failed to read MessagePack data",
+            TypedReadError::TypeMismatch(..) => "the type decoded isn't match with the expected one",
+            TypedReadError::OutOfRange => "out of range integral type conversion attempted",
+            TypedReadError::InvalidUtf8(..) => "the string decoded is not valid UTF-8",
+            TypedReadError::LengthMismatch(..) => "the container length doesn't match the expected one",
+        }
+    }
+
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            TypedReadError::InvalidMarkerRead(ref err) |
+            TypedReadError::InvalidDataRead(ref err) => Some(err),
+            TypedReadError::InvalidUtf8(ref err) => Some(err),
+            TypedReadError::TypeMismatch(..) |
+            TypedReadError::OutOfRange |
+            TypedReadError::LengthMismatch(..) => None,
+        }
+    }
+}
+
+impl Display for TypedReadError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        error::Error::description(self).fmt(f)
+    }
+}
+
+impl From<ValueReadError> for TypedReadError {
+    fn from(err: ValueReadError) -> TypedReadError {
+        match err {
+            ValueReadError::InvalidMarkerRead(err) => TypedReadError::InvalidMarkerRead(err),
+            ValueReadError::InvalidDataRead(err) => TypedReadError::InvalidDataRead(err),
+            ValueReadError::TypeMismatch(marker) => TypedReadError::TypeMismatch(marker),
+        }
+    }
+}
+
+impl From<NumValueReadError> for TypedReadError {
+    fn from(err: NumValueReadError) -> TypedReadError {
+        match err {
+            NumValueReadError::InvalidMarkerRead(err) => TypedReadError::InvalidMarkerRead(err),
+            NumValueReadError::InvalidDataRead(err) => TypedReadError::InvalidDataRead(err),
+            NumValueReadError::TypeMismatch(marker) => TypedReadError::TypeMismatch(marker),
+            NumValueReadError::OutOfRange => TypedReadError::OutOfRange,
+        }
+    }
+}
+
+/// A type that can be read from a MessagePack stream using the low-level decoding functions.
+///
+/// Implemented for booleans, integers (using `read_int`, which accepts any integer encoding that
+/// fits), floats, strings and vectors of readable values, which are read from arrays.
+///
+/// Tuples of up to 12 readable values are read from arrays of exactly the same arity, see the
+/// `read_tuple!` macro.
+pub trait Readable: Sized {
+    /// Attempts to read a value of this type from the given reader.
+    fn read_from<R: Read>(rd: &mut R) -> Result<Self, TypedReadError>;
+}
+
+impl Readable for bool {
+    fn read_from<R: Read>(rd: &mut R) -> Result<bool, TypedReadError> {
+        Ok(read_bool(rd)?)
+    }
+}
+
+macro_rules! impl_readable_int {
+    ($($ty:ty)*) => {
+        $(
+            impl Readable for $ty {
+                fn read_from<R: Read>(rd: &mut R) -> Result<$ty, TypedReadError> {
+                    Ok(read_int(rd)?)
+                }
+            }
+        )*
+    }
+}
+
+impl_readable_int!(u8 u16 u32 u64 usize i8 i16 i32 i64 isize);
+
+impl Readable for f32 {
+    fn read_from<R: Read>(rd: &mut R) -> Result<f32, TypedReadError> {
+        Ok(read_f32(rd)?)
+    }
+}
+
+impl Readable for f64 {
+    fn read_from<R: Read>(rd: &mut R) -> Result<f64, TypedReadError> {
+        Ok(read_f64(rd)?)
+    }
+}
+
+impl Readable for String {
+    fn read_from<R: Read>(rd: &mut R) -> Result<String, TypedReadError> {
+        let len = read_str_len(rd)? as u64;
+
+        let mut buf = Vec::new();
+        let nread = rd.take(len).read_to_end(&mut buf).map_err(TypedReadError::InvalidDataRead)?;
+        if nread as u64 != len {
+            return Err(TypedReadError::InvalidDataRead(unexpected_eof()));
+        }
+
+        String::from_utf8(buf).map_err(|err| TypedReadError::InvalidUtf8(err.utf8_error()))
+    }
+}
+
+impl<T: Readable> Readable for Vec<T> {
+    fn read_from<R: Read>(rd: &mut R) -> Result<Vec<T>, TypedReadError> {
+        read_array_of(rd)
+    }
+}
+
+macro_rules! impl_readable_tuple {
+    ($len:expr => $($name:ident)+) => {
+        impl<$($name: Readable),+> Readable for ($($name,)+) {
+            fn read_from<R: Read>(rd: &mut R) -> Result<($($name,)+), TypedReadError> {
+                let len = read_array_len(rd)?;
+                if len != $len {
+                    return Err(TypedReadError::LengthMismatch(len));
+                }
+
+                Ok(($($name::read_from(rd)?,)+))
+            }
+        }
+    }
+}
+
+impl_readable_tuple!(1 => T0);
+impl_readable_tuple!(2 => T0 T1);
+impl_readable_tuple!(3 => T0 T1 T2);
+impl_readable_tuple!(4 => T0 T1 T2 T3);
+impl_readable_tuple!(5 => T0 T1 T2 T3 T4);
+impl_readable_tuple!(6 => T0 T1 T2 T3 T4 T5);
+impl_readable_tuple!(7 => T0 T1 T2 T3 T4 T5 T6);
+impl_readable_tuple!(8 => T0 T1 T2 T3 T4 T5 T6 T7);
+impl_readable_tuple!(9 => T0 T1 T2 T3 T4 T5 T6 T7 T8);
+impl_readable_tuple!(10 => T0 T1 T2 T3 T4 T5 T6 T7 T8 T9);
+impl_readable_tuple!(11 => T0 T1 T2 T3 T4 T5 T6 T7 T8 T9 T10);
+impl_readable_tuple!(12 => T0 T1 T2 T3 T4 T5 T6 T7 T8 T9 T10 T11);
+
+fn unexpected_eof() -> Error {
+    ::std::io::ErrorKind::UnexpectedEof.into()
+}
+
+/// Attempts to read a MessagePack array from the given reader, calling `f` to read each of its
+/// elements.
+///
+/// The array header and the element count are handled by this function, so `f` must read exactly
+/// one element each time it is called.
+///
+/// # Errors
+///
+/// Returns the first error either from reading the array header or returned by `f`.
+///
+/// # Examples
+///
+/// ```
+/// use rmp::decode::{read_array_with, read_int, NumValueReadError};
+///
+/// let buf = [0x93, 0x01, 0x02, 0x03];
+///
+/// let vec: Result<Vec<u64>, NumValueReadError> = read_array_with(&mut &buf[..], |rd| {
+///     read_int(rd).map(|v: u64| v * 10)
+/// });
+///
+/// assert_eq!(vec![10, 20, 30], vec.unwrap());
+/// ```
+pub fn read_array_with<R, T, E, F>(rd: &mut R, mut f: F) -> Result<Vec<T>, E>
+    where R: Read,
+          E: From<ValueReadError>,
+          F: FnMut(&mut R) -> Result<T, E>
+{
+    let len = read_array_len(rd)?;
+
+    // The length is untrusted, so limit the preallocation.
+    let mut vec = Vec::with_capacity(::std::cmp::min(len, 4096) as usize);
+    for _ in 0..len {
+        vec.push(f(rd)?);
+    }
+
+    Ok(vec)
+}
+
+/// Attempts to read a MessagePack array from the given reader, where every element is of type `T`.
+///
+/// # Examples
+///
+/// ```
+/// use rmp::decode::read_array_of;
+///
+/// let buf = [0x93, 0x01, 0xcd, 0x01, 0x2c, 0x03];
+///
+/// assert_eq!(vec![1, 300, 3], read_array_of::<u32, _>(&mut &buf[..]).unwrap());
+/// ```
+pub fn read_array_of<T, R>(rd: &mut R) -> Result<Vec<T>, TypedReadError>
+    where T: Readable,
+          R: Read
+{
+    read_array_with(rd, T::read_from)
+}
+
+/// Attempts to read a MessagePack map from the given reader, calling `f` to read each of its
+/// entries.
+///
+/// The map header and the entry count are handled by this function, so `f` must read exactly one
+/// key and one value each time it is called.
+///
+/// # Examples
+///
+/// ```
+/// use rmp::decode::{read_map_with, read_int, Readable, TypedReadError};
+///
+/// // {"a": 1, "b": 2}
+/// let buf = [0x82, 0xa1, 0x61, 0x01, 0xa1, 0x62, 0x02];
+///
+/// let map: Result<Vec<(String, u8)>, TypedReadError> = read_map_with(&mut &buf[..], |rd| {
+///     let key = String::read_from(rd)?;
+///     let val = read_int(rd)?;
+///     Ok((key, val))
+/// });
+///
+/// assert_eq!(vec![("a".to_string(), 1), ("b".to_string(), 2)], map.unwrap());
+/// ```
+pub fn read_map_with<R, T, E, F>(rd: &mut R, mut f: F) -> Result<Vec<T>, E>
+    where R: Read,
+          E: From<ValueReadError>,
+          F: FnMut(&mut R) -> Result<T, E>
+{
+    let len = read_map_len(rd)?;
+
+    let mut vec = Vec::with_capacity(::std::cmp::min(len, 4096) as usize);
+    for _ in 0..len {
+        vec.push(f(rd)?);
+    }
+
+    Ok(vec)
+}
+
+/// Attempts to read a MessagePack map from the given reader, where every key is of type `K` and
+/// every value is of type `V`.
+///
+/// Entries are returned in the order they appear in the stream.
+///
+/// # Examples
+///
+/// ```
+/// use rmp::decode::read_map_of;
+///
+/// // {1: true, 2: false}
+/// let buf = [0x82, 0x01, 0xc3, 0x02, 0xc2];
+///
+/// assert_eq!(vec![(1, true), (2, false)], read_map_of::<u8, bool, _>(&mut &buf[..]).unwrap());
+/// ```
+pub fn read_map_of<K, V, R>(rd: &mut R) -> Result<Vec<(K, V)>, TypedReadError>
+    where K: Readable,
+          V: Readable,
+          R: Read
+{
+    read_map_with(rd, |rd| {
+        let key = K::read_from(rd)?;
+        let val = V::read_from(rd)?;
+        Ok((key, val))
+    })
+}
@@ -0,0 +1,192 @@
+use std::io::{self, ErrorKind};
+
+use Marker;
+use decode::{read_marker, read_data_u8, read_data_u16, read_data_u32, ValueReadError};
+
+/// The largest string, binary or extension payload found by `describe`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Blob {
+    /// The marker of the value holding the payload.
+    pub marker: Marker,
+    /// Position of the marker in the buffer.
+    pub offset: usize,
+    /// Length of the payload in bytes, excluding the header and the extension type.
+    pub len: usize,
+}
+
+/// A summary of an encoded value, as returned by `describe`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Summary {
+    /// The marker of the top-level value, which tells its type.
+    pub marker: Marker,
+    /// The number of bytes the top-level value occupies, including all nested values.
+    pub size: usize,
+    /// For each nesting level, starting with the top-level value, the total number of elements
+    /// of the arrays and entries of the maps at that level. Ends with the last level holding a
+    /// container or at the depth limit, so it is empty for scalars.
+    pub counts: Vec<u64>,
+    /// The largest string, binary or extension payload at any depth, if there is one.
+    pub largest_blob: Option<Blob>,
+}
+
+/// The default number of levels `describe` counts elements for.
+const DEFAULT_DEPTH: usize = 8;
+
+/// Summarizes the first MessagePack value encoded in the given buffer without decoding it.
+///
+/// Only headers are inspected: payloads are skipped, nested values are walked iteratively and
+/// nothing is allocated except for the element counts. This makes it cheap enough to route,
+/// measure or log opaque frames.
+///
+/// Element counts are reported for up to 8 levels, see `describe_to_depth` to change that.
+///
+/// # Errors
+///
+/// Returns `ValueReadError` if the buffer is truncated before the end of the value.
+///
+/// # Examples
+///
+/// ```
+/// use rmp::Marker;
+///
+/// // {"a": [1, 2, 3], "b": [4], "c": "hello"}
+/// let buf = [0x83, 0xa1, 0x61, 0x93, 0x01, 0x02, 0x03, 0xa1, 0x62, 0x91, 0x04,
+///            0xa1, 0x63, 0xa5, 0x68, 0x65, 0x6c, 0x6c, 0x6f];
+///
+/// let summary = rmp::describe(&buf).unwrap();
+///
+/// assert_eq!(Marker::FixMap(3), summary.marker);
+/// assert_eq!(buf.len(), summary.size);
+/// assert_eq!(vec![3, 4], summary.counts);
+///
+/// let blob = summary.largest_blob.unwrap();
+/// assert_eq!((13, 5), (blob.offset, blob.len));
+/// ```
+pub fn describe(buf: &[u8]) -> Result<Summary, ValueReadError> {
+    describe_to_depth(buf, DEFAULT_DEPTH)
+}
+
+/// Summarizes the first MessagePack value encoded in the given buffer like `describe` does, but
+/// reports element counts for up to `depth` levels.
+///
+/// The whole value is walked regardless of the depth, so that the size and the largest blob are
+/// always exact.
+pub fn describe_to_depth(buf: &[u8], depth: usize) -> Result<Summary, ValueReadError> {
+    let mut rd = buf;
+    let mut summary = None::<Summary>;
+
+    // Number of values left in each open container.
+    let mut stack: Vec<u64> = Vec::new();
+    let mut pending = 1u64;
+
+    loop {
+        while pending == 0 {
+            match stack.pop() {
+                Some(remaining) => pending = remaining,
+                None => {
+                    let mut summary = summary.expect("at least one value must be read");
+                    summary.size = buf.len() - rd.len();
+                    return Ok(summary);
+                }
+            }
+        }
+        pending -= 1;
+
+        let offset = buf.len() - rd.len();
+        let marker = read_marker(&mut rd)?;
+        let summary = summary.get_or_insert_with(|| {
+            Summary {
+                marker,
+                size: 0,
+                counts: Vec::new(),
+                largest_blob: None,
+            }
+        });
+
+        let (len, children) = match marker {
+            Marker::FixPos(..) | Marker::FixNeg(..) | Marker::Null | Marker::True |
+            Marker::False | Marker::Reserved => (0, None),
+            Marker::U8 | Marker::I8 => (1, None),
+            Marker::U16 | Marker::I16 => (2, None),
+            Marker::U32 | Marker::I32 | Marker::F32 => (4, None),
+            Marker::U64 | Marker::I64 | Marker::F64 => (8, None),
+            Marker::FixStr(len) => (len as usize, None),
+            Marker::Str8 | Marker::Bin8 => (read_data_u8(&mut rd)? as usize, None),
+            Marker::Str16 | Marker::Bin16 => (read_data_u16(&mut rd)? as usize, None),
+            Marker::Str32 | Marker::Bin32 => (read_data_u32(&mut rd)? as usize, None),
+            Marker::FixArray(len) => (0, Some((len as u64, len as u64))),
+            Marker::Array16 => {
+                let len = read_data_u16(&mut rd)? as u64;
+                (0, Some((len, len)))
+            }
+            Marker::Array32 => {
+                let len = read_data_u32(&mut rd)? as u64;
+                (0, Some((len, len)))
+            }
+            Marker::FixMap(len) => (0, Some((len as u64, 2 * len as u64))),
+            Marker::Map16 => {
+                let len = read_data_u16(&mut rd)? as u64;
+                (0, Some((len, 2 * len)))
+            }
+            Marker::Map32 => {
+                let len = read_data_u32(&mut rd)? as u64;
+                (0, Some((len, 2 * len)))
+            }
+            Marker::FixExt1 => (1, None),
+            Marker::FixExt2 => (2, None),
+            Marker::FixExt4 => (4, None),
+            Marker::FixExt8 => (8, None),
+            Marker::FixExt16 => (16, None),
+            Marker::Ext8 => (read_data_u8(&mut rd)? as usize, None),
+            Marker::Ext16 => (read_data_u16(&mut rd)? as usize, None),
+            Marker::Ext32 => (read_data_u32(&mut rd)? as usize, None),
+        };
+
+        if let Some((count, children)) = children {
+            let level = stack.len();
+            if level < depth {
+                if summary.counts.len() <= level {
+                    summary.counts.resize(level + 1, 0);
+                }
+                summary.counts[level] += count;
+            }
+
+            stack.push(pending);
+            pending = children;
+            continue;
+        }
+
+        let blob = match marker {
+            Marker::FixStr(..) | Marker::Str8 | Marker::Str16 | Marker::Str32 | Marker::Bin8 |
+            Marker::Bin16 | Marker::Bin32 => true,
+            Marker::FixExt1 | Marker::FixExt2 | Marker::FixExt4 | Marker::FixExt8 |
+            Marker::FixExt16 | Marker::Ext8 | Marker::Ext16 | Marker::Ext32 => {
+                // Skip the extension type.
+                skip(&mut rd, 1)?;
+                true
+            }
+            _ => false,
+        };
+
+        let larger = match summary.largest_blob {
+            Some(largest) => largest.len < len,
+            None => true,
+        };
+        if blob && larger {
+            summary.largest_blob = Some(Blob { marker, offset, len });
+        }
+
+        skip(&mut rd, len)?;
+    }
+}
+
+fn skip(rd: &mut &[u8], len: usize) -> Result<(), ValueReadError> {
+    if rd.len() < len {
+        let err = io::Error::from(ErrorKind::UnexpectedEof);
+        return Err(ValueReadError::InvalidDataRead(err));
+    }
+
+    *rd = &rd[len..];
+
+    Ok(())
+}
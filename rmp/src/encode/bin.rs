@@ -1,8 +1,6 @@
-use std::io::Write;
-
 use Marker;
-use encode::{write_marker, ValueWriteError};
-use super::{write_data_u8, write_data_u16, write_data_u32};
+use encode::{RmpWrite, ValueWriteError};
+use super::write_marker_data;
 
 /// Encodes and attempts to write the most efficient binary array length implementation to the given
 /// write, returning the marker used.
@@ -14,18 +12,17 @@ use super::{write_data_u8, write_data_u16, write_data_u32};
 ///
 /// This function will return `ValueWriteError` on any I/O error occurred while writing either the
 /// marker or the data.
-pub fn write_bin_len<W: Write>(wr: &mut W, len: u32) -> Result<Marker, ValueWriteError> {
+pub fn write_bin_len<W: RmpWrite>(wr: &mut W, len: u32) ->
+    Result<Marker, ValueWriteError<W::Error>>
+{
     if len < 256 {
-        try!(write_marker(wr, Marker::Bin8));
-        try!(write_data_u8(wr, len as u8));
+        try!(write_marker_data(wr, Marker::Bin8, &[len as u8]));
         Ok(Marker::Bin8)
     } else if len < 65536 {
-        try!(write_marker(wr, Marker::Bin16));
-        try!(write_data_u16(wr, len as u16));
+        try!(write_marker_data(wr, Marker::Bin16, &(len as u16).to_be_bytes()));
         Ok(Marker::Bin16)
     } else {
-        try!(write_marker(wr, Marker::Bin32));
-        try!(write_data_u32(wr, len));
+        try!(write_marker_data(wr, Marker::Bin32, &len.to_be_bytes()));
         Ok(Marker::Bin32)
     }
 }
@@ -37,7 +34,7 @@ pub fn write_bin_len<W: Write>(wr: &mut W, len: u32) -> Result<Marker, ValueWrit
 /// This function will return `ValueWriteError` on any I/O error occurred while writing either the
 /// marker or the data.
 // TODO: Docs, range check, example, visibility.
-pub fn write_bin<W: Write>(wr: &mut W, data: &[u8]) -> Result<(), ValueWriteError> {
+pub fn write_bin<W: RmpWrite>(wr: &mut W, data: &[u8]) -> Result<(), ValueWriteError<W::Error>> {
     try!(write_bin_len(wr, data.len() as u32));
-    wr.write_all(data).map_err(ValueWriteError::InvalidDataWrite)
+    wr.write_bytes(data).map_err(ValueWriteError::InvalidDataWrite)
 }
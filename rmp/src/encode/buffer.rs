@@ -0,0 +1,212 @@
+use core::cmp;
+use core::fmt::{self, Display, Formatter};
+#[cfg(feature = "std")]
+use std::error;
+#[cfg(feature = "std")]
+use std::io;
+
+use encode;
+#[cfg(not(feature = "std"))]
+use encode::RmpWrite;
+
+/// An error returned by the `try_write_*` functions when the buffer can't hold the encoded value.
+///
+/// Unlike the generic capacity error of writers, it tells the exact number of bytes the value
+/// needs, so the caller can grow the buffer once and retry.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BufferTooSmall {
+    needed: usize,
+}
+
+impl BufferTooSmall {
+    /// Returns the number of bytes the encoded value occupies, i.e. the minimum buffer size for
+    /// the write to succeed.
+    pub fn needed(&self) -> usize {
+        self.needed
+    }
+}
+
+impl Display for BufferTooSmall {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        write!(f, "buffer too small, {} bytes needed", self.needed)
+    }
+}
+
+#[cfg(feature = "std")]
+impl error::Error for BufferTooSmall {
+    fn description(&self) -> &str {
+        "buffer too small"
+    }
+}
+
+/// A writer copying into a slice as far as it fits and counting all the bytes that were meant to
+/// be written, instead of failing when the slice is full.
+///
+/// This allows to encode into a buffer of a guessed size and learn the size actually needed in
+/// one pass. With an empty slice it only counts, which measures encoded values without keeping
+/// them.
+///
+/// # Examples
+///
+/// ```
+/// use rmp::encode::{self, Counted};
+///
+/// let mut buf = [0; 4];
+/// let mut wr = Counted::new(&mut buf);
+/// encode::write_str(&mut wr, "le message").unwrap();
+/// assert_eq!(11, wr.count());
+/// assert!(!wr.fits());
+/// assert_eq!([0xaa, b'l', b'e', b' '], buf);
+/// ```
+pub struct Counted<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> Counted<'a> {
+    /// Constructs a writer filling the given buffer from its beginning.
+    pub fn new(buf: &'a mut [u8]) -> Counted<'a> {
+        Counted { buf, len: 0 }
+    }
+
+    /// Returns the number of bytes written so far, including those that didn't fit.
+    pub fn count(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if everything written so far fits into the buffer.
+    pub fn fits(&self) -> bool {
+        self.len <= self.buf.len()
+    }
+
+    fn append(&mut self, data: &[u8]) {
+        if self.len < self.buf.len() {
+            let end = cmp::min(self.buf.len(), self.len + data.len());
+            self.buf[self.len..end].copy_from_slice(&data[..end - self.len]);
+        }
+        self.len += data.len();
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'a> io::Write for Counted<'a> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.append(data);
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl<'a> RmpWrite for Counted<'a> {
+    type Error = encode::Error;
+
+    fn write_bytes(&mut self, data: &[u8]) -> Result<(), Self::Error> {
+        self.append(data);
+        Ok(())
+    }
+}
+
+/// Runs the encoding function against the buffer, returning the number of bytes written or
+/// needed.
+fn try_write<F, T, E>(buf: &mut [u8], f: F) -> Result<usize, BufferTooSmall>
+    where F: FnOnce(&mut Counted) -> Result<T, E>
+{
+    let mut wr = Counted::new(buf);
+    match f(&mut wr) {
+        Ok(..) => {}
+        Err(..) => unreachable!(),
+    }
+
+    if wr.fits() {
+        Ok(wr.count())
+    } else {
+        Err(BufferTooSmall { needed: wr.count() })
+    }
+}
+
+/// Encodes a nil value into the beginning of the given buffer, returning the number of bytes
+/// written.
+///
+/// # Errors
+///
+/// Returns `BufferTooSmall` with the number of bytes needed if the value doesn't fit. The buffer
+/// contents are unspecified in that case.
+pub fn try_write_nil(buf: &mut [u8]) -> Result<usize, BufferTooSmall> {
+    try_write(buf, |wr| encode::write_nil(wr))
+}
+
+/// Encodes a bool value into the beginning of the given buffer like `try_write_nil` does.
+pub fn try_write_bool(buf: &mut [u8], val: bool) -> Result<usize, BufferTooSmall> {
+    try_write(buf, |wr| encode::write_bool(wr, val))
+}
+
+/// Encodes an unsigned integer using the most compact representation into the beginning of the
+/// given buffer like `try_write_nil` does.
+pub fn try_write_uint(buf: &mut [u8], val: u64) -> Result<usize, BufferTooSmall> {
+    try_write(buf, |wr| encode::write_uint(wr, val))
+}
+
+/// Encodes a signed integer using the most compact representation into the beginning of the
+/// given buffer like `try_write_nil` does.
+pub fn try_write_sint(buf: &mut [u8], val: i64) -> Result<usize, BufferTooSmall> {
+    try_write(buf, |wr| encode::write_sint(wr, val))
+}
+
+/// Encodes a 32-bit float into the beginning of the given buffer like `try_write_nil` does.
+pub fn try_write_f32(buf: &mut [u8], val: f32) -> Result<usize, BufferTooSmall> {
+    try_write(buf, |wr| encode::write_f32(wr, val))
+}
+
+/// Encodes a 64-bit float into the beginning of the given buffer like `try_write_nil` does.
+pub fn try_write_f64(buf: &mut [u8], val: f64) -> Result<usize, BufferTooSmall> {
+    try_write(buf, |wr| encode::write_f64(wr, val))
+}
+
+/// Encodes a string, both its header and data, into the beginning of the given buffer like
+/// `try_write_nil` does.
+///
+/// # Examples
+///
+/// ```
+/// use rmp::encode::try_write_str;
+///
+/// let mut buf = vec![0; 4];
+/// let needed = match try_write_str(&mut buf, "le message") {
+///     Ok(..) => unreachable!(),
+///     Err(err) => err.needed(),
+/// };
+/// assert_eq!(11, needed);
+///
+/// buf.resize(needed, 0);
+/// assert_eq!(Ok(11), try_write_str(&mut buf, "le message"));
+/// assert_eq!(0xaa, buf[0]);
+/// ```
+pub fn try_write_str(buf: &mut [u8], data: &str) -> Result<usize, BufferTooSmall> {
+    try_write(buf, |wr| encode::write_str(wr, data))
+}
+
+/// Encodes a binary blob, both its header and data, into the beginning of the given buffer like
+/// `try_write_nil` does.
+pub fn try_write_bin(buf: &mut [u8], data: &[u8]) -> Result<usize, BufferTooSmall> {
+    try_write(buf, |wr| encode::write_bin(wr, data))
+}
+
+/// Encodes an array header into the beginning of the given buffer like `try_write_nil` does.
+pub fn try_write_array_len(buf: &mut [u8], len: u32) -> Result<usize, BufferTooSmall> {
+    try_write(buf, |wr| encode::write_array_len(wr, len))
+}
+
+/// Encodes a map header into the beginning of the given buffer like `try_write_nil` does.
+pub fn try_write_map_len(buf: &mut [u8], len: u32) -> Result<usize, BufferTooSmall> {
+    try_write(buf, |wr| encode::write_map_len(wr, len))
+}
+
+/// Encodes an extension header, including its type, into the beginning of the given buffer like
+/// `try_write_nil` does.
+pub fn try_write_ext_meta(buf: &mut [u8], len: u32, ty: i8) -> Result<usize, BufferTooSmall> {
+    try_write(buf, |wr| encode::write_ext_meta(wr, len, ty))
+}
@@ -1,8 +1,6 @@
-use std::io::Write;
-
 use Marker;
-use encode::ValueWriteError;
-use super::{write_marker, write_data_f32, write_data_f64};
+use encode::{RmpWrite, ValueWriteError};
+use super::write_marker_data;
 
 /// Encodes and attempts to write an `f32` value as a 5-byte sequence into the given write.
 ///
@@ -12,9 +10,8 @@ use super::{write_marker, write_data_f32, write_data_f64};
 ///
 /// This function will return `ValueWriteError` on any I/O error occurred while writing either the
 /// marker or the data.
-pub fn write_f32<W: Write>(wr: &mut W, val: f32) -> Result<(), ValueWriteError> {
-    try!(write_marker(wr, Marker::F32));
-    try!(write_data_f32(wr, val));
+pub fn write_f32<W: RmpWrite>(wr: &mut W, val: f32) -> Result<(), ValueWriteError<W::Error>> {
+    try!(write_marker_data(wr, Marker::F32, &val.to_be_bytes()));
     Ok(())
 }
 
@@ -26,8 +23,7 @@ pub fn write_f32<W: Write>(wr: &mut W, val: f32) -> Result<(), ValueWriteError>
 ///
 /// This function will return `ValueWriteError` on any I/O error occurred while writing either the
 /// marker or the data.
-pub fn write_f64<W: Write>(wr: &mut W, val: f64) -> Result<(), ValueWriteError> {
-    try!(write_marker(wr, Marker::F64));
-    try!(write_data_f64(wr, val));
+pub fn write_f64<W: RmpWrite>(wr: &mut W, val: f64) -> Result<(), ValueWriteError<W::Error>> {
+    try!(write_marker_data(wr, Marker::F64, &val.to_be_bytes()));
     Ok(())
 }
@@ -8,61 +8,228 @@ mod bin;
 mod vec;
 mod map;
 mod ext;
+mod buffer;
 
 pub use self::sint::{write_nfix, write_i8, write_i16, write_i32, write_i64, write_sint};
 pub use self::uint::{write_pfix, write_u8, write_u16, write_u32, write_u64, write_uint};
 pub use self::dec::{write_f32, write_f64};
 pub use self::str::{write_str_len, write_str};
 pub use self::bin::{write_bin_len, write_bin};
+pub use self::buffer::{try_write_nil, try_write_bool, try_write_uint, try_write_sint, try_write_f32,
+                       try_write_f64, try_write_str, try_write_bin, try_write_array_len,
+                       try_write_map_len, try_write_ext_meta, BufferTooSmall, Counted};
 
+#[cfg(feature = "std")]
 use std::error;
-use std::fmt::{self, Display, Formatter};
-use std::io::Write;
-
-use byteorder::{self, WriteBytesExt};
+use core::fmt::{self, Display, Formatter};
 
 use Marker;
 
 /// The error type for I/O operations of the `Write` and associated traits.
+///
+/// This is `std::io::Error` when the `std` feature is enabled and `FixedBufCapacityOverflow`
+/// otherwise, i.e. the error of writing into fixed-size byte buffers in both cases.
+#[cfg(feature = "std")]
 pub type Error = ::std::io::Error;
 
-// An error returned from the `write_marker` and `write_fixval` functions.
-struct MarkerWriteError(Error);
+/// The error type for writing into fixed-size byte buffers, which are the only fallible writers
+/// available without the `std` feature.
+#[cfg(not(feature = "std"))]
+pub type Error = FixedBufCapacityOverflow;
 
-impl From<Error> for MarkerWriteError {
-    fn from(err: Error) -> MarkerWriteError {
-        MarkerWriteError(err)
+/// The error type of an `RmpWrite` implementation.
+///
+/// Any `std::error::Error` qualifies when the `std` feature is enabled.
+#[cfg(feature = "std")]
+pub trait RmpWriteErr: error::Error + 'static {}
+
+#[cfg(feature = "std")]
+impl<T: error::Error + 'static> RmpWriteErr for T {}
+
+/// The error type of an `RmpWrite` implementation.
+///
+/// Without the `std` feature any type implementing both `Display` and `Debug` qualifies.
+#[cfg(not(feature = "std"))]
+pub trait RmpWriteErr: Display + fmt::Debug + 'static {}
+
+#[cfg(not(feature = "std"))]
+impl<T: Display + fmt::Debug + 'static> RmpWriteErr for T {}
+
+/// An error returned when writing into a fixed-size byte buffer, which has no space left.
+///
+/// Only used without the `std` feature, with it writing into `&mut [u8]` fails with
+/// `std::io::ErrorKind::WriteZero` instead.
+#[cfg(not(feature = "std"))]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FixedBufCapacityOverflow;
+
+#[cfg(not(feature = "std"))]
+impl Display for FixedBufCapacityOverflow {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        f.write_str("no space left in the fixed-size buffer")
     }
 }
 
-impl From<MarkerWriteError> for Error {
-    fn from(err: MarkerWriteError) -> Error {
-        match err {
-            MarkerWriteError(err) => err
+/// A sink of bytes the encoding functions can write into.
+///
+/// With the `std` feature enabled it is implemented for every `std::io::Write`, having
+/// `std::io::Error` as its error type. Without it, fixed-size byte buffers (`&mut [u8]`) are
+/// supported, which fail with the compact `FixedBufCapacityOverflow` when full, and so is
+/// `Vec<u8>` if the `alloc` feature is enabled.
+///
+/// Other sinks can implement this trait themselves, see `RmpRead` for the details.
+///
+/// # Examples
+///
+/// ```
+/// use std::error::Error;
+/// use std::fmt::{self, Display, Formatter};
+///
+/// use rmp::encode::{self, RmpWrite};
+///
+/// #[derive(Debug)]
+/// struct PageFull;
+///
+/// impl Display for PageFull {
+///     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+///         f.write_str("flash page is full")
+///     }
+/// }
+///
+/// impl Error for PageFull {}
+///
+/// /// A flash page, which can be programmed up to its size.
+/// struct Page {
+///     data: Vec<u8>,
+///     size: usize,
+/// }
+///
+/// impl RmpWrite for Page {
+///     type Error = PageFull;
+///
+///     fn write_bytes(&mut self, buf: &[u8]) -> Result<(), PageFull> {
+///         if self.data.len() + buf.len() > self.size {
+///             return Err(PageFull);
+///         }
+///
+///         self.data.extend_from_slice(buf);
+///         Ok(())
+///     }
+/// }
+///
+/// let mut page = Page { data: Vec::new(), size: 4 };
+///
+/// encode::write_u16(&mut page, 300).unwrap();
+/// assert_eq!(vec![0xcd, 0x01, 0x2c], page.data);
+/// assert!(encode::write_u16(&mut page, 300).is_err());
+/// ```
+pub trait RmpWrite {
+    /// The error type returned when the writing fails.
+    type Error: RmpWriteErr;
+
+    /// Writes a single byte.
+    fn write_u8(&mut self, val: u8) -> Result<(), Self::Error> {
+        self.write_bytes(&[val])
+    }
+
+    /// Writes all the given bytes, failing if not all of them could be written.
+    fn write_bytes(&mut self, buf: &[u8]) -> Result<(), Self::Error>;
+
+    /// Writes all the given bytes like `write_bytes`, also returning how many of them were
+    /// written if it fails.
+    ///
+    /// The encoding functions use the count to tell a failure to write the marker of a value
+    /// from a failure to write its data. The default implementation assumes that a failed
+    /// `write_bytes` wrote nothing.
+    fn write_bytes_counted(&mut self, buf: &[u8]) -> Result<(), (usize, Self::Error)> {
+        self.write_bytes(buf).map_err(|err| (0, err))
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: ::std::io::Write + ?Sized> RmpWrite for T {
+    type Error = ::std::io::Error;
+
+    fn write_bytes(&mut self, buf: &[u8]) -> Result<(), Self::Error> {
+        self.write_all(buf)
+    }
+
+    fn write_bytes_counted(&mut self, buf: &[u8]) -> Result<(), (usize, Self::Error)> {
+        use std::io::{Error, ErrorKind};
+
+        let mut written = 0;
+        while written < buf.len() {
+            match self.write(&buf[written..]) {
+                Ok(0) => {
+                    let err = Error::new(ErrorKind::WriteZero, "failed to write whole buffer");
+                    return Err((written, err));
+                }
+                Ok(n) => written += n,
+                Err(ref err) if err.kind() == ErrorKind::Interrupted => {}
+                Err(err) => return Err((written, err)),
+            }
         }
+
+        Ok(())
     }
 }
 
-/// Attempts to write the given marker into the writer.
-fn write_marker<W: Write>(wr: &mut W, marker: Marker) -> Result<(), MarkerWriteError> {
-    wr.write_u8(marker.to_u8()).map_err(MarkerWriteError)
+#[cfg(not(feature = "std"))]
+impl<'a, W: RmpWrite + ?Sized> RmpWrite for &'a mut W {
+    type Error = W::Error;
+
+    fn write_bytes(&mut self, buf: &[u8]) -> Result<(), Self::Error> {
+        (**self).write_bytes(buf)
+    }
+
+    fn write_bytes_counted(&mut self, buf: &[u8]) -> Result<(), (usize, Self::Error)> {
+        (**self).write_bytes_counted(buf)
+    }
 }
 
-/// An error returned from primitive values write functions.
-struct DataWriteError(Error);
+#[cfg(not(feature = "std"))]
+impl<'a> RmpWrite for &'a mut [u8] {
+    type Error = FixedBufCapacityOverflow;
 
-impl From<Error> for DataWriteError {
-    fn from(err: Error) -> DataWriteError {
-        DataWriteError(err)
+    fn write_bytes(&mut self, buf: &[u8]) -> Result<(), Self::Error> {
+        if buf.len() > self.len() {
+            return Err(FixedBufCapacityOverflow);
+        }
+
+        let (head, tail) = ::core::mem::replace(self, &mut []).split_at_mut(buf.len());
+        head.copy_from_slice(buf);
+        *self = tail;
+
+        Ok(())
+    }
+}
+
+/// Growable buffers never run out of space, so writing into them always succeeds. The error type
+/// matches the one of fixed-size buffers to keep `Error` the same for all built-in writers.
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+impl RmpWrite for ::alloc::vec::Vec<u8> {
+    type Error = FixedBufCapacityOverflow;
+
+    fn write_bytes(&mut self, buf: &[u8]) -> Result<(), Self::Error> {
+        self.extend_from_slice(buf);
+        Ok(())
     }
 }
 
-impl From<DataWriteError> for Error {
-    fn from(err: DataWriteError) -> Error {
-        err.0
+// An error returned from the `write_marker` and `write_fixval` functions.
+struct MarkerWriteError<E: RmpWriteErr>(E);
+
+impl<E: RmpWriteErr> From<E> for MarkerWriteError<E> {
+    fn from(err: E) -> MarkerWriteError<E> {
+        MarkerWriteError(err)
     }
 }
 
+/// Attempts to write the given marker into the writer.
+fn write_marker<W: RmpWrite>(wr: &mut W, marker: Marker) -> Result<(), MarkerWriteError<W::Error>> {
+    wr.write_u8(marker.to_u8()).map_err(MarkerWriteError)
+}
+
 /// Encodes and attempts to write a nil value into the given write.
 ///
 /// According to the MessagePack specification, a nil value is represented as a single `0xc0` byte.
@@ -80,8 +247,8 @@ impl From<DataWriteError> for Error {
 ///
 /// assert_eq!(vec![0xc0], buf);
 /// ```
-pub fn write_nil<W: Write>(wr: &mut W) -> Result<(), Error> {
-    write_marker(wr, Marker::Null).map_err(From::from)
+pub fn write_nil<W: RmpWrite>(wr: &mut W) -> Result<(), W::Error> {
+    write_marker(wr, Marker::Null).map_err(|err| err.0)
 }
 
 /// Encodes and attempts to write a bool value into the given write.
@@ -93,81 +260,55 @@ pub fn write_nil<W: Write>(wr: &mut W) -> Result<(), Error> {
 ///
 /// Each call to this function may generate an I/O error indicating that the operation could not be
 /// completed.
-pub fn write_bool<W: Write>(wr: &mut W, val: bool) -> Result<(), Error> {
+pub fn write_bool<W: RmpWrite>(wr: &mut W, val: bool) -> Result<(), W::Error> {
     let marker = if val {
         Marker::True
     } else {
         Marker::False
     };
 
-    write_marker(wr, marker).map_err(From::from)
-}
-
-fn write_data_u8<W: Write>(wr: &mut W, val: u8) -> Result<(), DataWriteError> {
-    wr.write_u8(val).map_err(DataWriteError)
-}
-
-fn write_data_u16<W: Write>(wr: &mut W, val: u16) -> Result<(), DataWriteError> {
-    wr.write_u16::<byteorder::BigEndian>(val).map_err(DataWriteError)
+    write_marker(wr, marker).map_err(|err| err.0)
 }
 
-fn write_data_u32<W: Write>(wr: &mut W, val: u32) -> Result<(), DataWriteError> {
-    wr.write_u32::<byteorder::BigEndian>(val).map_err(DataWriteError)
-}
-
-fn write_data_u64<W: Write>(wr: &mut W, val: u64) -> Result<(), DataWriteError> {
-    wr.write_u64::<byteorder::BigEndian>(val).map_err(DataWriteError)
-}
-
-fn write_data_i8<W: Write>(wr: &mut W, val: i8) -> Result<(), DataWriteError> {
-    wr.write_i8(val).map_err(DataWriteError)
-}
-
-fn write_data_i16<W: Write>(wr: &mut W, val: i16) -> Result<(), DataWriteError> {
-    wr.write_i16::<byteorder::BigEndian>(val).map_err(DataWriteError)
-}
-
-fn write_data_i32<W: Write>(wr: &mut W, val: i32) -> Result<(), DataWriteError> {
-    wr.write_i32::<byteorder::BigEndian>(val).map_err(DataWriteError)
-}
-
-fn write_data_i64<W: Write>(wr: &mut W, val: i64) -> Result<(), DataWriteError> {
-    wr.write_i64::<byteorder::BigEndian>(val).map_err(DataWriteError)
-}
-
-fn write_data_f32<W: Write>(wr: &mut W, val: f32) -> Result<(), DataWriteError> {
-    wr.write_f32::<byteorder::BigEndian>(val).map_err(DataWriteError)
-}
-
-fn write_data_f64<W: Write>(wr: &mut W, val: f64) -> Result<(), DataWriteError> {
-    wr.write_f64::<byteorder::BigEndian>(val).map_err(DataWriteError)
+/// Writes the marker followed by its data in a single call to the writer.
+///
+/// Scalars and headers are assembled on the stack first, so writers with a per-call cost, like
+/// buffered or length-checked ones, are entered once per value rather than once per part of it.
+/// A failure is reported as a marker error if nothing was written, and as a data error once the
+/// marker was.
+fn write_marker_data<W: RmpWrite>(wr: &mut W, marker: Marker, data: &[u8]) ->
+    Result<(), ValueWriteError<W::Error>>
+{
+    let mut buf = [0; 9];
+    buf[0] = marker.to_u8();
+    buf[1..=data.len()].copy_from_slice(data);
+    wr.write_bytes_counted(&buf[..=data.len()]).map_err(|(written, err)| {
+        if written == 0 {
+            ValueWriteError::InvalidMarkerWrite(err)
+        } else {
+            ValueWriteError::InvalidDataWrite(err)
+        }
+    })
 }
 
 /// An error that can occur when attempting to write multi-byte MessagePack value.
 #[derive(Debug)]
-pub enum ValueWriteError {
+pub enum ValueWriteError<E: RmpWriteErr = Error> {
     /// I/O error while writing marker.
-    InvalidMarkerWrite(Error),
+    InvalidMarkerWrite(E),
     /// I/O error while writing data.
-    InvalidDataWrite(Error),
+    InvalidDataWrite(E),
 }
 
-impl From<MarkerWriteError> for ValueWriteError {
-    fn from(err: MarkerWriteError) -> ValueWriteError {
+impl<E: RmpWriteErr> From<MarkerWriteError<E>> for ValueWriteError<E> {
+    fn from(err: MarkerWriteError<E>) -> ValueWriteError<E> {
         match err {
             MarkerWriteError(err) => ValueWriteError::InvalidMarkerWrite(err),
         }
     }
 }
 
-impl From<DataWriteError> for ValueWriteError {
-    fn from(err: DataWriteError) -> ValueWriteError {
-        match err {
-            DataWriteError(err) => ValueWriteError::InvalidDataWrite(err),
-        }
-    }
-}
-
+#[cfg(feature = "std")]
 impl From<ValueWriteError> for Error {
     fn from(err: ValueWriteError) -> Error {
         match err {
@@ -177,7 +318,8 @@ impl From<ValueWriteError> for Error {
     }
 }
 
-impl error::Error for ValueWriteError {
+#[cfg(feature = "std")]
+impl<E: RmpWriteErr> error::Error for ValueWriteError<E> {
     fn description(&self) -> &str {
         "error while writing multi-byte MessagePack value"
     }
@@ -190,9 +332,9 @@ impl error::Error for ValueWriteError {
     }
 }
 
-impl Display for ValueWriteError {
+impl<E: RmpWriteErr> Display for ValueWriteError<E> {
     fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
-        error::Error::description(self).fmt(f)
+        f.write_str("error while writing multi-byte MessagePack value")
     }
 }
 
@@ -203,17 +345,17 @@ impl Display for ValueWriteError {
 ///
 /// This function will return `ValueWriteError` on any I/O error occurred while writing either the
 /// marker or the data.
-pub fn write_array_len<W: Write>(wr: &mut W, len: u32) -> Result<Marker, ValueWriteError> {
+pub fn write_array_len<W: RmpWrite>(wr: &mut W, len: u32) ->
+    Result<Marker, ValueWriteError<W::Error>>
+{
     let marker = if len < 16 {
         try!(write_marker(wr, Marker::FixArray(len as u8)));
         Marker::FixArray(len as u8)
     } else if len < 65536 {
-        try!(write_marker(wr, Marker::Array16));
-        try!(write_data_u16(wr, len as u16));
+        try!(write_marker_data(wr, Marker::Array16, &(len as u16).to_be_bytes()));
         Marker::Array16
     } else {
-        try!(write_marker(wr, Marker::Array32));
-        try!(write_data_u32(wr, len));
+        try!(write_marker_data(wr, Marker::Array32, &len.to_be_bytes()));
         Marker::Array32
     };
 
@@ -227,17 +369,17 @@ pub fn write_array_len<W: Write>(wr: &mut W, len: u32) -> Result<Marker, ValueWr
 ///
 /// This function will return `ValueWriteError` on any I/O error occurred while writing either the
 /// marker or the data.
-pub fn write_map_len<W: Write>(wr: &mut W, len: u32) -> Result<Marker, ValueWriteError> {
+pub fn write_map_len<W: RmpWrite>(wr: &mut W, len: u32) ->
+    Result<Marker, ValueWriteError<W::Error>>
+{
     let marker = if len < 16 {
         try!(write_marker(wr, Marker::FixMap(len as u8)));
         Marker::FixMap(len as u8)
     } else if len < 65536 {
-        try!(write_marker(wr, Marker::Map16));
-        try!(write_data_u16(wr, len as u16));
+        try!(write_marker_data(wr, Marker::Map16, &(len as u16).to_be_bytes()));
         Marker::Map16
     } else {
-        try!(write_marker(wr, Marker::Map32));
-        try!(write_data_u32(wr, len));
+        try!(write_marker_data(wr, Marker::Map32, &len.to_be_bytes()));
         Marker::Map32
     };
 
@@ -252,52 +394,37 @@ pub fn write_map_len<W: Write>(wr: &mut W, len: u32) -> Result<Marker, ValueWrit
 /// This function will return `ValueWriteError` on any I/O error occurred while writing either the
 /// marker or the data.
 ///
-/// # Panics
+/// # Note
 ///
-/// Panics if `ty` is negative, because it is reserved for future MessagePack extension including
-/// 2-byte type information.
-pub fn write_ext_meta<W: Write>(wr: &mut W, len: u32, ty: i8) -> Result<Marker, ValueWriteError> {
-    assert!(ty >= 0);
-
+/// Negative types are reserved by the MessagePack specification for predefined extensions, like the
+/// timestamp type `-1`. They are accepted to allow writing such extensions.
+pub fn write_ext_meta<W: RmpWrite>(wr: &mut W, len: u32, ty: i8) ->
+    Result<Marker, ValueWriteError<W::Error>>
+{
+    let ty = ty as u8;
     let marker = match len {
-        1 => {
-            try!(write_marker(wr, Marker::FixExt1));
-            Marker::FixExt1
-        }
-        2 => {
-            try!(write_marker(wr, Marker::FixExt2));
-            Marker::FixExt2
-        }
-        4 => {
-            try!(write_marker(wr, Marker::FixExt4));
-            Marker::FixExt4
-        }
-        8 => {
-            try!(write_marker(wr, Marker::FixExt8));
-            Marker::FixExt8
-        }
-        16 => {
-            try!(write_marker(wr, Marker::FixExt16));
-            Marker::FixExt16
-        }
+        1 => Marker::FixExt1,
+        2 => Marker::FixExt2,
+        4 => Marker::FixExt4,
+        8 => Marker::FixExt8,
+        16 => Marker::FixExt16,
         len if len < 256 => {
-            try!(write_marker(wr, Marker::Ext8));
-            try!(write_data_u8(wr, len as u8));
-            Marker::Ext8
+            try!(write_marker_data(wr, Marker::Ext8, &[len as u8, ty]));
+            return Ok(Marker::Ext8);
         }
         len if len < 65536 => {
-            try!(write_marker(wr, Marker::Ext16));
-            try!(write_data_u16(wr, len as u16));
-            Marker::Ext16
+            let len = (len as u16).to_be_bytes();
+            try!(write_marker_data(wr, Marker::Ext16, &[len[0], len[1], ty]));
+            return Ok(Marker::Ext16);
         }
         len => {
-            try!(write_marker(wr, Marker::Ext32));
-            try!(write_data_u32(wr, len));
-            Marker::Ext32
+            let len = len.to_be_bytes();
+            try!(write_marker_data(wr, Marker::Ext32, &[len[0], len[1], len[2], len[3], ty]));
+            return Ok(Marker::Ext32);
         }
     };
 
-    try!(write_data_i8(wr, ty));
+    try!(write_marker_data(wr, marker, &[ty]));
 
     Ok(marker)
 }
@@ -1,8 +1,6 @@
-use std::io::Write;
-
 use Marker;
-use encode::{write_pfix, write_u8, write_u16, write_u32, write_u64, Error, ValueWriteError};
-use super::{write_data_i8, write_data_i16, write_data_i32, write_data_i64, write_marker};
+use encode::{write_pfix, write_u8, write_u16, write_u32, write_u64, RmpWrite, ValueWriteError};
+use super::{write_marker, write_marker_data};
 
 /// Encodes and attempts to write a negative small integer value as a negative fixnum into the
 /// given write.
@@ -24,9 +22,9 @@ use super::{write_data_i8, write_data_i16, write_data_i32, write_data_i64, write
 /// # Panics
 ///
 /// Panics if `val` does not fit in `[-32; 0)` range.
-pub fn write_nfix<W: Write>(wr: &mut W, val: i8) -> Result<(), Error> {
+pub fn write_nfix<W: RmpWrite>(wr: &mut W, val: i8) -> Result<(), W::Error> {
     assert!(-32 <= val && val < 0);
-    try!(write_marker(wr, Marker::FixNeg(val)));
+    write_marker(wr, Marker::FixNeg(val)).map_err(|err| err.0)?;
     Ok(())
 }
 
@@ -58,9 +56,8 @@ pub fn write_nfix<W: Write>(wr: &mut W, val: i8) -> Result<(), Error> {
 /// rmp::encode::write_i8(&mut &mut buf[..], -18).ok().unwrap();
 /// assert_eq!([0xd0, 0xee], buf);
 /// ```
-pub fn write_i8<W: Write>(wr: &mut W, val: i8) -> Result<(), ValueWriteError> {
-    try!(write_marker(wr, Marker::I8));
-    try!(write_data_i8(wr, val));
+pub fn write_i8<W: RmpWrite>(wr: &mut W, val: i8) -> Result<(), ValueWriteError<W::Error>> {
+    try!(write_marker_data(wr, Marker::I8, &[val as u8]));
     Ok(())
 }
 
@@ -79,9 +76,8 @@ pub fn write_i8<W: Write>(wr: &mut W, val: i8) -> Result<(), ValueWriteError> {
 ///
 /// This function will return `ValueWriteError` on any I/O error occurred while writing either the
 /// marker or the data.
-pub fn write_i16<W: Write>(wr: &mut W, val: i16) -> Result<(), ValueWriteError> {
-    try!(write_marker(wr, Marker::I16));
-    try!(write_data_i16(wr, val));
+pub fn write_i16<W: RmpWrite>(wr: &mut W, val: i16) -> Result<(), ValueWriteError<W::Error>> {
+    try!(write_marker_data(wr, Marker::I16, &val.to_be_bytes()));
     Ok(())
 }
 
@@ -100,9 +96,8 @@ pub fn write_i16<W: Write>(wr: &mut W, val: i16) -> Result<(), ValueWriteError>
 ///
 /// This function will return `ValueWriteError` on any I/O error occurred while writing either the
 /// marker or the data.
-pub fn write_i32<W: Write>(wr: &mut W, val: i32) -> Result<(), ValueWriteError> {
-    try!(write_marker(wr, Marker::I32));
-    try!(write_data_i32(wr, val));
+pub fn write_i32<W: RmpWrite>(wr: &mut W, val: i32) -> Result<(), ValueWriteError<W::Error>> {
+    try!(write_marker_data(wr, Marker::I32, &val.to_be_bytes()));
     Ok(())
 }
 
@@ -121,9 +116,8 @@ pub fn write_i32<W: Write>(wr: &mut W, val: i32) -> Result<(), ValueWriteError>
 ///
 /// This function will return `ValueWriteError` on any I/O error occurred while writing either the
 /// marker or the data.
-pub fn write_i64<W: Write>(wr: &mut W, val: i64) -> Result<(), ValueWriteError> {
-    try!(write_marker(wr, Marker::I64));
-    try!(write_data_i64(wr, val));
+pub fn write_i64<W: RmpWrite>(wr: &mut W, val: i64) -> Result<(), ValueWriteError<W::Error>> {
+    try!(write_marker_data(wr, Marker::I64, &val.to_be_bytes()));
     Ok(())
 }
 
@@ -144,7 +138,7 @@ pub fn write_i64<W: Write>(wr: &mut W, val: i64) -> Result<(), ValueWriteError>
 ///
 /// This function will return `ValueWriteError` on any I/O error occurred while writing either the
 /// marker or the data.
-pub fn write_sint<W: Write>(wr: &mut W, val: i64) -> Result<Marker, ValueWriteError> {
+pub fn write_sint<W: RmpWrite>(wr: &mut W, val: i64) -> Result<Marker, ValueWriteError<W::Error>> {
     match val {
         val if -32 <= val && val < 0 => {
             write_nfix(wr, val as i8)
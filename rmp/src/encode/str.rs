@@ -1,8 +1,6 @@
-use std::io::Write;
-
 use Marker;
-use encode::ValueWriteError;
-use super::{write_marker, write_data_u8, write_data_u16, write_data_u32};
+use encode::{RmpWrite, ValueWriteError};
+use super::{write_marker, write_marker_data};
 
 /// Encodes and attempts to write the most efficient string length implementation to the given
 /// write, returning the marker used.
@@ -11,21 +9,20 @@ use super::{write_marker, write_data_u8, write_data_u16, write_data_u32};
 ///
 /// This function will return `ValueWriteError` on any I/O error occurred while writing either the
 /// marker or the data.
-pub fn write_str_len<W: Write>(wr: &mut W, len: u32) -> Result<Marker, ValueWriteError> {
+pub fn write_str_len<W: RmpWrite>(wr: &mut W, len: u32) ->
+    Result<Marker, ValueWriteError<W::Error>>
+{
     if len < 32 {
         try!(write_marker(wr, Marker::FixStr(len as u8)));
         Ok(Marker::FixStr(len as u8))
     } else if len < 256 {
-        try!(write_marker(wr, Marker::Str8));
-        try!(write_data_u8(wr, len as u8));
+        try!(write_marker_data(wr, Marker::Str8, &[len as u8]));
         Ok(Marker::Str8)
     } else if len < 65536 {
-        try!(write_marker(wr, Marker::Str16));
-        try!(write_data_u16(wr, len as u16));
+        try!(write_marker_data(wr, Marker::Str16, &(len as u16).to_be_bytes()));
         Ok(Marker::Str16)
     } else {
-        try!(write_marker(wr, Marker::Str32));
-        try!(write_data_u32(wr, len));
+        try!(write_marker_data(wr, Marker::Str32, &len.to_be_bytes()));
         Ok(Marker::Str32)
     }
 }
@@ -38,7 +35,7 @@ pub fn write_str_len<W: Write>(wr: &mut W, len: u32) -> Result<Marker, ValueWrit
 /// This function will return `ValueWriteError` on any I/O error occurred while writing either the
 /// marker or the data.
 // TODO: Docs, range check, example, visibility.
-pub fn write_str<W: Write>(wr: &mut W, data: &str) -> Result<(), ValueWriteError> {
+pub fn write_str<W: RmpWrite>(wr: &mut W, data: &str) -> Result<(), ValueWriteError<W::Error>> {
     try!(write_str_len(wr, data.len() as u32));
-    wr.write_all(data.as_bytes()).map_err(ValueWriteError::InvalidDataWrite)
+    wr.write_bytes(data.as_bytes()).map_err(ValueWriteError::InvalidDataWrite)
 }
@@ -1,8 +1,6 @@
-use std::io::Write;
-
 use Marker;
-use encode::{Error, ValueWriteError};
-use super::{write_data_u8, write_data_u16, write_data_u32, write_data_u64, write_marker};
+use encode::{RmpWrite, ValueWriteError};
+use super::{write_marker, write_marker_data};
 
 /// Encodes and attempts to write an unsigned small integer value as a positive fixint into the
 /// given write.
@@ -24,9 +22,9 @@ use super::{write_data_u8, write_data_u16, write_data_u32, write_data_u64, write
 /// # Panics
 ///
 /// Panics if `val` is greater than 127.
-pub fn write_pfix<W: Write>(wr: &mut W, val: u8) -> Result<(), Error> {
+pub fn write_pfix<W: RmpWrite>(wr: &mut W, val: u8) -> Result<(), W::Error> {
     assert!(val < 128);
-    try!(write_marker(wr, Marker::FixPos(val)));
+    write_marker(wr, Marker::FixPos(val)).map_err(|err| err.0)?;
     Ok(())
 }
 
@@ -56,9 +54,8 @@ pub fn write_pfix<W: Write>(wr: &mut W, val: u8) -> Result<(), Error> {
 /// rmp::encode::write_u8(&mut &mut buf[..], 42).ok().unwrap();
 /// assert_eq!([0xcc, 0x2a], buf);
 /// ```
-pub fn write_u8<W: Write>(wr: &mut W, val: u8) -> Result<(), ValueWriteError> {
-    try!(write_marker(wr, Marker::U8));
-    try!(write_data_u8(wr, val));
+pub fn write_u8<W: RmpWrite>(wr: &mut W, val: u8) -> Result<(), ValueWriteError<W::Error>> {
+    try!(write_marker_data(wr, Marker::U8, &[val]));
     Ok(())
 }
 
@@ -76,9 +73,8 @@ pub fn write_u8<W: Write>(wr: &mut W, val: u8) -> Result<(), ValueWriteError> {
 ///
 /// This function will return `ValueWriteError` on any I/O error occurred while writing either the
 /// marker or the data.
-pub fn write_u16<W: Write>(wr: &mut W, val: u16) -> Result<(), ValueWriteError> {
-    try!(write_marker(wr, Marker::U16));
-    try!(write_data_u16(wr, val));
+pub fn write_u16<W: RmpWrite>(wr: &mut W, val: u16) -> Result<(), ValueWriteError<W::Error>> {
+    try!(write_marker_data(wr, Marker::U16, &val.to_be_bytes()));
     Ok(())
 }
 
@@ -96,9 +92,8 @@ pub fn write_u16<W: Write>(wr: &mut W, val: u16) -> Result<(), ValueWriteError>
 ///
 /// This function will return `ValueWriteError` on any I/O error occurred while writing either the
 /// marker or the data.
-pub fn write_u32<W: Write>(wr: &mut W, val: u32) -> Result<(), ValueWriteError> {
-    try!(write_marker(wr, Marker::U32));
-    try!(write_data_u32(wr, val));
+pub fn write_u32<W: RmpWrite>(wr: &mut W, val: u32) -> Result<(), ValueWriteError<W::Error>> {
+    try!(write_marker_data(wr, Marker::U32, &val.to_be_bytes()));
     Ok(())
 }
 
@@ -116,9 +111,8 @@ pub fn write_u32<W: Write>(wr: &mut W, val: u32) -> Result<(), ValueWriteError>
 ///
 /// This function will return `ValueWriteError` on any I/O error occurred while writing either the
 /// marker or the data.
-pub fn write_u64<W: Write>(wr: &mut W, val: u64) -> Result<(), ValueWriteError> {
-    try!(write_marker(wr, Marker::U64));
-    try!(write_data_u64(wr, val));
+pub fn write_u64<W: RmpWrite>(wr: &mut W, val: u64) -> Result<(), ValueWriteError<W::Error>> {
+    try!(write_marker_data(wr, Marker::U64, &val.to_be_bytes()));
     Ok(())
 }
 
@@ -135,7 +129,7 @@ pub fn write_u64<W: Write>(wr: &mut W, val: u64) -> Result<(), ValueWriteError>
 ///
 /// This function will return `ValueWriteError` on any I/O error occurred while writing either the
 /// marker or the data.
-pub fn write_uint<W: Write>(wr: &mut W, val: u64) -> Result<Marker, ValueWriteError> {
+pub fn write_uint<W: RmpWrite>(wr: &mut W, val: u64) -> Result<Marker, ValueWriteError<W::Error>> {
     if val < 128 {
         write_pfix(wr, val as u8)
             .and(Ok(Marker::FixPos(val as u8)))
@@ -40,6 +40,14 @@
 //!
 //!   RMP's error system guarantees that you never receive an error enum with unreachable variant.
 //!
+//! - **`no_std` support**
+//!
+//!   Disabling the default `std` feature makes the crate work without the standard library and
+//!   without an allocator. Values are then read from byte slices or `decode::Bytes` and written
+//!   into fixed-size byte buffers, while errors are compact enums that never allocate. Targets
+//!   having an allocator can enable the `alloc` feature to write into growable `Vec<u8>` buffers
+//!   as well.
+//!
 //! - **Robust and tested**
 //!
 //!   This project is developed using TDD and CI, so any found bugs will be fixed without breaking
@@ -148,13 +156,36 @@
 //!
 //! [read_int]: decode/fn.read_int.html
 
-extern crate byteorder;
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "std")]
+extern crate core;
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+extern crate alloc;
 extern crate num_traits;
 
+#[cfg(feature = "std")]
+mod canonical;
+#[cfg(feature = "std")]
+mod compare;
+#[cfg(feature = "std")]
+mod describe;
 mod marker;
+pub mod checksum;
 pub mod encode;
 pub mod decode;
+#[cfg(feature = "std")]
+pub mod parse;
+#[cfg(feature = "std")]
+pub mod stream;
+pub mod timestamp;
 
+#[cfg(feature = "std")]
+pub use canonical::{canonicalize, CanonicalizeError};
+#[cfg(feature = "std")]
+pub use compare::{compare, equal};
+#[cfg(feature = "std")]
+pub use describe::{describe, describe_to_depth, Blob, Summary};
 pub use marker::Marker;
 
 /// Version of the MessagePack [spec](http://github.com/msgpack/msgpack/blob/master/spec.md).
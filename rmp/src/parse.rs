@@ -0,0 +1,247 @@
+//! An incremental, push-based MessagePack parser, independent of any I/O.
+//!
+//! The decoding functions of this crate pull bytes from a reader and block until they arrive.
+//! Event loops and custom network stacks instead receive data in chunks of arbitrary size, when
+//! it is available. A `Parser` is fed such chunks and turns them into a stream of `Event`s, one
+//! per scalar and two per container, much like a SAX parser does for XML. It never reads on its
+//! own, so the caller decides where the bytes come from and when to parse them.
+//!
+//! Strings, binaries and extensions are reported in one piece, so the parser buffers their
+//! payload until it is complete. Everything before the event returned last is dropped from the
+//! buffer on the next call to `feed`.
+//!
+//! # Examples
+//!
+//! ```
+//! use rmp::parse::{Event, Parser};
+//!
+//! // {"id": [1, -2]}, split in the middle of the key.
+//! let mut parser = Parser::new();
+//! parser.feed(&[0x81, 0xa2, 0x69]);
+//!
+//! assert_eq!(Some(Event::MapStart(1)), parser.next_event().unwrap());
+//! assert_eq!(None, parser.next_event().unwrap());
+//! assert_eq!(1, parser.needed());
+//!
+//! parser.feed(&[0x64, 0x92, 0x01, 0xfe]);
+//!
+//! let mut events = Vec::new();
+//! while let Some(event) = parser.next_event().unwrap() {
+//!     events.push(format!("{:?}", event));
+//! }
+//! assert_eq!(vec!["Str([105, 100])", "ArrayStart(2)", "UInt(1)", "Int(-2)", "ArrayEnd",
+//!                 "MapEnd"], events);
+//! assert_eq!(0, parser.depth());
+//! ```
+
+use std::error;
+use std::fmt::{self, Display, Formatter};
+
+use Marker;
+use decode::{read_header, Header};
+
+/// A piece of MessagePack data recognized by a `Parser`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Event<'a> {
+    /// Nil.
+    Nil,
+    /// A boolean.
+    Bool(bool),
+    /// An integer in one of the unsigned encodings, including a positive fixint.
+    UInt(u64),
+    /// An integer in one of the signed encodings, including a negative fixint.
+    Int(i64),
+    /// A 32-bit float.
+    F32(f32),
+    /// A 64-bit float.
+    F64(f64),
+    /// The payload of a string, which is not validated as UTF-8.
+    Str(&'a [u8]),
+    /// The payload of a binary.
+    Bin(&'a [u8]),
+    /// The type and payload of an extension.
+    Ext(i8, &'a [u8]),
+    /// The start of an array of the given number of elements, which are the next events.
+    ArrayStart(u32),
+    /// The end of the array started last.
+    ArrayEnd,
+    /// The start of a map of the given number of entries, whose keys and values are the next
+    /// events in turn.
+    MapStart(u32),
+    /// The end of the map started last.
+    MapEnd,
+}
+
+/// An error returned by a `Parser` when the input is not valid MessagePack.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ParseError {
+    /// The input contains the reserved marker `0xc1`.
+    ReservedMarker,
+}
+
+impl error::Error for ParseError {
+    fn description(&self) -> &str {
+        match *self {
+            ParseError::ReservedMarker => "reserved MessagePack marker found",
+        }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            ParseError::ReservedMarker => f.write_str("reserved MessagePack marker found"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Container {
+    Array,
+    Map,
+}
+
+/// A push-based parser producing events from chunks of input.
+///
+/// Any number of top-level values can follow each other in the input: once `depth` returns zero
+/// after an event, a whole value was parsed and the next event starts the following one.
+#[derive(Debug, Default)]
+pub struct Parser {
+    buf: Vec<u8>,
+    // Position of the first byte not parsed yet.
+    pos: usize,
+    // The containers being parsed, with the number of values left in each.
+    stack: Vec<(Container, u64)>,
+    needed: usize,
+}
+
+impl Parser {
+    /// Constructs a parser waiting for the first value.
+    pub fn new() -> Parser {
+        Parser::default()
+    }
+
+    /// Appends the chunk to the input, releasing the bytes of the events returned so far.
+    pub fn feed(&mut self, chunk: &[u8]) {
+        if self.pos > 0 {
+            self.buf.drain(..self.pos);
+            self.pos = 0;
+        }
+        self.buf.extend_from_slice(chunk);
+    }
+
+    /// Returns the next event, or `None` if more input is needed to tell it.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ParseError` if the input is invalid. The parser stays at the faulty value, so
+    /// calling this function again returns the same error.
+    pub fn next_event(&mut self) -> Result<Option<Event<'_>>, ParseError> {
+        if let Some(&(container, 0)) = self.stack.last() {
+            self.stack.pop();
+            self.needed = 0;
+            return Ok(Some(match container {
+                Container::Array => Event::ArrayEnd,
+                Container::Map => Event::MapEnd,
+            }));
+        }
+
+        let input = &self.buf[self.pos..];
+        let marker = match input.first() {
+            Some(&byte) => Marker::from_u8(byte),
+            None => {
+                self.needed = 1;
+                return Ok(None);
+            }
+        };
+        if marker == Marker::Reserved {
+            return Err(ParseError::ReservedMarker);
+        }
+
+        let head_len = header_len(marker);
+        if input.len() < head_len {
+            self.needed = head_len - input.len();
+            return Ok(None);
+        }
+        // The header is complete and its marker is not reserved, so reading it can't fail.
+        let header = match read_header(&mut &input[..head_len]) {
+            Ok(header) => header,
+            Err(..) => return Err(ParseError::ReservedMarker),
+        };
+
+        let data_len = match header {
+            Header::Str(len) | Header::Bin(len) | Header::Ext(_, len) => len as usize,
+            _ => 0,
+        };
+        if input.len() - head_len < data_len {
+            self.needed = head_len + data_len - input.len();
+            return Ok(None);
+        }
+
+        if let Some(top) = self.stack.last_mut() {
+            top.1 -= 1;
+        }
+        match header {
+            Header::Array(len) => self.stack.push((Container::Array, len as u64)),
+            Header::Map(len) => self.stack.push((Container::Map, 2 * len as u64)),
+            _ => {}
+        }
+        self.needed = 0;
+
+        let start = self.pos + head_len;
+        self.pos = start + data_len;
+        let data = &self.buf[start..self.pos];
+        let event = match header {
+            Header::Nil => Event::Nil,
+            Header::Bool(val) => Event::Bool(val),
+            Header::UInt(val) => Event::UInt(val),
+            Header::SInt(val) => Event::Int(val),
+            Header::F32(val) => Event::F32(val),
+            Header::F64(val) => Event::F64(val),
+            Header::Str(..) => Event::Str(data),
+            Header::Bin(..) => Event::Bin(data),
+            Header::Ext(ty, _) => Event::Ext(ty, data),
+            Header::Array(len) => Event::ArrayStart(len),
+            Header::Map(len) => Event::MapStart(len),
+        };
+
+        Ok(Some(event))
+    }
+
+    /// Returns the least number of bytes to feed before `next_event` can return another event,
+    /// after it returned `None`.
+    ///
+    /// This is exact for strings, binaries and extensions whose header is complete, and a lower
+    /// bound otherwise. It is zero while events are available.
+    pub fn needed(&self) -> usize {
+        self.needed
+    }
+
+    /// Returns the number of arrays and maps the last event is nested in, or started.
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    /// Returns the number of bytes fed but not parsed yet.
+    pub fn buffered(&self) -> usize {
+        self.buf.len() - self.pos
+    }
+}
+
+/// Returns the length of the header starting with the marker, up to the payload.
+fn header_len(marker: Marker) -> usize {
+    1 + match marker {
+        Marker::U8 | Marker::I8 | Marker::Str8 | Marker::Bin8 => 1,
+        Marker::U16 | Marker::I16 | Marker::Str16 | Marker::Bin16 | Marker::Array16 |
+        Marker::Map16 => 2,
+        Marker::U32 | Marker::I32 | Marker::F32 | Marker::Str32 | Marker::Bin32 |
+        Marker::Array32 | Marker::Map32 => 4,
+        Marker::U64 | Marker::I64 | Marker::F64 => 8,
+        Marker::FixExt1 | Marker::FixExt2 | Marker::FixExt4 | Marker::FixExt8 |
+        Marker::FixExt16 => 1,
+        Marker::Ext8 => 2,
+        Marker::Ext16 => 3,
+        Marker::Ext32 => 5,
+        _ => 0,
+    }
+}
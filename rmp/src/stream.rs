@@ -0,0 +1,322 @@
+//! Append-only logs of MessagePack objects that survive torn writes.
+//!
+//! A process appending to a file can crash in the middle of a write, leaving part of an object
+//! at the end of the file, and disks occasionally return damaged data. A `LogWriter` follows each
+//! object with its CRC-32 and inserts a sync marker every few objects. A `LogReader` checks every
+//! object against its checksum and, on a damaged or torn one, drops bytes until the next sync
+//! marker, where it resumes reading.
+//!
+//! The log is itself a sequence of MessagePack values: each object is followed by its checksum
+//! as a uint 32, and sync markers are fixext 16 values of type `SYNC_TYPE` holding a fixed
+//! 16-byte pattern. A writer always starts with a sync marker, so objects appended to a log
+//! whose previous writer crashed can still be found after the torn tail.
+//!
+//! # Examples
+//!
+//! ```
+//! use rmp::stream::{LogReader, LogWriter};
+//!
+//! let mut log = LogWriter::new(Vec::new()).unwrap();
+//! log.append(&[0xa3, 0x6f, 0x6e, 0x65]).unwrap();
+//! log.append(&[0x92, 0x01, 0x02]).unwrap();
+//! let mut buf = log.into_inner();
+//!
+//! // The second object was only partly written before a crash, after which another writer
+//! // appended to the log.
+//! buf.truncate(buf.len() - 2);
+//! let mut log = LogWriter::new(buf).unwrap();
+//! log.append(&[0xc3]).unwrap();
+//! let buf = log.into_inner();
+//!
+//! let mut log = LogReader::new(&buf[..]);
+//! assert_eq!(Some(vec![0xa3, 0x6f, 0x6e, 0x65]), log.next_object().unwrap());
+//! assert_eq!(Some(vec![0xc3]), log.next_object().unwrap());
+//! assert_eq!(None, log.next_object().unwrap());
+//! assert_eq!(6, log.skipped());
+//! ```
+
+use std::io::{self, ErrorKind, Read, Write};
+
+use checksum::crc32;
+use decode::skip_value;
+
+/// Extension type of sync markers.
+pub const SYNC_TYPE: i8 = 0x52;
+
+/// The encoded sync marker.
+const SYNC: [u8; 18] = [
+    0xd8, SYNC_TYPE as u8,
+    0x9a, 0x3d, 0xe2, 0x71, 0x0c, 0xb4, 0x58, 0xf6, 0x27, 0x81, 0xce, 0x4b, 0x13, 0xa5, 0x6d, 0xe9,
+];
+
+/// Length of the checksum following each object, with its marker.
+const CRC_LEN: usize = 5;
+
+/// Number of bytes a `LogReader` reads at least at once.
+const CHUNK_LEN: usize = 8192;
+
+/// Writes objects to a log.
+///
+/// Each object is written with a single call to `write_all` on the underlying writer, which
+/// should be buffered, like a `BufWriter`, if it issues a system call on every write.
+#[derive(Debug)]
+pub struct LogWriter<W: Write> {
+    wr: W,
+    sync_interval: u32,
+    // Number of objects written since the last sync marker.
+    unsynced: u32,
+}
+
+impl<W: Write> LogWriter<W> {
+    /// Wraps the given writer, writing a sync marker every 64 objects.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the writer if it fails to write the initial sync marker.
+    pub fn new(wr: W) -> io::Result<Self> {
+        LogWriter::with_sync_interval(wr, 64)
+    }
+
+    /// Wraps the given writer, writing a sync marker every `interval` objects.
+    ///
+    /// A damaged object makes the reader lose the objects following it up to the next sync
+    /// marker, while each marker adds 18 bytes to the log.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `interval` is zero.
+    pub fn with_sync_interval(wr: W, interval: u32) -> io::Result<Self> {
+        assert!(interval > 0, "the sync interval must not be zero");
+
+        let mut log = LogWriter {
+            wr,
+            sync_interval: interval,
+            unsynced: 0,
+        };
+        log.sync()?;
+        Ok(log)
+    }
+
+    /// Appends the given object, which must be exactly one encoded value, to the log.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error of kind `InvalidInput` if the object is not exactly one value or is the
+    /// sync marker itself, and the error of the writer if it fails.
+    pub fn append(&mut self, obj: &[u8]) -> io::Result<()> {
+        let mut rd = obj;
+        if skip_value(&mut rd).is_err() || !rd.is_empty() || obj == SYNC {
+            return Err(io::Error::new(ErrorKind::InvalidInput, "not a single MessagePack value"));
+        }
+
+        let mut buf = Vec::with_capacity(SYNC.len() + obj.len() + CRC_LEN);
+        if self.unsynced == self.sync_interval {
+            buf.extend_from_slice(&SYNC);
+            self.unsynced = 0;
+        }
+        buf.extend_from_slice(obj);
+        buf.push(0xce);
+        buf.extend_from_slice(&crc32(obj).to_be_bytes());
+
+        self.wr.write_all(&buf)?;
+        self.unsynced += 1;
+        Ok(())
+    }
+
+    /// Writes a sync marker now, so that damage to the objects written so far can't affect the
+    /// ones appended next.
+    pub fn sync(&mut self) -> io::Result<()> {
+        self.wr.write_all(&SYNC)?;
+        self.unsynced = 0;
+        Ok(())
+    }
+
+    /// Flushes the underlying writer.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.wr.flush()
+    }
+
+    /// Gets a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.wr
+    }
+
+    /// Gets a mutable reference to the underlying writer.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.wr
+    }
+
+    /// Unwraps this `LogWriter`, returning the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.wr
+    }
+}
+
+/// Reads the objects of a log, skipping damaged ones.
+#[derive(Debug)]
+pub struct LogReader<R: Read> {
+    rd: R,
+    buf: Vec<u8>,
+    // Position of the first byte not read yet.
+    pos: usize,
+    eof: bool,
+    skipped: u64,
+    max_len: usize,
+}
+
+impl<R: Read> LogReader<R> {
+    /// Wraps the given reader, accepting objects of up to 64 MiB.
+    pub fn new(rd: R) -> Self {
+        LogReader {
+            rd,
+            buf: Vec::new(),
+            pos: 0,
+            eof: false,
+            skipped: 0,
+            max_len: 1 << 26,
+        }
+    }
+
+    /// Sets the maximum length of an object.
+    ///
+    /// A damaged length prefix can make an object look larger than the whole log. Longer
+    /// objects are treated as damaged instead of being buffered until the end of the log.
+    pub fn set_max_len(&mut self, len: usize) {
+        self.max_len = len;
+    }
+
+    /// Returns the next object whose checksum matches, or `None` at the end of the log.
+    ///
+    /// Damaged objects, including a torn one at the end of the log, are skipped along with the
+    /// bytes up to the next sync marker. Their number is counted by `skipped`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the underlying reader if it fails. Reading can then be resumed.
+    pub fn next_object(&mut self) -> io::Result<Option<Vec<u8>>> {
+        loop {
+            let avail = self.buf.len() - self.pos;
+            if avail == 0 {
+                if self.fill(0)? {
+                    continue;
+                }
+                return Ok(None);
+            }
+
+            let mut rd = &self.buf[self.pos..];
+            let len = match skip_value(&mut rd) {
+                Ok(()) => avail - rd.len(),
+                Err(..) => {
+                    // The value is incomplete.
+                    if avail <= self.max_len && self.fill(avail)? {
+                        continue;
+                    }
+                    self.resync()?;
+                    continue;
+                }
+            };
+
+            let obj = &self.buf[self.pos..self.pos + len];
+            if obj == SYNC {
+                self.pos += len;
+                continue;
+            }
+            if len > self.max_len {
+                self.resync()?;
+                continue;
+            }
+            if avail < len + CRC_LEN {
+                if self.fill(len + CRC_LEN - avail)? {
+                    continue;
+                }
+                self.resync()?;
+                continue;
+            }
+
+            let crc = &self.buf[self.pos + len..self.pos + len + CRC_LEN];
+            if crc[0] == 0xce && crc[1..] == crc32(obj).to_be_bytes() {
+                let obj = obj.to_vec();
+                self.pos += len + CRC_LEN;
+                return Ok(Some(obj));
+            }
+            self.resync()?;
+        }
+    }
+
+    /// Returns the number of bytes dropped so far because they were damaged.
+    pub fn skipped(&self) -> u64 {
+        self.skipped
+    }
+
+    /// Gets a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.rd
+    }
+
+    /// Unwraps this `LogReader`, returning the underlying reader.
+    ///
+    /// Bytes read from it but not returned yet are lost.
+    pub fn into_inner(self) -> R {
+        self.rd
+    }
+
+    /// Drops the damaged value at the current position along with the bytes following it, up to
+    /// the next sync marker or the end of the log.
+    fn resync(&mut self) -> io::Result<()> {
+        let mut from = self.pos + 1;
+        loop {
+            let found = self.buf[from..].windows(SYNC.len()).position(|w| w == SYNC);
+            if let Some(at) = found {
+                self.skipped += (from + at - self.pos) as u64;
+                self.pos = from + at;
+                return Ok(());
+            }
+
+            // Keep what may be the start of a marker, and read on.
+            let keep = self.buf.len().saturating_sub(SYNC.len() - 1).max(from);
+            self.skipped += (keep - self.pos) as u64;
+            self.pos = keep;
+            if !self.fill(0)? {
+                self.skipped += (self.buf.len() - self.pos) as u64;
+                self.pos = self.buf.len();
+                return Ok(());
+            }
+            from = self.pos;
+        }
+    }
+
+    /// Reads at least `len` more bytes, or a chunk if it is larger, unless the end of the log is
+    /// reached first. Returns `false` if nothing could be read.
+    fn fill(&mut self, len: usize) -> io::Result<bool> {
+        if self.pos > 0 {
+            self.buf.drain(..self.pos);
+            self.pos = 0;
+        }
+        if self.eof {
+            return Ok(false);
+        }
+
+        let start = self.buf.len();
+        let want = len.max(CHUNK_LEN);
+        self.buf.resize(start + want, 0);
+
+        let mut read = 0;
+        while read < len || read == 0 {
+            match self.rd.read(&mut self.buf[start + read..]) {
+                Ok(0) => {
+                    self.eof = true;
+                    break;
+                }
+                Ok(n) => read += n,
+                Err(ref err) if err.kind() == ErrorKind::Interrupted => {}
+                Err(err) => {
+                    self.buf.truncate(start + read);
+                    return Err(err);
+                }
+            }
+        }
+
+        self.buf.truncate(start + read);
+        Ok(read > 0)
+    }
+}
@@ -0,0 +1,180 @@
+//! The timestamp extension type defined by the MessagePack specification.
+//!
+//! A timestamp is an extension of type `-1` holding seconds since the Unix epoch together with
+//! nanoseconds, in one of three formats: 32 bits of unsigned seconds, 64 bits of 30-bit
+//! nanoseconds and 34-bit unsigned seconds, or 96 bits of 32-bit nanoseconds and 64-bit signed
+//! seconds. `Timestamp` reads all of them and writes the most compact one that fits, so values
+//! can be exchanged with any implementation supporting the extension.
+//!
+//! # Examples
+//!
+//! ```
+//! use rmp::encode;
+//! use rmp::timestamp::{self, Timestamp};
+//!
+//! let ts = Timestamp::new(1500000000, 0).unwrap();
+//!
+//! let mut buf = [0; 12];
+//! let data = ts.encode(&mut buf);
+//! assert_eq!([0x59, 0x68, 0x2f, 0x00], data);
+//! assert_eq!(Some(ts), Timestamp::from_ext_data(data));
+//!
+//! let mut out = Vec::new();
+//! encode::write_ext_meta(&mut out, data.len() as u32, timestamp::EXT_TYPE).unwrap();
+//! out.extend_from_slice(data);
+//! assert_eq!([0xd6, 0xff, 0x59, 0x68, 0x2f, 0x00], out[..]);
+//! ```
+
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+use alloc::vec::Vec;
+use core::time::Duration;
+#[cfg(feature = "std")]
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The extension type of timestamps.
+pub const EXT_TYPE: i8 = -1;
+
+const NANOS_PER_SEC: u32 = 1_000_000_000;
+
+/// A point in time with nanosecond precision, as represented by the timestamp extension.
+///
+/// Times before the epoch have negative seconds, while nanoseconds are always counted forward
+/// from the start of the second. Ordering is chronological.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Timestamp {
+    secs: i64,
+    nanos: u32,
+}
+
+impl Timestamp {
+    /// Constructs a timestamp from seconds since the epoch and nanoseconds after them.
+    ///
+    /// Returns `None` if the nanoseconds are not less than one second.
+    pub fn new(secs: i64, nanos: u32) -> Option<Timestamp> {
+        if nanos < NANOS_PER_SEC {
+            Some(Timestamp { secs, nanos })
+        } else {
+            None
+        }
+    }
+
+    /// Returns the number of whole seconds since the epoch, negative for earlier times.
+    pub fn secs(&self) -> i64 {
+        self.secs
+    }
+
+    /// Returns the number of nanoseconds after `secs`.
+    pub fn nanos(&self) -> u32 {
+        self.nanos
+    }
+
+    /// Decodes a timestamp from the data of a timestamp extension in any of its formats.
+    ///
+    /// Returns `None` if the data is not 4, 8 or 12 bytes long or if its nanoseconds are out of
+    /// range.
+    pub fn from_ext_data(data: &[u8]) -> Option<Timestamp> {
+        match data.len() {
+            4 => Timestamp::new(read_be(data) as i64, 0),
+            8 => {
+                let val = read_be(data);
+                Timestamp::new((val & 0x3_ffff_ffff) as i64, (val >> 34) as u32)
+            }
+            12 => Timestamp::new(read_be(&data[4..]) as i64, read_be(&data[..4]) as u32),
+            _ => None,
+        }
+    }
+
+    /// Encodes the timestamp as the data of a timestamp extension into the given buffer, in the
+    /// most compact format able to represent it, and returns the part of the buffer written.
+    pub fn encode<'a>(&self, buf: &'a mut [u8; 12]) -> &'a [u8] {
+        let len = if self.secs >> 34 == 0 {
+            let val = (self.nanos as u64) << 34 | self.secs as u64;
+            if val >> 32 == 0 {
+                // timestamp 32: unsigned seconds.
+                write_be(&mut buf[..4], val);
+                4
+            } else {
+                // timestamp 64: 30-bit nanoseconds and 34-bit unsigned seconds.
+                write_be(&mut buf[..8], val);
+                8
+            }
+        } else {
+            // timestamp 96: 32-bit nanoseconds and 64-bit signed seconds.
+            write_be(&mut buf[..4], self.nanos as u64);
+            write_be(&mut buf[4..], self.secs as u64);
+            12
+        };
+
+        &buf[..len]
+    }
+
+    /// Encodes the timestamp as the data of a timestamp extension, in the most compact format
+    /// able to represent it.
+    #[cfg(any(feature = "std", feature = "alloc"))]
+    pub fn to_ext_data(&self) -> Vec<u8> {
+        self.encode(&mut [0; 12]).to_vec()
+    }
+
+    /// Constructs a timestamp the given time after the epoch.
+    ///
+    /// Returns `None` if the duration is longer than `i64::MAX` seconds.
+    pub fn from_duration(dur: Duration) -> Option<Timestamp> {
+        if dur.as_secs() > i64::MAX as u64 {
+            return None;
+        }
+
+        Timestamp::new(dur.as_secs() as i64, dur.subsec_nanos())
+    }
+
+    /// Returns the time elapsed since the epoch, or `None` if the timestamp is before it.
+    pub fn to_duration(&self) -> Option<Duration> {
+        if self.secs < 0 {
+            return None;
+        }
+
+        Some(Duration::new(self.secs as u64, self.nanos))
+    }
+
+    /// Converts a system time into a timestamp.
+    ///
+    /// Returns `None` if the time is more than `i64::MAX` seconds away from the epoch.
+    #[cfg(feature = "std")]
+    pub fn from_system_time(time: SystemTime) -> Option<Timestamp> {
+        let dur = match time.duration_since(UNIX_EPOCH) {
+            Ok(dur) => return Timestamp::from_duration(dur),
+            Err(err) => err.duration(),
+        };
+        if dur.as_secs() > i64::MAX as u64 {
+            return None;
+        }
+
+        // Nanoseconds are counted forward, so round the seconds down.
+        match dur.subsec_nanos() {
+            0 => Timestamp::new(-(dur.as_secs() as i64), 0),
+            nanos => Timestamp::new(-(dur.as_secs() as i64) - 1, NANOS_PER_SEC - nanos),
+        }
+    }
+
+    /// Converts the timestamp into a system time, or returns `None` if the platform can't
+    /// represent it.
+    #[cfg(feature = "std")]
+    pub fn to_system_time(&self) -> Option<SystemTime> {
+        if self.secs >= 0 {
+            UNIX_EPOCH.checked_add(Duration::new(self.secs as u64, self.nanos))
+        } else {
+            UNIX_EPOCH.checked_sub(Duration::from_secs(self.secs.wrapping_neg() as u64))
+                .and_then(|time| time.checked_add(Duration::new(0, self.nanos)))
+        }
+    }
+}
+
+fn write_be(buf: &mut [u8], val: u64) {
+    let len = buf.len();
+    for (idx, byte) in buf.iter_mut().enumerate() {
+        *byte = (val >> (8 * (len - 1 - idx))) as u8;
+    }
+}
+
+fn read_be(buf: &[u8]) -> u64 {
+    buf.iter().fold(0, |val, &byte| val << 8 | byte as u64)
+}
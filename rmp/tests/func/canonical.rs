@@ -0,0 +1,88 @@
+use msgpack::{canonicalize, CanonicalizeError};
+
+fn canonical(buf: &[u8]) -> Result<Vec<u8>, CanonicalizeError> {
+    let mut out = Vec::new();
+    canonicalize(&mut &buf[..], &mut out).map(|()| out)
+}
+
+#[test]
+fn canonicalize_ints() {
+    assert_eq!(vec![0x2a], canonical(&[0xcf, 0, 0, 0, 0, 0, 0, 0, 0x2a]).unwrap());
+    assert_eq!(vec![0x2a], canonical(&[0xd2, 0, 0, 0, 0x2a]).unwrap());
+    assert_eq!(vec![0xcd, 0x01, 0x2c], canonical(&[0xd3, 0, 0, 0, 0, 0, 0, 0x01, 0x2c]).unwrap());
+    assert_eq!(vec![0xff], canonical(&[0xd1, 0xff, 0xff]).unwrap());
+    assert_eq!(vec![0xd0, 0x80], canonical(&[0xd2, 0xff, 0xff, 0xff, 0x80]).unwrap());
+}
+
+#[test]
+fn canonicalize_floats() {
+    // 0.5f64 fits into f32 losslessly, 0.1f64 does not.
+    assert_eq!(vec![0xca, 0x3f, 0x00, 0x00, 0x00],
+        canonical(&[0xcb, 0x3f, 0xe0, 0, 0, 0, 0, 0, 0]).unwrap());
+    let pointone = [0xcb, 0x3f, 0xb9, 0x99, 0x99, 0x99, 0x99, 0x99, 0x9a];
+    assert_eq!(pointone.to_vec(), canonical(&pointone).unwrap());
+}
+
+#[test]
+fn canonicalize_nan() {
+    let lhs = canonical(&[0xcb, 0x7f, 0xf8, 0, 0, 0, 0, 0, 0x01]).unwrap();
+    let rhs = canonical(&[0xca, 0xff, 0xc0, 0x00, 0x00]).unwrap();
+
+    assert_eq!(lhs, rhs);
+}
+
+#[test]
+fn canonicalize_str_bin_ext_headers() {
+    assert_eq!(vec![0xa2, 0x6f, 0x6b], canonical(&[0xda, 0x00, 0x02, 0x6f, 0x6b]).unwrap());
+    assert_eq!(vec![0xc4, 0x01, 0x2a], canonical(&[0xc6, 0, 0, 0, 0x01, 0x2a]).unwrap());
+    assert_eq!(vec![0xd4, 0xff, 0x2a], canonical(&[0xc7, 0x01, 0xff, 0x2a]).unwrap());
+}
+
+#[test]
+fn canonicalize_sorts_nested_maps() {
+    // [{"b": {2: nil, 1: nil}, "a": 0}]
+    let buf = [0xdc, 0x00, 0x01, 0x82, 0xa1, 0x62, 0x82, 0x02, 0xc0, 0x01, 0xc0, 0xa1, 0x61, 0x00];
+    let expected = vec![0x91, 0x82, 0xa1, 0x61, 0x00, 0xa1, 0x62, 0x82, 0x01, 0xc0, 0x02, 0xc0];
+
+    assert_eq!(expected, canonical(&buf).unwrap());
+}
+
+#[test]
+fn canonicalize_rejects_duplicate_keys() {
+    // {1: 1, 1u16: 2}
+    match canonical(&[0x82, 0x01, 0x01, 0xcd, 0x00, 0x01, 0x02]) {
+        Err(CanonicalizeError::DuplicateKey) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn canonicalize_truncated() {
+    match canonical(&[0xa3, 0x61]) {
+        Err(CanonicalizeError::InvalidDataRead(..)) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn canonicalize_depth_limit() {
+    let buf = vec![0x91; 2000];
+
+    match canonical(&buf) {
+        Err(CanonicalizeError::DepthLimitExceeded) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn canonicalize_stream() {
+    let buf = [0xcc, 0x01, 0xd9, 0x01, 0x61];
+    let mut rd = &buf[..];
+    let mut out = Vec::new();
+
+    while !rd.is_empty() {
+        canonicalize(&mut rd, &mut out).unwrap();
+    }
+
+    assert_eq!(vec![0x01, 0xa1, 0x61], out);
+}
@@ -0,0 +1,69 @@
+use std::hash::Hasher;
+
+use msgpack::{decode, encode};
+use msgpack::checksum::{self, ChecksumReader, ChecksumWriter, Crc32, XxHash64};
+
+const SPAM: &[u8] = b"Nobody inspects the spammish repetition";
+
+#[test]
+fn crc32_check_value() {
+    assert_eq!(0, checksum::crc32(b""));
+    assert_eq!(0xcbf4_3926, checksum::crc32(b"123456789"));
+}
+
+#[test]
+fn xxhash64_known_values() {
+    assert_eq!(0xef46_db37_51d8_e999, checksum::xxhash64(b"", 0));
+    assert_eq!(0x44bc_2cf5_ad77_0999, checksum::xxhash64(b"abc", 0));
+    assert_eq!(0xfbce_a83c_8a37_8bf1, checksum::xxhash64(SPAM, 0));
+}
+
+#[test]
+fn hashers_in_pieces() {
+    let data: Vec<u8> = SPAM.iter().cycle().take(100).cloned().collect();
+
+    for split in 0..data.len() {
+        let mut crc = Crc32::new();
+        crc.write(&data[..split]);
+        crc.write(&data[split..]);
+        assert_eq!(checksum::crc32(&data) as u64, crc.finish());
+
+        let mut xxh = XxHash64::with_seed(7);
+        xxh.write(&data[..split]);
+        xxh.write(&data[split..]);
+        assert_eq!(checksum::xxhash64(&data, 7), xxh.finish());
+    }
+}
+
+#[test]
+fn checksum_of_one_object_in_stream() {
+    let mut buf = Vec::new();
+    encode::write_str(&mut buf, "first").unwrap();
+    let (_, crc) = checksum::write_with_checksum(&mut buf, Crc32::new(), |wr| {
+        encode::write_map_len(wr, 1)?;
+        encode::write_str(wr, "k")?;
+        encode::write_sint(wr, -300)
+    }).unwrap();
+    encode::write_nil(&mut buf).unwrap();
+    assert_eq!(checksum::crc32(&buf[6..12]) as u64, crc);
+
+    let mut rd = &buf[6..];
+    let (_, digest) = checksum::read_with_checksum(&mut rd, Crc32::new(), |rd| {
+        decode::skip_value(rd)
+    }).unwrap();
+    assert_eq!(crc, digest);
+    assert_eq!([0xc0], rd);
+}
+
+#[test]
+fn checksum_adapters() {
+    let mut wr = ChecksumWriter::new(Vec::new(), XxHash64::new());
+    encode::write_bin(&mut wr, SPAM).unwrap();
+    let (buf, digest) = wr.finish();
+    assert_eq!(checksum::xxhash64(&buf, 0), digest);
+
+    let mut rd = ChecksumReader::new(&buf[..], XxHash64::new());
+    assert_eq!(SPAM.len() as u32, decode::read_bin_len(&mut rd).unwrap());
+    assert_eq!(checksum::xxhash64(&buf[..2], 0), rd.digest());
+    assert_eq!(SPAM.len(), rd.get_ref().len());
+}
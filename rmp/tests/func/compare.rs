@@ -0,0 +1,103 @@
+use std::cmp::Ordering;
+
+use msgpack::{compare, equal};
+use msgpack::encode::*;
+
+#[test]
+fn compare_ints_of_different_widths() {
+    let mut buf = Vec::new();
+    write_u64(&mut buf, 42).unwrap();
+
+    assert_eq!(Ordering::Equal, compare(&[0x2a], &buf).unwrap());
+    assert_eq!(Ordering::Equal, compare(&[0xd0, 0xff], &[0xff]).unwrap());
+    assert_eq!(Ordering::Equal, compare(&[0xd1, 0x00, 0x2a], &[0xcc, 0x2a]).unwrap());
+}
+
+#[test]
+fn compare_ints_by_value() {
+    assert_eq!(Ordering::Less, compare(&[0xff], &[0x00]).unwrap());
+    assert_eq!(Ordering::Less, compare(&[0xd3, 0x80, 0, 0, 0, 0, 0, 0, 0], &[0xe0]).unwrap());
+    assert_eq!(Ordering::Greater,
+        compare(&[0xcf, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff], &[0x7f]).unwrap());
+}
+
+#[test]
+fn compare_floats_of_different_widths() {
+    let mut f32buf = Vec::new();
+    let mut f64buf = Vec::new();
+    write_f32(&mut f32buf, 0.5).unwrap();
+    write_f64(&mut f64buf, 0.5).unwrap();
+
+    assert_eq!(Ordering::Equal, compare(&f32buf, &f64buf).unwrap());
+}
+
+#[test]
+fn compare_nan_equals_itself() {
+    let mut lhs = Vec::new();
+    let mut rhs = Vec::new();
+    write_f64(&mut lhs, ::std::f64::NAN).unwrap();
+    write_f64(&mut rhs, 1e300).unwrap();
+
+    assert!(equal(&lhs, &lhs));
+    assert_eq!(Ordering::Greater, compare(&lhs, &rhs).unwrap());
+}
+
+#[test]
+fn compare_int_is_not_float() {
+    let mut buf = Vec::new();
+    write_f64(&mut buf, 1.0).unwrap();
+
+    assert!(!equal(&[0x01], &buf));
+    assert_eq!(Ordering::Less, compare(&[0x01], &buf).unwrap());
+}
+
+#[test]
+fn compare_strings_with_different_headers() {
+    assert!(equal(&[0xa2, 0x6f, 0x6b], &[0xda, 0x00, 0x02, 0x6f, 0x6b]));
+    assert_eq!(Ordering::Less, compare(&[0xa1, 0x61], &[0xa1, 0x62]).unwrap());
+    // The string "a" is a prefix of "ab".
+    assert_eq!(Ordering::Less, compare(&[0xa1, 0x61], &[0xa2, 0x61, 0x62]).unwrap());
+}
+
+#[test]
+fn compare_string_is_not_binary() {
+    assert!(!equal(&[0xa1, 0x61], &[0xc4, 0x01, 0x61]));
+}
+
+#[test]
+fn compare_nested_containers() {
+    // {"k": [1, [2]]} written compactly and with the widest headers.
+    let lhs = [0x81, 0xa1, 0x6b, 0x92, 0x01, 0x91, 0x02];
+    let rhs = [0xdf, 0x00, 0x00, 0x00, 0x01, 0xdb, 0x00, 0x00, 0x00, 0x01, 0x6b,
+               0xdd, 0x00, 0x00, 0x00, 0x02, 0xcc, 0x01, 0xdc, 0x00, 0x01, 0xd0, 0x02];
+
+    assert_eq!(Ordering::Equal, compare(&lhs, &rhs).unwrap());
+}
+
+#[test]
+fn compare_arrays_lexicographically() {
+    // [1, 2] vs [1, 2, 0]: equal prefix, shorter is less.
+    assert_eq!(Ordering::Less, compare(&[0x92, 0x01, 0x02], &[0x93, 0x01, 0x02, 0x00]).unwrap());
+    // [[1, 3]] vs [[1, 2, 0]]: the difference inside wins over the length.
+    assert_eq!(Ordering::Greater,
+        compare(&[0x91, 0x92, 0x01, 0x03], &[0x91, 0x93, 0x01, 0x02, 0x00]).unwrap());
+    // [[1], 2] vs [[1], 3]
+    assert_eq!(Ordering::Less, compare(&[0x92, 0x91, 0x01, 0x02], &[0x92, 0x91, 0x01, 0x03]).unwrap());
+}
+
+#[test]
+fn compare_ext() {
+    assert!(equal(&[0xd4, 0x01, 0x2a], &[0xc7, 0x01, 0x01, 0x2a]));
+    assert_eq!(Ordering::Less, compare(&[0xd4, 0x01, 0x2a], &[0xd4, 0x02, 0x00]).unwrap());
+}
+
+#[test]
+fn compare_ignores_trailing_bytes() {
+    assert!(equal(&[0xc0, 0x01], &[0xc0]));
+}
+
+#[test]
+fn compare_truncated_buffer() {
+    assert!(compare(&[0x92, 0x01], &[0x92, 0x01, 0x02]).is_err());
+    assert!(!equal(&[0xa2, 0x61], &[0xa2, 0x61, 0x62]));
+}
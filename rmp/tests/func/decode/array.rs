@@ -89,3 +89,85 @@ fn from_null_read_array_len() {
     }
     assert_eq!(1, cur.position());
 }
+
+#[test]
+fn from_array_of_u32() {
+    let buf: &[u8] = &[0x93, 0x01, 0xcd, 0x01, 0x2c, 0xce, 0x00, 0x01, 0x00, 0x00];
+    let mut cur = Cursor::new(buf);
+
+    assert_eq!(vec![1, 300, 65536], read_array_of::<u32, _>(&mut cur).unwrap());
+    assert_eq!(10, cur.position());
+}
+
+#[test]
+fn from_array_of_nested_strings() {
+    let buf: &[u8] = &[0x92, 0x91, 0xa1, 0x61, 0x90];
+    let mut cur = Cursor::new(buf);
+
+    let expected = vec![vec!["a".to_string()], vec![]];
+    assert_eq!(expected, read_array_of::<Vec<String>, _>(&mut cur).unwrap());
+    assert_eq!(5, cur.position());
+}
+
+#[test]
+fn from_array_of_out_of_range() {
+    let buf: &[u8] = &[0x92, 0x01, 0xcd, 0x01, 0x2c];
+    let mut cur = Cursor::new(buf);
+
+    match read_array_of::<u8, _>(&mut cur) {
+        Err(TypedReadError::OutOfRange) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn from_array_of_invalid_utf8() {
+    let buf: &[u8] = &[0x91, 0xa2, 0xc3, 0x28];
+    let mut cur = Cursor::new(buf);
+
+    match read_array_of::<String, _>(&mut cur) {
+        Err(TypedReadError::InvalidUtf8(..)) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn from_array_with_type_mismatch() {
+    let buf: &[u8] = &[0x81, 0x01, 0x02];
+    let mut cur = Cursor::new(buf);
+
+    let res: Result<Vec<bool>, ValueReadError> = read_array_with(&mut cur, read_bool);
+    match res {
+        Err(ValueReadError::TypeMismatch(Marker::FixMap(1))) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn from_array_read_tuple() {
+    let buf: &[u8] = &[0x93, 0x2a, 0xa2, 0x6f, 0x6b, 0xc2];
+    let mut cur = Cursor::new(buf);
+
+    assert_eq!((42, "ok".to_string(), false), read_tuple!(&mut cur, (u32, String, bool)).unwrap());
+    assert_eq!(6, cur.position());
+}
+
+#[test]
+fn from_array_read_nested_tuple() {
+    let buf: &[u8] = &[0x92, 0x92, 0x01, 0xfe, 0x91, 0xc3];
+    let mut cur = Cursor::new(buf);
+
+    assert_eq!(((1, -2), (true,)), read_tuple!(&mut cur, ((u8, i64), (bool,))).unwrap());
+}
+
+#[test]
+fn from_array_read_tuple_arity_mismatch() {
+    let buf: &[u8] = &[0x92, 0x01, 0x02];
+    let mut cur = Cursor::new(buf);
+
+    match read_tuple!(&mut cur, (u8, u8, u8)) {
+        Err(TypedReadError::LengthMismatch(2)) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+    assert_eq!(1, cur.position());
+}
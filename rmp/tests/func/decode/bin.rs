@@ -59,3 +59,60 @@ fn from_bin32_max_read_len() {
     assert_eq!(4294967295, read_bin_len(&mut cur).unwrap());
     assert_eq!(5, cur.position());
 }
+
+#[test]
+fn from_bin8_read_bin() {
+    let buf: &[u8] = &[0xc4, 0x03, 0x01, 0x02, 0x03, 0xc0];
+    let mut cur = Cursor::new(buf);
+    let mut out = [0u8; 8];
+
+    assert_eq!(&[1, 2, 3], read_bin(&mut cur, &mut out).unwrap());
+    assert_eq!(5, cur.position());
+}
+
+#[test]
+fn from_bin16_read_bin_exact_buffer() {
+    let buf: &[u8] = &[0xc5, 0x00, 0x02, 0xff, 0xfe];
+    let mut cur = Cursor::new(buf);
+    let mut out = [0u8; 2];
+
+    assert_eq!(&[0xff, 0xfe], read_bin(&mut cur, &mut out).unwrap());
+    assert_eq!(5, cur.position());
+}
+
+#[test]
+fn from_bin8_read_bin_buffer_too_small() {
+    let buf: &[u8] = &[0xc4, 0x03, 0x01, 0x02, 0x03];
+    let mut cur = Cursor::new(buf);
+    let mut out = [0u8; 2];
+
+    match read_bin(&mut cur, &mut out) {
+        Err(DecodeBinError::BufferSizeTooSmall(3)) => (),
+        other => panic!("unexpected result: {:?}", other)
+    }
+    assert_eq!(2, cur.position());
+}
+
+#[test]
+fn from_bin8_read_bin_eof() {
+    let buf: &[u8] = &[0xc4, 0x03, 0x01];
+    let mut cur = Cursor::new(buf);
+    let mut out = [0u8; 8];
+
+    match read_bin(&mut cur, &mut out) {
+        Err(DecodeBinError::InvalidDataRead(..)) => (),
+        other => panic!("unexpected result: {:?}", other)
+    }
+}
+
+#[test]
+fn from_str_read_bin() {
+    let buf: &[u8] = &[0xa1, 0x61];
+    let mut cur = Cursor::new(buf);
+    let mut out = [0u8; 8];
+
+    match read_bin(&mut cur, &mut out) {
+        Err(DecodeBinError::TypeMismatch(Marker::FixStr(1))) => (),
+        other => panic!("unexpected result: {:?}", other)
+    }
+}
@@ -0,0 +1,140 @@
+use msgpack::decode::*;
+
+#[test]
+fn from_array_read_elements_within_bounds() {
+    // [1, "ab", [nil, 300]] followed by `true`.
+    let buf: &[u8] = &[0x93, 0x01, 0xa2, 0x61, 0x62, 0x92, 0xc0, 0xcd, 0x01, 0x2c, 0xc3];
+    let mut rd = buf;
+
+    let len = read_array_len(&mut rd).unwrap();
+    {
+        let mut guard = Bounded::array(&mut rd, len);
+        assert_eq!(1, read_int::<u8, _>(&mut guard).unwrap());
+
+        let mut out = [0; 2];
+        assert_eq!("ab", read_str(&mut guard, &mut out).unwrap());
+
+        assert_eq!(2, read_array_len(&mut guard).unwrap());
+        read_nil(&mut guard).unwrap();
+        assert!(!guard.is_finished());
+        assert_eq!(300, read_int::<u16, _>(&mut guard).unwrap());
+
+        assert!(guard.is_finished());
+        guard.finish().unwrap();
+    }
+
+    assert_eq!(&[0xc3], rd);
+}
+
+#[test]
+fn from_array_fail_reading_past_bounds() {
+    let buf: &[u8] = &[0x91, 0x01, 0x02];
+    let mut rd = buf;
+
+    let len = read_array_len(&mut rd).unwrap();
+    let mut guard = Bounded::array(&mut rd, len);
+    assert_eq!(1, read_pfix(&mut guard).unwrap());
+
+    match read_pfix(&mut guard) {
+        Err(ValueReadError::InvalidMarkerRead(BoundedReadError::OutOfBounds)) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn from_map_with_nested_lengths_read_within_bounds() {
+    // {"k": array16 [1, 2]} followed by `nil`.
+    let buf: &[u8] = &[0x81, 0xa1, 0x6b, 0xdc, 0x00, 0x02, 0x01, 0x02, 0xc0];
+    let mut rd = buf;
+
+    let len = read_map_len(&mut rd).unwrap();
+    {
+        let mut guard = Bounded::map(&mut rd, len);
+        assert_eq!(1, read_str_len(&mut guard).unwrap());
+        assert_eq!(0x6b, read_data_u8(&mut guard).unwrap());
+        assert_eq!(2, read_array_len(&mut guard).unwrap());
+        assert_eq!(1, read_pfix(&mut guard).unwrap());
+        assert_eq!(2, read_pfix(&mut guard).unwrap());
+        assert!(read_nil(&mut guard).is_err());
+        guard.finish().unwrap();
+    }
+
+    assert_eq!(&[0xc0], rd);
+}
+
+#[test]
+fn from_misaligned_data_fail_reading_past_bounds() {
+    // A decoder taking the u16 for a u32 tries to read the following value as data.
+    let buf: &[u8] = &[0x91, 0xcd, 0x01, 0x2c, 0xc3, 0xc3];
+    let mut rd = buf;
+
+    let len = read_array_len(&mut rd).unwrap();
+    let mut guard = Bounded::array(&mut rd, len);
+    read_marker(&mut guard).unwrap();
+
+    match read_data_u32(&mut guard) {
+        Err(ValueReadError::InvalidDataRead(BoundedReadError::OutOfBounds)) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn from_ext_value_read_within_bounds() {
+    let buf: &[u8] = &[0xc7, 0x02, 0x05, 0xaa, 0xbb, 0xc3];
+    let mut rd = buf;
+
+    {
+        let mut guard = Bounded::value(&mut rd);
+        assert_eq!(ExtMeta { typeid: 5, size: 2 }, read_ext_meta(&mut guard).unwrap());
+
+        let mut data = [0; 2];
+        guard.read_exact_buf(&mut data).unwrap();
+        assert_eq!([0xaa, 0xbb], data);
+        assert!(guard.read_u8().is_err());
+        guard.finish().unwrap();
+    }
+
+    assert_eq!(&[0xc3], rd);
+}
+
+#[test]
+fn from_bin_data_fail_reading_past_bounds() {
+    let buf: &[u8] = &[0xc4, 0x02, 0x01, 0x02, 0x03];
+    let mut rd = buf;
+
+    let len = read_bin_len(&mut rd).unwrap();
+    let mut guard = Bounded::bytes(&mut rd, len);
+
+    let mut data = [0; 3];
+    match guard.read_exact_buf(&mut data) {
+        Err(BoundedReadError::OutOfBounds) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn from_array_fail_finish_early() {
+    let buf: &[u8] = &[0x92, 0x01, 0x02];
+    let mut rd = buf;
+
+    let len = read_array_len(&mut rd).unwrap();
+    let mut guard = Bounded::array(&mut rd, len);
+    assert_eq!(1, read_pfix(&mut guard).unwrap());
+
+    match guard.finish() {
+        Err(BoundedReadError::Unfinished) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn from_truncated_value_fail_with_read_error() {
+    let buf: &[u8] = &[0xcd, 0x01];
+    let mut rd = buf;
+
+    let mut guard = Bounded::value(&mut rd);
+    match read_int::<u16, _>(&mut guard) {
+        Err(NumValueReadError::InvalidDataRead(BoundedReadError::InvalidRead(..))) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
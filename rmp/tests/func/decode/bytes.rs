@@ -0,0 +1,46 @@
+use std::io;
+
+use msgpack::decode::*;
+
+#[test]
+fn from_bytes_tracks_position() {
+    let buf = [0xcd, 0x01, 0x2c, 0xa2, 0x6f, 0x6b, 0xc3];
+    let mut rd = Bytes::new(&buf[..]);
+
+    assert_eq!(300, read_u16(&mut rd).unwrap());
+    assert_eq!(3, rd.position());
+
+    let mut out = [0u8; 2];
+    assert_eq!("ok", read_str(&mut rd, &mut out).unwrap());
+    assert_eq!(6, rd.position());
+
+    assert_eq!(&[0xc3], rd.remaining_slice());
+    assert!(read_bool(&mut rd).unwrap());
+}
+
+#[test]
+fn from_bytes_truncated() {
+    let buf = [0xcf, 0x00, 0x00, 0x00, 0x01];
+    let mut rd = Bytes::new(&buf[..]);
+
+    match read_u64(&mut rd) {
+        Err(ValueReadError::InvalidDataRead(err)) => {
+            assert_eq!(BytesReadError::InsufficientBytes { expected: 8, actual: 4, position: 1 },
+                       err);
+
+            let err: io::Error = err.into();
+            assert_eq!(io::ErrorKind::UnexpectedEof, err.kind());
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn from_bytes_empty() {
+    let mut rd = Bytes::from(&[][..]);
+
+    match read_nil(&mut rd) {
+        Err(ValueReadError::InvalidMarkerRead(BytesReadError::InsufficientBytes { expected: 1, actual: 0, position: 0 })) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
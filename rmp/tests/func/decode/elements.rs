@@ -0,0 +1,195 @@
+use msgpack::Marker;
+use msgpack::decode::*;
+
+#[test]
+fn from_array_yield_elements() {
+    // [1, "ab", [nil, {1: 2}], 3.0f32] followed by `false`.
+    let buf: &[u8] = &[
+        0x94, 0x01, 0xa2, 0x61, 0x62, 0x92, 0xc0, 0x81, 0x01, 0x02, 0xca, 0x40, 0x40, 0x00, 0x00,
+        0xc2
+    ];
+
+    let mut elements = read_array_elements(buf).unwrap();
+    assert_eq!(4, elements.remaining());
+
+    assert_eq!(&[0x01], elements.next().unwrap().unwrap());
+    assert_eq!(&[0xa2, 0x61, 0x62], elements.next().unwrap().unwrap());
+    assert_eq!(&[0x92, 0xc0, 0x81, 0x01, 0x02], elements.next().unwrap().unwrap());
+    assert_eq!(1, elements.remaining());
+
+    let mut rd = elements.next().unwrap().unwrap();
+    assert_eq!(3.0, read_f32(&mut rd).unwrap());
+    assert!(rd.is_empty());
+
+    assert!(elements.next().is_none());
+    assert_eq!(&[0xc2], elements.tail());
+}
+
+#[test]
+fn from_empty_array_yield_nothing() {
+    let buf: &[u8] = &[0x90, 0xc0];
+
+    let mut elements = read_array_elements(buf).unwrap();
+    assert!(elements.next().is_none());
+    assert_eq!(&[0xc0], elements.tail());
+}
+
+#[test]
+fn from_array_element_reader_does_not_over_read() {
+    // [[nil], 42]: reading past the first element must not reach the second one.
+    let buf: &[u8] = &[0x92, 0x91, 0xc0, 0x2a];
+
+    let mut elements = read_array_elements(buf).unwrap();
+    let mut rd = elements.next().unwrap().unwrap();
+    assert_eq!(1, read_array_len(&mut rd).unwrap());
+    read_nil(&mut rd).unwrap();
+    assert!(read_int::<u8, _>(&mut rd).is_err());
+
+    assert_eq!(&[0x2a], elements.next().unwrap().unwrap());
+}
+
+#[test]
+fn from_array_with_ext_yield_elements() {
+    let buf: &[u8] = &[0x92, 0xd4, 0x01, 0x02, 0xc7, 0x02, 0x05, 0xaa, 0xbb];
+
+    let elements = read_array_elements(buf).unwrap().collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(vec![&[0xd4, 0x01, 0x02][..], &[0xc7, 0x02, 0x05, 0xaa, 0xbb][..]], elements);
+}
+
+#[test]
+fn from_truncated_array_stop_after_error() {
+    // [1, "abc"] with the string data cut short.
+    let buf: &[u8] = &[0x93, 0x01, 0xa3, 0x61];
+
+    let mut elements = read_array_elements(buf).unwrap();
+    assert_eq!(&[0x01], elements.next().unwrap().unwrap());
+
+    match elements.next() {
+        Some(Err(ValueReadError::InvalidDataRead(..))) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+    assert!(elements.next().is_none());
+    assert_eq!(&[0xa3, 0x61], elements.tail());
+}
+
+#[test]
+fn from_nested_truncated_array_stop_after_error() {
+    let buf: &[u8] = &[0x91, 0x92, 0x01];
+
+    let mut elements = read_array_elements(buf).unwrap();
+    match elements.next() {
+        Some(Err(ValueReadError::InvalidMarkerRead(..))) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+    assert!(elements.next().is_none());
+}
+
+#[test]
+fn from_non_array_read_elements_fails() {
+    let buf: &[u8] = &[0x80];
+
+    match read_array_elements(buf) {
+        Err(ValueReadError::TypeMismatch(Marker::FixMap(0))) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn from_array_after_read_len_yield_elements() {
+    let buf: &[u8] = &[0xdc, 0x00, 0x02, 0xc3, 0xc2];
+    let mut rd = buf;
+
+    let len = read_array_len(&mut rd).unwrap();
+    let elements = Elements::new(rd, len)
+        .map(|elem| read_bool(&mut elem.unwrap()).unwrap())
+        .collect::<Vec<_>>();
+
+    assert_eq!(vec![true, false], elements);
+}
+
+#[test]
+fn from_map_yield_entries() {
+    // {"a": [1, 2], 3: nil} followed by `true`.
+    let buf: &[u8] = &[0x82, 0xa1, 0x61, 0x92, 0x01, 0x02, 0x03, 0xc0, 0xc3];
+
+    let mut entries = read_map_elements(buf).unwrap();
+    assert_eq!(2, entries.remaining());
+
+    assert_eq!((&[0xa1, 0x61][..], &[0x92, 0x01, 0x02][..]), entries.next().unwrap().unwrap());
+    assert_eq!((&[0x03][..], &[0xc0][..]), entries.next().unwrap().unwrap());
+    assert!(entries.next().is_none());
+    assert_eq!(0, entries.remaining());
+    assert_eq!(&[0xc3], entries.tail());
+}
+
+#[test]
+fn from_map_with_missing_value_stop_after_error() {
+    let buf: &[u8] = &[0x81, 0x01];
+
+    let mut entries = read_map_elements(buf).unwrap();
+    match entries.next() {
+        Some(Err(ValueReadError::InvalidMarkerRead(..))) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+    assert!(entries.next().is_none());
+    assert_eq!(&[0x01], entries.tail());
+}
+
+#[test]
+fn from_map_after_read_len_yield_entries() {
+    let buf: &[u8] = &[0xde, 0x00, 0x01, 0x01, 0x02];
+    let mut rd = buf;
+
+    let len = read_map_len(&mut rd).unwrap();
+    let mut entries = Entries::new(rd, len);
+
+    let (mut key, mut val) = entries.next().unwrap().unwrap();
+    assert_eq!(1, read_int::<u8, _>(&mut key).unwrap());
+    assert_eq!(2, read_int::<u8, _>(&mut val).unwrap());
+    assert!(entries.next().is_none());
+}
+
+#[test]
+fn from_concatenated_values_yield_object_bounds() {
+    // {"a": 1}, [2, 3], -1, bin [0x00]
+    let buf: &[u8] = &[0x81, 0xa1, 0x61, 0x01, 0x92, 0x02, 0x03, 0xff, 0xc4, 0x01, 0x00];
+    let mut objects = object_bounds(buf);
+
+    assert_eq!(0, objects.position());
+    assert_eq!(&[0x81, 0xa1, 0x61, 0x01], objects.next().unwrap().unwrap());
+    assert_eq!(4, objects.position());
+    assert_eq!(&[0x92, 0x02, 0x03], objects.next().unwrap().unwrap());
+    assert_eq!(&[0xff], objects.next().unwrap().unwrap());
+    assert_eq!(&[0xc4, 0x01, 0x00], objects.next().unwrap().unwrap());
+    assert!(objects.next().is_none());
+    assert_eq!(buf.len(), objects.position());
+}
+
+#[test]
+fn from_empty_buffer_yield_no_objects() {
+    assert!(object_bounds(&[]).next().is_none());
+}
+
+#[test]
+fn from_truncated_last_object_stop_after_error() {
+    // 1, "abc" missing its last byte
+    let buf: &[u8] = &[0x01, 0xa3, 0x61, 0x62];
+    let mut objects = object_bounds(buf);
+
+    assert_eq!(&[0x01], objects.next().unwrap().unwrap());
+    assert!(objects.next().unwrap().is_err());
+    assert!(objects.next().is_none());
+    assert_eq!(&[0xa3, 0x61, 0x62], objects.tail());
+}
+
+#[test]
+fn from_reserved_marker_stop_after_error() {
+    let mut objects = object_bounds(&[0x01, 0xc1, 0x02]);
+
+    assert_eq!(&[0x01], objects.next().unwrap().unwrap());
+    match objects.next() {
+        Some(Err(ValueReadError::TypeMismatch(Marker::Reserved))) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+    assert!(objects.next().is_none());
+}
@@ -1,5 +1,6 @@
 use std::io::Cursor;
 
+use msgpack::Marker;
 use msgpack::decode::*;
 
 #[test]
@@ -126,3 +127,76 @@ fn from_ext32_read_ext_meta() {
     assert_eq!(ExtMeta { typeid: 1, size: 4294967295 }, read_ext_meta(&mut cur).unwrap());
     assert_eq!(6, cur.position());
 }
+
+#[test]
+fn from_ext8_read_ext_header_in_chunks() {
+    let buf: &[u8] = &[0xc7, 0x05, 0x2a, 0x01, 0x02, 0x03, 0x04, 0x05, 0xc3];
+    let mut cur = Cursor::new(buf);
+
+    {
+        let mut payload = read_ext_header(&mut cur).unwrap();
+        assert_eq!(42, payload.typeid());
+        assert_eq!(5, payload.size());
+
+        let mut chunk = [0; 2];
+        assert_eq!(2, payload.read_chunk(&mut chunk).unwrap());
+        assert_eq!([0x01, 0x02], chunk);
+        assert_eq!(2, payload.read_chunk(&mut chunk).unwrap());
+        assert_eq!([0x03, 0x04], chunk);
+        assert_eq!(1, payload.read_chunk(&mut chunk).unwrap());
+        assert_eq!(0x05, chunk[0]);
+        assert_eq!(0, payload.read_chunk(&mut chunk).unwrap());
+        assert_eq!(0, payload.remaining());
+    }
+
+    assert_eq!(8, cur.position());
+}
+
+#[test]
+fn from_fixext4_read_ext_header_does_not_over_read() {
+    use std::io::Read;
+
+    let buf: &[u8] = &[0xd6, 0x01, 0x01, 0x02, 0x03, 0x04, 0xc0];
+    let mut cur = Cursor::new(buf);
+
+    let mut out = Vec::new();
+    read_ext_header(&mut cur).unwrap().read_to_end(&mut out).unwrap();
+
+    assert_eq!(vec![0x01, 0x02, 0x03, 0x04], out);
+    assert_eq!(6, cur.position());
+}
+
+#[test]
+fn from_ext16_read_ext_header_skip() {
+    let mut buf = vec![0xc8, 0x03, 0x00, 0x05];
+    buf.extend_from_slice(&[0xaa; 0x300]);
+    buf.push(0xc2);
+    let mut cur = Cursor::new(&buf[..]);
+
+    let payload = read_ext_header(&mut cur).unwrap();
+    assert_eq!(5, payload.typeid());
+    assert_eq!(0x300, payload.remaining());
+    payload.skip().unwrap();
+
+    assert_eq!(0x304, cur.position());
+}
+
+#[test]
+fn from_truncated_ext_read_ext_header_fails() {
+    let buf: &[u8] = &[0xd5, 0x01, 0x01];
+    let mut cur = Cursor::new(buf);
+
+    let mut payload = read_ext_header(&mut cur).unwrap();
+    assert!(payload.read_chunk(&mut [0; 4]).is_err());
+}
+
+#[test]
+fn from_bin_read_ext_header_fails() {
+    let buf: &[u8] = &[0xc4, 0x00];
+    let mut cur = Cursor::new(buf);
+
+    match read_ext_header(&mut cur) {
+        Err(ValueReadError::TypeMismatch(Marker::Bin8)) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
@@ -0,0 +1,95 @@
+use msgpack::Marker;
+use msgpack::decode::*;
+
+fn headers(buf: &[u8]) -> Vec<Header> {
+    let mut rd = buf;
+    let mut vec = Vec::new();
+    while !rd.is_empty() {
+        vec.push(read_header(&mut rd).unwrap());
+    }
+
+    vec
+}
+
+#[test]
+fn from_scalars_read_header() {
+    let buf = [
+        0xc0, 0xc3, 0xc2, 0x7f, 0xcc, 0x80, 0xcf, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+        0xe0, 0xd0, 0x05, 0xd1, 0x80, 0x00, 0xca, 0x3f, 0x80, 0x00, 0x00,
+        0xcb, 0x3f, 0xf0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ];
+
+    assert_eq!(vec![
+        Header::Nil,
+        Header::Bool(true),
+        Header::Bool(false),
+        Header::UInt(127),
+        Header::UInt(128),
+        Header::UInt(u64::MAX),
+        Header::SInt(-32),
+        Header::SInt(5),
+        Header::SInt(-32768),
+        Header::F32(1.0),
+        Header::F64(1.0),
+    ], headers(&buf));
+}
+
+#[test]
+fn from_lengths_read_header() {
+    let buf = [
+        0xa3, 0xd9, 0x20, 0xda, 0x01, 0x00, 0xdb, 0x00, 0x01, 0x00, 0x00,
+        0xc4, 0x02, 0xc5, 0x01, 0x00, 0xc6, 0x00, 0x01, 0x00, 0x00,
+        0x9f, 0xdc, 0x00, 0x10, 0xdd, 0x00, 0x01, 0x00, 0x00,
+        0x8f, 0xde, 0x00, 0x10, 0xdf, 0x00, 0x01, 0x00, 0x00,
+    ];
+
+    assert_eq!(vec![
+        Header::Str(3),
+        Header::Str(32),
+        Header::Str(256),
+        Header::Str(65536),
+        Header::Bin(2),
+        Header::Bin(256),
+        Header::Bin(65536),
+        Header::Array(15),
+        Header::Array(16),
+        Header::Array(65536),
+        Header::Map(15),
+        Header::Map(16),
+        Header::Map(65536),
+    ], headers(&buf));
+}
+
+#[test]
+fn from_ext_read_header() {
+    let buf = [
+        0xd4, 0x01, 0xd8, 0xff, 0xc7, 0x03, 0x2a, 0xc8, 0x01, 0x00, 0x02,
+        0xc9, 0x00, 0x01, 0x00, 0x00, 0x80,
+    ];
+
+    assert_eq!(vec![
+        Header::Ext(1, 1),
+        Header::Ext(-1, 16),
+        Header::Ext(42, 3),
+        Header::Ext(2, 256),
+        Header::Ext(-128, 65536),
+    ], headers(&buf));
+}
+
+#[test]
+fn from_reserved_read_header() {
+    let mut rd = &[0xc1][..];
+    match read_header(&mut rd) {
+        Err(ValueReadError::TypeMismatch(Marker::Reserved)) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn from_truncated_read_header() {
+    let mut rd = &[0xc9, 0x00, 0x00, 0x01, 0x00][..];
+    match read_header(&mut rd) {
+        Err(ValueReadError::InvalidDataRead(..)) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
@@ -0,0 +1,50 @@
+use msgpack::Marker;
+use msgpack::decode::*;
+
+#[test]
+fn from_peeked_marker_read_value() {
+    // [1, -2]
+    let buf: &[u8] = &[0x92, 0x01, 0xfe];
+    let mut rd = Lookahead::new(buf);
+
+    assert_eq!(Marker::FixArray(2), rd.peek_marker().unwrap());
+    assert!(rd.has_peeked());
+    assert_eq!(2, read_array_len(&mut rd).unwrap());
+    assert!(!rd.has_peeked());
+
+    assert_eq!(Marker::FixPos(1), rd.peek_marker().unwrap());
+    assert_eq!(1, read_pfix(&mut rd).unwrap());
+    assert_eq!(-2, read_nfix(&mut rd).unwrap());
+}
+
+#[test]
+fn from_peeked_marker_read_data_in_one_piece() {
+    let buf: &[u8] = &[0xcd, 0x01, 0x2c];
+    let mut rd = Lookahead::new(buf);
+
+    assert_eq!(Marker::U16, rd.peek_marker().unwrap());
+
+    let mut out = [0; 3];
+    rd.read_exact_buf(&mut out).unwrap();
+    assert_eq!([0xcd, 0x01, 0x2c], out);
+}
+
+#[test]
+fn from_empty_reader_peek_fails() {
+    let buf: &[u8] = &[];
+    let mut rd = Lookahead::new(buf);
+
+    assert!(rd.peek_marker().is_err());
+    assert!(!rd.has_peeked());
+}
+
+#[test]
+fn into_inner_returns_peeked_byte() {
+    let buf: &[u8] = &[0xc0, 0xc3];
+    let mut rd = Lookahead::new(buf);
+
+    rd.peek_marker().unwrap();
+    let (rest, peeked) = rd.into_inner();
+    assert_eq!(Some(0xc0), peeked);
+    assert_eq!(&[0xc3], rest);
+}
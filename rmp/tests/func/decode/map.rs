@@ -59,3 +59,104 @@ fn from_null_read_map_len() {
     }
     assert_eq!(1, cur.position());
 }
+
+#[test]
+fn from_map_entries_raw_bytes() {
+    // {1: "a", [2, 3]: {4: nil}}
+    let buf: &[u8] = &[0x82, 0x01, 0xa1, 0x61, 0x92, 0x02, 0x03, 0x81, 0x04, 0xc0];
+    let mut cur = Cursor::new(buf);
+
+    let mut entries = Vec::new();
+    let len = read_map_entries(&mut cur, |key, val| entries.push((key.to_vec(), val.to_vec())))
+        .unwrap();
+
+    assert_eq!(2, len);
+    assert_eq!(vec![(vec![0x01], vec![0xa1, 0x61]), (vec![0x92, 0x02, 0x03], vec![0x81, 0x04, 0xc0])],
+        entries);
+    assert_eq!(10, cur.position());
+}
+
+#[test]
+fn from_map_entries_type_mismatch() {
+    let buf: &[u8] = &[0x91, 0x01];
+    let mut cur = Cursor::new(buf);
+
+    match read_map_entries(&mut cur, |_, _| {}) {
+        Err(ValueReadError::TypeMismatch(Marker::FixArray(1))) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn from_map_entries_truncated_value() {
+    let buf: &[u8] = &[0x81, 0x01, 0xd9, 0x03, 0x61];
+    let mut cur = Cursor::new(buf);
+
+    match read_map_entries(&mut cur, |_, _| {}) {
+        Err(ValueReadError::InvalidDataRead(..)) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn from_map_entries_reserved_marker() {
+    // {1: [0xc1]}
+    let buf: &[u8] = &[0x81, 0x01, 0x91, 0xc1];
+    let mut cur = Cursor::new(buf);
+
+    match read_map_entries(&mut cur, |_, _| {}) {
+        Err(ValueReadError::TypeMismatch(Marker::Reserved)) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn from_unique_map_entries() {
+    let buf: &[u8] = &[0x83, 0xa1, 0x61, 0x01, 0xa1, 0x62, 0x01, 0xa1, 0x63, 0x01];
+    let mut cur = Cursor::new(buf);
+
+    let mut count = 0;
+    assert_eq!(3, read_unique_map_entries(&mut cur, |_, _| count += 1).unwrap());
+    assert_eq!(3, count);
+    assert_eq!(10, cur.position());
+}
+
+#[test]
+fn from_unique_map_entries_duplicate_key() {
+    // {[1]: 1, "a": 2, [1]: 3}
+    let buf: &[u8] = &[0x83, 0x91, 0x01, 0x01, 0xa1, 0x61, 0x02, 0x91, 0x01, 0x03];
+    let mut cur = Cursor::new(buf);
+
+    let mut count = 0;
+    match read_unique_map_entries(&mut cur, |_, _| count += 1) {
+        Err(MapReadError::DuplicateKey(2)) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+    assert_eq!(2, count);
+}
+
+#[test]
+fn from_map_of_string_to_f64() {
+    // {"pi": 3.14}
+    let buf: &[u8] = &[0x81, 0xa2, 0x70, 0x69, 0xcb, 0x40, 0x09, 0x1e, 0xb8, 0x51, 0xeb, 0x85, 0x1f];
+    let mut cur = Cursor::new(buf);
+
+    assert_eq!(vec![("pi".to_string(), 3.14)], read_map_of::<String, f64, _>(&mut cur).unwrap());
+    assert_eq!(13, cur.position());
+}
+
+#[test]
+fn from_map_with_entries() {
+    // {1: [true], 2: []}
+    let buf: &[u8] = &[0x82, 0x01, 0x91, 0xc3, 0x02, 0x90];
+    let mut cur = Cursor::new(buf);
+
+    let res: Result<Vec<(u8, usize)>, TypedReadError> = read_map_with(&mut cur, |rd| {
+        let key = u8::read_from(rd)?;
+        let val = Vec::<bool>::read_from(rd)?;
+        Ok((key, val.len()))
+    });
+
+    assert_eq!(vec![(1, 1), (2, 0)], res.unwrap());
+    assert_eq!(6, cur.position());
+}
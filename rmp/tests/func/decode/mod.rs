@@ -8,3 +8,9 @@ mod bin;
 mod array;
 mod map;
 mod ext;
+mod bytes;
+mod bounded;
+mod elements;
+mod header;
+mod lookahead;
+mod skip;
@@ -0,0 +1,88 @@
+use std::io::Cursor;
+
+use msgpack::Marker;
+use msgpack::decode::*;
+
+#[test]
+fn from_scalars_skip_each() {
+    // nil, 300, -129, 1.5f32, "abc", bin [1, 2], fixext1
+    let buf: &[u8] = &[0xc0, 0xcd, 0x01, 0x2c, 0xd1, 0xff, 0x7f, 0xca, 0x3f, 0xc0, 0x00, 0x00,
+                       0xa3, 0x61, 0x62, 0x63, 0xc4, 0x02, 0x01, 0x02, 0xd4, 0x01, 0x2a, 0xc3];
+    let mut rd = buf;
+
+    for _ in 0..7 {
+        skip_value(&mut rd).unwrap();
+    }
+    assert_eq!(&[0xc3], rd);
+}
+
+#[test]
+fn from_nested_containers_skip_whole_value() {
+    // {"a": [1, {"b": [[], "c"]}], 2: bin8 of 300 bytes} followed by 5.
+    let mut buf = vec![0x82, 0xa1, 0x61, 0x92, 0x01, 0x81, 0xa1, 0x62, 0x92, 0x90, 0xa1, 0x63,
+                       0x02, 0xc5, 0x01, 0x2c];
+    buf.extend_from_slice(&[0; 300]);
+    buf.push(0x05);
+
+    let mut cur = Cursor::new(&buf[..]);
+    skip_value(&mut cur).unwrap();
+    assert_eq!(buf.len() as u64 - 1, cur.position());
+    assert_eq!(5, read_pfix(&mut cur).unwrap());
+}
+
+#[test]
+fn from_deeply_nested_arrays_skip_without_recursion() {
+    let mut buf = vec![0x91; 100_000];
+    buf.push(0xc0);
+    let mut rd = &buf[..];
+
+    skip_value(&mut rd).unwrap();
+    assert!(rd.is_empty());
+}
+
+#[test]
+fn from_peeked_marker_skip_data() {
+    // [1, 2] followed by nil.
+    let buf: &[u8] = &[0x92, 0x01, 0x02, 0xc0];
+    let mut rd = buf;
+
+    let marker = read_marker(&mut rd).unwrap();
+    assert_eq!(Marker::FixArray(2), marker);
+    skip_data(&mut rd, marker).unwrap();
+    assert_eq!(&[0xc0], rd);
+}
+
+#[test]
+fn from_truncated_value_skip_fails() {
+    // [1, "ab
+    let buf: &[u8] = &[0x92, 0x01, 0xa3, 0x61, 0x62];
+    let mut rd = buf;
+
+    match skip_value(&mut rd) {
+        Err(ValueReadError::InvalidDataRead(..)) => (),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn from_truncated_array_skip_fails_on_marker() {
+    let buf: &[u8] = &[0x92, 0x01];
+    let mut rd = buf;
+
+    match skip_value(&mut rd) {
+        Err(ValueReadError::InvalidMarkerRead(..)) => (),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn from_reserved_marker_skip_fails() {
+    // [1, 0xc1]
+    let buf: &[u8] = &[0x92, 0x01, 0xc1];
+    let mut rd = buf;
+
+    match skip_value(&mut rd) {
+        Err(ValueReadError::TypeMismatch(Marker::Reserved)) => (),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
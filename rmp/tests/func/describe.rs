@@ -0,0 +1,66 @@
+use msgpack::{describe, describe_to_depth, Marker};
+use msgpack::encode::*;
+
+#[test]
+fn describe_scalar() {
+    let summary = describe(&[0xcd, 0x01, 0x2c, 0xc0]).unwrap();
+
+    assert_eq!(Marker::U16, summary.marker);
+    assert_eq!(3, summary.size);
+    assert!(summary.counts.is_empty());
+    assert_eq!(None, summary.largest_blob);
+}
+
+#[test]
+fn describe_nested_counts() {
+    // [[1, 2], {"a": [3]}, [[]]]
+    let buf = [0x93, 0x92, 0x01, 0x02, 0x81, 0xa1, 0x61, 0x91, 0x03, 0x91, 0x90];
+
+    let summary = describe(&buf).unwrap();
+
+    assert_eq!(Marker::FixArray(3), summary.marker);
+    assert_eq!(buf.len(), summary.size);
+    assert_eq!(vec![3, 4, 1], summary.counts);
+}
+
+#[test]
+fn describe_limits_depth() {
+    let buf = [0x91, 0x91, 0x91, 0x91, 0xc0];
+
+    let summary = describe_to_depth(&buf, 2).unwrap();
+
+    assert_eq!(vec![1, 1], summary.counts);
+    assert_eq!(buf.len(), summary.size);
+}
+
+#[test]
+fn describe_largest_blob() {
+    let mut buf = Vec::new();
+    write_array_len(&mut buf, 3).unwrap();
+    write_str(&mut buf, "hello").unwrap();
+    write_ext_meta(&mut buf, 300, 1).unwrap();
+    buf.extend_from_slice(&[0; 300]);
+    write_bin(&mut buf, &[0; 200]).unwrap();
+
+    let summary = describe(&buf).unwrap();
+
+    let blob = summary.largest_blob.unwrap();
+    assert_eq!(Marker::Ext16, blob.marker);
+    assert_eq!(7, blob.offset);
+    assert_eq!(300, blob.len);
+    assert_eq!(buf.len(), summary.size);
+}
+
+#[test]
+fn describe_ignores_trailing_bytes() {
+    let summary = describe(&[0x92, 0x01, 0x02, 0xc0, 0xc0]).unwrap();
+
+    assert_eq!(3, summary.size);
+}
+
+#[test]
+fn describe_truncated_fails() {
+    assert!(describe(&[0x92, 0x01]).is_err());
+    assert!(describe(&[0xa5, 0x68, 0x65]).is_err());
+    assert!(describe(&[]).is_err());
+}
@@ -0,0 +1,65 @@
+use msgpack::encode::*;
+
+#[test]
+fn pass_try_write_fits() {
+    let mut buf = [0x00; 4];
+
+    assert_eq!(Ok(3), try_write_uint(&mut buf, 300));
+
+    assert_eq!([0xcd, 0x01, 0x2c, 0x00], buf);
+}
+
+#[test]
+fn pass_try_write_exact() {
+    let mut buf = [0x00; 1];
+
+    assert_eq!(Ok(1), try_write_nil(&mut buf));
+
+    assert_eq!([0xc0], buf);
+}
+
+#[test]
+fn fail_try_write_reports_needed() {
+    let mut buf = [0x00; 2];
+
+    assert_eq!(9, try_write_f64(&mut buf, 3.14).unwrap_err().needed());
+    assert_eq!(5, try_write_sint(&mut buf, -100000).unwrap_err().needed());
+    assert_eq!(3 + 300, try_write_bin(&mut buf, &[0; 300]).unwrap_err().needed());
+    assert_eq!(6, try_write_ext_meta(&mut buf, 65536, 1).unwrap_err().needed());
+    assert_eq!(1, try_write_bool(&mut [], true).unwrap_err().needed());
+}
+
+#[test]
+fn pass_try_write_retry_with_needed_size() {
+    let data = "a".repeat(40);
+    let mut buf = vec![0x00; 8];
+
+    let needed = try_write_str(&mut buf, &data).unwrap_err().needed();
+    assert_eq!(2 + 40, needed);
+
+    buf.resize(needed, 0x00);
+    assert_eq!(Ok(needed), try_write_str(&mut buf, &data));
+    assert_eq!([0xd9, 0x28, 0x61], buf[..3]);
+}
+
+#[test]
+fn pass_try_write_headers() {
+    let mut buf = [0x00; 5];
+
+    assert_eq!(Ok(3), try_write_array_len(&mut buf, 16));
+    assert_eq!([0xdc, 0x00, 0x10], buf[..3]);
+    assert_eq!(Ok(5), try_write_map_len(&mut buf, 65536));
+    assert_eq!([0xdf, 0x00, 0x01, 0x00, 0x00], buf);
+}
+
+#[test]
+fn pass_counted_empty_slice_only_counts() {
+    let mut wr = Counted::new(&mut []);
+
+    write_array_len(&mut wr, 2).unwrap();
+    write_str(&mut wr, "spam").unwrap();
+    write_uint(&mut wr, 300).unwrap();
+
+    assert_eq!(9, wr.count());
+    assert!(!wr.fits());
+}
@@ -73,3 +73,12 @@ fn pass_pack_meta_32() {
 
     assert_eq!([0xc9, 0xff, 0xff, 0xff, 0xff, 0x10], buf);
 }
+
+#[test]
+fn pass_pack_meta_reserved_type() {
+    let mut buf = [0x00, 0x00];
+
+    assert_eq!(Marker::FixExt4, write_ext_meta(&mut &mut buf[..], 4, -1).unwrap());
+
+    assert_eq!([0xd6, 0xff], buf);
+}
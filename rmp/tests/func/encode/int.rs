@@ -43,6 +43,22 @@ fn pass_pack_u16() {
     assert_eq!([0xcd, 0xff, 0xff], buf);
 }
 
+#[test]
+fn fail_pack_u16_reports_the_part_not_written() {
+    let mut buf = [];
+    match write_u16(&mut &mut buf[..], 300) {
+        Err(ValueWriteError::InvalidMarkerWrite(..)) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    let mut buf = [0x00, 0x00];
+    match write_u16(&mut &mut buf[..], 300) {
+        Err(ValueWriteError::InvalidDataWrite(..)) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+    assert_eq!([0xcd, 0x01], buf);
+}
+
 #[test]
 fn pass_pack_u32() {
     let mut buf = [0x00, 0x00, 0x00, 0x00, 0x00];
@@ -1,3 +1,10 @@
 mod encode;
 mod decode;
 mod mirror;
+mod compare;
+mod canonical;
+mod describe;
+mod parse;
+mod stream;
+mod checksum;
+mod timestamp;
@@ -0,0 +1,73 @@
+use msgpack::parse::{Event, ParseError, Parser};
+
+fn events(parser: &mut Parser) -> Vec<String> {
+    let mut events = Vec::new();
+    while let Some(event) = parser.next_event().unwrap() {
+        events.push(format!("{:?}", event));
+    }
+    events
+}
+
+#[test]
+fn parse_scalars() {
+    let mut parser = Parser::new();
+    parser.feed(&[0xc0, 0xc3, 0xcd, 0x01, 0x2c, 0xd0, 0x80, 0xca, 0x3f, 0x80, 0x00, 0x00]);
+
+    assert_eq!(vec!["Nil", "Bool(true)", "UInt(300)", "Int(-128)", "F32(1.0)"],
+               events(&mut parser));
+    assert_eq!(0, parser.buffered());
+}
+
+#[test]
+fn parse_empty_containers() {
+    let mut parser = Parser::new();
+    parser.feed(&[0x91, 0x80]);
+
+    assert_eq!(vec!["ArrayStart(1)", "MapStart(0)", "MapEnd", "ArrayEnd"], events(&mut parser));
+    assert_eq!(0, parser.depth());
+}
+
+#[test]
+fn parse_byte_by_byte() {
+    // [bin [1, 2], ext(5, [3])]
+    let buf = [0x92, 0xc4, 0x02, 0x01, 0x02, 0xd4, 0x05, 0x03];
+
+    let mut parser = Parser::new();
+    let mut all = Vec::new();
+    for &byte in buf.iter() {
+        parser.feed(&[byte]);
+        all.extend(events(&mut parser));
+    }
+
+    assert_eq!(vec!["ArrayStart(2)", "Bin([1, 2])", "Ext(5, [3])", "ArrayEnd"], all);
+}
+
+#[test]
+fn parse_reports_needed_bytes() {
+    let mut parser = Parser::new();
+    assert_eq!(None, parser.next_event().unwrap());
+    assert_eq!(1, parser.needed());
+
+    // str8 of 5 bytes, with only the marker available.
+    parser.feed(&[0xd9]);
+    assert_eq!(None, parser.next_event().unwrap());
+    assert_eq!(1, parser.needed());
+
+    parser.feed(&[0x05, b'h', b'e']);
+    assert_eq!(None, parser.next_event().unwrap());
+    assert_eq!(3, parser.needed());
+
+    parser.feed(b"llo");
+    assert_eq!(Some(Event::Str(b"hello")), parser.next_event().unwrap());
+    assert_eq!(0, parser.needed());
+}
+
+#[test]
+fn parse_fail_on_reserved_marker() {
+    let mut parser = Parser::new();
+    parser.feed(&[0x91, 0xc1]);
+
+    assert_eq!(Some(Event::ArrayStart(1)), parser.next_event().unwrap());
+    assert_eq!(Err(ParseError::ReservedMarker), parser.next_event());
+    assert_eq!(Err(ParseError::ReservedMarker), parser.next_event());
+}
@@ -0,0 +1,131 @@
+use std::io::{self, ErrorKind, Read};
+
+use msgpack::stream::{LogReader, LogWriter, SYNC_TYPE};
+
+/// A reader returning at most one byte per call.
+struct Trickle<'a>(&'a [u8]);
+
+impl<'a> Read for Trickle<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        (&mut self.0).take(1).read(buf)
+    }
+}
+
+fn objects<R: Read>(log: &mut LogReader<R>) -> Vec<Vec<u8>> {
+    let mut objs = Vec::new();
+    while let Some(obj) = log.next_object().unwrap() {
+        objs.push(obj);
+    }
+    objs
+}
+
+fn write_log(objs: &[&[u8]], interval: u32) -> Vec<u8> {
+    let mut log = LogWriter::with_sync_interval(Vec::new(), interval).unwrap();
+    for obj in objs {
+        log.append(obj).unwrap();
+    }
+    log.into_inner()
+}
+
+const OBJS: [&[u8]; 5] = [
+    &[0x01], &[0xa2, 0x68, 0x69], &[0x92, 0xc0, 0xc3], &[0x80], &[0xcc, 0xff],
+];
+
+#[test]
+fn pass_log_layout() {
+    let buf = write_log(&[&[0x2a]], 64);
+
+    // A sync marker, then 42 and its CRC-32 as a uint 32.
+    assert_eq!(18 + 1 + 5, buf.len());
+    assert_eq!(&[0xd8, SYNC_TYPE as u8], &buf[..2]);
+    assert_eq!(&[0x2a, 0xce, 0x09, 0xb9, 0x26, 0x5b], &buf[18..]);
+}
+
+#[test]
+fn pass_log_round_trip() {
+    let buf = write_log(&OBJS, 2);
+    // A sync marker starts the log and precedes the third and fifth objects.
+    let objs_len: usize = OBJS.iter().map(|obj| obj.len() + 5).sum();
+    assert_eq!(3 * 18 + objs_len, buf.len());
+
+    let mut log = LogReader::new(&buf[..]);
+    assert_eq!(OBJS.to_vec(), objects(&mut log));
+    assert_eq!(0, log.skipped());
+
+    let mut log = LogReader::new(Trickle(&buf));
+    assert_eq!(OBJS.to_vec(), objects(&mut log));
+    assert_eq!(0, log.skipped());
+}
+
+#[test]
+fn pass_log_empty() {
+    assert_eq!(None, LogReader::new(&[][..]).next_object().unwrap());
+
+    let buf = write_log(&[], 64);
+    assert_eq!(18, buf.len());
+    let mut log = LogReader::new(&buf[..]);
+    assert_eq!(None, log.next_object().unwrap());
+    assert_eq!(0, log.skipped());
+}
+
+#[test]
+fn pass_log_skipping_to_next_sync_after_damage() {
+    let mut buf = write_log(&OBJS, 2);
+    // Damage the payload of the second object: the rest of its sync block is lost.
+    buf[18 + 6 + 1] ^= 0x01;
+
+    let mut log = LogReader::new(&buf[..]);
+    assert_eq!(vec![OBJS[0], OBJS[2], OBJS[3], OBJS[4]], objects(&mut log));
+    assert_eq!(8, log.skipped());
+
+    let mut log = LogReader::new(Trickle(&buf));
+    assert_eq!(vec![OBJS[0], OBJS[2], OBJS[3], OBJS[4]], objects(&mut log));
+    assert_eq!(8, log.skipped());
+}
+
+#[test]
+fn pass_log_with_damaged_length() {
+    let mut buf = write_log(&OBJS, 2);
+    // The second object now claims to be a bin 32 longer than the whole log.
+    buf[18 + 6] = 0xc6;
+
+    let mut log = LogReader::new(&buf[..]);
+    log.set_max_len(64);
+    assert_eq!(vec![OBJS[0], OBJS[2], OBJS[3], OBJS[4]], objects(&mut log));
+    assert_eq!(8, log.skipped());
+}
+
+#[test]
+fn pass_log_with_torn_tail() {
+    let full = write_log(&OBJS, 64);
+
+    for cut in 1..8 {
+        let buf = &full[..full.len() - cut];
+        let mut log = LogReader::new(buf);
+        assert_eq!(OBJS[..4].to_vec(), objects(&mut log));
+        assert_eq!(7 - cut as u64, log.skipped());
+    }
+
+    // Appending after the torn tail starts with a sync marker.
+    let mut log = LogWriter::new(full[..full.len() - 3].to_vec()).unwrap();
+    log.append(&[0xc2]).unwrap();
+    let buf = log.into_inner();
+
+    let mut log = LogReader::new(&buf[..]);
+    assert_eq!(vec![OBJS[0], OBJS[1], OBJS[2], OBJS[3], &[0xc2]], objects(&mut log));
+    assert_eq!(4, log.skipped());
+}
+
+#[test]
+fn fail_log_append_invalid_object() {
+    let mut log = LogWriter::new(Vec::new()).unwrap();
+    let sync = log.get_ref().clone();
+
+    for obj in &[&[][..], &[0x01, 0x02], &[0x92, 0x01], &[0xc1], &sync[..]] {
+        match log.append(obj) {
+            Err(ref err) if err.kind() == ErrorKind::InvalidInput => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+    assert_eq!(sync, log.into_inner());
+}
@@ -0,0 +1,28 @@
+use std::time::{Duration, UNIX_EPOCH};
+
+use msgpack::timestamp::Timestamp;
+
+#[test]
+fn encode_picks_the_most_compact_format() {
+    let mut buf = [0; 12];
+
+    let ts = Timestamp::new(0xffff_ffff, 0).unwrap();
+    assert_eq!([0xff, 0xff, 0xff, 0xff], ts.encode(&mut buf));
+
+    let ts = Timestamp::new(0x3_ffff_ffff, 999_999_999).unwrap();
+    assert_eq!([0xee, 0x6b, 0x27, 0xff, 0xff, 0xff, 0xff, 0xff], ts.encode(&mut buf));
+
+    let ts = Timestamp::new(-1, 500).unwrap();
+    assert_eq!([0, 0, 0x01, 0xf4, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff],
+               ts.encode(&mut buf));
+    assert_eq!(Some(ts), Timestamp::from_ext_data(ts.encode(&mut buf)));
+}
+
+#[test]
+fn system_time_before_the_epoch_rounds_seconds_down() {
+    let time = UNIX_EPOCH - Duration::new(1, 250);
+
+    let ts = Timestamp::from_system_time(time).unwrap();
+    assert_eq!((-2, 999_999_750), (ts.secs(), ts.nanos()));
+    assert_eq!(Some(time), ts.to_system_time());
+}
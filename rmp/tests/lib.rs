@@ -1,3 +1,4 @@
+#[macro_use]
 extern crate rmp as msgpack;
 
 #[cfg(test)]
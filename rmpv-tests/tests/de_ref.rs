@@ -11,7 +11,7 @@ use serde_bytes::ByteBuf;
 
 use rmpv::ValueRef;
 use rmpv::decode;
-use rmpv::ext::deserialize_from;
+use rmpv::ext::{deserialize_from, from_value_ref};
 
 /// Tests that a `ValueRef` is properly decoded from bytes using two different mechanisms: direct
 /// deserialization using `rmp::decode::read_value_ref` and using `serde`.
@@ -249,6 +249,16 @@ fn pass_enum_from_value() {
         deserialize_from(ValueRef::Array(vec![ValueRef::from(3), ValueRef::Array(vec![ValueRef::from("John"), ValueRef::from(42)])])).unwrap());
 }
 
+#[test]
+fn pass_ext() {
+    test_decode(&[0xd4, 0x2a, 0xcc], ValueRef::Ext(42, &[0xcc]));
+    test_decode(&[0xc7, 0x03, 0xff, 0x01, 0x02, 0x03], ValueRef::Ext(-1, &[1, 2, 3]));
+
+    let val = ValueRef::Ext(42, &[1, 2]);
+    assert_eq!(val, deserialize_from::<ValueRef, _>(val.clone()).unwrap());
+    assert_eq!(val, deserialize_from::<ValueRef, _>(&val).unwrap());
+}
+
 #[test]
 fn pass_from_slice() {
     let buf = [0x93, 0xa4, 0x4a, 0x6f, 0x68, 0x6e, 0xa5, 0x53, 0x6d, 0x69, 0x74, 0x68, 0x2a];
@@ -256,3 +266,23 @@ fn pass_from_slice() {
     assert_eq!(ValueRef::Array(vec![ValueRef::from("John"), ValueRef::from("Smith"), ValueRef::from(42)]),
         rmps::from_slice(&buf[..]).unwrap());
 }
+
+#[test]
+fn pass_struct_from_slice_by_value_ref() {
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct User<'a> {
+        name: &'a str,
+        key: &'a [u8],
+    }
+
+    // {"name": "John", "key": [1, 2]}
+    let buf = [0x82, 0xa4, 0x6e, 0x61, 0x6d, 0x65, 0xa4, 0x4a, 0x6f, 0x68, 0x6e,
+               0xa3, 0x6b, 0x65, 0x79, 0xc4, 0x02, 0x01, 0x02];
+    let val = decode::read_value_ref(&mut &buf[..]).unwrap();
+
+    let user: User = from_value_ref(val).unwrap();
+    assert_eq!(User { name: "John", key: &[1, 2] }, user);
+    // Both fields point into the buffer.
+    assert_eq!(&buf[7..11], user.name.as_bytes());
+    assert_eq!(buf[17..].as_ptr(), user.key.as_ptr());
+}
@@ -11,7 +11,7 @@ use serde_bytes::ByteBuf;
 
 use rmpv::Value;
 use rmpv::decode;
-use rmpv::ext::from_value;
+use rmpv::ext::{from_borrowed_value, from_value};
 
 /// Tests that a `Value` is properly decoded from bytes using two different mechanisms: direct
 /// deserialization using `rmp::decode::read_value` and using `serde`.
@@ -77,6 +77,14 @@ fn pass_bin() {
     test_decode(&[0xc4, 0x02, 0xcc, 0x80], Value::from(&[0xcc, 0x80][..]));
 }
 
+#[test]
+fn pass_ext() {
+    test_decode(&[0xd4, 0x2a, 0xcc], Value::Ext(42, vec![0xcc]));
+    test_decode(&[0xc7, 0x03, 0xff, 0x01, 0x02, 0x03], Value::Ext(-1, vec![1, 2, 3]));
+    test_decode(&[0x91, 0xd5, 0x01, 0x02, 0x03],
+                Value::Array(vec![Value::Ext(1, vec![2, 3])]));
+}
+
 #[test]
 fn pass_array() {
     test_decode(&[0x92, 0xa2, 0x6c, 0x65, 0xa4, 0x73, 0x68, 0x69, 0x74],
@@ -240,3 +248,82 @@ fn pass_enum_from_value() {
     assert_eq!(Enum::Struct { name: "John".into(), age: 42 },
         from_value(Value::Array(vec![Value::from(3), Value::Array(vec![Value::from("John"), Value::from(42)])])).unwrap());
 }
+
+#[test]
+fn pass_struct_from_borrowed_value() {
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct User<'a> {
+        name: &'a str,
+        key: &'a [u8],
+        tags: Vec<&'a str>,
+    }
+
+    let doc = Value::Map(vec![
+        (Value::from("users"), Value::Array(vec![
+            Value::Map(vec![
+                (Value::from("name"), Value::from("John")),
+                (Value::from("key"), Value::Binary(vec![1, 2])),
+                (Value::from("tags"), Value::Array(vec![Value::from("admin")])),
+            ]),
+        ])),
+        (Value::from("count"), Value::from(1)),
+    ]);
+
+    let users: Vec<User> = from_borrowed_value(&doc["users"]).unwrap();
+    assert_eq!(vec![User { name: "John", key: &[1, 2], tags: vec!["admin"] }], users);
+    assert_eq!(1u32, from_borrowed_value(&doc["count"]).unwrap());
+
+    // The document is left intact.
+    assert_eq!(Some(&Value::from(1)), doc.get("count"));
+}
+
+#[test]
+fn pass_enum_from_borrowed_value() {
+    #[derive(Debug, PartialEq, Deserialize)]
+    enum Enum<'a> {
+        Unit,
+        Newtype(&'a str),
+        Struct { name: &'a str, age: u32 },
+    }
+
+    let val = Value::Array(vec![Value::from(0), Value::Array(vec![])]);
+    assert_eq!(Enum::Unit, from_borrowed_value(&val).unwrap());
+    let val = Value::Array(vec![Value::from(1), Value::Array(vec![Value::from("John")])]);
+    assert_eq!(Enum::Newtype("John"), from_borrowed_value(&val).unwrap());
+    let val = Value::Array(vec![Value::from(2),
+                                Value::Array(vec![Value::from("John"), Value::from(42)])]);
+    assert_eq!(Enum::Struct { name: "John", age: 42 }, from_borrowed_value(&val).unwrap());
+}
+
+#[test]
+fn pass_option_from_borrowed_value() {
+    assert_eq!(None::<&str>, from_borrowed_value(&Value::Nil).unwrap());
+    assert_eq!(Some("John"), from_borrowed_value(&Value::from("John")).unwrap());
+}
+
+
+#[test]
+fn pass_ext_from_value() {
+    #[derive(Debug, PartialEq, Deserialize)]
+    #[serde(rename = "_ExtStruct")]
+    struct Ext((i8, ByteBuf));
+
+    let val = Value::Ext(42, vec![1, 2]);
+    assert_eq!(val, from_value::<Value>(val.clone()).unwrap());
+    assert_eq!(val, from_borrowed_value::<Value>(&val).unwrap());
+    assert_eq!(Ext((42, ByteBuf::from(vec![1, 2]))), from_value(val.clone()).unwrap());
+    assert_eq!(Ext((42, ByteBuf::from(vec![1, 2]))), from_borrowed_value(&val).unwrap());
+
+    assert!(from_value::<Ext>(Value::from(42)).is_err());
+}
+
+#[test]
+fn pass_neovim_handles_from_value() {
+    use rmps::neovim::{Buffer, Window};
+
+    let val = Value::Array(vec![Value::Ext(0, vec![0x05]), Value::Ext(0, vec![0xcd, 0x03, 0xe8])]);
+    assert_eq!(vec![Buffer(5), Buffer(1000)], from_value::<Vec<Buffer>>(val.clone()).unwrap());
+    assert_eq!(vec![Buffer(5), Buffer(1000)], from_borrowed_value::<Vec<Buffer>>(&val).unwrap());
+
+    assert!(from_value::<Window>(Value::Ext(0, vec![0x05])).is_err());
+}
@@ -12,9 +12,9 @@ use serde::Serialize;
 use serde_bytes::{Bytes, ByteBuf};
 
 use rmps::Serializer;
-use rmpv::Value;
+use rmpv::{Value, ValueRef};
 use rmpv::encode;
-use rmpv::ext::to_value;
+use rmpv::ext::{from_value, to_value, to_value_named};
 
 /// Tests that a `Value` is properly encoded using two different mechanisms: direct serialization
 /// using `rmp::encode::write_value` and using `serde`.
@@ -88,6 +88,12 @@ fn pass_bin() {
     test_encode(Value::from(Cow::from(&[0xcc, 0x80][..])), &[0xc4, 0x02, 0xcc, 0x80]);
 }
 
+#[test]
+fn pass_ext() {
+    test_encode(Value::Ext(42, vec![0xcc]), &[0xd4, 0x2a, 0xcc]);
+    test_encode(Value::Ext(-1, vec![1, 2, 3]), &[0xc7, 0x03, 0xff, 0x01, 0x02, 0x03]);
+}
+
 #[test]
 fn pass_array() {
     test_encode(Value::Array(vec![Value::from("le"), Value::from("shit")]),
@@ -104,6 +110,24 @@ fn pass_value_map() {
     test_encode(val, &[0x82, 0x00, 0xa2, 0x6c, 0x65, 0x01, 0xa4, 0x73, 0x68, 0x69, 0x74]);
 }
 
+#[test]
+fn pass_value_ref() {
+    let val = ValueRef::Map(vec![
+        (ValueRef::from("name"), ValueRef::from("John")),
+        (ValueRef::from(-1), ValueRef::Array(vec![ValueRef::Nil, ValueRef::F64(0.5)])),
+        (ValueRef::Binary(&[0xcc]), ValueRef::Ext(42, &[0xcc])),
+    ]);
+
+    let mut buf0 = Vec::new();
+    encode::write_value_ref(&mut buf0, &val).unwrap();
+
+    let mut buf1 = Vec::new();
+    val.serialize(&mut Serializer::new(&mut buf1)).unwrap();
+    assert_eq!(buf0, buf1);
+
+    assert_eq!(val.to_owned(), to_value(&val).unwrap());
+}
+
 #[test]
 fn pass_uint_to_value() {
     assert_eq!(Value::from(i8::min_value()), to_value(i8::min_value()).unwrap());
@@ -155,6 +179,11 @@ fn pass_bin_to_value() {
     assert_eq!(Value::from(vec![0, 1, 2]), to_value(ByteBuf::from(&[0, 1, 2][..])).unwrap());
 }
 
+#[test]
+fn pass_ext_to_value() {
+    assert_eq!(Value::Ext(42, vec![1, 2]), to_value(Value::Ext(42, vec![1, 2])).unwrap());
+}
+
 #[test]
 fn pass_vec_to_value() {
     assert_eq!(Value::from(vec![Value::from("John"), Value::from("Smith")]),
@@ -249,3 +278,46 @@ fn pass_enum_to_value() {
     assert_eq!(Value::Array(vec![Value::from(3), Value::Array(vec![Value::from("John"), Value::from(42)])]),
         to_value(Enum::Struct { name: "John".into(), age: 42 }).unwrap());
 }
+
+#[test]
+fn pass_struct_to_value_named() {
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Inner {
+        id: u32,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Struct {
+        name: String,
+        inner: Vec<Inner>,
+    }
+
+    let val = Struct { name: "John".into(), inner: vec![Inner { id: 42 }] };
+    let expected = Value::Map(vec![
+        (Value::from("name"), Value::from("John")),
+        (Value::from("inner"), Value::Array(vec![
+            Value::Map(vec![(Value::from("id"), Value::from(42))]),
+        ])),
+    ]);
+
+    let named = to_value_named(&val).unwrap();
+    assert_eq!(expected, named);
+    assert_eq!(val, from_value(named).unwrap());
+}
+
+#[test]
+fn pass_enum_to_value_named() {
+    #[derive(Debug, PartialEq, Serialize)]
+    enum Enum {
+        Tuple(String, u32),
+        Struct { name: String, age: u32 },
+    }
+
+    assert_eq!(Value::Array(vec![Value::from(0), Value::Array(vec![Value::from("John"), Value::from(42)])]),
+        to_value_named(Enum::Tuple("John".into(), 42)).unwrap());
+    assert_eq!(Value::Array(vec![Value::from(1), Value::Map(vec![
+            (Value::from("name"), Value::from("John")),
+            (Value::from("age"), Value::from(42)),
+        ])]),
+        to_value_named(Enum::Struct { name: "John".into(), age: 42 }).unwrap());
+}
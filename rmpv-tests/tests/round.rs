@@ -112,3 +112,14 @@ fn pass_newtype_struct() {
 
     test_round(Newtype("John".into()), Value::Array(vec![Value::from("John")]));
 }
+
+#[test]
+fn pass_raw_value_through_value() {
+    use rmps::RawValue;
+
+    let raw = RawValue::from_value(&(1, "a")).unwrap();
+
+    let val = rmpv::ext::to_value(&raw).unwrap();
+    assert_eq!(Value::Array(vec![Value::from(&[0x92, 0x01, 0xa1, 0x61][..])]), val);
+    assert_eq!(raw, rmpv::ext::from_value::<RawValue>(val).unwrap());
+}
@@ -116,3 +116,40 @@ fn from_complex_read_value_ref_to_owned(b: &mut Bencher) {
     });
     b.bytes = buf.len() as u64;
 }
+
+/// Encodes 1000 records like `{"id": 1, "name": "user1", "active": true}`.
+fn records() -> Vec<u8> {
+    use rmpv::Value;
+
+    let records = (0..1000).map(|id| Value::Map(vec![
+        (Value::from("id"), Value::from(id)),
+        (Value::from("name"), Value::from(format!("user{}", id))),
+        (Value::from("active"), Value::from(true)),
+    ])).collect();
+
+    let mut buf = Vec::new();
+    rmpv::encode::write_value(&mut buf, &Value::Array(records)).unwrap();
+    buf
+}
+
+#[bench]
+fn from_records_read_value(b: &mut Bencher) {
+    let buf = records();
+
+    b.iter(|| {
+        let res = read_value(&mut &buf[..]).unwrap();
+        test::black_box(res);
+    });
+    b.bytes = buf.len() as u64;
+}
+
+#[bench]
+fn from_records_read_compact_value(b: &mut Bencher) {
+    let buf = records();
+
+    b.iter(|| {
+        let res = read_compact_value(&mut &buf[..]).unwrap();
+        test::black_box(res);
+    });
+    b.bytes = buf.len() as u64;
+}
@@ -0,0 +1,253 @@
+//! A value type storing short strings and binaries inline.
+//!
+//! Each string, binary and extension of a `Value` lives in its own heap allocation, even when it
+//! is a two-letter map key. `CompactValue` keeps payloads of up to `INLINE_CAP` bytes inside the
+//! value itself, so decoding a typical map-heavy message allocates only for its arrays, maps and
+//! longer payloads. It is also smaller than `Value`, which saves memory in large arrays.
+//!
+//! Compact values are read from slices with `decode::read_compact_value`, which copies short
+//! payloads from the input into the value, and written with `encode::write_compact_value`.
+//! Strings are stored as raw bytes and validated as UTF-8 when accessed with `as_str`.
+//!
+//! The `records` benchmarks of `rmpv` compare decoding into both types.
+//!
+//! # Examples
+//!
+//! ```
+//! use rmpv::Value;
+//! use rmpv::compact::CompactValue;
+//! use rmpv::decode::read_compact_value;
+//! use rmpv::encode::write_compact_value;
+//!
+//! // {"id": 42}
+//! let buf = [0x81, 0xa2, 0x69, 0x64, 0x2a];
+//! let val = read_compact_value(&mut &buf[..]).unwrap();
+//!
+//! if let CompactValue::Map(ref map) = val {
+//!     assert_eq!(Some("id"), map[0].0.as_str());
+//!     assert!(map[0].0.as_bytes().unwrap().is_inline());
+//! }
+//!
+//! let mut out = Vec::new();
+//! write_compact_value(&mut out, &val).unwrap();
+//! assert_eq!(buf[..], out[..]);
+//! assert_eq!(Value::Map(vec![(Value::from("id"), Value::from(42))]), Value::from(val));
+//! ```
+
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::{self, Debug, Formatter};
+use core::ops::Deref;
+use core::str;
+
+use {Integer, Utf8String, Value, ValueRef};
+
+/// The largest payload stored inline, chosen so that `SmallBytes` is as large as a `Vec`.
+pub const INLINE_CAP: usize = 22;
+
+#[derive(Clone)]
+enum Repr {
+    Inline(u8, [u8; INLINE_CAP]),
+    Heap(Box<[u8]>),
+}
+
+/// A byte buffer stored inline if it is at most `INLINE_CAP` bytes long.
+#[derive(Clone)]
+pub struct SmallBytes {
+    repr: Repr,
+}
+
+impl SmallBytes {
+    /// Copies the given bytes, allocating only if they don't fit inline.
+    pub fn new(data: &[u8]) -> SmallBytes {
+        if data.len() > INLINE_CAP {
+            return SmallBytes { repr: Repr::Heap(data.into()) };
+        }
+
+        let mut buf = [0; INLINE_CAP];
+        buf[..data.len()].copy_from_slice(data);
+        SmallBytes { repr: Repr::Inline(data.len() as u8, buf) }
+    }
+
+    /// Takes over the vector, or copies its content inline if it fits.
+    pub fn from_vec(data: Vec<u8>) -> SmallBytes {
+        if data.len() > INLINE_CAP {
+            SmallBytes { repr: Repr::Heap(data.into_boxed_slice()) }
+        } else {
+            SmallBytes::new(&data)
+        }
+    }
+
+    /// Returns `true` if the bytes are stored inline.
+    pub fn is_inline(&self) -> bool {
+        match self.repr {
+            Repr::Inline(..) => true,
+            Repr::Heap(..) => false,
+        }
+    }
+
+    /// Returns the bytes as a slice.
+    pub fn as_slice(&self) -> &[u8] {
+        match self.repr {
+            Repr::Inline(len, ref buf) => &buf[..len as usize],
+            Repr::Heap(ref buf) => buf,
+        }
+    }
+
+    /// Copies the bytes into a vector, or takes over the heap buffer.
+    pub fn into_vec(self) -> Vec<u8> {
+        match self.repr {
+            Repr::Inline(len, buf) => buf[..len as usize].to_vec(),
+            Repr::Heap(buf) => buf.into_vec(),
+        }
+    }
+}
+
+impl Deref for SmallBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.as_slice()
+    }
+}
+
+impl PartialEq for SmallBytes {
+    fn eq(&self, other: &SmallBytes) -> bool {
+        self.as_slice() == other.as_slice()
+    }
+}
+
+impl Debug for SmallBytes {
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), fmt::Error> {
+        Debug::fmt(self.as_slice(), fmt)
+    }
+}
+
+/// A MessagePack value whose short strings, binaries and extensions don't allocate.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CompactValue {
+    /// Nil represents nil.
+    Nil,
+    /// Boolean represents true or false.
+    Boolean(bool),
+    /// Integer represents an integer.
+    Integer(Integer),
+    /// A 32-bit floating point number.
+    F32(f32),
+    /// A 64-bit floating point number.
+    F64(f64),
+    /// The bytes of a string, which are valid UTF-8 unless decoded with `Utf8Policy::Preserve` or
+    /// `Utf8Policy::Lossy`.
+    String(SmallBytes),
+    /// Binary extending Raw type represents a byte array.
+    Binary(SmallBytes),
+    /// Array represents a sequence of objects.
+    Array(Vec<CompactValue>),
+    /// Map represents key-value pairs of objects.
+    Map(Vec<(CompactValue, CompactValue)>),
+    /// Extended implements Extension interface: represents a tuple of type information and a byte
+    /// array where type information is an integer whose meaning is defined by applications.
+    Ext(i8, SmallBytes),
+}
+
+impl CompactValue {
+    /// If the value is a string with valid UTF-8, returns it.
+    pub fn as_str(&self) -> Option<&str> {
+        match *self {
+            CompactValue::String(ref val) => str::from_utf8(val).ok(),
+            _ => None,
+        }
+    }
+
+    /// If the value is a string, a binary or an extension, returns its payload.
+    pub fn as_bytes(&self) -> Option<&SmallBytes> {
+        match *self {
+            CompactValue::String(ref val) |
+            CompactValue::Binary(ref val) |
+            CompactValue::Ext(_, ref val) => Some(val),
+            _ => None,
+        }
+    }
+}
+
+impl From<Value> for CompactValue {
+    fn from(val: Value) -> CompactValue {
+        match val {
+            Value::Nil => CompactValue::Nil,
+            Value::Boolean(val) => CompactValue::Boolean(val),
+            Value::Integer(val) => CompactValue::Integer(val),
+            Value::F32(val) => CompactValue::F32(val),
+            Value::F64(val) => CompactValue::F64(val),
+            Value::String(val) => CompactValue::String(SmallBytes::from_vec(val.into_bytes())),
+            Value::Binary(val) => CompactValue::Binary(SmallBytes::from_vec(val)),
+            Value::Array(vec) => {
+                CompactValue::Array(vec.into_iter().map(CompactValue::from).collect())
+            }
+            Value::Map(map) => {
+                let map = map.into_iter()
+                    .map(|(key, val)| (CompactValue::from(key), CompactValue::from(val)))
+                    .collect();
+                CompactValue::Map(map)
+            }
+            Value::Ext(ty, data) => CompactValue::Ext(ty, SmallBytes::from_vec(data)),
+        }
+    }
+}
+
+impl<'a> From<ValueRef<'a>> for CompactValue {
+    fn from(val: ValueRef<'a>) -> CompactValue {
+        match val {
+            ValueRef::Nil => CompactValue::Nil,
+            ValueRef::Boolean(val) => CompactValue::Boolean(val),
+            ValueRef::Integer(val) => CompactValue::Integer(val),
+            ValueRef::F32(val) => CompactValue::F32(val),
+            ValueRef::F64(val) => CompactValue::F64(val),
+            ValueRef::String(val) => CompactValue::String(SmallBytes::new(val.as_bytes())),
+            ValueRef::Binary(val) => CompactValue::Binary(SmallBytes::new(val)),
+            ValueRef::Array(vec) => {
+                CompactValue::Array(vec.into_iter().map(CompactValue::from).collect())
+            }
+            ValueRef::Map(map) => {
+                let map = map.into_iter()
+                    .map(|(key, val)| (CompactValue::from(key), CompactValue::from(val)))
+                    .collect();
+                CompactValue::Map(map)
+            }
+            ValueRef::Ext(ty, data) => CompactValue::Ext(ty, SmallBytes::new(data)),
+        }
+    }
+}
+
+impl From<CompactValue> for Value {
+    fn from(val: CompactValue) -> Value {
+        match val {
+            CompactValue::Nil => Value::Nil,
+            CompactValue::Boolean(val) => Value::Boolean(val),
+            CompactValue::Integer(val) => Value::Integer(val),
+            CompactValue::F32(val) => Value::F32(val),
+            CompactValue::F64(val) => Value::F64(val),
+            CompactValue::String(val) => Value::String(utf8_string(val.into_vec())),
+            CompactValue::Binary(val) => Value::Binary(val.into_vec()),
+            CompactValue::Array(vec) => Value::Array(vec.into_iter().map(Value::from).collect()),
+            CompactValue::Map(map) => {
+                let map = map.into_iter()
+                    .map(|(key, val)| (Value::from(key), Value::from(val)))
+                    .collect();
+                Value::Map(map)
+            }
+            CompactValue::Ext(ty, data) => Value::Ext(ty, data.into_vec()),
+        }
+    }
+}
+
+/// Wraps the bytes of a string, keeping them as they are if they are not valid UTF-8.
+fn utf8_string(buf: Vec<u8>) -> Utf8String {
+    match String::from_utf8(buf) {
+        Ok(s) => Utf8String::from(s),
+        Err(err) => {
+            let e = err.utf8_error();
+            Utf8String { s: Err((err.into_bytes(), e)) }
+        }
+    }
+}
@@ -0,0 +1,224 @@
+//! Conversions between values and standard types.
+//!
+//! Values are converted into primitives, strings and collections with `TryFrom`, which fails with
+//! an `Error` giving the value back if it has another type or does not fit, and maps are built
+//! from `BTreeMap` and `HashMap` with `From`. Collections are converted element by element, so
+//! typed data nested in a dynamic value is extracted in a single call.
+//!
+//! Integers are converted into every integer type they fit in, whatever their encoding, while
+//! floats are only converted into `f64`, or into `f32` if they were one, so that no conversion
+//! loses precision. Arrays of bytes may also be binaries, like `Vec<u8>` written by serde.
+//!
+//! # Examples
+//!
+//! ```
+//! use std::collections::BTreeMap;
+//! use std::convert::TryFrom;
+//!
+//! use rmpv::Value;
+//!
+//! let val = Value::Map(vec![
+//!     (Value::from("ports"), Value::Array(vec![Value::from(80), Value::from(443)])),
+//! ]);
+//!
+//! let map = BTreeMap::<String, Vec<u16>>::try_from(val.clone()).unwrap();
+//! assert_eq!(vec![80, 443], map["ports"]);
+//!
+//! let mut limits = BTreeMap::new();
+//! limits.insert("conns", 1024u32);
+//! assert_eq!(Value::Map(vec![(Value::from("conns"), Value::from(1024))]), Value::from(limits));
+//!
+//! // 443 does not fit into an `u8`, and the error holds it.
+//! let err = BTreeMap::<String, Vec<u8>>::try_from(val).unwrap_err();
+//! assert_eq!(Value::from(443), *err.value());
+//! ```
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use core::fmt::{self, Display, Formatter};
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+#[cfg(feature = "std")]
+use std::error;
+#[cfg(feature = "std")]
+use std::hash::{BuildHasher, Hash};
+
+use num_traits::NumCast;
+
+use {IntPriv, Utf8String, Value};
+
+/// An error converting a value into another type.
+///
+/// For collections the error holds the key or element that could not be converted, the others
+/// being dropped.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Error {
+    value: Value,
+    expected: &'static str,
+}
+
+impl Error {
+    fn new(value: Value, expected: &'static str) -> Error {
+        Error { value, expected }
+    }
+
+    /// Returns the value that could not be converted.
+    pub fn value(&self) -> &Value {
+        &self.value
+    }
+
+    /// Returns the value that could not be converted, consuming the error.
+    pub fn into_value(self) -> Value {
+        self.value
+    }
+
+    /// Returns the name of the type the value was to be converted into.
+    pub fn expected(&self) -> &'static str {
+        self.expected
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), fmt::Error> {
+        write!(fmt, "expected {}, found {}", self.expected, self.value.display_truncated(1, 4, 16))
+    }
+}
+
+#[cfg(feature = "std")]
+impl error::Error for Error {
+    fn description(&self) -> &str {
+        "value has another type"
+    }
+}
+
+macro_rules! impl_try_from_int {
+    ($($ty:ident)*) => {$(
+        impl TryFrom<Value> for $ty {
+            type Error = Error;
+
+            fn try_from(val: Value) -> Result<$ty, Error> {
+                let res = match val {
+                    Value::Integer(ref n) => match n.n {
+                        IntPriv::PosInt(n) => NumCast::from(n),
+                        IntPriv::NegInt(n) => NumCast::from(n),
+                    },
+                    _ => None,
+                };
+
+                res.ok_or_else(|| Error::new(val, stringify!($ty)))
+            }
+        }
+    )*}
+}
+
+impl_try_from_int!(u8 u16 u32 u64 usize i8 i16 i32 i64 isize);
+
+impl TryFrom<Value> for f32 {
+    type Error = Error;
+
+    fn try_from(val: Value) -> Result<f32, Error> {
+        match val {
+            Value::F32(v) => Ok(v),
+            val => Err(Error::new(val, "f32")),
+        }
+    }
+}
+
+impl TryFrom<Value> for f64 {
+    type Error = Error;
+
+    fn try_from(val: Value) -> Result<f64, Error> {
+        match val {
+            Value::F32(v) => Ok(From::from(v)),
+            Value::F64(v) => Ok(v),
+            val => Err(Error::new(val, "f64")),
+        }
+    }
+}
+
+impl TryFrom<Value> for bool {
+    type Error = Error;
+
+    fn try_from(val: Value) -> Result<bool, Error> {
+        match val {
+            Value::Boolean(v) => Ok(v),
+            val => Err(Error::new(val, "bool")),
+        }
+    }
+}
+
+/// Strings with invalid UTF-8 fail to convert.
+impl TryFrom<Value> for String {
+    type Error = Error;
+
+    fn try_from(val: Value) -> Result<String, Error> {
+        match val {
+            Value::String(Utf8String { s: Ok(s) }) => Ok(s),
+            val => Err(Error::new(val, "string")),
+        }
+    }
+}
+
+impl<T: TryFrom<Value, Error = Error>> TryFrom<Value> for Vec<T> {
+    type Error = Error;
+
+    fn try_from(val: Value) -> Result<Vec<T>, Error> {
+        match val {
+            Value::Array(vec) => vec.into_iter().map(T::try_from).collect(),
+            Value::Binary(buf) => buf.into_iter().map(|b| T::try_from(Value::from(b))).collect(),
+            val => Err(Error::new(val, "array")),
+        }
+    }
+}
+
+/// Later entries replace earlier ones with the same key.
+impl<K, V> TryFrom<Value> for BTreeMap<K, V>
+    where K: TryFrom<Value, Error = Error> + Ord,
+          V: TryFrom<Value, Error = Error>
+{
+    type Error = Error;
+
+    fn try_from(val: Value) -> Result<BTreeMap<K, V>, Error> {
+        match val {
+            Value::Map(map) => {
+                map.into_iter().map(|(k, v)| Ok((K::try_from(k)?, V::try_from(v)?))).collect()
+            }
+            val => Err(Error::new(val, "map")),
+        }
+    }
+}
+
+/// Later entries replace earlier ones with the same key.
+#[cfg(feature = "std")]
+impl<K, V, S> TryFrom<Value> for HashMap<K, V, S>
+    where K: TryFrom<Value, Error = Error> + Eq + Hash,
+          V: TryFrom<Value, Error = Error>,
+          S: BuildHasher + Default
+{
+    type Error = Error;
+
+    fn try_from(val: Value) -> Result<HashMap<K, V, S>, Error> {
+        match val {
+            Value::Map(map) => {
+                map.into_iter().map(|(k, v)| Ok((K::try_from(k)?, V::try_from(v)?))).collect()
+            }
+            val => Err(Error::new(val, "map")),
+        }
+    }
+}
+
+impl<K: Into<Value>, V: Into<Value>> From<BTreeMap<K, V>> for Value {
+    fn from(v: BTreeMap<K, V>) -> Self {
+        Value::Map(v.into_iter().map(|(k, v)| (k.into(), v.into())).collect())
+    }
+}
+
+/// The entries are in the iteration order of the map.
+#[cfg(feature = "std")]
+impl<K: Into<Value>, V: Into<Value>, S> From<HashMap<K, V, S>> for Value {
+    fn from(v: HashMap<K, V, S>) -> Self {
+        Value::Map(v.into_iter().map(|(k, v)| (k.into(), v.into())).collect())
+    }
+}
@@ -0,0 +1,47 @@
+use rmp::decode as rmp_decode;
+
+use compact::CompactValue;
+use super::{read_value_ref_with_config, Config, Error};
+use super::value_ref::BorrowRead;
+
+/// Attempts to read a `CompactValue` from the given reader.
+///
+/// The value is read as a `ValueRef` first, whose payloads are then copied into the compact value,
+/// so the reader has the same requirements as with `read_value_ref`. Only arrays, maps and
+/// payloads too long to be stored inline allocate.
+///
+/// # Errors
+///
+/// This function returns the same errors as `read_value_ref`.
+///
+/// # Examples
+///
+/// ```
+/// use rmpv::compact::CompactValue;
+/// use rmpv::decode::read_compact_value;
+///
+/// let buf = [0xa2, 0x69, 0x64];
+/// let val = read_compact_value(&mut &buf[..]).unwrap();
+///
+/// assert_eq!(Some("id"), val.as_str());
+/// assert!(val.as_bytes().unwrap().is_inline());
+/// ```
+pub fn read_compact_value<'a, R>(rd: &mut R) -> Result<CompactValue, Error>
+    where R: BorrowRead<'a>,
+          R::Error: Into<rmp_decode::Error>
+{
+    read_compact_value_with_config(rd, &Config::default())
+}
+
+/// Attempts to read a `CompactValue` from the given reader, applying the decoding options from
+/// the given `Config` like `read_value_ref_with_config` does.
+///
+/// Strings with invalid UTF-8 are kept as they are with both `Utf8Policy::Preserve` and
+/// `Utf8Policy::Lossy`.
+pub fn read_compact_value_with_config<'a, R>(rd: &mut R, config: &Config) ->
+    Result<CompactValue, Error>
+    where R: BorrowRead<'a>,
+          R::Error: Into<rmp_decode::Error>
+{
+    read_value_ref_with_config(rd, config).map(CompactValue::from)
+}
@@ -1,38 +1,336 @@
+use alloc::vec::Vec;
+use core::fmt::{self, Display, Formatter};
+use core::str::Utf8Error;
+#[cfg(feature = "std")]
 use std::error;
-use std::fmt::{self, Display, Formatter};
+#[cfg(feature = "std")]
 use std::io::{self, ErrorKind};
 
-use rmp::decode::{MarkerReadError, ValueReadError};
+use rmp::Marker;
+use rmp::decode::{self as rmp_decode, MarkerReadError, RmpReadErr, ValueReadError};
 
+mod compact;
 pub mod value;
 pub mod value_ref;
 
-pub use self::value::read_value;
-pub use self::value_ref::read_value_ref;
+pub use self::compact::{read_compact_value, read_compact_value_with_config};
+pub use self::value::{read_value, read_value_with_builder, read_value_with_config, ValueBuilder};
+pub use self::value_ref::{read_value_ref, read_value_ref_with_config};
+
+/// Describes what to do with a MessagePack string whose payload is not valid UTF-8.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Utf8Policy {
+    /// Keep the raw bytes inside the `String` variant together with the UTF-8 error.
+    ///
+    /// This is the default behavior and matches `read_value`.
+    Preserve,
+    /// Decode the payload as `Binary` instead, so that every `String` value holds valid UTF-8.
+    Binary,
+    /// Replace invalid sequences with `U+FFFD REPLACEMENT CHARACTER`.
+    ///
+    /// Borrowed `ValueRef` strings can't be altered without allocating, so `read_value_ref_with_config`
+    /// treats this policy as `Preserve`.
+    Lossy,
+    /// Fail with `Error::InvalidUtf8`.
+    Error,
+}
+
+// Written by hand because deriving it needs `#[default]` on the variant, which is newer than the
+// minimum supported Rust version.
+impl Default for Utf8Policy {
+    fn default() -> Utf8Policy {
+        Utf8Policy::Preserve
+    }
+}
+
+/// Describes what to do with a map holding several entries with the same key.
+///
+/// Keys are compared by their MessagePack encoding as written by `rmpv::encode`, so integers of
+/// different widths are the same key while `F32` and `F64` floats never are. Consumers that check
+/// a map before passing it on, like an authorization layer in front of a service, must agree with
+/// the service on which of the duplicates counts, or an attacker can smuggle a second value past
+/// the check. Rejecting or collapsing duplicates while decoding removes the ambiguity.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DuplicateKeys {
+    /// Keep all entries in the order they were read.
+    ///
+    /// This is the default behavior and matches `read_value`.
+    Allow,
+    /// Fail with `Error::DuplicateKey`.
+    Reject,
+    /// Keep only the first entry for every key.
+    FirstWins,
+    /// Keep only the last entry for every key, at the position it was read.
+    LastWins,
+}
+
+impl Default for DuplicateKeys {
+    fn default() -> DuplicateKeys {
+        DuplicateKeys::Allow
+    }
+}
+
+/// Applies the policy to the entries of a map given their encoded keys, returning which of them
+/// are dropped.
+fn dropped_entries(policy: DuplicateKeys, keys: &[Vec<u8>]) -> Result<Vec<bool>, Error> {
+    // The sort is stable, so entries with the same key stay in the order they were read.
+    let mut order: Vec<usize> = (0..keys.len()).collect();
+    order.sort_by(|&a, &b| keys[a].cmp(&keys[b]));
+
+    let mut dropped = vec![false; keys.len()];
+    for pair in order.windows(2) {
+        if keys[pair[0]] != keys[pair[1]] {
+            continue;
+        }
+        match policy {
+            DuplicateKeys::Allow => {}
+            DuplicateKeys::Reject => return Err(Error::DuplicateKey),
+            DuplicateKeys::FirstWins => dropped[pair[1]] = true,
+            DuplicateKeys::LastWins => dropped[pair[0]] = true,
+        }
+    }
+
+    Ok(dropped)
+}
+
+/// Decoding options for `read_value_with_config` and `read_value_ref_with_config`.
+///
+/// # Examples
+///
+/// ```
+/// use rmpv::decode::{Config, Utf8Policy};
+///
+/// let config = Config::new().utf8(Utf8Policy::Error);
+/// assert_eq!(Utf8Policy::Error, config.utf8_policy());
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Config {
+    utf8: Utf8Policy,
+    str_as_binary: bool,
+    max_depth: Option<usize>,
+    max_len: Option<u32>,
+    max_alloc: Option<usize>,
+    duplicate_keys: DuplicateKeys,
+    sort_keys: bool,
+}
+
+impl Config {
+    /// Constructs a new `Config` with the default options, which decode exactly like
+    /// `read_value` does.
+    pub fn new() -> Config {
+        Config::default()
+    }
+
+    /// Sets the policy applied to string payloads that are not valid UTF-8.
+    pub fn utf8(mut self, policy: Utf8Policy) -> Config {
+        self.utf8 = policy;
+        self
+    }
+
+    /// Returns the policy applied to string payloads that are not valid UTF-8.
+    pub fn utf8_policy(&self) -> Utf8Policy {
+        self.utf8
+    }
+
+    /// Sets whether all string payloads are decoded as `Binary`.
+    ///
+    /// Data produced by encoders that predate the 2013 MessagePack specification uses the same
+    /// raw type for both text and bytes. Enable this option when such raw values are known to
+    /// hold bytes, so they are never interpreted as text. The UTF-8 policy is not applied then.
+    pub fn str_as_binary(mut self, enabled: bool) -> Config {
+        self.str_as_binary = enabled;
+        self
+    }
+
+    /// Returns `true` if all string payloads are decoded as `Binary`.
+    pub fn is_str_as_binary(&self) -> bool {
+        self.str_as_binary
+    }
+
+    /// Sets the maximum nesting depth of arrays and maps.
+    ///
+    /// Values are decoded recursively, so a few bytes of nested array markers are enough to
+    /// overflow the stack when the depth is unbounded. Decoding a container nested deeper than
+    /// this fails with `Error::DepthLimitExceeded`. A depth of zero only allows scalar values.
+    pub fn max_depth(mut self, depth: usize) -> Config {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    /// Returns the maximum nesting depth of arrays and maps, or `None` if it is unbounded.
+    pub fn depth_limit(&self) -> Option<usize> {
+        self.max_depth
+    }
+
+    /// Sets the maximum length of a single array, map, string, binary or extension.
+    ///
+    /// Lengths are checked against the limit as soon as they are read, before anything is
+    /// allocated for them. Longer values fail with `Error::LengthLimitExceeded`.
+    pub fn max_len(mut self, len: u32) -> Config {
+        self.max_len = Some(len);
+        self
+    }
+
+    /// Returns the maximum length of a single value, or `None` if it is unbounded.
+    pub fn len_limit(&self) -> Option<u32> {
+        self.max_len
+    }
+
+    /// Sets the maximum number of bytes allocated while decoding a whole value.
+    ///
+    /// The allocation is estimated as the size of all string, binary and extension payloads
+    /// copied out of the input, plus the size of the decoded value type for every array element
+    /// and twice that for every map entry. Like lengths, it is checked before allocating, and
+    /// decoding fails with `Error::AllocLimitExceeded` once the total would exceed the limit.
+    pub fn max_alloc(mut self, bytes: usize) -> Config {
+        self.max_alloc = Some(bytes);
+        self
+    }
+
+    /// Returns the maximum number of bytes allocated while decoding, or `None` if it is
+    /// unbounded.
+    pub fn alloc_limit(&self) -> Option<usize> {
+        self.max_alloc
+    }
+
+    /// Sets the policy applied to maps holding several entries with the same key.
+    ///
+    /// Finding duplicates encodes every key of every map again, so this costs some time for
+    /// maps with many entries. The policy applies to `Value` and `ValueRef` maps, while custom
+    /// `ValueBuilder`s receive every entry and handle duplicates themselves.
+    pub fn duplicate_keys(mut self, policy: DuplicateKeys) -> Config {
+        self.duplicate_keys = policy;
+        self
+    }
+
+    /// Returns the policy applied to maps holding several entries with the same key.
+    pub fn duplicate_key_policy(&self) -> DuplicateKeys {
+        self.duplicate_keys
+    }
+
+    /// Sets whether the entries of every map are sorted by key, like `Value::sort_keys` does,
+    /// so that they can be looked up with `Value::get_sorted` right away.
+    ///
+    /// This applies to `Value` maps, while `ValueRef` maps and custom `ValueBuilder`s get the
+    /// entries in the order they were read.
+    pub fn sort_keys(mut self, enabled: bool) -> Config {
+        self.sort_keys = enabled;
+        self
+    }
+
+    /// Returns `true` if the entries of maps are sorted by key.
+    pub fn is_sort_keys(&self) -> bool {
+        self.sort_keys
+    }
+}
+
+/// The resources used so far while decoding a value, checked against the limits of a `Config`.
+struct Budget<'a> {
+    config: &'a Config,
+    depth: usize,
+    allocated: usize,
+}
+
+impl<'a> Budget<'a> {
+    fn new(config: &'a Config) -> Budget<'a> {
+        Budget {
+            config,
+            depth: 0,
+            allocated: 0,
+        }
+    }
+
+    /// Accounts for a container of the given length, whose elements take `size` bytes each.
+    fn enter(&mut self, len: u32, size: usize) -> Result<(), Error> {
+        if let Some(depth) = self.config.max_depth {
+            if self.depth >= depth {
+                return Err(Error::DepthLimitExceeded);
+            }
+        }
+
+        self.check_len(len)?;
+        self.alloc((len as usize).saturating_mul(size))?;
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn leave(&mut self) {
+        self.depth -= 1;
+    }
+
+    fn check_len(&self, len: u32) -> Result<(), Error> {
+        match self.config.max_len {
+            Some(max) if len > max => Err(Error::LengthLimitExceeded(len)),
+            _ => Ok(()),
+        }
+    }
+
+    fn alloc(&mut self, bytes: usize) -> Result<(), Error> {
+        let allocated = self.allocated.saturating_add(bytes);
+        match self.config.max_alloc {
+            Some(max) if allocated > max => Err(Error::AllocLimitExceeded),
+            _ => {
+                self.allocated = allocated;
+                Ok(())
+            }
+        }
+    }
+}
 
 /// This type represents all possible errors that can occur when deserializing a value.
+///
+/// Read failures are reported as `rmp::decode::Error`, which is `std::io::Error` with the `std`
+/// feature and `rmp::decode::BytesReadError` without it.
 #[derive(Debug)]
 pub enum Error {
     /// Error while reading marker byte.
-    InvalidMarkerRead(io::Error),
+    InvalidMarkerRead(rmp_decode::Error),
     /// Error while reading data.
-    InvalidDataRead(io::Error),
+    InvalidDataRead(rmp_decode::Error),
+    /// A string payload is not valid UTF-8 and the decoding policy rejects such strings.
+    InvalidUtf8(Utf8Error),
+    /// Arrays or maps are nested deeper than the configured maximum depth.
+    DepthLimitExceeded,
+    /// An array, map, string, binary or extension is longer than the configured maximum, which
+    /// is the length read.
+    LengthLimitExceeded(u32),
+    /// Decoding the value would allocate more than the configured maximum.
+    AllocLimitExceeded,
+    /// A map holds several entries with the same key and the decoding policy rejects such maps.
+    DuplicateKey,
+    /// The value read is not of the type expected, like a map where an array is required, or it
+    /// starts with the reserved marker.
+    TypeMismatch(Marker),
 }
 
+#[cfg(feature = "std")]
 impl Error {
     pub fn kind(&self) -> ErrorKind {
         match *self {
             Error::InvalidMarkerRead(ref err) => err.kind(),
             Error::InvalidDataRead(ref err) => err.kind(),
+            Error::InvalidUtf8(..) |
+            Error::DepthLimitExceeded |
+            Error::LengthLimitExceeded(..) |
+            Error::AllocLimitExceeded |
+            Error::DuplicateKey |
+            Error::TypeMismatch(..) => ErrorKind::InvalidData,
         }
     }
 }
 
+#[cfg(feature = "std")]
 impl error::Error for Error {
     fn description(&self) -> &str {
         match *self {
             Error::InvalidMarkerRead(..) => "I/O error while reading marker byte",
             Error::InvalidDataRead(..) => "I/O error while reading non-marker bytes",
+            Error::InvalidUtf8(..) => "string payload is not valid UTF-8",
+            Error::DepthLimitExceeded => "depth limit exceeded",
+            Error::LengthLimitExceeded(..) => "length limit exceeded",
+            Error::AllocLimitExceeded => "allocation limit exceeded",
+            Error::DuplicateKey => "duplicate map key",
+            Error::TypeMismatch(..) => "type mismatch",
         }
     }
 
@@ -40,6 +338,12 @@ impl error::Error for Error {
         match *self {
             Error::InvalidMarkerRead(ref err) => Some(err),
             Error::InvalidDataRead(ref err) => Some(err),
+            Error::InvalidUtf8(ref err) => Some(err),
+            Error::DepthLimitExceeded |
+            Error::LengthLimitExceeded(..) |
+            Error::AllocLimitExceeded |
+            Error::DuplicateKey |
+            Error::TypeMismatch(..) => None,
         }
     }
 }
@@ -53,33 +357,46 @@ impl Display for Error {
             Error::InvalidDataRead(ref err) => {
                 write!(fmt, "I/O error while reading non-marker bytes: {}", err)
             }
+            Error::InvalidUtf8(ref err) => {
+                write!(fmt, "string payload is not valid UTF-8: {}", err)
+            }
+            Error::DepthLimitExceeded => fmt.write_str("depth limit exceeded"),
+            Error::LengthLimitExceeded(len) => write!(fmt, "length limit exceeded: {}", len),
+            Error::AllocLimitExceeded => fmt.write_str("allocation limit exceeded"),
+            Error::DuplicateKey => fmt.write_str("duplicate map key"),
+            Error::TypeMismatch(marker) => write!(fmt, "type mismatch: {:?}", marker),
         }
     }
 }
 
-impl From<MarkerReadError> for Error {
-    fn from(err: MarkerReadError) -> Error {
-        Error::InvalidMarkerRead(err.0)
+impl<E> From<MarkerReadError<E>> for Error
+    where E: RmpReadErr + Into<rmp_decode::Error>
+{
+    fn from(err: MarkerReadError<E>) -> Error {
+        Error::InvalidMarkerRead(err.0.into())
     }
 }
 
-impl From<ValueReadError> for Error {
-    fn from(err: ValueReadError) -> Error {
+impl<E> From<ValueReadError<E>> for Error
+    where E: RmpReadErr + Into<rmp_decode::Error>
+{
+    fn from(err: ValueReadError<E>) -> Error {
         match err {
-            ValueReadError::InvalidMarkerRead(err) => Error::InvalidMarkerRead(err),
-            ValueReadError::InvalidDataRead(err) => Error::InvalidDataRead(err),
-            ValueReadError::TypeMismatch(..) => {
-                Error::InvalidMarkerRead(io::Error::new(ErrorKind::Other, "type mismatch"))
-            }
+            ValueReadError::InvalidMarkerRead(err) => Error::InvalidMarkerRead(err.into()),
+            ValueReadError::InvalidDataRead(err) => Error::InvalidDataRead(err.into()),
+            ValueReadError::TypeMismatch(marker) => Error::TypeMismatch(marker),
         }
     }
 }
 
+#[cfg(feature = "std")]
 impl Into<io::Error> for Error {
     fn into(self) -> io::Error {
         match self {
             Error::InvalidMarkerRead(err) |
             Error::InvalidDataRead(err) => err,
+            Error::InvalidUtf8(err) => io::Error::new(ErrorKind::InvalidData, err),
+            err => io::Error::new(ErrorKind::InvalidData, err),
         }
     }
 }
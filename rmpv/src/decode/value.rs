@@ -1,192 +1,479 @@
-use std::io::Read;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::mem;
 
 use rmp::Marker;
-use rmp::decode::{read_marker, read_data_u8, read_data_u16, read_data_u32, read_data_u64,
+use rmp::decode::{self as rmp_decode, RmpRead, read_marker, read_data_u8, read_data_u16, read_data_u32, read_data_u64,
                   read_data_i8, read_data_i16, read_data_i32, read_data_i64, read_data_f32,
                   read_data_f64};
 
-use {Utf8String, Value};
-use super::Error;
+use {Integer, Utf8String, Value};
+use encode::write_value;
+use map::sort_entries;
+use super::{dropped_entries, Budget, Config, DuplicateKeys, Error, Utf8Policy};
 
-fn read_array_data<R: Read>(rd: &mut R, mut len: usize) -> Result<Vec<Value>, Error> {
-    let mut vec = Vec::with_capacity(len);
+/// Callbacks constructing a document from decoded MessagePack data.
+///
+/// `read_value_with_builder` walks the input and calls back for every scalar and container, so
+/// data can be decoded straight into a custom document type, like the object model of a
+/// scripting language behind FFI bindings, without building an intermediate `Value` tree first.
+/// Decoding into `Value` itself is implemented on top of this trait.
+///
+/// Containers are built in three steps: `begin_*` creates an accumulator given the number of
+/// elements announced, which the decoded elements are then pushed into, and `end_*` turns it into
+/// the finished value.
+///
+/// Every callback may fail, which stops decoding right away and returns its error from
+/// `read_value_with_builder`, so builders can reject input as soon as they see it.
+///
+/// # Examples
+///
+/// ```
+/// use rmpv::{Integer, Utf8String};
+/// use rmpv::decode::{read_value_with_builder, Config, Error, ValueBuilder};
+///
+/// /// Sums all integers, ignoring everything else.
+/// struct Sum;
+///
+/// impl ValueBuilder for Sum {
+///     type Value = i64;
+///     type Array = i64;
+///     type Map = i64;
+///
+///     fn nil(&mut self) -> Result<i64, Error> { Ok(0) }
+///     fn boolean(&mut self, _: bool) -> Result<i64, Error> { Ok(0) }
+///     fn integer(&mut self, val: Integer) -> Result<i64, Error> { Ok(val.as_i64().unwrap_or(0)) }
+///     fn f32(&mut self, _: f32) -> Result<i64, Error> { Ok(0) }
+///     fn f64(&mut self, _: f64) -> Result<i64, Error> { Ok(0) }
+///     fn string(&mut self, _: Utf8String) -> Result<i64, Error> { Ok(0) }
+///     fn binary(&mut self, _: Vec<u8>) -> Result<i64, Error> { Ok(0) }
+///     fn ext(&mut self, _: i8, _: Vec<u8>) -> Result<i64, Error> { Ok(0) }
+///
+///     fn begin_array(&mut self, _: u32) -> Result<i64, Error> { Ok(0) }
+///     fn push_element(&mut self, sum: &mut i64, val: i64) -> Result<(), Error> {
+///         *sum += val;
+///         Ok(())
+///     }
+///     fn end_array(&mut self, sum: i64) -> Result<i64, Error> { Ok(sum) }
+///
+///     fn begin_map(&mut self, _: u32) -> Result<i64, Error> { Ok(0) }
+///     fn push_entry(&mut self, sum: &mut i64, key: i64, val: i64) -> Result<(), Error> {
+///         *sum += key + val;
+///         Ok(())
+///     }
+///     fn end_map(&mut self, sum: i64) -> Result<i64, Error> { Ok(sum) }
+/// }
+///
+/// // [1, {2: 3}, "four", [5]]
+/// let buf = [0x94, 0x01, 0x81, 0x02, 0x03, 0xa4, 0x66, 0x6f, 0x75, 0x72, 0x91, 0x05];
+///
+/// let sum = read_value_with_builder(&mut &buf[..], &Config::new(), &mut Sum).unwrap();
+/// assert_eq!(11, sum);
+/// ```
+pub trait ValueBuilder {
+    /// The type of the values constructed.
+    type Value;
+    /// The accumulator the elements of an array are pushed into.
+    type Array;
+    /// The accumulator the entries of a map are pushed into.
+    type Map;
+
+    /// Constructs a nil value, which reserved markers are decoded as too.
+    fn nil(&mut self) -> Result<Self::Value, Error>;
+    /// Constructs a boolean value.
+    fn boolean(&mut self, val: bool) -> Result<Self::Value, Error>;
+    /// Constructs an integer value.
+    fn integer(&mut self, val: Integer) -> Result<Self::Value, Error>;
+    /// Constructs a 32-bit floating point value.
+    fn f32(&mut self, val: f32) -> Result<Self::Value, Error>;
+    /// Constructs a 64-bit floating point value.
+    fn f64(&mut self, val: f64) -> Result<Self::Value, Error>;
+    /// Constructs a string value, which holds invalid UTF-8 only with `Utf8Policy::Preserve`.
+    fn string(&mut self, val: Utf8String) -> Result<Self::Value, Error>;
+    /// Constructs a binary value, also called for strings decoded as binary by the `Config`.
+    fn binary(&mut self, val: Vec<u8>) -> Result<Self::Value, Error>;
+    /// Constructs an extension value of the given type.
+    fn ext(&mut self, ty: i8, data: Vec<u8>) -> Result<Self::Value, Error>;
+
+    /// Starts an array of the given number of elements.
+    fn begin_array(&mut self, len: u32) -> Result<Self::Array, Error>;
+    /// Appends the next element to an array.
+    fn push_element(&mut self, array: &mut Self::Array, val: Self::Value) -> Result<(), Error>;
+    /// Finishes an array after all its elements were pushed.
+    fn end_array(&mut self, array: Self::Array) -> Result<Self::Value, Error>;
+
+    /// Starts a map of the given number of entries.
+    fn begin_map(&mut self, len: u32) -> Result<Self::Map, Error>;
+    /// Appends the next entry to a map.
+    fn push_entry(&mut self, map: &mut Self::Map, key: Self::Value, val: Self::Value) ->
+        Result<(), Error>;
+    /// Finishes a map after all its entries were pushed.
+    fn end_map(&mut self, map: Self::Map) -> Result<Self::Value, Error>;
+}
+
+/// Builds `Value` trees, backing `read_value` and `read_value_with_config`.
+struct ValueTree {
+    duplicate_keys: DuplicateKeys,
+    sort_keys: bool,
+}
+
+impl ValueBuilder for ValueTree {
+    type Value = Value;
+    type Array = Vec<Value>;
+    type Map = Vec<(Value, Value)>;
+
+    fn nil(&mut self) -> Result<Value, Error> {
+        Ok(Value::Nil)
+    }
+
+    fn boolean(&mut self, val: bool) -> Result<Value, Error> {
+        Ok(Value::Boolean(val))
+    }
+
+    fn integer(&mut self, val: Integer) -> Result<Value, Error> {
+        Ok(Value::Integer(val))
+    }
+
+    fn f32(&mut self, val: f32) -> Result<Value, Error> {
+        Ok(Value::F32(val))
+    }
+
+    fn f64(&mut self, val: f64) -> Result<Value, Error> {
+        Ok(Value::F64(val))
+    }
+
+    fn string(&mut self, val: Utf8String) -> Result<Value, Error> {
+        Ok(Value::String(val))
+    }
+
+    fn binary(&mut self, val: Vec<u8>) -> Result<Value, Error> {
+        Ok(Value::Binary(val))
+    }
+
+    fn ext(&mut self, ty: i8, data: Vec<u8>) -> Result<Value, Error> {
+        Ok(Value::Ext(ty, data))
+    }
+
+    fn begin_array(&mut self, len: u32) -> Result<Vec<Value>, Error> {
+        Ok(Vec::with_capacity(len as usize))
+    }
+
+    fn push_element(&mut self, array: &mut Vec<Value>, val: Value) -> Result<(), Error> {
+        array.push(val);
+        Ok(())
+    }
+
+    fn end_array(&mut self, array: Vec<Value>) -> Result<Value, Error> {
+        Ok(Value::Array(array))
+    }
+
+    fn begin_map(&mut self, len: u32) -> Result<Vec<(Value, Value)>, Error> {
+        Ok(Vec::with_capacity(len as usize))
+    }
+
+    fn push_entry(&mut self, map: &mut Vec<(Value, Value)>, key: Value, val: Value) ->
+        Result<(), Error> {
+        map.push((key, val));
+        Ok(())
+    }
 
-    while len > 0 {
-        vec.push(read_value(rd)?);
-        len -= 1;
+    fn end_map(&mut self, mut map: Vec<(Value, Value)>) -> Result<Value, Error> {
+        if self.sort_keys {
+            sort_entries(&mut map);
+        }
+        if self.duplicate_keys == DuplicateKeys::Allow || map.len() < 2 {
+            return Ok(Value::Map(map));
+        }
+
+        let keys: Vec<Vec<u8>> = map.iter().map(|entry| {
+            let mut buf = Vec::new();
+            // Writing into a vector can't fail.
+            write_value(&mut buf, &entry.0).unwrap();
+            buf
+        }).collect();
+
+        let dropped = dropped_entries(self.duplicate_keys, &keys)?;
+        let map = map.into_iter()
+            .zip(dropped)
+            .filter(|&(_, dropped)| !dropped)
+            .map(|(entry, _)| entry)
+            .collect();
+        Ok(Value::Map(map))
+    }
+}
+
+fn read_array_data<R, B>(rd: &mut R, len: u32, budget: &mut Budget, builder: &mut B) ->
+    Result<B::Value, Error>
+    where R: RmpRead,
+          R::Error: Into<rmp_decode::Error>,
+          B: ValueBuilder
+{
+    budget.enter(len, mem::size_of::<B::Value>())?;
+    let mut array = builder.begin_array(len)?;
+
+    for _ in 0..len {
+        let val = read_value_budgeted(rd, budget, builder)?;
+        builder.push_element(&mut array, val)?;
     }
 
-    Ok(vec)
+    budget.leave();
+    builder.end_array(array)
 }
 
-fn read_map_data<R: Read>(rd: &mut R, mut len: usize) -> Result<Vec<(Value, Value)>, Error> {
-    let mut vec = Vec::with_capacity(len);
+fn read_map_data<R, B>(rd: &mut R, len: u32, budget: &mut Budget, builder: &mut B) ->
+    Result<B::Value, Error>
+    where R: RmpRead,
+          R::Error: Into<rmp_decode::Error>,
+          B: ValueBuilder
+{
+    budget.enter(len, 2 * mem::size_of::<B::Value>())?;
+    let mut map = builder.begin_map(len)?;
 
-    while len > 0 {
-        vec.push((read_value(rd)?, read_value(rd)?));
-        len -= 1;
+    for _ in 0..len {
+        let key = read_value_budgeted(rd, budget, builder)?;
+        let val = read_value_budgeted(rd, budget, builder)?;
+        builder.push_entry(&mut map, key, val)?;
     }
 
-    Ok(vec)
+    budget.leave();
+    builder.end_map(map)
 }
 
-fn read_str_data<R: Read>(rd: &mut R, len: usize) -> Result<Utf8String, Error> {
-    match String::from_utf8(read_bin_data(rd, len)?) {
-        Ok(s) => Ok(Utf8String::from(s)),
+fn read_str_data<R, B>(rd: &mut R, len: u32, budget: &mut Budget, builder: &mut B) ->
+    Result<B::Value, Error>
+    where R: RmpRead,
+          R::Error: Into<rmp_decode::Error>,
+          B: ValueBuilder
+{
+    let config = budget.config;
+    let buf = read_bin_data(rd, len, budget)?;
+    if config.str_as_binary {
+        return builder.binary(buf);
+    }
+
+    match String::from_utf8(buf) {
+        Ok(s) => builder.string(Utf8String::from(s)),
         Err(err) => {
-            let e = err.utf8_error();
-            let s = Utf8String {
-                s: Err((err.into_bytes(), e)),
-            };
-            Ok(s)
+            match config.utf8 {
+                Utf8Policy::Preserve => {
+                    let e = err.utf8_error();
+                    let s = Utf8String {
+                        s: Err((err.into_bytes(), e)),
+                    };
+                    builder.string(s)
+                }
+                Utf8Policy::Binary => builder.binary(err.into_bytes()),
+                Utf8Policy::Lossy => {
+                    let s = String::from_utf8_lossy(err.as_bytes()).into_owned();
+                    builder.string(Utf8String::from(s))
+                }
+                Utf8Policy::Error => Err(Error::InvalidUtf8(err.utf8_error())),
+            }
         }
     }
 }
 
-fn read_bin_data<R: Read>(rd: &mut R, len: usize) -> Result<Vec<u8>, Error> {
-    let mut buf = Vec::with_capacity(len);
-    buf.resize(len as usize, 0u8);
-    rd.read_exact(&mut buf[..]).map_err(Error::InvalidDataRead)?;
+fn read_bin_data<R>(rd: &mut R, len: u32, budget: &mut Budget) -> Result<Vec<u8>, Error>
+    where R: RmpRead,
+          R::Error: Into<rmp_decode::Error>
+{
+    budget.check_len(len)?;
+    budget.alloc(len as usize)?;
+
+    let mut buf = vec![0u8; len as usize];
+    rd.read_exact_buf(&mut buf[..]).map_err(|err| Error::InvalidDataRead(err.into()))?;
 
     Ok(buf)
 }
 
-fn read_ext_body<R: Read>(rd: &mut R, len: usize) -> Result<(i8, Vec<u8>), Error> {
+fn read_ext_body<R, B>(rd: &mut R, len: u32, budget: &mut Budget, builder: &mut B) ->
+    Result<B::Value, Error>
+    where R: RmpRead,
+          R::Error: Into<rmp_decode::Error>,
+          B: ValueBuilder
+{
     let ty = read_data_i8(rd)?;
-    let vec = read_bin_data(rd, len)?;
+    let vec = read_bin_data(rd, len, budget)?;
 
-    Ok((ty, vec))
+    builder.ext(ty, vec)
 }
 
 /// Attempts to read bytes from the given reader and interpret them as a `Value`.
 ///
+/// Any `std::io::Read` can be used as a reader. Without the `std` feature values are read from
+/// byte slices or `rmp::decode::Bytes` instead.
+///
 /// # Errors
 ///
 /// This function will return `Error` on any I/O error while either reading or decoding a `Value`.
 /// All instances of `ErrorKind::Interrupted` are handled by this function and the underlying
 /// operation is retried.
 pub fn read_value<R>(rd: &mut R) -> Result<Value, Error>
-    where R: Read
+    where R: RmpRead,
+          R::Error: Into<rmp_decode::Error>
+{
+    read_value_with_config(rd, &Config::default())
+}
+
+/// Attempts to read bytes from the given reader and interpret them as a `Value`, applying the
+/// decoding options from the given `Config`.
+///
+/// # Errors
+///
+/// In addition to the errors returned by `read_value`, this function returns
+/// `Error::InvalidUtf8` when a string payload is not valid UTF-8 and the configured
+/// `Utf8Policy` is `Error`, the limit errors when the value exceeds one of the configured
+/// limits, and `Error::DuplicateKey` when a map holds the same key twice and the configured
+/// `DuplicateKeys` policy is `Reject`.
+///
+/// Untrusted input should always be decoded with limits, since otherwise a few bytes are enough
+/// to claim gigabytes of elements or to nest arrays deep enough to overflow the stack:
+///
+/// ```
+/// use rmpv::decode::{read_value_with_config, Config, Error};
+///
+/// let config = Config::new().max_depth(32).max_len(1024).max_alloc(1 << 20);
+///
+/// let nested = [0x91; 64];
+/// match read_value_with_config(&mut &nested[..], &config) {
+///     Err(Error::DepthLimitExceeded) => {}
+///     other => panic!("unexpected result: {:?}", other),
+/// }
+///
+/// // An array claiming 2^32 - 1 elements.
+/// let huge = [0xdd, 0xff, 0xff, 0xff, 0xff];
+/// match read_value_with_config(&mut &huge[..], &config) {
+///     Err(Error::LengthLimitExceeded(0xffffffff)) => {}
+///     other => panic!("unexpected result: {:?}", other),
+/// }
+/// ```
+///
+/// # Examples
+///
+/// ```
+/// use rmpv::Value;
+/// use rmpv::decode::{read_value_with_config, Config, Utf8Policy};
+///
+/// let buf = [0xa2, 0xc3, 0x28];
+/// let config = Config::new().utf8(Utf8Policy::Binary);
+///
+/// assert_eq!(Value::Binary(vec![0xc3, 0x28]), read_value_with_config(&mut &buf[..], &config).unwrap());
+/// ```
+pub fn read_value_with_config<R>(rd: &mut R, config: &Config) -> Result<Value, Error>
+    where R: RmpRead,
+          R::Error: Into<rmp_decode::Error>
+{
+    let mut tree = ValueTree {
+        duplicate_keys: config.duplicate_keys,
+        sort_keys: config.sort_keys,
+    };
+    read_value_with_builder(rd, config, &mut tree)
+}
+
+/// Attempts to read a value from the given reader, constructing it with the given `ValueBuilder`
+/// and applying the decoding options from the given `Config`.
+///
+/// # Errors
+///
+/// This function returns the same errors as `read_value_with_config`.
+pub fn read_value_with_builder<R, B>(rd: &mut R, config: &Config, builder: &mut B) ->
+    Result<B::Value, Error>
+    where R: RmpRead,
+          R::Error: Into<rmp_decode::Error>,
+          B: ValueBuilder
+{
+    read_value_budgeted(rd, &mut Budget::new(config), builder)
+}
+
+fn read_value_budgeted<R, B>(rd: &mut R, budget: &mut Budget, builder: &mut B) ->
+    Result<B::Value, Error>
+    where R: RmpRead,
+          R::Error: Into<rmp_decode::Error>,
+          B: ValueBuilder
 {
     let val = match read_marker(rd)? {
-        Marker::Null => Value::Nil,
-        Marker::True => Value::Boolean(true),
-        Marker::False => Value::Boolean(false),
-        Marker::FixPos(val) => Value::from(val),
-        Marker::FixNeg(val) => Value::from(val),
-        Marker::U8 => Value::from(read_data_u8(rd)?),
-        Marker::U16 => Value::from(read_data_u16(rd)?),
-        Marker::U32 => Value::from(read_data_u32(rd)?),
-        Marker::U64 => Value::from(read_data_u64(rd)?),
-        Marker::I8 => Value::from(read_data_i8(rd)?),
-        Marker::I16 => Value::from(read_data_i16(rd)?),
-        Marker::I32 => Value::from(read_data_i32(rd)?),
-        Marker::I64 => Value::from(read_data_i64(rd)?),
-        Marker::F32 => Value::F32(read_data_f32(rd)?),
-        Marker::F64 => Value::F64(read_data_f64(rd)?),
+        Marker::Null => builder.nil()?,
+        Marker::True => builder.boolean(true)?,
+        Marker::False => builder.boolean(false)?,
+        Marker::FixPos(val) => builder.integer(Integer::from(val))?,
+        Marker::FixNeg(val) => builder.integer(Integer::from(val))?,
+        Marker::U8 => builder.integer(Integer::from(read_data_u8(rd)?))?,
+        Marker::U16 => builder.integer(Integer::from(read_data_u16(rd)?))?,
+        Marker::U32 => builder.integer(Integer::from(read_data_u32(rd)?))?,
+        Marker::U64 => builder.integer(Integer::from(read_data_u64(rd)?))?,
+        Marker::I8 => builder.integer(Integer::from(read_data_i8(rd)?))?,
+        Marker::I16 => builder.integer(Integer::from(read_data_i16(rd)?))?,
+        Marker::I32 => builder.integer(Integer::from(read_data_i32(rd)?))?,
+        Marker::I64 => builder.integer(Integer::from(read_data_i64(rd)?))?,
+        Marker::F32 => builder.f32(read_data_f32(rd)?)?,
+        Marker::F64 => builder.f64(read_data_f64(rd)?)?,
         Marker::FixStr(len) => {
-            let res = read_str_data(rd, len as usize)?;
-            Value::String(res)
+            read_str_data(rd, len as u32, budget, builder)?
         }
         Marker::Str8 => {
             let len = read_data_u8(rd)?;
-            let res = read_str_data(rd, len as usize)?;
-            Value::String(res)
+            read_str_data(rd, len as u32, budget, builder)?
         }
         Marker::Str16 => {
             let len = read_data_u16(rd)?;
-            let res = read_str_data(rd, len as usize)?;
-            Value::String(res)
+            read_str_data(rd, len as u32, budget, builder)?
         }
         Marker::Str32 => {
             let len = read_data_u32(rd)?;
-            let res = read_str_data(rd, len as usize)?;
-            Value::String(res)
+            read_str_data(rd, len, budget, builder)?
         }
         Marker::FixArray(len) => {
-            let vec = read_array_data(rd, len as usize)?;
-            Value::Array(vec)
+            read_array_data(rd, len as u32, budget, builder)?
         }
         Marker::Array16 => {
             let len = read_data_u16(rd)?;
-            let vec = read_array_data(rd, len as usize)?;
-            Value::Array(vec)
+            read_array_data(rd, len as u32, budget, builder)?
         }
         Marker::Array32 => {
             let len = read_data_u32(rd)?;
-            let vec = read_array_data(rd, len as usize)?;
-            Value::Array(vec)
+            read_array_data(rd, len, budget, builder)?
         }
         Marker::FixMap(len) => {
-            let map = read_map_data(rd, len as usize)?;
-            Value::Map(map)
+            read_map_data(rd, len as u32, budget, builder)?
         }
         Marker::Map16 => {
             let len = read_data_u16(rd)?;
-            let map = read_map_data(rd, len as usize)?;
-            Value::Map(map)
+            read_map_data(rd, len as u32, budget, builder)?
         }
         Marker::Map32 => {
             let len = read_data_u32(rd)?;
-            let map = read_map_data(rd, len as usize)?;
-            Value::Map(map)
+            read_map_data(rd, len, budget, builder)?
         }
         Marker::Bin8 => {
             let len = read_data_u8(rd)?;
-            let vec = read_bin_data(rd, len as usize)?;
-            Value::Binary(vec)
+            builder.binary(read_bin_data(rd, len as u32, budget)?)?
         }
         Marker::Bin16 => {
             let len = read_data_u16(rd)?;
-            let vec = read_bin_data(rd, len as usize)?;
-            Value::Binary(vec)
+            builder.binary(read_bin_data(rd, len as u32, budget)?)?
         }
         Marker::Bin32 => {
             let len = read_data_u32(rd)?;
-            let vec = read_bin_data(rd, len as usize)?;
-            Value::Binary(vec)
-        }
-        Marker::FixExt1 => {
-            let len = 1 as usize;
-            let (ty, vec) = read_ext_body(rd, len)?;
-            Value::Ext(ty, vec)
-        }
-        Marker::FixExt2 => {
-            let len = 2 as usize;
-            let (ty, vec) = read_ext_body(rd, len)?;
-            Value::Ext(ty, vec)
-        }
-        Marker::FixExt4 => {
-            let len = 4 as usize;
-            let (ty, vec) = read_ext_body(rd, len)?;
-            Value::Ext(ty, vec)
-        }
-        Marker::FixExt8 => {
-            let len = 8 as usize;
-            let (ty, vec) = read_ext_body(rd, len)?;
-            Value::Ext(ty, vec)
-        }
-        Marker::FixExt16 => {
-            let len = 16 as usize;
-            let (ty, vec) = read_ext_body(rd, len)?;
-            Value::Ext(ty, vec)
+            builder.binary(read_bin_data(rd, len, budget)?)?
         }
+        Marker::FixExt1 => read_ext_body(rd, 1, budget, builder)?,
+        Marker::FixExt2 => read_ext_body(rd, 2, budget, builder)?,
+        Marker::FixExt4 => read_ext_body(rd, 4, budget, builder)?,
+        Marker::FixExt8 => read_ext_body(rd, 8, budget, builder)?,
+        Marker::FixExt16 => read_ext_body(rd, 16, budget, builder)?,
         Marker::Ext8 => {
-            let len = read_data_u8(rd)? as usize;
-            let (ty, vec) = read_ext_body(rd, len)?;
-            Value::Ext(ty, vec)
+            let len = read_data_u8(rd)?;
+            read_ext_body(rd, len as u32, budget, builder)?
         }
         Marker::Ext16 => {
-            let len = read_data_u16(rd)? as usize;
-            let (ty, vec) = read_ext_body(rd, len)?;
-            Value::Ext(ty, vec)
+            let len = read_data_u16(rd)?;
+            read_ext_body(rd, len as u32, budget, builder)?
         }
         Marker::Ext32 => {
-            let len = read_data_u32(rd)? as usize;
-            let (ty, vec) = read_ext_body(rd, len)?;
-            Value::Ext(ty, vec)
+            let len = read_data_u32(rd)?;
+            read_ext_body(rd, len, budget, builder)?
         }
-        Marker::Reserved => Value::Nil,
+        Marker::Reserved => builder.nil()?,
     };
 
     Ok(val)
@@ -1,37 +1,54 @@
-use std;
-use std::io::{self, Cursor, ErrorKind, Read};
-use std::str;
+use alloc::vec::Vec;
+use core::{mem, str};
+#[cfg(feature = "std")]
+use std::io::Cursor;
 
 use rmp::Marker;
-use rmp::decode::{read_marker, read_data_u8, read_data_u16, read_data_u32, read_data_u64,
+use rmp::decode::{self as rmp_decode, RmpRead, read_marker, read_data_u8, read_data_u16, read_data_u32, read_data_u64,
                   read_data_i8, read_data_i16, read_data_i32, read_data_i64, read_data_f32,
                   read_data_f64};
 
 use {Utf8StringRef, ValueRef};
-use super::Error;
+use encode::write_value_ref;
+use super::{dropped_entries, Budget, Config, DuplicateKeys, Error, Utf8Policy};
 
-fn read_str_data<'a, R>(rd: &mut R, len: usize) -> Result<Utf8StringRef<'a>, Error>
-    where R: BorrowRead<'a>
+fn read_str_data<'a, R>(rd: &mut R, len: u32, budget: &Budget) -> Result<ValueRef<'a>, Error>
+    where R: BorrowRead<'a>,
+          R::Error: Into<rmp_decode::Error>
 {
-    let buf = read_bin_data(rd, len)?;
+    let buf = read_bin_data(rd, len, budget)?;
+    if budget.config.str_as_binary {
+        return Ok(ValueRef::Binary(buf));
+    }
+
     match str::from_utf8(buf) {
-        Ok(s) => Ok(Utf8StringRef::from(s)),
+        Ok(s) => Ok(ValueRef::String(Utf8StringRef::from(s))),
         Err(err) => {
-            let s = Utf8StringRef {
-                s: Err((buf, err)),
-            };
-            Ok(s)
+            match budget.config.utf8 {
+                Utf8Policy::Preserve | Utf8Policy::Lossy => {
+                    let s = Utf8StringRef {
+                        s: Err((buf, err)),
+                    };
+                    Ok(ValueRef::String(s))
+                }
+                Utf8Policy::Binary => Ok(ValueRef::Binary(buf)),
+                Utf8Policy::Error => Err(Error::InvalidUtf8(err)),
+            }
         }
     }
 }
 
-fn read_bin_data<'a, R>(rd: &mut R, len: usize) -> Result<&'a [u8], Error>
-    where R: BorrowRead<'a>
+// Payloads are borrowed from the input, so only their length counts against the limits.
+fn read_bin_data<'a, R>(rd: &mut R, len: u32, budget: &Budget) -> Result<&'a [u8], Error>
+    where R: BorrowRead<'a>,
+          R::Error: Into<rmp_decode::Error>
 {
+    budget.check_len(len)?;
+    let len = len as usize;
     let buf = rd.fill_buf();
 
     if len > buf.len() {
-        return Err(Error::InvalidDataRead(io::Error::new(ErrorKind::UnexpectedEof, "unexpected EOF")));
+        return Err(Error::InvalidDataRead(unexpected_eof(len, buf.len())));
     }
 
     // Take a slice.
@@ -41,38 +58,81 @@ fn read_bin_data<'a, R>(rd: &mut R, len: usize) -> Result<&'a [u8], Error>
     Ok(buf)
 }
 
-fn read_ext_body<'a, R>(rd: &mut R, len: usize) -> Result<(i8, &'a [u8]), Error>
-    where R: BorrowRead<'a>
+#[cfg(feature = "std")]
+fn unexpected_eof(_expected: usize, _actual: usize) -> rmp_decode::Error {
+    rmp_decode::Error::new(::std::io::ErrorKind::UnexpectedEof, "unexpected EOF")
+}
+
+// Borrowing readers don't track their position, so it's relative to the input left.
+#[cfg(not(feature = "std"))]
+fn unexpected_eof(expected: usize, actual: usize) -> rmp_decode::Error {
+    rmp_decode::BytesReadError::InsufficientBytes {
+        expected: expected,
+        actual: actual,
+        position: 0,
+    }
+}
+
+fn read_ext_body<'a, R>(rd: &mut R, len: u32, budget: &Budget) -> Result<(i8, &'a [u8]), Error>
+    where R: BorrowRead<'a>,
+          R::Error: Into<rmp_decode::Error>
 {
     let ty = read_data_i8(rd)?;
-    let buf = read_bin_data(rd, len)?;
+    let buf = read_bin_data(rd, len, budget)?;
 
     Ok((ty, buf))
 }
 
-fn read_array_data<'a, R>(rd: &mut R, mut len: usize) -> Result<Vec<ValueRef<'a>>, Error>
-    where R: BorrowRead<'a>
+fn read_array_data<'a, R>(rd: &mut R, len: u32, budget: &mut Budget) ->
+    Result<Vec<ValueRef<'a>>, Error>
+    where R: BorrowRead<'a>,
+          R::Error: Into<rmp_decode::Error>
 {
-    let mut vec = Vec::with_capacity(len);
+    budget.enter(len, mem::size_of::<ValueRef>())?;
+    let mut vec = Vec::with_capacity(len as usize);
 
-    while len > 0 {
-        vec.push(read_value_ref(rd)?);
-        len -= 1;
+    for _ in 0..len {
+        vec.push(read_value_ref_budgeted(rd, budget)?);
     }
 
+    budget.leave();
     Ok(vec)
 }
 
-fn read_map_data<'a, R>(rd: &mut R, mut len: usize) -> Result<Vec<(ValueRef<'a>, ValueRef<'a>)>, Error>
-    where R: BorrowRead<'a>
+fn read_map_data<'a, R>(rd: &mut R, len: u32, budget: &mut Budget) ->
+    Result<Vec<(ValueRef<'a>, ValueRef<'a>)>, Error>
+    where R: BorrowRead<'a>,
+          R::Error: Into<rmp_decode::Error>
 {
-    let mut vec = Vec::with_capacity(len);
+    budget.enter(len, mem::size_of::<(ValueRef, ValueRef)>())?;
+    let mut vec = Vec::with_capacity(len as usize);
+
+    for _ in 0..len {
+        let key = read_value_ref_budgeted(rd, budget)?;
+        let val = read_value_ref_budgeted(rd, budget)?;
+        vec.push((key, val));
+    }
 
-    while len > 0 {
-        vec.push((read_value_ref(rd)?, read_value_ref(rd)?));
-        len -= 1;
+    budget.leave();
+    let policy = budget.config.duplicate_keys;
+    if policy == DuplicateKeys::Allow || vec.len() < 2 {
+        return Ok(vec);
     }
 
+    let keys: Vec<Vec<u8>> = vec.iter().map(|entry| {
+        let mut buf = Vec::new();
+        // Writing into a vector can't fail.
+        write_value_ref(&mut buf, &entry.0).unwrap();
+        buf
+    }).collect();
+    let dropped = dropped_entries(policy, &keys)?;
+
+    let vec = vec.into_iter()
+        .zip(dropped)
+        .filter(|&(_, dropped)| !dropped)
+        .map(|(entry, _)| entry)
+        .collect();
+
     Ok(vec)
 }
 
@@ -80,7 +140,7 @@ fn read_map_data<'a, R>(rd: &mut R, mut len: usize) -> Result<Vec<(ValueRef<'a>,
 ///
 /// This magic trait acts like a standard BufRead but unlike the standard this has an explicit
 /// internal buffer lifetime, which allows to borrow from underlying buffer while consuming bytes.
-pub trait BorrowRead<'a>: Read {
+pub trait BorrowRead<'a>: RmpRead {
     /// Returns the buffer contents.
     ///
     /// This function is a lower-level call. It needs to be paired with the consume method to
@@ -108,9 +168,10 @@ impl<'a> BorrowRead<'a> for &'a [u8] {
 }
 
 /// Useful when you want to know how much bytes has been consumed during ValueRef decoding.
+#[cfg(feature = "std")]
 impl<'a> BorrowRead<'a> for Cursor<&'a [u8]> {
     fn fill_buf(&self) -> &'a [u8] {
-        let len = std::cmp::min(self.position(), self.get_ref().len() as u64);
+        let len = ::core::cmp::min(self.position(), self.get_ref().len() as u64);
         &self.get_ref()[len as usize..]
     }
 
@@ -130,10 +191,10 @@ impl<'a> BorrowRead<'a> for Cursor<&'a [u8]> {
 /// itself but permits to mutate the buffer it contains. It allows to perform a completely
 /// zero-copy reading without a data loss fear in case of an error.
 ///
-/// Currently only two types fit in this requirement: `&[u8]` and `Cursor<&[u8]>`. Using Cursor is
-/// helpful, when you need to know how exactly many bytes the decoded ValueRef consumes. A `Vec<u8>`
-/// type doesn't fit in the `BorrowRead` requirement, because its mut reference can mutate the
-/// underlying buffer - use `Vec::as_slice()` if you need to decode a value from the vector.
+/// Currently only two types fit in this requirement: `&[u8]` and, with the `std` feature,
+/// `Cursor<&[u8]>`. Using Cursor is helpful, when you need to know how exactly many bytes the
+/// decoded ValueRef consumes. A `Vec<u8>` type doesn't fit in the `BorrowRead` requirement,
+/// because its mut reference can mutate the underlying buffer - use `Vec::as_slice()` if you need to decode a value from the vector.
 ///
 /// # Errors
 ///
@@ -151,7 +212,28 @@ impl<'a> BorrowRead<'a> for Cursor<&'a [u8]> {
 /// assert_eq!(ValueRef::from("le message"), read_value_ref(&mut rd).unwrap());
 /// ```
 pub fn read_value_ref<'a, R>(rd: &mut R) -> Result<ValueRef<'a>, Error>
-    where R: BorrowRead<'a>
+    where R: BorrowRead<'a>,
+          R::Error: Into<rmp_decode::Error>
+{
+    read_value_ref_with_config(rd, &Config::default())
+}
+
+/// Attempts to read a `ValueRef` from the given reader, applying the decoding options from the
+/// given `Config`.
+///
+/// See `read_value_ref` for the reader requirements and `read_value_with_config` for the
+/// additional errors the configuration may produce. Strings, binaries and extensions are borrowed
+/// from the input, so only arrays and maps count against the allocation limit.
+pub fn read_value_ref_with_config<'a, R>(rd: &mut R, config: &Config) -> Result<ValueRef<'a>, Error>
+    where R: BorrowRead<'a>,
+          R::Error: Into<rmp_decode::Error>
+{
+    read_value_ref_budgeted(rd, &mut Budget::new(config))
+}
+
+fn read_value_ref_budgeted<'a, R>(rd: &mut R, budget: &mut Budget) -> Result<ValueRef<'a>, Error>
+    where R: BorrowRead<'a>,
+          R::Error: Into<rmp_decode::Error>
 {
     let mut rd = rd;
 
@@ -174,105 +256,101 @@ pub fn read_value_ref<'a, R>(rd: &mut R) -> Result<ValueRef<'a>, Error>
         Marker::F32 => ValueRef::F32(read_data_f32(rd)?),
         Marker::F64 => ValueRef::F64(read_data_f64(rd)?),
         Marker::FixStr(len) => {
-            let res = read_str_data(rd, len as usize)?;
-            ValueRef::String(res)
+            read_str_data(rd, len as u32, budget)?
         }
         Marker::Str8 => {
             let len = read_data_u8(rd)?;
-            let res = read_str_data(rd, len as usize)?;
-            ValueRef::String(res)
+            read_str_data(rd, len as u32, budget)?
         }
         Marker::Str16 => {
             let len = read_data_u16(rd)?;
-            let res = read_str_data(rd, len as usize)?;
-            ValueRef::String(res)
+            read_str_data(rd, len as u32, budget)?
         }
         Marker::Str32 => {
             let len = read_data_u32(rd)?;
-            let res = read_str_data(rd, len as usize)?;
-            ValueRef::String(res)
+            read_str_data(rd, len, budget)?
         }
         Marker::Bin8 => {
             let len = read_data_u8(rd)?;
-            let res = read_bin_data(rd, len as usize)?;
+            let res = read_bin_data(rd, len as u32, budget)?;
             ValueRef::Binary(res)
         }
         Marker::Bin16 => {
             let len = read_data_u16(rd)?;
-            let res = read_bin_data(rd, len as usize)?;
+            let res = read_bin_data(rd, len as u32, budget)?;
             ValueRef::Binary(res)
         }
         Marker::Bin32 => {
             let len = read_data_u32(rd)?;
-            let res = read_bin_data(rd, len as usize)?;
+            let res = read_bin_data(rd, len, budget)?;
             ValueRef::Binary(res)
         }
         Marker::FixArray(len) => {
-            let vec = read_array_data(rd, len as usize)?;
+            let vec = read_array_data(rd, len as u32, budget)?;
             ValueRef::Array(vec)
         }
         Marker::Array16 => {
             let len = read_data_u16(rd)?;
-            let vec = read_array_data(rd, len as usize)?;
+            let vec = read_array_data(rd, len as u32, budget)?;
             ValueRef::Array(vec)
         }
         Marker::Array32 => {
             let len = read_data_u32(rd)?;
-            let vec = read_array_data(rd, len as usize)?;
+            let vec = read_array_data(rd, len, budget)?;
             ValueRef::Array(vec)
         }
         Marker::FixMap(len) => {
-            let map = read_map_data(rd, len as usize)?;
+            let map = read_map_data(rd, len as u32, budget)?;
             ValueRef::Map(map)
         }
         Marker::Map16 => {
             let len = read_data_u16(rd)?;
-            let map = read_map_data(rd, len as usize)?;
+            let map = read_map_data(rd, len as u32, budget)?;
             ValueRef::Map(map)
         }
         Marker::Map32 => {
             let len = read_data_u32(rd)?;
-            let map = read_map_data(rd, len as usize)?;
+            let map = read_map_data(rd, len, budget)?;
             ValueRef::Map(map)
         }
         Marker::FixExt1 => {
             let len = 1;
-            let (ty, vec) = read_ext_body(rd, len as usize)?;
+            let (ty, vec) = read_ext_body(rd, len as u32, budget)?;
             ValueRef::Ext(ty, vec)
         }
         Marker::FixExt2 => {
             let len = 2;
-            let (ty, vec) = read_ext_body(rd, len as usize)?;
+            let (ty, vec) = read_ext_body(rd, len as u32, budget)?;
             ValueRef::Ext(ty, vec)
         }
         Marker::FixExt4 => {
             let len = 4;
-            let (ty, vec) = read_ext_body(rd, len as usize)?;
+            let (ty, vec) = read_ext_body(rd, len as u32, budget)?;
             ValueRef::Ext(ty, vec)
         }
         Marker::FixExt8 => {
             let len = 8;
-            let (ty, vec) = read_ext_body(rd, len as usize)?;
+            let (ty, vec) = read_ext_body(rd, len as u32, budget)?;
             ValueRef::Ext(ty, vec)
         }
         Marker::FixExt16 => {
             let len = 16;
-            let (ty, vec) = read_ext_body(rd, len as usize)?;
+            let (ty, vec) = read_ext_body(rd, len as u32, budget)?;
             ValueRef::Ext(ty, vec)
         }
         Marker::Ext8 => {
             let len = read_data_u8(rd)?;
-            let (ty, vec) = read_ext_body(rd, len as usize)?;
+            let (ty, vec) = read_ext_body(rd, len as u32, budget)?;
             ValueRef::Ext(ty, vec)
         }
         Marker::Ext16 => {
             let len = read_data_u16(rd)?;
-            let (ty, vec) = read_ext_body(rd, len as usize)?;
+            let (ty, vec) = read_ext_body(rd, len as u32, budget)?;
             ValueRef::Ext(ty, vec)
         }
         Marker::Ext32 => {
             let len = read_data_u32(rd)?;
-            let (ty, vec) = read_ext_body(rd, len as usize)?;
+            let (ty, vec) = read_ext_body(rd, len, budget)?;
             ValueRef::Ext(ty, vec)
         }
         Marker::Reserved => ValueRef::Nil,
@@ -0,0 +1,266 @@
+//! Deduplication of repeated subtrees through extension back-references.
+//!
+//! Documents with heavy structural sharing, like lists of records pointing to the same nested
+//! objects, repeat the same bytes over and over when encoded. `compress` replaces every repeated
+//! array, map, string or binary with a small extension value referencing its first occurrence,
+//! and `expand` reconstructs the original value on the receiving side.
+//!
+//! Values are numbered in the order of a depth-first walk, counting each array, map, string and
+//! binary that is written out in full. A back-reference is an extension of the chosen type with
+//! the 4-byte big-endian number of the referenced value as its data. It is emitted only for
+//! values whose encoding is longer than the reference itself, and only once the referenced value
+//! is complete, so a value can't refer to any of its ancestors.
+//!
+//! Subtrees are compared by their encoding, so data that was shared through `Arc`s before being
+//! converted into a `Value` is deduplicated just as well as data that only happens to be equal.
+//!
+//! Both sides must agree on the extension type, which must not be used for anything else in the
+//! document.
+//!
+//! # Examples
+//!
+//! ```
+//! use rmpv::Value;
+//! use rmpv::dedup;
+//!
+//! let address = Value::Map(vec![
+//!     (Value::from("city"), Value::from("Saint Petersburg")),
+//!     (Value::from("street"), Value::from("Nevsky prospect")),
+//! ]);
+//! let val = Value::Array(vec![address.clone(), address.clone(), address]);
+//!
+//! let compressed = dedup::compress(&val, 42);
+//! assert_eq!(Value::Ext(42, vec![0, 0, 0, 1]), compressed[1]);
+//!
+//! let mut plain = Vec::new();
+//! rmpv::encode::write_value(&mut plain, &val).unwrap();
+//! let mut buf = Vec::new();
+//! rmpv::encode::write_value(&mut buf, &compressed).unwrap();
+//! assert!(buf.len() < plain.len() / 2);
+//!
+//! let decoded = rmpv::decode::read_value(&mut &buf[..]).unwrap();
+//! assert_eq!(val, dedup::expand(&decoded, 42).unwrap());
+//! ```
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::convert::Infallible;
+use core::fmt::{self, Display, Formatter};
+#[cfg(feature = "std")]
+use std::error;
+
+use rmp::encode::RmpWrite;
+
+use Value;
+use encode::write_value;
+
+/// Size of an encoded back-reference: a fixext4 marker, the type and the index.
+const REF_LEN: usize = 6;
+
+/// An error returned by `expand` when a back-reference is malformed or points to a value that
+/// doesn't precede it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct InvalidReference;
+
+impl Display for InvalidReference {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        f.write_str("invalid back-reference to a shared value")
+    }
+}
+
+#[cfg(feature = "std")]
+impl error::Error for InvalidReference {
+    fn description(&self) -> &str {
+        "invalid back-reference to a shared value"
+    }
+}
+
+/// Returns a copy of the value, where every repeated array, map, string or binary is replaced by
+/// a back-reference extension of the given type.
+///
+/// Every subtree is encoded to be compared with the previous ones, so the time spent grows with
+/// both the size and the depth of the value.
+pub fn compress(val: &Value, ty: i8) -> Value {
+    let mut compressor = Compressor {
+        ty,
+        seen: BTreeMap::new(),
+        next: 0,
+    };
+
+    compressor.compress(val)
+}
+
+/// Returns a copy of the value, where every back-reference extension of the given type is
+/// replaced by the value it points to.
+///
+/// # Errors
+///
+/// Returns `InvalidReference` if an extension of the given type doesn't hold 4 bytes or refers
+/// to a value that isn't complete at its position.
+pub fn expand(val: &Value, ty: i8) -> Result<Value, InvalidReference> {
+    let mut targets = Vec::new();
+    collect_refs(val, ty, &mut targets)?;
+    targets.sort();
+    targets.dedup();
+
+    let mut expander = Expander {
+        ty,
+        targets,
+        next: 0,
+        table: BTreeMap::new(),
+    };
+
+    expander.expand(val)
+}
+
+fn encode_ref(ty: i8, idx: u32) -> Value {
+    let data = vec![(idx >> 24) as u8, (idx >> 16) as u8, (idx >> 8) as u8, idx as u8];
+    Value::Ext(ty, data)
+}
+
+fn decode_ref(data: &[u8]) -> Result<u32, InvalidReference> {
+    if data.len() != 4 {
+        return Err(InvalidReference);
+    }
+
+    Ok(data.iter().fold(0, |idx, &byte| (idx << 8) | byte as u32))
+}
+
+struct Compressor {
+    ty: i8,
+    /// Maps the encodings of complete values to their numbers.
+    seen: BTreeMap<Vec<u8>, u32>,
+    next: u32,
+}
+
+impl Compressor {
+    fn compress(&mut self, val: &Value) -> Value {
+        match *val {
+            Value::Array(..) | Value::Map(..) | Value::String(..) | Value::Binary(..) => {}
+            ref val => return val.clone(),
+        }
+
+        let mut buf = Buf(Vec::new());
+        match write_value(&mut buf, val) {
+            Ok(()) => {}
+            Err(..) => unreachable!(),
+        }
+        let encoded = buf.0;
+
+        if encoded.len() > REF_LEN {
+            if let Some(&idx) = self.seen.get(&encoded) {
+                return encode_ref(self.ty, idx);
+            }
+        }
+
+        let idx = self.next;
+        self.next += 1;
+
+        let res = match *val {
+            Value::Array(ref vec) => {
+                Value::Array(vec.iter().map(|val| self.compress(val)).collect())
+            }
+            Value::Map(ref map) => {
+                let map = map.iter()
+                    .map(|entry| {
+                        let (ref key, ref val) = *entry;
+                        (self.compress(key), self.compress(val))
+                    })
+                    .collect();
+
+                Value::Map(map)
+            }
+            ref val => val.clone(),
+        };
+
+        self.seen.entry(encoded).or_insert(idx);
+
+        res
+    }
+}
+
+/// Collects the numbers of all referenced values, so that only those are retained while
+/// expanding.
+fn collect_refs(val: &Value, ty: i8, targets: &mut Vec<u32>) -> Result<(), InvalidReference> {
+    match *val {
+        Value::Ext(ext_ty, ref data) if ext_ty == ty => targets.push(decode_ref(data)?),
+        Value::Array(ref vec) => {
+            for val in vec {
+                collect_refs(val, ty, targets)?;
+            }
+        }
+        Value::Map(ref map) => {
+            for entry in map {
+                let (ref key, ref val) = *entry;
+                collect_refs(key, ty, targets)?;
+                collect_refs(val, ty, targets)?;
+            }
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+struct Expander {
+    ty: i8,
+    /// Sorted numbers of the referenced values.
+    targets: Vec<u32>,
+    next: u32,
+    /// Complete referenced values by their numbers.
+    table: BTreeMap<u32, Value>,
+}
+
+impl Expander {
+    fn expand(&mut self, val: &Value) -> Result<Value, InvalidReference> {
+        if let Value::Ext(ty, ref data) = *val {
+            if ty == self.ty {
+                let idx = decode_ref(data)?;
+                return self.table.get(&idx).cloned().ok_or(InvalidReference);
+            }
+        }
+
+        match *val {
+            Value::Array(..) | Value::Map(..) | Value::String(..) | Value::Binary(..) => {}
+            ref val => return Ok(val.clone()),
+        }
+
+        let idx = self.next;
+        self.next += 1;
+
+        let res = match *val {
+            Value::Array(ref vec) => {
+                let vec = vec.iter().map(|val| self.expand(val)).collect::<Result<_, _>>()?;
+                Value::Array(vec)
+            }
+            Value::Map(ref map) => {
+                let mut vec = Vec::with_capacity(map.len());
+                for entry in map {
+                    let (ref key, ref val) = *entry;
+                    vec.push((self.expand(key)?, self.expand(val)?));
+                }
+
+                Value::Map(vec)
+            }
+            ref val => val.clone(),
+        };
+
+        if self.targets.binary_search(&idx).is_ok() {
+            self.table.insert(idx, res.clone());
+        }
+
+        Ok(res)
+    }
+}
+
+/// A growable buffer to encode subtrees into.
+struct Buf(Vec<u8>);
+
+impl RmpWrite for Buf {
+    type Error = Infallible;
+
+    fn write_bytes(&mut self, buf: &[u8]) -> Result<(), Infallible> {
+        self.0.extend_from_slice(buf);
+        Ok(())
+    }
+}
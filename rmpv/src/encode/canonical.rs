@@ -0,0 +1,124 @@
+use alloc::vec::Vec;
+use core::fmt::{self, Display, Formatter};
+#[cfg(feature = "std")]
+use std::error;
+
+use rmp::encode::{RmpWrite, RmpWriteErr, ValueWriteError, write_array_len, write_map_len};
+
+use Value;
+use super::write_value;
+
+/// An error that can occur when writing a value in canonical form.
+#[derive(Debug)]
+pub enum CanonicalError<E: RmpWriteErr = ::rmp::encode::Error> {
+    /// Writing to the underlying writer failed.
+    Write(ValueWriteError<E>),
+    /// A map has several entries with the same key, holding the encoded key.
+    DuplicateKey(Vec<u8>),
+}
+
+impl<E: RmpWriteErr> From<ValueWriteError<E>> for CanonicalError<E> {
+    fn from(err: ValueWriteError<E>) -> CanonicalError<E> {
+        CanonicalError::Write(err)
+    }
+}
+
+impl<E: RmpWriteErr> Display for CanonicalError<E> {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            CanonicalError::Write(ref err) => Display::fmt(err, f),
+            CanonicalError::DuplicateKey(..) => f.write_str("duplicate map key"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: RmpWriteErr> error::Error for CanonicalError<E> {
+    fn description(&self) -> &str {
+        match *self {
+            CanonicalError::Write(..) => "error while writing MessagePack value",
+            CanonicalError::DuplicateKey(..) => "duplicate map key",
+        }
+    }
+
+    fn cause(&self) -> Option<&dyn error::Error> {
+        match *self {
+            CanonicalError::Write(ref err) => Some(err),
+            CanonicalError::DuplicateKey(..) => None,
+        }
+    }
+}
+
+/// Writes the value in canonical form, so that equal contents always give the same bytes.
+///
+/// The entries of every map are sorted by the bytes of their encoded keys, and maps holding the
+/// same key twice are rejected. Everything else is written like `write_value` does, which always
+/// picks the smallest format. The output can then be hashed or signed, and compared byte for byte
+/// with the canonical output of other implementations sorting the same way.
+///
+/// Keys are ordered like `rmp::canonicalize` does, but floats keep their width here.
+///
+/// # Errors
+///
+/// Returns `CanonicalError::DuplicateKey` if a map has several entries with the same key, in
+/// which case part of the value may already have been written.
+///
+/// # Examples
+///
+/// ```
+/// use rmpv::Value;
+/// use rmpv::encode::write_value_canonical;
+///
+/// let val = Value::Map(vec![
+///     (Value::from("b"), Value::from(2)),
+///     (Value::from("a"), Value::from(1)),
+/// ]);
+///
+/// let mut buf = Vec::new();
+/// write_value_canonical(&mut buf, &val).unwrap();
+///
+/// assert_eq!(vec![0x82, 0xa1, 0x61, 0x01, 0xa1, 0x62, 0x02], buf);
+/// ```
+pub fn write_value_canonical<W>(wr: &mut W, val: &Value) -> Result<(), CanonicalError<W::Error>>
+    where W: RmpWrite
+{
+    match *val {
+        Value::Array(ref vec) => {
+            write_array_len(wr, vec.len() as u32)?;
+            for val in vec {
+                write_value_canonical(wr, val)?;
+            }
+        }
+        Value::Map(ref map) => {
+            let mut entries = Vec::with_capacity(map.len());
+            for entry in map {
+                entries.push((encode(&entry.0)?, encode(&entry.1)?));
+            }
+            entries.sort_by(|lhs, rhs| lhs.0.cmp(&rhs.0));
+            if let Some(pair) = entries.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+                return Err(CanonicalError::DuplicateKey(pair[0].0.clone()));
+            }
+
+            write_map_len(wr, entries.len() as u32)?;
+            for (key, val) in entries {
+                wr.write_bytes(&key)
+                    .and_then(|()| wr.write_bytes(&val))
+                    .map_err(ValueWriteError::InvalidDataWrite)?;
+            }
+        }
+        ref val => write_value(wr, val)?,
+    }
+
+    Ok(())
+}
+
+/// Encodes the value in canonical form into a new buffer.
+fn encode<E: RmpWriteErr>(val: &Value) -> Result<Vec<u8>, CanonicalError<E>> {
+    let mut buf = Vec::new();
+    match write_value_canonical(&mut buf, val) {
+        Ok(()) => Ok(buf),
+        Err(CanonicalError::DuplicateKey(key)) => Err(CanonicalError::DuplicateKey(key)),
+        // Writing into a vector can't fail.
+        Err(CanonicalError::Write(..)) => unreachable!(),
+    }
+}
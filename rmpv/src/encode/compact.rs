@@ -0,0 +1,63 @@
+use core::str;
+
+use rmp::encode::{RmpWrite, ValueWriteError, write_nil, write_bool, write_uint, write_sint,
+                  write_f32, write_f64, write_str_len, write_bin_len, write_array_len,
+                  write_map_len, write_ext_meta};
+
+use {Integer, IntPriv};
+use compact::CompactValue;
+
+/// Encodes and attempts to write the given `CompactValue` the way `write_value` writes the equal
+/// `Value`.
+///
+/// Strings with invalid UTF-8 are written as binaries.
+pub fn write_compact_value<W>(wr: &mut W, val: &CompactValue) ->
+    Result<(), ValueWriteError<W::Error>>
+    where W: RmpWrite
+{
+    match *val {
+        CompactValue::Nil => write_nil(wr).map_err(ValueWriteError::InvalidMarkerWrite)?,
+        CompactValue::Boolean(val) => {
+            write_bool(wr, val).map_err(ValueWriteError::InvalidMarkerWrite)?
+        }
+        CompactValue::Integer(Integer { n: IntPriv::PosInt(n) }) => {
+            write_uint(wr, n)?;
+        }
+        CompactValue::Integer(Integer { n: IntPriv::NegInt(n) }) => {
+            write_sint(wr, n)?;
+        }
+        CompactValue::F32(val) => write_f32(wr, val)?,
+        CompactValue::F64(val) => write_f64(wr, val)?,
+        CompactValue::String(ref data) => {
+            if str::from_utf8(data).is_ok() {
+                write_str_len(wr, data.len() as u32)?;
+            } else {
+                write_bin_len(wr, data.len() as u32)?;
+            }
+            wr.write_bytes(data).map_err(ValueWriteError::InvalidDataWrite)?;
+        }
+        CompactValue::Binary(ref data) => {
+            write_bin_len(wr, data.len() as u32)?;
+            wr.write_bytes(data).map_err(ValueWriteError::InvalidDataWrite)?;
+        }
+        CompactValue::Array(ref vec) => {
+            write_array_len(wr, vec.len() as u32)?;
+            for val in vec {
+                write_compact_value(wr, val)?;
+            }
+        }
+        CompactValue::Map(ref map) => {
+            write_map_len(wr, map.len() as u32)?;
+            for entry in map {
+                write_compact_value(wr, &entry.0)?;
+                write_compact_value(wr, &entry.1)?;
+            }
+        }
+        CompactValue::Ext(ty, ref data) => {
+            write_ext_meta(wr, data.len() as u32, ty)?;
+            wr.write_bytes(data).map_err(ValueWriteError::InvalidDataWrite)?;
+        }
+    }
+
+    Ok(())
+}
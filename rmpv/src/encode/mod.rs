@@ -1,7 +1,11 @@
 pub use rmp::encode::ValueWriteError as Error;
 
+mod canonical;
+mod compact;
 mod value;
 mod value_ref;
 
+pub use self::canonical::{write_value_canonical, CanonicalError};
+pub use self::compact::write_compact_value;
 pub use self::value::write_value;
 pub use self::value_ref::write_value_ref;
@@ -1,10 +1,8 @@
-use std::io::Write;
-
-use rmp::encode::{write_nil, write_bool, write_uint, write_sint, write_f32, write_f64, write_str,
-                  write_bin, write_array_len, write_map_len, write_ext_meta};
+use rmp::encode::{RmpWrite, ValueWriteError, write_nil, write_bool, write_uint, write_sint,
+                  write_f32, write_f64, write_str, write_bin, write_array_len, write_map_len,
+                  write_ext_meta};
 
 use {Integer, IntPriv, Utf8String, Value};
-use super::Error;
 
 /// Encodes and attempts to write the most efficient representation of the given Value.
 ///
@@ -12,15 +10,15 @@ use super::Error;
 ///
 /// All instances of `ErrorKind::Interrupted` are handled by this function and the underlying
 /// operation is retried.
-pub fn write_value<W>(wr: &mut W, val: &Value) -> Result<(), Error>
-    where W: Write
+pub fn write_value<W>(wr: &mut W, val: &Value) -> Result<(), ValueWriteError<W::Error>>
+    where W: RmpWrite
 {
     match *val {
         Value::Nil => {
-            write_nil(wr).map_err(|err| Error::InvalidMarkerWrite(err))?;
+            write_nil(wr).map_err(ValueWriteError::InvalidMarkerWrite)?;
         }
         Value::Boolean(val) => {
-            write_bool(wr, val).map_err(|err| Error::InvalidMarkerWrite(err))?;
+            write_bool(wr, val).map_err(ValueWriteError::InvalidMarkerWrite)?;
         }
         Value::Integer(Integer { n }) => {
             match n {
@@ -62,7 +60,7 @@ pub fn write_value<W>(wr: &mut W, val: &Value) -> Result<(), Error>
         }
         Value::Ext(ty, ref data) => {
             write_ext_meta(wr, data.len() as u32, ty)?;
-            wr.write_all(data).map_err(|err| Error::InvalidDataWrite(err))?;
+            wr.write_bytes(data).map_err(ValueWriteError::InvalidDataWrite)?;
         }
     }
 
@@ -1,10 +1,8 @@
-use std::io::Write;
-
-use rmp::encode::{write_bool, write_nil, write_sint, write_uint, write_f32, write_f64, write_str,
-                  write_bin, write_array_len, write_map_len, write_ext_meta};
+use rmp::encode::{RmpWrite, ValueWriteError, write_bool, write_nil, write_sint, write_uint,
+                  write_f32, write_f64, write_str, write_bin, write_array_len, write_map_len,
+                  write_ext_meta};
 
 use {Integer, IntPriv, Utf8StringRef, ValueRef};
-use super::Error;
 
 /// Encodes and attempts to write the given non-owning ValueRef into the Write.
 ///
@@ -24,15 +22,15 @@ use super::Error;
 /// write_value_ref(&mut buf, &val).unwrap();
 /// assert_eq!(vec![0xaa, 0x6c, 0x65, 0x20, 0x6d, 0x65, 0x73, 0x73, 0x61, 0x67, 0x65], buf);
 /// ```
-pub fn write_value_ref<W>(wr: &mut W, val: &ValueRef) -> Result<(), Error>
-    where W: Write
+pub fn write_value_ref<W>(wr: &mut W, val: &ValueRef) -> Result<(), ValueWriteError<W::Error>>
+    where W: RmpWrite
 {
     match *val {
         ValueRef::Nil => {
-            write_nil(wr).map_err(|err| Error::InvalidMarkerWrite(err))?;
+            write_nil(wr).map_err(ValueWriteError::InvalidMarkerWrite)?;
         }
         ValueRef::Boolean(val) => {
-            write_bool(wr, val).map_err(|err| Error::InvalidMarkerWrite(err))?;
+            write_bool(wr, val).map_err(ValueWriteError::InvalidMarkerWrite)?;
         }
         ValueRef::Integer(Integer { n }) => {
             match n {
@@ -74,7 +72,7 @@ pub fn write_value_ref<W>(wr: &mut W, val: &ValueRef) -> Result<(), Error>
         }
         ValueRef::Ext(ty, data) => {
             write_ext_meta(wr, data.len() as u32, ty)?;
-            wr.write_all(data).map_err(|err| Error::InvalidDataWrite(err))?;
+            wr.write_bytes(data).map_err(ValueWriteError::InvalidDataWrite)?;
         }
     }
 
@@ -1,14 +1,15 @@
 use std::fmt::{self, Display, Formatter};
-use std::iter::ExactSizeIterator;
+use std::iter::{ExactSizeIterator, Map};
 use std::slice::Iter;
 use std::vec::IntoIter;
 
 use serde::{self, Deserialize, Deserializer};
 use serde::de::{self, DeserializeSeed, IntoDeserializer, SeqAccess, Unexpected, Visitor};
+use serde_bytes::ByteBuf;
 
 use {Integer, IntPriv, Utf8String, Utf8StringRef, Value, ValueRef};
 
-use super::{Error, ValueExt};
+use super::{Error, ValueExt, EXT_STRUCT_NAME};
 
 pub fn from_value<T>(val: Value) -> Result<T, Error>
     where T: for<'de> Deserialize<'de>
@@ -16,6 +17,61 @@ pub fn from_value<T>(val: Value) -> Result<T, Error>
     deserialize_from(val)
 }
 
+/// Deserializes a value of type `T` from a borrowed `Value`, without cloning it.
+///
+/// Strings and binaries are passed to `T` borrowed from the value, so `T` may hold `&str` and
+/// `&[u8]` fields pointing into it, and a single decoded document can be projected into several
+/// typed structs.
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::BTreeMap;
+///
+/// use rmpv::Value;
+/// use rmpv::ext::from_borrowed_value;
+///
+/// let doc = Value::Map(vec![
+///     (Value::from("user"), Value::Map(vec![(Value::from("name"), Value::from("alice"))])),
+///     (Value::from("tags"), Value::Array(vec![Value::from("admin")])),
+/// ]);
+///
+/// let user: BTreeMap<&str, &str> = from_borrowed_value(&doc["user"]).unwrap();
+/// let tags: Vec<&str> = from_borrowed_value(&doc["tags"]).unwrap();
+/// assert_eq!("alice", user["name"]);
+/// assert_eq!(vec!["admin"], tags);
+/// ```
+pub fn from_borrowed_value<'de, T>(val: &'de Value) -> Result<T, Error>
+    where T: Deserialize<'de>
+{
+    deserialize_from(val)
+}
+
+/// Deserializes a value of type `T` from a `ValueRef`.
+///
+/// Together with `decode::read_value_ref` this deserializes a typed struct from a slice without
+/// copying: `&str` and `&[u8]` fields of `T` borrow from the slice the `ValueRef` points into.
+///
+/// # Examples
+///
+/// ```
+/// use rmpv::decode::read_value_ref;
+/// use rmpv::ext::from_value_ref;
+///
+/// // ["John", [1, 2]]
+/// let buf = [0x92, 0xa4, 0x4a, 0x6f, 0x68, 0x6e, 0xc4, 0x02, 0x01, 0x02];
+/// let val = read_value_ref(&mut &buf[..]).unwrap();
+///
+/// let (name, key): (&str, &[u8]) = from_value_ref(val).unwrap();
+/// assert_eq!("John", name);
+/// assert_eq!(&[1, 2], key);
+/// ```
+pub fn from_value_ref<'de, T>(val: ValueRef<'de>) -> Result<T, Error>
+    where T: Deserialize<'de>
+{
+    deserialize_from(val)
+}
+
 pub fn deserialize_from<'de, T, D>(val: D) -> Result<T, Error>
     where T: Deserialize<'de>,
           D: Deserializer<'de, Error = Error>
@@ -115,6 +171,15 @@ impl<'de> Deserialize<'de> for Value {
                 Ok(Value::Binary(v.to_owned()))
             }
 
+            /// Extensions are passed by `rmp_serde` as a newtype struct of their type and data.
+            #[inline]
+            fn visit_newtype_struct<D>(self, de: D) -> Result<Value, D::Error>
+                where D: de::Deserializer<'de>
+            {
+                let (ty, data): (i8, ByteBuf) = Deserialize::deserialize(de)?;
+                Ok(Value::Ext(ty, data.into()))
+            }
+
             #[inline]
             fn visit_map<V>(self, mut visitor: V) -> Result<Value, V::Error>
                 where V: de::MapAccess<'de>
@@ -221,6 +286,14 @@ impl<'de> Deserialize<'de> for ValueRef<'de> {
                 Ok(ValueRef::Binary(v))
             }
 
+            #[inline]
+            fn visit_newtype_struct<D>(self, de: D) -> Result<Self::Value, D::Error>
+                where D: Deserializer<'de>
+            {
+                let (ty, data) = Deserialize::deserialize(de)?;
+                Ok(ValueRef::Ext(ty, data))
+            }
+
             #[inline]
             fn visit_map<V>(self, mut visitor: V) -> Result<Self::Value, V::Error>
                 where V: de::MapAccess<'de>
@@ -285,13 +358,8 @@ impl<'de> Deserializer<'de> for Value {
                     Err(de::Error::invalid_length(len, &"fewer elements in map"))
                 }
             }
-            Value::Ext(..) => {
-                // TODO: [i8, [u8]] can be represented as:
-                //      - (0i8, Vec<u8>),
-                //      - struct F(i8, Vec<u8>),
-                //      - struct F {ty: i8, val: Vec<u8>}
-                //      - enum F{ A(Vec<u8>), B { name: Vec<u8> } }
-                unimplemented!();
+            Value::Ext(ty, v) => {
+                visitor.visit_newtype_struct(ExtDeserializer::new(ty, Value::Binary(v)))
             }
         }
     }
@@ -311,9 +379,12 @@ impl<'de> Deserializer<'de> for Value {
     }
 
     #[inline]
-    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value, Self::Error>
+    fn deserialize_newtype_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value, Self::Error>
         where V: Visitor<'de>
     {
+        if name == EXT_STRUCT_NAME {
+            return self.deserialize_any(visitor);
+        }
         ValueBase::deserialize_newtype_struct(self, visitor)
     }
 
@@ -376,8 +447,8 @@ impl<'de> Deserializer<'de> for ValueRef<'de> {
                     Err(de::Error::invalid_length(len, &"fewer elements in map"))
                 }
             }
-            ValueRef::Ext(..) => {
-                unimplemented!();
+            ValueRef::Ext(ty, v) => {
+                visitor.visit_newtype_struct(ExtDeserializer::new(ty, ValueRef::Binary(v)))
             }
         }
     }
@@ -397,9 +468,12 @@ impl<'de> Deserializer<'de> for ValueRef<'de> {
     }
 
     #[inline]
-    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value, Self::Error>
+    fn deserialize_newtype_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value, Self::Error>
         where V: Visitor<'de>
     {
+        if name == EXT_STRUCT_NAME {
+            return self.deserialize_any(visitor);
+        }
         ValueBase::deserialize_newtype_struct(self, visitor)
     }
 
@@ -462,8 +536,8 @@ impl<'de> Deserializer<'de> for &'de ValueRef<'de> {
                     Err(de::Error::invalid_length(len, &"fewer elements in map"))
                 }
             }
-            ValueRef::Ext(..) => {
-                unimplemented!();
+            ValueRef::Ext(ty, v) => {
+                visitor.visit_newtype_struct(ExtDeserializer::new(ty, ValueRef::Binary(v)))
             }
         }
     }
@@ -505,9 +579,12 @@ impl<'de> Deserializer<'de> for &'de ValueRef<'de> {
     }
 
     #[inline]
-    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value, Self::Error>
+    fn deserialize_newtype_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value, Self::Error>
         where V: Visitor<'de>
     {
+        if name == EXT_STRUCT_NAME {
+            return self.deserialize_any(visitor);
+        }
         match self {
             &ValueRef::Array(ref v) => {
                 let iter = v.into_iter();
@@ -776,6 +853,154 @@ impl<'de, U: ValueBase<'de> + ValueExt> de::VariantAccess<'de> for VariantDeseri
     }
 }
 
+impl<'de> Deserializer<'de> for &'de Value {
+    type Error = Error;
+
+    #[inline]
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+        where V: Visitor<'de>
+    {
+        match *self {
+            Value::Nil => visitor.visit_unit(),
+            Value::Boolean(v) => visitor.visit_bool(v),
+            Value::Integer(Integer { n }) => {
+                match n {
+                    IntPriv::PosInt(v) => visitor.visit_u64(v),
+                    IntPriv::NegInt(v) => visitor.visit_i64(v)
+                }
+            }
+            Value::F32(v) => visitor.visit_f32(v),
+            Value::F64(v) => visitor.visit_f64(v),
+            Value::String(ref v) => {
+                match v.s {
+                    Ok(ref v) => visitor.visit_borrowed_str(v),
+                    Err(ref v) => visitor.visit_borrowed_bytes(&v.0),
+                }
+            }
+            Value::Binary(ref v) => visitor.visit_borrowed_bytes(v),
+            Value::Array(ref v) => {
+                let len = v.len();
+                let mut de = SeqDeserializer::new(v.iter());
+                let seq = visitor.visit_seq(&mut de)?;
+                if de.iter.len() == 0 {
+                    Ok(seq)
+                } else {
+                    Err(de::Error::invalid_length(len, &"fewer elements in array"))
+                }
+            }
+            Value::Map(ref v) => {
+                let len = v.len();
+                let mut de = MapDeserializer::new(v.iter().map(entry_refs as EntryRefs));
+                let map = visitor.visit_map(&mut de)?;
+                if de.iter.len() == 0 {
+                    Ok(map)
+                } else {
+                    Err(de::Error::invalid_length(len, &"fewer elements in map"))
+                }
+            }
+            Value::Ext(ty, ref v) => {
+                visitor.visit_newtype_struct(ExtDeserializer::new(ty, ValueRef::Binary(v)))
+            }
+        }
+    }
+
+    #[inline]
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+        where V: Visitor<'de>
+    {
+        ValueBase::deserialize_option(self, visitor)
+    }
+
+    #[inline]
+    fn deserialize_enum<V>(self, _name: &str, _variants: &'static [&'static str], visitor: V) -> Result<V::Value, Self::Error>
+        where V: Visitor<'de>
+    {
+        ValueBase::deserialize_enum(self, visitor)
+    }
+
+    #[inline]
+    fn deserialize_newtype_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value, Self::Error>
+        where V: Visitor<'de>
+    {
+        if name == EXT_STRUCT_NAME {
+            return self.deserialize_any(visitor);
+        }
+        ValueBase::deserialize_newtype_struct(self, visitor)
+    }
+
+    #[inline]
+    fn deserialize_unit_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value, Self::Error>
+        where V: Visitor<'de>
+    {
+        ValueBase::deserialize_unit_struct(self, visitor)
+    }
+
+    forward_to_deserialize_any! {
+        bool u8 u16 u32 u64 i8 i16 i32 i64 f32 f64 char str string unit seq
+        bytes byte_buf map tuple_struct struct
+        identifier tuple ignored_any
+    }
+}
+
+/// Presents an extension as the tuple of its type and data, as `rmp_serde` does.
+///
+/// The data is given by a binary value, which either owns it or borrows it.
+struct ExtDeserializer<D> {
+    ty: i8,
+    data: Option<D>,
+    // Number of tuple elements produced.
+    pos: u8,
+}
+
+impl<D> ExtDeserializer<D> {
+    fn new(ty: i8, data: D) -> Self {
+        ExtDeserializer { ty, data: Some(data), pos: 0 }
+    }
+}
+
+impl<'de, D: Deserializer<'de, Error = Error>> SeqAccess<'de> for ExtDeserializer<D> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
+        where T: DeserializeSeed<'de>
+    {
+        self.pos += 1;
+        match self.pos {
+            1 => seed.deserialize(self.ty.into_deserializer()).map(Some),
+            _ => match self.data.take() {
+                Some(data) => seed.deserialize(data).map(Some),
+                None => Ok(None),
+            },
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(2usize.saturating_sub(self.pos as usize))
+    }
+}
+
+impl<'de, D: Deserializer<'de, Error = Error>> Deserializer<'de> for ExtDeserializer<D> {
+    type Error = Error;
+
+    #[inline]
+    fn deserialize_any<V>(mut self, visitor: V) -> Result<V::Value, Self::Error>
+        where V: Visitor<'de>
+    {
+        let val = visitor.visit_seq(&mut self)?;
+        if self.pos < 2 {
+            return Err(de::Error::invalid_length(self.pos as usize, &"an extension tuple"));
+        }
+
+        Ok(val)
+    }
+
+    forward_to_deserialize_any! {
+        bool u8 u16 u32 u64 i8 i16 i32 i64 f32 f64 char str string bytes byte_buf option unit
+        unit_struct newtype_struct seq tuple tuple_struct map struct enum identifier
+        ignored_any
+    }
+}
+
 pub struct MapRefDeserializer<'de> {
     val: Option<&'de ValueRef<'de>>,
     iter: Iter<'de, (ValueRef<'de>, ValueRef<'de>)>,
@@ -1084,3 +1309,37 @@ impl<'de> ValueBase<'de> for ValueRef<'de> {
         }
     }
 }
+
+type EntryRefs<'de> = fn(&'de (Value, Value)) -> (&'de Value, &'de Value);
+
+fn entry_refs(entry: &(Value, Value)) -> (&Value, &Value) {
+    (&entry.0, &entry.1)
+}
+
+impl<'de> ValueBase<'de> for &'de Value {
+    type Item = &'de Value;
+    type Iter = Iter<'de, Value>;
+    type MapIter = Map<Iter<'de, (Value, Value)>, EntryRefs<'de>>;
+    type MapDeserializer = MapDeserializer<Self::MapIter, Self::Item>;
+
+    #[inline]
+    fn is_nil(&self) -> bool {
+        Value::is_nil(self)
+    }
+
+    #[inline]
+    fn into_iter(self) -> Result<Self::Iter, Self::Item> {
+        match *self {
+            Value::Array(ref v) => Ok(v.iter()),
+            ref other => Err(other)
+        }
+    }
+
+    #[inline]
+    fn into_map_iter(self) -> Result<Self::MapIter, Self::Item> {
+        match *self {
+            Value::Map(ref v) => Ok(v.iter().map(entry_refs as EntryRefs)),
+            ref other => Err(other)
+        }
+    }
+}
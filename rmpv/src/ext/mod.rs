@@ -5,12 +5,20 @@ use serde::de::Unexpected;
 
 use {Integer, IntPriv, Value, ValueRef};
 
-pub use self::de::{deserialize_from, from_value, EnumRefDeserializer};
-pub use self::se::to_value;
+pub use self::de::{deserialize_from, from_borrowed_value, from_value, from_value_ref,
+                   EnumRefDeserializer};
+pub use self::se::{to_value, to_value_named};
 
 mod de;
 mod se;
 
+/// Name of the newtype struct around `(type, data)` that `rmp_serde` reads and writes extensions
+/// as.
+///
+/// Must match `rmp_serde::MSGPACK_EXT_STRUCT_NAME`, which can't be used here without depending on
+/// it.
+const EXT_STRUCT_NAME: &str = "_ExtStruct";
+
 #[derive(Debug)]
 pub enum Error {
     Syntax(String),
@@ -67,6 +75,12 @@ impl ValueExt for Value {
     }
 }
 
+impl ValueExt for &Value {
+    fn unexpected(&self) -> Unexpected<'_> {
+        (**self).unexpected()
+    }
+}
+
 impl<'a> ValueExt for ValueRef<'a> {
     fn unexpected(&self) -> Unexpected {
         match *self {
@@ -1,13 +1,18 @@
 use std::fmt::Display;
 
 use serde::Serialize;
-use serde::ser::{self, SerializeSeq, SerializeTuple, SerializeTupleStruct, SerializeMap, SerializeStruct};
+use serde::ser::{self, SerializeSeq, SerializeTuple, SerializeTupleStruct, SerializeMap};
 use serde_bytes::Bytes;
 
-use {Integer, IntPriv, Value};
+use {Integer, IntPriv, Value, ValueRef};
 
-use super::Error;
+use super::{Error, EXT_STRUCT_NAME};
 
+/// Extensions are serialized as a newtype struct named `_ExtStruct` around an `(i8, bytes)` tuple,
+/// which `rmp_serde` writes as a native extension and `to_value` turns back into `Value::Ext`.
+///
+/// Other serializers see an ordinary newtype struct, which most of them write as its inner tuple:
+/// JSON, for example, gets `[42, [1, 2]]` for `Value::Ext(42, vec![1, 2])`.
 impl Serialize for Value {
     fn serialize<S>(&self, s: S) -> Result<S::Ok, S::Error>
         where S: ser::Serializer
@@ -45,11 +50,51 @@ impl Serialize for Value {
                 state.end()
             }
             Value::Ext(ty, ref buf) => {
-                let mut state = s.serialize_seq(Some(2))?;
-                state.serialize_element(&ty)?;
-                state.serialize_element(buf)?;
+                s.serialize_newtype_struct(EXT_STRUCT_NAME, &(ty, Bytes::from(&buf[..])))
+            }
+        }
+    }
+}
+
+impl<'a> Serialize for ValueRef<'a> {
+    fn serialize<S>(&self, s: S) -> Result<S::Ok, S::Error>
+        where S: ser::Serializer
+    {
+        match *self {
+            ValueRef::Nil => s.serialize_unit(),
+            ValueRef::Boolean(v) => s.serialize_bool(v),
+            ValueRef::Integer(Integer { n }) => {
+                match n {
+                    IntPriv::PosInt(n) => s.serialize_u64(n),
+                    IntPriv::NegInt(n) => s.serialize_i64(n),
+                }
+            }
+            ValueRef::F32(v) => s.serialize_f32(v),
+            ValueRef::F64(v) => s.serialize_f64(v),
+            ValueRef::String(ref v) => {
+                match v.as_str() {
+                    Some(v) => s.serialize_str(v),
+                    None => Bytes::from(v.as_bytes()).serialize(s),
+                }
+            }
+            ValueRef::Binary(v) => Bytes::from(v).serialize(s),
+            ValueRef::Array(ref array) => {
+                let mut state = s.serialize_seq(Some(array.len()))?;
+                for item in array {
+                    state.serialize_element(item)?;
+                }
                 state.end()
             }
+            ValueRef::Map(ref map) => {
+                let mut state = s.serialize_map(Some(map.len()))?;
+                for (key, val) in map {
+                    state.serialize_entry(key, val)?;
+                }
+                state.end()
+            }
+            ValueRef::Ext(ty, buf) => {
+                s.serialize_newtype_struct(EXT_STRUCT_NAME, &(ty, Bytes::from(buf)))
+            }
         }
     }
 }
@@ -60,7 +105,9 @@ impl ser::Error for Error {
     }
 }
 
-struct Serializer;
+struct Serializer {
+    named: bool,
+}
 
 /// Convert a `T` into `rmpv::Value` which is an enum that can represent any valid MessagePack data.
 ///
@@ -74,7 +121,44 @@ struct Serializer;
 /// assert_eq!(Value::String("John Smith".into()), val);
 /// ```
 pub fn to_value<T: Serialize>(value: T) -> Result<Value, Error> {
-    value.serialize(Serializer)
+    value.serialize(Serializer { named: false })
+}
+
+/// Convert a `T` into `rmpv::Value`, with structs represented as maps from field names to values.
+///
+/// Struct variants become a map in place of the array of fields. Use it to produce values for
+/// consumers which expect maps, like most dynamically typed MessagePack implementations.
+///
+/// ```rust
+/// # extern crate serde;
+/// # extern crate rmpv;
+/// # use serde::ser::{Serialize, SerializeStruct, Serializer};
+/// # use rmpv::Value;
+/// #
+/// struct User {
+///     name: String,
+/// }
+///
+/// impl Serialize for User {
+///     fn serialize<S: Serializer>(&self, se: S) -> Result<S::Ok, S::Error> {
+///         let mut state = se.serialize_struct("User", 1)?;
+///         state.serialize_field("name", &self.name)?;
+///         state.end()
+///     }
+/// }
+///
+/// # fn main() {
+/// let val = rmpv::ext::to_value_named(User { name: "John".into() }).unwrap();
+///
+/// assert_eq!(Value::Map(vec![(Value::from("name"), Value::from("John"))]), val);
+/// # }
+/// ```
+pub fn to_value_named<T: Serialize>(value: T) -> Result<Value, Error> {
+    value.serialize(Serializer { named: true })
+}
+
+fn to_value_with<T: ?Sized + Serialize>(value: &T, named: bool) -> Result<Value, Error> {
+    value.serialize(Serializer { named: named })
 }
 
 impl ser::Serializer for Serializer {
@@ -86,7 +170,7 @@ impl ser::Serializer for Serializer {
     type SerializeTupleStruct = SerializeVec;
     type SerializeTupleVariant = SerializeTupleVariant;
     type SerializeMap = DefaultSerializeMap;
-    type SerializeStruct = SerializeVec;
+    type SerializeStruct = SerializeStruct;
     type SerializeStructVariant = SerializeStructVariant;
 
     #[inline]
@@ -181,10 +265,25 @@ impl ser::Serializer for Serializer {
     }
 
     #[inline]
-    fn serialize_newtype_struct<T: ?Sized>(self, _name: &'static str, value: &T) -> Result<Self::Ok, Self::Error>
+    fn serialize_newtype_struct<T: ?Sized>(self, name: &'static str, value: &T) -> Result<Self::Ok, Self::Error>
         where T: Serialize
     {
-        Ok(Value::Array(vec![to_value(value)?]))
+        if name == EXT_STRUCT_NAME {
+            if let Value::Array(ref vec) = to_value_with(value, self.named)? {
+                if let [ref ty, Value::Binary(ref data)] = vec[..] {
+                    match ty.as_i64() {
+                        Some(ty) if ty as i8 as i64 == ty => {
+                            return Ok(Value::Ext(ty as i8, data.clone()));
+                        }
+                        _ => {}
+                    }
+                }
+            }
+
+            return Err(Error::Syntax("expected a tuple of an extension type and bytes".into()));
+        }
+
+        Ok(Value::Array(vec![to_value_with(value, self.named)?]))
     }
 
     fn serialize_newtype_variant<T: ?Sized>(self, _name: &'static str, idx: u32, _variant: &'static str, value: &T) -> Result<Self::Ok, Self::Error>
@@ -192,7 +291,7 @@ impl ser::Serializer for Serializer {
     {
         let vec = vec![
             Value::from(idx),
-            Value::Array(vec![to_value(value)?]),
+            Value::Array(vec![to_value_with(value, self.named)?]),
         ];
         Ok(Value::Array(vec))
     }
@@ -211,6 +310,7 @@ impl ser::Serializer for Serializer {
 
     fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
         let se = SerializeVec {
+            named: self.named,
             vec: Vec::with_capacity(len.unwrap_or(0))
         };
         Ok(se)
@@ -226,6 +326,7 @@ impl ser::Serializer for Serializer {
 
     fn serialize_tuple_variant(self, _name: &'static str, idx: u32, _variant: &'static str, len: usize) -> Result<Self::SerializeTupleVariant, Error> {
         let se = SerializeTupleVariant {
+            named: self.named,
             idx: idx,
             vec: Vec::with_capacity(len),
         };
@@ -234,20 +335,28 @@ impl ser::Serializer for Serializer {
 
     fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, Error> {
         let se = DefaultSerializeMap {
+            named: self.named,
             map: Vec::with_capacity(len.unwrap_or(0)),
             next_key: None,
         };
         Ok(se)
     }
 
-    fn serialize_struct(self, name: &'static str, len: usize) -> Result<Self::SerializeStruct, Error> {
-        self.serialize_tuple_struct(name, len)
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<Self::SerializeStruct, Error> {
+        let se = SerializeStruct {
+            named: self.named,
+            fields: Vec::with_capacity(len),
+        };
+        Ok(se)
     }
 
     fn serialize_struct_variant(self, _name: &'static str, idx: u32, _variant: &'static str, len: usize) -> Result<Self::SerializeStructVariant, Error> {
         let se = SerializeStructVariant {
             idx: idx,
-            vec: Vec::with_capacity(len),
+            fields: SerializeStruct {
+                named: self.named,
+                fields: Vec::with_capacity(len),
+            },
         };
         Ok(se)
     }
@@ -255,6 +364,7 @@ impl ser::Serializer for Serializer {
 
 #[doc(hidden)]
 pub struct SerializeVec {
+    named: bool,
     vec: Vec<Value>,
 }
 
@@ -262,20 +372,39 @@ pub struct SerializeVec {
 /// index with a tuple of arguments.
 #[doc(hidden)]
 pub struct SerializeTupleVariant {
+    named: bool,
     idx: u32,
     vec: Vec<Value>,
 }
 
 #[doc(hidden)]
 pub struct DefaultSerializeMap {
+    named: bool,
     map: Vec<(Value, Value)>,
     next_key: Option<Value>,
 }
 
+/// Collects struct fields together with their names, which are kept only in the named mode.
+#[doc(hidden)]
+pub struct SerializeStruct {
+    named: bool,
+    fields: Vec<(&'static str, Value)>,
+}
+
+impl SerializeStruct {
+    fn into_value(self) -> Value {
+        if self.named {
+            Value::Map(self.fields.into_iter().map(|(key, val)| (Value::from(key), val)).collect())
+        } else {
+            Value::Array(self.fields.into_iter().map(|(_, val)| val).collect())
+        }
+    }
+}
+
 #[doc(hidden)]
 pub struct SerializeStructVariant {
     idx: u32,
-    vec: Vec<Value>,
+    fields: SerializeStruct,
 }
 
 impl SerializeSeq for SerializeVec {
@@ -285,7 +414,7 @@ impl SerializeSeq for SerializeVec {
     fn serialize_element<T: ?Sized>(&mut self, value: &T) -> Result<(), Error>
         where T: Serialize
     {
-        self.vec.push(to_value(&value)?);
+        self.vec.push(to_value_with(value, self.named)?);
         Ok(())
     }
 
@@ -331,7 +460,7 @@ impl ser::SerializeTupleVariant for SerializeTupleVariant {
     fn serialize_field<T: ?Sized>(&mut self, value: &T) -> Result<(), Error>
         where T: Serialize
     {
-        self.vec.push(to_value(&value)?);
+        self.vec.push(to_value_with(value, self.named)?);
         Ok(())
     }
 
@@ -347,7 +476,7 @@ impl ser::SerializeMap for DefaultSerializeMap {
     fn serialize_key<T: ?Sized>(&mut self, key: &T) -> Result<(), Error>
         where T: Serialize
     {
-        self.next_key = Some(to_value(key)?);
+        self.next_key = Some(to_value_with(key, self.named)?);
         Ok(())
     }
 
@@ -358,7 +487,7 @@ impl ser::SerializeMap for DefaultSerializeMap {
         // expected failure.
         let key = self.next_key.take()
             .expect("`serialize_value` called before `serialize_key`");
-        self.map.push((key, to_value(&value)?));
+        self.map.push((key, to_value_with(value, self.named)?));
         Ok(())
     }
 
@@ -367,18 +496,20 @@ impl ser::SerializeMap for DefaultSerializeMap {
     }
 }
 
-impl SerializeStruct for SerializeVec {
+impl ser::SerializeStruct for SerializeStruct {
     type Ok = Value;
     type Error = Error;
 
-    fn serialize_field<T: ?Sized>(&mut self, _key: &'static str, value: &T) -> Result<(), Error>
+    fn serialize_field<T: ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), Error>
         where T: Serialize
     {
-        ser::SerializeSeq::serialize_element(self, value)
+        let val = to_value_with(value, self.named)?;
+        self.fields.push((key, val));
+        Ok(())
     }
 
     fn end(self) -> Result<Value, Error> {
-        ser::SerializeSeq::end(self)
+        Ok(self.into_value())
     }
 }
 
@@ -386,14 +517,13 @@ impl ser::SerializeStructVariant for SerializeStructVariant {
     type Ok = Value;
     type Error = Error;
 
-    fn serialize_field<T: ?Sized>(&mut self, _key: &'static str, value: &T) -> Result<(), Error>
+    fn serialize_field<T: ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), Error>
         where T: Serialize
     {
-        self.vec.push(to_value(&value)?);
-        Ok(())
+        ser::SerializeStruct::serialize_field(&mut self.fields, key, value)
     }
 
     fn end(self) -> Result<Value, Error> {
-        Ok(Value::Array(vec![Value::from(self.idx), Value::Array(self.vec)]))
+        Ok(Value::Array(vec![Value::from(self.idx), self.fields.into_value()]))
     }
 }
@@ -0,0 +1,231 @@
+//! Decoding with shared storage for repeated strings.
+//!
+//! Every string of a `Value` owns its bytes, so an array of ten thousand records with the same
+//! field names holds ten thousand copies of each name. Decoding through an `Interner` instead
+//! produces an `InternedValue`, whose strings are reference-counted and allocated once per
+//! distinct content. This mostly pays off for map keys, but applies to any string, like enum
+//! tags.
+//!
+//! The interner remembers every string it has seen, so it can be reused across messages sharing
+//! the same vocabulary, at the cost of keeping them alive until it is dropped or cleared.
+//!
+//! # Examples
+//!
+//! ```
+//! use rmpv::Value;
+//! use rmpv::intern::{self, Interner};
+//!
+//! // [{"id": 1}, {"id": 2}]
+//! let buf = [0x92, 0x81, 0xa2, 0x69, 0x64, 0x01, 0x81, 0xa2, 0x69, 0x64, 0x02];
+//!
+//! let mut interner = Interner::new();
+//! let val = intern::read_value_interned(&mut interner, &mut &buf[..]).unwrap();
+//! assert_eq!(1, interner.len());
+//!
+//! let val = val.into_value();
+//! assert_eq!(Value::from(2), val[1]["id"]);
+//! ```
+
+use alloc::collections::BTreeSet;
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+
+use rmp::decode::RmpRead;
+use rmp::decode as rmp_decode;
+
+use {Integer, Utf8String, Value};
+use decode::{read_value_with_builder, Config, Error, ValueBuilder};
+
+/// A value decoded with an `Interner`, whose strings share their storage.
+#[derive(Clone, Debug, PartialEq)]
+pub enum InternedValue {
+    /// Any value but a valid UTF-8 string, an array or a map.
+    Plain(Value),
+    /// A string, shared with all equal strings decoded by the same interner.
+    String(Rc<str>),
+    /// An array of interned values.
+    Array(Vec<InternedValue>),
+    /// A map of interned keys and values.
+    Map(Vec<(InternedValue, InternedValue)>),
+}
+
+impl InternedValue {
+    /// If the value is a string, returns it.
+    pub fn as_str(&self) -> Option<&str> {
+        match *self {
+            InternedValue::String(ref val) => Some(val),
+            _ => None,
+        }
+    }
+
+    /// If the value is neither a string, an array nor a map, returns the `Value` it holds.
+    ///
+    /// Strings with invalid UTF-8, which are not interned, are returned here too.
+    pub fn as_plain(&self) -> Option<&Value> {
+        match *self {
+            InternedValue::Plain(ref val) => Some(val),
+            _ => None,
+        }
+    }
+
+    /// Converts the value into a `Value`, copying each string into its own allocation.
+    pub fn into_value(self) -> Value {
+        match self {
+            InternedValue::Plain(val) => val,
+            InternedValue::String(val) => Value::from(&*val),
+            InternedValue::Array(vec) => {
+                Value::Array(vec.into_iter().map(InternedValue::into_value).collect())
+            }
+            InternedValue::Map(map) => {
+                let map = map.into_iter()
+                    .map(|(key, val)| (key.into_value(), val.into_value()))
+                    .collect();
+                Value::Map(map)
+            }
+        }
+    }
+}
+
+/// A set of strings handing out a shared copy of each.
+#[derive(Debug, Default)]
+pub struct Interner {
+    strings: BTreeSet<Rc<str>>,
+}
+
+impl Interner {
+    /// Constructs an empty interner.
+    pub fn new() -> Interner {
+        Interner::default()
+    }
+
+    /// Returns the shared copy of the given string, allocating it if it was not seen yet.
+    pub fn intern(&mut self, val: &str) -> Rc<str> {
+        if let Some(rc) = self.strings.get(val) {
+            return rc.clone();
+        }
+
+        let rc: Rc<str> = Rc::from(val);
+        self.strings.insert(rc.clone());
+        rc
+    }
+
+    /// Returns the number of distinct strings held.
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    /// Returns `true` if no string was interned.
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+
+    /// Releases the strings held, which stay alive as long as values refer to them.
+    pub fn clear(&mut self) {
+        self.strings.clear();
+    }
+}
+
+/// Attempts to read a value from the given reader like `read_value`, interning its strings with
+/// the given interner.
+///
+/// # Errors
+///
+/// This function returns the same errors as `read_value`.
+pub fn read_value_interned<R>(interner: &mut Interner, rd: &mut R) -> Result<InternedValue, Error>
+    where R: RmpRead,
+          R::Error: Into<rmp_decode::Error>
+{
+    read_value_interned_with_config(interner, rd, &Config::new())
+}
+
+/// Attempts to read a value from the given reader like `read_value_with_config`, interning its
+/// strings with the given interner.
+///
+/// Like with other custom builders, the duplicate key policy of the config is not applied.
+///
+/// # Errors
+///
+/// This function returns the same errors as `read_value_with_builder`.
+pub fn read_value_interned_with_config<R>(interner: &mut Interner, rd: &mut R, config: &Config) ->
+    Result<InternedValue, Error>
+    where R: RmpRead,
+          R::Error: Into<rmp_decode::Error>
+{
+    read_value_with_builder(rd, config, &mut InternedTree { interner })
+}
+
+/// Builds `InternedValue` trees, backing `read_value_interned_with_config`.
+struct InternedTree<'a> {
+    interner: &'a mut Interner,
+}
+
+impl<'a> ValueBuilder for InternedTree<'a> {
+    type Value = InternedValue;
+    type Array = Vec<InternedValue>;
+    type Map = Vec<(InternedValue, InternedValue)>;
+
+    fn nil(&mut self) -> Result<InternedValue, Error> {
+        Ok(InternedValue::Plain(Value::Nil))
+    }
+
+    fn boolean(&mut self, val: bool) -> Result<InternedValue, Error> {
+        Ok(InternedValue::Plain(Value::Boolean(val)))
+    }
+
+    fn integer(&mut self, val: Integer) -> Result<InternedValue, Error> {
+        Ok(InternedValue::Plain(Value::Integer(val)))
+    }
+
+    fn f32(&mut self, val: f32) -> Result<InternedValue, Error> {
+        Ok(InternedValue::Plain(Value::F32(val)))
+    }
+
+    fn f64(&mut self, val: f64) -> Result<InternedValue, Error> {
+        Ok(InternedValue::Plain(Value::F64(val)))
+    }
+
+    fn string(&mut self, val: Utf8String) -> Result<InternedValue, Error> {
+        match val.as_str() {
+            Some(s) => Ok(InternedValue::String(self.interner.intern(s))),
+            None => Ok(InternedValue::Plain(Value::String(val))),
+        }
+    }
+
+    fn binary(&mut self, val: Vec<u8>) -> Result<InternedValue, Error> {
+        Ok(InternedValue::Plain(Value::Binary(val)))
+    }
+
+    fn ext(&mut self, ty: i8, data: Vec<u8>) -> Result<InternedValue, Error> {
+        Ok(InternedValue::Plain(Value::Ext(ty, data)))
+    }
+
+    fn begin_array(&mut self, len: u32) -> Result<Vec<InternedValue>, Error> {
+        Ok(Vec::with_capacity(len as usize))
+    }
+
+    fn push_element(&mut self, array: &mut Vec<InternedValue>, val: InternedValue) ->
+        Result<(), Error> {
+        array.push(val);
+        Ok(())
+    }
+
+    fn end_array(&mut self, array: Vec<InternedValue>) -> Result<InternedValue, Error> {
+        Ok(InternedValue::Array(array))
+    }
+
+    fn begin_map(&mut self, len: u32) -> Result<Vec<(InternedValue, InternedValue)>, Error> {
+        Ok(Vec::with_capacity(len as usize))
+    }
+
+    fn push_entry(&mut self, map: &mut Vec<(InternedValue, InternedValue)>, key: InternedValue,
+                  val: InternedValue) -> Result<(), Error>
+    {
+        map.push((key, val));
+        Ok(())
+    }
+
+    fn end_map(&mut self, map: Vec<(InternedValue, InternedValue)>) ->
+        Result<InternedValue, Error> {
+        Ok(InternedValue::Map(map))
+    }
+}
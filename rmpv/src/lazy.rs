@@ -0,0 +1,171 @@
+//! Reading single fields of encoded documents without decoding them.
+//!
+//! Decoding a whole document into a `Value` or a `ValueRef` builds a tree of every value in it,
+//! which is wasted work when only a couple of fields are needed. A `LazyValue` instead keeps the
+//! encoded bytes and walks them on access: looking up a map key or an array element skips the
+//! entries before it by their headers and lengths, without decoding or allocating anything, and
+//! returns the found child as another `LazyValue`. Only the values finally asked for are decoded.
+//!
+//! The bytes are not checked upfront, so truncated or malformed input is reported by the
+//! accessors reaching it.
+//!
+//! # Examples
+//!
+//! ```
+//! use rmpv::ValueRef;
+//! use rmpv::lazy::LazyValue;
+//!
+//! // {"id": 42, "tags": ["a", "b"]}
+//! let buf = [0x82, 0xa2, 0x69, 0x64, 0x2a, 0xa4, 0x74, 0x61, 0x67, 0x73, 0x92, 0xa1, 0x61,
+//!            0xa1, 0x62];
+//! let doc = LazyValue::new(&buf);
+//!
+//! let tag = doc.get("tags").unwrap().unwrap().index(1).unwrap().unwrap();
+//! assert_eq!(&[0xa1, 0x62], tag.as_slice());
+//! assert_eq!(ValueRef::from("b"), tag.decode().unwrap());
+//!
+//! assert_eq!(None, doc.get("name").unwrap());
+//! ```
+
+use rmp::Marker;
+use rmp::decode::{self as rmp_decode, Elements};
+
+use {Value, ValueRef};
+use decode::{read_value, read_value_ref, Error};
+
+/// An encoded value whose children are decoded on access.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LazyValue<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> LazyValue<'a> {
+    /// Wraps the value encoded at the beginning of the given slice.
+    ///
+    /// Bytes following the first value are ignored by the accessors.
+    pub fn new(buf: &'a [u8]) -> LazyValue<'a> {
+        LazyValue { buf }
+    }
+
+    /// Returns the wrapped bytes.
+    ///
+    /// The values returned by `get` and `index` hold exactly their own encoding.
+    pub fn as_slice(&self) -> &'a [u8] {
+        self.buf
+    }
+
+    /// Reads the marker of the value.
+    ///
+    /// # Errors
+    ///
+    /// This function returns `Error::InvalidMarkerRead` if the slice is empty.
+    pub fn marker(&self) -> Result<Marker, Error> {
+        Ok(rmp_decode::read_marker(&mut &self.buf[..])?)
+    }
+
+    /// Looks up the value of the first entry with the given string key if the value is a map,
+    /// returning `None` if there is no such entry or if the value is not a map.
+    ///
+    /// Entries are compared by key up to the one found, and keys that are not valid UTF-8
+    /// strings never match.
+    ///
+    /// # Errors
+    ///
+    /// This function returns `Error` if the map is truncated or malformed before the entry.
+    pub fn get(&self, key: &str) -> Result<Option<LazyValue<'a>>, Error> {
+        match self.marker()? {
+            Marker::FixMap(..) | Marker::Map16 | Marker::Map32 => {}
+            _ => return Ok(None),
+        }
+
+        for entry in rmp_decode::read_map_elements(self.buf)? {
+            let entry = entry?;
+            if let Ok((name, _)) = rmp_decode::read_str_from_slice(entry.0) {
+                if name == key {
+                    return Ok(Some(LazyValue::new(entry.1)));
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Looks up the element at the given position if the value is an array, returning `None` if
+    /// the array is shorter or if the value is not an array.
+    ///
+    /// # Errors
+    ///
+    /// This function returns `Error` if the array is truncated or malformed before the element.
+    pub fn index(&self, idx: usize) -> Result<Option<LazyValue<'a>>, Error> {
+        match self.marker()? {
+            Marker::FixArray(..) | Marker::Array16 | Marker::Array32 => {}
+            _ => return Ok(None),
+        }
+
+        match rmp_decode::read_array_elements(self.buf)?.nth(idx) {
+            Some(elem) => Ok(Some(LazyValue::new(elem?))),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the number of elements of an array or entries of a map, or `None` for other
+    /// values.
+    ///
+    /// # Errors
+    ///
+    /// This function returns `Error` if the header is truncated.
+    pub fn len(&self) -> Result<Option<u32>, Error> {
+        let len = match self.marker()? {
+            Marker::FixArray(..) | Marker::Array16 | Marker::Array32 => {
+                rmp_decode::read_array_len(&mut &self.buf[..])?
+            }
+            Marker::FixMap(..) | Marker::Map16 | Marker::Map32 => {
+                rmp_decode::read_map_len(&mut &self.buf[..])?
+            }
+            _ => return Ok(None),
+        };
+
+        Ok(Some(len))
+    }
+
+    /// Returns an iterator over the elements of the value if it is an array.
+    ///
+    /// # Errors
+    ///
+    /// This function returns `Error` if the value is not an array or its header is truncated.
+    pub fn elements(&self) -> Result<LazyElements<'a>, Error> {
+        Ok(LazyElements { inner: rmp_decode::read_array_elements(self.buf)? })
+    }
+
+    /// Decodes the value, borrowing its strings and binaries from the wrapped bytes.
+    ///
+    /// # Errors
+    ///
+    /// This function returns the same errors as `read_value_ref`.
+    pub fn decode(&self) -> Result<ValueRef<'a>, Error> {
+        read_value_ref(&mut &self.buf[..])
+    }
+
+    /// Decodes the value into an owned `Value`.
+    ///
+    /// # Errors
+    ///
+    /// This function returns the same errors as `read_value`.
+    pub fn to_value(&self) -> Result<Value, Error> {
+        read_value(&mut &self.buf[..])
+    }
+}
+
+/// An iterator over the elements of an array, yielding them as lazy values.
+#[derive(Clone, Debug)]
+pub struct LazyElements<'a> {
+    inner: Elements<'a>,
+}
+
+impl<'a> Iterator for LazyElements<'a> {
+    type Item = Result<LazyValue<'a>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|elem| Ok(LazyValue::new(elem?)))
+    }
+}
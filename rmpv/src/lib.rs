@@ -4,7 +4,20 @@
 //!
 //! ```
 //! ```
+//!
+//! # `no_std` support
+//!
+//! `Value`, `ValueRef` and their encoding and decoding functions only need an allocator. Disable
+//! the default `std` feature to use them without the standard library: values are then decoded
+//! from byte slices and encoded into fixed-size buffers, because `std::io::Read` and
+//! `std::io::Write` are not available.
+
+#![cfg_attr(not(feature = "std"), no_std)]
 
+#[cfg(feature = "std")]
+extern crate core;
+#[macro_use]
+extern crate alloc;
 #[cfg(feature = "with-serde")]
 #[macro_use]
 extern crate serde;
@@ -13,15 +26,32 @@ extern crate serde_bytes;
 extern crate rmp;
 extern crate num_traits;
 
-use std::borrow::Cow;
-use std::fmt::{self, Debug, Display};
-use std::ops::Index;
-use std::str::Utf8Error;
+use alloc::borrow::Cow;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::{self, Debug, Display};
+use core::ops::Index;
+use core::str::Utf8Error;
 
 use num_traits::NumCast;
 
+pub mod compact;
+pub mod convert;
 pub mod decode;
+pub mod dedup;
 pub mod encode;
+pub mod intern;
+pub mod lazy;
+pub mod map;
+pub mod parse;
+mod pointer;
+pub mod redact;
+pub mod registry;
+#[cfg(feature = "std")]
+pub mod rpc;
+pub mod text;
+pub mod timestamp;
+pub mod transform;
 
 #[cfg(feature = "with-serde")]
 pub mod ext;
@@ -47,7 +77,7 @@ impl Integer {
     #[inline]
     pub fn is_i64(&self) -> bool {
         match self.n {
-            IntPriv::PosInt(n) => n <= std::i64::MAX as u64,
+            IntPriv::PosInt(n) => n <= i64::MAX as u64,
             IntPriv::NegInt(..) => true,
         }
     }
@@ -521,6 +551,11 @@ impl Value {
         }
     }
 
+    /// Returns true if the `Value` is an integer. Returns false otherwise.
+    pub fn is_integer(&self) -> bool {
+        self.is_i64() || self.is_u64()
+    }
+
     /// Returns true if the `Value` is a Number. Returns false otherwise.
     ///
     /// # Examples
@@ -769,9 +804,201 @@ impl Value {
             None
         }
     }
+
+    /// If the `Value` is an integer that fits into an i8, returns it.
+    /// Returns None otherwise.
+    pub fn as_i8(&self) -> Option<i8> {
+        self.as_int()
+    }
+
+    /// If the `Value` is an integer that fits into an i16, returns it.
+    /// Returns None otherwise.
+    pub fn as_i16(&self) -> Option<i16> {
+        self.as_int()
+    }
+
+    /// If the `Value` is an integer that fits into an i32, returns it.
+    /// Returns None otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rmpv::Value;
+    ///
+    /// assert_eq!(Some(-42), Value::from(-42).as_i32());
+    ///
+    /// assert_eq!(None, Value::from(1u64 << 31).as_i32());
+    /// assert_eq!(None, Value::F64(42.0).as_i32());
+    /// ```
+    pub fn as_i32(&self) -> Option<i32> {
+        self.as_int()
+    }
+
+    /// If the `Value` is an integer that fits into an u8, returns it.
+    /// Returns None otherwise.
+    pub fn as_u8(&self) -> Option<u8> {
+        self.as_int()
+    }
+
+    /// If the `Value` is an integer that fits into an u16, returns it.
+    /// Returns None otherwise.
+    pub fn as_u16(&self) -> Option<u16> {
+        self.as_int()
+    }
+
+    /// If the `Value` is an integer that fits into an u32, returns it.
+    /// Returns None otherwise.
+    pub fn as_u32(&self) -> Option<u32> {
+        self.as_int()
+    }
+
+    fn as_int<T: NumCast>(&self) -> Option<T> {
+        match *self {
+            Value::Integer(Integer { n: IntPriv::PosInt(n) }) => NumCast::from(n),
+            Value::Integer(Integer { n: IntPriv::NegInt(n) }) => NumCast::from(n),
+            _ => None,
+        }
+    }
+
+    /// If the `Value` is a number that a f32 represents exactly, returns it as a f32.
+    /// Returns None otherwise.
+    ///
+    /// Unlike `as_f64` this never rounds: a f64 is returned only if converting it to a f32 and
+    /// back gives the same number, or if it is NaN, and likewise for integers.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rmpv::Value;
+    ///
+    /// assert_eq!(Some(0.5), Value::F32(0.5).as_f32());
+    /// assert_eq!(Some(0.5), Value::F64(0.5).as_f32());
+    /// assert_eq!(Some(16777216.0), Value::from(1 << 24).as_f32());
+    ///
+    /// assert_eq!(None, Value::F64(0.1).as_f32());
+    /// assert_eq!(None, Value::from((1 << 24) + 1).as_f32());
+    /// ```
+    pub fn as_f32(&self) -> Option<f32> {
+        match *self {
+            Value::F32(v) => Some(v),
+            Value::F64(v) if v.is_nan() => Some(v as f32),
+            Value::F64(v) if (v as f32) as f64 == v => Some(v as f32),
+            Value::Integer(Integer { n: IntPriv::PosInt(n) }) if fits_f32(n) => Some(n as f32),
+            Value::Integer(Integer { n: IntPriv::NegInt(n) }) if fits_f32(n.unsigned_abs()) => {
+                Some(n as f32)
+            }
+            _ => None,
+        }
+    }
+
+    /// If the `Value` is a Binary, returns the associated slice. Unlike `as_slice` this returns
+    /// None for strings.
+    pub fn as_bin(&self) -> Option<&[u8]> {
+        if let Value::Binary(ref val) = *self {
+            Some(val)
+        } else {
+            None
+        }
+    }
+
+    /// If the `Value` is a String, returns it whether it is valid UTF-8 or not.
+    /// Returns None otherwise.
+    pub fn as_utf8_string(&self) -> Option<&Utf8String> {
+        if let Value::String(ref val) = *self {
+            Some(val)
+        } else {
+            None
+        }
+    }
+
+    /// If the `Value` is an Array, returns a mutable reference to the associated vector.
+    /// Returns None otherwise.
+    pub fn as_array_mut(&mut self) -> Option<&mut Vec<Value>> {
+        if let Value::Array(ref mut array) = *self {
+            Some(array)
+        } else {
+            None
+        }
+    }
+
+    /// If the `Value` is a Map, returns a mutable reference to the associated vector of key-value
+    /// tuples. Returns None otherwise.
+    pub fn as_map_mut(&mut self) -> Option<&mut Vec<(Value, Value)>> {
+        if let Value::Map(ref mut map) = *self {
+            Some(map)
+        } else {
+            None
+        }
+    }
+
+    /// If the `Value` is a String with valid UTF-8, returns the associated string.
+    /// Returns None otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rmpv::Value;
+    ///
+    /// assert_eq!(Some("le message".into()), Value::from("le message").into_string());
+    ///
+    /// assert_eq!(None, Value::Binary(vec![0x61]).into_string());
+    /// ```
+    pub fn into_string(self) -> Option<String> {
+        if let Value::String(val) = self {
+            val.into_str()
+        } else {
+            None
+        }
+    }
+
+    /// If the `Value` is a Binary or a String, returns the associated bytes.
+    /// Returns None otherwise.
+    pub fn into_bytes(self) -> Option<Vec<u8>> {
+        match self {
+            Value::Binary(val) => Some(val),
+            Value::String(val) => Some(val.into_bytes()),
+            _ => None,
+        }
+    }
+
+    /// If the `Value` is an Array, returns the associated vector.
+    /// Returns None otherwise.
+    pub fn into_array(self) -> Option<Vec<Value>> {
+        if let Value::Array(array) = self {
+            Some(array)
+        } else {
+            None
+        }
+    }
+
+    /// If the `Value` is a Map, returns the associated vector of key-value tuples.
+    /// Returns None otherwise.
+    pub fn into_map(self) -> Option<Vec<(Value, Value)>> {
+        if let Value::Map(map) = self {
+            Some(map)
+        } else {
+            None
+        }
+    }
+
+    /// If the `Value` is an Ext, returns the associated tuple with a ty and data.
+    /// Returns None otherwise.
+    pub fn into_ext(self) -> Option<(i8, Vec<u8>)> {
+        if let Value::Ext(ty, buf) = self {
+            Some((ty, buf))
+        } else {
+            None
+        }
+    }
 }
 
 static NIL: Value = Value::Nil;
+
+/// Returns true if the magnitude of an integer is exactly representable as a f32, that is if its
+/// significant bits fit into the 24-bit mantissa.
+fn fits_f32(n: u64) -> bool {
+    n.checked_shr(n.trailing_zeros()).unwrap_or(0) < 1 << 24
+}
 static NIL_REF: ValueRef<'static> = ValueRef::Nil;
 
 impl Index<usize> for Value {
@@ -782,6 +1009,19 @@ impl Index<usize> for Value {
     }
 }
 
+/// Looks up the value of the first map entry with the given string key, returning `Nil` if there
+/// is none or if the value is not a map.
+impl Index<&str> for Value {
+    type Output = Value;
+
+    fn index(&self, key: &str) -> &Value {
+        self.as_map()
+            .and_then(|map| map.iter().find(|entry| entry.0.as_str() == Some(key)))
+            .map(|entry| &entry.1)
+            .unwrap_or(&NIL)
+    }
+}
+
 impl From<bool> for Value {
     fn from(v: bool) -> Self {
         Value::Boolean(v)
@@ -953,6 +1193,149 @@ impl Display for Value {
     }
 }
 
+impl Value {
+    /// Returns an object that displays this value like `Display` does, but abridged, which is
+    /// useful for logging large values.
+    ///
+    /// Arrays and maps nested deeper than `max_depth` levels are shown as `[...]` and `{...}`,
+    /// only the first `max_elems` elements of each array or entries of each map are shown, and
+    /// strings, binaries and extension data are cut after `max_bytes` bytes. Every omission is
+    /// marked with the number of elements or bytes left out.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rmpv::Value;
+    ///
+    /// let name = Value::Map(vec![(Value::from("first"), Value::from("John"))]);
+    /// let val = Value::Map(vec![
+    ///     (Value::from("user"), Value::Map(vec![(Value::from("name"), name)])),
+    ///     (Value::from("list"), Value::Array((0..10000).map(Value::from).collect())),
+    ///     (Value::from("blob"), Value::Binary(vec![0; 4096])),
+    /// ]);
+    ///
+    /// let expected = concat!(r#"{"user": {"name": {...}}, "list": [0, 1, 2, …(+9997)], "#,
+    ///                        r#""blob": [0, 0, 0, 0, …(+4092 bytes)]}"#);
+    /// assert_eq!(expected, format!("{}", val.display_truncated(2, 3, 4)));
+    /// ```
+    pub fn display_truncated(&self, max_depth: usize, max_elems: usize, max_bytes: usize) ->
+        DisplayTruncated<'_>
+    {
+        DisplayTruncated {
+            val: self,
+            max_depth,
+            max_elems,
+            max_bytes,
+        }
+    }
+}
+
+/// Displays a `Value` with limited depth and length, see `Value::display_truncated`.
+pub struct DisplayTruncated<'a> {
+    val: &'a Value,
+    max_depth: usize,
+    max_elems: usize,
+    max_bytes: usize,
+}
+
+impl<'a> DisplayTruncated<'a> {
+    fn fmt_value(&self, val: &Value, depth: usize, f: &mut fmt::Formatter) ->
+        Result<(), fmt::Error>
+    {
+        match *val {
+            Value::String(Utf8String { s: Ok(ref s) }) => {
+                if s.len() <= self.max_bytes {
+                    return write!(f, "\"{}\"", s);
+                }
+
+                let mut end = self.max_bytes;
+                while !s.is_char_boundary(end) {
+                    end -= 1;
+                }
+                write!(f, "\"{}\"…(+{} bytes)", &s[..end], s.len() - end)
+            }
+            Value::String(Utf8String { s: Err((ref buf, ..)) }) => self.fmt_bytes(buf, f),
+            Value::Binary(ref buf) => self.fmt_bytes(buf, f),
+            Value::Array(ref vec) => {
+                if vec.is_empty() {
+                    return f.write_str("[]");
+                }
+                if depth >= self.max_depth {
+                    return f.write_str("[...]");
+                }
+
+                f.write_str("[")?;
+                for (idx, val) in vec.iter().take(self.max_elems).enumerate() {
+                    if idx > 0 {
+                        f.write_str(", ")?;
+                    }
+                    self.fmt_value(val, depth + 1, f)?;
+                }
+                fmt_omitted(vec.len(), self.max_elems, "", f)?;
+                f.write_str("]")
+            }
+            Value::Map(ref map) => {
+                if map.is_empty() {
+                    return f.write_str("{}");
+                }
+                if depth >= self.max_depth {
+                    return f.write_str("{...}");
+                }
+
+                f.write_str("{")?;
+                for (idx, entry) in map.iter().take(self.max_elems).enumerate() {
+                    if idx > 0 {
+                        f.write_str(", ")?;
+                    }
+                    self.fmt_value(&entry.0, depth + 1, f)?;
+                    f.write_str(": ")?;
+                    self.fmt_value(&entry.1, depth + 1, f)?;
+                }
+                fmt_omitted(map.len(), self.max_elems, "", f)?;
+                f.write_str("}")
+            }
+            Value::Ext(ty, ref data) => {
+                write!(f, "[{}, ", ty)?;
+                self.fmt_bytes(data, f)?;
+                f.write_str("]")
+            }
+            ref val => Display::fmt(val, f),
+        }
+    }
+
+    fn fmt_bytes(&self, buf: &[u8], f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.write_str("[")?;
+        for (idx, byte) in buf.iter().take(self.max_bytes).enumerate() {
+            if idx > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{}", byte)?;
+        }
+        fmt_omitted(buf.len(), self.max_bytes, " bytes", f)?;
+        f.write_str("]")
+    }
+}
+
+/// Writes the marker of the tail past the first `limit` of `len` items, if there is one.
+fn fmt_omitted(len: usize, limit: usize, unit: &str, f: &mut fmt::Formatter) ->
+    Result<(), fmt::Error>
+{
+    if len <= limit {
+        return Ok(());
+    }
+
+    if limit > 0 {
+        f.write_str(", ")?;
+    }
+    write!(f, "…(+{}{})", len - limit, unit)
+}
+
+impl<'a> Display for DisplayTruncated<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        self.fmt_value(self.val, 0, f)
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum ValueRef<'a> {
     /// Nil represents nil.
@@ -0,0 +1,392 @@
+//! Looking up and editing map entries by key.
+//!
+//! A `Value::Map` is a vector of key-value pairs, which keeps the order of the entries and allows
+//! keys of any type. The methods here do the key comparisons on top of it: `Value::get`,
+//! `Value::get_mut` and `Value::remove` accept anything implementing `Key`, like `&str`, integers
+//! and `&Value`, while `Value::insert` and `Value::entry` take keys convertible into a `Value`.
+//!
+//! Entries are found by a linear scan, and the first entry with a matching key is taken if a map
+//! holds several. For maps read many times, `Value::sort_keys` sorts the entries once so that
+//! `Value::get_sorted` finds them by binary search. Keys are sorted by type first, in the order
+//! of the `Value` variants, then by value: numerically for integers, by their total order for
+//! floats, bytewise for strings and binaries, and element by element for the other types.
+//!
+//! # Examples
+//!
+//! ```
+//! use rmpv::Value;
+//!
+//! let mut val = Value::Nil;
+//! val.insert("name", "alice");
+//! val.insert(1, vec![Value::from(2)]);
+//! *val.entry("visits").or_insert(Value::from(0)) = Value::from(1);
+//!
+//! assert_eq!(Some(&Value::from("alice")), val.get("name"));
+//! assert_eq!(Some(Value::from("alice")), val.insert("name", "bob"));
+//! assert_eq!(Some(vec![Value::from(2)]), val.remove(1).and_then(|v| v.into_array()));
+//!
+//! assert_eq!(r#"{"name": "bob", "visits": 1}"#, val.to_string());
+//! ```
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::mem;
+
+use {Integer, Value};
+
+/// A key entries of a map are compared to.
+pub trait Key {
+    /// Returns true if the entry with the given key matches.
+    fn matches(&self, key: &Value) -> bool;
+
+    /// Compares this key with the key of an entry, in the order `Value::sort_keys` sorts by.
+    fn compare(&self, key: &Value) -> Ordering;
+}
+
+/// Strings match keys with the same valid UTF-8 contents.
+impl Key for str {
+    fn matches(&self, key: &Value) -> bool {
+        key.as_str() == Some(self)
+    }
+
+    fn compare(&self, key: &Value) -> Ordering {
+        match *key {
+            Value::String(ref s) => self.as_bytes().cmp(s.as_bytes()),
+            ref key => STRING_RANK.cmp(&rank(key)),
+        }
+    }
+}
+
+impl Key for String {
+    fn matches(&self, key: &Value) -> bool {
+        Key::matches(self.as_str(), key)
+    }
+
+    fn compare(&self, key: &Value) -> Ordering {
+        Key::compare(self.as_str(), key)
+    }
+}
+
+/// Values match keys equal to them.
+impl Key for Value {
+    fn matches(&self, key: &Value) -> bool {
+        self == key
+    }
+
+    fn compare(&self, key: &Value) -> Ordering {
+        cmp_values(self, key)
+    }
+}
+
+impl<T: Key + ?Sized> Key for &T {
+    fn matches(&self, key: &Value) -> bool {
+        (**self).matches(key)
+    }
+
+    fn compare(&self, key: &Value) -> Ordering {
+        (**self).compare(key)
+    }
+}
+
+macro_rules! impl_key_for_int {
+    ($($ty:ident)*) => {$(
+        /// Integers match integer keys with the same value, whatever their encoding.
+        impl Key for $ty {
+            fn matches(&self, key: &Value) -> bool {
+                match *key {
+                    Value::Integer(ref n) => *n == Integer::from(*self),
+                    _ => false,
+                }
+            }
+
+            fn compare(&self, key: &Value) -> Ordering {
+                cmp_values(&Value::Integer(Integer::from(*self)), key)
+            }
+        }
+    )*}
+}
+
+impl_key_for_int!(u8 u16 u32 u64 usize i8 i16 i32 i64 isize);
+
+impl Value {
+    /// If the `Value` is a Map, returns the value of the first entry with the given key.
+    /// Returns None otherwise.
+    pub fn get<K: Key>(&self, key: K) -> Option<&Value> {
+        self.as_map()?.iter().find(|entry| key.matches(&entry.0)).map(|entry| &entry.1)
+    }
+
+    /// If the `Value` is a Map, returns a mutable reference to the value of the first entry with
+    /// the given key. Returns None otherwise.
+    pub fn get_mut<K: Key>(&mut self, key: K) -> Option<&mut Value> {
+        let map = self.as_map_mut()?;
+        map.iter_mut().find(|entry| key.matches(&entry.0)).map(|entry| &mut entry.1)
+    }
+
+    /// If the `Value` is a Map sorted with `sort_keys`, returns the value of the first entry with
+    /// the given key, found by binary search. Returns None otherwise.
+    ///
+    /// The result is unspecified if the map is not sorted: the entry may not be found.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rmpv::Value;
+    ///
+    /// let mut val: Value = r#"{"b": 2, 1: "one", "a": 1}"#.parse().unwrap();
+    /// val.sort_keys();
+    ///
+    /// assert_eq!(r#"{1: "one", "a": 1, "b": 2}"#, val.to_string());
+    /// assert_eq!(Some(&Value::from(1)), val.get_sorted("a"));
+    /// assert_eq!(Some(&Value::from("one")), val.get_sorted(1));
+    /// ```
+    pub fn get_sorted<K: Key>(&self, key: K) -> Option<&Value> {
+        let map = self.as_map()?;
+        let idx = map.partition_point(|entry| key.compare(&entry.0) == Ordering::Greater);
+        match map.get(idx) {
+            Some(entry) if key.compare(&entry.0) == Ordering::Equal => Some(&entry.1),
+            _ => None,
+        }
+    }
+
+    /// Sorts the entries of this map and of all maps nested in it by key, for `get_sorted`.
+    ///
+    /// The sort is stable, so entries with the same key stay in the same order.
+    pub fn sort_keys(&mut self) {
+        match *self {
+            Value::Array(ref mut vec) => {
+                for val in vec {
+                    val.sort_keys();
+                }
+            }
+            Value::Map(ref mut map) => {
+                for entry in map.iter_mut() {
+                    entry.0.sort_keys();
+                    entry.1.sort_keys();
+                }
+                sort_entries(map);
+            }
+            _ => {}
+        }
+    }
+
+    /// If the `Value` is a Map, removes the first entry with the given key and returns its value,
+    /// keeping the order of the other entries. Returns None otherwise.
+    pub fn remove<K: Key>(&mut self, key: K) -> Option<Value> {
+        let map = self.as_map_mut()?;
+        let idx = map.iter().position(|entry| key.matches(&entry.0))?;
+        Some(map.remove(idx).1)
+    }
+
+    /// Sets the value of the first entry with the given key, returning the previous one, or
+    /// appends a new entry if there is none.
+    ///
+    /// Nil is replaced with an empty map first.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `Value` is neither a Map nor Nil.
+    pub fn insert<K: Into<Value>, V: Into<Value>>(&mut self, key: K, val: V) -> Option<Value> {
+        match self.entry(key) {
+            Entry::Occupied(mut entry) => Some(entry.insert(val.into())),
+            Entry::Vacant(entry) => {
+                entry.insert(val.into());
+                None
+            }
+        }
+    }
+
+    /// Returns the first entry with the given key, or the place to append it at, for in-place
+    /// manipulation.
+    ///
+    /// Nil is replaced with an empty map first.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `Value` is neither a Map nor Nil.
+    pub fn entry<K: Into<Value>>(&mut self, key: K) -> Entry<'_> {
+        if self.is_nil() {
+            *self = Value::Map(Vec::new());
+        }
+
+        let key = key.into();
+        let map = match *self {
+            Value::Map(ref mut map) => map,
+            ref val => panic!("cannot access entries of a non-map value: {}", val),
+        };
+
+        match map.iter().position(|entry| entry.0 == key) {
+            Some(idx) => Entry::Occupied(OccupiedEntry { map, idx }),
+            None => Entry::Vacant(VacantEntry { map, key }),
+        }
+    }
+}
+
+/// An entry of a map, either present or not, returned by `Value::entry`.
+#[derive(Debug)]
+pub enum Entry<'a> {
+    /// The map holds an entry with the key.
+    Occupied(OccupiedEntry<'a>),
+    /// The map holds no entry with the key.
+    Vacant(VacantEntry<'a>),
+}
+
+impl<'a> Entry<'a> {
+    /// Returns the key of the entry.
+    pub fn key(&self) -> &Value {
+        match *self {
+            Entry::Occupied(ref entry) => entry.key(),
+            Entry::Vacant(ref entry) => entry.key(),
+        }
+    }
+
+    /// Appends the entry with the given value if it is vacant, and returns a mutable reference
+    /// to its value.
+    pub fn or_insert(self, default: Value) -> &'a mut Value {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default),
+        }
+    }
+
+    /// Appends the entry with the result of the given function if it is vacant, and returns a
+    /// mutable reference to its value.
+    pub fn or_insert_with<F: FnOnce() -> Value>(self, default: F) -> &'a mut Value {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    /// Calls the given function with the value of the entry if it is occupied.
+    pub fn and_modify<F: FnOnce(&mut Value)>(self, f: F) -> Self {
+        match self {
+            Entry::Occupied(mut entry) => {
+                f(entry.get_mut());
+                Entry::Occupied(entry)
+            }
+            entry => entry,
+        }
+    }
+}
+
+/// An entry present in a map.
+#[derive(Debug)]
+pub struct OccupiedEntry<'a> {
+    map: &'a mut Vec<(Value, Value)>,
+    idx: usize,
+}
+
+impl<'a> OccupiedEntry<'a> {
+    /// Returns the key of the entry.
+    pub fn key(&self) -> &Value {
+        &self.map[self.idx].0
+    }
+
+    /// Returns the value of the entry.
+    pub fn get(&self) -> &Value {
+        &self.map[self.idx].1
+    }
+
+    /// Returns a mutable reference to the value of the entry.
+    pub fn get_mut(&mut self) -> &mut Value {
+        &mut self.map[self.idx].1
+    }
+
+    /// Returns a mutable reference to the value of the entry, bound to the map.
+    pub fn into_mut(self) -> &'a mut Value {
+        &mut self.map[self.idx].1
+    }
+
+    /// Sets the value of the entry, returning the previous one.
+    pub fn insert(&mut self, val: Value) -> Value {
+        mem::replace(self.get_mut(), val)
+    }
+
+    /// Removes the entry from the map, keeping the order of the others, and returns its value.
+    pub fn remove(self) -> Value {
+        self.map.remove(self.idx).1
+    }
+}
+
+/// The place of an entry missing from a map, at its end.
+#[derive(Debug)]
+pub struct VacantEntry<'a> {
+    map: &'a mut Vec<(Value, Value)>,
+    key: Value,
+}
+
+impl<'a> VacantEntry<'a> {
+    /// Returns the key of the entry.
+    pub fn key(&self) -> &Value {
+        &self.key
+    }
+
+    /// Appends the entry with the given value, and returns a mutable reference to it.
+    pub fn insert(self, val: Value) -> &'a mut Value {
+        self.map.push((self.key, val));
+        let idx = self.map.len() - 1;
+        &mut self.map[idx].1
+    }
+}
+
+/// Sorts the entries of a map by key like `Value::sort_keys`, leaving nested maps as they are.
+pub fn sort_entries(map: &mut [(Value, Value)]) {
+    map.sort_by(|lhs, rhs| cmp_values(&lhs.0, &rhs.0));
+}
+
+const STRING_RANK: u8 = 5;
+
+/// Returns the position of the type of the value in the key order.
+fn rank(val: &Value) -> u8 {
+    match *val {
+        Value::Nil => 0,
+        Value::Boolean(..) => 1,
+        Value::Integer(..) => 2,
+        Value::F32(..) => 3,
+        Value::F64(..) => 4,
+        Value::String(..) => STRING_RANK,
+        Value::Binary(..) => 6,
+        Value::Array(..) => 7,
+        Value::Map(..) => 8,
+        Value::Ext(..) => 9,
+    }
+}
+
+fn cmp_values(lhs: &Value, rhs: &Value) -> Ordering {
+    match (lhs, rhs) {
+        (Value::Boolean(lhs), Value::Boolean(rhs)) => lhs.cmp(rhs),
+        (Value::Integer(lhs), Value::Integer(rhs)) => cmp_ints(lhs, rhs),
+        (Value::F32(lhs), Value::F32(rhs)) => lhs.total_cmp(rhs),
+        (Value::F64(lhs), Value::F64(rhs)) => lhs.total_cmp(rhs),
+        (Value::String(lhs), Value::String(rhs)) => lhs.as_bytes().cmp(rhs.as_bytes()),
+        (Value::Binary(lhs), Value::Binary(rhs)) => lhs.cmp(rhs),
+        (Value::Array(lhs), Value::Array(rhs)) => cmp_seqs(lhs, rhs, cmp_values),
+        (Value::Map(lhs), Value::Map(rhs)) => {
+            cmp_seqs(lhs, rhs, |lhs, rhs| {
+                cmp_values(&lhs.0, &rhs.0).then_with(|| cmp_values(&lhs.1, &rhs.1))
+            })
+        }
+        (Value::Ext(lty, lhs), Value::Ext(rty, rhs)) => (lty, lhs).cmp(&(rty, rhs)),
+        (lhs, rhs) => rank(lhs).cmp(&rank(rhs)),
+    }
+}
+
+/// Compares sequences element by element, the shorter one first if one is a prefix of the other.
+fn cmp_seqs<T, F>(lhs: &[T], rhs: &[T], cmp: F) -> Ordering
+    where F: Fn(&T, &T) -> Ordering
+{
+    lhs.iter()
+        .zip(rhs)
+        .map(|(lhs, rhs)| cmp(lhs, rhs))
+        .find(|&ord| ord != Ordering::Equal)
+        .unwrap_or_else(|| lhs.len().cmp(&rhs.len()))
+}
+
+fn cmp_ints(lhs: &Integer, rhs: &Integer) -> Ordering {
+    match (lhs.as_i64(), rhs.as_i64()) {
+        (Some(lhs), Some(rhs)) => lhs.cmp(&rhs),
+        // Only integers above `i64::MAX` don't fit, and those fit into an u64.
+        _ => lhs.as_u64().cmp(&rhs.as_u64()),
+    }
+}
@@ -0,0 +1,398 @@
+//! Parsing of values from a JSON-like text syntax.
+//!
+//! `Value` implements `FromStr`, which makes writing fixtures for MessagePack pipelines much less
+//! tedious than building values programmatically. The syntax is JSON extended to the types
+//! MessagePack has and JSON lacks:
+//!
+//! - `nil` is accepted next to `null`.
+//! - Integers are signed or unsigned 64-bit ones, numbers with a fraction or an exponent are
+//!   parsed as `F64`.
+//! - Map keys can be any values, not only strings.
+//! - `bin(...)` is a binary of the bytes written in hexadecimal inside the parentheses, optionally
+//!   separated by whitespace, like `bin(01 02 ff)`.
+//! - `ext(ty, ...)` is an extension of the given type with its data written like binaries, like
+//!   `ext(-1, 00 00 00 01)`.
+//! - `f32(...)` and `f64(...)` are floats of the given width, which may also be `nan`, `inf` or
+//!   `-inf`, like `f32(1.5)`.
+//! - `str(...)` is a string of the given bytes, written like binaries, for strings that are not
+//!   valid UTF-8.
+//!
+//! The `text` module renders values in this syntax.
+//!
+//! # Examples
+//!
+//! ```
+//! use rmpv::Value;
+//!
+//! let val: Value = r#"{"a": [1, true, null], 42: bin(dead beef)}"#.parse().unwrap();
+//!
+//! assert_eq!(Value::Map(vec![
+//!     (Value::from("a"), Value::Array(vec![Value::from(1), Value::from(true), Value::Nil])),
+//!     (Value::from(42), Value::Binary(vec![0xde, 0xad, 0xbe, 0xef])),
+//! ]), val);
+//! ```
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::char;
+use core::fmt::{self, Display, Formatter};
+use core::str::FromStr;
+#[cfg(feature = "std")]
+use std::error;
+
+use {Utf8String, Value};
+
+/// The maximum nesting depth of arrays and maps, which keeps the parser from running out of stack.
+const MAX_DEPTH: usize = 1024;
+
+/// An error returned when parsing a `Value` from text fails.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseError {
+    offset: usize,
+    desc: &'static str,
+}
+
+impl ParseError {
+    /// Returns the byte offset in the text where parsing failed.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        write!(f, "{} at offset {}", self.desc, self.offset)
+    }
+}
+
+#[cfg(feature = "std")]
+impl error::Error for ParseError {
+    fn description(&self) -> &str {
+        self.desc
+    }
+}
+
+impl FromStr for Value {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Value, ParseError> {
+        let mut parser = Parser {
+            s,
+            buf: s.as_bytes(),
+            pos: 0,
+            depth: 0,
+        };
+
+        let val = parser.parse_value()?;
+        parser.skip_whitespace();
+        if parser.pos < parser.buf.len() {
+            return Err(parser.error("trailing characters"));
+        }
+
+        Ok(val)
+    }
+}
+
+struct Parser<'a> {
+    s: &'a str,
+    buf: &'a [u8],
+    pos: usize,
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self, desc: &'static str) -> ParseError {
+        ParseError { offset: self.pos, desc }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.buf.get(self.pos).cloned()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ') | Some(b'\t') | Some(b'\n') | Some(b'\r') = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    /// Consumes the given byte after optional whitespace.
+    fn expect(&mut self, byte: u8, desc: &'static str) -> Result<(), ParseError> {
+        self.skip_whitespace();
+        if self.peek() != Some(byte) {
+            return Err(self.error(desc));
+        }
+
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn parse_value(&mut self) -> Result<Value, ParseError> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(b'[') => self.parse_array(),
+            Some(b'{') => self.parse_map(),
+            Some(b'"') => Ok(Value::from(self.parse_string()?)),
+            Some(b'-') | Some(b'0'..=b'9') => self.parse_number(),
+            Some(b'a'..=b'z') => self.parse_word(),
+            Some(..) => Err(self.error("expected a value")),
+            None => Err(self.error("unexpected end of input")),
+        }
+    }
+
+    fn enter(&mut self) -> Result<(), ParseError> {
+        if self.depth >= MAX_DEPTH {
+            return Err(self.error("nesting too deep"));
+        }
+
+        self.depth += 1;
+        self.pos += 1;
+        Ok(())
+    }
+
+    /// Calls the function for each comma-separated element up to the closing byte.
+    fn parse_elements<F>(&mut self, close: u8, mut f: F) -> Result<(), ParseError>
+        where F: FnMut(&mut Self) -> Result<(), ParseError>
+    {
+        self.enter()?;
+
+        self.skip_whitespace();
+        if self.peek() == Some(close) {
+            self.pos += 1;
+            self.depth -= 1;
+            return Ok(());
+        }
+
+        loop {
+            f(self)?;
+
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(byte) if byte == close => {
+                    self.pos += 1;
+                    self.depth -= 1;
+                    return Ok(());
+                }
+                _ => return Err(self.error("expected ',' or a closing bracket")),
+            }
+        }
+    }
+
+    fn parse_array(&mut self) -> Result<Value, ParseError> {
+        let mut vec = Vec::new();
+        self.parse_elements(b']', |parser| {
+            vec.push(parser.parse_value()?);
+            Ok(())
+        })?;
+
+        Ok(Value::Array(vec))
+    }
+
+    fn parse_map(&mut self) -> Result<Value, ParseError> {
+        let mut map = Vec::new();
+        self.parse_elements(b'}', |parser| {
+            let key = parser.parse_value()?;
+            parser.expect(b':', "expected ':'")?;
+            map.push((key, parser.parse_value()?));
+            Ok(())
+        })?;
+
+        Ok(Value::Map(map))
+    }
+
+    fn parse_string(&mut self) -> Result<String, ParseError> {
+        // Skip the opening quote.
+        self.pos += 1;
+
+        let mut s = String::new();
+        loop {
+            let start = self.pos;
+            while let Some(byte) = self.peek() {
+                if byte == b'"' || byte == b'\\' || byte < 0x20 {
+                    break;
+                }
+                self.pos += 1;
+            }
+            // The run ends at an ASCII byte, so it is on a character boundary.
+            s.push_str(&self.s[start..self.pos]);
+
+            match self.peek() {
+                Some(b'"') => {
+                    self.pos += 1;
+                    return Ok(s);
+                }
+                Some(b'\\') => {
+                    self.pos += 1;
+                    s.push(self.parse_escape()?);
+                }
+                Some(..) => return Err(self.error("control character in string")),
+                None => return Err(self.error("unterminated string")),
+            }
+        }
+    }
+
+    fn parse_escape(&mut self) -> Result<char, ParseError> {
+        let ch = match self.peek() {
+            Some(b'"') => '"',
+            Some(b'\\') => '\\',
+            Some(b'/') => '/',
+            Some(b'b') => '\u{8}',
+            Some(b'f') => '\u{c}',
+            Some(b'n') => '\n',
+            Some(b'r') => '\r',
+            Some(b't') => '\t',
+            Some(b'u') => {
+                self.pos += 1;
+                let code = self.parse_hex4()?;
+                if code & 0xfc00 != 0xd800 {
+                    let ch = char::from_u32(code);
+                    return ch.ok_or_else(|| self.error("invalid unicode escape"));
+                }
+
+                // A high surrogate must be followed by an escaped low one.
+                if self.buf[self.pos..].starts_with(b"\\u") {
+                    self.pos += 2;
+                    let low = self.parse_hex4()?;
+                    if low & 0xfc00 == 0xdc00 {
+                        let code = 0x10000 + ((code - 0xd800) << 10) + (low - 0xdc00);
+                        if let Some(ch) = char::from_u32(code) {
+                            return Ok(ch);
+                        }
+                    }
+                }
+                return Err(self.error("invalid unicode escape"));
+            }
+            _ => return Err(self.error("invalid escape")),
+        };
+
+        self.pos += 1;
+        Ok(ch)
+    }
+
+    fn parse_hex4(&mut self) -> Result<u32, ParseError> {
+        let mut code = 0;
+        for _ in 0..4 {
+            let digit = self.peek()
+                .and_then(|byte| (byte as char).to_digit(16))
+                .ok_or_else(|| self.error("invalid unicode escape"))?;
+            code = code << 4 | digit;
+            self.pos += 1;
+        }
+
+        Ok(code)
+    }
+
+    fn parse_number(&mut self) -> Result<Value, ParseError> {
+        let start = self.pos;
+        let mut float = false;
+        while let Some(byte) = self.peek() {
+            match byte {
+                b'0'..=b'9' | b'-' | b'+' => {}
+                b'.' | b'e' | b'E' => float = true,
+                _ => break,
+            }
+            self.pos += 1;
+        }
+
+        let s = &self.s[start..self.pos];
+        let val = if float {
+            s.parse::<f64>().ok().map(Value::F64)
+        } else if s.starts_with('-') {
+            s.parse::<i64>().ok().map(Value::from)
+        } else {
+            s.parse::<u64>().ok().map(Value::from)
+        };
+
+        val.ok_or(ParseError { offset: start, desc: "invalid number" })
+    }
+
+    fn parse_word(&mut self) -> Result<Value, ParseError> {
+        let start = self.pos;
+        while let Some(b'a'..=b'z') | Some(b'0'..=b'9') = self.peek() {
+            self.pos += 1;
+        }
+
+        match &self.buf[start..self.pos] {
+            b"null" | b"nil" => Ok(Value::Nil),
+            b"true" => Ok(Value::Boolean(true)),
+            b"false" => Ok(Value::Boolean(false)),
+            b"bin" => {
+                self.expect(b'(', "expected '('")?;
+                Ok(Value::Binary(self.parse_hex_bytes()?))
+            }
+            b"ext" => {
+                self.expect(b'(', "expected '('")?;
+                self.skip_whitespace();
+                let ty = match self.parse_number()? {
+                    Value::Integer(n) => n.as_i64(),
+                    _ => None,
+                };
+                let ty = match ty {
+                    Some(ty) if ty >= i8::MIN as i64 && ty <= i8::MAX as i64 => ty as i8,
+                    _ => return Err(ParseError { offset: start, desc: "invalid extension type" }),
+                };
+                self.expect(b',', "expected ','")?;
+                Ok(Value::Ext(ty, self.parse_hex_bytes()?))
+            }
+            b"f32" => {
+                let val = self.parse_float()?.parse().ok();
+                val.map(Value::F32).ok_or(ParseError { offset: start, desc: "invalid number" })
+            }
+            b"f64" => {
+                let val = self.parse_float()?.parse().ok();
+                val.map(Value::F64).ok_or(ParseError { offset: start, desc: "invalid number" })
+            }
+            b"str" => {
+                self.expect(b'(', "expected '('")?;
+                let s = match String::from_utf8(self.parse_hex_bytes()?) {
+                    Ok(s) => Utf8String::from(s),
+                    Err(err) => {
+                        let e = err.utf8_error();
+                        Utf8String { s: Err((err.into_bytes(), e)) }
+                    }
+                };
+                Ok(Value::String(s))
+            }
+            _ => Err(ParseError { offset: start, desc: "unknown literal" }),
+        }
+    }
+
+    /// Returns the text of the float inside parentheses, which is checked by the caller.
+    fn parse_float(&mut self) -> Result<&'a str, ParseError> {
+        self.expect(b'(', "expected '('")?;
+        self.skip_whitespace();
+
+        let start = self.pos;
+        while let Some(b'0'..=b'9') | Some(b'a'..=b'z') | Some(b'.') | Some(b'-') | Some(b'+') =
+            self.peek()
+        {
+            self.pos += 1;
+        }
+        let s = &self.s[start..self.pos];
+
+        self.expect(b')', "expected ')'")?;
+        Ok(s)
+    }
+
+    /// Parses hexadecimal bytes up to the closing parenthesis.
+    fn parse_hex_bytes(&mut self) -> Result<Vec<u8>, ParseError> {
+        let mut vec = Vec::new();
+        loop {
+            self.skip_whitespace();
+            if self.peek() == Some(b')') {
+                self.pos += 1;
+                return Ok(vec);
+            }
+
+            let mut byte = 0;
+            for _ in 0..2 {
+                let digit = self.peek()
+                    .and_then(|byte| (byte as char).to_digit(16))
+                    .ok_or_else(|| self.error("expected a hexadecimal byte"))?;
+                byte = byte << 4 | digit as u8;
+                self.pos += 1;
+            }
+            vec.push(byte);
+        }
+    }
+}
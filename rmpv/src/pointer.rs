@@ -0,0 +1,164 @@
+//! Pointer paths addressing locations inside values, shared by redaction, transformations and
+//! `Value::pointer`.
+//!
+//! Paths follow the JSON Pointer syntax: segments are prefixed with `/`, with `~1` standing for
+//! `/` and `~0` for `~` inside a segment. A segment matches a string map key, an integer map key
+//! or an array index with the same textual representation, while the segment `*` matches any of
+//! them, except in `Value::pointer` which addresses a single value. The empty path addresses the
+//! whole value.
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use Value;
+
+/// A single segment of a pointer path.
+#[derive(Clone, Debug)]
+pub enum Segment {
+    /// Matches a map key or an array index with exactly this representation.
+    Name(String),
+    /// Matches any map key or array index.
+    Any,
+}
+
+impl Segment {
+    pub fn matches_key(&self, key: &Value) -> bool {
+        match *self {
+            Segment::Any => true,
+            Segment::Name(ref name) => {
+                match *key {
+                    Value::String(ref s) => s.as_str() == Some(name.as_str()),
+                    Value::Integer(ref n) => n.to_string() == *name,
+                    _ => false,
+                }
+            }
+        }
+    }
+
+    pub fn matches_index(&self, idx: usize) -> bool {
+        match *self {
+            Segment::Any => true,
+            Segment::Name(ref name) => name.parse() == Ok(idx),
+        }
+    }
+
+    /// Returns the array index this segment names, if any.
+    fn index(&self) -> Option<usize> {
+        match *self {
+            Segment::Any => None,
+            Segment::Name(ref name) => name.parse().ok(),
+        }
+    }
+}
+
+/// Splits the pointer path into its segments.
+pub fn parse(path: &str) -> Vec<Segment> {
+    path.split('/')
+        .skip(1)
+        .map(|segment| {
+            if segment == "*" {
+                Segment::Any
+            } else {
+                Segment::Name(unescape(segment))
+            }
+        })
+        .collect()
+}
+
+/// Splits the pointer path into its segments like `parse`, or returns `None` if it is neither
+/// empty nor starts with `/`.
+pub fn parse_checked(path: &str) -> Option<Vec<Segment>> {
+    if is_valid(path) {
+        Some(parse(path))
+    } else {
+        None
+    }
+}
+
+fn is_valid(path: &str) -> bool {
+    path.is_empty() || path.starts_with('/')
+}
+
+fn unescape(segment: &str) -> String {
+    segment.replace("~1", "/").replace("~0", "~")
+}
+
+/// Splits the pointer path into segments matching a single key or index each, or returns `None`
+/// if it is neither empty nor starts with `/`.
+fn parse_exact(path: &str) -> Option<Vec<Segment>> {
+    if !is_valid(path) {
+        return None;
+    }
+
+    Some(path.split('/').skip(1).map(|segment| Segment::Name(unescape(segment))).collect())
+}
+
+impl Value {
+    /// Looks up a value by a pointer path like `/users/0/name`, returning `None` if nothing is
+    /// found there.
+    ///
+    /// Each segment is matched against string and integer map keys or array indices, the first
+    /// matching entry of a map being taken. The path must be empty, addressing the value itself,
+    /// or start with `/`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rmpv::Value;
+    ///
+    /// let val: Value = r#"{"users": [{"name": "alice"}, {"name": "bob"}], 7: "seven"}"#
+    ///     .parse()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(Some(&Value::from("bob")), val.pointer("/users/1/name"));
+    /// assert_eq!(Some(&Value::from("seven")), val.pointer("/7"));
+    /// assert_eq!(None, val.pointer("/users/2"));
+    /// assert_eq!(Some(&val), val.pointer(""));
+    /// ```
+    pub fn pointer(&self, path: &str) -> Option<&Value> {
+        let mut val = self;
+        for segment in parse_exact(path)? {
+            val = match *val {
+                Value::Map(ref map) => {
+                    &map.iter().find(|entry| segment.matches_key(&entry.0))?.1
+                }
+                Value::Array(ref vec) => {
+                    vec.get(segment.index()?)?
+                }
+                _ => return None,
+            };
+        }
+
+        Some(val)
+    }
+
+    /// Looks up a value by a pointer path like `pointer` does, returning a mutable reference to
+    /// it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rmpv::Value;
+    ///
+    /// let mut val: Value = r#"{"limits": {"depth": 8}}"#.parse().unwrap();
+    ///
+    /// *val.pointer_mut("/limits/depth").unwrap() = Value::from(16);
+    /// assert_eq!(Value::from(16), val["limits"]["depth"]);
+    /// ```
+    pub fn pointer_mut(&mut self, path: &str) -> Option<&mut Value> {
+        let mut val = self;
+        for segment in parse_exact(path)? {
+            val = match *val {
+                Value::Map(ref mut map) => {
+                    &mut map.iter_mut().find(|entry| segment.matches_key(&entry.0))?.1
+                }
+                Value::Array(ref mut vec) => {
+                    vec.get_mut(segment.index()?)?
+                }
+                _ => return None,
+            };
+        }
+
+        Some(val)
+    }
+}
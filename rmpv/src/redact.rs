@@ -0,0 +1,225 @@
+//! Masking of sensitive fields, so that values can be logged safely.
+//!
+//! # Examples
+//!
+//! ```
+//! use rmpv::Value;
+//! use rmpv::redact::Redactor;
+//!
+//! let val = Value::Map(vec![
+//!     (Value::from("user"), Value::Map(vec![
+//!         (Value::from("name"), Value::from("John")),
+//!         (Value::from("password"), Value::from("hunter2")),
+//!     ])),
+//!     (Value::from("api_token"), Value::from("0123456789")),
+//! ]);
+//!
+//! let redactor = Redactor::new()
+//!     .path("/user/password")
+//!     .key("*token*");
+//!
+//! let expected = r#"{"user": {"name": "John", "password": "***"}, "api_token": "***"}"#;
+//! assert_eq!(expected, format!("{}", redactor.redact(&val)));
+//! ```
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::convert::Infallible;
+
+use rmp::encode::RmpWrite;
+
+use Value;
+use encode::write_value;
+use pointer::{self, Segment};
+
+/// Describes what a redacted value is replaced with.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Mask {
+    /// Replace with the given value.
+    Value(Value),
+    /// Replace with a string holding the 64-bit FNV-1a hash of the value's MessagePack encoding in
+    /// hexadecimal.
+    ///
+    /// Equal values produce equal hashes, so they can still be correlated across log entries.
+    /// The hash is not cryptographic: short or guessable secrets can be recovered by brute force.
+    Hash,
+}
+
+impl Default for Mask {
+    fn default() -> Mask {
+        Mask::Value(Value::from("***"))
+    }
+}
+
+/// Produces copies of values with the selected fields masked.
+///
+/// Fields are selected either by pointer paths, which address a single location, or by key
+/// patterns, which match map keys at any depth. When a field is selected, its whole value is
+/// replaced by the mask, while the key is kept.
+///
+/// Like the decoding `Config`, a `Redactor` is built once and can be reused for any number of
+/// values.
+#[derive(Clone, Debug, Default)]
+pub struct Redactor {
+    paths: Vec<Vec<Segment>>,
+    keys: Vec<String>,
+    mask: Mask,
+}
+
+impl Redactor {
+    /// Constructs a new `Redactor`, which selects nothing and masks with `"***"`.
+    pub fn new() -> Redactor {
+        Redactor::default()
+    }
+
+    /// Selects the value at the given pointer path.
+    ///
+    /// Paths follow the JSON Pointer syntax: segments are prefixed with `/`, with `~1` standing for
+    /// `/` and `~0` for `~` inside a segment. A segment matches a string map key, an integer map
+    /// key or an array index with the same textual representation, while the segment `*` matches
+    /// any of them. The empty path selects the whole value.
+    pub fn path(mut self, path: &str) -> Redactor {
+        self.paths.push(pointer::parse(path));
+        self
+    }
+
+    /// Selects the values of all string map keys matching the given pattern, at any depth.
+    ///
+    /// The pattern is compared exactly, except for `*`, which matches any sequence of characters,
+    /// so `*token*` selects both `token` and `api_token_v2`.
+    pub fn key(mut self, pattern: &str) -> Redactor {
+        self.keys.push(pattern.into());
+        self
+    }
+
+    /// Sets what the selected values are replaced with.
+    pub fn mask(mut self, mask: Mask) -> Redactor {
+        self.mask = mask;
+        self
+    }
+
+    /// Returns a copy of the given value with all selected fields masked.
+    pub fn redact(&self, val: &Value) -> Value {
+        let paths = self.paths.iter().map(|path| &path[..]).collect::<Vec<_>>();
+        self.redact_value(val, &paths)
+    }
+
+    /// Redacts the value, given the remainders of the paths matched so far.
+    fn redact_value(&self, val: &Value, paths: &[&[Segment]]) -> Value {
+        if paths.iter().any(|path| path.is_empty()) {
+            return self.masked(val);
+        }
+
+        match *val {
+            Value::Array(ref vec) => {
+                let vec = vec.iter()
+                    .enumerate()
+                    .map(|(idx, val)| {
+                        let paths = descend(paths, |segment| segment.matches_index(idx));
+                        self.redact_value(val, &paths)
+                    })
+                    .collect();
+
+                Value::Array(vec)
+            }
+            Value::Map(ref map) => {
+                let map = map.iter()
+                    .map(|entry| {
+                        let (ref key, ref val) = *entry;
+                        let val = if self.is_secret_key(key) {
+                            self.masked(val)
+                        } else {
+                            let paths = descend(paths, |segment| segment.matches_key(key));
+                            self.redact_value(val, &paths)
+                        };
+
+                        (key.clone(), val)
+                    })
+                    .collect();
+
+                Value::Map(map)
+            }
+            ref val => val.clone(),
+        }
+    }
+
+    fn is_secret_key(&self, key: &Value) -> bool {
+        match key.as_str() {
+            Some(key) => self.keys.iter().any(|pattern| glob_match(pattern, key)),
+            None => false,
+        }
+    }
+
+    fn masked(&self, val: &Value) -> Value {
+        match self.mask {
+            Mask::Value(ref mask) => mask.clone(),
+            Mask::Hash => {
+                let mut hasher = Fnv1a::new();
+                match write_value(&mut hasher, val) {
+                    Ok(()) => {}
+                    Err(..) => unreachable!(),
+                }
+
+                Value::from(format!("{:016x}", hasher.0))
+            }
+        }
+    }
+}
+
+/// Returns the remainders of the paths whose first segment matches.
+fn descend<'a, F>(paths: &[&'a [Segment]], f: F) -> Vec<&'a [Segment]>
+    where F: Fn(&Segment) -> bool
+{
+    paths.iter()
+        .filter(|path| f(&path[0]))
+        .map(|path| &path[1..])
+        .collect()
+}
+
+/// Matches the text against a pattern, where `*` stands for any sequence of characters.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or("");
+    if !text.starts_with(first) {
+        return false;
+    }
+
+    let mut rest = &text[first.len()..];
+    let mut parts = parts.collect::<Vec<_>>();
+    let last = match parts.pop() {
+        Some(last) => last,
+        // No wildcards at all.
+        None => return rest.is_empty(),
+    };
+
+    for part in parts {
+        match rest.find(part) {
+            Some(pos) => rest = &rest[pos + part.len()..],
+            None => return false,
+        }
+    }
+
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+/// A 64-bit FNV-1a hasher, fed with the encoded value.
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn new() -> Fnv1a {
+        Fnv1a(0xcbf29ce484222325)
+    }
+}
+
+impl RmpWrite for Fnv1a {
+    type Error = Infallible;
+
+    fn write_bytes(&mut self, buf: &[u8]) -> Result<(), Infallible> {
+        for &byte in buf {
+            self.0 ^= byte as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+
+        Ok(())
+    }
+}
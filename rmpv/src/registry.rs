@@ -0,0 +1,302 @@
+//! Typed decoding of extensions through a registry of codecs.
+//!
+//! A `Value` holds every extension as its raw type and data, leaving it to the application to
+//! recognize UUIDs, decimals or its own types afterwards. An `ExtRegistry` maps extension types to
+//! a pair of functions converting between the data and a Rust type, and decodes values into a
+//! `TypedValue` tree where registered extensions are already converted.
+//!
+//! Extensions that are not registered, or whose data the decode function rejects, are kept as
+//! `Value::Ext`. Values produced by serde, like `ext::to_value` does, are converted with
+//! `ExtRegistry::to_typed` in the same way.
+//!
+//! # Examples
+//!
+//! ```
+//! use rmpv::Value;
+//! use rmpv::decode::Config;
+//! use rmpv::registry::{ExtRegistry, TypedValue};
+//! use rmpv::timestamp::{self, Timestamp};
+//!
+//! let registry = ExtRegistry::new()
+//!     .register(timestamp::EXT_TYPE, Timestamp::to_ext_data, Timestamp::from_ext_data);
+//!
+//! // [timestamp(1500000000), ext(5, [1])]
+//! let buf = [0x92, 0xd6, 0xff, 0x59, 0x68, 0x2f, 0x00, 0xd4, 0x05, 0x01];
+//! let val = registry.read_value(&mut &buf[..], &Config::new()).unwrap();
+//!
+//! if let TypedValue::Array(ref vec) = val {
+//!     assert_eq!(Some(&Timestamp::new(1500000000, 0).unwrap()), vec[0].downcast_ref());
+//!     assert!(vec[1].downcast_ref::<Timestamp>().is_none());
+//! }
+//!
+//! assert_eq!(Value::from(Timestamp::new(1500000000, 0).unwrap()),
+//!            registry.encode(&Timestamp::new(1500000000, 0).unwrap()).unwrap());
+//! ```
+
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::any::{Any, TypeId};
+use core::fmt::{self, Debug, Formatter};
+
+use rmp::decode::RmpRead;
+use rmp::decode as rmp_decode;
+
+use {Integer, Utf8String, Value};
+use decode::{read_value_with_builder, Config, Error, ValueBuilder};
+
+/// A value decoded with an `ExtRegistry`, where registered extensions hold their Rust type.
+pub enum TypedValue {
+    /// Any value but an array, a map or a registered extension.
+    Plain(Value),
+    /// An array of typed values.
+    Array(Vec<TypedValue>),
+    /// A map of typed keys and values.
+    Map(Vec<(TypedValue, TypedValue)>),
+    /// A registered extension of the given type, decoded by its codec.
+    Typed(i8, Box<dyn Any>),
+}
+
+impl TypedValue {
+    /// If the value is a registered extension decoded into a `T`, returns a reference to it.
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        match *self {
+            TypedValue::Typed(_, ref val) => val.downcast_ref(),
+            _ => None,
+        }
+    }
+
+    /// If the value is neither an array, a map nor a registered extension, returns the `Value`
+    /// it holds.
+    pub fn as_plain(&self) -> Option<&Value> {
+        match *self {
+            TypedValue::Plain(ref val) => Some(val),
+            _ => None,
+        }
+    }
+}
+
+impl Debug for TypedValue {
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            TypedValue::Plain(ref val) => fmt.debug_tuple("Plain").field(val).finish(),
+            TypedValue::Array(ref vec) => fmt.debug_tuple("Array").field(vec).finish(),
+            TypedValue::Map(ref map) => fmt.debug_tuple("Map").field(map).finish(),
+            // Registered types are not required to implement `Debug`.
+            TypedValue::Typed(ty, _) => write!(fmt, "Typed({}, ..)", ty),
+        }
+    }
+}
+
+type EncodeFn = Box<dyn Fn(&dyn Any) -> Option<Vec<u8>>>;
+type DecodeFn = Box<dyn Fn(&[u8]) -> Option<Box<dyn Any>>>;
+
+/// The type-erased functions registered for an extension type.
+struct Codec {
+    type_id: TypeId,
+    encode: EncodeFn,
+    decode: DecodeFn,
+}
+
+/// Maps extension types to the functions converting their data from and into Rust types.
+#[derive(Default)]
+pub struct ExtRegistry {
+    codecs: BTreeMap<i8, Codec>,
+}
+
+impl ExtRegistry {
+    /// Constructs an empty registry.
+    pub fn new() -> ExtRegistry {
+        ExtRegistry::default()
+    }
+
+    /// Registers the functions encoding a `T` into the data of extensions of type `ty` and
+    /// decoding it back, replacing any previously registered for `ty`.
+    ///
+    /// The decode function returns `None` for malformed data, which leaves the extension raw.
+    /// A Rust type should be registered for a single extension type, since `encode` picks any of
+    /// them otherwise.
+    pub fn register<T: Any>(mut self, ty: i8, encode: fn(&T) -> Vec<u8>,
+                            decode: fn(&[u8]) -> Option<T>) -> ExtRegistry
+    {
+        let codec = Codec {
+            type_id: TypeId::of::<T>(),
+            encode: Box::new(move |val: &dyn Any| val.downcast_ref().map(encode)),
+            decode: Box::new(move |data: &[u8]| {
+                decode(data).map(|val| Box::new(val) as Box<dyn Any>)
+            }),
+        };
+        self.codecs.insert(ty, codec);
+        self
+    }
+
+    /// Returns `true` if functions are registered for the extension type.
+    pub fn is_registered(&self, ty: i8) -> bool {
+        self.codecs.contains_key(&ty)
+    }
+
+    /// Decodes the value into a `T` if it is an extension registered for that type.
+    ///
+    /// Returns `None` for other values and for malformed data.
+    pub fn decode<T: Any>(&self, val: &Value) -> Option<T> {
+        match *val {
+            Value::Ext(ty, ref data) => {
+                let codec = self.codecs.get(&ty)?;
+                if codec.type_id != TypeId::of::<T>() {
+                    return None;
+                }
+                (codec.decode)(data)?.downcast().ok().map(|val| *val)
+            }
+            _ => None,
+        }
+    }
+
+    /// Encodes the value as an extension, or returns `None` if its type is not registered.
+    pub fn encode<T: Any>(&self, val: &T) -> Option<Value> {
+        self.codecs.iter()
+            .find(|&(_, codec)| codec.type_id == TypeId::of::<T>())
+            .and_then(|(&ty, codec)| (codec.encode)(val).map(|data| Value::Ext(ty, data)))
+    }
+
+    /// Reads a value from the given reader like `read_value_with_config`, decoding registered
+    /// extensions with their codecs.
+    ///
+    /// Like with other custom builders, the duplicate key policy of the config is not applied.
+    ///
+    /// # Errors
+    ///
+    /// This function returns the same errors as `read_value_with_builder`.
+    pub fn read_value<R>(&self, rd: &mut R, config: &Config) -> Result<TypedValue, Error>
+        where R: RmpRead,
+              R::Error: Into<rmp_decode::Error>
+    {
+        read_value_with_builder(rd, config, &mut TypedTree { registry: self })
+    }
+
+    /// Converts a value into a `TypedValue`, decoding registered extensions with their codecs.
+    pub fn to_typed(&self, val: Value) -> TypedValue {
+        match val {
+            Value::Array(vec) => {
+                TypedValue::Array(vec.into_iter().map(|val| self.to_typed(val)).collect())
+            }
+            Value::Map(map) => {
+                let map = map.into_iter()
+                    .map(|(key, val)| (self.to_typed(key), self.to_typed(val)))
+                    .collect();
+                TypedValue::Map(map)
+            }
+            Value::Ext(ty, data) => self.typed_ext(ty, data),
+            val => TypedValue::Plain(val),
+        }
+    }
+
+    /// Converts a `TypedValue` back into a value, encoding registered extensions with their
+    /// codecs.
+    ///
+    /// Typed extensions whose type is not registered with this registry are written as nil.
+    pub fn to_value(&self, val: TypedValue) -> Value {
+        match val {
+            TypedValue::Plain(val) => val,
+            TypedValue::Array(vec) => {
+                Value::Array(vec.into_iter().map(|val| self.to_value(val)).collect())
+            }
+            TypedValue::Map(map) => {
+                let map = map.into_iter()
+                    .map(|(key, val)| (self.to_value(key), self.to_value(val)))
+                    .collect();
+                Value::Map(map)
+            }
+            TypedValue::Typed(ty, val) => {
+                self.codecs.get(&ty)
+                    .and_then(|codec| (codec.encode)(&*val))
+                    .map_or(Value::Nil, |data| Value::Ext(ty, data))
+            }
+        }
+    }
+
+    fn typed_ext(&self, ty: i8, data: Vec<u8>) -> TypedValue {
+        match self.codecs.get(&ty).and_then(|codec| (codec.decode)(&data)) {
+            Some(val) => TypedValue::Typed(ty, val),
+            None => TypedValue::Plain(Value::Ext(ty, data)),
+        }
+    }
+}
+
+impl Debug for ExtRegistry {
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), fmt::Error> {
+        fmt.debug_struct("ExtRegistry")
+            .field("types", &self.codecs.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+/// Builds `TypedValue` trees, backing `ExtRegistry::read_value`.
+struct TypedTree<'a> {
+    registry: &'a ExtRegistry,
+}
+
+impl<'a> ValueBuilder for TypedTree<'a> {
+    type Value = TypedValue;
+    type Array = Vec<TypedValue>;
+    type Map = Vec<(TypedValue, TypedValue)>;
+
+    fn nil(&mut self) -> Result<TypedValue, Error> {
+        Ok(TypedValue::Plain(Value::Nil))
+    }
+
+    fn boolean(&mut self, val: bool) -> Result<TypedValue, Error> {
+        Ok(TypedValue::Plain(Value::Boolean(val)))
+    }
+
+    fn integer(&mut self, val: Integer) -> Result<TypedValue, Error> {
+        Ok(TypedValue::Plain(Value::Integer(val)))
+    }
+
+    fn f32(&mut self, val: f32) -> Result<TypedValue, Error> {
+        Ok(TypedValue::Plain(Value::F32(val)))
+    }
+
+    fn f64(&mut self, val: f64) -> Result<TypedValue, Error> {
+        Ok(TypedValue::Plain(Value::F64(val)))
+    }
+
+    fn string(&mut self, val: Utf8String) -> Result<TypedValue, Error> {
+        Ok(TypedValue::Plain(Value::String(val)))
+    }
+
+    fn binary(&mut self, val: Vec<u8>) -> Result<TypedValue, Error> {
+        Ok(TypedValue::Plain(Value::Binary(val)))
+    }
+
+    fn ext(&mut self, ty: i8, data: Vec<u8>) -> Result<TypedValue, Error> {
+        Ok(self.registry.typed_ext(ty, data))
+    }
+
+    fn begin_array(&mut self, len: u32) -> Result<Vec<TypedValue>, Error> {
+        Ok(Vec::with_capacity(len as usize))
+    }
+
+    fn push_element(&mut self, array: &mut Vec<TypedValue>, val: TypedValue) -> Result<(), Error> {
+        array.push(val);
+        Ok(())
+    }
+
+    fn end_array(&mut self, array: Vec<TypedValue>) -> Result<TypedValue, Error> {
+        Ok(TypedValue::Array(array))
+    }
+
+    fn begin_map(&mut self, len: u32) -> Result<Vec<(TypedValue, TypedValue)>, Error> {
+        Ok(Vec::with_capacity(len as usize))
+    }
+
+    fn push_entry(&mut self, map: &mut Vec<(TypedValue, TypedValue)>, key: TypedValue,
+                  val: TypedValue) -> Result<(), Error>
+    {
+        map.push((key, val));
+        Ok(())
+    }
+
+    fn end_map(&mut self, map: Vec<(TypedValue, TypedValue)>) -> Result<TypedValue, Error> {
+        Ok(TypedValue::Map(map))
+    }
+}
@@ -0,0 +1,487 @@
+//! MessagePack-RPC messages and blocking transports.
+//!
+//! The protocol exchanges three kinds of messages, each encoded as an array:
+//!
+//! - a request `[0, id, method, params]`, which the peer answers with a response of the same id;
+//! - a response `[1, id, error, result]`, where the error is nil on success;
+//! - a notification `[2, method, params]`, which is not answered.
+//!
+//! `Message` converts between these arrays and typed messages and reads and writes them on a
+//! stream. On top of it a `Client` sends requests with increasing ids and waits for their
+//! responses, while `serve` answers the requests it receives with a `Handler`. Both work over any
+//! pair of `io::Read` and `io::Write` halves, like a cloned `TcpStream` or the standard input and
+//! output of a child process, and block until the peer answers.
+//!
+//! Messages are decoded with the limits of `decode_config`, so that a peer can't make the reader
+//! allocate more memory than a message may reasonably need by announcing huge lengths. The
+//! `_with_config` variants take other limits.
+//!
+//! # Examples
+//!
+//! ```
+//! use std::io::Cursor;
+//!
+//! use rmpv::Value;
+//! use rmpv::rpc::{self, Client, Handler, Message, Request};
+//!
+//! struct Adder;
+//!
+//! impl Handler for Adder {
+//!     fn request(&mut self, method: &str, params: Vec<Value>) -> Result<Value, Value> {
+//!         match method {
+//!             "add" => Ok(Value::from(params.iter().filter_map(Value::as_u64).sum::<u64>())),
+//!             _ => Err(Value::from("unknown method")),
+//!         }
+//!     }
+//! }
+//!
+//! // The request a client would send, as the server receives it.
+//! let req = Message::Request(Request {
+//!     id: 0,
+//!     method: "add".into(),
+//!     params: vec![Value::from(2), Value::from(3)],
+//! });
+//! let mut input = Vec::new();
+//! req.write(&mut input).unwrap();
+//!
+//! let mut output = Vec::new();
+//! rpc::serve(Cursor::new(input), &mut output, &mut Adder).unwrap();
+//!
+//! // A client reading the server output gets the sum.
+//! let mut client = Client::new(Cursor::new(output), Vec::new());
+//! assert_eq!(Value::from(5), client.call("add", vec![Value::from(2), Value::from(3)]).unwrap());
+//! ```
+
+use std::collections::VecDeque;
+use std::error;
+use std::fmt::{self, Display, Formatter};
+use std::io::{self, ErrorKind, Read, Write};
+
+use Value;
+use decode::{self, read_value_with_config, Config};
+use encode::write_value;
+use rmp::encode::{write_array_len, write_nil, write_str, write_uint, ValueWriteError};
+
+const REQUEST: u64 = 0;
+const RESPONSE: u64 = 1;
+const NOTIFICATION: u64 = 2;
+
+/// Returns the decoder config messages are read with unless another one is given.
+///
+/// It limits the nesting depth to 128, the length of every array, map, string, binary and
+/// extension to 2^24 and the memory allocated for a message to 64 MiB.
+pub fn decode_config() -> Config {
+    Config::new().max_depth(128).max_len(1 << 24).max_alloc(1 << 26)
+}
+
+/// A call of a method, answered by a response with the same id.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Request {
+    /// The id chosen by the caller to match the response with.
+    pub id: u32,
+    /// The name of the method to call.
+    pub method: String,
+    /// The arguments of the call.
+    pub params: Vec<Value>,
+}
+
+/// The answer to a request, holding either its result or an error.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Response {
+    /// The id of the request answered.
+    pub id: u32,
+    /// The result of the call, or the error it failed with.
+    pub result: Result<Value, Value>,
+}
+
+/// A call of a method that is not answered.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Notification {
+    /// The name of the method to call.
+    pub method: String,
+    /// The arguments of the call.
+    pub params: Vec<Value>,
+}
+
+/// A MessagePack-RPC message.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Message {
+    Request(Request),
+    Response(Response),
+    Notification(Notification),
+}
+
+/// An error reading, writing or exchanging messages.
+#[derive(Debug)]
+pub enum Error {
+    /// I/O error while writing a message, or while reading one in the middle of it.
+    Io(io::Error),
+    /// The message read is not valid MessagePack.
+    Decode(decode::Error),
+    /// The value read is not a MessagePack-RPC message.
+    InvalidMessage(Value),
+    /// A response was received for a request that is not waiting for one, with the given id.
+    UnexpectedResponse(u32),
+    /// The stream ended before the response to a request was received.
+    Closed,
+    /// The peer answered the request with the given error.
+    Remote(Value),
+}
+
+impl error::Error for Error {
+    fn description(&self) -> &str {
+        match *self {
+            Error::Io(..) => "I/O error while exchanging messages",
+            Error::Decode(..) => "error while decoding a message",
+            Error::InvalidMessage(..) => "invalid MessagePack-RPC message",
+            Error::UnexpectedResponse(..) => "response to an unknown request",
+            Error::Closed => "stream closed before the response was received",
+            Error::Remote(..) => "the request failed",
+        }
+    }
+
+    fn cause(&self) -> Option<&dyn error::Error> {
+        match *self {
+            Error::Io(ref err) => Some(err),
+            Error::Decode(ref err) => Some(err),
+            Error::InvalidMessage(..) |
+            Error::UnexpectedResponse(..) |
+            Error::Closed |
+            Error::Remote(..) => None,
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, fmt: &mut Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            Error::Io(ref err) => write!(fmt, "I/O error while exchanging messages: {}", err),
+            Error::Decode(ref err) => write!(fmt, "error while decoding a message: {}", err),
+            Error::InvalidMessage(ref val) => {
+                write!(fmt, "invalid MessagePack-RPC message: {}", val.display_truncated(2, 4, 32))
+            }
+            Error::UnexpectedResponse(id) => write!(fmt, "response to an unknown request {}", id),
+            Error::Closed => fmt.write_str("stream closed before the response was received"),
+            Error::Remote(ref val) => {
+                write!(fmt, "the request failed: {}", val.display_truncated(2, 4, 32))
+            }
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        Error::Io(err)
+    }
+}
+
+impl Message {
+    /// Converts a value into a message, failing with `Error::InvalidMessage` if it does not have
+    /// the layout of one.
+    pub fn from_value(val: Value) -> Result<Message, Error> {
+        let mut vec = match val {
+            Value::Array(vec) => vec,
+            val => return Err(Error::InvalidMessage(val)),
+        };
+
+        // The layout is checked in place first, so the parts are only moved out of a valid
+        // message and the value is returned intact otherwise.
+        let kind = vec.first().and_then(Value::as_u64);
+        match (kind, vec.len()) {
+            (Some(REQUEST), 4) if vec[2].is_str() && vec[3].is_array() => {
+                if let Some(id) = as_id(&vec[1]) {
+                    let params = vec.pop().and_then(Value::into_array).unwrap_or_default();
+                    let method = vec.pop().and_then(Value::into_string).unwrap_or_default();
+                    return Ok(Message::Request(Request { id, method, params }));
+                }
+            }
+            (Some(RESPONSE), 4) => {
+                if let Some(id) = as_id(&vec[1]) {
+                    let res = vec.pop().unwrap_or(Value::Nil);
+                    let result = match vec.pop() {
+                        Some(Value::Nil) | None => Ok(res),
+                        Some(err) => Err(err),
+                    };
+                    return Ok(Message::Response(Response { id, result }));
+                }
+            }
+            (Some(NOTIFICATION), 3) if vec[1].is_str() && vec[2].is_array() => {
+                let params = vec.pop().and_then(Value::into_array).unwrap_or_default();
+                let method = vec.pop().and_then(Value::into_string).unwrap_or_default();
+                return Ok(Message::Notification(Notification { method, params }));
+            }
+            _ => {}
+        }
+
+        Err(Error::InvalidMessage(Value::Array(vec)))
+    }
+
+    /// Reads the next message from the given reader, with the limits of `decode_config`.
+    ///
+    /// Returns `None` if the stream ends before the message starts, which is how the peer closes
+    /// the connection.
+    pub fn read<R: Read>(rd: &mut R) -> Result<Option<Message>, Error> {
+        Message::read_with_config(rd, &decode_config())
+    }
+
+    /// Reads the next message from the given reader, decoding it with the given config.
+    ///
+    /// # Errors
+    ///
+    /// Fails with `Error::Decode` if the message exceeds the limits of the config.
+    pub fn read_with_config<R: Read>(rd: &mut R, config: &Config) ->
+        Result<Option<Message>, Error>
+    {
+        // The end of the stream is only detected at the first byte, since running out of input
+        // in the middle of the message is an error.
+        let mut marker = [0];
+        loop {
+            match rd.read(&mut marker) {
+                Ok(0) => return Ok(None),
+                Ok(..) => break,
+                Err(ref err) if err.kind() == ErrorKind::Interrupted => {}
+                Err(err) => return Err(Error::Io(err)),
+            }
+        }
+
+        let val = read_value_with_config(&mut (&marker[..]).chain(rd), config)
+            .map_err(Error::Decode)?;
+        Message::from_value(val).map(Some)
+    }
+
+    /// Writes the message to the given writer in one piece, and flushes it.
+    pub fn write<W: Write>(&self, wr: &mut W) -> Result<(), Error> {
+        let mut buf = Vec::new();
+        self.encode(&mut buf).map_err(io::Error::from)?;
+        wr.write_all(&buf)?;
+        wr.flush()?;
+        Ok(())
+    }
+
+    /// Encodes the message with the layout of `Value::from`, straight from its fields.
+    fn encode(&self, buf: &mut Vec<u8>) -> Result<(), ValueWriteError> {
+        match *self {
+            Message::Request(ref req) => {
+                write_array_len(buf, 4)?;
+                write_uint(buf, REQUEST)?;
+                write_uint(buf, req.id as u64)?;
+                write_str(buf, &req.method)?;
+                write_params(buf, &req.params)
+            }
+            Message::Response(ref resp) => {
+                write_array_len(buf, 4)?;
+                write_uint(buf, RESPONSE)?;
+                write_uint(buf, resp.id as u64)?;
+                match resp.result {
+                    Ok(ref val) => {
+                        write_nil(buf).map_err(ValueWriteError::InvalidMarkerWrite)?;
+                        write_value(buf, val)
+                    }
+                    Err(ref err) => {
+                        write_value(buf, err)?;
+                        write_nil(buf).map_err(ValueWriteError::InvalidMarkerWrite)
+                    }
+                }
+            }
+            Message::Notification(ref not) => {
+                write_array_len(buf, 3)?;
+                write_uint(buf, NOTIFICATION)?;
+                write_str(buf, &not.method)?;
+                write_params(buf, &not.params)
+            }
+        }
+    }
+}
+
+fn write_params(buf: &mut Vec<u8>, params: &[Value]) -> Result<(), ValueWriteError> {
+    write_array_len(buf, params.len() as u32)?;
+    for val in params {
+        write_value(buf, val)?;
+    }
+    Ok(())
+}
+
+fn as_id(val: &Value) -> Option<u32> {
+    val.as_u64().filter(|&id| id <= u32::MAX as u64).map(|id| id as u32)
+}
+
+impl From<Message> for Value {
+    fn from(msg: Message) -> Value {
+        let vec = match msg {
+            Message::Request(req) => {
+                vec![
+                    Value::from(REQUEST),
+                    Value::from(req.id),
+                    Value::from(req.method),
+                    Value::Array(req.params),
+                ]
+            }
+            Message::Response(resp) => {
+                let (err, res) = match resp.result {
+                    Ok(val) => (Value::Nil, val),
+                    Err(err) => (err, Value::Nil),
+                };
+                vec![Value::from(RESPONSE), Value::from(resp.id), err, res]
+            }
+            Message::Notification(not) => {
+                vec![Value::from(NOTIFICATION), Value::from(not.method), Value::Array(not.params)]
+            }
+        };
+
+        Value::Array(vec)
+    }
+}
+
+/// Answers the requests and notifications received from a peer.
+pub trait Handler {
+    /// Handles a request, returning its result or the error sent back to the caller.
+    fn request(&mut self, method: &str, params: Vec<Value>) -> Result<Value, Value>;
+
+    /// Handles a notification. The default implementation ignores it.
+    fn notification(&mut self, method: &str, params: Vec<Value>) {
+        let _ = (method, params);
+    }
+}
+
+/// Reads messages from the reader until it ends, answering requests with the handler and writing
+/// their responses to the writer.
+///
+/// # Errors
+///
+/// Fails on I/O and decoding errors, on values that are not messages and on responses, since no
+/// request was sent.
+pub fn serve<R, W, H>(rd: R, wr: W, handler: &mut H) -> Result<(), Error>
+    where R: Read,
+          W: Write,
+          H: Handler
+{
+    serve_with_config(rd, wr, handler, &decode_config())
+}
+
+/// Serves requests like `serve`, decoding the messages received with the given config.
+pub fn serve_with_config<R, W, H>(mut rd: R, mut wr: W, handler: &mut H, config: &Config) ->
+    Result<(), Error>
+    where R: Read,
+          W: Write,
+          H: Handler
+{
+    while let Some(msg) = Message::read_with_config(&mut rd, config)? {
+        handle(msg, &mut wr, handler)?;
+    }
+
+    Ok(())
+}
+
+/// Answers a request or a notification with the handler, failing on responses.
+fn handle<W: Write, H: Handler>(msg: Message, wr: &mut W, handler: &mut H) -> Result<(), Error> {
+    match msg {
+        Message::Request(req) => {
+            let result = handler.request(&req.method, req.params);
+            Message::Response(Response { id: req.id, result }).write(wr)
+        }
+        Message::Notification(not) => {
+            handler.notification(&not.method, not.params);
+            Ok(())
+        }
+        Message::Response(resp) => Err(Error::UnexpectedResponse(resp.id)),
+    }
+}
+
+/// Keeps the notifications received while waiting for a response, and refuses requests.
+struct Queue<'a>(&'a mut VecDeque<Notification>);
+
+impl<'a> Handler for Queue<'a> {
+    fn request(&mut self, method: &str, _params: Vec<Value>) -> Result<Value, Value> {
+        Err(Value::from(format!("the client does not serve {}", method)))
+    }
+
+    fn notification(&mut self, method: &str, params: Vec<Value>) {
+        self.0.push_back(Notification { method: method.into(), params });
+    }
+}
+
+/// Sends requests and notifications to a peer, waiting for the response of each request.
+///
+/// Ids are assigned to requests in sequence, wrapping around after `u32::MAX`.
+#[derive(Debug)]
+pub struct Client<R, W> {
+    rd: R,
+    wr: W,
+    next_id: u32,
+    notifications: VecDeque<Notification>,
+    config: Config,
+}
+
+impl<R: Read, W: Write> Client<R, W> {
+    /// Constructs a client reading responses from the reader and writing requests to the writer.
+    pub fn new(rd: R, wr: W) -> Self {
+        Client::with_config(rd, wr, decode_config())
+    }
+
+    /// Constructs a client decoding the messages it receives with the given config.
+    pub fn with_config(rd: R, wr: W, config: Config) -> Self {
+        Client {
+            rd,
+            wr,
+            next_id: 0,
+            notifications: VecDeque::new(),
+            config,
+        }
+    }
+
+    /// Calls the method with the given parameters, returning its result.
+    ///
+    /// Notifications received in the meantime are kept for `next_notification`, and requests from
+    /// the peer are answered with an error. Use `call_with` to handle them instead.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Remote` if the peer answers with an error, and `Error::Closed` if the
+    /// stream ends before the response.
+    pub fn call(&mut self, method: &str, params: Vec<Value>) -> Result<Value, Error> {
+        let mut notifications = VecDeque::new();
+        let res = self.call_with(method, params, &mut Queue(&mut notifications));
+        self.notifications.append(&mut notifications);
+        res
+    }
+
+    /// Calls the method with the given parameters, answering the requests and notifications
+    /// received until its response with the given handler.
+    pub fn call_with<H: Handler>(&mut self, method: &str, params: Vec<Value>, handler: &mut H)
+        -> Result<Value, Error>
+    {
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1);
+
+        let req = Request { id, method: method.into(), params };
+        Message::Request(req).write(&mut self.wr)?;
+
+        loop {
+            match Message::read_with_config(&mut self.rd, &self.config)? {
+                Some(Message::Response(resp)) => {
+                    if resp.id != id {
+                        return Err(Error::UnexpectedResponse(resp.id));
+                    }
+                    return resp.result.map_err(Error::Remote);
+                }
+                Some(msg) => handle(msg, &mut self.wr, handler)?,
+                None => return Err(Error::Closed),
+            }
+        }
+    }
+
+    /// Sends a notification, which the peer does not answer.
+    pub fn notify(&mut self, method: &str, params: Vec<Value>) -> Result<(), Error> {
+        Message::Notification(Notification { method: method.into(), params }).write(&mut self.wr)
+    }
+
+    /// Returns the oldest notification received while waiting for a response in `call`.
+    pub fn next_notification(&mut self) -> Option<Notification> {
+        self.notifications.pop_front()
+    }
+
+    /// Returns the reader and the writer, consuming the client.
+    pub fn into_inner(self) -> (R, W) {
+        (self.rd, self.wr)
+    }
+}
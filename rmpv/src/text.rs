@@ -0,0 +1,193 @@
+//! A human-readable text format for values, which can be parsed back without loss.
+//!
+//! `Display` shows values in a compact way meant for reading, which loses type information: it
+//! doesn't tell `F32` from `F64` or `1.0` from `1`, and prints binaries and extensions like
+//! arrays. The text written by this module is instead the syntax of the `parse` module, with
+//! annotations where JSON-like text would be ambiguous:
+//!
+//! - Binaries are written as `bin(...)` and extensions as `ext(ty, ...)`, with their data in
+//!   hexadecimal.
+//! - `F64` values always have a fraction or an exponent, so they are not mistaken for integers,
+//!   and `F32` values are wrapped in `f32(...)`. Non-finite floats are written as `nan`, `inf` and
+//!   `-inf` inside the wrapper of their width.
+//! - Strings holding invalid UTF-8 are written as `str(...)` with their bytes in hexadecimal.
+//!
+//! Rendering and parsing back any value gives the same value, except that `NaN` never compares
+//! equal to itself. This makes the format useful for test fixtures, logs and diffs of binary
+//! payloads, where `to_string_pretty` puts every element on its own line.
+//!
+//! # Examples
+//!
+//! ```
+//! use rmpv::Value;
+//! use rmpv::text;
+//!
+//! let val = Value::Map(vec![
+//!     (Value::from("id"), Value::from(42)),
+//!     (Value::from("scale"), Value::F32(1.5)),
+//!     (Value::from("raw"), Value::Binary(vec![0xde, 0xad])),
+//! ]);
+//!
+//! let s = text::to_string(&val);
+//! assert_eq!(r#"{"id": 42, "scale": f32(1.5), "raw": bin(de ad)}"#, s);
+//! assert_eq!(val, text::from_str(&s).unwrap());
+//!
+//! assert_eq!("{\n  \"id\": 42,\n  \"scale\": f32(1.5),\n  \"raw\": bin(de ad)\n}",
+//!            text::to_string_pretty(&val));
+//! ```
+
+use alloc::string::String;
+use core::fmt::{self, Write};
+
+use {Utf8String, Value};
+use parse::ParseError;
+
+/// Renders the value on a single line.
+pub fn to_string(val: &Value) -> String {
+    let mut s = String::new();
+    // Writing into a string can't fail.
+    write_value(&mut s, val, None).unwrap();
+    s
+}
+
+/// Renders the value with every array element and map entry on its own line, indented by two
+/// spaces per nesting level.
+///
+/// Empty arrays and maps are written as `[]` and `{}`.
+pub fn to_string_pretty(val: &Value) -> String {
+    let mut s = String::new();
+    write_value(&mut s, val, Some(0)).unwrap();
+    s
+}
+
+/// Parses a value from its text, accepting any text produced by `to_string` or
+/// `to_string_pretty`.
+///
+/// This is the same as `str::parse`, see the `parse` module for the syntax.
+///
+/// # Errors
+///
+/// Fails if the text is not exactly one value.
+pub fn from_str(s: &str) -> Result<Value, ParseError> {
+    s.parse()
+}
+
+/// Writes the value, indenting nested elements from the given level if there is one.
+fn write_value<W: Write>(wr: &mut W, val: &Value, indent: Option<usize>) -> fmt::Result {
+    match *val {
+        Value::Nil => wr.write_str("nil"),
+        Value::Boolean(val) => write!(wr, "{}", val),
+        Value::Integer(ref val) => write!(wr, "{}", val),
+        Value::F32(val) if val.is_finite() => write!(wr, "f32({:?})", val),
+        Value::F32(val) => write!(wr, "f32({})", non_finite(val as f64)),
+        Value::F64(val) if val.is_finite() => write!(wr, "{:?}", val),
+        Value::F64(val) => write!(wr, "f64({})", non_finite(val)),
+        Value::String(ref s) => write_string(wr, s),
+        Value::Binary(ref buf) => {
+            wr.write_str("bin(")?;
+            write_hex(wr, buf)?;
+            wr.write_str(")")
+        }
+        Value::Ext(ty, ref buf) => {
+            write!(wr, "ext({}, ", ty)?;
+            write_hex(wr, buf)?;
+            wr.write_str(")")
+        }
+        Value::Array(ref vec) => {
+            write_elements(wr, "[", "]", vec, indent, |wr, val, indent| {
+                write_value(wr, val, indent)
+            })
+        }
+        Value::Map(ref map) => {
+            write_elements(wr, "{", "}", map, indent, |wr, entry, indent| {
+                write_value(wr, &entry.0, indent)?;
+                wr.write_str(": ")?;
+                write_value(wr, &entry.1, indent)
+            })
+        }
+    }
+}
+
+fn non_finite(val: f64) -> &'static str {
+    if val.is_nan() {
+        "nan"
+    } else if val > 0.0 {
+        "inf"
+    } else {
+        "-inf"
+    }
+}
+
+fn write_string<W: Write>(wr: &mut W, s: &Utf8String) -> fmt::Result {
+    let s = match s.as_str() {
+        Some(s) => s,
+        None => {
+            wr.write_str("str(")?;
+            write_hex(wr, s.as_bytes())?;
+            return wr.write_str(")");
+        }
+    };
+
+    wr.write_char('"')?;
+    for ch in s.chars() {
+        match ch {
+            '"' => wr.write_str("\\\"")?,
+            '\\' => wr.write_str("\\\\")?,
+            '\n' => wr.write_str("\\n")?,
+            '\r' => wr.write_str("\\r")?,
+            '\t' => wr.write_str("\\t")?,
+            ch if (ch as u32) < 0x20 => write!(wr, "\\u{:04x}", ch as u32)?,
+            ch => wr.write_char(ch)?,
+        }
+    }
+    wr.write_char('"')
+}
+
+fn write_hex<W: Write>(wr: &mut W, buf: &[u8]) -> fmt::Result {
+    for (idx, byte) in buf.iter().enumerate() {
+        if idx > 0 {
+            wr.write_char(' ')?;
+        }
+        write!(wr, "{:02x}", byte)?;
+    }
+
+    Ok(())
+}
+
+fn write_elements<W, T, F>(wr: &mut W, open: &str, close: &str, elems: &[T], indent: Option<usize>,
+                           mut f: F) -> fmt::Result
+    where W: Write,
+          F: FnMut(&mut W, &T, Option<usize>) -> fmt::Result
+{
+    wr.write_str(open)?;
+    if elems.is_empty() {
+        return wr.write_str(close);
+    }
+
+    let inner = indent.map(|level| level + 1);
+    for (idx, elem) in elems.iter().enumerate() {
+        if idx > 0 {
+            wr.write_char(',')?;
+        }
+        match inner {
+            Some(level) => write_newline(wr, level)?,
+            None if idx > 0 => wr.write_char(' ')?,
+            None => {}
+        }
+        f(wr, elem, inner)?;
+    }
+    if let Some(level) = indent {
+        write_newline(wr, level)?;
+    }
+
+    wr.write_str(close)
+}
+
+fn write_newline<W: Write>(wr: &mut W, level: usize) -> fmt::Result {
+    wr.write_char('\n')?;
+    for _ in 0..level {
+        wr.write_str("  ")?;
+    }
+
+    Ok(())
+}
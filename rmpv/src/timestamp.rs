@@ -0,0 +1,60 @@
+//! The timestamp extension type defined by the MessagePack specification.
+//!
+//! A timestamp is an extension of type `-1` holding seconds since the Unix epoch together with
+//! nanoseconds, in one of three formats: 32 bits of unsigned seconds, 64 bits of 30-bit
+//! nanoseconds and 34-bit unsigned seconds, or 96 bits of 32-bit nanoseconds and 64-bit signed
+//! seconds. `Timestamp` reads all of them and writes the most compact one that fits, so values
+//! can be exchanged with any implementation supporting the extension.
+//!
+//! Timestamps are stored in a `Value` as its `Ext` variant and converted with `From` and
+//! `Value::as_timestamp`. The codec itself lives in `rmp::timestamp` and is re-exported here.
+//!
+//! # Examples
+//!
+//! ```
+//! use rmpv::Value;
+//! use rmpv::timestamp::Timestamp;
+//!
+//! let ts = Timestamp::new(1500000000, 0).unwrap();
+//!
+//! let val = Value::from(ts);
+//! assert_eq!(Value::Ext(-1, vec![0x59, 0x68, 0x2f, 0x00]), val);
+//! assert_eq!(Some(ts), val.as_timestamp());
+//! ```
+
+pub use rmp::timestamp::{Timestamp, EXT_TYPE};
+
+use {Value, ValueRef};
+
+impl From<Timestamp> for Value {
+    fn from(ts: Timestamp) -> Value {
+        Value::Ext(EXT_TYPE, ts.to_ext_data())
+    }
+}
+
+impl Value {
+    /// Returns true if the `Value` is a timestamp extension holding valid data.
+    pub fn is_timestamp(&self) -> bool {
+        self.as_timestamp().is_some()
+    }
+
+    /// If the `Value` is a timestamp extension, returns the timestamp it holds.
+    ///
+    /// Returns `None` for other values, including extensions of type `-1` with malformed data.
+    pub fn as_timestamp(&self) -> Option<Timestamp> {
+        match *self {
+            Value::Ext(EXT_TYPE, ref data) => Timestamp::from_ext_data(data),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> ValueRef<'a> {
+    /// If the `ValueRef` is a timestamp extension, returns the timestamp it holds.
+    pub fn as_timestamp(&self) -> Option<Timestamp> {
+        match *self {
+            ValueRef::Ext(EXT_TYPE, data) => Timestamp::from_ext_data(data),
+            _ => None,
+        }
+    }
+}
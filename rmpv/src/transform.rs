@@ -0,0 +1,227 @@
+//! Combinators rebuilding or mutating whole value trees.
+//!
+//! Data-cleaning passes over decoded documents, like normalizing keys, dropping nulls or
+//! rewriting a single field, usually need the same recursive walk over arrays and maps. The
+//! methods added to `Value` here do that walk, leaving only the interesting part to a closure.
+//!
+//! # Examples
+//!
+//! ```
+//! use rmpv::Value;
+//!
+//! let val: Value = r#"{"Name": "John", "Email": null, "Tags": ["a", null]}"#.parse().unwrap();
+//!
+//! let mut val = val.filter_map_keys(|key| match key.as_str() {
+//!     Some(key) => Some(Value::from(key.to_lowercase())),
+//!     None => None,
+//! });
+//! val.retain(|val| !val.is_nil());
+//!
+//! let expected: Value = r#"{"name": "John", "tags": ["a"]}"#.parse().unwrap();
+//! assert_eq!(expected, val);
+//! ```
+
+use alloc::vec::Vec;
+use core::mem;
+
+use Value;
+use pointer::{self, Segment};
+
+impl Value {
+    /// Rebuilds the value with the function applied to every value that is not an array or a
+    /// map, at any depth.
+    ///
+    /// Arrays and maps keep their structure, and map keys are left as they are.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rmpv::Value;
+    ///
+    /// let val: Value = r#"{"a": 1, "b": [2, "x"]}"#.parse().unwrap();
+    /// let val = val.map_values(|val| match val.as_i64() {
+    ///     Some(n) => Value::from(n * 10),
+    ///     None => val,
+    /// });
+    ///
+    /// assert_eq!(r#"{"a": 10, "b": [20, "x"]}"#.parse::<Value>().unwrap(), val);
+    /// ```
+    pub fn map_values<F>(self, mut f: F) -> Value
+        where F: FnMut(Value) -> Value
+    {
+        map_leaves(self, &mut f)
+    }
+
+    /// Rebuilds the value with the function applied to the keys of every map, at any depth.
+    ///
+    /// Entries for which the function returns `None` are dropped together with their values.
+    /// Maps are rebuilt from the innermost ones outwards, so the function sees the keys of nested
+    /// maps before those of the maps containing them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rmpv::Value;
+    ///
+    /// let val: Value = r#"{"id": 1, "_rev": 2, "user": {"_id": 3, "name": "John"}}"#
+    ///     .parse()
+    ///     .unwrap();
+    /// let val = val.filter_map_keys(|key| match key.as_str() {
+    ///     Some(name) if name.starts_with('_') => None,
+    ///     _ => Some(key),
+    /// });
+    ///
+    /// assert_eq!(r#"{"id": 1, "user": {"name": "John"}}"#.parse::<Value>().unwrap(), val);
+    /// ```
+    pub fn filter_map_keys<F>(self, mut f: F) -> Value
+        where F: FnMut(Value) -> Option<Value>
+    {
+        map_keys(self, &mut f)
+    }
+
+    /// Removes array elements and map entries whose value doesn't satisfy the predicate, at any
+    /// depth.
+    ///
+    /// Containers are pruned before being tested themselves, so the predicate can drop arrays
+    /// and maps that were emptied by it. The value itself is never removed, even if it doesn't
+    /// satisfy the predicate.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rmpv::Value;
+    ///
+    /// let mut val: Value = r#"{"a": null, "b": {"c": null}, "d": [1, null]}"#.parse().unwrap();
+    /// val.retain(|val| match *val {
+    ///     Value::Nil => false,
+    ///     Value::Map(ref map) => !map.is_empty(),
+    ///     _ => true,
+    /// });
+    ///
+    /// assert_eq!(r#"{"d": [1]}"#.parse::<Value>().unwrap(), val);
+    /// ```
+    pub fn retain<F>(&mut self, mut f: F)
+        where F: FnMut(&Value) -> bool
+    {
+        retain(self, &mut f)
+    }
+
+    /// Replaces the values at the given pointer path with the result of the function, returning
+    /// how many of them were replaced.
+    ///
+    /// The path has the same syntax as the one of `redact::Redactor::path`: segments match map
+    /// keys and array indices by their textual representation, `*` matches any of them, and the
+    /// empty path addresses the whole value. Locations missing from the value are skipped, and
+    /// nothing is replaced if the path is neither empty nor starts with `/`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rmpv::Value;
+    ///
+    /// let mut val: Value = r#"{"users": [{"name": "John"}, {"name": "Jane"}]}"#.parse().unwrap();
+    /// let count = val.transform_at("/users/*/name", |name| {
+    ///     Value::from(name.as_str().unwrap_or("").to_uppercase())
+    /// });
+    ///
+    /// assert_eq!(2, count);
+    /// assert_eq!(r#"{"users": [{"name": "JOHN"}, {"name": "JANE"}]}"#.parse::<Value>().unwrap(),
+    ///            val);
+    /// ```
+    pub fn transform_at<F>(&mut self, pointer: &str, mut f: F) -> usize
+        where F: FnMut(Value) -> Value
+    {
+        match pointer::parse_checked(pointer) {
+            Some(path) => transform_at(self, &path, &mut f),
+            None => 0,
+        }
+    }
+}
+
+fn map_leaves<F>(val: Value, f: &mut F) -> Value
+    where F: FnMut(Value) -> Value
+{
+    match val {
+        Value::Array(vec) => {
+            Value::Array(vec.into_iter().map(|val| map_leaves(val, f)).collect())
+        }
+        Value::Map(map) => {
+            let map = map.into_iter()
+                .map(|(key, val)| (key, map_leaves(val, f)))
+                .collect();
+
+            Value::Map(map)
+        }
+        val => f(val),
+    }
+}
+
+fn map_keys<F>(val: Value, f: &mut F) -> Value
+    where F: FnMut(Value) -> Option<Value>
+{
+    match val {
+        Value::Array(vec) => Value::Array(vec.into_iter().map(|val| map_keys(val, f)).collect()),
+        Value::Map(map) => {
+            let mut vec = Vec::with_capacity(map.len());
+            for (key, val) in map {
+                let val = map_keys(val, f);
+                if let Some(key) = f(key) {
+                    vec.push((key, val));
+                }
+            }
+
+            Value::Map(vec)
+        }
+        val => val,
+    }
+}
+
+fn retain<F>(val: &mut Value, f: &mut F)
+    where F: FnMut(&Value) -> bool
+{
+    match *val {
+        Value::Array(ref mut vec) => {
+            for val in vec.iter_mut() {
+                retain(val, f);
+            }
+            vec.retain(|val| f(val));
+        }
+        Value::Map(ref mut map) => {
+            for entry in map.iter_mut() {
+                retain(&mut entry.1, f);
+            }
+            map.retain(|entry| f(&entry.1));
+        }
+        _ => {}
+    }
+}
+
+fn transform_at<F>(val: &mut Value, path: &[Segment], f: &mut F) -> usize
+    where F: FnMut(Value) -> Value
+{
+    let (segment, path) = match path.split_first() {
+        Some(split) => split,
+        None => {
+            let old = mem::replace(val, Value::Nil);
+            *val = f(old);
+            return 1;
+        }
+    };
+
+    match *val {
+        Value::Array(ref mut vec) => {
+            vec.iter_mut()
+                .enumerate()
+                .filter(|&(idx, _)| segment.matches_index(idx))
+                .map(|(_, val)| transform_at(val, path, f))
+                .sum()
+        }
+        Value::Map(ref mut map) => {
+            map.iter_mut()
+                .filter(|entry| segment.matches_key(&entry.0))
+                .map(|entry| transform_at(&mut entry.1, path, f))
+                .sum()
+        }
+        _ => 0,
+    }
+}
@@ -0,0 +1,99 @@
+extern crate rmpv;
+
+use std::mem;
+
+use rmpv::Value;
+use rmpv::compact::{CompactValue, SmallBytes, INLINE_CAP};
+use rmpv::decode::{read_compact_value, read_compact_value_with_config, read_value, Config,
+                   Error, Utf8Policy};
+use rmpv::encode::{write_compact_value, write_value};
+
+#[test]
+fn compact_value_is_smaller_than_value() {
+    assert_eq!(mem::size_of::<Vec<u8>>(), mem::size_of::<SmallBytes>());
+    assert!(mem::size_of::<CompactValue>() < mem::size_of::<Value>());
+}
+
+#[test]
+fn small_bytes_are_inline_up_to_capacity() {
+    let long = vec![7; INLINE_CAP + 1];
+
+    let short = SmallBytes::new(&long[..INLINE_CAP]);
+    assert!(short.is_inline());
+    assert_eq!(long[..INLINE_CAP], short[..]);
+
+    let heap = SmallBytes::from_vec(long.clone());
+    assert!(!heap.is_inline());
+    assert_eq!(long, heap.into_vec());
+
+    assert!(SmallBytes::from_vec(vec![1, 2]).is_inline());
+    assert_eq!(SmallBytes::new(&[]), SmallBytes::from_vec(Vec::new()));
+}
+
+#[test]
+fn compact_value_round_trips() {
+    let val = Value::Array(vec![
+        Value::Nil,
+        Value::from(-42),
+        Value::from(1.5),
+        Value::from("le message"),
+        Value::from("a string too long to fit inline"),
+        Value::Binary(vec![1, 2, 3]),
+        Value::Map(vec![(Value::from("key"), Value::Ext(5, vec![0; 30]))]),
+    ]);
+    let mut buf = Vec::new();
+    write_value(&mut buf, &val).unwrap();
+
+    let compact = read_compact_value(&mut &buf[..]).unwrap();
+    assert_eq!(CompactValue::from(val.clone()), compact);
+    if let CompactValue::Array(ref vec) = compact {
+        assert_eq!(Some("le message"), vec[3].as_str());
+        assert!(vec[3].as_bytes().unwrap().is_inline());
+        assert!(!vec[4].as_bytes().unwrap().is_inline());
+        assert_eq!(None, vec[5].as_str());
+    }
+
+    let mut out = Vec::new();
+    write_compact_value(&mut out, &compact).unwrap();
+    assert_eq!(buf, out);
+    assert_eq!(val, Value::from(compact));
+}
+
+#[test]
+fn compact_value_keeps_invalid_utf8() {
+    let buf = [0xa2, 0xc3, 0x28];
+    let config = Config::new().utf8(Utf8Policy::Preserve);
+
+    let compact = read_compact_value_with_config(&mut &buf[..], &config).unwrap();
+    assert_eq!(None, compact.as_str());
+    assert_eq!(&[0xc3, 0x28], &compact.as_bytes().unwrap()[..]);
+    assert_eq!(read_value(&mut &buf[..]).unwrap(), Value::from(compact.clone()));
+
+    // Like `write_value`, which writes invalid strings as binaries.
+    let mut out = Vec::new();
+    write_compact_value(&mut out, &compact).unwrap();
+    assert_eq!([0xc4, 0x02, 0xc3, 0x28], out[..]);
+}
+
+#[test]
+fn compact_value_applies_config() {
+    // ["ab", "\xc3("]
+    let buf = [0x92, 0xa2, 0x61, 0x62, 0xa2, 0xc3, 0x28];
+
+    let config = Config::new().utf8(Utf8Policy::Binary);
+    let val = read_compact_value_with_config(&mut &buf[..], &config).unwrap();
+    let expected = Value::Array(vec!["ab".into(), Value::Binary(vec![0xc3, 0x28])]);
+    assert_eq!(CompactValue::from(expected), val);
+
+    let config = Config::new().utf8(Utf8Policy::Error);
+    match read_compact_value_with_config(&mut &buf[..], &config) {
+        Err(Error::InvalidUtf8(..)) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    let config = Config::new().max_len(1);
+    match read_compact_value_with_config(&mut &buf[..], &config) {
+        Err(Error::LengthLimitExceeded(2)) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
@@ -0,0 +1,106 @@
+extern crate rmpv;
+
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
+
+use rmpv::Value;
+
+#[test]
+fn try_from_int() {
+    assert_eq!(Ok(255), u8::try_from(Value::from(255)));
+    assert_eq!(Ok(-128), i8::try_from(Value::from(-128)));
+    assert_eq!(Ok(u64::max_value()), u64::try_from(Value::from(u64::max_value())));
+    assert_eq!(Ok(i64::min_value()), i64::try_from(Value::from(i64::min_value())));
+    assert_eq!(Ok(42), i32::try_from(Value::from(42u64)));
+
+    let err = u8::try_from(Value::from(256)).unwrap_err();
+    assert_eq!(Value::from(256), *err.value());
+    assert_eq!("u8", err.expected());
+    assert_eq!("expected u8, found 256", err.to_string());
+
+    assert!(u32::try_from(Value::from(-1)).is_err());
+    assert!(i64::try_from(Value::from(u64::max_value())).is_err());
+    assert!(i64::try_from(Value::F64(1.0)).is_err());
+}
+
+#[test]
+fn try_from_float() {
+    assert_eq!(Ok(0.5), f32::try_from(Value::F32(0.5)));
+    assert_eq!(Ok(0.5), f64::try_from(Value::F32(0.5)));
+    assert_eq!(Ok(0.5), f64::try_from(Value::F64(0.5)));
+
+    assert!(f32::try_from(Value::F64(0.5)).is_err());
+    assert!(f64::try_from(Value::from(1)).is_err());
+}
+
+#[test]
+fn try_from_bool_and_string() {
+    assert_eq!(Ok(true), bool::try_from(Value::Boolean(true)));
+    assert!(bool::try_from(Value::from(1)).is_err());
+
+    assert_eq!(Ok("le message".to_string()), String::try_from(Value::from("le message")));
+    assert!(String::try_from(Value::Binary(b"le message".to_vec())).is_err());
+}
+
+#[test]
+fn try_from_vec() {
+    let val = Value::Array(vec![Value::from(1), Value::from(2)]);
+    assert_eq!(Ok(vec![1u8, 2]), Vec::try_from(val));
+
+    let val = Value::Array(vec![Value::Array(vec![Value::from("a")]), Value::Array(vec![])]);
+    assert_eq!(Ok(vec![vec!["a".to_string()], vec![]]), Vec::try_from(val));
+
+    // Binaries are arrays of bytes as well.
+    assert_eq!(Ok(vec![1u8, 2]), Vec::try_from(Value::Binary(vec![1, 2])));
+    assert_eq!(Ok(vec![1u16, 2]), Vec::try_from(Value::Binary(vec![1, 2])));
+
+    let err = Vec::<u8>::try_from(Value::Array(vec![Value::from(1), Value::Nil])).unwrap_err();
+    assert_eq!(Value::Nil, err.into_value());
+
+    let err = Vec::<u8>::try_from(Value::from("12")).unwrap_err();
+    assert_eq!("array", err.expected());
+}
+
+#[test]
+fn try_from_map() {
+    let val = Value::Map(vec![
+        (Value::from("b"), Value::from(2)),
+        (Value::from("a"), Value::from(1)),
+        (Value::from("b"), Value::from(3)),
+    ]);
+
+    let map = BTreeMap::<String, i32>::try_from(val.clone()).unwrap();
+    let entries: Vec<_> = map.into_iter().collect();
+    assert_eq!(vec![("a".to_string(), 1), ("b".to_string(), 3)], entries);
+
+    let map = HashMap::<String, u64>::try_from(val).unwrap();
+    assert_eq!(2, map.len());
+    assert_eq!(3, map["b"]);
+
+    let val = Value::Map(vec![(Value::from(1), Value::Boolean(true))]);
+    let map = HashMap::<u8, bool>::try_from(val).unwrap();
+    assert_eq!(Some(&true), map.get(&1));
+
+    let val = Value::Map(vec![(Value::from(1), Value::Boolean(true))]);
+    let err = BTreeMap::<String, bool>::try_from(val).unwrap_err();
+    assert_eq!(Value::from(1), *err.value());
+
+    assert!(BTreeMap::<String, bool>::try_from(Value::Array(vec![])).is_err());
+}
+
+#[test]
+fn from_map() {
+    let mut map = BTreeMap::new();
+    map.insert("b", vec![2u8]);
+    map.insert("a", vec![1u8]);
+
+    let expected = Value::Map(vec![
+        (Value::from("a"), Value::Binary(vec![1])),
+        (Value::from("b"), Value::Binary(vec![2])),
+    ]);
+    assert_eq!(expected, Value::from(map));
+
+    let mut map = HashMap::new();
+    map.insert(1, "one".to_string());
+    assert_eq!(Value::Map(vec![(Value::from(1), Value::from("one"))]), Value::from(map));
+}
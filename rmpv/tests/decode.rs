@@ -1,7 +1,10 @@
+extern crate rmp;
 extern crate rmpv;
 
-use rmpv::Value;
-use rmpv::decode::{read_value, Error};
+use rmp::decode::Bytes;
+use rmpv::{Integer, Utf8String, Value};
+use rmpv::decode::{read_value, read_value_with_builder, read_value_with_config, Config,
+                   DuplicateKeys, Error, Utf8Policy, ValueBuilder};
 
 #[test]
 fn from_null_decode_value() {
@@ -124,6 +127,43 @@ fn from_str8_invalid_utf8() {
     }
 }
 
+#[test]
+fn from_str8_invalid_utf8_as_binary() {
+    let buf: &[u8] = &[0x92, 0xd9, 0x02, 0xc3, 0x28, 0xa2, 0x6f, 0x6b];
+    let config = Config::new().utf8(Utf8Policy::Binary);
+
+    let expected = Value::Array(vec![Value::Binary(vec![0xc3, 0x28]), Value::from("ok")]);
+    assert_eq!(expected, read_value_with_config(&mut &buf[..], &config).unwrap());
+}
+
+#[test]
+fn from_str8_invalid_utf8_as_error() {
+    let buf: &[u8] = &[0x91, 0xd9, 0x02, 0xc3, 0x28];
+    let config = Config::new().utf8(Utf8Policy::Error);
+
+    match read_value_with_config(&mut &buf[..], &config) {
+        Err(Error::InvalidUtf8(err)) => assert_eq!(0, err.valid_up_to()),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn from_str8_invalid_utf8_lossy() {
+    let buf: &[u8] = &[0xa3, 0x61, 0xc3, 0x28];
+    let config = Config::new().utf8(Utf8Policy::Lossy);
+
+    assert_eq!(Value::from("a\u{fffd}("), read_value_with_config(&mut &buf[..], &config).unwrap());
+}
+
+#[test]
+fn from_raw_as_binary() {
+    let buf: &[u8] = &[0x92, 0xa2, 0x6f, 0x6b, 0xa1, 0xff];
+    let config = Config::new().str_as_binary(true).utf8(Utf8Policy::Error);
+
+    let expected = Value::Array(vec![Value::Binary(vec![0x6f, 0x6b]), Value::Binary(vec![0xff])]);
+    assert_eq!(expected, read_value_with_config(&mut &buf[..], &config).unwrap());
+}
+
 #[test]
 fn from_array_of_two_integers() {
     let buf: &[u8] = &[0x92, 0x04, 0x2a];
@@ -131,3 +171,294 @@ fn from_array_of_two_integers() {
     let vec = vec![Value::from(4), Value::from(42)];
     assert_eq!(Value::Array(vec), read_value(&mut &buf[..]).unwrap());
 }
+
+#[test]
+fn from_bytes_reader() {
+    let mut rd = Bytes::new(&[0x92, 0x04, 0xa1, 0x61, 0xc0]);
+
+    let vec = vec![Value::from(4), Value::from("a")];
+    assert_eq!(Value::Array(vec), read_value(&mut rd).unwrap());
+    assert_eq!(4, rd.position());
+}
+
+#[test]
+fn from_bytes_reader_truncated() {
+    let mut rd = Bytes::new(&[0x92, 0x04, 0xa3, 0x61]);
+
+    match read_value(&mut rd) {
+        Err(Error::InvalidDataRead(..)) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+/// A document model of its own, rendering values as text like `[1,{"a":nil}]`.
+struct Text;
+
+impl ValueBuilder for Text {
+    type Value = String;
+    type Array = Vec<String>;
+    type Map = Vec<String>;
+
+    fn nil(&mut self) -> Result<String, Error> {
+        Ok("nil".into())
+    }
+
+    fn boolean(&mut self, val: bool) -> Result<String, Error> {
+        Ok(val.to_string())
+    }
+
+    fn integer(&mut self, val: Integer) -> Result<String, Error> {
+        Ok(val.to_string())
+    }
+
+    fn f32(&mut self, val: f32) -> Result<String, Error> {
+        Ok(format!("{}f32", val))
+    }
+
+    fn f64(&mut self, val: f64) -> Result<String, Error> {
+        Ok(format!("{}f64", val))
+    }
+
+    fn string(&mut self, val: Utf8String) -> Result<String, Error> {
+        Ok(format!("{:?}", val.as_str().unwrap_or("<invalid>")))
+    }
+
+    fn binary(&mut self, val: Vec<u8>) -> Result<String, Error> {
+        Ok(format!("bin{:?}", val))
+    }
+
+    fn ext(&mut self, ty: i8, data: Vec<u8>) -> Result<String, Error> {
+        Ok(format!("ext({}){:?}", ty, data))
+    }
+
+    fn begin_array(&mut self, len: u32) -> Result<Vec<String>, Error> {
+        Ok(Vec::with_capacity(len as usize))
+    }
+
+    fn push_element(&mut self, array: &mut Vec<String>, val: String) -> Result<(), Error> {
+        array.push(val);
+        Ok(())
+    }
+
+    fn end_array(&mut self, array: Vec<String>) -> Result<String, Error> {
+        Ok(format!("[{}]", array.join(",")))
+    }
+
+    fn begin_map(&mut self, len: u32) -> Result<Vec<String>, Error> {
+        Ok(Vec::with_capacity(len as usize))
+    }
+
+    fn push_entry(&mut self, map: &mut Vec<String>, key: String, val: String) -> Result<(), Error> {
+        map.push(format!("{}:{}", key, val));
+        Ok(())
+    }
+
+    fn end_map(&mut self, map: Vec<String>) -> Result<String, Error> {
+        Ok(format!("{{{}}}", map.join(",")))
+    }
+}
+
+#[test]
+fn from_nested_value_into_custom_builder() {
+    // [1, -2, {"a": nil}, [true, 1.5], bin [1, 2], fixext1 (5, 3)]
+    let buf = [
+        0x96, 0x01, 0xfe, 0x81, 0xa1, 0x61, 0xc0, 0x92, 0xc3, 0xcb, 0x3f, 0xf8, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0xc4, 0x02, 0x01, 0x02, 0xd4, 0x05, 0x03
+    ];
+
+    let text = read_value_with_builder(&mut &buf[..], &Config::new(), &mut Text).unwrap();
+    assert_eq!("[1,-2,{\"a\":nil},[true,1.5f64],bin[1, 2],ext(5)[3]]", text);
+}
+
+#[test]
+fn from_invalid_utf8_into_custom_builder_with_config() {
+    let buf = [0xa2, 0xc3, 0x28];
+
+    let config = Config::new().utf8(Utf8Policy::Binary);
+    let text = read_value_with_builder(&mut &buf[..], &config, &mut Text).unwrap();
+    assert_eq!("bin[195, 40]", text);
+
+    let config = Config::new().utf8(Utf8Policy::Error);
+    match read_value_with_builder(&mut &buf[..], &config, &mut Text) {
+        Err(Error::InvalidUtf8(..)) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn from_truncated_array_into_custom_builder() {
+    let buf = [0x92, 0x01];
+
+    match read_value_with_builder(&mut &buf[..], &Config::new(), &mut Text) {
+        Err(Error::InvalidMarkerRead(..)) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn from_nested_arrays_with_depth_limit() {
+    // [[[1]]]
+    let buf = [0x91, 0x91, 0x91, 0x01];
+
+    let config = Config::new().max_depth(3);
+    assert_eq!(Value::Array(vec![Value::Array(vec![Value::Array(vec![Value::from(1)])])]),
+               read_value_with_config(&mut &buf[..], &config).unwrap());
+
+    let config = Config::new().max_depth(2);
+    match read_value_with_config(&mut &buf[..], &config) {
+        Err(Error::DepthLimitExceeded) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn from_deeply_nested_arrays_with_depth_limit_does_not_overflow() {
+    let buf = vec![0x91; 1 << 20];
+
+    let config = Config::new().max_depth(64);
+    match read_value_with_config(&mut &buf[..], &config) {
+        Err(Error::DepthLimitExceeded) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn from_sibling_arrays_with_depth_limit() {
+    // [[1], [2], {3: [4]}]
+    let buf = [0x93, 0x91, 0x01, 0x91, 0x02, 0x81, 0x03, 0x91, 0x04];
+
+    let config = Config::new().max_depth(3);
+    assert!(read_value_with_config(&mut &buf[..], &config).is_ok());
+}
+
+#[test]
+fn from_huge_lengths_with_len_limit() {
+    let config = Config::new().max_len(16);
+
+    // Each of these claims 2^32 - 1 elements or bytes in just a few bytes.
+    let bufs: [&[u8]; 4] = [
+        &[0xdd, 0xff, 0xff, 0xff, 0xff],
+        &[0xdf, 0xff, 0xff, 0xff, 0xff],
+        &[0xdb, 0xff, 0xff, 0xff, 0xff],
+        &[0xc6, 0xff, 0xff, 0xff, 0xff],
+    ];
+    for buf in &bufs {
+        match read_value_with_config(&mut &buf[..], &config) {
+            Err(Error::LengthLimitExceeded(0xffffffff)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    // fixext16
+    let mut buf = vec![0xd8, 0x01];
+    buf.extend_from_slice(&[0; 16]);
+    assert_eq!(Value::Ext(1, vec![0; 16]), read_value_with_config(&mut &buf[..], &config).unwrap());
+}
+
+#[test]
+fn from_many_strings_with_alloc_limit() {
+    // ["abcd", "efgh"], where every string payload takes 4 bytes.
+    let buf = [0x92, 0xa4, 0x61, 0x62, 0x63, 0x64, 0xa4, 0x65, 0x66, 0x67, 0x68];
+    let array = 2 * std::mem::size_of::<Value>();
+
+    let config = Config::new().max_alloc(array + 8);
+    assert_eq!(Value::Array(vec![Value::from("abcd"), Value::from("efgh")]),
+               read_value_with_config(&mut &buf[..], &config).unwrap());
+
+    let config = Config::new().max_alloc(array + 7);
+    match read_value_with_config(&mut &buf[..], &config) {
+        Err(Error::AllocLimitExceeded) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn from_value_with_default_config_is_unlimited() {
+    let config = Config::new();
+    assert_eq!(None, config.depth_limit());
+    assert_eq!(None, config.len_limit());
+    assert_eq!(None, config.alloc_limit());
+
+    let config = config.max_depth(1).max_len(2).max_alloc(3);
+    assert_eq!(Some(1), config.depth_limit());
+    assert_eq!(Some(2), config.len_limit());
+    assert_eq!(Some(3), config.alloc_limit());
+}
+
+#[test]
+fn from_map_with_duplicate_keys() {
+    // {1: "a", 2: "b", 1: "c"}, with the second 1 encoded as u16.
+    let buf = [0x83, 0x01, 0xa1, 0x61, 0x02, 0xa1, 0x62, 0xcd, 0x00, 0x01, 0xa1, 0x63];
+
+    let all = Value::Map(vec![
+        (Value::from(1), Value::from("a")),
+        (Value::from(2), Value::from("b")),
+        (Value::from(1), Value::from("c")),
+    ]);
+    assert_eq!(all, read_value(&mut &buf[..]).unwrap());
+
+    let config = Config::new().duplicate_keys(DuplicateKeys::FirstWins);
+    let first = Value::Map(vec![
+        (Value::from(1), Value::from("a")),
+        (Value::from(2), Value::from("b")),
+    ]);
+    assert_eq!(first, read_value_with_config(&mut &buf[..], &config).unwrap());
+
+    let config = Config::new().duplicate_keys(DuplicateKeys::LastWins);
+    let last = Value::Map(vec![
+        (Value::from(2), Value::from("b")),
+        (Value::from(1), Value::from("c")),
+    ]);
+    assert_eq!(last, read_value_with_config(&mut &buf[..], &config).unwrap());
+
+    let config = Config::new().duplicate_keys(DuplicateKeys::Reject);
+    match read_value_with_config(&mut &buf[..], &config) {
+        Err(Error::DuplicateKey) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn from_map_with_duplicate_keys_rejected_before_the_rest() {
+    // [{1: 1, 1: 2}, [...]], the array claiming 2^32 - 1 elements that never come.
+    let buf = [0x92, 0x82, 0x01, 0x01, 0x01, 0x02, 0xdd, 0xff, 0xff, 0xff, 0xff];
+
+    let config = Config::new().duplicate_keys(DuplicateKeys::Reject);
+    match read_value_with_config(&mut &buf[..], &config) {
+        Err(Error::DuplicateKey) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn from_map_with_sorted_keys() {
+    // {"b": {2: nil, 1: nil}, "a": 1, "b": 2}
+    let buf = [0x83, 0xa1, 0x62, 0x82, 0x02, 0xc0, 0x01, 0xc0, 0xa1, 0x61, 0x01, 0xa1, 0x62, 0x02];
+
+    let config = Config::new().sort_keys(true);
+    assert!(config.is_sort_keys());
+    let val = read_value_with_config(&mut &buf[..], &config).unwrap();
+    assert_eq!(r#"{"a": 1, "b": {1: nil, 2: nil}, "b": 2}"#, val.to_string());
+    assert_eq!(Some(&Value::from(1)), val.get_sorted("a"));
+
+    let config = config.duplicate_keys(DuplicateKeys::LastWins);
+    let val = read_value_with_config(&mut &buf[..], &config).unwrap();
+    assert_eq!(r#"{"a": 1, "b": 2}"#, val.to_string());
+}
+
+#[test]
+fn from_nested_map_with_duplicate_keys_rejected() {
+    // [{"a": 1, "a": 2}]
+    let buf = [0x91, 0x82, 0xa1, 0x61, 0x01, 0xa1, 0x61, 0x02];
+
+    let config = Config::new().duplicate_keys(DuplicateKeys::Reject);
+    match read_value_with_config(&mut &buf[..], &config) {
+        Err(Error::DuplicateKey) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    // Floats of different widths are different keys: {1.0f32: 1, 1.0f64: 2}.
+    let buf = [0x82, 0xca, 0x3f, 0x80, 0x00, 0x00, 0x01,
+               0xcb, 0x3f, 0xf0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02];
+    assert!(read_value_with_config(&mut &buf[..], &config).is_ok());
+}
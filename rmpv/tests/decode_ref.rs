@@ -1,7 +1,8 @@
 extern crate rmpv;
 
 use rmpv::ValueRef;
-use rmpv::decode::{read_value_ref, Error};
+use rmpv::decode::{read_value_ref, read_value_ref_with_config, Config, DuplicateKeys, Error,
+                   Utf8Policy};
 
 #[test]
 fn from_nil() {
@@ -301,6 +302,35 @@ fn from_string_invalid_utf8() {
     }
 }
 
+#[test]
+fn from_string_invalid_utf8_as_binary() {
+    let buf = [0xd9, 0x02, 0xc3, 0x28];
+    let config = Config::new().utf8(Utf8Policy::Binary);
+
+    let mut rd = &buf[..];
+    assert_eq!(ValueRef::Binary(&[0xc3, 0x28]), read_value_ref_with_config(&mut rd, &config).unwrap());
+    assert_eq!(0, rd.len());
+}
+
+#[test]
+fn from_string_as_binary() {
+    let buf = [0xa2, 0x6f, 0x6b];
+    let config = Config::new().str_as_binary(true);
+
+    assert_eq!(ValueRef::Binary(&[0x6f, 0x6b]), read_value_ref_with_config(&mut &buf[..], &config).unwrap());
+}
+
+#[test]
+fn from_string_invalid_utf8_as_error() {
+    let buf = [0xd9, 0x02, 0xc3, 0x28];
+    let config = Config::new().utf8(Utf8Policy::Error);
+
+    match read_value_ref_with_config(&mut &buf[..], &config) {
+        Err(Error::InvalidUtf8(..)) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
 #[test]
 fn from_bin8() {
     let buf = [0xc4, 0x05, 0x00, 0x01, 0x02, 0x03, 0x04];
@@ -688,3 +718,65 @@ fn into_owned() {
 
     assert_eq!(expected, val.to_owned());
 }
+
+#[test]
+fn from_nested_arrays_with_depth_limit() {
+    // [[[1]]]
+    let buf = [0x91, 0x91, 0x91, 0x01];
+
+    let config = Config::new().max_depth(3);
+    assert!(read_value_ref_with_config(&mut &buf[..], &config).is_ok());
+
+    let config = Config::new().max_depth(2);
+    match read_value_ref_with_config(&mut &buf[..], &config) {
+        Err(Error::DepthLimitExceeded) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn from_huge_array_with_len_limit() {
+    let buf = [0xdd, 0xff, 0xff, 0xff, 0xff];
+
+    let config = Config::new().max_len(1024);
+    match read_value_ref_with_config(&mut &buf[..], &config) {
+        Err(Error::LengthLimitExceeded(0xffffffff)) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn from_borrowed_strings_with_alloc_limit() {
+    // ["abcd", "efgh"], whose strings are borrowed rather than allocated.
+    let buf = [0x92, 0xa4, 0x61, 0x62, 0x63, 0x64, 0xa4, 0x65, 0x66, 0x67, 0x68];
+    let array = 2 * std::mem::size_of::<ValueRef>();
+
+    let config = Config::new().max_alloc(array);
+    assert_eq!(ValueRef::Array(vec![ValueRef::from("abcd"), ValueRef::from("efgh")]),
+               read_value_ref_with_config(&mut &buf[..], &config).unwrap());
+
+    let config = Config::new().max_alloc(array - 1);
+    match read_value_ref_with_config(&mut &buf[..], &config) {
+        Err(Error::AllocLimitExceeded) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn from_map_with_duplicate_keys() {
+    // {"a": 1, "b": 2, "a": 3}
+    let buf = [0x83, 0xa1, 0x61, 0x01, 0xa1, 0x62, 0x02, 0xa1, 0x61, 0x03];
+
+    let config = Config::new().duplicate_keys(DuplicateKeys::LastWins);
+    assert_eq!(ValueRef::Map(vec![
+                   (ValueRef::from("b"), ValueRef::from(2)),
+                   (ValueRef::from("a"), ValueRef::from(3)),
+               ]),
+               read_value_ref_with_config(&mut &buf[..], &config).unwrap());
+
+    let config = Config::new().duplicate_keys(DuplicateKeys::Reject);
+    match read_value_ref_with_config(&mut &buf[..], &config) {
+        Err(Error::DuplicateKey) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
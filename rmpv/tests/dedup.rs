@@ -0,0 +1,73 @@
+extern crate rmpv;
+
+use rmpv::Value;
+use rmpv::dedup::{self, InvalidReference};
+
+fn record(name: &str) -> Value {
+    Value::Map(vec![
+        (Value::from("name"), Value::from(name)),
+        (Value::from("tags"), Value::Array(vec![Value::from("alpha"), Value::from("beta")])),
+    ])
+}
+
+#[test]
+fn compress_without_repeats() {
+    let val = Value::Array(vec![record("first"), Value::from("second"), Value::from(42)]);
+    assert_eq!(val, dedup::compress(&val, 1));
+}
+
+#[test]
+fn compress_repeated_subtrees() {
+    let val = Value::Array(vec![record("first"), record("first"), record("second")]);
+    let compressed = dedup::compress(&val, 1);
+
+    // The array is #0, the first record #1.
+    assert_eq!(Value::Ext(1, vec![0, 0, 0, 1]), compressed[1]);
+    // The tags of the third record repeat those of the first one, numbered after its name.
+    let tags = compressed[2].as_map().unwrap()[1].1.clone();
+    assert_eq!(Value::Ext(1, vec![0, 0, 0, 5]), tags);
+
+    assert_eq!(val, dedup::expand(&compressed, 1).unwrap());
+}
+
+#[test]
+fn compress_keeps_short_values() {
+    let val = Value::Array(vec![Value::from("ab"), Value::from("ab"), Value::Array(vec![])]);
+    assert_eq!(val, dedup::compress(&val, 1));
+}
+
+#[test]
+fn expand_nested_references() {
+    let inner = Value::Array(vec![record("first"), record("first")]);
+    let val = Value::Map(vec![
+        (Value::from("a"), inner.clone()),
+        (Value::from("b"), inner),
+    ]);
+
+    let compressed = dedup::compress(&val, -100);
+    assert_eq!(val, dedup::expand(&compressed, -100).unwrap());
+}
+
+#[test]
+fn expand_keeps_other_ext_types() {
+    let val = Value::Array(vec![Value::Ext(2, vec![1, 2, 3]), Value::Ext(2, vec![1, 2, 3])]);
+    assert_eq!(val, dedup::expand(&val, 1).unwrap());
+}
+
+#[test]
+fn expand_forward_reference_fails() {
+    let val = Value::Array(vec![Value::Ext(1, vec![0, 0, 0, 1]), record("first")]);
+    assert_eq!(Err(InvalidReference), dedup::expand(&val, 1));
+}
+
+#[test]
+fn expand_reference_to_ancestor_fails() {
+    let val = Value::Array(vec![Value::Ext(1, vec![0, 0, 0, 0])]);
+    assert_eq!(Err(InvalidReference), dedup::expand(&val, 1));
+}
+
+#[test]
+fn expand_malformed_reference_fails() {
+    let val = Value::Array(vec![Value::Ext(1, vec![0, 0])]);
+    assert_eq!(Err(InvalidReference), dedup::expand(&val, 1));
+}
@@ -0,0 +1,54 @@
+extern crate rmpv;
+
+use rmpv::Value;
+use rmpv::encode::{write_value, write_value_canonical, CanonicalError};
+
+fn canonical(val: &Value) -> Vec<u8> {
+    let mut buf = Vec::new();
+    write_value_canonical(&mut buf, val).unwrap();
+    buf
+}
+
+#[test]
+fn pack_canonical_sorts_nested_maps() {
+    let val: Value = r#"{"b": [{2: nil, 1: nil}], "a": true, 0: false}"#.parse().unwrap();
+    let sorted: Value = r#"{0: false, "a": true, "b": [{1: nil, 2: nil}]}"#.parse().unwrap();
+
+    let mut buf = Vec::new();
+    write_value(&mut buf, &sorted).unwrap();
+
+    assert_eq!(buf, canonical(&val));
+    assert_eq!(buf, canonical(&sorted));
+}
+
+#[test]
+fn pack_canonical_keeps_other_values() {
+    let val: Value = r#"[1, -1, 1.5, "s", bin(00), ext(1, ff), [], {}]"#.parse().unwrap();
+
+    let mut buf = Vec::new();
+    write_value(&mut buf, &val).unwrap();
+
+    assert_eq!(buf, canonical(&val));
+}
+
+#[test]
+fn pack_canonical_rejects_duplicate_keys() {
+    let val: Value = r#"[{"k": 1, "k": 2}]"#.parse().unwrap();
+
+    let mut buf = Vec::new();
+    match write_value_canonical(&mut buf, &val) {
+        Err(CanonicalError::DuplicateKey(key)) => assert_eq!(vec![0xa1, 0x6b], key),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn pack_canonical_when_buffer_is_too_small() {
+    let val: Value = r#"{"a": 1}"#.parse().unwrap();
+
+    let mut buf = [0; 3];
+    match write_value_canonical(&mut &mut buf[..], &val) {
+        Err(CanonicalError::Write(..)) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
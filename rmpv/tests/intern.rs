@@ -0,0 +1,67 @@
+extern crate rmpv;
+
+use std::rc::Rc;
+
+use rmpv::Value;
+use rmpv::decode::{Config, Utf8Policy};
+use rmpv::encode::write_value;
+use rmpv::intern::{self, InternedValue, Interner};
+
+fn records(count: usize) -> Value {
+    let record = |id: usize| Value::Map(vec![
+        (Value::from("id"), Value::from(id)),
+        (Value::from("name"), Value::from("le message")),
+    ]);
+    Value::Array((0..count).map(record).collect())
+}
+
+#[test]
+fn repeated_strings_share_storage() {
+    let val = records(100);
+    let mut buf = Vec::new();
+    write_value(&mut buf, &val).unwrap();
+
+    let mut interner = Interner::new();
+    let interned = intern::read_value_interned(&mut interner, &mut &buf[..]).unwrap();
+    assert_eq!(3, interner.len());
+
+    let first = interner.intern("id");
+    assert_eq!(101, Rc::strong_count(&first) - 1);
+
+    if let InternedValue::Array(ref vec) = interned {
+        if let InternedValue::Map(ref map) = vec[99] {
+            assert_eq!(Some("name"), map[1].0.as_str());
+            assert_eq!(Some(&Value::from(99)), map[0].1.as_plain());
+        }
+    }
+    assert_eq!(val, interned.into_value());
+}
+
+#[test]
+fn interner_is_reused_across_messages() {
+    let mut interner = Interner::new();
+    assert!(interner.is_empty());
+
+    let buf = [0xa2, 0x69, 0x64];
+    let first = intern::read_value_interned(&mut interner, &mut &buf[..]).unwrap();
+    let second = intern::read_value_interned(&mut interner, &mut &buf[..]).unwrap();
+    match (first, second) {
+        (InternedValue::String(a), InternedValue::String(b)) => assert!(Rc::ptr_eq(&a, &b)),
+        other => panic!("unexpected values: {:?}", other),
+    }
+
+    interner.clear();
+    assert_eq!(0, interner.len());
+}
+
+#[test]
+fn invalid_utf8_is_not_interned() {
+    let config = Config::new().utf8(Utf8Policy::Preserve);
+    let buf = [0xa2, 0xc3, 0x28];
+
+    let mut interner = Interner::new();
+    let val = intern::read_value_interned_with_config(&mut interner, &mut &buf[..], &config)
+        .unwrap();
+    assert!(val.as_plain().map_or(false, |val| val.as_str().is_none()));
+    assert!(interner.is_empty());
+}
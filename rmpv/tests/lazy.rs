@@ -0,0 +1,95 @@
+extern crate rmp;
+extern crate rmpv;
+
+use rmp::Marker;
+use rmpv::{Value, ValueRef};
+use rmpv::decode::Error;
+use rmpv::encode::write_value;
+use rmpv::lazy::LazyValue;
+
+fn document() -> Vec<u8> {
+    let val = Value::Map(vec![
+        (Value::from("id"), Value::from(42)),
+        (Value::from(1), Value::from("int key")),
+        (Value::from("users"), Value::Array(vec![
+            Value::Map(vec![(Value::from("name"), Value::from("alice"))]),
+            Value::Map(vec![(Value::from("name"), Value::from("bob"))]),
+        ])),
+        (Value::from("id"), Value::from(43)),
+    ]);
+
+    let mut buf = Vec::new();
+    write_value(&mut buf, &val).unwrap();
+    buf
+}
+
+#[test]
+fn get_finds_first_entry_with_key() {
+    let buf = document();
+    let doc = LazyValue::new(&buf);
+
+    assert_eq!(ValueRef::from(42), doc.get("id").unwrap().unwrap().decode().unwrap());
+    assert_eq!(None, doc.get("missing").unwrap());
+}
+
+#[test]
+fn nested_lookup_returns_exact_slices() {
+    let buf = document();
+    let doc = LazyValue::new(&buf);
+
+    let users = doc.get("users").unwrap().unwrap();
+    assert_eq!(Some(2), users.len().unwrap());
+
+    let name = users.index(1).unwrap().unwrap().get("name").unwrap().unwrap();
+    assert_eq!(&[0xa3, b'b', b'o', b'b'], name.as_slice());
+    assert_eq!(Value::from("bob"), name.to_value().unwrap());
+
+    assert_eq!(None, users.index(2).unwrap());
+}
+
+#[test]
+fn elements_yield_lazy_values() {
+    let buf = document();
+    let users = LazyValue::new(&buf).get("users").unwrap().unwrap();
+
+    let names: Vec<Value> = users.elements().unwrap()
+        .map(|user| user.unwrap().get("name").unwrap().unwrap().to_value().unwrap())
+        .collect();
+    assert_eq!(vec![Value::from("alice"), Value::from("bob")], names);
+}
+
+#[test]
+fn scalars_have_no_children() {
+    let buf = [0x2a];
+    let val = LazyValue::new(&buf);
+
+    assert_eq!(None, val.get("id").unwrap());
+    assert_eq!(None, val.index(0).unwrap());
+    assert_eq!(None, val.len().unwrap());
+    assert!(val.elements().is_err());
+}
+
+#[test]
+fn siblings_after_found_entry_are_not_read() {
+    // {"a": 1, "b": <truncated string>}
+    let buf = [0x82, 0xa1, 0x61, 0x01, 0xa1, 0x62, 0xa5, 0x68];
+    let doc = LazyValue::new(&buf);
+
+    assert_eq!(ValueRef::from(1), doc.get("a").unwrap().unwrap().decode().unwrap());
+    assert!(doc.get("c").is_err());
+    assert!(doc.decode().is_err());
+}
+
+#[test]
+fn empty_input_is_an_error() {
+    assert!(LazyValue::new(&[]).get("a").is_err());
+    assert!(LazyValue::new(&[]).decode().is_err());
+}
+
+#[test]
+fn elements_of_non_array_fail() {
+    match LazyValue::new(&[0x80]).elements() {
+        Err(Error::TypeMismatch(Marker::FixMap(0))) => {}
+        other => panic!("unexpected result: {:?}", other.map(|_| ())),
+    }
+}
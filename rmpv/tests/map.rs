@@ -0,0 +1,195 @@
+extern crate rmpv;
+
+use std::cmp::Ordering;
+
+use rmpv::Value;
+use rmpv::map::{sort_entries, Entry, Key};
+
+fn map() -> Value {
+    Value::Map(vec![
+        (Value::from("name"), Value::from("alice")),
+        (Value::from(1u8), Value::from("one")),
+        (Value::Boolean(true), Value::from("yes")),
+        (Value::from("name"), Value::from("bob")),
+    ])
+}
+
+#[test]
+fn get_by_str_int_and_value() {
+    let val = map();
+
+    assert_eq!(Some(&Value::from("alice")), val.get("name"));
+    assert_eq!(Some(&Value::from("alice")), val.get(&"name".to_string()));
+    assert_eq!(Some(&Value::from("one")), val.get(1i64));
+    assert_eq!(Some(&Value::from("one")), val.get(1usize));
+    assert_eq!(Some(&Value::from("yes")), val.get(&Value::Boolean(true)));
+
+    assert_eq!(None, val.get("missing"));
+    assert_eq!(None, val.get(-1));
+    assert_eq!(None, Value::Array(vec![Value::from("name")]).get("name"));
+}
+
+#[test]
+fn get_mut() {
+    let mut val = map();
+
+    *val.get_mut(1).unwrap() = Value::Nil;
+    assert_eq!(Some(&Value::Nil), val.get(1));
+    assert_eq!(None, Value::Nil.get_mut("name"));
+}
+
+#[test]
+fn remove_first_match() {
+    let mut val = map();
+
+    assert_eq!(Some(Value::from("alice")), val.remove("name"));
+    assert_eq!(Some(Value::from("bob")), val.remove("name"));
+    assert_eq!(None, val.remove("name"));
+    assert_eq!(Some(Value::from("one")), val.remove(1u32));
+
+    assert_eq!(Value::Map(vec![(Value::Boolean(true), Value::from("yes"))]), val);
+}
+
+#[test]
+fn insert_replaces_or_appends() {
+    let mut val = map();
+
+    assert_eq!(Some(Value::from("alice")), val.insert("name", "carol"));
+    assert_eq!(None, val.insert(2, Value::Nil));
+
+    let expected = Value::Map(vec![
+        (Value::from("name"), Value::from("carol")),
+        (Value::from(1u8), Value::from("one")),
+        (Value::Boolean(true), Value::from("yes")),
+        (Value::from("name"), Value::from("bob")),
+        (Value::from(2), Value::Nil),
+    ]);
+    assert_eq!(expected, val);
+}
+
+#[test]
+fn insert_into_nil() {
+    let mut val = Value::Nil;
+    val.insert("key", 42);
+
+    assert_eq!(Value::Map(vec![(Value::from("key"), Value::from(42))]), val);
+}
+
+#[test]
+#[should_panic]
+fn insert_into_array() {
+    Value::Array(vec![]).insert("key", 42);
+}
+
+#[test]
+fn entry() {
+    let mut val = Value::Map(vec![]);
+
+    for _ in 0..3 {
+        val.entry("count")
+            .and_modify(|n| *n = Value::from(n.as_u64().unwrap() + 1))
+            .or_insert(Value::from(1));
+    }
+    assert_eq!(Some(&Value::from(3)), val.get("count"));
+
+    match val.entry("count") {
+        Entry::Occupied(entry) => {
+            assert_eq!(Value::from("count"), *entry.key());
+            assert_eq!(Value::from(3), entry.remove());
+        }
+        Entry::Vacant(..) => panic!("expected an occupied entry"),
+    }
+
+    match val.entry(7) {
+        Entry::Vacant(entry) => {
+            assert_eq!(Value::from(7), *entry.key());
+            *entry.insert(Value::Nil) = Value::from("seven");
+        }
+        Entry::Occupied(..) => panic!("expected a vacant entry"),
+    }
+
+    val.entry("list").or_insert_with(|| Value::Array(vec![]));
+    assert_eq!(r#"{7: "seven", "list": []}"#, val.to_string());
+}
+
+#[test]
+fn sort_keys_by_type_then_value() {
+    let mut val = Value::Map(vec![
+        (Value::from("b"), Value::Nil),
+        (Value::Binary(vec![0]), Value::Nil),
+        (Value::from(u64::max_value()), Value::Nil),
+        (Value::F64(-1.0), Value::Nil),
+        (Value::from(-2), Value::Nil),
+        (Value::Array(vec![Value::from(1), Value::from(2)]), Value::Nil),
+        (Value::from("a"), Value::Nil),
+        (Value::Array(vec![Value::from(1)]), Value::Nil),
+        (Value::Boolean(false), Value::Nil),
+        (Value::Ext(1, vec![]), Value::Nil),
+        (Value::from(3), Value::Nil),
+        (Value::Nil, Value::Nil),
+        (Value::F32(0.5), Value::Nil),
+    ]);
+    val.sort_keys();
+
+    let keys: Vec<String> = val.as_map().unwrap().iter().map(|e| e.0.to_string()).collect();
+    let expected = ["nil", "false", "-2", "3", "18446744073709551615", "0.5", "-1", "\"a\"",
+                    "\"b\"", "[0]", "[1]", "[1, 2]", "[1, []]"];
+    assert_eq!(expected[..], keys[..]);
+}
+
+#[test]
+fn sort_keys_nested() {
+    let inner = Value::Map(vec![(Value::from(2), Value::Nil), (Value::from(1), Value::Nil)]);
+    let mut val = Value::Array(vec![Value::Map(vec![
+        (Value::from(2), inner),
+        (Value::from(1), Value::Nil),
+    ])]);
+    val.sort_keys();
+
+    assert_eq!("[{1: nil, 2: {1: nil, 2: nil}}]", val.to_string());
+
+    // Only the given entries are sorted here.
+    let mut entries = vec![(Value::from(2), Value::Nil), (Value::from(1), Value::Array(vec![]))];
+    sort_entries(&mut entries);
+    assert_eq!(Value::from(1), entries[0].0);
+}
+
+#[test]
+fn get_sorted() {
+    let entries = (0..100).rev().map(|n| (Value::from(n * 2), Value::from(n))).collect();
+    let mut val = Value::Map(entries);
+    val.insert("name", "alice");
+    val.insert(Value::Boolean(true), "yes");
+    val.sort_keys();
+
+    for n in 0..100 {
+        assert_eq!(Some(&Value::from(n)), val.get_sorted(n * 2));
+        assert_eq!(None, val.get_sorted(n * 2 + 1));
+    }
+    assert_eq!(Some(&Value::from("alice")), val.get_sorted("name"));
+    assert_eq!(Some(&Value::from("yes")), val.get_sorted(&Value::Boolean(true)));
+    assert_eq!(None, val.get_sorted("missing"));
+    assert_eq!(None, val.get_sorted(-1));
+    assert_eq!(None, Value::Nil.get_sorted("name"));
+}
+
+#[test]
+fn get_sorted_first_duplicate() {
+    let mut val = Value::Map(vec![
+        (Value::from("a"), Value::from(1)),
+        (Value::from("b"), Value::from(2)),
+        (Value::from("a"), Value::from(3)),
+    ]);
+    val.sort_keys();
+
+    assert_eq!(Some(&Value::from(1)), val.get_sorted("a"));
+}
+
+#[test]
+fn compare_keys() {
+    assert_eq!(Ordering::Less, "a".compare(&Value::from("b")));
+    assert_eq!(Ordering::Greater, "a".compare(&Value::from(1)));
+    assert_eq!(Ordering::Less, "a".compare(&Value::Binary(vec![])));
+    assert_eq!(Ordering::Equal, 1u8.compare(&Value::from(1i64)));
+    assert_eq!(Ordering::Less, (-1).compare(&Value::from(u64::max_value())));
+}
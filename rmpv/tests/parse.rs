@@ -0,0 +1,106 @@
+extern crate rmpv;
+
+use rmpv::Value;
+use rmpv::parse::ParseError;
+
+fn parse(s: &str) -> Result<Value, ParseError> {
+    s.parse()
+}
+
+#[test]
+fn parse_scalars() {
+    assert_eq!(Value::Nil, parse("null").unwrap());
+    assert_eq!(Value::Nil, parse(" nil ").unwrap());
+    assert_eq!(Value::Boolean(true), parse("true").unwrap());
+    assert_eq!(Value::Boolean(false), parse("false").unwrap());
+    assert_eq!(Value::from(0), parse("0").unwrap());
+    assert_eq!(Value::from(u64::MAX), parse("18446744073709551615").unwrap());
+    assert_eq!(Value::from(i64::MIN), parse("-9223372036854775808").unwrap());
+    assert_eq!(Value::F64(-1.5), parse("-1.5").unwrap());
+    assert_eq!(Value::F64(1e3), parse("1e3").unwrap());
+}
+
+#[test]
+fn parse_strings() {
+    assert_eq!(Value::from(""), parse(r#""""#).unwrap());
+    assert_eq!(Value::from("le message"), parse(r#""le message""#).unwrap());
+    assert_eq!(Value::from("привет"), parse(r#""привет""#).unwrap());
+    assert_eq!(Value::from("\"\\/\u{8}\u{c}\n\r\t"), parse(r#""\"\\\/\b\f\n\r\t""#).unwrap());
+    assert_eq!(Value::from("é😀"), parse(r#""\u00e9\ud83d\ude00""#).unwrap());
+}
+
+#[test]
+fn parse_containers() {
+    let val = parse(r#"{"a": [1, true, null], "b": {}, 42: [], [1]: {"c": -1}}"#).unwrap();
+
+    assert_eq!(Value::Map(vec![
+        (Value::from("a"), Value::Array(vec![Value::from(1), Value::from(true), Value::Nil])),
+        (Value::from("b"), Value::Map(vec![])),
+        (Value::from(42), Value::Array(vec![])),
+        (Value::Array(vec![Value::from(1)]), Value::Map(vec![(Value::from("c"), Value::from(-1))])),
+    ]), val);
+}
+
+#[test]
+fn parse_binary_and_ext() {
+    assert_eq!(Value::Binary(vec![]), parse("bin()").unwrap());
+    assert_eq!(Value::Binary(vec![0x01, 0x02, 0xff]), parse("bin(01 02 FF)").unwrap());
+    assert_eq!(Value::Binary(vec![0xde, 0xad]), parse("bin( dead )").unwrap());
+    assert_eq!(Value::Ext(-1, vec![0, 0, 0, 1]), parse("ext(-1, 00000001)").unwrap());
+    assert_eq!(Value::Ext(42, vec![]), parse("ext( 42 ,)").unwrap());
+}
+
+#[test]
+fn parse_display_output() {
+    let val = Value::Array(vec![
+        Value::Nil,
+        Value::from("le message"),
+        Value::Map(vec![(Value::from(1), Value::from(false))]),
+    ]);
+
+    assert_eq!(val, parse(&format!("{}", val)).unwrap());
+}
+
+#[test]
+fn parse_errors() {
+    let cases = [
+        ("", 0),
+        ("[1, 2", 5),
+        ("[1 2]", 3),
+        ("{\"a\" 1}", 5),
+        ("\"abc", 4),
+        ("\"\\x\"", 2),
+        ("\"\\ud83d\"", 7),
+        ("18446744073709551616", 0),
+        ("truth", 0),
+        ("bin(0)", 5),
+        ("ext(128, 00)", 0),
+        ("1 2", 2),
+        ("@", 0),
+    ];
+
+    for &(s, offset) in &cases {
+        match parse(s) {
+            Err(err) => assert_eq!(offset, err.offset(), "{:?}: {}", s, err),
+            Ok(val) => panic!("{:?} parsed as {}", s, val),
+        }
+    }
+}
+
+#[test]
+fn parse_nesting_limit() {
+    let s = "[".repeat(2000) + &"]".repeat(2000);
+    assert!(parse(&s).is_err());
+
+    let s = "[".repeat(100) + &"]".repeat(100);
+    assert!(parse(&s).is_ok());
+}
+
+#[test]
+fn parse_annotated_floats() {
+    assert_eq!(Value::F32(1.5), parse("f32(1.5)").unwrap());
+    assert_eq!(Value::F32(2.0), parse("f32( 2 )").unwrap());
+    assert_eq!(Value::F64(1.0), parse("f64(1)").unwrap());
+    assert_eq!(Value::F64(-1.0 / 0.0), parse("f64(-inf)").unwrap());
+    assert!(parse("[f32(x)]").is_err());
+}
@@ -0,0 +1,110 @@
+extern crate rmpv;
+
+use rmpv::Value;
+use rmpv::redact::{Mask, Redactor};
+
+fn credentials(password: &str) -> Value {
+    Value::Map(vec![
+        (Value::from("login"), Value::from("admin")),
+        (Value::from("password"), Value::from(password)),
+    ])
+}
+
+#[test]
+fn redact_nothing() {
+    let val = credentials("hunter2");
+    assert_eq!(val, Redactor::new().redact(&val));
+}
+
+#[test]
+fn redact_path() {
+    let val = Value::Array(vec![credentials("hunter2"), credentials("qwerty")]);
+    let redactor = Redactor::new().path("/1/password");
+
+    let expected = Value::Array(vec![credentials("hunter2"), credentials("***")]);
+    assert_eq!(expected, redactor.redact(&val));
+}
+
+#[test]
+fn redact_path_wildcard() {
+    let val = Value::Array(vec![credentials("hunter2"), credentials("qwerty")]);
+    let redactor = Redactor::new().path("/*/password");
+
+    let expected = Value::Array(vec![credentials("***"), credentials("***")]);
+    assert_eq!(expected, redactor.redact(&val));
+}
+
+#[test]
+fn redact_path_escaped_and_integer_keys() {
+    let val = Value::Map(vec![
+        (Value::from("a/b"), Value::from(1)),
+        (Value::from(42), Value::from(2)),
+    ]);
+    let redactor = Redactor::new().path("/a~1b").path("/42");
+
+    let expected = Value::Map(vec![
+        (Value::from("a/b"), Value::from("***")),
+        (Value::from(42), Value::from("***")),
+    ]);
+    assert_eq!(expected, redactor.redact(&val));
+}
+
+#[test]
+fn redact_path_root() {
+    let val = credentials("hunter2");
+    assert_eq!(Value::from("***"), Redactor::new().path("").redact(&val));
+}
+
+#[test]
+fn redact_path_missing() {
+    let val = credentials("hunter2");
+    assert_eq!(val, Redactor::new().path("/password/nested").redact(&val));
+}
+
+#[test]
+fn redact_key_pattern_at_any_depth() {
+    let val = Value::Map(vec![
+        (Value::from("access_token"), Value::from("abc")),
+        (Value::from("nested"), Value::Array(vec![Value::Map(vec![
+            (Value::from("token"), Value::from("def")),
+            (Value::from("tokens_left"), Value::from(3)),
+        ])])),
+    ]);
+    let redactor = Redactor::new().key("*token");
+
+    let expected = Value::Map(vec![
+        (Value::from("access_token"), Value::from("***")),
+        (Value::from("nested"), Value::Array(vec![Value::Map(vec![
+            (Value::from("token"), Value::from("***")),
+            (Value::from("tokens_left"), Value::from(3)),
+        ])])),
+    ]);
+    assert_eq!(expected, redactor.redact(&val));
+}
+
+#[test]
+fn redact_key_masks_whole_subtree() {
+    let val = Value::Map(vec![(Value::from("secret"), credentials("hunter2"))]);
+    let redactor = Redactor::new().key("secret").mask(Mask::Value(Value::Nil));
+
+    assert_eq!(Value::Map(vec![(Value::from("secret"), Value::Nil)]), redactor.redact(&val));
+}
+
+#[test]
+fn redact_with_hash() {
+    let val = Value::Array(vec![
+        credentials("hunter2"),
+        credentials("hunter2"),
+        credentials("qwerty"),
+    ]);
+    let redactor = Redactor::new().path("/*/password").mask(Mask::Hash);
+
+    let res = redactor.redact(&val);
+    let first = res[0].as_map().unwrap()[1].1.clone();
+    let second = res[1].as_map().unwrap()[1].1.clone();
+    let third = res[2].as_map().unwrap()[1].1.clone();
+
+    assert_eq!(16, first.as_str().unwrap().len());
+    assert_eq!(first, second);
+    assert!(first != third);
+}
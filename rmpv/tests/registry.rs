@@ -0,0 +1,84 @@
+extern crate rmpv;
+
+use rmpv::Value;
+use rmpv::decode::Config;
+use rmpv::registry::{ExtRegistry, TypedValue};
+
+#[derive(Debug, PartialEq)]
+struct Decimal {
+    mantissa: i32,
+    scale: u8,
+}
+
+fn encode_decimal(val: &Decimal) -> Vec<u8> {
+    let mut buf = val.mantissa.to_be_bytes().to_vec();
+    buf.push(val.scale);
+    buf
+}
+
+fn decode_decimal(data: &[u8]) -> Option<Decimal> {
+    if data.len() != 5 {
+        return None;
+    }
+
+    Some(Decimal {
+        mantissa: i32::from_be_bytes([data[0], data[1], data[2], data[3]]),
+        scale: data[4],
+    })
+}
+
+fn registry() -> ExtRegistry {
+    ExtRegistry::new().register(7, encode_decimal, decode_decimal)
+}
+
+#[test]
+fn read_value_decodes_registered_extensions() {
+    // {"price": ext(7, 1234 / 10^2), "raw": ext(8, [1])}
+    let buf = [0x82, 0xa5, 0x70, 0x72, 0x69, 0x63, 0x65, 0xc7, 0x05, 0x07,
+               0x00, 0x00, 0x04, 0xd2, 0x02, 0xa3, 0x72, 0x61, 0x77, 0xd4, 0x08, 0x01];
+
+    let val = registry().read_value(&mut &buf[..], &Config::new()).unwrap();
+    let map = match val {
+        TypedValue::Map(map) => map,
+        other => panic!("unexpected value: {:?}", other),
+    };
+
+    assert_eq!(Some(&Value::from("price")), map[0].0.as_plain());
+    assert_eq!(Some(&Decimal { mantissa: 1234, scale: 2 }), map[0].1.downcast_ref());
+    assert_eq!(Some(&Value::Ext(8, vec![1])), map[1].1.as_plain());
+}
+
+#[test]
+fn malformed_extension_is_kept_raw() {
+    let registry = registry();
+
+    let val = Value::Ext(7, vec![1, 2]);
+    assert!(registry.decode::<Decimal>(&val).is_none());
+    assert_eq!(Some(&val), registry.to_typed(val.clone()).as_plain());
+}
+
+#[test]
+fn decode_checks_registered_type() {
+    let registry = registry();
+    let val = Value::Ext(7, vec![0, 0, 0, 5, 1]);
+
+    assert_eq!(Some(Decimal { mantissa: 5, scale: 1 }), registry.decode(&val));
+    assert_eq!(None, registry.decode::<u32>(&val));
+    assert_eq!(None, registry.decode::<Decimal>(&Value::from(5)));
+}
+
+#[test]
+fn typed_value_round_trips_through_registry() {
+    let registry = registry();
+    let dec = Decimal { mantissa: -1, scale: 3 };
+
+    let val = Value::Array(vec![Value::Nil, registry.encode(&dec).unwrap()]);
+    assert_eq!(Value::Ext(7, vec![0xff, 0xff, 0xff, 0xff, 3]), val[1]);
+
+    let typed = registry.to_typed(val.clone());
+    assert_eq!(val, registry.to_value(typed));
+
+    assert!(registry.encode(&42u8).is_none());
+    assert!(registry.is_registered(7));
+    assert!(!registry.is_registered(8));
+}
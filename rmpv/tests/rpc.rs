@@ -0,0 +1,204 @@
+extern crate rmpv;
+
+use std::io::Cursor;
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+
+use rmpv::Value;
+use rmpv::rpc::{self, Client, Error, Handler, Message, Notification, Request, Response};
+
+fn write_all(msgs: &[Message]) -> Vec<u8> {
+    let mut buf = Vec::new();
+    for msg in msgs {
+        msg.write(&mut buf).unwrap();
+    }
+    buf
+}
+
+#[test]
+fn message_layout() {
+    let req = Message::Request(Request { id: 7, method: "ping".into(), params: vec![Value::Nil] });
+    assert_eq!("[0, 7, \"ping\", [nil]]", Value::from(req.clone()).to_string());
+
+    let ok = Message::Response(Response { id: 7, result: Ok(Value::from("pong")) });
+    assert_eq!("[1, 7, nil, \"pong\"]", Value::from(ok.clone()).to_string());
+
+    let err = Message::Response(Response { id: 7, result: Err(Value::from("no")) });
+    assert_eq!("[1, 7, \"no\", nil]", Value::from(err.clone()).to_string());
+
+    let not = Message::Notification(Notification { method: "tick".into(), params: vec![] });
+    assert_eq!("[2, \"tick\", []]", Value::from(not.clone()).to_string());
+
+    let buf = write_all(&[req.clone(), ok.clone(), err.clone(), not.clone()]);
+    let mut rd = &buf[..];
+    for msg in &[req, ok, err, not] {
+        assert_eq!(Some(msg), Message::read(&mut rd).unwrap().as_ref());
+    }
+    assert_eq!(None, Message::read(&mut rd).unwrap());
+}
+
+#[test]
+fn invalid_messages() {
+    let vals = [
+        Value::from(0),
+        Value::Array(vec![Value::from(0), Value::from(1), Value::from("m")]),
+        Value::Array(vec![Value::from(0), Value::from(-1), Value::from("m"), Value::Array(vec![])]),
+        Value::Array(vec![Value::from(1), Value::from(1u64 << 32), Value::Nil, Value::Nil]),
+        Value::Array(vec![Value::from(2), Value::from("m"), Value::Nil]),
+        Value::Array(vec![Value::from(3), Value::from("m"), Value::Array(vec![])]),
+    ];
+
+    for val in &vals {
+        match Message::from_value(val.clone()) {
+            Err(Error::InvalidMessage(ref v)) if v == val => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    // A message cut in the middle is an error, unlike the end of the stream.
+    let buf = write_all(&[Message::Notification(Notification {
+        method: "tick".into(),
+        params: vec![],
+    })]);
+    match Message::read(&mut &buf[..buf.len() - 1]) {
+        Err(Error::Decode(..)) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn huge_lengths_are_rejected() {
+    use rmpv::decode::{self, Config};
+
+    // An array claiming 2^32 - 1 elements.
+    let huge = [0xdd, 0xff, 0xff, 0xff, 0xff];
+    match Message::read(&mut &huge[..]) {
+        Err(Error::Decode(decode::Error::LengthLimitExceeded(0xffffffff))) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    let mut output = Vec::new();
+    match rpc::serve(&huge[..], &mut output, &mut Calc { notified: vec![] }) {
+        Err(Error::Decode(decode::Error::LengthLimitExceeded(0xffffffff))) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+    assert!(output.is_empty());
+
+    // Other limits are passed along with the config.
+    let input = write_all(&[Message::Notification(Notification {
+        method: "tick".into(),
+        params: vec![Value::from(1), Value::from(2)],
+    })]);
+    let config = Config::new().max_len(1);
+    let mut calc = Calc { notified: vec![] };
+    match rpc::serve_with_config(&input[..], Vec::new(), &mut calc, &config) {
+        Err(Error::Decode(decode::Error::LengthLimitExceeded(..))) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+    assert!(calc.notified.is_empty());
+
+    let mut client = Client::with_config(Cursor::new(input), Vec::new(), config);
+    match client.call("ping", vec![]) {
+        Err(Error::Decode(decode::Error::LengthLimitExceeded(..))) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+struct Calc {
+    notified: Vec<String>,
+}
+
+impl Handler for Calc {
+    fn request(&mut self, method: &str, params: Vec<Value>) -> Result<Value, Value> {
+        match method {
+            "add" => Ok(Value::from(params.iter().filter_map(Value::as_i64).sum::<i64>())),
+            "notified" => {
+                Ok(Value::Array(self.notified.iter().map(|m| Value::from(&m[..])).collect()))
+            }
+            _ => Err(Value::from(format!("no method {}", method))),
+        }
+    }
+
+    fn notification(&mut self, method: &str, _params: Vec<Value>) {
+        self.notified.push(method.into());
+    }
+}
+
+#[test]
+fn client_and_server_over_tcp() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let server = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut calc = Calc { notified: Vec::new() };
+        rpc::serve(stream.try_clone().unwrap(), stream, &mut calc).unwrap();
+        calc.notified
+    });
+
+    let stream = TcpStream::connect(addr).unwrap();
+    let mut client = Client::new(stream.try_clone().unwrap(), stream);
+
+    assert_eq!(Value::from(6), client.call("add", vec![Value::from(1), Value::from(5)]).unwrap());
+    match client.call("sub", vec![]) {
+        Err(Error::Remote(ref val)) => assert_eq!(Value::from("no method sub"), *val),
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    client.notify("started", vec![]).unwrap();
+    let notified = client.call("notified", vec![]).unwrap();
+    assert_eq!(Value::Array(vec![Value::from("started")]), notified);
+
+    drop(client);
+    assert_eq!(vec!["started".to_string()], server.join().unwrap());
+}
+
+#[test]
+fn client_receives_messages_before_response() {
+    // The peer sends a notification and a request of its own before answering the two calls.
+    let input = write_all(&[
+        Message::Notification(Notification { method: "tick".into(), params: vec![] }),
+        Message::Response(Response { id: 0, result: Ok(Value::Nil) }),
+        Message::Request(Request { id: 9, method: "add".into(), params: vec![Value::from(2)] }),
+        Message::Response(Response { id: 1, result: Ok(Value::Boolean(true)) }),
+    ]);
+
+    let mut client = Client::new(Cursor::new(input), Vec::new());
+    assert_eq!(Value::Nil, client.call("first", vec![]).unwrap());
+
+    let mut calc = Calc { notified: Vec::new() };
+    assert_eq!(Value::Boolean(true), client.call_with("second", vec![], &mut calc).unwrap());
+
+    assert_eq!(Some(Notification { method: "tick".into(), params: vec![] }),
+               client.next_notification());
+    assert_eq!(None, client.next_notification());
+
+    // Both calls were sent, followed by the answer to the peer request.
+    let (_, output) = client.into_inner();
+    let mut rd = &output[..];
+    let ids: Vec<_> = (0..3).map(|_| match Message::read(&mut rd).unwrap().unwrap() {
+        Message::Request(req) => req.id,
+        Message::Response(resp) => {
+            assert_eq!(Ok(Value::from(2)), resp.result);
+            resp.id
+        }
+        msg => panic!("unexpected message: {:?}", msg),
+    }).collect();
+    assert_eq!(vec![0, 1, 9], ids);
+}
+
+#[test]
+fn client_fails_on_closed_stream_and_unknown_responses() {
+    let mut client = Client::new(Cursor::new(Vec::new()), Vec::new());
+    match client.call("ping", vec![]) {
+        Err(Error::Closed) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    let input = write_all(&[Message::Response(Response { id: 5, result: Ok(Value::Nil) })]);
+    let mut client = Client::new(Cursor::new(input), Vec::new());
+    match client.call("ping", vec![]) {
+        Err(Error::UnexpectedResponse(5)) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
@@ -0,0 +1,91 @@
+extern crate rmpv;
+
+use rmpv::Value;
+use rmpv::decode::read_value;
+use rmpv::text::{from_str, to_string, to_string_pretty};
+
+fn assert_roundtrip(val: &Value) {
+    assert_eq!(*val, from_str(&to_string(val)).unwrap(), "{}", to_string(val));
+    assert_eq!(*val, from_str(&to_string_pretty(val)).unwrap(), "{}", to_string_pretty(val));
+}
+
+#[test]
+fn text_of_scalars() {
+    assert_eq!("nil", to_string(&Value::Nil));
+    assert_eq!("true", to_string(&Value::from(true)));
+    assert_eq!("18446744073709551615", to_string(&Value::from(u64::MAX)));
+    assert_eq!("-9223372036854775808", to_string(&Value::from(i64::MIN)));
+    assert_eq!("1.0", to_string(&Value::F64(1.0)));
+    assert_eq!("1e300", to_string(&Value::F64(1e300)));
+    assert_eq!("f32(0.1)", to_string(&Value::F32(0.1)));
+    assert_eq!("f64(-inf)", to_string(&Value::F64(-1.0 / 0.0)));
+    assert_eq!("f32(nan)", to_string(&Value::F32(0.0 / 0.0)));
+    assert_eq!(r#""a\"b\\c\n\u0001""#, to_string(&Value::from("a\"b\\c\n\u{1}")));
+    assert_eq!("bin()", to_string(&Value::Binary(vec![])));
+    assert_eq!("ext(-1, 00 01)", to_string(&Value::Ext(-1, vec![0, 1])));
+}
+
+#[test]
+fn text_of_invalid_utf8_string() {
+    let val = read_value(&mut &[0xa2, 0xc3, 0x28][..]).unwrap();
+    assert_eq!("str(c3 28)", to_string(&val));
+
+    let parsed = from_str("str(c3 28)").unwrap();
+    assert_eq!(val, parsed);
+    assert!(parsed.as_str().is_none());
+}
+
+#[test]
+fn text_of_containers() {
+    let val = Value::Array(vec![
+        Value::Map(vec![(Value::from(1), Value::Array(vec![])), (Value::Nil, Value::Map(vec![]))]),
+        Value::from("x"),
+    ]);
+
+    assert_eq!(r#"[{1: [], nil: {}}, "x"]"#, to_string(&val));
+    assert_eq!("[\n  {\n    1: [],\n    nil: {}\n  },\n  \"x\"\n]", to_string_pretty(&val));
+}
+
+#[test]
+fn text_roundtrip() {
+    let vals = vec![
+        Value::Nil,
+        Value::from(false),
+        Value::from(0),
+        Value::from(-1),
+        Value::F32(1.0),
+        Value::F32(3.4028235e38),
+        Value::F32(1e-45),
+        Value::F32(1.0 / 0.0),
+        Value::F64(-0.0),
+        Value::F64(0.1),
+        Value::F64(1.0 / 0.0),
+        Value::F64(5e-324),
+        Value::from("привет 😀 \t\r"),
+        Value::Binary(vec![0, 0x7f, 0xff]),
+        Value::Ext(127, vec![]),
+        Value::Array(vec![Value::Array(vec![Value::Map(vec![
+            (Value::Array(vec![Value::from(1)]), Value::F32(-2.5)),
+            (Value::Binary(vec![1]), Value::Ext(-128, vec![2])),
+        ])])]),
+    ];
+
+    for val in &vals {
+        assert_roundtrip(val);
+    }
+}
+
+#[test]
+fn text_roundtrip_of_nan() {
+    match from_str(&to_string(&Value::F64(0.0 / 0.0))).unwrap() {
+        Value::F64(val) => assert!(val.is_nan()),
+        val => panic!("unexpected value: {:?}", val),
+    }
+}
+
+#[test]
+fn text_with_invalid_float_fails() {
+    assert!(from_str("f32(abc)").is_err());
+    assert!(from_str("f64(1.5").is_err());
+    assert!(from_str("f16(1.5)").is_err());
+}
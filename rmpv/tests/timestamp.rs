@@ -0,0 +1,88 @@
+extern crate rmpv;
+
+use std::time::{Duration, UNIX_EPOCH};
+
+use rmpv::{Value, ValueRef};
+use rmpv::timestamp::Timestamp;
+
+#[test]
+fn timestamp_32_bit_format() {
+    let ts = Timestamp::new(0xffff_ffff, 0).unwrap();
+    assert_eq!(vec![0xff, 0xff, 0xff, 0xff], ts.to_ext_data());
+    assert_eq!(Some(ts), Timestamp::from_ext_data(&[0xff, 0xff, 0xff, 0xff]));
+}
+
+#[test]
+fn timestamp_64_bit_format() {
+    let ts = Timestamp::new(0x3_ffff_ffff, 999_999_999).unwrap();
+    let data = ts.to_ext_data();
+    assert_eq!(vec![0xee, 0x6b, 0x27, 0xff, 0xff, 0xff, 0xff, 0xff], data);
+    assert_eq!(Some(ts), Timestamp::from_ext_data(&data));
+
+    // Seconds fitting into 32 bits use the 64-bit format as soon as there are nanoseconds.
+    assert_eq!(8, Timestamp::new(1, 1).unwrap().to_ext_data().len());
+}
+
+#[test]
+fn timestamp_96_bit_format() {
+    let ts = Timestamp::new(-1, 500).unwrap();
+    let data = ts.to_ext_data();
+    assert_eq!(vec![0, 0, 0x01, 0xf4, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff], data);
+    assert_eq!(Some(ts), Timestamp::from_ext_data(&data));
+
+    let ts = Timestamp::new(0x4_0000_0000, 0).unwrap();
+    assert_eq!(12, ts.to_ext_data().len());
+    assert_eq!(Some(ts), Timestamp::from_ext_data(&ts.to_ext_data()));
+}
+
+#[test]
+fn timestamp_rejects_invalid_data() {
+    assert_eq!(None, Timestamp::new(0, 1_000_000_000));
+    assert_eq!(None, Timestamp::from_ext_data(&[0, 0, 0]));
+    // 64-bit format with 0x3fffffff nanoseconds.
+    assert_eq!(None, Timestamp::from_ext_data(&[0xff, 0xff, 0xff, 0xfc, 0, 0, 0, 0]));
+    assert_eq!(None, Timestamp::from_ext_data(&[0xff, 0xff, 0xff, 0xff, 0, 0, 0, 0, 0, 0, 0, 0]));
+}
+
+#[test]
+fn timestamp_to_and_from_value() {
+    let ts = Timestamp::new(1500000000, 0).unwrap();
+    let val = Value::from(ts);
+    assert_eq!(Value::Ext(-1, vec![0x59, 0x68, 0x2f, 0x00]), val);
+    assert_eq!(Some(ts), val.as_timestamp());
+    assert_eq!(Some(ts), ValueRef::Ext(-1, &[0x59, 0x68, 0x2f, 0x00]).as_timestamp());
+
+    assert_eq!(None, Value::Ext(1, vec![0x59, 0x68, 0x2f, 0x00]).as_timestamp());
+    assert_eq!(None, Value::Ext(-1, vec![0x59]).as_timestamp());
+    assert_eq!(None, Value::from(1500000000).as_timestamp());
+}
+
+#[test]
+fn timestamp_to_and_from_duration() {
+    let ts = Timestamp::from_duration(Duration::new(2, 500)).unwrap();
+    assert_eq!((2, 500), (ts.secs(), ts.nanos()));
+    assert_eq!(Some(Duration::new(2, 500)), ts.to_duration());
+
+    assert_eq!(None, Timestamp::from_duration(Duration::from_secs(u64::max_value())));
+    assert_eq!(None, Timestamp::new(-1, 0).unwrap().to_duration());
+}
+
+#[test]
+fn timestamp_to_and_from_system_time() {
+    let time = UNIX_EPOCH + Duration::new(1500000000, 42);
+    let ts = Timestamp::from_system_time(time).unwrap();
+    assert_eq!(Timestamp::new(1500000000, 42).unwrap(), ts);
+    assert_eq!(Some(time), ts.to_system_time());
+
+    let time = UNIX_EPOCH - Duration::new(1, 250_000_000);
+    let ts = Timestamp::from_system_time(time).unwrap();
+    assert_eq!(Timestamp::new(-2, 750_000_000).unwrap(), ts);
+    assert_eq!(Some(time), ts.to_system_time());
+}
+
+#[test]
+fn timestamp_order_is_chronological() {
+    let before = Timestamp::new(-1, 999_999_999).unwrap();
+    let after = Timestamp::new(0, 0).unwrap();
+    assert!(before < after);
+}
@@ -0,0 +1,102 @@
+extern crate rmpv;
+
+use rmpv::Value;
+
+fn parse(s: &str) -> Value {
+    s.parse().unwrap()
+}
+
+#[test]
+fn map_values_leaves_keys_and_structure() {
+    let val = parse(r#"{"a": [1, {"b": 2}], "c": "x", 3: []}"#);
+    let val = val.map_values(|val| match val.as_u64() {
+        Some(n) => Value::from(n + 1),
+        None => Value::Nil,
+    });
+
+    assert_eq!(parse(r#"{"a": [2, {"b": 3}], "c": nil, 3: []}"#), val);
+}
+
+#[test]
+fn filter_map_keys_renames_and_drops() {
+    let val = parse(r#"[{"A": 1, "B": {"C": 2, "drop": 3}}, {"drop": 4}]"#);
+    let val = val.filter_map_keys(|key| match key.as_str() {
+        Some("drop") => None,
+        Some(name) => Some(Value::from(name.to_lowercase())),
+        None => Some(key),
+    });
+
+    assert_eq!(parse(r#"[{"a": 1, "b": {"c": 2}}, {}]"#), val);
+}
+
+#[test]
+fn filter_map_keys_visits_inner_maps_first() {
+    let val = parse(r#"{"outer": {"inner": 1}}"#);
+    let mut seen = Vec::new();
+    val.filter_map_keys(|key| {
+        seen.push(key.clone());
+        Some(key)
+    });
+
+    assert_eq!(vec![Value::from("inner"), Value::from("outer")], seen);
+}
+
+#[test]
+fn retain_prunes_bottom_up() {
+    let mut val = parse(r#"[null, [null, [null]], {"a": null, "b": 1}]"#);
+    val.retain(|val| match *val {
+        Value::Nil => false,
+        Value::Array(ref vec) => !vec.is_empty(),
+        _ => true,
+    });
+
+    assert_eq!(parse(r#"[{"b": 1}]"#), val);
+}
+
+#[test]
+fn retain_keeps_root() {
+    let mut val = Value::Nil;
+    val.retain(|_| false);
+    assert_eq!(Value::Nil, val);
+}
+
+#[test]
+fn transform_at_single_path() {
+    let mut val = parse(r#"{"a": [1, 2, 3], 5: "x"}"#);
+
+    assert_eq!(1, val.transform_at("/a/1", |_| Value::from("two")));
+    assert_eq!(1, val.transform_at("/5", |_| Value::Nil));
+    assert_eq!(parse(r#"{"a": [1, "two", 3], 5: nil}"#), val);
+}
+
+#[test]
+fn transform_at_wildcard() {
+    let mut val = parse(r#"[{"n": 1}, {"n": 2}, {"m": 3}]"#);
+    let count = val.transform_at("/*/n", |val| Value::from(val.as_u64().unwrap() * 100));
+
+    assert_eq!(2, count);
+    assert_eq!(parse(r#"[{"n": 100}, {"n": 200}, {"m": 3}]"#), val);
+}
+
+#[test]
+fn transform_at_root_and_missing() {
+    let mut val = parse(r#"{"a/b": {"~": 1}}"#);
+
+    assert_eq!(0, val.transform_at("/missing/x", |_| unreachable!()));
+    assert_eq!(0, val.transform_at("/a~1b/~0/deeper", |_| unreachable!()));
+    assert_eq!(1, val.transform_at("/a~1b/~0", |_| Value::from(2)));
+    assert_eq!(parse(r#"{"a/b": {"~": 2}}"#), val);
+
+    assert_eq!(1, val.transform_at("", |_| Value::from(true)));
+    assert_eq!(Value::from(true), val);
+}
+
+#[test]
+fn transform_at_invalid_path() {
+    let mut val = parse(r#"{"a": 1, "": {"a": 2}}"#);
+
+    assert_eq!(0, val.transform_at("a", |_| unreachable!()));
+    assert_eq!(0, val.transform_at("*", |_| unreachable!()));
+    assert_eq!(0, val.transform_at("x/a", |_| unreachable!()));
+    assert_eq!(parse(r#"{"a": 1, "": {"a": 2}}"#), val);
+}
@@ -58,6 +58,45 @@ fn display_ext() {
     assert_eq!("[1, [100, 42]]", format!("{}", Value::Ext(1, vec![100, 42])));
 }
 
+#[test]
+fn display_truncated_within_limits() {
+    let val = Value::Map(vec![(Value::from("a"), Value::Array(vec![Value::from(1)]))]);
+    assert_eq!(format!("{}", val), format!("{}", val.display_truncated(8, 8, 8)));
+}
+
+#[test]
+fn display_truncated_depth() {
+    let val = Value::Array(vec![Value::Array(vec![Value::Nil]), Value::Array(vec![])]);
+
+    assert_eq!("[...]", format!("{}", val.display_truncated(0, 8, 8)));
+    assert_eq!("[[...], []]", format!("{}", val.display_truncated(1, 8, 8)));
+
+    let val = Value::Map(vec![(Value::Nil, Value::Nil)]);
+    assert_eq!("{...}", format!("{}", val.display_truncated(0, 8, 8)));
+}
+
+#[test]
+fn display_truncated_elems() {
+    let val = Value::Array(vec![Value::from(1), Value::from(2), Value::from(3)]);
+    assert_eq!("[1, …(+2)]", format!("{}", val.display_truncated(8, 1, 8)));
+    assert_eq!("[…(+3)]", format!("{}", val.display_truncated(8, 0, 8)));
+
+    let val = Value::Map(vec![(Value::from(1), Value::Nil), (Value::from(2), Value::Nil)]);
+    assert_eq!("{1: nil, …(+1)}", format!("{}", val.display_truncated(8, 1, 8)));
+}
+
+#[test]
+fn display_truncated_bytes() {
+    assert_eq!("\"le s\"…(+5 bytes)",
+               format!("{}", Value::from("le string").display_truncated(8, 8, 4)));
+    // Never splits a multi-byte character.
+    assert_eq!("\"\"…(+2 bytes)", format!("{}", Value::from("я").display_truncated(8, 8, 1)));
+    assert_eq!("[1, 2, …(+1 bytes)]",
+               format!("{}", Value::Binary(vec![1, 2, 3]).display_truncated(8, 8, 2)));
+    assert_eq!("[1, [1, …(+2 bytes)]]",
+               format!("{}", Value::Ext(1, vec![1, 2, 3]).display_truncated(8, 8, 1)));
+}
+
 #[test]
 fn from_bool() {
     assert_eq!(Value::Boolean(true), Value::from(true));
@@ -130,6 +169,77 @@ fn is_nil() {
     assert!(!Value::Boolean(true).is_nil());
 }
 
+#[test]
+fn as_narrow_int() {
+    assert_eq!(Some(-128), Value::from(-128).as_i8());
+    assert_eq!(None, Value::from(-129).as_i8());
+    assert_eq!(Some(i16::max_value()), Value::from(i16::max_value()).as_i16());
+    assert_eq!(None, Value::from(u16::max_value()).as_i16());
+    assert_eq!(Some(255), Value::from(255).as_u8());
+    assert_eq!(None, Value::from(-1).as_u8());
+    assert_eq!(Some(u16::max_value()), Value::from(u16::max_value()).as_u16());
+    assert_eq!(Some(u32::max_value()), Value::from(u32::max_value()).as_u32());
+    assert_eq!(None, Value::from(1u64 << 32).as_u32());
+    assert_eq!(None, Value::F32(1.0).as_u32());
+    assert!(Value::from(-1).is_integer());
+    assert!(!Value::F64(1.0).is_integer());
+}
+
+#[test]
+fn as_f32_exact() {
+    assert_eq!(Some(-0.25), Value::F64(-0.25).as_f32());
+    assert!(Value::F64(std::f64::NAN).as_f32().unwrap().is_nan());
+    assert_eq!(None, Value::F64(1e300).as_f32());
+    assert_eq!(Some(-16777216.0), Value::from(-(1 << 24)).as_f32());
+    assert_eq!(None, Value::from(-(1 << 24) - 1).as_f32());
+    assert_eq!(None, Value::from(u64::max_value()).as_f32());
+    assert_eq!(None, Value::from(i64::max_value()).as_f32());
+    assert_eq!(Some(-9223372036854775808.0), Value::from(i64::min_value()).as_f32());
+    assert_eq!(Some(0.0), Value::from(0).as_f32());
+    assert_eq!(None, Value::Nil.as_f32());
+}
+
+#[test]
+fn as_and_into_containers() {
+    let mut val = Value::Array(vec![Value::from(1)]);
+    val.as_array_mut().unwrap().push(Value::from(2));
+    assert_eq!(Some(vec![Value::from(1), Value::from(2)]), val.clone().into_array());
+    assert_eq!(None, val.as_map_mut());
+    assert_eq!(None, val.into_map());
+
+    let mut val = Value::Map(vec![]);
+    val.as_map_mut().unwrap().push((Value::from("k"), Value::Nil));
+    assert_eq!(Some(vec![(Value::from("k"), Value::Nil)]), val.into_map());
+
+    let val = Value::Ext(42, vec![1, 2]);
+    assert_eq!(Some((42, vec![1, 2])), val.clone().into_ext());
+    assert_eq!(None, val.into_array());
+}
+
+#[test]
+fn as_and_into_raw() {
+    let val = Value::Binary(vec![0xff]);
+    assert_eq!(Some(&[0xff][..]), val.as_bin());
+    assert_eq!(None, val.as_utf8_string());
+    assert_eq!(Some(vec![0xff]), val.into_bytes());
+
+    let val = Value::from("le message");
+    assert_eq!(None, val.as_bin());
+    assert_eq!(Some("le message"), val.as_utf8_string().and_then(|s| s.as_str()));
+    assert_eq!(Some(b"le message".to_vec()), val.clone().into_bytes());
+    assert_eq!(Some("le message".to_string()), val.into_string());
+    assert_eq!(None, Value::Nil.into_bytes());
+}
+
+#[test]
+fn is_timestamp() {
+    use rmpv::timestamp::Timestamp;
+
+    assert!(Value::from(Timestamp::new(1, 0).unwrap()).is_timestamp());
+    assert!(!Value::Ext(-1, vec![0; 3]).is_timestamp());
+    assert!(!Value::Ext(1, vec![0; 4]).is_timestamp());
+}
+
 #[test]
 fn monadic_index() {
     let val = Value::Array(vec![
@@ -148,3 +258,52 @@ fn monadic_index() {
     assert!(val[2].is_nil());
     assert!(val[1][2][3][4][5].is_nil());
 }
+
+#[test]
+fn monadic_index_by_key() {
+    let val = Value::Map(vec![
+        (Value::from("items"), Value::Array(vec![Value::Map(vec![
+            (Value::from("id"), Value::from(7)),
+        ])])),
+        (Value::from(1), Value::from("int key")),
+    ]);
+
+    assert_eq!(Some(7), val["items"][0]["id"].as_u64());
+
+    assert!(val["missing"]["id"].is_nil());
+    assert!(val["1"].is_nil());
+    assert!(val[0]["items"].is_nil());
+}
+
+#[test]
+fn pointer_escapes_and_literal_segments() {
+    let val = Value::Map(vec![
+        (Value::from("a/b"), Value::from(1)),
+        (Value::from("m~n"), Value::from(2)),
+        (Value::from("*"), Value::from(3)),
+        (Value::from(""), Value::from(4)),
+        (Value::from(-5), Value::from(5)),
+    ]);
+
+    assert_eq!(Some(&Value::from(1)), val.pointer("/a~1b"));
+    assert_eq!(Some(&Value::from(2)), val.pointer("/m~0n"));
+    assert_eq!(Some(&Value::from(3)), val.pointer("/*"));
+    assert_eq!(Some(&Value::from(4)), val.pointer("/"));
+    assert_eq!(Some(&Value::from(5)), val.pointer("/-5"));
+
+    assert_eq!(None, val.pointer("a~1b"));
+    assert_eq!(None, val.pointer("/a~1b/0"));
+}
+
+#[test]
+fn pointer_into_arrays() {
+    let mut val = Value::Array(vec![Value::Nil, Value::Array(vec![Value::from(1)])]);
+
+    assert_eq!(Some(&Value::from(1)), val.pointer("/1/0"));
+    assert_eq!(None, val.pointer("/1/1"));
+    assert_eq!(None, val.pointer("/x"));
+
+    *val.pointer_mut("/0").unwrap() = Value::from(true);
+    assert_eq!(Value::from(true), val[0]);
+    assert!(val.pointer_mut("/2").is_none());
+}